@@ -146,6 +146,13 @@ impl From<surgedb_core::Error> for SurgeError {
             surgedb_core::Error::VectorNotFound(id) => SurgeError::VectorNotFound { id },
             surgedb_core::Error::DuplicateId(id) => SurgeError::DuplicateId { id },
             surgedb_core::Error::EmptyIndex => SurgeError::EmptyIndex,
+            surgedb_core::Error::InvalidId(msg) => SurgeError::InvalidConfig { message: msg },
+            e @ (surgedb_core::Error::MetadataLimitExceeded { .. }
+            | surgedb_core::Error::NormOutOfRange { .. }
+            | surgedb_core::Error::UnrepresentableValue { .. }
+            | surgedb_core::Error::VersionConflict { .. }) => SurgeError::InvalidConfig {
+                message: e.to_string(),
+            },
             surgedb_core::Error::InvalidConfig(msg) => SurgeError::InvalidConfig { message: msg },
             surgedb_core::Error::InvalidHnswParam {
                 param,
@@ -154,6 +161,7 @@ impl From<surgedb_core::Error> for SurgeError {
             } => SurgeError::InvalidConfig {
                 message: format!("{} = {}: {}", param, value, reason),
             },
+            surgedb_core::Error::InvalidFilter(msg) => SurgeError::InvalidConfig { message: msg },
             surgedb_core::Error::Storage(msg) => SurgeError::StorageError { message: msg },
             surgedb_core::Error::CollectionNotFound(name) => {
                 SurgeError::CollectionNotFound { name }
@@ -164,6 +172,14 @@ impl From<surgedb_core::Error> for SurgeError {
             surgedb_core::Error::CapacityExceeded { message } => {
                 SurgeError::CapacityExceeded { message }
             }
+            surgedb_core::Error::AliasNotFound(name) => SurgeError::CollectionNotFound { name },
+            surgedb_core::Error::AliasConflict(msg) => SurgeError::InvalidConfig { message: msg },
+            e @ surgedb_core::Error::CollectionBusy(_) => SurgeError::InvalidConfig {
+                message: e.to_string(),
+            },
+            e @ surgedb_core::Error::InvalidCollectionName { .. } => SurgeError::InvalidConfig {
+                message: e.to_string(),
+            },
             surgedb_core::Error::Io(e) => SurgeError::IoError {
                 message: e.to_string(),
             },
@@ -213,6 +229,9 @@ pub enum DistanceMetric {
     Cosine,
     Euclidean,
     DotProduct,
+    Manhattan,
+    Hamming,
+    Jaccard,
 }
 
 impl From<DistanceMetric> for surgedb_core::DistanceMetric {
@@ -221,6 +240,9 @@ impl From<DistanceMetric> for surgedb_core::DistanceMetric {
             DistanceMetric::Cosine => surgedb_core::DistanceMetric::Cosine,
             DistanceMetric::Euclidean => surgedb_core::DistanceMetric::Euclidean,
             DistanceMetric::DotProduct => surgedb_core::DistanceMetric::DotProduct,
+            DistanceMetric::Manhattan => surgedb_core::DistanceMetric::Manhattan,
+            DistanceMetric::Hamming => surgedb_core::DistanceMetric::Hamming,
+            DistanceMetric::Jaccard => surgedb_core::DistanceMetric::Jaccard,
         }
     }
 }
@@ -309,6 +331,26 @@ pub enum SearchFilter {
     Or {
         filters: Vec<SearchFilter>,
     },
+    Not {
+        filters: Vec<SearchFilter>,
+    },
+    Range {
+        field: String,
+        gt: Option<f64>,
+        gte: Option<f64>,
+        lt: Option<f64>,
+        lte: Option<f64>,
+    },
+    NoneOf {
+        field: String,
+        values_json: Vec<String>,
+    },
+    GeoRadius {
+        field: String,
+        lat: f64,
+        lon: f64,
+        radius_meters: f64,
+    },
 }
 
 impl SearchFilter {
@@ -322,16 +364,10 @@ impl SearchFilter {
                 })?;
                 Ok(surgedb_core::filter::Filter::Exact(field.clone(), value))
             }
-            SearchFilter::OneOf { field, values_json } => {
-                let values: Result<Vec<serde_json::Value>, _> = values_json
-                    .iter()
-                    .map(|v| serde_json::from_str(v))
-                    .collect();
-                let values = values.map_err(|e| SurgeError::SerializationError {
-                    message: e.to_string(),
-                })?;
-                Ok(surgedb_core::filter::Filter::OneOf(field.clone(), values))
-            }
+            SearchFilter::OneOf { field, values_json } => Ok(surgedb_core::filter::Filter::OneOf(
+                field.clone(),
+                parse_values(values_json)?,
+            )),
             SearchFilter::And { filters } => {
                 let core_filters: Result<Vec<_>, _> =
                     filters.iter().map(|f| f.to_core_filter()).collect();
@@ -342,10 +378,56 @@ impl SearchFilter {
                     filters.iter().map(|f| f.to_core_filter()).collect();
                 Ok(surgedb_core::filter::Filter::Or(core_filters?))
             }
+            SearchFilter::Not { filters } => {
+                let core_filters: Result<Vec<_>, _> =
+                    filters.iter().map(|f| f.to_core_filter()).collect();
+                Ok(surgedb_core::filter::Filter::Not(Box::new(
+                    surgedb_core::filter::Filter::Or(core_filters?),
+                )))
+            }
+            SearchFilter::Range {
+                field,
+                gt,
+                gte,
+                lt,
+                lte,
+            } => Ok(surgedb_core::filter::Filter::Range {
+                field: field.clone(),
+                gt: *gt,
+                gte: *gte,
+                lt: *lt,
+                lte: *lte,
+            }),
+            SearchFilter::NoneOf { field, values_json } => {
+                Ok(surgedb_core::filter::Filter::Not(Box::new(
+                    surgedb_core::filter::Filter::OneOf(field.clone(), parse_values(values_json)?),
+                )))
+            }
+            SearchFilter::GeoRadius {
+                field,
+                lat,
+                lon,
+                radius_meters,
+            } => Ok(surgedb_core::filter::Filter::GeoRadius {
+                field: field.clone(),
+                center: (*lat, *lon),
+                radius_meters: *radius_meters,
+            }),
         }
     }
 }
 
+/// Parse a list of JSON-encoded values
+fn parse_values(values_json: &[String]) -> Result<Vec<serde_json::Value>, SurgeError> {
+    values_json
+        .iter()
+        .map(|v| serde_json::from_str(v))
+        .collect::<Result<_, _>>()
+        .map_err(|e| SurgeError::SerializationError {
+            message: e.to_string(),
+        })
+}
+
 // =============================================================================
 // Internal Database Wrapper
 // =============================================================================
@@ -354,9 +436,9 @@ type BatchItems = Vec<(surgedb_core::VectorId, Vec<f32>, Option<serde_json::Valu
 
 /// Internal enum to hold different database types
 enum DbInner {
-    InMemory(surgedb_core::VectorDb),
-    Quantized(surgedb_core::QuantizedVectorDb),
-    Persistent(surgedb_core::PersistentVectorDb),
+    InMemory(Box<surgedb_core::VectorDb>),
+    Quantized(Box<surgedb_core::QuantizedVectorDb>),
+    Persistent(Box<surgedb_core::PersistentVectorDb>),
 }
 
 // =============================================================================
@@ -385,7 +467,7 @@ impl SurgeClient {
         let db = surgedb_core::VectorDb::new(core_config)?;
 
         Ok(Self {
-            inner: Arc::new(RwLock::new(DbInner::InMemory(db))),
+            inner: Arc::new(RwLock::new(DbInner::InMemory(Box::new(db)))),
             config: SurgeConfig {
                 dimensions,
                 ..Default::default()
@@ -403,7 +485,7 @@ impl SurgeClient {
                 ..Default::default()
             };
             let db = surgedb_core::PersistentVectorDb::open(&path, core_config)?;
-            DbInner::Persistent(db)
+            DbInner::Persistent(Box::new(db))
         } else if config.quantization != Quantization::None {
             // Quantized in-memory database
             let core_config = surgedb_core::QuantizedConfig {
@@ -413,7 +495,7 @@ impl SurgeClient {
                 ..Default::default()
             };
             let db = surgedb_core::QuantizedVectorDb::new(core_config)?;
-            DbInner::Quantized(db)
+            DbInner::Quantized(Box::new(db))
         } else {
             // Regular in-memory database
             let core_config = surgedb_core::Config {
@@ -422,7 +504,7 @@ impl SurgeClient {
                 ..Default::default()
             };
             let db = surgedb_core::VectorDb::new(core_config)?;
-            DbInner::InMemory(db)
+            DbInner::InMemory(Box::new(db))
         };
 
         Ok(Self {
@@ -865,7 +865,7 @@ fn show_info() {
     println!("  - Binary quantization (32x compression)");
     println!("  - ACID-compliant persistence (WAL + snapshots)");
     println!("  - Mmap-based disk-resident vector storage");
-    println!("  - Cosine, Euclidean, and Dot Product metrics");
+    println!("  - Cosine, Euclidean, Dot Product, Manhattan, Hamming and Jaccard metrics");
     println!();
 
     #[cfg(target_arch = "aarch64")]
@@ -0,0 +1,61 @@
+//! # SurgeDB Cluster
+//!
+//! Spreads collections over several nodes so one collection can hold more
+//! vectors than fit in a single machine's memory.
+//!
+//! Every clustered collection is split into a fixed number of shards by a
+//! hash of the vector ID ([`ShardMap`]). Each shard is kept on
+//! `replication` nodes, which agree on its writes through a [Raft](raft)
+//! group, and is stored on each of them as an ordinary collection named
+//! `{collection}.shard{n}`. A search asks one replica of every shard and
+//! [`merge`]s the answers.
+//!
+//! This crate does no networking: [`ClusterNode`] queues the messages its
+//! Raft groups send, and the embedding server delivers them. Each group's
+//! term, vote and log are kept on disk when the node is given a directory.
+//! Once a shard's collections are saved, the entries they reflect are
+//! compacted out of its log; a replica too far behind for the rest gets the
+//! shard's contents as a snapshot.
+
+pub mod merge;
+pub mod node;
+pub mod raft;
+pub mod shard;
+pub mod storage;
+
+pub use merge::merge;
+pub use node::{ClusterNode, Command, Outgoing, Record, ShardHit, ShardStatus};
+pub use raft::{Entry, HardState, Message, Raft, Role, Snapshot, Unsaved};
+pub use shard::{shard_collection, Placement, PlacementViolation, ShardMap};
+
+use thiserror::Error;
+
+/// A node of the cluster
+pub type NodeId = u64;
+/// A shard of every clustered collection
+pub type ShardId = u32;
+
+/// Result type alias for cluster operations
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Writes to the shard go through another replica
+    #[error("Not the leader of this shard (leader: {leader:?})")]
+    NotLeader { leader: Option<NodeId> },
+
+    /// The shard has no replica on this node
+    #[error("Shard {0} is not kept on this node")]
+    UnknownShard(ShardId),
+
+    /// The cluster layout is not usable
+    #[error("Invalid cluster configuration: {0}")]
+    InvalidConfig(String),
+
+    /// Raft state couldn't be read or written
+    #[error("Raft storage failed: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Core(#[from] surgedb_core::Error),
+}
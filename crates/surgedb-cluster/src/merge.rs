@@ -0,0 +1,40 @@
+//! Merging per-shard search results
+
+use crate::node::ShardHit;
+
+/// The `k` closest of the hits returned by every shard
+///
+/// Each ID lives on one shard, so no hit is duplicated across lists.
+pub fn merge(results: Vec<Vec<ShardHit>>, k: usize) -> Vec<ShardHit> {
+    let mut hits: Vec<ShardHit> = results.into_iter().flatten().collect();
+    hits.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+    hits.truncate(k);
+    hits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(id: &str, distance: f32) -> ShardHit {
+        ShardHit {
+            id: id.to_string(),
+            distance,
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn test_merge_keeps_closest() {
+        let merged = merge(
+            vec![
+                vec![hit("a", 0.1), hit("b", 0.5)],
+                vec![],
+                vec![hit("c", 0.2), hit("d", 0.3)],
+            ],
+            3,
+        );
+        let ids: Vec<&str> = merged.iter().map(|h| h.id.as_str()).collect();
+        assert_eq!(ids, ["a", "c", "d"]);
+    }
+}
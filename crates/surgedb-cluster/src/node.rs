@@ -0,0 +1,910 @@
+//! The shards kept on one node
+
+use crate::raft::{Entry, Message, Raft, Role, Snapshot};
+use crate::shard::{shard_collection, ShardMap};
+use crate::storage::GroupStorage;
+use crate::{Error, NodeId, Result, ShardId};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::Arc;
+use surgedb_core::filter::Filter;
+use surgedb_core::{Config, Database};
+use tracing::warn;
+
+/// Outcomes of applied commands kept per shard for proposers to collect
+const MAX_OUTCOMES: usize = 4096;
+/// Applied entries a shard's log keeps before it is compacted, unless
+/// [`ClusterNode::with_compaction`] says otherwise
+pub const DEFAULT_COMPACT_AFTER: u64 = 1024;
+/// Records per upsert in a snapshot
+const SNAPSHOT_BATCH: usize = 256;
+
+/// A write to one shard, agreed on by its Raft group
+///
+/// Replaying a command leaves a shard as it was, so a restarted node, which
+/// applies its log after the last snapshot again, ends up with the same data
+/// as the others. A snapshot is the commands that build a shard's
+/// collections from nothing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Command {
+    CreateCollection {
+        name: String,
+        config: Box<Config>,
+    },
+    DeleteCollection {
+        name: String,
+    },
+    Upsert {
+        collection: String,
+        items: Vec<Record>,
+    },
+    Delete {
+        collection: String,
+        ids: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub id: String,
+    pub vector: Vec<f32>,
+    #[serde(default)]
+    pub metadata: Option<Value>,
+}
+
+/// A search result from one shard
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShardHit {
+    pub id: String,
+    pub distance: f32,
+    pub metadata: Option<Value>,
+}
+
+/// A Raft message for another node
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Outgoing {
+    pub to: NodeId,
+    pub shard: ShardId,
+    pub message: Message<Command>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShardStatus {
+    pub shard: ShardId,
+    pub replicas: Vec<NodeId>,
+    pub role: Role,
+    pub leader: Option<NodeId>,
+    pub term: u64,
+    pub commit_index: u64,
+    pub applied_index: u64,
+    /// Last entry compacted out of the log
+    pub snapshot_index: u64,
+}
+
+struct Group {
+    raft: Raft<Command>,
+    /// Where the group's Raft state is kept, unless only in memory
+    storage: Option<GroupStorage>,
+    /// Snapshot from the leader that failed to install, to retry
+    snapshot: Option<Snapshot<Command>>,
+    applied: u64,
+    /// `(index, term, result)` of the latest applied commands
+    outcomes: VecDeque<(u64, u64, std::result::Result<(), String>)>,
+}
+
+impl Group {
+    /// Write what changed in the Raft state to the group's storage
+    fn save(&mut self) -> Result<()> {
+        if let Some(unsaved) = self.raft.unsaved() {
+            if let Some(storage) = &mut self.storage {
+                storage.save(&unsaved)?;
+            }
+            self.raft.saved();
+        }
+        Ok(())
+    }
+}
+
+/// One node's replicas of the cluster's shards
+///
+/// Each shard of a collection is stored in the node's [`Database`] as the
+/// collection [`shard_collection`] names.
+pub struct ClusterNode {
+    id: NodeId,
+    map: ShardMap,
+    db: Arc<Database>,
+    groups: Mutex<BTreeMap<ShardId, Group>>,
+    /// Held while applying so commands of a shard are applied in log order
+    applying: Mutex<()>,
+    /// Applied entries after which a shard's log is compacted
+    compact_after: u64,
+}
+
+impl ClusterNode {
+    /// Node `id` of the cluster laid out by `map`, keeping the Raft state of
+    /// its shards in `dir` or, without one, in memory only
+    ///
+    /// A node given the directory it used before resumes with the terms,
+    /// votes and logs it had, and applies the committed entries after each
+    /// shard's last snapshot again. The snapshot's state is in the shard
+    /// collections, so `db` must be the database the node used before.
+    pub fn new(id: NodeId, map: ShardMap, db: Arc<Database>, dir: Option<PathBuf>) -> Result<Self> {
+        if !map.nodes().contains(&id) {
+            return Err(Error::InvalidConfig(format!(
+                "Node {} is not in the cluster",
+                id
+            )));
+        }
+        let groups = map
+            .shards_of(id)
+            .into_iter()
+            .map(|shard| {
+                let replicas = map.replicas(shard);
+                let (raft, storage) = match &dir {
+                    Some(dir) => {
+                        let (mut storage, state, log) = GroupStorage::open(dir, shard)?;
+                        if storage.install_interrupted() {
+                            // Partly replaced; the leader sends the snapshot again
+                            drop_shard(&db, shard)?;
+                            storage.installed()?;
+                        }
+                        (Raft::restore(id, &replicas, state, log), Some(storage))
+                    }
+                    None => (Raft::new(id, &replicas), None),
+                };
+                let group = Group {
+                    applied: raft.snapshot_index(),
+                    raft,
+                    storage,
+                    snapshot: None,
+                    outcomes: VecDeque::new(),
+                };
+                Ok((shard, group))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            id,
+            map,
+            db,
+            groups: Mutex::new(groups),
+            applying: Mutex::new(()),
+            compact_after: DEFAULT_COMPACT_AFTER,
+        })
+    }
+
+    /// Compact a shard's log once it holds `entries` applied entries, after
+    /// saving the shard collections
+    pub fn with_compaction(mut self, entries: u64) -> Self {
+        self.compact_after = entries.max(1);
+        self
+    }
+
+    pub fn id(&self) -> NodeId {
+        self.id
+    }
+
+    pub fn map(&self) -> &ShardMap {
+        &self.map
+    }
+
+    /// Advance the clock of every local shard's group
+    pub fn tick(&self) {
+        for group in self.groups.lock().values_mut() {
+            group.raft.tick();
+        }
+    }
+
+    /// Hand a message from node `from` to the group of `shard`
+    pub fn step(&self, shard: ShardId, from: NodeId, message: Message<Command>) -> Result<()> {
+        let mut groups = self.groups.lock();
+        let group = groups.get_mut(&shard).ok_or(Error::UnknownShard(shard))?;
+        group.raft.step(from, message);
+        Ok(())
+    }
+
+    /// Messages for other nodes queued since the last call
+    ///
+    /// A group's messages are only handed out once its state is saved; if
+    /// that fails they are dropped, like messages lost on the way.
+    pub fn take_messages(&self) -> Vec<Outgoing> {
+        let mut groups = self.groups.lock();
+        let mut outgoing = Vec::new();
+        for (&shard, group) in groups.iter_mut() {
+            if let Err(e) = group.save() {
+                warn!("Shard {} failed to save its Raft state: {}", shard, e);
+                group.raft.take_messages();
+                continue;
+            }
+            outgoing.extend(
+                group
+                    .raft
+                    .take_messages()
+                    .into_iter()
+                    .map(|(to, message)| Outgoing { to, shard, message }),
+            );
+        }
+        outgoing
+    }
+
+    /// Start replicating `command` to the replicas of `shard`; returns the
+    /// term and log index to pass to [`outcome`](Self::outcome)
+    pub fn propose(&self, shard: ShardId, command: Command) -> Result<(u64, u64)> {
+        let mut groups = self.groups.lock();
+        let group = groups.get_mut(&shard).ok_or(Error::UnknownShard(shard))?;
+        group
+            .raft
+            .propose(command)
+            .map_err(|leader| Error::NotLeader { leader })
+    }
+
+    /// Result of applying the command proposed at `term` and `index`, or
+    /// `None` while it isn't applied yet
+    ///
+    /// Fails with [`Error::NotLeader`] once a new leader replaced the entry;
+    /// the command never takes effect and can be proposed again.
+    pub fn outcome(
+        &self,
+        shard: ShardId,
+        term: u64,
+        index: u64,
+    ) -> Result<Option<std::result::Result<(), String>>> {
+        let groups = self.groups.lock();
+        let group = groups.get(&shard).ok_or(Error::UnknownShard(shard))?;
+        let superseded = Err(Error::NotLeader {
+            leader: group.raft.leader(),
+        });
+        if group.applied < index {
+            return match group.raft.entry_term(index) {
+                Some(t) if t != term => superseded,
+                None if group.raft.term() > term => superseded,
+                _ => Ok(None),
+            };
+        }
+        match group.outcomes.iter().find(|(i, _, _)| *i == index) {
+            Some((_, t, _)) if *t != term => superseded,
+            Some((_, _, result)) => Ok(Some(result.clone())),
+            None => Ok(Some(Err("The outcome is no longer kept".to_string()))),
+        }
+    }
+
+    /// Apply the commands committed since the last call to the local shard
+    /// collections
+    ///
+    /// Also installs snapshots from leaders, compacts logs that grew past
+    /// [`with_compaction`](Self::with_compaction), and builds the snapshots
+    /// lagging replicas need.
+    pub fn apply_committed(&self) {
+        let _applying = self.applying.lock();
+        let committed: Vec<_> = self
+            .groups
+            .lock()
+            .iter_mut()
+            .filter_map(|(&shard, group)| {
+                if let Err(e) = group.save() {
+                    warn!("Shard {} failed to save its Raft state: {}", shard, e);
+                    return None;
+                }
+                // A newer snapshot replaces one that failed to install
+                if let Some(snapshot) = group.raft.take_snapshot() {
+                    group.snapshot = Some(snapshot);
+                }
+                // Entries committed after it are handed out once it is installed
+                let snapshot = group.snapshot.take();
+                let entries = match snapshot {
+                    Some(_) => Vec::new(),
+                    None => group.raft.take_committed(),
+                };
+                Some((shard, snapshot, entries))
+            })
+            .collect();
+        for (shard, snapshot, entries) in committed {
+            match snapshot {
+                Some(snapshot) => self.install(shard, snapshot),
+                None => self.apply_entries(shard, entries),
+            }
+            self.compact(shard);
+            self.send_snapshots(shard);
+        }
+    }
+
+    fn apply_entries(&self, shard: ShardId, entries: Vec<Entry<Command>>) {
+        let mut outcomes = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let result = match entry.command {
+                Some(command) => self.apply(shard, command).map_err(|e| {
+                    warn!(
+                        "Shard {} failed to apply entry {}: {}",
+                        shard, entry.index, e
+                    );
+                    e.to_string()
+                }),
+                None => Ok(()),
+            };
+            outcomes.push((entry.index, entry.term, result));
+        }
+        let mut groups = self.groups.lock();
+        let group = groups.get_mut(&shard).expect("groups are never removed");
+        if let Some(&(index, _, _)) = outcomes.last() {
+            group.applied = index;
+        }
+        group.outcomes.extend(outcomes);
+        let excess = group.outcomes.len().saturating_sub(MAX_OUTCOMES);
+        group.outcomes.drain(..excess);
+    }
+
+    /// Replace the local collections of `shard` with `snapshot` and save
+    /// them, then tell the group
+    fn install(&self, shard: ShardId, snapshot: Snapshot<Command>) {
+        let marked = {
+            let mut groups = self.groups.lock();
+            let group = groups.get_mut(&shard).expect("groups are never removed");
+            if snapshot.index <= group.applied {
+                // Sent again before the leader heard it was installed
+                group.raft.snapshot_installed(snapshot.index, snapshot.term);
+                return;
+            }
+            match &group.storage {
+                Some(storage) => storage.installing(),
+                None => Ok(()),
+            }
+        };
+        let result = marked.and_then(|()| {
+            drop_shard(&self.db, shard)?;
+            for command in snapshot.commands.iter().cloned() {
+                self.apply(shard, command)?;
+            }
+            self.flush(shard)
+        });
+        let mut groups = self.groups.lock();
+        let group = groups.get_mut(&shard).expect("groups are never removed");
+        if let Err(e) = result {
+            warn!(
+                "Shard {} failed to install the snapshot up to entry {}: {}",
+                shard, snapshot.index, e
+            );
+            group.snapshot.get_or_insert(snapshot);
+            return;
+        }
+        group.raft.snapshot_installed(snapshot.index, snapshot.term);
+        group.applied = group.applied.max(snapshot.index);
+        let saved = group.save().and_then(|()| match &mut group.storage {
+            Some(storage) => storage.installed(),
+            None => Ok(()),
+        });
+        if let Err(e) = saved {
+            warn!("Shard {} failed to save its Raft state: {}", shard, e);
+        }
+    }
+
+    /// Save the collections of `shard` and drop the applied entries from its
+    /// log, once there are enough of them
+    fn compact(&self, shard: ShardId) {
+        let applied = {
+            let groups = self.groups.lock();
+            let group = &groups[&shard];
+            if group.applied < group.raft.snapshot_index() + self.compact_after {
+                return;
+            }
+            group.applied
+        };
+        if let Err(e) = self.flush(shard) {
+            warn!("Shard {} failed to save its collections: {}", shard, e);
+            return;
+        }
+        let mut groups = self.groups.lock();
+        let group = groups.get_mut(&shard).expect("groups are never removed");
+        group.raft.compact(applied);
+        if let Err(e) = group.save() {
+            warn!("Shard {} failed to save its Raft state: {}", shard, e);
+        }
+    }
+
+    /// Send the replicas of `shard` whose next entries were compacted away
+    /// the state of the local collections
+    fn send_snapshots(&self, shard: ShardId) {
+        let peers = self
+            .groups
+            .lock()
+            .get_mut(&shard)
+            .expect("groups are never removed")
+            .raft
+            .take_snapshot_requests();
+        if peers.is_empty() {
+            return;
+        }
+        let commands = match self.snapshot(shard) {
+            Ok(commands) => commands,
+            Err(e) => {
+                warn!("Shard {} failed to build a snapshot: {}", shard, e);
+                return;
+            }
+        };
+        let mut groups = self.groups.lock();
+        let group = groups.get_mut(&shard).expect("groups are never removed");
+        for peer in peers {
+            group
+                .raft
+                .send_snapshot(peer, group.applied, commands.clone());
+        }
+    }
+
+    /// Commands that build the local collections of `shard` from nothing
+    fn snapshot(&self, shard: ShardId) -> Result<Vec<Command>> {
+        let mut commands = Vec::new();
+        for name in shard_collections(&self.db, shard) {
+            let collection = self.db.get_collection(&shard_collection(&name, shard))?;
+            commands.push(Command::CreateCollection {
+                name: name.clone(),
+                config: Box::new(collection.config()),
+            });
+            let mut items = Vec::new();
+            for record in collection.scan() {
+                let (id, vector, metadata) = record?;
+                items.push(Record {
+                    id: id.to_string(),
+                    vector,
+                    metadata,
+                });
+                if items.len() == SNAPSHOT_BATCH {
+                    commands.push(Command::Upsert {
+                        collection: name.clone(),
+                        items: std::mem::take(&mut items),
+                    });
+                }
+            }
+            if !items.is_empty() {
+                commands.push(Command::Upsert {
+                    collection: name,
+                    items,
+                });
+            }
+        }
+        Ok(commands)
+    }
+
+    /// Write the collections of `shard` to disk, so none of their data is
+    /// left only in their WALs
+    fn flush(&self, shard: ShardId) -> Result<()> {
+        for name in shard_collections(&self.db, shard) {
+            self.db
+                .get_collection(&shard_collection(&name, shard))?
+                .flush()?;
+        }
+        Ok(())
+    }
+
+    fn apply(&self, shard: ShardId, command: Command) -> surgedb_core::Result<()> {
+        match command {
+            Command::CreateCollection { name, config } => {
+                self.db
+                    .create_collection_if_missing(&shard_collection(&name, shard), *config)?;
+            }
+            Command::DeleteCollection { name } => {
+                match self.db.delete_collection(&shard_collection(&name, shard)) {
+                    Ok(()) | Err(surgedb_core::Error::CollectionNotFound(_)) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            Command::Upsert { collection, items } => {
+                self.db
+                    .get_collection(&shard_collection(&collection, shard))?
+                    .upsert_batch(
+                        items
+                            .into_iter()
+                            .map(|r| (r.id, r.vector, r.metadata))
+                            .collect(),
+                    )?;
+            }
+            Command::Delete { collection, ids } => {
+                self.db
+                    .get_collection(&shard_collection(&collection, shard))?
+                    .delete_batch(&ids)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Search this node's replica of `shard`
+    ///
+    /// A replica that isn't the leader may not have applied the latest writes.
+    pub fn search_shard(
+        &self,
+        collection: &str,
+        shard: ShardId,
+        query: &[f32],
+        k: usize,
+        filter: Option<&Filter>,
+    ) -> Result<Vec<ShardHit>> {
+        if !self.groups.lock().contains_key(&shard) {
+            return Err(Error::UnknownShard(shard));
+        }
+        let collection = self
+            .db
+            .get_collection(&shard_collection(collection, shard))?;
+        // A small collection may leave some of its shards empty
+        if collection.is_empty() {
+            return Ok(Vec::new());
+        }
+        let hits = collection.search(query, k, filter)?;
+        Ok(hits
+            .into_iter()
+            .map(|(id, distance, metadata)| ShardHit {
+                id: id.to_string(),
+                distance,
+                metadata,
+            })
+            .collect())
+    }
+
+    pub fn status(&self) -> Vec<ShardStatus> {
+        self.groups
+            .lock()
+            .iter()
+            .map(|(&shard, group)| ShardStatus {
+                shard,
+                replicas: self.map.replicas(shard),
+                role: group.raft.role(),
+                leader: group.raft.leader(),
+                term: group.raft.term(),
+                commit_index: group.raft.commit_index(),
+                applied_index: group.applied,
+                snapshot_index: group.raft.snapshot_index(),
+            })
+            .collect()
+    }
+}
+
+/// Names of the clustered collections with a local collection for `shard`
+fn shard_collections(db: &Database, shard: ShardId) -> Vec<String> {
+    let suffix = shard_collection("", shard);
+    db.list_collections()
+        .into_iter()
+        .filter_map(|name| name.strip_suffix(&suffix).map(str::to_string))
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// Delete the local collections of `shard`
+fn drop_shard(db: &Database, shard: ShardId) -> Result<()> {
+    for name in shard_collections(db, shard) {
+        match db.delete_collection(&shard_collection(&name, shard)) {
+            Ok(()) | Err(surgedb_core::Error::CollectionNotFound(_)) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merge;
+    use crate::raft::ELECTION_TICKS;
+    use surgedb_core::DistanceMetric;
+
+    fn cluster(nodes: u64, shards: u32, replication: usize) -> Vec<ClusterNode> {
+        let map = ShardMap::new((1..=nodes).collect(), shards, replication).unwrap();
+        (1..=nodes)
+            .map(|id| ClusterNode::new(id, map.clone(), Arc::new(Database::new()), None).unwrap())
+            .collect()
+    }
+
+    /// Deliver every queued message, then apply what was committed
+    fn settle(nodes: &[ClusterNode]) {
+        settle_without(nodes, &[]);
+    }
+
+    /// Like [`settle`], losing the messages to and from the nodes in `down`
+    fn settle_without(nodes: &[ClusterNode], down: &[NodeId]) {
+        loop {
+            for node in nodes {
+                node.apply_committed();
+            }
+            let messages: Vec<_> = nodes
+                .iter()
+                .flat_map(|n| n.take_messages().into_iter().map(move |m| (n.id(), m)))
+                .filter(|(from, m)| !down.contains(from) && !down.contains(&m.to))
+                .collect();
+            if messages.is_empty() {
+                break;
+            }
+            for (from, m) in messages {
+                nodes[m.to as usize - 1]
+                    .step(m.shard, from, m.message)
+                    .unwrap();
+            }
+        }
+    }
+
+    fn upsert(id: &str, x: f32) -> Command {
+        Command::Upsert {
+            collection: "c".to_string(),
+            items: vec![Record {
+                id: id.to_string(),
+                vector: vec![x, 1.0],
+                metadata: None,
+            }],
+        }
+    }
+
+    fn find_leader(nodes: &[ClusterNode], shard: ShardId) -> Option<&ClusterNode> {
+        nodes.iter().find(|n| {
+            n.status()
+                .iter()
+                .any(|s| s.shard == shard && s.role == Role::Leader)
+        })
+    }
+
+    fn leader(nodes: &[ClusterNode], shard: ShardId) -> &ClusterNode {
+        find_leader(nodes, shard).expect("shard has a leader")
+    }
+
+    /// Tick until every shard has a leader; split votes can take a few rounds
+    fn elect(nodes: &[ClusterNode]) {
+        let shards = nodes[0].map().shards();
+        for _ in 0..ELECTION_TICKS * 100 {
+            if (0..shards).all(|shard| find_leader(nodes, shard).is_some()) {
+                return;
+            }
+            for node in nodes {
+                node.tick();
+            }
+            settle(nodes);
+        }
+        panic!("Shards still without a leader");
+    }
+
+    fn write(nodes: &[ClusterNode], shard: ShardId, command: Command) {
+        let node = leader(nodes, shard);
+        let (term, index) = node.propose(shard, command).unwrap();
+        settle(nodes);
+        // Followers learn the commit index with the next append
+        for node in nodes {
+            node.tick();
+            node.tick();
+        }
+        settle(nodes);
+        assert_eq!(node.outcome(shard, term, index).unwrap(), Some(Ok(())));
+    }
+
+    #[test]
+    fn test_sharded_writes_and_search() {
+        let nodes = cluster(3, 4, 2);
+        elect(&nodes);
+        let map = nodes[0].map().clone();
+        let config = Config::builder(2)
+            .distance_metric(DistanceMetric::Euclidean)
+            .build()
+            .unwrap();
+        let mut by_shard: BTreeMap<ShardId, Vec<Record>> = BTreeMap::new();
+        for i in 0..40 {
+            let id = format!("v{}", i);
+            by_shard.entry(map.shard_of(&id)).or_default().push(Record {
+                id,
+                vector: vec![i as f32, 0.0],
+                metadata: None,
+            });
+        }
+        for shard in 0..map.shards() {
+            let create = Command::CreateCollection {
+                name: "c".to_string(),
+                config: Box::new(config.clone()),
+            };
+            write(&nodes, shard, create);
+            let items = by_shard.remove(&shard).unwrap_or_default();
+            let upsert = Command::Upsert {
+                collection: "c".to_string(),
+                items,
+            };
+            write(&nodes, shard, upsert);
+        }
+
+        // Every replica of a shard holds the same vectors
+        for shard in 0..map.shards() {
+            let counts: Vec<usize> = map
+                .replicas(shard)
+                .iter()
+                .map(|&id| {
+                    let db = &nodes[id as usize - 1].db;
+                    db.get_collection(&shard_collection("c", shard))
+                        .unwrap()
+                        .len()
+                })
+                .collect();
+            assert_eq!(counts[0], counts[1]);
+        }
+
+        // One replica of each shard answers
+        let results = (0..map.shards())
+            .map(|shard| {
+                let replica = map.replicas(shard)[0];
+                nodes[replica as usize - 1]
+                    .search_shard("c", shard, &[20.2, 0.0], 3, None)
+                    .unwrap()
+            })
+            .collect();
+        let ids: Vec<String> = merge(results, 3).into_iter().map(|h| h.id).collect();
+        assert_eq!(ids, ["v20", "v21", "v19"]);
+
+        let missing = (0..map.shards())
+            .find(|s| !map.shards_of(1).contains(s))
+            .unwrap();
+        assert!(matches!(
+            nodes[0].search_shard("c", missing, &[0.0, 0.0], 1, None),
+            Err(Error::UnknownShard(_))
+        ));
+
+        let follower = map
+            .replicas(0)
+            .into_iter()
+            .find(|&id| id != leader(&nodes, 0).id())
+            .unwrap();
+        let delete = Command::Delete {
+            collection: "c".to_string(),
+            ids: vec!["v0".to_string()],
+        };
+        assert!(matches!(
+            nodes[follower as usize - 1].propose(0, delete),
+            Err(Error::NotLeader { leader: Some(_) })
+        ));
+    }
+
+    #[test]
+    fn test_restart_resumes_from_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let map = ShardMap::new(vec![1, 2, 3], 1, 3).unwrap();
+        let open = |id: NodeId| {
+            let db = Arc::new(Database::new());
+            let raft_dir = dir.path().join(id.to_string());
+            ClusterNode::new(id, map.clone(), db, Some(raft_dir)).unwrap()
+        };
+        let mut nodes: Vec<ClusterNode> = (1..=3).map(open).collect();
+        elect(&nodes);
+        let config = Config::builder(2).build().unwrap();
+        let create = Command::CreateCollection {
+            name: "c".to_string(),
+            config: Box::new(config),
+        };
+        write(&nodes, 0, create);
+        let upsert = Command::Upsert {
+            collection: "c".to_string(),
+            items: vec![Record {
+                id: "a".to_string(),
+                vector: vec![1.0, 0.0],
+                metadata: None,
+            }],
+        };
+        write(&nodes, 0, upsert);
+
+        let follower = (0..3)
+            .find(|&i| nodes[i].status()[0].role != Role::Leader)
+            .unwrap();
+        let before = nodes[follower].status().remove(0);
+        nodes[follower] = open(follower as NodeId + 1);
+        let after = nodes[follower].status().remove(0);
+        assert_eq!(after.term, before.term);
+        assert_eq!(after.commit_index, before.commit_index);
+
+        // Its committed writes are applied again without the leader's help
+        nodes[follower].apply_committed();
+        let shard = nodes[follower]
+            .db
+            .get_collection(&shard_collection("c", 0))
+            .unwrap();
+        assert_eq!(shard.len(), 1);
+    }
+
+    #[test]
+    fn test_lagging_replica_gets_a_snapshot() {
+        let map = ShardMap::new(vec![1, 2, 3], 1, 3).unwrap();
+        let nodes: Vec<ClusterNode> = (1..=3)
+            .map(|id| {
+                let db = Arc::new(Database::new());
+                ClusterNode::new(id, map.clone(), db, None)
+                    .unwrap()
+                    .with_compaction(4)
+            })
+            .collect();
+        elect(&nodes);
+        let create = Command::CreateCollection {
+            name: "c".to_string(),
+            config: Box::new(Config::builder(2).build().unwrap()),
+        };
+        write(&nodes, 0, create);
+        write(&nodes, 0, upsert("old", 0.0));
+
+        let lagging = (1..=3).find(|&id| id != leader(&nodes, 0).id()).unwrap();
+        let up: Vec<&ClusterNode> = nodes.iter().filter(|n| n.id() != lagging).collect();
+        for i in 0..10 {
+            leader(&nodes, 0)
+                .propose(0, upsert(&format!("v{}", i), i as f32))
+                .unwrap();
+            settle_without(&nodes, &[lagging]);
+            for node in &up {
+                node.tick();
+                node.tick();
+            }
+            settle_without(&nodes, &[lagging]);
+        }
+        let ahead = leader(&nodes, 0).status().remove(0);
+        assert!(ahead.snapshot_index > 0);
+        assert!(ahead.applied_index - ahead.snapshot_index < 4);
+
+        let replica = &nodes[lagging as usize - 1];
+        let shard = || {
+            replica
+                .db
+                .get_collection(&shard_collection("c", 0))
+                .unwrap()
+        };
+        // A snapshot sent while it was down is resent after a while
+        for _ in 0..ELECTION_TICKS * 20 {
+            for node in &nodes {
+                node.tick();
+            }
+            settle(&nodes);
+            if shard().len() == 11 {
+                break;
+            }
+        }
+        assert_eq!(shard().len(), 11);
+        let status = replica.status().remove(0);
+        assert!(status.snapshot_index >= ahead.snapshot_index);
+        assert_eq!(status.applied_index, ahead.applied_index);
+
+        // Later writes reach it as entries
+        write(&nodes, 0, upsert("new", 20.0));
+        assert_eq!(shard().len(), 12);
+    }
+
+    #[test]
+    fn test_restart_replays_only_after_the_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let map = ShardMap::new(vec![1, 2, 3], 1, 3).unwrap();
+        let open = |id: NodeId| {
+            let node_dir = dir.path().join(id.to_string());
+            let db = Arc::new(Database::open(node_dir.join("data")).unwrap());
+            ClusterNode::new(id, map.clone(), db, Some(node_dir.join("raft")))
+                .unwrap()
+                .with_compaction(3)
+        };
+        let mut nodes: Vec<ClusterNode> = (1..=3).map(open).collect();
+        elect(&nodes);
+        let create = Command::CreateCollection {
+            name: "c".to_string(),
+            config: Box::new(Config::builder(2).build().unwrap()),
+        };
+        write(&nodes, 0, create);
+        for i in 0..5 {
+            write(&nodes, 0, upsert(&format!("v{}", i), i as f32));
+        }
+
+        let follower = (0..3)
+            .find(|&i| nodes[i].status()[0].role != Role::Leader)
+            .unwrap();
+        let before = nodes[follower].status().remove(0);
+        assert!(before.snapshot_index > 0);
+        drop(nodes.remove(follower));
+        nodes.insert(follower, open(follower as NodeId + 1));
+
+        // It starts from the snapshot, with its state in the collections
+        let after = nodes[follower].status().remove(0);
+        assert_eq!(after.snapshot_index, before.snapshot_index);
+        assert_eq!(after.applied_index, before.snapshot_index);
+        assert_eq!(after.commit_index, before.commit_index);
+        nodes[follower].apply_committed();
+        assert_eq!(
+            nodes[follower].status()[0].applied_index,
+            before.commit_index
+        );
+        let shard = nodes[follower]
+            .db
+            .get_collection(&shard_collection("c", 0))
+            .unwrap();
+        assert_eq!(shard.len(), 5);
+    }
+}
@@ -0,0 +1,1055 @@
+//! Raft consensus for one replica group
+//!
+//! A state machine without I/O, in the style of etcd's raft: the caller
+//! feeds it clock ticks and incoming messages, sends the messages it queues
+//! and applies the entries it commits. An entry is committed once a majority
+//! of the group has it in its log, so it survives any minority of the group
+//! failing. A new leader first commits an empty entry of its own term, which
+//! also commits what earlier leaders left behind.
+//!
+//! Before sending the messages a member queued, the caller writes what
+//! [`unsaved`](Raft::unsaved) returns to stable storage, so a member that
+//! restarts with [`restore`](Raft::restore) can't vote twice in a term or
+//! lose entries it acknowledged.
+//!
+//! Once the caller has saved the state its applied entries built, it
+//! [`compact`](Raft::compact)s them out of the log. A member whose next
+//! entries were compacted away gets a [`Snapshot`] instead: the leader asks
+//! the caller for one with [`take_snapshot_requests`](Raft::take_snapshot_requests),
+//! and the member's caller installs what [`take_snapshot`](Raft::take_snapshot)
+//! returns and reports it with [`snapshot_installed`](Raft::snapshot_installed).
+
+use crate::NodeId;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Ticks without hearing from a leader before a follower stands for
+/// election; each timeout is drawn between this and twice this
+pub const ELECTION_TICKS: u32 = 10;
+/// Ticks between a leader's heartbeats
+pub const HEARTBEAT_TICKS: u32 = 2;
+/// Most entries sent in one append
+const MAX_APPEND: usize = 256;
+/// Ticks a leader waits to hear a snapshot was installed before sending
+/// another
+const SNAPSHOT_RETRY_TICKS: u32 = ELECTION_TICKS * 10;
+
+/// A log entry; `command` is `None` for the entry a leader starts its term with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry<C> {
+    pub term: u64,
+    /// Position in the log, from 1
+    pub index: u64,
+    pub command: Option<C>,
+}
+
+/// The state of a group up to a log index, for a member whose next entries
+/// were compacted away
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot<C> {
+    /// Index of the last entry it covers
+    pub index: u64,
+    /// Term of that entry
+    pub term: u64,
+    /// Commands that build the state from nothing
+    pub commands: Vec<C>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Message<C> {
+    RequestVote {
+        term: u64,
+        last_index: u64,
+        last_term: u64,
+    },
+    Vote {
+        term: u64,
+        granted: bool,
+    },
+    Append {
+        term: u64,
+        prev_index: u64,
+        prev_term: u64,
+        entries: Vec<Entry<C>>,
+        commit: u64,
+    },
+    /// Answered with an `AppendReply` once installed
+    Snapshot {
+        term: u64,
+        snapshot: Snapshot<C>,
+    },
+    AppendReply {
+        term: u64,
+        success: bool,
+        /// Last index the follower now shares with the leader, or on failure
+        /// where the leader should retry from
+        match_index: u64,
+    },
+}
+
+impl<C> Message<C> {
+    pub fn term(&self) -> u64 {
+        match self {
+            Message::RequestVote { term, .. }
+            | Message::Vote { term, .. }
+            | Message::Append { term, .. }
+            | Message::Snapshot { term, .. }
+            | Message::AppendReply { term, .. } => *term,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    Follower,
+    Candidate,
+    Leader,
+}
+
+/// What a member must remember across restarts besides its log
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HardState {
+    pub term: u64,
+    pub voted_for: Option<NodeId>,
+    pub commit: u64,
+    /// Index of the last entry compacted out of the log, whose state the
+    /// caller saved; the log continues after it
+    #[serde(default)]
+    pub snapshot_index: u64,
+    /// Term of that entry
+    #[serde(default)]
+    pub snapshot_term: u64,
+}
+
+/// Changes to write to stable storage before the queued messages are sent
+#[derive(Debug, Clone, PartialEq)]
+pub struct Unsaved<C> {
+    /// The hard state, if it changed
+    pub state: Option<HardState>,
+    /// Last index of the stored log that is still current
+    pub keep: u64,
+    /// Entries to store after it
+    pub entries: Vec<Entry<C>>,
+}
+
+/// One member's view of a replica group
+pub struct Raft<C> {
+    id: NodeId,
+    /// The other members
+    peers: Vec<NodeId>,
+    term: u64,
+    voted_for: Option<NodeId>,
+    /// Entries after the snapshot
+    log: Vec<Entry<C>>,
+    /// Index and term of the last entry compacted out of the log
+    snapshot_index: u64,
+    snapshot_term: u64,
+    commit: u64,
+    applied: u64,
+    role: Role,
+    leader: Option<NodeId>,
+    votes: HashSet<NodeId>,
+    next_index: HashMap<NodeId, u64>,
+    match_index: HashMap<NodeId, u64>,
+    /// Ticks since the last heartbeat sent or leader heard from
+    elapsed: u32,
+    election_timeout: u32,
+    outbox: Vec<(NodeId, Message<C>)>,
+    /// Hard state last written to stable storage
+    saved: HardState,
+    /// Last index of the log that is in stable storage
+    stable: u64,
+    /// Snapshot from the leader, until the caller takes it to install
+    incoming: Option<Snapshot<C>>,
+    /// Peers to send a snapshot, until the caller takes them
+    snapshot_wanted: BTreeSet<NodeId>,
+    /// Ticks since a snapshot was sent to each peer that hasn't installed it
+    snapshot_sent: HashMap<NodeId, u32>,
+}
+
+impl<C: Clone> Raft<C> {
+    /// Member `id` of the group made of `members`, which may include `id`
+    pub fn new(id: NodeId, members: &[NodeId]) -> Self {
+        Self::restore(id, members, HardState::default(), Vec::new())
+    }
+
+    /// Like [`new`](Self::new), resuming from the state and log stored before
+    /// a restart; `log` holds the entries after the snapshot
+    ///
+    /// The committed entries after the snapshot are handed out again by
+    /// [`take_committed`](Self::take_committed).
+    pub fn restore(id: NodeId, members: &[NodeId], state: HardState, log: Vec<Entry<C>>) -> Self {
+        let mut peers: Vec<NodeId> = members.iter().copied().filter(|&m| m != id).collect();
+        peers.sort_unstable();
+        peers.dedup();
+        let last_index = state.snapshot_index + log.len() as u64;
+        Self {
+            id,
+            peers,
+            term: state.term,
+            voted_for: state.voted_for,
+            commit: state.commit.clamp(state.snapshot_index, last_index),
+            stable: last_index,
+            log,
+            snapshot_index: state.snapshot_index,
+            snapshot_term: state.snapshot_term,
+            applied: state.snapshot_index,
+            role: Role::Follower,
+            leader: None,
+            votes: HashSet::new(),
+            next_index: HashMap::new(),
+            match_index: HashMap::new(),
+            elapsed: 0,
+            election_timeout: random_timeout(),
+            outbox: Vec::new(),
+            saved: state,
+            incoming: None,
+            snapshot_wanted: BTreeSet::new(),
+            snapshot_sent: HashMap::new(),
+        }
+    }
+
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    /// Leader of the current term, if known
+    pub fn leader(&self) -> Option<NodeId> {
+        self.leader
+    }
+
+    pub fn term(&self) -> u64 {
+        self.term
+    }
+
+    pub fn commit_index(&self) -> u64 {
+        self.commit
+    }
+
+    pub fn last_index(&self) -> u64 {
+        self.snapshot_index + self.log.len() as u64
+    }
+
+    /// Index of the last entry compacted out of the log
+    pub fn snapshot_index(&self) -> u64 {
+        self.snapshot_index
+    }
+
+    /// Term of the entry at `index`; 0 for the empty start of the log, and
+    /// `None` past the end or before the snapshot
+    pub fn entry_term(&self, index: u64) -> Option<u64> {
+        if index == self.snapshot_index {
+            return Some(self.snapshot_term);
+        }
+        let position = index.checked_sub(self.snapshot_index + 1)?;
+        self.log.get(position as usize).map(|e| e.term)
+    }
+
+    fn last_term(&self) -> u64 {
+        self.log.last().map_or(self.snapshot_term, |e| e.term)
+    }
+
+    /// Entries of the log up to `index`, which is not before the snapshot
+    fn offset(&self, index: u64) -> usize {
+        (index - self.snapshot_index) as usize
+    }
+
+    fn quorum(&self) -> usize {
+        self.peers.len().div_ceil(2) + 1
+    }
+
+    /// Advance the clock by one tick
+    pub fn tick(&mut self) {
+        self.elapsed += 1;
+        if self.role == Role::Leader {
+            for ticks in self.snapshot_sent.values_mut() {
+                *ticks += 1;
+            }
+            self.snapshot_sent
+                .retain(|_, &mut ticks| ticks < SNAPSHOT_RETRY_TICKS);
+            if self.elapsed >= HEARTBEAT_TICKS {
+                self.elapsed = 0;
+                self.broadcast_append();
+            }
+        } else if self.elapsed >= self.election_timeout {
+            self.campaign();
+        }
+    }
+
+    /// Append `command` to the log if this member leads; returns the term
+    /// and index it was given, or the leader to send it to instead
+    ///
+    /// The command takes effect once committed. If leadership changes first,
+    /// another entry may end up at that index, so callers compare the term
+    /// of the committed entry with [`entry_term`](Self::entry_term).
+    pub fn propose(&mut self, command: C) -> Result<(u64, u64), Option<NodeId>> {
+        if self.role != Role::Leader {
+            return Err(self.leader);
+        }
+        let index = self.append_own(Some(command));
+        Ok((self.term, index))
+    }
+
+    /// Messages queued for other members since the last call
+    pub fn take_messages(&mut self) -> Vec<(NodeId, Message<C>)> {
+        std::mem::take(&mut self.outbox)
+    }
+
+    /// What changed since the last [`saved`](Self::saved), if anything
+    pub fn unsaved(&self) -> Option<Unsaved<C>> {
+        let state = self.hard_state();
+        let state = (state != self.saved).then_some(state);
+        if state.is_none() && self.stable == self.last_index() {
+            return None;
+        }
+        Some(Unsaved {
+            state,
+            keep: self.stable,
+            entries: self.log[self.offset(self.stable)..].to_vec(),
+        })
+    }
+
+    /// Record that what [`unsaved`](Self::unsaved) returned was written
+    pub fn saved(&mut self) {
+        self.saved = self.hard_state();
+        self.stable = self.last_index();
+    }
+
+    fn hard_state(&self) -> HardState {
+        HardState {
+            term: self.term,
+            voted_for: self.voted_for,
+            commit: self.commit,
+            snapshot_index: self.snapshot_index,
+            snapshot_term: self.snapshot_term,
+        }
+    }
+
+    /// Entries committed since the last call, to apply in order
+    pub fn take_committed(&mut self) -> Vec<Entry<C>> {
+        let entries = self.log[self.offset(self.applied)..self.offset(self.commit)].to_vec();
+        self.applied = self.commit;
+        entries
+    }
+
+    /// Drop the applied entries up to `index` from the log, once the caller
+    /// has saved the state they built
+    pub fn compact(&mut self, index: u64) {
+        let index = index.min(self.applied);
+        if index <= self.snapshot_index {
+            return;
+        }
+        self.snapshot_term = self
+            .entry_term(index)
+            .expect("applied entries are in the log");
+        self.log.drain(..self.offset(index));
+        self.snapshot_index = index;
+        self.stable = self.stable.max(index);
+    }
+
+    /// Snapshot received from the leader, to install in place of the state
+    /// built so far
+    ///
+    /// Entries committed after it are handed out once it is reported
+    /// installed.
+    pub fn take_snapshot(&mut self) -> Option<Snapshot<C>> {
+        self.incoming.take()
+    }
+
+    /// Record that the caller installed and saved the snapshot up to `index`
+    /// at `term`, and tell the leader
+    pub fn snapshot_installed(&mut self, index: u64, term: u64) {
+        if index > self.snapshot_index {
+            if self.entry_term(index) == Some(term) {
+                // The log continues past it; keep what follows
+                self.log.drain(..self.offset(index));
+                self.stable = self.stable.max(index);
+            } else {
+                self.log.clear();
+                self.stable = index;
+            }
+            self.snapshot_index = index;
+            self.snapshot_term = term;
+            self.commit = self.commit.max(index);
+            self.applied = self.applied.max(index);
+        }
+        if let Some(leader) = self.leader {
+            self.send(
+                leader,
+                Message::AppendReply {
+                    term: self.term,
+                    success: true,
+                    match_index: index,
+                },
+            );
+        }
+    }
+
+    /// Peers that need a snapshot, as the entries they lack were compacted
+    /// away; the caller answers with [`send_snapshot`](Self::send_snapshot)
+    pub fn take_snapshot_requests(&mut self) -> Vec<NodeId> {
+        std::mem::take(&mut self.snapshot_wanted)
+            .into_iter()
+            .collect()
+    }
+
+    /// Send `peer` the state up to `index` as `commands`, which build it from
+    /// nothing; `index` must be applied
+    pub fn send_snapshot(&mut self, peer: NodeId, index: u64, commands: Vec<C>) {
+        if self.role != Role::Leader || index > self.applied {
+            return;
+        }
+        let Some(term) = self.entry_term(index) else {
+            return;
+        };
+        self.snapshot_sent.insert(peer, 0);
+        self.send(
+            peer,
+            Message::Snapshot {
+                term: self.term,
+                snapshot: Snapshot {
+                    index,
+                    term,
+                    commands,
+                },
+            },
+        );
+    }
+
+    /// Handle `message` from member `from`
+    pub fn step(&mut self, from: NodeId, message: Message<C>) {
+        if message.term() > self.term {
+            let leader = matches!(message, Message::Append { .. } | Message::Snapshot { .. })
+                .then_some(from);
+            self.become_follower(message.term(), leader);
+        }
+        match message {
+            Message::RequestVote {
+                term,
+                last_index,
+                last_term,
+            } => {
+                let up_to_date = last_term > self.last_term()
+                    || (last_term == self.last_term() && last_index >= self.last_index());
+                let granted =
+                    term == self.term && self.voted_for.is_none_or(|v| v == from) && up_to_date;
+                if granted {
+                    self.voted_for = Some(from);
+                    self.elapsed = 0;
+                }
+                self.send(
+                    from,
+                    Message::Vote {
+                        term: self.term,
+                        granted,
+                    },
+                );
+            }
+            Message::Vote { term, granted } => {
+                if self.role == Role::Candidate && term == self.term && granted {
+                    self.votes.insert(from);
+                    if self.votes.len() >= self.quorum() {
+                        self.become_leader();
+                    }
+                }
+            }
+            Message::Append {
+                term,
+                prev_index,
+                prev_term,
+                entries,
+                commit,
+            } => self.handle_append(from, term, prev_index, prev_term, entries, commit),
+            Message::Snapshot { term, snapshot } => self.handle_snapshot(from, term, snapshot),
+            Message::AppendReply {
+                term,
+                success,
+                match_index,
+            } => {
+                if self.role != Role::Leader || term != self.term {
+                    return;
+                }
+                if success {
+                    self.snapshot_sent.remove(&from);
+                    let matched = self.match_index.entry(from).or_default();
+                    *matched = (*matched).max(match_index);
+                    let matched = *matched;
+                    self.next_index.insert(from, matched + 1);
+                    self.maybe_commit();
+                    if matched < self.last_index() {
+                        self.send_append(from);
+                    }
+                } else {
+                    let next = self.next_index.entry(from).or_insert(1);
+                    *next = (match_index + 1).min(next.saturating_sub(1)).max(1);
+                    self.send_append(from);
+                }
+            }
+        }
+    }
+
+    fn handle_append(
+        &mut self,
+        from: NodeId,
+        term: u64,
+        prev_index: u64,
+        prev_term: u64,
+        entries: Vec<Entry<C>>,
+        commit: u64,
+    ) {
+        if term < self.term {
+            self.send(
+                from,
+                Message::AppendReply {
+                    term: self.term,
+                    success: false,
+                    match_index: 0,
+                },
+            );
+            return;
+        }
+        // A candidate of this term lost to `from`
+        self.role = Role::Follower;
+        self.leader = Some(from);
+        self.elapsed = 0;
+
+        let compacted = prev_index < self.snapshot_index;
+        if !compacted && self.entry_term(prev_index) != Some(prev_term) {
+            let retry_from = self.last_index().min(prev_index.saturating_sub(1));
+            self.send(
+                from,
+                Message::AppendReply {
+                    term: self.term,
+                    success: false,
+                    match_index: retry_from,
+                },
+            );
+            return;
+        }
+
+        let matched = prev_index + entries.len() as u64;
+        for entry in entries {
+            // Compacted entries were committed, so they match the leader's
+            if entry.index <= self.snapshot_index {
+                continue;
+            }
+            match self.entry_term(entry.index) {
+                Some(term) if term == entry.term => continue,
+                Some(_) => {
+                    // Conflicts with the leader's log; committed entries never do
+                    self.log.truncate(self.offset(entry.index - 1));
+                    self.stable = self.stable.min(entry.index - 1);
+                    self.log.push(entry);
+                }
+                None => self.log.push(entry),
+            }
+        }
+        self.commit = self.commit.max(commit.min(matched));
+        self.send(
+            from,
+            Message::AppendReply {
+                term: self.term,
+                success: true,
+                match_index: matched,
+            },
+        );
+    }
+
+    fn handle_snapshot(&mut self, from: NodeId, term: u64, snapshot: Snapshot<C>) {
+        if term < self.term {
+            self.send(
+                from,
+                Message::AppendReply {
+                    term: self.term,
+                    success: false,
+                    match_index: 0,
+                },
+            );
+            return;
+        }
+        self.role = Role::Follower;
+        self.leader = Some(from);
+        self.elapsed = 0;
+
+        if snapshot.index <= self.commit {
+            // Its committed entries already cover the snapshot
+            self.send(
+                from,
+                Message::AppendReply {
+                    term: self.term,
+                    success: true,
+                    match_index: self.commit,
+                },
+            );
+            return;
+        }
+        self.incoming = Some(snapshot);
+    }
+
+    fn campaign(&mut self) {
+        self.term += 1;
+        self.role = Role::Candidate;
+        self.voted_for = Some(self.id);
+        self.leader = None;
+        self.votes = HashSet::from([self.id]);
+        self.elapsed = 0;
+        self.election_timeout = random_timeout();
+        if self.votes.len() >= self.quorum() {
+            self.become_leader();
+            return;
+        }
+        let (last_index, last_term) = (self.last_index(), self.last_term());
+        for peer in self.peers.clone() {
+            self.send(
+                peer,
+                Message::RequestVote {
+                    term: self.term,
+                    last_index,
+                    last_term,
+                },
+            );
+        }
+    }
+
+    fn become_follower(&mut self, term: u64, leader: Option<NodeId>) {
+        self.term = term;
+        self.voted_for = None;
+        self.role = Role::Follower;
+        self.leader = leader;
+        self.elapsed = 0;
+        self.election_timeout = random_timeout();
+    }
+
+    fn become_leader(&mut self) {
+        self.role = Role::Leader;
+        self.leader = Some(self.id);
+        self.elapsed = 0;
+        let next = self.last_index() + 1;
+        self.next_index = self.peers.iter().map(|&p| (p, next)).collect();
+        self.match_index = self.peers.iter().map(|&p| (p, 0)).collect();
+        self.snapshot_wanted.clear();
+        self.snapshot_sent.clear();
+        self.append_own(None);
+    }
+
+    /// Append an entry of the current term and send it out
+    fn append_own(&mut self, command: Option<C>) -> u64 {
+        let index = self.last_index() + 1;
+        self.log.push(Entry {
+            term: self.term,
+            index,
+            command,
+        });
+        self.maybe_commit();
+        self.broadcast_append();
+        index
+    }
+
+    fn broadcast_append(&mut self) {
+        for peer in self.peers.clone() {
+            if self.next(peer) <= self.snapshot_index {
+                // Keeps a peer waiting for a snapshot from standing for election
+                let heartbeat = Message::Append {
+                    term: self.term,
+                    prev_index: self.snapshot_index,
+                    prev_term: self.snapshot_term,
+                    entries: Vec::new(),
+                    commit: self.commit,
+                };
+                self.send(peer, heartbeat);
+            }
+            self.send_append(peer);
+        }
+    }
+
+    fn next(&self, peer: NodeId) -> u64 {
+        self.next_index
+            .get(&peer)
+            .copied()
+            .unwrap_or(1)
+            .clamp(1, self.last_index() + 1)
+    }
+
+    fn send_append(&mut self, peer: NodeId) {
+        let prev_index = self.next(peer) - 1;
+        if prev_index < self.snapshot_index {
+            // What it lacks was compacted away
+            if !self.snapshot_sent.contains_key(&peer) {
+                self.snapshot_wanted.insert(peer);
+            }
+            return;
+        }
+        let start = self.offset(prev_index);
+        let end = (start + MAX_APPEND).min(self.log.len());
+        let message = Message::Append {
+            term: self.term,
+            prev_index,
+            prev_term: self.entry_term(prev_index).unwrap_or(0),
+            entries: self.log[start..end].to_vec(),
+            commit: self.commit,
+        };
+        self.send(peer, message);
+    }
+
+    /// Commit the latest entry of this term a majority has
+    fn maybe_commit(&mut self) {
+        for index in (self.commit + 1..=self.last_index()).rev() {
+            if self.entry_term(index) != Some(self.term) {
+                break;
+            }
+            let replicas = 1 + self.match_index.values().filter(|&&m| m >= index).count();
+            if replicas >= self.quorum() {
+                self.commit = index;
+                break;
+            }
+        }
+    }
+
+    fn send(&mut self, to: NodeId, message: Message<C>) {
+        self.outbox.push((to, message));
+    }
+}
+
+fn random_timeout() -> u32 {
+    rand::thread_rng().gen_range(ELECTION_TICKS..ELECTION_TICKS * 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    /// Members connected by an in-memory network that can be cut
+    struct Network {
+        nodes: BTreeMap<NodeId, Raft<u32>>,
+        down: HashSet<NodeId>,
+        /// Commands each member applied, as its state
+        states: BTreeMap<NodeId, Vec<u32>>,
+    }
+
+    impl Network {
+        fn new(size: u64) -> Self {
+            let members: Vec<NodeId> = (1..=size).collect();
+            Self {
+                nodes: members
+                    .iter()
+                    .map(|&id| (id, Raft::new(id, &members)))
+                    .collect(),
+                down: HashSet::new(),
+                states: BTreeMap::new(),
+            }
+        }
+
+        /// Apply what each member committed, installing snapshots and
+        /// answering requests for them as a caller would
+        fn apply(&mut self) {
+            for (id, node) in &mut self.nodes {
+                let state = self.states.entry(*id).or_default();
+                if let Some(snapshot) = node.take_snapshot() {
+                    *state = snapshot.commands;
+                    node.snapshot_installed(snapshot.index, snapshot.term);
+                }
+                state.extend(node.take_committed().into_iter().filter_map(|e| e.command));
+                for peer in node.take_snapshot_requests() {
+                    node.send_snapshot(peer, node.applied, state.clone());
+                }
+            }
+        }
+
+        /// Deliver messages until none are left
+        fn settle(&mut self) {
+            loop {
+                let mut messages = Vec::new();
+                for (&id, node) in &mut self.nodes {
+                    for (to, message) in node.take_messages() {
+                        messages.push((id, to, message));
+                    }
+                }
+                if messages.is_empty() {
+                    return;
+                }
+                for (from, to, message) in messages {
+                    if !self.down.contains(&from) && !self.down.contains(&to) {
+                        self.nodes.get_mut(&to).unwrap().step(from, message);
+                    }
+                }
+            }
+        }
+
+        fn tick(&mut self, ticks: u32) {
+            for _ in 0..ticks {
+                for (id, node) in &mut self.nodes {
+                    if !self.down.contains(id) {
+                        node.tick();
+                    }
+                }
+                self.settle();
+            }
+        }
+
+        /// Tick until a member that is up leads; split votes can take a few
+        /// rounds
+        fn elect(&mut self) -> NodeId {
+            for _ in 0..ELECTION_TICKS * 100 {
+                if let Some(leader) = self.leader() {
+                    return leader;
+                }
+                self.tick(1);
+            }
+            panic!("No leader elected");
+        }
+
+        fn leader(&self) -> Option<NodeId> {
+            let leaders: Vec<NodeId> = self
+                .nodes
+                .iter()
+                .filter(|(id, node)| !self.down.contains(id) && node.role() == Role::Leader)
+                .map(|(&id, _)| id)
+                .collect();
+            let newest = leaders.iter().map(|id| self.nodes[id].term()).max()?;
+            leaders
+                .into_iter()
+                .find(|id| self.nodes[id].term() == newest)
+        }
+
+        fn commands(&mut self, id: NodeId) -> Vec<u32> {
+            let node = self.nodes.get_mut(&id).unwrap();
+            node.log[..node.offset(node.commit)]
+                .iter()
+                .filter_map(|e| e.command)
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_elects_one_leader_and_replicates() {
+        let mut net = Network::new(3);
+        let leader = net.elect();
+        assert_eq!(
+            net.nodes
+                .values()
+                .filter(|n| n.role() == Role::Leader)
+                .count(),
+            1
+        );
+
+        let (term, index) = net.nodes.get_mut(&leader).unwrap().propose(7).unwrap();
+        net.settle();
+        net.tick(HEARTBEAT_TICKS);
+        for id in 1..=3 {
+            assert_eq!(net.commands(id), [7]);
+            assert_eq!(net.nodes[&id].entry_term(index), Some(term));
+        }
+        let follower = (1..=3).find(|&id| id != leader).unwrap();
+        assert_eq!(
+            net.nodes.get_mut(&follower).unwrap().propose(8),
+            Err(Some(leader))
+        );
+    }
+
+    #[test]
+    fn test_leader_failure() {
+        let mut net = Network::new(3);
+        let old = net.elect();
+        net.nodes.get_mut(&old).unwrap().propose(1).unwrap();
+        net.settle();
+
+        // Cut off, the old leader can't commit
+        net.down.insert(old);
+        net.nodes.get_mut(&old).unwrap().propose(2).unwrap();
+        let new = net.elect();
+        assert_ne!(new, old);
+        net.nodes.get_mut(&new).unwrap().propose(3).unwrap();
+        net.tick(HEARTBEAT_TICKS);
+
+        // Back, it drops its uncommitted entry for the new leader's log
+        net.down.clear();
+        net.tick(ELECTION_TICKS);
+        assert_eq!(net.leader(), Some(new));
+        for id in 1..=3 {
+            assert_eq!(net.commands(id), [1, 3]);
+        }
+        let committed = net.nodes.get_mut(&old).unwrap().take_committed();
+        assert_eq!(
+            committed
+                .iter()
+                .filter_map(|e| e.command)
+                .collect::<Vec<_>>(),
+            [1, 3]
+        );
+    }
+
+    #[test]
+    fn test_minority_cannot_commit() {
+        let mut net = Network::new(3);
+        let leader = net.elect();
+        net.down.extend((1..=3).filter(|&id| id != leader));
+        net.nodes.get_mut(&leader).unwrap().propose(1).unwrap();
+        net.tick(HEARTBEAT_TICKS * 3);
+        assert!(net.commands(leader).is_empty());
+    }
+
+    #[test]
+    fn test_single_member_commits_alone() {
+        let mut raft = Raft::new(1, &[1]);
+        for _ in 0..ELECTION_TICKS * 2 {
+            raft.tick();
+        }
+        assert_eq!(raft.role(), Role::Leader);
+        raft.propose(5).unwrap();
+        let committed = raft.take_committed();
+        assert_eq!(
+            committed
+                .iter()
+                .filter_map(|e| e.command)
+                .collect::<Vec<_>>(),
+            [5]
+        );
+        assert!(raft.take_messages().is_empty());
+    }
+
+    #[test]
+    fn test_restored_member_keeps_its_vote_and_log() {
+        let mut raft = Raft::new(1, &[1, 2, 3]);
+        raft.step(
+            2,
+            Message::RequestVote {
+                term: 1,
+                last_index: 0,
+                last_term: 0,
+            },
+        );
+        let entry = Entry {
+            term: 1,
+            index: 1,
+            command: Some(4),
+        };
+        raft.step(
+            2,
+            Message::Append {
+                term: 1,
+                prev_index: 0,
+                prev_term: 0,
+                entries: vec![entry.clone()],
+                commit: 1,
+            },
+        );
+        let unsaved = raft.unsaved().unwrap();
+        let state = HardState {
+            term: 1,
+            voted_for: Some(2),
+            commit: 1,
+            ..Default::default()
+        };
+        assert_eq!(unsaved.state, Some(state));
+        assert_eq!((unsaved.keep, unsaved.entries.clone()), (0, vec![entry]));
+        raft.saved();
+        assert!(raft.unsaved().is_none());
+
+        let mut restored = Raft::restore(1, &[1, 2, 3], state, unsaved.entries);
+        restored.step(
+            3,
+            Message::RequestVote {
+                term: 1,
+                last_index: 1,
+                last_term: 1,
+            },
+        );
+        assert_eq!(
+            restored.take_messages(),
+            [(
+                3,
+                Message::Vote {
+                    term: 1,
+                    granted: false
+                }
+            )]
+        );
+        let committed = restored.take_committed();
+        assert_eq!(
+            committed
+                .iter()
+                .filter_map(|e| e.command)
+                .collect::<Vec<_>>(),
+            [4]
+        );
+    }
+
+    #[test]
+    fn test_compacted_log_restores_after_snapshot() {
+        let mut raft = Raft::new(1, &[1]);
+        for _ in 0..ELECTION_TICKS * 2 {
+            raft.tick();
+        }
+        for command in 1..=3 {
+            raft.propose(command).unwrap();
+        }
+        assert_eq!(raft.take_committed().len(), 4);
+        raft.saved();
+        raft.compact(3);
+        assert_eq!(raft.snapshot_index(), 3);
+        assert_eq!(raft.last_index(), 4);
+        assert_eq!(raft.entry_term(2), None);
+
+        let unsaved = raft.unsaved().unwrap();
+        let state = unsaved.state.unwrap();
+        assert_eq!((state.snapshot_index, state.snapshot_term), (3, 1));
+        assert_eq!((unsaved.keep, unsaved.entries.len()), (4, 0));
+        raft.saved();
+
+        // Only the entries after the snapshot are applied again
+        let log = raft.log.clone();
+        let mut restored = Raft::restore(1, &[1], state, log);
+        assert_eq!(restored.last_index(), 4);
+        let committed = restored.take_committed();
+        assert_eq!(
+            committed
+                .iter()
+                .filter_map(|e| e.command)
+                .collect::<Vec<_>>(),
+            [3]
+        );
+    }
+
+    #[test]
+    fn test_lagging_member_catches_up_from_snapshot() {
+        let mut net = Network::new(3);
+        let leader = net.elect();
+        let lagging = (1..=3).find(|&id| id != leader).unwrap();
+        net.down.insert(lagging);
+        for command in 1..=5 {
+            net.nodes
+                .get_mut(&leader)
+                .unwrap()
+                .propose(command)
+                .unwrap();
+            net.settle();
+        }
+        net.tick(HEARTBEAT_TICKS);
+        net.apply();
+        for node in net.nodes.values_mut() {
+            node.compact(node.applied);
+        }
+        let snapshot_index = net.nodes[&leader].snapshot_index();
+        assert!(snapshot_index >= 6);
+        assert_eq!(net.nodes[&leader].log.len(), 0);
+
+        net.down.clear();
+        for _ in 0..ELECTION_TICKS {
+            net.tick(1);
+            net.apply();
+        }
+        assert_eq!(net.leader(), Some(leader));
+        assert_eq!(net.states[&lagging], [1, 2, 3, 4, 5]);
+        assert_eq!(net.nodes[&lagging].snapshot_index(), snapshot_index);
+
+        // It follows with entries again
+        net.nodes.get_mut(&leader).unwrap().propose(6).unwrap();
+        net.tick(HEARTBEAT_TICKS);
+        net.apply();
+        assert_eq!(net.states[&lagging], [1, 2, 3, 4, 5, 6]);
+        assert_eq!(net.nodes[&lagging].snapshot_index(), snapshot_index);
+    }
+}
@@ -0,0 +1,336 @@
+//! Placement of vectors on shards and of shards on nodes
+
+use crate::{Error, NodeId, Result, ShardId};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// How strictly the replicas of a shard are kept in distinct failure domains
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Placement {
+    /// Use as many distinct domains as there are, sharing one only when there
+    /// are fewer domains than replicas
+    #[default]
+    Spread,
+    /// Refuse a map in which any shard has two replicas in one domain
+    Strict,
+}
+
+/// Replicas of a shard that share a failure domain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlacementViolation {
+    pub shard: ShardId,
+    pub domain: String,
+    pub nodes: Vec<NodeId>,
+}
+
+/// Which shard every vector belongs to and which nodes keep every shard
+///
+/// All nodes must be started with the same map; vectors don't move when it
+/// changes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShardMap {
+    /// Sorted and without duplicates
+    nodes: Vec<NodeId>,
+    shards: u32,
+    replication: usize,
+    /// Failure domain (rack, zone) of each labelled node; every unlabelled
+    /// node is a domain of its own
+    #[serde(default)]
+    domains: BTreeMap<NodeId, String>,
+}
+
+impl ShardMap {
+    pub fn new(mut nodes: Vec<NodeId>, shards: u32, replication: usize) -> Result<Self> {
+        nodes.sort_unstable();
+        nodes.dedup();
+        if nodes.is_empty() {
+            return Err(Error::InvalidConfig("A cluster needs a node".to_string()));
+        }
+        if shards == 0 {
+            return Err(Error::InvalidConfig("A cluster needs a shard".to_string()));
+        }
+        if replication == 0 || replication > nodes.len() {
+            return Err(Error::InvalidConfig(format!(
+                "Replication must be between 1 and the number of nodes ({})",
+                nodes.len()
+            )));
+        }
+        Ok(Self {
+            nodes,
+            shards,
+            replication,
+            domains: BTreeMap::new(),
+        })
+    }
+
+    /// Label nodes with their failure domains, so replicas of a shard land in
+    /// distinct ones; with [`Placement::Strict`], fails unless they all can
+    pub fn with_domains(
+        mut self,
+        domains: BTreeMap<NodeId, String>,
+        placement: Placement,
+    ) -> Result<Self> {
+        if let Some(node) = domains.keys().find(|node| !self.nodes.contains(node)) {
+            return Err(Error::InvalidConfig(format!(
+                "Failure domain given for unknown node {}",
+                node
+            )));
+        }
+        self.domains = domains;
+        if placement == Placement::Strict {
+            if let Some(violation) = self.violations().first() {
+                return Err(Error::InvalidConfig(format!(
+                    "Shard {} has replicas {:?} in one failure domain ({}); \
+                     {} replicas need at least {} domains",
+                    violation.shard,
+                    violation.nodes,
+                    violation.domain,
+                    self.replication,
+                    self.replication
+                )));
+            }
+        }
+        Ok(self)
+    }
+
+    pub fn nodes(&self) -> &[NodeId] {
+        &self.nodes
+    }
+
+    pub fn shards(&self) -> u32 {
+        self.shards
+    }
+
+    pub fn replication(&self) -> usize {
+        self.replication
+    }
+
+    /// Failure domain `node` was labelled with
+    pub fn domain(&self, node: NodeId) -> Option<&str> {
+        self.domains.get(&node).map(String::as_str)
+    }
+
+    pub fn domains(&self) -> &BTreeMap<NodeId, String> {
+        &self.domains
+    }
+
+    /// Shard holding the vector `id`
+    pub fn shard_of(&self, id: &str) -> ShardId {
+        (fnv1a(id.as_bytes()) % self.shards as u64) as ShardId
+    }
+
+    /// Nodes keeping `shard`
+    ///
+    /// Shards start at consecutive places on a ring of the nodes, interleaved
+    /// by failure domain, so they spread evenly over nodes and domains. From
+    /// there, each replica goes to the next node in a domain the shard isn't
+    /// in yet, and once every domain is used, to the next node.
+    pub fn replicas(&self, shard: ShardId) -> Vec<NodeId> {
+        let ring = self.ring();
+        let start = shard as usize % ring.len();
+        let order = || ring[start..].iter().chain(&ring[..start]).copied();
+        let mut replicas: Vec<NodeId> = Vec::with_capacity(self.replication);
+        for node in order() {
+            if replicas.len() == self.replication {
+                break;
+            }
+            let domain = self.domain_key(node);
+            if replicas.iter().all(|&r| self.domain_key(r) != domain) {
+                replicas.push(node);
+            }
+        }
+        for node in order() {
+            if replicas.len() == self.replication {
+                break;
+            }
+            if !replicas.contains(&node) {
+                replicas.push(node);
+            }
+        }
+        replicas
+    }
+
+    /// Shards whose replicas share a failure domain, with the replicas in it
+    pub fn violations(&self) -> Vec<PlacementViolation> {
+        let mut violations = Vec::new();
+        for shard in 0..self.shards {
+            let mut by_domain: BTreeMap<&str, Vec<NodeId>> = BTreeMap::new();
+            for node in self.replicas(shard) {
+                if let Some(domain) = self.domain(node) {
+                    by_domain.entry(domain).or_default().push(node);
+                }
+            }
+            violations.extend(
+                by_domain
+                    .into_iter()
+                    .filter(|(_, nodes)| nodes.len() > 1)
+                    .map(|(domain, mut nodes)| {
+                        nodes.sort_unstable();
+                        PlacementViolation {
+                            shard,
+                            domain: domain.to_string(),
+                            nodes,
+                        }
+                    }),
+            );
+        }
+        violations
+    }
+
+    /// Shards with a replica on `node`
+    pub fn shards_of(&self, node: NodeId) -> Vec<ShardId> {
+        (0..self.shards)
+            .filter(|&shard| self.replicas(shard).contains(&node))
+            .collect()
+    }
+
+    /// The nodes taking turns between domains: the first node of each
+    /// domain, then the second of each, and so on
+    fn ring(&self) -> Vec<NodeId> {
+        let mut groups: Vec<(DomainKey<'_>, Vec<NodeId>)> = Vec::new();
+        for &node in &self.nodes {
+            let domain = self.domain_key(node);
+            match groups.iter_mut().find(|(d, _)| *d == domain) {
+                Some((_, nodes)) => nodes.push(node),
+                None => groups.push((domain, vec![node])),
+            }
+        }
+        let longest = groups.iter().map(|(_, nodes)| nodes.len()).max();
+        (0..longest.unwrap_or(0))
+            .flat_map(|i| groups.iter().filter_map(move |(_, nodes)| nodes.get(i)))
+            .copied()
+            .collect()
+    }
+
+    fn domain_key(&self, node: NodeId) -> DomainKey<'_> {
+        match self.domain(node) {
+            Some(domain) => DomainKey::Labelled(domain),
+            None => DomainKey::Node(node),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum DomainKey<'a> {
+    Labelled(&'a str),
+    /// An unlabelled node, alone in its domain
+    Node(NodeId),
+}
+
+/// Name of the local collection storing `shard` of `collection`
+pub fn shard_collection(collection: &str, shard: ShardId) -> String {
+    format!("{}.shard{}", collection, shard)
+}
+
+/// 64-bit FNV-1a, stable across builds and platforms unlike `DefaultHasher`
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_placement() {
+        let map = ShardMap::new(vec![3, 1, 2, 2], 4, 2).unwrap();
+        assert_eq!(map.nodes(), [1, 2, 3]);
+        assert_eq!(map.replicas(0), [1, 2]);
+        assert_eq!(map.replicas(2), [3, 1]);
+        assert_eq!(map.shards_of(1), [0, 2, 3]);
+        // Every shard has `replication` distinct replicas
+        for shard in 0..4 {
+            let mut replicas = map.replicas(shard);
+            replicas.dedup();
+            assert_eq!(replicas.len(), 2);
+        }
+    }
+
+    #[test]
+    fn test_shard_of_is_stable_and_spread() {
+        let map = ShardMap::new(vec![1], 8, 1).unwrap();
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+        let mut counts = [0; 8];
+        for i in 0..8000 {
+            counts[map.shard_of(&format!("doc-{}", i)) as usize] += 1;
+        }
+        assert!(
+            counts.iter().all(|&c| (800..1200).contains(&c)),
+            "{counts:?}"
+        );
+    }
+
+    fn zones(labels: &[(NodeId, &str)]) -> BTreeMap<NodeId, String> {
+        labels.iter().map(|&(n, z)| (n, z.to_string())).collect()
+    }
+
+    #[test]
+    fn test_replicas_spread_over_domains() {
+        // Nodes 1 and 2 share a rack; consecutive placement would put shard 0
+        // on both
+        let labels = zones(&[(1, "a"), (2, "a"), (3, "b"), (4, "b"), (5, "c"), (6, "c")]);
+        let map = ShardMap::new((1..=6).collect(), 12, 3)
+            .unwrap()
+            .with_domains(labels.clone(), Placement::Strict)
+            .unwrap();
+        assert_eq!(map.replicas(0), [1, 3, 5]);
+        assert!(map.violations().is_empty());
+        for shard in 0..12 {
+            let mut domains: Vec<_> = map
+                .replicas(shard)
+                .into_iter()
+                .map(|n| map.domain(n).unwrap())
+                .collect();
+            domains.sort_unstable();
+            assert_eq!(domains, ["a", "b", "c"]);
+        }
+        // Still spread evenly over the nodes
+        for node in 1..=6 {
+            assert_eq!(map.shards_of(node).len(), 6);
+        }
+
+        // Without labels, placement is unchanged
+        let plain = ShardMap::new((1..=6).collect(), 12, 3).unwrap();
+        let unlabelled = plain
+            .clone()
+            .with_domains(BTreeMap::new(), Placement::Strict);
+        assert_eq!(unlabelled.unwrap(), plain);
+        assert_eq!(plain.replicas(0), [1, 2, 3]);
+        assert!(plain.violations().is_empty());
+    }
+
+    #[test]
+    fn test_too_few_domains() {
+        let labels = zones(&[(1, "a"), (2, "a"), (3, "b")]);
+        let map = ShardMap::new(vec![1, 2, 3], 2, 3).unwrap();
+        assert!(map
+            .clone()
+            .with_domains(labels.clone(), Placement::Strict)
+            .is_err());
+        // Spread shares a domain only as much as it has to
+        let map = map.with_domains(labels, Placement::Spread).unwrap();
+        assert_eq!(
+            map.violations(),
+            [0, 1].map(|shard| PlacementViolation {
+                shard,
+                domain: "a".to_string(),
+                nodes: vec![1, 2],
+            })
+        );
+        let unknown = ShardMap::new(vec![1, 2], 1, 1)
+            .unwrap()
+            .with_domains(zones(&[(9, "a")]), Placement::Spread);
+        assert!(unknown.is_err());
+    }
+
+    #[test]
+    fn test_invalid_maps() {
+        assert!(ShardMap::new(vec![], 1, 1).is_err());
+        assert!(ShardMap::new(vec![1], 0, 1).is_err());
+        assert!(ShardMap::new(vec![1, 2], 1, 3).is_err());
+        assert!(ShardMap::new(vec![1, 2], 1, 0).is_err());
+    }
+}
@@ -0,0 +1,380 @@
+//! Raft state of one replica group on disk
+//!
+//! A group keeps its [`HardState`] in `shard{n}.state`, replaced as a whole,
+//! and its log in `shard{n}.log`, one JSON entry per line. The log is
+//! appended to, and cut back where a new leader replaced entries. Once the
+//! state records a later snapshot, the log is rewritten without the entries
+//! it covers; entries the snapshot covers that are still in the file after a
+//! crash are skipped when it is opened. Both are synced before
+//! [`save`](GroupStorage::save) returns.
+//!
+//! While a snapshot from the leader replaces the shard's data,
+//! `shard{n}.installing` exists. A group opened with it left behind starts
+//! over from an empty log, keeping only its term and vote, so the leader
+//! sends it the snapshot again.
+
+use crate::raft::{Entry, HardState, Unsaved};
+use crate::{Result, ShardId};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+pub struct GroupStorage {
+    state_path: PathBuf,
+    log_path: PathBuf,
+    marker_path: PathBuf,
+    /// Whether the group was opened after a snapshot install was cut short
+    interrupted: bool,
+    log: File,
+    /// Index of the entry before the first in the log file
+    base: u64,
+    /// Offset of each entry in the log file, then the offset of its end
+    offsets: Vec<u64>,
+}
+
+impl GroupStorage {
+    /// Open the files of `shard` in `dir`, creating them if missing, along
+    /// with the state and the log after its snapshot they hold
+    pub fn open<C: DeserializeOwned>(
+        dir: &Path,
+        shard: ShardId,
+    ) -> Result<(Self, HardState, Vec<Entry<C>>)> {
+        std::fs::create_dir_all(dir)?;
+        let state_path = dir.join(format!("shard{}.state", shard));
+        let mut state: HardState = match std::fs::read(&state_path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(std::io::Error::from)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HardState::default(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let log_path = dir.join(format!("shard{}.log", shard));
+        let marker_path = dir.join(format!("shard{}.installing", shard));
+        let interrupted = marker_path.exists();
+        if interrupted {
+            warn!(
+                "Shard {} was cut short installing a snapshot; starting over from an empty log",
+                shard
+            );
+            state = HardState {
+                term: state.term,
+                voted_for: state.voted_for,
+                ..Default::default()
+            };
+            write_state(&state_path, &state)?;
+        }
+        let mut log = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(interrupted)
+            .open(&log_path)?;
+        let mut entries: Vec<Entry<C>> = Vec::new();
+        let mut base = state.snapshot_index;
+        let mut offsets = vec![0];
+        let mut reader = BufReader::new(&mut log);
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let read = reader.read_line(&mut line)?;
+            if read == 0 {
+                break;
+            }
+            // A write cut short by a crash leaves a partial last line
+            let entry: Option<Entry<C>> = line
+                .strip_suffix('\n')
+                .and_then(|l| serde_json::from_str(l).ok());
+            // The file starts after a snapshot, at or before the one saved
+            if offsets.len() == 1 {
+                if let Some(first) = &entry {
+                    if first.index <= state.snapshot_index + 1 {
+                        base = first.index - 1;
+                    }
+                }
+            }
+            let next = base + offsets.len() as u64;
+            let Some(entry) = entry.filter(|e| e.index == next) else {
+                warn!(
+                    "Dropping the unreadable end of {} after entry {}",
+                    log_path.display(),
+                    next - 1
+                );
+                break;
+            };
+            if entry.index > state.snapshot_index {
+                entries.push(entry);
+            }
+            offsets.push(offsets[offsets.len() - 1] + read as u64);
+        }
+        let end = offsets[offsets.len() - 1];
+        log.set_len(end)?;
+
+        let mut storage = Self {
+            state_path,
+            log_path,
+            marker_path,
+            interrupted,
+            log,
+            base,
+            offsets,
+        };
+        storage.compact(state.snapshot_index)?;
+        Ok((storage, state, entries))
+    }
+
+    /// Write `unsaved` to disk
+    pub fn save<C: Serialize>(&mut self, unsaved: &Unsaved<C>) -> Result<()> {
+        if let Some(state) = &unsaved.state {
+            write_state(&self.state_path, state)?;
+            // Only once the state records the snapshot are its entries dropped
+            self.compact(state.snapshot_index)?;
+        }
+
+        let keep = (unsaved.keep.max(self.base) - self.base) as usize;
+        let keep = keep.min(self.offsets.len() - 1);
+        if keep == self.offsets.len() - 1 && unsaved.entries.is_empty() {
+            return Ok(());
+        }
+        self.offsets.truncate(keep + 1);
+        let start = self.offsets[keep];
+        let mut bytes = Vec::new();
+        for entry in &unsaved.entries {
+            serde_json::to_writer(&mut bytes, entry).map_err(std::io::Error::from)?;
+            bytes.push(b'\n');
+            self.offsets.push(start + bytes.len() as u64);
+        }
+        self.log.set_len(start)?;
+        self.log.seek(SeekFrom::Start(start))?;
+        self.log.write_all(&bytes)?;
+        self.log.sync_data()?;
+        Ok(())
+    }
+
+    /// Whether the group was opened after a snapshot install was cut short,
+    /// so the shard's data must be dropped before it is used
+    pub fn install_interrupted(&self) -> bool {
+        self.interrupted
+    }
+
+    /// Record that the shard's data is about to be replaced by a snapshot
+    pub fn installing(&self) -> Result<()> {
+        File::create(&self.marker_path)?.sync_all()?;
+        Ok(())
+    }
+
+    /// Record that the shard's data is whole again, after a snapshot was
+    /// installed and its state saved, or the data of an interrupted install
+    /// was dropped
+    pub fn installed(&mut self) -> Result<()> {
+        match std::fs::remove_file(&self.marker_path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        self.interrupted = false;
+        Ok(())
+    }
+
+    /// Rewrite the log file without the entries up to `snapshot_index`
+    fn compact(&mut self, snapshot_index: u64) -> Result<()> {
+        if snapshot_index <= self.base {
+            return Ok(());
+        }
+        let dropped = ((snapshot_index - self.base) as usize).min(self.offsets.len() - 1);
+        let start = self.offsets[dropped];
+        let mut tail = Vec::new();
+        self.log.seek(SeekFrom::Start(start))?;
+        self.log.read_to_end(&mut tail)?;
+
+        let tmp = self.log_path.with_extension("log.tmp");
+        let mut file = File::create(&tmp)?;
+        file.write_all(&tail)?;
+        file.sync_all()?;
+        std::fs::rename(&tmp, &self.log_path)?;
+        self.log = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&self.log_path)?;
+        self.offsets = self.offsets[dropped..].iter().map(|o| o - start).collect();
+        self.base = snapshot_index;
+        Ok(())
+    }
+}
+
+/// Replace the state file as a whole
+fn write_state(path: &Path, state: &HardState) -> Result<()> {
+    let tmp = path.with_extension("state.tmp");
+    let mut file = File::create(&tmp)?;
+    file.write_all(&serde_json::to_vec(state).map_err(std::io::Error::from)?)?;
+    file.sync_all()?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(term: u64, index: u64) -> Entry<u32> {
+        Entry {
+            term,
+            index,
+            command: Some(index as u32),
+        }
+    }
+
+    #[test]
+    fn test_reopen_keeps_state_and_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut storage, state, log) = GroupStorage::open::<u32>(dir.path(), 3).unwrap();
+        assert_eq!(state, HardState::default());
+        assert!(log.is_empty());
+
+        let state = HardState {
+            term: 2,
+            voted_for: Some(1),
+            commit: 1,
+            ..Default::default()
+        };
+        storage
+            .save(&Unsaved {
+                state: Some(state),
+                keep: 0,
+                entries: vec![entry(1, 1), entry(1, 2), entry(1, 3)],
+            })
+            .unwrap();
+        // A new leader replaced the last two entries
+        storage
+            .save(&Unsaved {
+                state: None,
+                keep: 1,
+                entries: vec![entry(2, 2)],
+            })
+            .unwrap();
+        drop(storage);
+
+        let (_, reopened, log) = GroupStorage::open::<u32>(dir.path(), 3).unwrap();
+        assert_eq!(reopened, state);
+        assert_eq!(log, [entry(1, 1), entry(2, 2)]);
+    }
+
+    #[test]
+    fn test_partial_last_entry_is_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut storage, _, _) = GroupStorage::open::<u32>(dir.path(), 0).unwrap();
+        storage
+            .save(&Unsaved {
+                state: None,
+                keep: 0,
+                entries: vec![entry(1, 1), entry(1, 2)],
+            })
+            .unwrap();
+        drop(storage);
+        let path = dir.path().join("shard0.log");
+        let len = std::fs::metadata(&path).unwrap().len();
+        OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(len - 5)
+            .unwrap();
+
+        let (mut storage, _, log) = GroupStorage::open::<u32>(dir.path(), 0).unwrap();
+        assert_eq!(log, [entry(1, 1)]);
+        storage
+            .save(&Unsaved {
+                state: None,
+                keep: 1,
+                entries: vec![entry(1, 2)],
+            })
+            .unwrap();
+        drop(storage);
+        let (_, _, log) = GroupStorage::open::<u32>(dir.path(), 0).unwrap();
+        assert_eq!(log, [entry(1, 1), entry(1, 2)]);
+    }
+
+    #[test]
+    fn test_snapshot_drops_covered_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut storage, _, _) = GroupStorage::open::<u32>(dir.path(), 1).unwrap();
+        storage
+            .save(&Unsaved {
+                state: None,
+                keep: 0,
+                entries: (1..=4).map(|i| entry(1, i)).collect(),
+            })
+            .unwrap();
+        let state = HardState {
+            term: 1,
+            commit: 4,
+            snapshot_index: 2,
+            snapshot_term: 1,
+            ..Default::default()
+        };
+        storage
+            .save(&Unsaved {
+                state: Some(state),
+                keep: 4,
+                entries: vec![entry(1, 5)],
+            })
+            .unwrap();
+        drop(storage);
+        let path = dir.path().join("shard1.log");
+        let lines = std::fs::read_to_string(&path).unwrap().lines().count();
+        assert_eq!(lines, 3);
+
+        let (_, reopened, log) = GroupStorage::open::<u32>(dir.path(), 1).unwrap();
+        assert_eq!(reopened, state);
+        assert_eq!(log, [entry(1, 3), entry(1, 4), entry(1, 5)]);
+
+        // A crash after the state was replaced left the covered entries
+        let later = HardState {
+            snapshot_index: 4,
+            ..state
+        };
+        std::fs::write(
+            dir.path().join("shard1.state"),
+            serde_json::to_vec(&later).unwrap(),
+        )
+        .unwrap();
+        let (_, _, log) = GroupStorage::open::<u32>(dir.path(), 1).unwrap();
+        assert_eq!(log, [entry(1, 5)]);
+        let lines = std::fs::read_to_string(&path).unwrap().lines().count();
+        assert_eq!(lines, 1);
+    }
+
+    #[test]
+    fn test_interrupted_install_starts_over() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut storage, _, _) = GroupStorage::open::<u32>(dir.path(), 2).unwrap();
+        let state = HardState {
+            term: 3,
+            voted_for: Some(2),
+            commit: 2,
+            ..Default::default()
+        };
+        storage
+            .save(&Unsaved {
+                state: Some(state),
+                keep: 0,
+                entries: vec![entry(1, 1), entry(3, 2)],
+            })
+            .unwrap();
+        storage.installing().unwrap();
+        drop(storage);
+
+        let (mut storage, reopened, log) = GroupStorage::open::<u32>(dir.path(), 2).unwrap();
+        assert!(storage.install_interrupted());
+        assert_eq!((reopened.term, reopened.voted_for), (3, Some(2)));
+        assert_eq!(reopened.commit, 0);
+        assert!(log.is_empty());
+        storage.installed().unwrap();
+        drop(storage);
+
+        let (storage, _, log) = GroupStorage::open::<u32>(dir.path(), 2).unwrap();
+        assert!(!storage.install_interrupted());
+        assert!(log.is_empty());
+    }
+}
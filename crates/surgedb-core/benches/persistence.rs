@@ -7,10 +7,10 @@ use rand::{rngs::StdRng, Rng, SeedableRng};
 #[cfg(feature = "persistence")]
 use serde_json::{json, Value};
 #[cfg(feature = "persistence")]
-use surgedb_core::{DistanceMetric, PersistentConfig, PersistentVectorDb};
-#[cfg(feature = "persistence")]
 use surgedb_core::types::VectorId;
 #[cfg(feature = "persistence")]
+use surgedb_core::{DistanceMetric, GroupCommit, PersistentConfig, PersistentVectorDb};
+#[cfg(feature = "persistence")]
 use tempfile::tempdir;
 
 #[cfg(feature = "persistence")]
@@ -47,21 +47,30 @@ fn bench_open_insert_checkpoint(c: &mut Criterion) {
 
     for dim in [128_usize, 384].iter() {
         for size in bench_sizes() {
-            for sync_writes in [false, true].iter() {
+            // No syncs, one fsync per write, and group commit
+            let modes = [
+                ("nosync", false, None),
+                ("sync", true, None),
+                ("group", true, Some(GroupCommit::default())),
+            ];
+            for (mode, sync_writes, group_commit) in modes {
                 let items = generate_vectors(size, *dim, 42);
                 group.bench_with_input(
-                    BenchmarkId::new(format!("dim{dim}_sync{sync_writes}"), size),
+                    BenchmarkId::new(format!("dim{dim}_{mode}"), size),
                     &size,
                     |b, _| {
                         b.iter_batched(
                             || {
                                 let dir = tempdir().expect("tempdir");
-                                let mut config = PersistentConfig::default();
-                                config.dimensions = *dim;
-                                config.distance_metric = DistanceMetric::Cosine;
-                                config.sync_writes = *sync_writes;
-                                let db = PersistentVectorDb::open(dir.path(), config)
-                                    .expect("open db");
+                                let config = PersistentConfig {
+                                    dimensions: *dim,
+                                    distance_metric: DistanceMetric::Cosine,
+                                    sync_writes,
+                                    group_commit,
+                                    ..Default::default()
+                                };
+                                let db =
+                                    PersistentVectorDb::open(dir.path(), config).expect("open db");
                                 (dir, db)
                             },
                             |(_dir, mut db)| {
@@ -89,9 +98,11 @@ fn bench_search(c: &mut Criterion) {
     for dim in [128_usize, 384].iter() {
         for size in bench_sizes() {
             let dir = tempdir().expect("tempdir");
-            let mut config = PersistentConfig::default();
-            config.dimensions = *dim;
-            config.distance_metric = DistanceMetric::Cosine;
+            let config = PersistentConfig {
+                dimensions: *dim,
+                distance_metric: DistanceMetric::Cosine,
+                ..Default::default()
+            };
 
             let mut db = PersistentVectorDb::open(dir.path(), config).expect("open db");
             let items = generate_vectors(size, *dim, 77);
@@ -3,8 +3,8 @@
 use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
 use rand::{rngs::StdRng, Rng, SeedableRng};
 use serde_json::{json, Value};
-use surgedb_core::{QuantizationType, QuantizedConfig, QuantizedVectorDb, DistanceMetric};
 use surgedb_core::types::VectorId;
+use surgedb_core::{DistanceMetric, QuantizationType, QuantizedConfig, QuantizedVectorDb};
 
 fn bench_sizes() -> Vec<usize> {
     let mut sizes = vec![2_000, 10_000];
@@ -32,7 +32,12 @@ fn generate_vectors(
         .collect()
 }
 
-fn build_db(dim: usize, count: usize, seed: u64, quantization: QuantizationType) -> QuantizedVectorDb {
+fn build_db(
+    dim: usize,
+    count: usize,
+    seed: u64,
+    quantization: QuantizationType,
+) -> QuantizedVectorDb {
     let config = QuantizedConfig {
         dimensions: dim,
         distance_metric: DistanceMetric::Cosine,
@@ -57,12 +62,15 @@ fn bench_upsert_batch(c: &mut Criterion) {
                     &size,
                     |b, _| {
                         b.iter_batched(
-                            || QuantizedVectorDb::new(QuantizedConfig {
-                                dimensions: *dim,
-                                distance_metric: DistanceMetric::Cosine,
-                                quantization: *quant,
-                                ..Default::default()
-                            }).expect("create quantized db"),
+                            || {
+                                QuantizedVectorDb::new(QuantizedConfig {
+                                    dimensions: *dim,
+                                    distance_metric: DistanceMetric::Cosine,
+                                    quantization: *quant,
+                                    ..Default::default()
+                                })
+                                .expect("create quantized db")
+                            },
                             |mut db| {
                                 db.upsert_batch(items.clone()).expect("upsert batch");
                                 black_box(db.len());
@@ -93,9 +101,7 @@ fn bench_search(c: &mut Criterion) {
                     &size,
                     |b, _| {
                         b.iter(|| {
-                            let results = db
-                                .search(black_box(&query), 10, None)
-                                .expect("search");
+                            let results = db.search(black_box(&query), 10, None).expect("search");
                             black_box(results.len());
                         });
                     },
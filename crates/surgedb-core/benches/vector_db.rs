@@ -4,8 +4,8 @@ use criterion::{black_box, criterion_group, criterion_main, BatchSize, Benchmark
 use rand::{rngs::StdRng, Rng, SeedableRng};
 use serde_json::{json, Value};
 use surgedb_core::filter::Filter;
-use surgedb_core::{Config, DistanceMetric, VectorDb};
 use surgedb_core::types::VectorId;
+use surgedb_core::{Config, DistanceMetric, VectorDb};
 
 fn bench_sizes() -> Vec<usize> {
     let mut sizes = vec![2_000, 10_000];
@@ -54,11 +54,14 @@ fn bench_insert_single(c: &mut Criterion) {
 
         group.bench_with_input(BenchmarkId::from_parameter(dim), dim, |b, _| {
             b.iter_batched(
-                || VectorDb::new(Config {
-                    dimensions: *dim,
-                    distance_metric: DistanceMetric::Cosine,
-                    ..Default::default()
-                }).expect("create db"),
+                || {
+                    VectorDb::new(Config {
+                        dimensions: *dim,
+                        distance_metric: DistanceMetric::Cosine,
+                        ..Default::default()
+                    })
+                    .expect("create db")
+                },
                 |mut db| {
                     let (id, vec, meta) = vector.clone();
                     db.insert(id, &vec, meta).expect("insert");
@@ -83,11 +86,14 @@ fn bench_upsert_batch(c: &mut Criterion) {
                 &size,
                 |b, _| {
                     b.iter_batched(
-                        || VectorDb::new(Config {
-                            dimensions: *dim,
-                            distance_metric: DistanceMetric::Cosine,
-                            ..Default::default()
-                        }).expect("create db"),
+                        || {
+                            VectorDb::new(Config {
+                                dimensions: *dim,
+                                distance_metric: DistanceMetric::Cosine,
+                                ..Default::default()
+                            })
+                            .expect("create db")
+                        },
                         |mut db| {
                             db.upsert_batch(items.clone()).expect("upsert batch");
                             black_box(db.len());
@@ -116,9 +122,7 @@ fn bench_search(c: &mut Criterion) {
                 &size,
                 |b, _| {
                     b.iter(|| {
-                        let results = db
-                            .search(black_box(&query), 10, None)
-                            .expect("search");
+                        let results = db.search(black_box(&query), 10, None).expect("search");
                         black_box(results.len());
                     });
                 },
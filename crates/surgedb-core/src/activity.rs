@@ -0,0 +1,156 @@
+//! Recent activity of a collection, minute by minute
+//!
+//! Alongside its latency histograms, every [`Collection`](crate::db::Collection)
+//! counts its operations in a ring of one-minute buckets covering the last
+//! [`ACTIVITY_MINUTES`] minutes. A dashboard can draw query rate, latency and
+//! insert rate sparklines from one call, without an external metrics store.
+//! Minutes are wall-clock minutes, so series of different collections line
+//! up. Like the histograms, the ring starts empty when a collection is opened
+//! and nothing is recorded on WASM.
+
+use crate::latency::Operation;
+use crate::sync::RwLock;
+use serde::Serialize;
+
+/// Minutes of activity kept per collection
+pub const ACTIVITY_MINUTES: usize = 60;
+
+/// Operations of a collection during one minute
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct ActivityMinute {
+    /// Start of the minute, in seconds since the Unix epoch
+    pub start: u64,
+    pub searches: u64,
+    /// Insert and upsert calls; a batch counts once
+    pub inserts: u64,
+    /// Records written by those calls
+    pub inserted_records: u64,
+    pub deletes: u64,
+    /// Mean search latency in microseconds; 0 without searches
+    pub search_mean_us: f64,
+    pub search_max_us: u64,
+    /// Mean insert latency in microseconds; 0 without inserts
+    pub insert_mean_us: f64,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Bucket {
+    /// Minutes since the Unix epoch
+    minute: u64,
+    searches: u64,
+    search_us: u64,
+    search_max_us: u64,
+    inserts: u64,
+    inserted_records: u64,
+    insert_us: u64,
+    deletes: u64,
+}
+
+pub(crate) struct ActivityRecorder {
+    buckets: RwLock<[Bucket; ACTIVITY_MINUTES]>,
+}
+
+impl Default for ActivityRecorder {
+    fn default() -> Self {
+        Self {
+            buckets: RwLock::new([Bucket::default(); ACTIVITY_MINUTES]),
+        }
+    }
+}
+
+impl ActivityRecorder {
+    /// Count one `op` of `records` records that took `micros`, at `now` in
+    /// seconds since the Unix epoch
+    pub(crate) fn record(&self, op: Operation, micros: u64, records: u64, now: u64) {
+        let minute = now / 60;
+        let mut buckets = self.buckets.write();
+        let bucket = &mut buckets[minute as usize % ACTIVITY_MINUTES];
+        if bucket.minute != minute {
+            *bucket = Bucket {
+                minute,
+                ..Bucket::default()
+            };
+        }
+        match op {
+            Operation::Search => {
+                bucket.searches += 1;
+                bucket.search_us = bucket.search_us.saturating_add(micros);
+                bucket.search_max_us = bucket.search_max_us.max(micros);
+            }
+            Operation::Insert => {
+                bucket.inserts += 1;
+                bucket.inserted_records += records;
+                bucket.insert_us = bucket.insert_us.saturating_add(micros);
+            }
+            Operation::Delete => bucket.deletes += 1,
+        }
+    }
+
+    /// The last [`ACTIVITY_MINUTES`] minutes up to the one holding `now`,
+    /// oldest first, with idle minutes included
+    pub(crate) fn minutes(&self, now: u64) -> Vec<ActivityMinute> {
+        let current = now / 60;
+        let buckets = self.buckets.read();
+        (0..ACTIVITY_MINUTES as u64)
+            .rev()
+            .filter_map(|ago| current.checked_sub(ago))
+            .map(|minute| {
+                let mut point = ActivityMinute {
+                    start: minute * 60,
+                    ..ActivityMinute::default()
+                };
+                let bucket = &buckets[minute as usize % ACTIVITY_MINUTES];
+                if bucket.minute != minute {
+                    return point;
+                }
+                let mean = |total: u64, count: u64| match count {
+                    0 => 0.0,
+                    count => total as f64 / count as f64,
+                };
+                point.searches = bucket.searches;
+                point.inserts = bucket.inserts;
+                point.inserted_records = bucket.inserted_records;
+                point.deletes = bucket.deletes;
+                point.search_mean_us = mean(bucket.search_us, bucket.searches);
+                point.search_max_us = bucket.search_max_us;
+                point.insert_mean_us = mean(bucket.insert_us, bucket.inserts);
+                point
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_minutes_roll_over() {
+        let recorder = ActivityRecorder::default();
+        let start = 1_000 * 60;
+        recorder.record(Operation::Search, 100, 1, start);
+        recorder.record(Operation::Search, 300, 1, start + 59);
+        recorder.record(Operation::Insert, 50, 20, start + 60);
+        recorder.record(Operation::Delete, 10, 1, start + 61);
+
+        let minutes = recorder.minutes(start + 90);
+        assert_eq!(minutes.len(), ACTIVITY_MINUTES);
+        let last = minutes[ACTIVITY_MINUTES - 1];
+        assert_eq!(last.start, start + 60);
+        assert_eq!((last.inserts, last.inserted_records), (1, 20));
+        assert_eq!((last.deletes, last.searches), (1, 0));
+        assert_eq!(last.insert_mean_us, 50.0);
+        let previous = minutes[ACTIVITY_MINUTES - 2];
+        assert_eq!((previous.searches, previous.search_max_us), (2, 300));
+        assert_eq!(previous.search_mean_us, 200.0);
+
+        // An hour on, the old buckets are reused, not added to
+        let later = start + 60 * (ACTIVITY_MINUTES as u64 + 1);
+        recorder.record(Operation::Search, 10, 1, later);
+        let minutes = recorder.minutes(later);
+        assert_eq!(minutes[ACTIVITY_MINUTES - 1].searches, 1);
+        assert!(minutes[..ACTIVITY_MINUTES - 1]
+            .iter()
+            .all(|m| m.searches + m.inserts + m.deletes == 0));
+    }
+}
@@ -0,0 +1,431 @@
+//! Pluggable approximate nearest neighbor indexes
+//!
+//! A collection keeps vectors, IDs and metadata in its storage and gives its
+//! index only internal IDs and vectors. The index measures distances through
+//! the storage, which also tells it which records are deleted and which match
+//! a filter. [`AnnIndex`] is that contract. HNSW is the default
+//! implementation, [`FlatIndex`] an exact brute-force scan and
+//! [`SegmentedIndex`] HNSW over segments built in the background; `Config::index`
+//! picks one per collection, and embedded users can bring their own with
+//! [`VectorDb::with_index`](crate::VectorDb::with_index).
+//!
+//! Partition graphs and named vector spaces are always HNSW.
+
+use crate::distance::DistanceMetric;
+use crate::error::Result;
+use crate::filter::Filter;
+use crate::hnsw::{HnswConfig, HnswIndex};
+use crate::segment::SegmentedIndex;
+use crate::storage::VectorStorageTrait;
+use crate::sync::{MaybeSend, MaybeSync, RwLock};
+use crate::types::{InternalId, SearchUsage};
+use roaring::RoaringBitmap;
+use serde::{Deserialize, Serialize};
+
+/// Storage an index reads, shareable with the threads that build it
+pub trait IndexStorage: VectorStorageTrait + MaybeSync {}
+
+impl<T: VectorStorageTrait + MaybeSync + ?Sized> IndexStorage for T {}
+
+/// An approximate nearest neighbor index over a collection's internal IDs
+pub trait AnnIndex: MaybeSend + MaybeSync {
+    /// Add the stored vector `internal_id`
+    fn insert(
+        &self,
+        internal_id: InternalId,
+        vector: &[f32],
+        storage: &dyn IndexStorage,
+    ) -> Result<()>;
+
+    /// Add many stored vectors at once
+    fn insert_batch(
+        &self,
+        items: &[(InternalId, &[f32])],
+        storage: &dyn IndexStorage,
+    ) -> Result<()> {
+        for &(internal_id, vector) in items {
+            self.insert(internal_id, vector, storage)?;
+        }
+        Ok(())
+    }
+
+    /// Forget a deleted or overwritten vector
+    ///
+    /// Indexes may instead keep it and skip it in searches, since the
+    /// storage reports it as deleted until the collection is compacted.
+    fn remove(&self, internal_id: InternalId);
+
+    /// The `k` nearest live vectors to `query` that match `filter`, closest
+    /// first, with `ef_search` bounding the work if the index has such a knob
+    fn search(
+        &self,
+        query: &[f32],
+        k: usize,
+        ef_search: Option<usize>,
+        storage: &dyn IndexStorage,
+        filter: Option<&Filter>,
+        usage: &mut SearchUsage,
+    ) -> Result<Vec<(InternalId, f32)>>;
+
+    /// [`search`](Self::search) leaving out vectors further than
+    /// `max_distance` from `query`
+    ///
+    /// Indexes that can stop searching at the cutoff should; by default the
+    /// results of a full search are cut.
+    #[allow(clippy::too_many_arguments)]
+    fn search_within(
+        &self,
+        query: &[f32],
+        k: usize,
+        ef_search: Option<usize>,
+        max_distance: Option<f32>,
+        storage: &dyn IndexStorage,
+        filter: Option<&Filter>,
+        usage: &mut SearchUsage,
+    ) -> Result<Vec<(InternalId, f32)>> {
+        let results = self.search(query, k, ef_search, storage, filter, usage)?;
+        Ok(crate::within(results, max_distance))
+    }
+
+    /// Number of entries, including ones kept after [`remove`](Self::remove)
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Approximate bytes held by the index, beyond the stored vectors
+    fn memory_usage(&self) -> usize;
+
+    /// The index contents as bytes, for [`deserialize`](Self::deserialize)
+    fn serialize(&self) -> Result<Vec<u8>>;
+
+    /// Replace the index contents with ones from [`serialize`](Self::serialize)
+    fn deserialize(&self, bytes: &[u8]) -> Result<()>;
+
+    /// An empty index of the same kind and settings, which compaction
+    /// rebuilds into
+    fn fresh(&self) -> Box<dyn AnnIndex>;
+
+    /// The index as HNSW, whose graph snapshots store and exports show
+    fn as_hnsw(&self) -> Option<&HnswIndex> {
+        None
+    }
+}
+
+/// Built-in index a collection uses
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IndexKind {
+    /// Hierarchical navigable small world graph
+    #[default]
+    #[serde(alias = "HNSW")]
+    Hnsw,
+    /// Exact scan over every vector: slower searches, perfect recall and no
+    /// graph to build, for small collections
+    Flat,
+    /// HNSW graphs over sealed segments, built and merged in the background,
+    /// so writes never wait on graph construction; see [`crate::segment`]
+    Segmented,
+}
+
+impl IndexKind {
+    /// An empty index of this kind
+    pub fn build(self, hnsw: &HnswConfig, metric: DistanceMetric) -> Box<dyn AnnIndex> {
+        match self {
+            IndexKind::Hnsw => Box::new(HnswIndex::new(hnsw.clone(), metric)),
+            IndexKind::Flat => Box::new(FlatIndex::new(metric)),
+            IndexKind::Segmented => Box::new(SegmentedIndex::new(hnsw.clone(), metric)),
+        }
+    }
+}
+
+impl AnnIndex for HnswIndex {
+    fn insert(
+        &self,
+        internal_id: InternalId,
+        vector: &[f32],
+        storage: &dyn IndexStorage,
+    ) -> Result<()> {
+        HnswIndex::insert(self, internal_id, vector, &storage)
+    }
+
+    fn insert_batch(
+        &self,
+        items: &[(InternalId, &[f32])],
+        storage: &dyn IndexStorage,
+    ) -> Result<()> {
+        HnswIndex::insert_batch(self, items, &storage)
+    }
+
+    /// Deleted nodes stay in the graph to route through and are skipped in
+    /// results, until compaction rebuilds it
+    fn remove(&self, _internal_id: InternalId) {}
+
+    fn search(
+        &self,
+        query: &[f32],
+        k: usize,
+        ef_search: Option<usize>,
+        storage: &dyn IndexStorage,
+        filter: Option<&Filter>,
+        usage: &mut SearchUsage,
+    ) -> Result<Vec<(InternalId, f32)>> {
+        self.search_with_ef(query, k, ef_search, &storage, filter, usage)
+    }
+
+    fn search_within(
+        &self,
+        query: &[f32],
+        k: usize,
+        ef_search: Option<usize>,
+        max_distance: Option<f32>,
+        storage: &dyn IndexStorage,
+        filter: Option<&Filter>,
+        usage: &mut SearchUsage,
+    ) -> Result<Vec<(InternalId, f32)>> {
+        HnswIndex::search_within(
+            self,
+            query,
+            k,
+            ef_search,
+            max_distance,
+            &storage,
+            filter,
+            usage,
+        )
+    }
+
+    fn len(&self) -> usize {
+        HnswIndex::len(self)
+    }
+
+    fn memory_usage(&self) -> usize {
+        HnswIndex::memory_usage(self)
+    }
+
+    fn serialize(&self) -> Result<Vec<u8>> {
+        Ok(bincode::serialize(&self.get_state())?)
+    }
+
+    fn deserialize(&self, bytes: &[u8]) -> Result<()> {
+        self.load_state(bincode::deserialize(bytes)?);
+        Ok(())
+    }
+
+    fn fresh(&self) -> Box<dyn AnnIndex> {
+        Box::new(HnswIndex::new(
+            self.config().clone(),
+            self.distance_metric(),
+        ))
+    }
+
+    fn as_hnsw(&self) -> Option<&HnswIndex> {
+        Some(self)
+    }
+}
+
+/// Exact nearest neighbors by measuring the query against every vector
+pub struct FlatIndex {
+    metric: DistanceMetric,
+    members: RwLock<RoaringBitmap>,
+}
+
+impl FlatIndex {
+    pub fn new(metric: DistanceMetric) -> Self {
+        Self {
+            metric,
+            members: RwLock::new(RoaringBitmap::new()),
+        }
+    }
+}
+
+impl AnnIndex for FlatIndex {
+    fn insert(
+        &self,
+        internal_id: InternalId,
+        _vector: &[f32],
+        _storage: &dyn IndexStorage,
+    ) -> Result<()> {
+        self.members.write().insert(internal_id.as_u32());
+        Ok(())
+    }
+
+    fn remove(&self, internal_id: InternalId) {
+        self.members.write().remove(internal_id.as_u32());
+    }
+
+    fn search(
+        &self,
+        query: &[f32],
+        k: usize,
+        _ef_search: Option<usize>,
+        storage: &dyn IndexStorage,
+        filter: Option<&Filter>,
+        usage: &mut SearchUsage,
+    ) -> Result<Vec<(InternalId, f32)>> {
+        let members = self.members.read();
+        Ok(exact_search(
+            members.iter().map(InternalId),
+            query,
+            k,
+            self.metric,
+            storage,
+            filter,
+            usage,
+        ))
+    }
+
+    fn len(&self) -> usize {
+        self.members.read().len() as usize
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.members.read().serialized_size()
+    }
+
+    fn serialize(&self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        self.members.read().serialize_into(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn deserialize(&self, bytes: &[u8]) -> Result<()> {
+        *self.members.write() = RoaringBitmap::deserialize_from(bytes)?;
+        Ok(())
+    }
+
+    fn fresh(&self) -> Box<dyn AnnIndex> {
+        Box::new(FlatIndex::new(self.metric))
+    }
+}
+
+/// The `k` of `members` nearest `query`, measured one by one
+pub(crate) fn exact_search(
+    members: impl Iterator<Item = InternalId>,
+    query: &[f32],
+    k: usize,
+    metric: DistanceMetric,
+    storage: &dyn IndexStorage,
+    filter: Option<&Filter>,
+    usage: &mut SearchUsage,
+) -> Vec<(InternalId, f32)> {
+    if k == 0 {
+        return Vec::new();
+    }
+    let bitmap = filter.and_then(|f| storage.filter_bitmap(f));
+    let mut hits: Vec<(InternalId, f32)> = members
+        .filter(|&id| !storage.is_deleted(id))
+        .filter(|&id| match (&bitmap, filter) {
+            (Some(bitmap), _) => bitmap.contains(id.as_u32()),
+            (None, Some(f)) => storage.get_metadata(id).is_some_and(|m| f.matches(&m)),
+            (None, None) => true,
+        })
+        .filter_map(|id| {
+            usage.vectors_scanned += 1;
+            Some((id, storage.distance(id, query, metric)?))
+        })
+        .collect();
+    nearest(&mut hits, k);
+    hits
+}
+
+/// Keep the `k` nearest of `hits`, closest first
+pub(crate) fn nearest(hits: &mut Vec<(InternalId, f32)>, k: usize) {
+    let by_distance = |a: &(InternalId, f32), b: &(InternalId, f32)| {
+        a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal)
+    };
+    if k == 0 {
+        hits.clear();
+        return;
+    }
+    if hits.len() > k {
+        hits.select_nth_unstable_by(k - 1, by_distance);
+        hits.truncate(k);
+    }
+    hits.sort_by(by_distance);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::VectorStorage;
+    use crate::types::VectorId;
+    use serde_json::json;
+
+    #[test]
+    fn test_flat_index_is_exact() {
+        let storage = VectorStorage::new(2);
+        let index: Box<dyn AnnIndex> =
+            IndexKind::Flat.build(&HnswConfig::default(), DistanceMetric::Euclidean);
+        let mut ids = Vec::new();
+        for i in 0..50 {
+            let vector = [i as f32, 0.0];
+            let internal_id = storage
+                .insert(
+                    VectorId::from(format!("v{i}")),
+                    &vector,
+                    Some(json!({ "even": i % 2 == 0 })),
+                )
+                .unwrap();
+            index.insert(internal_id, &vector, &storage).unwrap();
+            ids.push(internal_id);
+        }
+
+        let mut usage = SearchUsage::default();
+        let hits = index
+            .search(&[10.2, 0.0], 3, None, &storage, None, &mut usage)
+            .unwrap();
+        let found: Vec<_> = hits.iter().map(|h| h.0).collect();
+        assert_eq!(found, vec![ids[10], ids[11], ids[9]]);
+        assert_eq!(usage.vectors_scanned, 50);
+
+        let odd = Filter::Exact("even".to_string(), json!(false));
+        let hits = index
+            .search(&[10.2, 0.0], 2, None, &storage, Some(&odd), &mut usage)
+            .unwrap();
+        let found: Vec<_> = hits.iter().map(|h| h.0).collect();
+        assert_eq!(found, vec![ids[11], ids[9]]);
+
+        // Removed and deleted vectors are never returned
+        index.remove(ids[10]);
+        storage.delete(&VectorId::from("v11")).unwrap();
+        let hits = index
+            .search(&[10.2, 0.0], 1, None, &storage, None, &mut usage)
+            .unwrap();
+        assert_eq!(hits[0].0, ids[9]);
+        assert_eq!(index.len(), 49);
+
+        let restored = index.fresh();
+        restored.deserialize(&index.serialize().unwrap()).unwrap();
+        assert_eq!(restored.len(), 49);
+    }
+
+    #[test]
+    fn test_hnsw_round_trips_through_the_trait() {
+        let storage = VectorStorage::new(2);
+        let index = IndexKind::Hnsw.build(&HnswConfig::default(), DistanceMetric::Euclidean);
+        let items: Vec<(InternalId, Vec<f32>)> = (0..30)
+            .map(|i| {
+                let vector = vec![i as f32, 1.0];
+                let id = storage
+                    .insert(VectorId::from(format!("v{i}")), &vector, None)
+                    .unwrap();
+                (id, vector)
+            })
+            .collect();
+        let batch: Vec<(InternalId, &[f32])> =
+            items.iter().map(|(id, v)| (*id, v.as_slice())).collect();
+        index.insert(batch[0].0, batch[0].1, &storage).unwrap();
+        index.insert_batch(&batch[1..], &storage).unwrap();
+        assert!(index.as_hnsw().is_some());
+
+        let restored = index.fresh();
+        assert!(restored.is_empty());
+        restored.deserialize(&index.serialize().unwrap()).unwrap();
+        let mut usage = SearchUsage::default();
+        let hits = restored
+            .search(&[7.1, 1.0], 1, None, &storage, None, &mut usage)
+            .unwrap();
+        assert_eq!(hits[0].0, items[7].0);
+    }
+}
@@ -6,7 +6,7 @@
 use crate::types::InternalId;
 use roaring::RoaringBitmap;
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 /// Inverted index for metadata fields
@@ -14,6 +14,8 @@ use std::sync::Arc;
 pub struct BitmapIndex {
     /// field -> value_str -> bitmap
     index: HashMap<String, HashMap<String, Arc<RoaringBitmap>>>,
+    /// Fields being rebuilt, which filters don't use until they are complete
+    rebuilding: HashSet<String>,
 }
 
 impl BitmapIndex {
@@ -21,14 +23,48 @@ impl BitmapIndex {
         Self::default()
     }
 
+    /// Approximate bytes used by the index (keys plus compressed bitmaps)
+    pub fn memory_usage(&self) -> usize {
+        self.index
+            .iter()
+            .map(|(field, values)| {
+                field.capacity()
+                    + values
+                        .iter()
+                        .map(|(value, bitmap)| value.capacity() + bitmap.serialized_size())
+                        .sum::<usize>()
+            })
+            .sum()
+    }
+
     /// Index a document's metadata
     pub fn index(&mut self, internal_id: InternalId, metadata: &Value) {
         let id = internal_id.as_u32();
-        self.index_recursive(id, metadata, "");
+        self.index_recursive(id, metadata, "", None);
+    }
+
+    /// Index only the values of `field` in a document's metadata
+    pub fn index_field(&mut self, internal_id: InternalId, metadata: &Value, field: &str) {
+        self.index_recursive(internal_id.as_u32(), metadata, "", Some(field));
+    }
+
+    /// Drop the index of `field` and answer filters on it by scanning until
+    /// [`finish_rebuild`](Self::finish_rebuild); returns false if it is
+    /// already being rebuilt
+    pub fn begin_rebuild(&mut self, field: &str) -> bool {
+        if !self.rebuilding.insert(field.to_string()) {
+            return false;
+        }
+        self.index.remove(field);
+        true
     }
 
-    /// Recursively index JSON fields
-    fn index_recursive(&mut self, id: u32, value: &Value, prefix: &str) {
+    pub fn finish_rebuild(&mut self, field: &str) {
+        self.rebuilding.remove(field);
+    }
+
+    /// Recursively index JSON fields, or only `only`
+    fn index_recursive(&mut self, id: u32, value: &Value, prefix: &str, only: Option<&str>) {
         match value {
             Value::Object(map) => {
                 for (k, v) in map {
@@ -37,23 +73,20 @@ impl BitmapIndex {
                     } else {
                         format!("{}.{}", prefix, k)
                     };
-                    self.index_recursive(id, v, &key);
+                    self.index_recursive(id, v, &key, only);
                 }
             }
             Value::Array(arr) => {
                 // Index array elements with the same key (for "tags": ["a", "b"])
                 for v in arr {
-                    self.index_recursive(id, v, prefix);
+                    self.index_recursive(id, v, prefix, only);
                 }
             }
             primitive => {
                 // Index primitive value
-                if !prefix.is_empty() {
+                if !prefix.is_empty() && only.is_none_or(|field| field == prefix) {
                     let val_str = primitive.to_string();
-                    let field = self
-                        .index
-                        .entry(prefix.to_string())
-                        .or_insert_with(HashMap::new);
+                    let field = self.index.entry(prefix.to_string()).or_default();
                     let entry = field
                         .entry(val_str)
                         .or_insert_with(|| Arc::new(RoaringBitmap::new()));
@@ -112,10 +145,14 @@ impl BitmapIndex {
     }
 
     /// Execute a filter query and return matching internal IDs
+    ///
+    /// `None` if any clause can't be answered from the index, in which case
+    /// the filter has to be evaluated per vector.
     pub fn filter(&self, filter: &crate::filter::Filter) -> Option<Arc<RoaringBitmap>> {
         use crate::filter::Filter;
 
         match filter {
+            Filter::Exact(key, _) | Filter::OneOf(key, _) if self.rebuilding.contains(key) => None,
             Filter::Exact(key, value) => {
                 if let Some(values) = self.index.get(key) {
                     values.get(&value.to_string()).cloned()
@@ -139,16 +176,15 @@ impl BitmapIndex {
             Filter::And(filters) => {
                 let mut result: Option<RoaringBitmap> = None;
                 for f in filters {
-                    if let Some(bitmap) = self.filter(f) {
-                        match result {
-                            None => result = Some(bitmap.as_ref().clone()),
-                            Some(ref mut r) => *r &= bitmap.as_ref(),
-                        }
+                    let bitmap = self.filter(f)?;
+                    match result {
+                        None => result = Some(bitmap.as_ref().clone()),
+                        Some(ref mut r) => *r &= bitmap.as_ref(),
+                    }
 
-                        // Optimization: if empty, stop
-                        if result.as_ref().map(|r| r.is_empty()).unwrap_or(false) {
-                            return Some(Arc::new(RoaringBitmap::new()));
-                        }
+                    // Optimization: if empty, stop
+                    if result.as_ref().map(|r| r.is_empty()).unwrap_or(false) {
+                        return Some(Arc::new(RoaringBitmap::new()));
                     }
                 }
                 result.map(Arc::new)
@@ -156,9 +192,7 @@ impl BitmapIndex {
             Filter::Or(filters) => {
                 let mut result = RoaringBitmap::new();
                 for f in filters {
-                    if let Some(bitmap) = self.filter(f) {
-                        result |= bitmap.as_ref();
-                    }
+                    result |= self.filter(f)?.as_ref();
                 }
                 Some(Arc::new(result))
             }
@@ -168,7 +202,7 @@ impl BitmapIndex {
                 // For now, return None to fallback to scan-based filtering for NOT.
                 None
             }
-            Filter::Range { .. } | Filter::GeoRadius { .. } => {
+            Filter::Range { .. } | Filter::GeoRadius { .. } | Filter::Expr(_) => {
                 // Range queries on bitmaps require range-encoded bitmaps or B-trees.
                 // Fallback to scan for now.
                 None
@@ -182,6 +216,37 @@ mod tests {
     use super::*;
     use serde_json::json;
 
+    #[test]
+    fn test_rebuild_field() {
+        let mut index = BitmapIndex::new();
+        let docs = [
+            json!({ "tag": "A", "val": 1 }),
+            json!({ "tag": "B", "val": 1 }),
+        ];
+        for (i, doc) in docs.iter().enumerate() {
+            index.index(InternalId::from(i), doc);
+        }
+        let tag_a = crate::filter::Filter::Exact("tag".to_string(), json!("A"));
+        let val_1 = crate::filter::Filter::Exact("val".to_string(), json!(1));
+
+        assert!(index.begin_rebuild("tag"));
+        assert!(!index.begin_rebuild("tag"));
+        // Filters on the field fall back to scanning; other fields still work
+        assert!(index.filter(&tag_a).is_none());
+        assert_eq!(index.filter(&val_1).unwrap().len(), 2);
+
+        for (i, doc) in docs.iter().enumerate() {
+            index.index_field(InternalId::from(i), doc, "tag");
+        }
+        index.finish_rebuild("tag");
+        assert_eq!(
+            index.filter(&tag_a).unwrap().iter().collect::<Vec<_>>(),
+            [0]
+        );
+        // Other fields were left as they were
+        assert_eq!(index.filter(&val_1).unwrap().len(), 2);
+    }
+
     #[test]
     fn test_indexing_and_filtering() {
         let mut index = BitmapIndex::new();
@@ -212,5 +277,24 @@ mod tests {
         let result = index.filter(&filter).unwrap();
         assert!(result.contains(3));
         assert_eq!(result.len(), 1);
+
+        // Clauses the index can't answer leave the whole filter to per-vector checks
+        let range = crate::filter::Filter::Range {
+            field: "val".to_string(),
+            gt: Some(15.0),
+            gte: None,
+            lt: None,
+            lte: None,
+        };
+        let filter = crate::filter::Filter::And(vec![
+            crate::filter::Filter::Exact("tag".to_string(), json!("A")),
+            range.clone(),
+        ]);
+        assert!(index.filter(&filter).is_none());
+        let filter = crate::filter::Filter::Or(vec![
+            crate::filter::Filter::Exact("tag".to_string(), json!("B")),
+            range,
+        ]);
+        assert!(index.filter(&filter).is_none());
     }
 }
@@ -1,20 +1,62 @@
+use crate::activity::ActivityMinute;
+use crate::distance_to::{DistanceBounds, MAX_DISTANCE_TO_CANDIDATES};
+use crate::group::{GroupBy, SearchGroup, MAX_GROUP_CANDIDATES};
+use crate::latency::{LatencyRecorder, Operation, OperationLatencies};
+use crate::naming::{is_reserved, validate_name, NameCase};
+use crate::negatives::{HardNegativeQuery, HardNegatives, MinedNegatives, MAX_NEGATIVE_CANDIDATES};
+use crate::recommend::{self, Recommend, RecommendStrategy};
+use crate::recovery::{IntegrityReport, RecoveryMode, RecoveryProgress, RecoveryStatus};
+use crate::scan;
+use crate::summary::{self, CollectionSummary};
 use crate::sync::RwLock;
-use crate::types::VectorId;
+use crate::transform::VectorTransform;
+use crate::types::{
+    CompactionReport, DtypeConversion, FilterRecall, FilterStrategy, GarbageStats, GraphRepair,
+    ListCursor, ListPage, MemoryBreakdown, NormFilter, PayloadSize, SearchHit, SearchParams,
+    SearchUsage, VectorDtype, VectorId,
+};
+use crate::versions::Versions;
 use crate::{
-    Config, Error, QuantizationType, QuantizedConfig, QuantizedVectorDb, Result, VectorDb,
+    CachedFilterInfo, Config, DistanceMetric, Error, Fusion, GraphExport, GraphStats, HybridHit,
+    IdType, IndexKind, NamedVectors, QuantizationType, QuantizedConfig, QuantizedVectorDb, Result,
+    Scan, ScrollPage, SparseVector, VectorDb,
 };
-use serde::Serialize;
+use rand::seq::SliceRandom;
+#[cfg(all(feature = "persistence", feature = "parallel"))]
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
-use tracing::{debug, info};
+use tracing::{debug, error, info};
 
 #[derive(Debug, Clone, Serialize)]
 pub struct CollectionStats {
     pub vector_count: usize,
+    /// Deleted or overwritten vectors whose slots have not been reclaimed
+    pub deleted_count: usize,
     pub memory_usage_bytes: usize,
     pub quantization: String,
     pub dimensions: usize,
+    pub id_type: IdType,
+    /// In-memory usage by component (vectors, graph, IDs, metadata)
+    pub memory_breakdown: MemoryBreakdown,
+    /// Space held by deleted or overwritten records
+    pub garbage: GarbageStats,
+    /// Latency of inserts, searches and deletes since the collection was opened
+    pub latency: OperationLatencies,
+    /// Records with the largest metadata, largest first
+    pub largest_payloads: Vec<PayloadSize>,
+    /// Sequence number of the last write, as [`Collection::write_seq`]
+    pub write_seq: u64,
+    /// ID of the write log `write_seq` counts in; only on-disk collections
+    /// keep one, and their sequence survives restarts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_id: Option<u64>,
+    /// Records refused by the norm filter since the collection was opened
+    pub norm_rejections: u64,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -24,142 +66,1907 @@ pub struct DatabaseStats {
     pub total_memory_bytes: usize,
 }
 
-/// Enum representing either a standard, quantized, or persistent collection
-pub enum Collection {
+/// Changes to an existing collection, applied by [`Database::update_collection`]
+///
+/// Fields left `None` are kept as they are.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CollectionUpdate {
+    /// New name; aliases pointing to the collection follow it
+    pub name: Option<String>,
+    /// Default candidate list size of searches
+    pub ef_search: Option<usize>,
+    /// Quantization to re-encode the collection with; on-disk collections only
+    pub quantization: Option<QuantizationType>,
+    /// Transform of query vectors, `Some(None)` to remove it; a transform
+    /// applied on insert can only be changed while the collection is empty
+    #[serde(default, deserialize_with = "present")]
+    pub transform: Option<Option<VectorTransform>>,
+    /// Norm filter of written vectors, `Some(None)` to remove it
+    #[serde(default, deserialize_with = "present")]
+    pub norm_filter: Option<Option<NormFilter>>,
+}
+
+/// A field that is present, even as `null`, as `Some`
+fn present<'de, D, T>(deserializer: D) -> std::result::Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+/// IDs and distances found by one query, with the work it took
+type IdResults = (Vec<(VectorId, f32)>, SearchUsage);
+
+/// A record's vector and metadata, with its version
+pub type VersionedRecord = (Vec<f32>, Option<Value>, u64);
+
+/// Records listed in a collection's stats by metadata size
+const LARGEST_PAYLOADS: usize = 5;
+
+/// Records upserted per batch of a Parquet import
+#[cfg(feature = "parquet")]
+const PARQUET_IMPORT_BATCH_SIZE: usize = 1024;
+
+/// Slots a metadata index rebuild goes through between releasing its locks
+const INDEX_REBUILD_CHUNK: usize = 4096;
+
+/// Storage behind a collection: standard, quantized, or persistent
+#[derive(Clone)]
+enum Backend {
     Standard(Arc<RwLock<VectorDb>>),
     Quantized(Arc<RwLock<QuantizedVectorDb>>),
     #[cfg(feature = "persistence")]
     Persistent(Arc<RwLock<crate::persistent::PersistentVectorDb>>),
 }
 
-impl Collection {
-    pub fn insert(&self, id: String, vector: &[f32], metadata: Option<Value>) -> Result<()> {
-        match self {
-            Collection::Standard(db) => db.write().insert(id, vector, metadata),
-            Collection::Quantized(db) => db.write().insert(id, vector, metadata),
+/// Apply `write` to a persistent database, waiting for its WAL sync only
+/// after the write lock is released, so concurrent writers share the fsync
+#[cfg(feature = "persistence")]
+fn persist<T>(
+    db: &RwLock<crate::persistent::PersistentVectorDb>,
+    write: impl FnOnce(&mut crate::persistent::PersistentVectorDb) -> Result<T>,
+) -> Result<T> {
+    let (result, pending) = db.write().deferring_sync(write);
+    let value = result?;
+    if let Some(pending) = pending {
+        pending.wait()?;
+    }
+    Ok(value)
+}
+
+/// Run `query`, which reads records with `filter`, as one query of
+/// [`expr::scoped`](crate::expr::scoped) if the filter has an expression
+///
+/// An expression failing on a record, or running out of the query's
+/// budget, then fails the query.
+fn filtered<T>(
+    filter: Option<&crate::filter::Filter>,
+    query: impl FnOnce() -> Result<T>,
+) -> Result<T> {
+    match filter {
+        #[cfg(feature = "expr")]
+        Some(filter) if filter.has_expr() => crate::expr::scoped(query),
+        _ => query(),
+    }
+}
+
+/// A named collection of a [`Database`]; clones share it
+///
+/// A handle stays usable after its collection is deleted. A deleted
+/// on-disk collection's files are removed once its last handle is dropped.
+#[derive(Clone)]
+pub struct Collection {
+    // Dropped before `lifecycle`, so the files are closed before teardown
+    backend: Backend,
+    latency: Arc<LatencyRecorder>,
+    lifecycle: Arc<Lifecycle>,
+    /// Cluster summaries by cluster count, valid until the next write
+    summaries: Arc<RwLock<HashMap<usize, Arc<CollectionSummary>>>>,
+    /// Linear transform of query vectors, and maybe written ones
+    transform: Arc<RwLock<Option<Arc<VectorTransform>>>>,
+    /// Band of norms written vectors must fall in
+    norm_filter: Arc<RwLock<Option<NormFilter>>>,
+    /// Records refused by `norm_filter`
+    norm_rejections: Arc<AtomicU64>,
+    /// Number type written vectors are given in, and how they are converted
+    dtype: (VectorDtype, DtypeConversion),
+    /// Version of each vector, for conditional writes
+    versions: Arc<Versions>,
+}
+
+/// State shared by the handles of a collection
+#[derive(Default)]
+struct Lifecycle {
+    /// Imports, restores and compactions running on the collection
+    jobs: AtomicUsize,
+    /// Directory of the deleted collection, removed with its last handle
+    #[cfg(feature = "persistence")]
+    teardown: std::sync::Mutex<Option<std::path::PathBuf>>,
+}
+
+#[cfg(feature = "persistence")]
+impl Drop for Lifecycle {
+    fn drop(&mut self) {
+        let dir = self.teardown.get_mut().ok().and_then(Option::take);
+        if let Some(dir) = dir {
+            match std::fs::remove_dir_all(&dir) {
+                Ok(()) => debug!("Removed files of deleted collection at {:?}", dir),
+                Err(e) => error!("Failed to remove deleted collection at {:?}: {}", dir, e),
+            }
+        }
+    }
+}
+
+/// Marks a long-running job on a collection while it is alive
+///
+/// Deleting a collection with a job running fails unless forced; see
+/// [`Database::force_delete_collection`].
+pub struct CollectionJob {
+    lifecycle: Arc<Lifecycle>,
+}
+
+impl Drop for CollectionJob {
+    fn drop(&mut self) {
+        self.lifecycle.jobs.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Collection {
+    fn new(
+        backend: Backend,
+        transform: Option<VectorTransform>,
+        norm_filter: Option<NormFilter>,
+        dtype: (VectorDtype, DtypeConversion),
+    ) -> Self {
+        let mut collection = Self {
+            backend,
+            latency: Arc::default(),
+            lifecycle: Arc::default(),
+            summaries: Arc::new(RwLock::new(HashMap::new())),
+            transform: Arc::new(RwLock::new(transform.map(Arc::new))),
+            norm_filter: Arc::new(RwLock::new(norm_filter)),
+            norm_rejections: Arc::default(),
+            dtype,
+            versions: Arc::default(),
+        };
+        collection.versions = Arc::new(Versions::new(collection.write_seq()));
+        collection
+    }
+
+    /// The collection's vector transform, if it has one
+    pub fn transform(&self) -> Option<Arc<VectorTransform>> {
+        self.transform.read().clone()
+    }
+
+    /// `query` as searched, after the collection's transform
+    fn query_vector<'a>(&self, query: &'a [f32]) -> Result<Cow<'a, [f32]>> {
+        match self.transform() {
+            Some(transform) => Ok(Cow::Owned(transform.apply(query)?)),
+            None => Ok(Cow::Borrowed(query)),
+        }
+    }
+
+    /// A query made from stored vectors, as searched: only a transform that
+    /// stored vectors haven't been through is applied
+    fn stored_query<'a>(&self, vector: &'a [f32]) -> Result<Cow<'a, [f32]>> {
+        match self.transform() {
+            Some(transform) if !transform.apply_on_insert => {
+                Ok(Cow::Owned(transform.apply(vector)?))
+            }
+            _ => Ok(Cow::Borrowed(vector)),
+        }
+    }
+
+    /// `vector` as stored, after a transform applied on insert
+    fn written_vector<'a>(&self, vector: &'a [f32]) -> Result<Cow<'a, [f32]>> {
+        match self.transform() {
+            Some(transform) if transform.apply_on_insert => {
+                Ok(Cow::Owned(transform.apply(vector)?))
+            }
+            _ => Ok(Cow::Borrowed(vector)),
+        }
+    }
+
+    /// [`written_vector`](Self::written_vector) for each item of a batch
+    fn written_items<K, M>(
+        &self,
+        mut items: Vec<(K, Vec<f32>, M)>,
+    ) -> Result<Vec<(K, Vec<f32>, M)>> {
+        if let Some(transform) = self.transform().filter(|t| t.apply_on_insert) {
+            for (_, vector, _) in &mut items {
+                *vector = transform.apply(vector)?;
+            }
+        }
+        Ok(items)
+    }
+
+    /// Refuse a write if a vector of `items` is outside the norm filter,
+    /// counting every record that is
+    fn check_norms<'a>(&self, items: impl Iterator<Item = (&'a str, &'a [f32])>) -> Result<()> {
+        let Some(filter) = self.norm_filter() else {
+            return Ok(());
+        };
+        let mut first = None;
+        let mut rejected = 0;
+        for (id, vector) in items {
+            if let Err(e) = filter.check(id, vector) {
+                rejected += 1;
+                first.get_or_insert(e);
+            }
+        }
+        match first {
+            Some(e) => {
+                self.norm_rejections.fetch_add(rejected, Ordering::Relaxed);
+                Err(e)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Mark a long-running job, such as an import, until the guard is dropped
+    pub fn begin_job(&self) -> CollectionJob {
+        self.lifecycle.jobs.fetch_add(1, Ordering::SeqCst);
+        CollectionJob {
+            lifecycle: self.lifecycle.clone(),
+        }
+    }
+
+    /// Jobs marked with [`begin_job`](Self::begin_job) that are still running
+    pub fn active_jobs(&self) -> usize {
+        self.lifecycle.jobs.load(Ordering::SeqCst)
+    }
+
+    pub fn insert(&self, id: String, vector: &[f32], metadata: Option<Value>) -> Result<()> {
+        let write = self.versions.begin(vec![id.clone()]);
+        self.write_insert(id, vector, metadata)?;
+        write.written(self.write_seq());
+        Ok(())
+    }
+
+    /// Insert `id` unless it already exists; returns whether it was inserted
+    ///
+    /// Checks for the ID without reading the stored vector, so re-running an
+    /// ingestion skips what it already wrote cheaply. Fails with
+    /// [`Error::VersionConflict`] if another write to the ID is running.
+    pub fn insert_if_absent(
+        &self,
+        id: String,
+        vector: &[f32],
+        metadata: Option<Value>,
+    ) -> Result<bool> {
+        let key = id.clone();
+        match self.versions.write_if(
+            &key,
+            None,
+            || Ok(self.contains(&key)),
+            || self.write_insert(id, vector, metadata),
+            || self.write_seq(),
+        ) {
+            Ok(()) => Ok(true),
+            // An unconditional write may have inserted it since the check
+            Err(Error::VersionConflict {
+                actual: Some(_), ..
+            })
+            | Err(Error::DuplicateId(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn write_insert(&self, id: String, vector: &[f32], metadata: Option<Value>) -> Result<()> {
+        let _timer = self.latency.time(Operation::Insert);
+        self.check_norms(std::iter::once((id.as_str(), vector)))?;
+        let vector = &*self.written_vector(vector)?;
+        match &self.backend {
+            Backend::Standard(db) => db.write().insert(id, vector, metadata),
+            Backend::Quantized(db) => db.write().insert(id, vector, metadata),
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => persist(db, |db| db.insert(id, vector, metadata)),
+        }
+    }
+
+    pub fn upsert(&self, id: String, vector: &[f32], metadata: Option<Value>) -> Result<()> {
+        let write = self.versions.begin(vec![id.clone()]);
+        self.write_upsert(id, vector, metadata)?;
+        write.written(self.write_seq());
+        Ok(())
+    }
+
+    /// Upsert `id` only if it exists at `version`
+    ///
+    /// Fails with [`Error::VersionConflict`] if it doesn't, or if another
+    /// write to it is running, so concurrent writers never overwrite each
+    /// other's changes unseen.
+    pub fn upsert_if_version(
+        &self,
+        id: String,
+        vector: &[f32],
+        metadata: Option<Value>,
+        version: u64,
+    ) -> Result<()> {
+        let key = id.clone();
+        self.versions.write_if(
+            &key,
+            Some(version),
+            || Ok(self.contains(&key)),
+            || self.write_upsert(id, vector, metadata),
+            || self.write_seq(),
+        )
+    }
+
+    fn write_upsert(&self, id: String, vector: &[f32], metadata: Option<Value>) -> Result<()> {
+        let _timer = self.latency.time(Operation::Insert);
+        self.check_norms(std::iter::once((id.as_str(), vector)))?;
+        let vector = &*self.written_vector(vector)?;
+        match &self.backend {
+            Backend::Standard(db) => db.write().upsert(id, vector, metadata),
+            Backend::Quantized(db) => db.write().upsert(id, vector, metadata),
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => persist(db, |db| {
+                // Check the new record before the old one is deleted
+                let config = db.config();
+                if vector.len() != config.dimensions {
+                    return Err(Error::DimensionMismatch {
+                        expected: config.dimensions,
+                        got: vector.len(),
+                    });
+                }
+                config
+                    .metadata_limits
+                    .check(&VectorId::from(id.as_str()), metadata.as_ref())?;
+                let _ = db.delete(id.clone());
+                db.insert(id, vector, metadata)
+            }),
+        }
+    }
+
+    pub fn upsert_batch(&self, items: Vec<(String, Vec<f32>, Option<Value>)>) -> Result<()> {
+        let _timer = self.latency.time(Operation::Insert).records(items.len());
+        self.check_norms(items.iter().map(|(id, v, _)| (id.as_str(), v.as_slice())))?;
+        let items = self.written_items(items)?;
+        let write = self
+            .versions
+            .begin(items.iter().map(|(id, _, _)| id.clone()).collect());
+        match &self.backend {
+            Backend::Standard(db) => {
+                let items_converted: Vec<(VectorId, Vec<f32>, Option<Value>)> = items
+                    .into_iter()
+                    .map(|(id, vec, meta)| (VectorId::from(id), vec, meta))
+                    .collect();
+                db.write().upsert_batch(items_converted)
+            }
+            Backend::Quantized(db) => {
+                let items_converted: Vec<(VectorId, Vec<f32>, Option<Value>)> = items
+                    .into_iter()
+                    .map(|(id, vec, meta)| (VectorId::from(id), vec, meta))
+                    .collect();
+                db.write().upsert_batch(items_converted)
+            }
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => persist(db, |db| {
+                db.upsert_batch(
+                    items
+                        .into_iter()
+                        .map(|(id, vec, meta)| (VectorId::from(id), vec, meta))
+                        .collect(),
+                )
+            }),
+        }?;
+        write.written(self.write_seq());
+        Ok(())
+    }
+
+    /// Delete every vector matching `filter` and upsert `items` as one update
+    ///
+    /// Readers never observe a state in between. Returns the number of vectors
+    /// that matched `filter`.
+    pub fn replace(
+        &self,
+        filter: &crate::filter::Filter,
+        items: Vec<(String, Vec<f32>, Option<Value>)>,
+    ) -> Result<usize> {
+        self.check_norms(items.iter().map(|(id, v, _)| (id.as_str(), v.as_slice())))?;
+        let items: Vec<(VectorId, Vec<f32>, Option<Value>)> = self
+            .written_items(items)?
+            .into_iter()
+            .map(|(id, vec, meta)| (VectorId::from(id), vec, meta))
+            .collect();
+        // The vectors matching `filter` are only known once it runs; those
+        // not written again are gone, so only the written ones need versions
+        let write = self.versions.begin_bulk();
+        let written: Vec<String> = items.iter().map(|(id, _, _)| id.to_string()).collect();
+        let deleted = filtered(Some(filter), || match &self.backend {
+            Backend::Standard(db) => db.write().replace(filter, items),
+            Backend::Quantized(db) => db.write().replace(filter, items),
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => persist(db, |db| db.replace(filter, items)),
+        })?;
+        write.written_ids(written, self.write_seq());
+        Ok(deleted)
+    }
+
+    pub fn delete(&self, id: &str) -> Result<bool> {
+        let write = self.versions.begin(vec![id.to_string()]);
+        let deleted = self.write_delete(id)?;
+        write.deleted(self.write_seq());
+        Ok(deleted)
+    }
+
+    /// Delete `id` only if it exists at `version`
+    ///
+    /// Fails with [`Error::VersionConflict`] like
+    /// [`upsert_if_version`](Self::upsert_if_version).
+    pub fn delete_if_version(&self, id: &str, version: u64) -> Result<()> {
+        self.versions.write_if(
+            id,
+            Some(version),
+            || Ok(self.contains(id)),
+            || self.write_delete(id).map(drop),
+            || self.write_seq(),
+        )
+    }
+
+    fn write_delete(&self, id: &str) -> Result<bool> {
+        let _timer = self.latency.time(Operation::Delete);
+        match &self.backend {
+            Backend::Standard(db) => db.write().delete(id),
+            Backend::Quantized(db) => db.write().delete(id),
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => persist(db, |db| db.delete(id)),
+        }
+    }
+
+    /// Delete the vectors with the given IDs in one step; returns those that
+    /// existed
+    pub fn delete_batch(&self, ids: &[String]) -> Result<Vec<VectorId>> {
+        let _timer = self.latency.time(Operation::Delete);
+        let write = self.versions.begin(ids.to_vec());
+        let ids = ids.iter().map(String::as_str);
+        let deleted = match &self.backend {
+            Backend::Standard(db) => db.write().delete_batch(ids),
+            Backend::Quantized(db) => db.write().delete_batch(ids),
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => persist(db, |db| db.delete_batch(ids)),
+        }?;
+        write.deleted(self.write_seq());
+        Ok(deleted)
+    }
+
+    /// Delete every vector whose metadata matches `filter`; returns their IDs
+    pub fn delete_by_filter(&self, filter: &crate::filter::Filter) -> Result<Vec<VectorId>> {
+        let _timer = self.latency.time(Operation::Delete);
+        let write = self.versions.begin_bulk();
+        let deleted = filtered(Some(filter), || match &self.backend {
+            Backend::Standard(db) => db.write().delete_by_filter(filter),
+            Backend::Quantized(db) => db.write().delete_by_filter(filter),
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => persist(db, |db| db.delete_by_filter(filter)),
+        })?;
+        write.written_ids(deleted.iter().map(|id| id.as_str()), self.write_seq());
+        Ok(deleted)
+    }
+
+    /// Change the metadata of `id` without resending its vector; returns false
+    /// if there is no such vector
+    ///
+    /// `metadata` replaces the current metadata (`null` clears it), or with
+    /// `merge` is applied to it as a JSON merge patch, where `null` removes a
+    /// key. The vector stays linked in the graph.
+    pub fn update_metadata(&self, id: &str, metadata: Value, merge: bool) -> Result<bool> {
+        let write = self.versions.begin(vec![id.to_string()]);
+        let updated = match &self.backend {
+            Backend::Standard(db) => db.write().update_metadata(id, metadata, merge),
+            Backend::Quantized(db) => db.write().update_metadata(id, metadata, merge),
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => persist(db, |db| db.update_metadata(id, metadata, merge)),
+        }?;
+        write.written(self.write_seq());
+        Ok(updated)
+    }
+
+    /// Sequence number of the last write visible to reads
+    ///
+    /// A search that sees this number also sees every write that returned
+    /// before it was read. Persistent collections use their WAL sequence,
+    /// which survives restarts.
+    pub fn write_seq(&self) -> u64 {
+        match &self.backend {
+            Backend::Standard(db) => db.read().write_seq(),
+            Backend::Quantized(db) => db.read().write_seq(),
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => db.read().write_seq(),
+        }
+    }
+
+    /// Number of live vectors
+    pub fn len(&self) -> usize {
+        match &self.backend {
+            Backend::Standard(db) => db.read().len(),
+            Backend::Quantized(db) => db.read().len(),
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => db.read().len(),
+        }
+    }
+
+    /// Number of live vectors, or of those whose metadata matches `filter`
+    ///
+    /// Uses a cached or indexed filter's bitmap when there is one, and
+    /// otherwise checks the metadata of every record.
+    pub fn count(&self, filter: Option<&crate::filter::Filter>) -> Result<usize> {
+        filtered(filter, || match &self.backend {
+            Backend::Standard(db) => db.read().count(filter),
+            Backend::Quantized(db) => db.read().count(filter),
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => db.read().count(filter),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, id: &str) -> Result<Option<(Vec<f32>, Option<Value>)>> {
+        match &self.backend {
+            Backend::Standard(db) => db.read().get(id),
+            Backend::Quantized(db) => db.read().get(id),
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => db.read().get(id),
+        }
+    }
+
+    /// Whether `id` exists, without copying its vector
+    pub fn contains(&self, id: &str) -> bool {
+        match &self.backend {
+            Backend::Standard(db) => db.read().contains(id),
+            Backend::Quantized(db) => db.read().contains(id),
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => db.read().contains(id),
+        }
+    }
+
+    /// Like [`get`](Self::get), with the vector's version
+    ///
+    /// The version grows with every write to the vector; pass it to
+    /// [`upsert_if_version`](Self::upsert_if_version) or
+    /// [`delete_if_version`](Self::delete_if_version) to write only if
+    /// nobody else has since.
+    pub fn get_versioned(&self, id: &str) -> Result<Option<VersionedRecord>> {
+        // Read first, so a write in between makes the version stale rather
+        // than labeling old data with a new version
+        let version = self.versions.version(id);
+        Ok(self
+            .get(id)?
+            .map(|(vector, metadata)| (vector, metadata, version)))
+    }
+
+    /// Version of `id`, if it exists
+    pub fn version(&self, id: &str) -> Result<Option<u64>> {
+        Ok(self.get_versioned(id)?.map(|(_, _, version)| version))
+    }
+
+    pub fn get_metadata_batch(&self, ids: &[String]) -> Vec<(VectorId, Option<Value>)> {
+        match &self.backend {
+            Backend::Standard(db) => db.read().get_metadata_batch(ids),
+            Backend::Quantized(db) => db.read().get_metadata_batch(ids),
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => db.read().get_metadata_batch(ids),
+        }
+    }
+
+    /// Rebuild the metadata index of `field` from the stored payloads
+    ///
+    /// Runs on the calling thread in chunks, so writes and queries go on in
+    /// between; filters on the field scan the payloads until it is done.
+    /// `progress` is called with the slots done and the total after each
+    /// chunk. Quantized collections have no metadata index to rebuild.
+    pub fn rebuild_field_index(
+        &self,
+        field: &str,
+        mut progress: impl FnMut(usize, usize),
+    ) -> Result<()> {
+        let _job = self.begin_job();
+        let total = self.with_storage(|storage| storage.begin_index_rebuild(field))??;
+        progress(0, total);
+        let mut done = 0;
+        while done < total {
+            let end = (done + INDEX_REBUILD_CHUNK).min(total);
+            self.with_storage(|storage| storage.rebuild_index_chunk(field, done..end))?;
+            done = end;
+            progress(done, total);
+        }
+        self.with_storage(|storage| storage.finish_index_rebuild(field))
+    }
+
+    /// Whether filters can use a metadata index; quantized collections have none
+    pub fn has_metadata_index(&self) -> bool {
+        !matches!(self.backend, Backend::Quantized(_))
+    }
+
+    /// Run `f` on the unquantized storage of the collection
+    fn with_storage<T>(&self, f: impl FnOnce(&crate::storage::VectorStorage) -> T) -> Result<T> {
+        match &self.backend {
+            Backend::Standard(db) => Ok(f(db.read().storage())),
+            Backend::Quantized(_) => Err(Error::InvalidConfig(
+                "Quantized collections have no metadata index".to_string(),
+            )),
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => Ok(f(db.read().storage())),
+        }
+    }
+
+    pub fn cache_filter(&self, filter: crate::filter::Filter) -> Result<CachedFilterInfo> {
+        match &self.backend {
+            Backend::Standard(db) => db.read().cache_filter(filter),
+            Backend::Quantized(db) => db.read().cache_filter(filter),
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => db.read().cache_filter(filter),
+        }
+    }
+
+    pub fn uncache_filter(&self, id: &str) -> bool {
+        match &self.backend {
+            Backend::Standard(db) => db.read().uncache_filter(id),
+            Backend::Quantized(db) => db.read().uncache_filter(id),
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => db.read().uncache_filter(id),
+        }
+    }
+
+    pub fn cached_filters(&self) -> Vec<CachedFilterInfo> {
+        match &self.backend {
+            Backend::Standard(db) => db.read().cached_filters(),
+            Backend::Quantized(db) => db.read().cached_filters(),
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => db.read().cached_filters(),
+        }
+    }
+
+    pub fn search(
+        &self,
+        query: &[f32],
+        k: usize,
+        filter: Option<&crate::filter::Filter>,
+    ) -> Result<Vec<(VectorId, f32, Option<Value>)>> {
+        let _timer = self.latency.time(Operation::Search);
+        let query = &*self.query_vector(query)?;
+        filtered(filter, || match &self.backend {
+            Backend::Standard(db) => db.read().search(query, k, filter),
+            Backend::Quantized(db) => db.read().search(query, k, filter),
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => db.read().search(query, k, filter),
+        })
+    }
+
+    pub fn search_with_usage(
+        &self,
+        query: &[f32],
+        k: usize,
+        filter: Option<&crate::filter::Filter>,
+    ) -> Result<(Vec<SearchHit>, SearchUsage)> {
+        let _timer = self.latency.time(Operation::Search);
+        let query = &*self.query_vector(query)?;
+        filtered(filter, || match &self.backend {
+            Backend::Standard(db) => db.read().search_with_usage(query, k, filter),
+            Backend::Quantized(db) => db.read().search_with_usage(query, k, filter),
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => db.read().search_with_usage(query, k, filter),
+        })
+    }
+
+    /// Search with per-search overrides of the collection's defaults
+    pub fn search_with_params(
+        &self,
+        query: &[f32],
+        k: usize,
+        filter: Option<&crate::filter::Filter>,
+        params: SearchParams,
+    ) -> Result<(Vec<SearchHit>, SearchUsage)> {
+        let _timer = self.latency.time(Operation::Search);
+        let query = &*self.query_vector(query)?;
+        filtered(filter, || self.hits_with_params(query, k, filter, params))
+    }
+
+    /// [`search_with_params`](Self::search_with_params) of a query already
+    /// transformed, kept out of the latency figures
+    fn hits_with_params(
+        &self,
+        query: &[f32],
+        k: usize,
+        filter: Option<&crate::filter::Filter>,
+        params: SearchParams,
+    ) -> Result<(Vec<SearchHit>, SearchUsage)> {
+        match &self.backend {
+            Backend::Standard(db) => db.read().search_with_params(query, k, filter, params),
+            Backend::Quantized(db) => db.read().search_with_params(query, k, filter, params),
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => db.read().search_with_params(query, k, filter, params),
+        }
+    }
+
+    pub fn search_ids(
+        &self,
+        query: &[f32],
+        k: usize,
+        filter: Option<&crate::filter::Filter>,
+    ) -> Result<Vec<(VectorId, f32)>> {
+        let _timer = self.latency.time(Operation::Search);
+        let query = &*self.query_vector(query)?;
+        filtered(filter, || match &self.backend {
+            Backend::Standard(db) => db.read().search_ids(query, k, filter),
+            Backend::Quantized(db) => db.read().search_ids(query, k, filter),
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => db.read().search_ids(query, k, filter),
+        })
+    }
+
+    pub fn search_ids_with_usage(
+        &self,
+        query: &[f32],
+        k: usize,
+        filter: Option<&crate::filter::Filter>,
+    ) -> Result<(Vec<(VectorId, f32)>, SearchUsage)> {
+        let _timer = self.latency.time(Operation::Search);
+        let query = &*self.query_vector(query)?;
+        filtered(filter, || match &self.backend {
+            Backend::Standard(db) => db.read().search_ids_with_usage(query, k, filter),
+            Backend::Quantized(db) => db.read().search_ids_with_usage(query, k, filter),
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => db.read().search_ids_with_usage(query, k, filter),
+        })
+    }
+
+    /// [`search_ids_with_usage`](Self::search_ids_with_usage) with per-search overrides
+    pub fn search_ids_with_params(
+        &self,
+        query: &[f32],
+        k: usize,
+        filter: Option<&crate::filter::Filter>,
+        params: SearchParams,
+    ) -> Result<(Vec<(VectorId, f32)>, SearchUsage)> {
+        let _timer = self.latency.time(Operation::Search);
+        let query = &*self.query_vector(query)?;
+        filtered(filter, || self.ids_with_params(query, k, filter, params))
+    }
+
+    /// Searches the collection runs itself, kept out of its latency figures;
+    /// `query` is not transformed
+    fn ids_with_params(
+        &self,
+        query: &[f32],
+        k: usize,
+        filter: Option<&crate::filter::Filter>,
+        params: SearchParams,
+    ) -> Result<(Vec<(VectorId, f32)>, SearchUsage)> {
+        match &self.backend {
+            Backend::Standard(db) => db.read().search_ids_with_params(query, k, filter, params),
+            Backend::Quantized(db) => db.read().search_ids_with_params(query, k, filter, params),
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => db.read().search_ids_with_params(query, k, filter, params),
+        }
+    }
+
+    /// Set the sparse vector of record `id`; returns false if there is no such record
+    ///
+    /// Quantized in-memory collections don't support sparse vectors.
+    pub fn set_sparse(&self, id: &str, vector: SparseVector) -> Result<bool> {
+        let write = self.versions.begin(vec![id.to_string()]);
+        let set = match &self.backend {
+            Backend::Standard(db) => db.write().set_sparse(id, vector),
+            Backend::Quantized(_) => Err(Self::sparse_unsupported()),
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => persist(db, |db| db.set_sparse(id, vector)),
+        }?;
+        write.written(self.write_seq());
+        Ok(set)
+    }
+
+    /// Sparse vector of record `id`, if it has one
+    pub fn get_sparse(&self, id: &str) -> Option<SparseVector> {
+        match &self.backend {
+            Backend::Standard(db) => db.read().get_sparse(id),
+            Backend::Quantized(_) => None,
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => db.read().get_sparse(id),
+        }
+    }
+
+    /// Set the vector of record `id` in the named space `name`; returns false
+    /// if there is no such record
+    pub fn set_named_vector(&self, id: &str, name: &str, vector: &[f32]) -> Result<bool> {
+        let write = self.versions.begin(vec![id.to_string()]);
+        let set = match &self.backend {
+            Backend::Standard(db) => db.write().set_named_vector(id, name, vector),
+            Backend::Quantized(_) => Err(Self::named_unsupported()),
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => persist(db, |db| db.set_named_vector(id, name, vector)),
+        }?;
+        write.written(self.write_seq());
+        Ok(set)
+    }
+
+    /// Named vectors of record `id`, by space
+    pub fn get_named_vectors(&self, id: &str) -> Vec<(String, Vec<f32>)> {
+        match &self.backend {
+            Backend::Standard(db) => db.read().get_named_vectors(id),
+            Backend::Quantized(_) => Vec::new(),
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => db.read().get_named_vectors(id),
+        }
+    }
+
+    /// Search the named space `name` instead of the primary vectors
+    pub fn search_named(
+        &self,
+        name: &str,
+        query: &[f32],
+        k: usize,
+        filter: Option<&crate::filter::Filter>,
+        params: SearchParams,
+    ) -> Result<(Vec<SearchHit>, SearchUsage)> {
+        let _timer = self.latency.time(Operation::Search);
+        filtered(filter, || match &self.backend {
+            Backend::Standard(db) => db.read().search_named(name, query, k, filter, params),
+            Backend::Quantized(_) => Err(Self::named_unsupported()),
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => db.read().search_named(name, query, k, filter, params),
+        })
+    }
+
+    /// Search with a dense and a sparse query, fusing both rankings
+    pub fn search_hybrid(
+        &self,
+        dense: &[f32],
+        sparse: &SparseVector,
+        k: usize,
+        filter: Option<&crate::filter::Filter>,
+        fusion: Fusion,
+        params: SearchParams,
+    ) -> Result<(Vec<HybridHit>, SearchUsage)> {
+        let _timer = self.latency.time(Operation::Search);
+        let dense = &*self.query_vector(dense)?;
+        filtered(filter, || match &self.backend {
+            Backend::Standard(db) => db
+                .read()
+                .search_hybrid(dense, sparse, k, filter, fusion, params),
+            Backend::Quantized(_) => Err(Self::sparse_unsupported()),
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => db
+                .read()
+                .search_hybrid(dense, sparse, k, filter, fusion, params),
+        })
+    }
+
+    /// Keyword search of the collection's text fields
+    ///
+    /// Scores are BM25, so higher is better. Fails if the collection declares
+    /// no `text_fields`.
+    pub fn search_text(
+        &self,
+        query: &str,
+        k: usize,
+        filter: Option<&crate::filter::Filter>,
+    ) -> Result<(Vec<SearchHit>, SearchUsage)> {
+        let _timer = self.latency.time(Operation::Search);
+        filtered(filter, || match &self.backend {
+            Backend::Standard(db) => db.read().search_text(query, k, filter),
+            Backend::Quantized(_) => Err(Self::text_unsupported()),
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => db.read().search_text(query, k, filter),
+        })
+    }
+
+    /// Search with a dense vector and a text query, fusing both rankings
+    pub fn search_hybrid_text(
+        &self,
+        dense: &[f32],
+        query: &str,
+        k: usize,
+        filter: Option<&crate::filter::Filter>,
+        fusion: Fusion,
+        params: SearchParams,
+    ) -> Result<(Vec<HybridHit>, SearchUsage)> {
+        let _timer = self.latency.time(Operation::Search);
+        let dense = &*self.query_vector(dense)?;
+        filtered(filter, || match &self.backend {
+            Backend::Standard(db) => db
+                .read()
+                .search_hybrid_text(dense, query, k, filter, fusion, params),
+            Backend::Quantized(_) => Err(Self::text_unsupported()),
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => db
+                .read()
+                .search_hybrid_text(dense, query, k, filter, fusion, params),
+        })
+    }
+
+    fn text_unsupported() -> Error {
+        Error::InvalidConfig("Text search is not supported for quantized collections".to_string())
+    }
+
+    fn named_unsupported() -> Error {
+        Error::InvalidConfig(
+            "Named vectors are not supported for quantized collections".to_string(),
+        )
+    }
+
+    fn sparse_unsupported() -> Error {
+        Error::InvalidConfig(
+            "Sparse vectors are not supported for quantized collections".to_string(),
+        )
+    }
+
+    /// Records like the positive examples of `examples` and unlike its
+    /// negative ones, leaving out the examples themselves
+    ///
+    /// Fails if an example doesn't exist. `filter` and `params` apply to
+    /// each search the strategy runs.
+    pub fn recommend(
+        &self,
+        examples: &Recommend,
+        k: usize,
+        filter: Option<&crate::filter::Filter>,
+        params: SearchParams,
+    ) -> Result<(Vec<SearchHit>, SearchUsage)> {
+        examples.validate()?;
+        filtered(filter, || {
+            let vectors = |ids: &[String]| {
+                ids.iter()
+                    .map(|id| match self.get(id)? {
+                        Some((vector, _)) => Ok(vector),
+                        None => Err(Error::VectorNotFound(id.clone())),
+                    })
+                    .collect::<Result<Vec<_>>>()
+            };
+            let positive = vectors(&examples.positive)?;
+            let negative = vectors(&examples.negative)?;
+
+            // The examples are likely among the nearest records, so search past them
+            let excluded: HashSet<&str> = examples.examples().collect();
+            let search_k = k + excluded.len();
+            let recommended = |id: &VectorId| !excluded.contains(id.to_string().as_str());
+
+            match examples.strategy {
+                RecommendStrategy::AverageVector => {
+                    let target = recommend::average_target(&positive, &negative);
+                    let _timer = self.latency.time(Operation::Search);
+                    let (mut hits, usage) = self.hits_with_params(
+                        &self.stored_query(&target)?,
+                        search_k,
+                        filter,
+                        params,
+                    )?;
+                    hits.retain(|(id, _, _)| recommended(id));
+                    hits.truncate(k);
+                    Ok((hits, usage))
+                }
+                RecommendStrategy::BestScore => {
+                    let mut usage = SearchUsage::default();
+                    let mut seen = HashSet::new();
+                    let mut candidates = Vec::new();
+                    for example in &positive {
+                        let _timer = self.latency.time(Operation::Search);
+                        let (hits, searched) = self.hits_with_params(
+                            &self.stored_query(example)?,
+                            search_k,
+                            filter,
+                            params,
+                        )?;
+                        usage.vectors_scanned += searched.vectors_scanned;
+                        usage.graph_hops += searched.graph_hops;
+                        usage.rescored_candidates += searched.rescored_candidates;
+                        for (id, _, metadata) in hits {
+                            if !recommended(&id) || !seen.insert(id.clone()) {
+                                continue;
+                            }
+                            if let Some((vector, _)) = self.get(&id.to_string())? {
+                                candidates.push(((id, metadata), vector));
+                            }
+                        }
+                    }
+                    let metric = self.config().distance_metric;
+                    let hits =
+                        recommend::rank_best_score(candidates, &positive, &negative, metric, k)
+                            .into_iter()
+                            .map(|((id, metadata), distance)| (id, distance, metadata))
+                            .collect();
+                    Ok((hits, usage))
+                }
+            }
+        })
+    }
+
+    /// The nearest hits for each value of a metadata field, per `group_by`
+    ///
+    /// Groups are ordered by their nearest hit. The search widens, up to
+    /// [`MAX_GROUP_CANDIDATES`], until every group is full or no more records
+    /// match.
+    pub fn search_grouped(
+        &self,
+        query: &[f32],
+        group_by: &GroupBy,
+        filter: Option<&crate::filter::Filter>,
+        params: SearchParams,
+    ) -> Result<(Vec<SearchGroup>, SearchUsage)> {
+        group_by.validate()?;
+        filtered(filter, || {
+            let _timer = self.latency.time(Operation::Search);
+            let query = &*self.query_vector(query)?;
+            let mut usage = SearchUsage::default();
+            let mut k = group_by
+                .capacity()
+                .saturating_mul(2)
+                .min(MAX_GROUP_CANDIDATES);
+            loop {
+                let (hits, searched) = match &self.backend {
+                    Backend::Standard(db) => db.read().search_with_params(query, k, filter, params),
+                    Backend::Quantized(db) => {
+                        db.read().search_with_params(query, k, filter, params)
+                    }
+                    #[cfg(feature = "persistence")]
+                    Backend::Persistent(db) => {
+                        db.read().search_with_params(query, k, filter, params)
+                    }
+                }?;
+                usage.vectors_scanned += searched.vectors_scanned;
+                usage.graph_hops += searched.graph_hops;
+                usage.rescored_candidates += searched.rescored_candidates;
+                let exhausted = hits.len() < k || k >= MAX_GROUP_CANDIDATES;
+                let (groups, full) = group_by.collect(hits);
+                if full || exhausted {
+                    return Ok((groups, usage));
+                }
+                k = k.saturating_mul(4).min(MAX_GROUP_CANDIDATES);
+            }
+        })
+    }
+
+    /// Nearest neighbors of `query` that also meet `bounds` on their
+    /// distance to other vectors; see [`distance_to`](crate::distance_to)
+    ///
+    /// The bound vectors are transformed like the query.
+    pub fn search_bounded(
+        &self,
+        query: &[f32],
+        k: usize,
+        filter: Option<&crate::filter::Filter>,
+        params: SearchParams,
+        bounds: &DistanceBounds,
+    ) -> Result<(Vec<SearchHit>, SearchUsage)> {
+        bounds.validate(self.config().dimensions)?;
+        filtered(filter, || {
+            let _timer = self.latency.time(Operation::Search);
+            let query = &*self.query_vector(query)?;
+            let bounds = bounds.map_vectors(|v| Ok(self.query_vector(v)?.into_owned()))?;
+            let metric = self.config().distance_metric;
+            let mut usage = SearchUsage::default();
+            let mut candidates = k.saturating_mul(4).clamp(k, MAX_DISTANCE_TO_CANDIDATES);
+            loop {
+                let (hits, searched) = self.hits_with_params(query, candidates, filter, params)?;
+                usage.vectors_scanned += searched.vectors_scanned;
+                usage.graph_hops += searched.graph_hops;
+                usage.rescored_candidates += searched.rescored_candidates;
+                let exhausted = hits.len() < candidates || candidates >= MAX_DISTANCE_TO_CANDIDATES;
+                let mut admitted = Vec::with_capacity(k);
+                for hit in hits {
+                    if admitted.len() == k {
+                        break;
+                    }
+                    let Some((vector, _)) = self.get(&hit.0.to_string())? else {
+                        continue;
+                    };
+                    usage.vectors_scanned += 1;
+                    if bounds.admits(metric, &vector) {
+                        admitted.push(hit);
+                    }
+                }
+                if admitted.len() == k || exhausted {
+                    return Ok((admitted, usage));
+                }
+                candidates = candidates.saturating_mul(4).min(MAX_DISTANCE_TO_CANDIDATES);
+            }
+        })
+    }
+
+    /// Mine hard negatives for each query, in parallel; results are in query
+    /// order. See [`negatives`](crate::negatives)
+    ///
+    /// Fails if a positive doesn't exist.
+    pub fn hard_negatives(
+        &self,
+        queries: &[HardNegativeQuery],
+        options: &HardNegatives,
+        filter: Option<&crate::filter::Filter>,
+        params: SearchParams,
+    ) -> Result<(Vec<MinedNegatives>, SearchUsage)> {
+        options.validate()?;
+        let metric = self.distance_metric();
+        let mined = self.for_each_query(queries, |query| {
+            filtered(filter, || {
+                let Some((positive, _)) = self.get(&query.positive_id)? else {
+                    return Err(Error::VectorNotFound(query.positive_id.clone()));
+                };
+                if positive.len() != query.vector.len() {
+                    return Err(Error::DimensionMismatch {
+                        expected: positive.len(),
+                        got: query.vector.len(),
+                    });
+                }
+                let vector = self.query_vector(&query.vector)?;
+                let positive_distance = metric.distance(&vector, &positive);
+                let mut usage = SearchUsage::default();
+                let mut k = options.first_search();
+                loop {
+                    let _timer = self.latency.time(Operation::Search);
+                    let (hits, searched) = self.ids_with_params(&vector, k, filter, params)?;
+                    usage.vectors_scanned += searched.vectors_scanned;
+                    usage.graph_hops += searched.graph_hops;
+                    usage.rescored_candidates += searched.rescored_candidates;
+                    let exhausted = hits.len() < k || k >= MAX_NEGATIVE_CANDIDATES;
+                    let (negatives, full) =
+                        options.select(&hits, &query.positive_id, positive_distance, metric);
+                    if full || exhausted {
+                        let mined = MinedNegatives {
+                            positive_id: query.positive_id.clone(),
+                            positive_distance,
+                            negatives,
+                        };
+                        return Ok((mined, usage));
+                    }
+                    k = k.saturating_mul(4).min(MAX_NEGATIVE_CANDIDATES);
+                }
+            })
+        })?;
+        let mut usage = SearchUsage::default();
+        let mined = mined
+            .into_iter()
+            .map(|(mined, searched)| {
+                usage.vectors_scanned += searched.vectors_scanned;
+                usage.graph_hops += searched.graph_hops;
+                usage.rescored_candidates += searched.rescored_candidates;
+                mined
+            })
+            .collect();
+        Ok((mined, usage))
+    }
+
+    /// Run [`search_with_params`](Self::search_with_params) for each query,
+    /// in parallel; results are in query order
+    pub fn search_batch(
+        &self,
+        queries: &[Vec<f32>],
+        k: usize,
+        filter: Option<&crate::filter::Filter>,
+        params: SearchParams,
+    ) -> Result<Vec<(Vec<SearchHit>, SearchUsage)>> {
+        self.for_each_query(queries, |query| {
+            self.search_with_params(query, k, filter, params)
+        })
+    }
+
+    /// [`search_batch`](Self::search_batch) returning IDs and distances only
+    pub fn search_ids_batch(
+        &self,
+        queries: &[Vec<f32>],
+        k: usize,
+        filter: Option<&crate::filter::Filter>,
+        params: SearchParams,
+    ) -> Result<Vec<IdResults>> {
+        self.for_each_query(queries, |query| {
+            self.search_ids_with_params(query, k, filter, params)
+        })
+    }
+
+    #[cfg(feature = "parallel")]
+    fn for_each_query<Q: Sync, T: Send>(
+        &self,
+        queries: &[Q],
+        search: impl Fn(&Q) -> Result<T> + Sync,
+    ) -> Result<Vec<T>> {
+        use rayon::prelude::*;
+        queries.par_iter().map(&search).collect()
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    fn for_each_query<Q, T>(
+        &self,
+        queries: &[Q],
+        search: impl Fn(&Q) -> Result<T>,
+    ) -> Result<Vec<T>> {
+        queries.iter().map(search).collect()
+    }
+
+    /// List records in pages, starting a listing if `cursor` is `None`
+    ///
+    /// Unlike [`list`](Self::list), each page costs the same however far the
+    /// listing got. Every record that exists when the listing starts and
+    /// isn't deleted during it is returned exactly once, with its current
+    /// metadata; records inserted after the start are not returned. A cursor
+    /// only works on the collection that issued it and until that collection
+    /// is reopened or restored.
+    pub fn list_page(&self, cursor: Option<ListCursor>, limit: usize) -> Result<ListPage> {
+        match &self.backend {
+            Backend::Standard(db) => db.read().list_page(cursor, limit),
+            Backend::Quantized(db) => db.read().list_page(cursor, limit),
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => db.read().list_page(cursor, limit),
+        }
+    }
+
+    /// Iterate over every record with its vector and metadata, a page at a
+    /// time; see [`Scan`]
+    pub fn scan(&self) -> Scan<'_> {
+        Scan::new(
+            move |cursor, limit| self.list_page(cursor, limit),
+            move |id| self.get(id),
+        )
+    }
+
+    /// Up to `limit` records with their vectors and metadata, continuing the
+    /// listing at `cursor` or starting one if it is `None`
+    ///
+    /// Pages follow the same rules as [`list_page`](Self::list_page), and each
+    /// is read under one lock. With a `filter`, only matching records are
+    /// returned and a page examines at most
+    /// [`MAX_SCROLL_SCANNED`](crate::scan::MAX_SCROLL_SCANNED) records, so it
+    /// may be short even when more follow; only a `next` of `None` ends the
+    /// listing.
+    pub fn scroll(
+        &self,
+        cursor: Option<ListCursor>,
+        limit: usize,
+        filter: Option<&crate::filter::Filter>,
+    ) -> Result<ScrollPage> {
+        filtered(filter, || match &self.backend {
+            Backend::Standard(db) => {
+                let db = db.read();
+                scan::scroll(
+                    |c, n| db.list_page(c, n),
+                    |id| db.get(id),
+                    cursor,
+                    limit,
+                    filter,
+                )
+            }
+            Backend::Quantized(db) => {
+                let db = db.read();
+                scan::scroll(
+                    |c, n| db.list_page(c, n),
+                    |id| db.get(id),
+                    cursor,
+                    limit,
+                    filter,
+                )
+            }
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => {
+                let db = db.read();
+                scan::scroll(
+                    |c, n| db.list_page(c, n),
+                    |id| db.get(id),
+                    cursor,
+                    limit,
+                    filter,
+                )
+            }
+        })
+    }
+
+    pub fn list(&self, offset: usize, limit: usize) -> Vec<(VectorId, Option<Value>)> {
+        match &self.backend {
+            Backend::Standard(db) => db.read().list(offset, limit),
+            Backend::Quantized(db) => db.read().list(offset, limit),
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => db.read().list(offset, limit),
+        }
+    }
+
+    pub fn distance_metric(&self) -> DistanceMetric {
+        match &self.backend {
+            Backend::Standard(db) => db.read().config().distance_metric,
+            Backend::Quantized(db) => db.read().config().distance_metric,
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => db.read().config().distance_metric,
+        }
+    }
+
+    /// Configuration equivalent to the one the collection was created with
+    pub fn config(&self) -> Config {
+        let transform = self.transform().map(|t| (*t).clone());
+        let config = match &self.backend {
+            Backend::Standard(db) => db.read().config().clone(),
+            Backend::Quantized(db) => {
+                let db = db.read();
+                let config = db.config();
+                Config {
+                    dimensions: config.dimensions,
+                    distance_metric: config.distance_metric,
+                    hnsw: config.hnsw.clone(),
+                    quantization: config.quantization,
+                    id_type: config.id_type,
+                    metadata_compression: config.metadata_compression,
+                    metadata_limits: config.metadata_limits,
+                    ..Config::default()
+                }
+            }
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => {
+                let db = db.read();
+                let config = db.config();
+                Config {
+                    dimensions: config.dimensions,
+                    distance_metric: config.distance_metric,
+                    hnsw: config.hnsw.clone(),
+                    id_type: config.id_type,
+                    metadata_compression: config.metadata_compression,
+                    partition_field: config.partition_field.clone(),
+                    text_fields: config.text_fields.clone(),
+                    group_commit: config.group_commit,
+                    quantization: config.quantization,
+                    named_vectors: config.named_vectors.clone(),
+                    index: config.index,
+                    metadata_limits: config.metadata_limits,
+                    ..Config::default()
+                }
+            }
+        };
+        Config {
+            transform,
+            norm_filter: self.norm_filter(),
+            dtype: self.dtype.0,
+            dtype_conversion: self.dtype.1,
+            ..config
+        }
+    }
+
+    /// Number type written vectors are given in
+    pub fn dtype(&self) -> VectorDtype {
+        self.dtype.0
+    }
+
+    /// A vector given in the collection's dtype, as the `f32` values it is
+    /// stored as; see [`VectorDtype::convert`]
+    pub fn convert_vector(&self, values: &[f64]) -> Result<Vec<f32>> {
+        let (dtype, conversion) = self.dtype;
+        dtype.convert(values, conversion)
+    }
+
+    /// Band of norms written vectors must fall in, if the collection has one
+    pub fn norm_filter(&self) -> Option<NormFilter> {
+        *self.norm_filter.read()
+    }
+
+    /// Records refused by the norm filter since the collection was opened
+    pub fn norm_rejections(&self) -> u64 {
+        self.norm_rejections.load(Ordering::Relaxed)
+    }
+
+    /// Replace the norm filter of written vectors
+    fn set_norm_filter(&self, filter: Option<NormFilter>) -> Result<()> {
+        if let Some(filter) = &filter {
+            filter.validate()?;
+        }
+        *self.norm_filter.write() = filter;
+        Ok(())
+    }
+
+    /// Replace the vector transform
+    ///
+    /// A transform applied on insert, old or new, can only be changed while
+    /// the collection is empty, since stored vectors went through it.
+    fn set_transform(&self, transform: Option<VectorTransform>) -> Result<()> {
+        let mut current = self.transform.write();
+        if let Some(transform) = &transform {
+            transform.validate(self.config_dimensions())?;
+        }
+        let on_insert = |t: Option<&VectorTransform>| t.is_some_and(|t| t.apply_on_insert);
+        if (on_insert(current.as_deref()) || on_insert(transform.as_ref()))
+            && current.as_deref() != transform.as_ref()
+            && !self.is_empty()
+        {
+            return Err(Error::InvalidConfig(
+                "A transform applied on insert can only be changed while the collection is empty"
+                    .to_string(),
+            ));
+        }
+        *current = transform.map(Arc::new);
+        Ok(())
+    }
+
+    fn config_dimensions(&self) -> usize {
+        match &self.backend {
+            Backend::Standard(db) => db.read().config().dimensions,
+            Backend::Quantized(db) => db.read().config().dimensions,
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => db.read().config().dimensions,
+        }
+    }
+
+    /// Export the HNSW graph, optionally for one `level` or a `sample` of nodes
+    pub fn export_graph(&self, level: Option<usize>, sample: Option<usize>) -> Result<GraphExport> {
+        match &self.backend {
+            Backend::Standard(db) => Ok(db.read().export_graph(level, sample)),
+            Backend::Quantized(db) => db.read().export_graph(level, sample),
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => Ok(db.read().export_graph(level, sample)),
+        }
+    }
+
+    /// Cluster summary with at most `k` clusters; see [`summary`](crate::summary)
+    ///
+    /// Summaries are cached per `k` and computed again after a write.
+    pub fn summary(&self, k: usize) -> Result<Arc<CollectionSummary>> {
+        summary::validate_k(k)?;
+        let write_seq = self.write_seq();
+        if let Some(cached) = self.summaries.read().get(&k) {
+            if cached.write_seq == write_seq && cached.vector_count == self.len() {
+                return Ok(cached.clone());
+            }
+        }
+        let computed = Arc::new(summary::summarize(
+            || self.scan(),
+            k,
+            self.distance_metric(),
+            write_seq,
+        )?);
+        self.summaries.write().insert(k, computed.clone());
+        Ok(computed)
+    }
+
+    /// Node and link counts of each HNSW layer, or `None` when the
+    /// collection isn't indexed with HNSW
+    pub fn graph_stats(&self) -> Option<GraphStats> {
+        match &self.backend {
+            Backend::Standard(db) => db.read().graph_stats(),
+            Backend::Quantized(db) => Some(db.read().graph_stats()),
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => db.read().graph_stats(),
+        }
+    }
+
+    /// Relink up to `budget` HNSW nodes left linking to deleted or missing
+    /// nodes, those in the most searched regions first
+    ///
+    /// Deletions leave a node's links to the deleted vector in place until
+    /// compaction, so searches around it slowly degrade; after a crash the
+    /// graph may also hold broken links. Runs under the collection's read
+    /// lock, taking the graph's write lock one node at a time. Collections
+    /// not indexed with HNSW have nothing to repair.
+    pub fn repair_graph(&self, budget: usize) -> GraphRepair {
+        match &self.backend {
+            Backend::Standard(db) => db.read().repair_graph(budget),
+            Backend::Quantized(db) => db.read().repair_graph(budget),
+            #[cfg(feature = "persistence")]
+            Backend::Persistent(db) => db.read().repair_graph(budget),
+        }
+    }
+
+    /// Estimate recall@k of approximate search
+    ///
+    /// Uses up to `sample_size` stored vectors as queries and compares the
+    /// indexed results against an exact scan. Returns `None` for an empty
+    /// collection. Cost is O(sample_size * n), so run it off the hot path.
+    pub fn estimate_recall(&self, sample_size: usize, k: usize) -> Result<Option<f64>> {
+        let metric = self.distance_metric();
+        let vectors: Vec<(VectorId, Vec<f32>)> = self
+            .list(0, usize::MAX)
+            .into_iter()
+            .filter_map(|(id, _)| {
+                let (vector, _) = self.get(&id.as_str()).ok()??;
+                Some((id, vector))
+            })
+            .collect();
+
+        let k = k.min(vectors.len());
+        if k == 0 || sample_size == 0 {
+            return Ok(None);
+        }
+
+        let mut rng = rand::thread_rng();
+        let queries: Vec<&(VectorId, Vec<f32>)> =
+            vectors.choose_multiple(&mut rng, sample_size).collect();
+
+        let mut found = 0usize;
+        for (_, query) in &queries {
+            let mut exact: Vec<(&VectorId, f32)> = vectors
+                .iter()
+                .map(|(id, v)| (id, metric.distance(query, v)))
+                .collect();
+            exact.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+            let truth: std::collections::HashSet<&VectorId> =
+                exact.into_iter().take(k).map(|(id, _)| id).collect();
+
+            found += self
+                .ids_with_params(query, k, None, SearchParams::default())?
+                .0
+                .iter()
+                .filter(|(id, _)| truth.contains(id))
+                .count();
+        }
+
+        Ok(Some(found as f64 / (queries.len() * k) as f64))
+    }
+
+    /// Estimate recall@k of searches filtered by `filter`
+    ///
+    /// Uses up to `sample_size` records matching `filter` as queries and
+    /// compares filtered graph search against an exact scan of the matching
+    /// records. An exact scan is recommended when recall falls short of
+    /// `target_recall`, or when it would measure fewer vectors than graph
+    /// search did. Returns `None` if no record matches. Cost is
+    /// O(n + sample_size * matches), so run it off the hot path.
+    pub fn estimate_filter_recall(
+        &self,
+        filter: &crate::filter::Filter,
+        sample_size: usize,
+        k: usize,
+        target_recall: f64,
+    ) -> Result<Option<FilterRecall>> {
+        filter.validate()?;
+        filtered(Some(filter), || {
+            let metric = self.distance_metric();
+            let matching: Vec<(VectorId, Vec<f32>)> = self
+                .list(0, usize::MAX)
+                .into_iter()
+                .filter(|(_, metadata)| metadata.as_ref().is_some_and(|m| filter.matches(m)))
+                .filter_map(|(id, _)| {
+                    let (vector, _) = self.get(&id.as_str()).ok()??;
+                    Some((id, vector))
+                })
+                .collect();
+
+            let k = k.min(matching.len());
+            if k == 0 || sample_size == 0 {
+                return Ok(None);
+            }
+
+            let mut rng = rand::thread_rng();
+            let queries: Vec<&(VectorId, Vec<f32>)> =
+                matching.choose_multiple(&mut rng, sample_size).collect();
+
+            let mut found = 0usize;
+            let mut scanned = 0u64;
+            for (_, query) in &queries {
+                let mut exact: Vec<(&VectorId, f32)> = matching
+                    .iter()
+                    .map(|(id, v)| (id, metric.distance(query, v)))
+                    .collect();
+                exact.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+                let truth: std::collections::HashSet<&VectorId> =
+                    exact.into_iter().take(k).map(|(id, _)| id).collect();
+
+                let (hits, usage) =
+                    self.ids_with_params(query, k, Some(filter), SearchParams::default())?;
+                found += hits.iter().filter(|(id, _)| truth.contains(id)).count();
+                scanned += usage.vectors_scanned;
+            }
+
+            let recall = found as f64 / (queries.len() * k) as f64;
+            let mean_vectors_scanned = scanned as f64 / queries.len() as f64;
+            let recommended =
+                if recall < target_recall || (matching.len() as f64) < mean_vectors_scanned {
+                    FilterStrategy::Exact
+                } else {
+                    FilterStrategy::Graph
+                };
+            Ok(Some(FilterRecall {
+                matches: matching.len(),
+                queries: queries.len(),
+                k,
+                recall,
+                mean_vectors_scanned,
+                recommended,
+            }))
+        })
+    }
+
+    /// Sweep HNSW parameters on a random sample of the collection
+    ///
+    /// See [`crate::tune::sweep`]; the collection itself is left untouched.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn tune(&self, options: &crate::tune::TuneOptions) -> Result<crate::tune::TuneReport> {
+        let ids = self.list(0, usize::MAX);
+        let mut rng = rand::thread_rng();
+        let vectors: Vec<Vec<f32>> = ids
+            .choose_multiple(&mut rng, options.sample_size + options.queries)
+            .filter_map(|(id, _)| Some(self.get(&id.as_str()).ok()??.0))
+            .collect();
+        crate::tune::sweep(self.distance_metric(), vectors, options)
+    }
+
+    /// Change the candidate list size searches use by default
+    pub fn set_ef_search(&self, ef_search: usize) {
+        match &self.backend {
+            Backend::Standard(db) => db.write().set_ef_search(ef_search),
+            Backend::Quantized(db) => db.write().set_ef_search(ef_search),
             #[cfg(feature = "persistence")]
-            Collection::Persistent(db) => db.write().insert(id, vector, metadata),
+            Backend::Persistent(db) => db.write().set_ef_search(ef_search),
         }
     }
 
-    pub fn upsert(&self, id: String, vector: &[f32], metadata: Option<Value>) -> Result<()> {
-        match self {
-            Collection::Standard(db) => db.write().upsert(id, vector, metadata),
-            Collection::Quantized(db) => db.write().upsert(id, vector, metadata),
+    /// Whether the collection is stored on disk, so its quantization can be
+    /// changed with [`Database::update_collection`]
+    pub fn on_disk(&self) -> bool {
+        match &self.backend {
             #[cfg(feature = "persistence")]
-            Collection::Persistent(db) => {
-                let mut db = db.write();
-                let _ = db.delete(id.clone());
-                db.insert(id, vector, metadata)
-            }
+            Backend::Persistent(_) => true,
+            _ => false,
         }
     }
 
-    pub fn upsert_batch(&self, items: Vec<(String, Vec<f32>, Option<Value>)>) -> Result<()> {
-        match self {
-            Collection::Standard(db) => {
-                let items_converted: Vec<(VectorId, Vec<f32>, Option<Value>)> = items
-                    .into_iter()
-                    .map(|(id, vec, meta)| (VectorId::from(id), vec, meta))
-                    .collect();
-                db.write().upsert_batch(items_converted)
-            }
-            Collection::Quantized(db) => {
-                let items_converted: Vec<(VectorId, Vec<f32>, Option<Value>)> = items
-                    .into_iter()
-                    .map(|(id, vec, meta)| (VectorId::from(id), vec, meta))
-                    .collect();
-                db.write().upsert_batch(items_converted)
-            }
-            #[cfg(feature = "persistence")]
-            Collection::Persistent(db) => {
-                let mut db = db.write();
-                for (id, vector, metadata) in items {
-                    let _ = db.delete(id.clone());
-                    db.insert(id, &vector, metadata)?;
-                }
-                Ok(())
-            }
+    /// Re-encode an on-disk collection with `quantization`, rebuilding its
+    /// graph; writes and searches wait for the rebuild
+    #[cfg(feature = "persistence")]
+    fn set_quantization(&self, quantization: QuantizationType) -> Result<()> {
+        let _job = self.begin_job();
+        match &self.backend {
+            Backend::Persistent(db) => db.write().set_quantization(quantization),
+            _ => Err(Error::InvalidConfig(
+                "Quantization can only be changed on on-disk collections".to_string(),
+            )),
         }
     }
 
-    pub fn delete(&self, id: &str) -> Result<bool> {
-        match self {
-            Collection::Standard(db) => db.write().delete(id),
-            Collection::Quantized(db) => db.write().delete(id),
+    /// Checkpoint an on-disk collection with writes since its last
+    /// checkpoint, so none are left only in the WAL; returns whether it took
+    /// a checkpoint
+    ///
+    /// Waits for running writes and jobs. In-memory collections have nothing
+    /// to flush. See [`PersistentVectorDb::flush`](crate::PersistentVectorDb::flush).
+    pub fn flush(&self) -> Result<bool> {
+        match &self.backend {
             #[cfg(feature = "persistence")]
-            Collection::Persistent(db) => db.write().delete(id),
+            Backend::Persistent(db) => db.write().flush(),
+            _ => Ok(false),
         }
     }
 
-    pub fn get(&self, id: &str) -> Result<Option<(Vec<f32>, Option<Value>)>> {
-        match self {
-            Collection::Standard(db) => db.read().get(id),
-            Collection::Quantized(db) => db.read().get(id),
+    /// Check that the collection's IDs, vectors and graph agree with each other
+    ///
+    /// Only on-disk collections are checked, as they are the ones a crash or
+    /// a damaged file can leave inconsistent.
+    pub fn verify(&self) -> Result<IntegrityReport> {
+        match &self.backend {
             #[cfg(feature = "persistence")]
-            Collection::Persistent(db) => db.read().get(id),
+            Backend::Persistent(db) => Ok(db.read().verify()),
+            _ => Err(Error::InvalidConfig(
+                "Only on-disk collections can be verified".to_string(),
+            )),
         }
     }
 
-    pub fn search(
+    /// Write the collection's configuration, vectors, metadata and graph to
+    /// one file at `path`; returns the number of vectors written
+    ///
+    /// The snapshot is taken under one read lock, so it is consistent, and
+    /// can be restored into any database with [`Database::restore`].
+    #[cfg(feature = "persistence")]
+    pub fn snapshot(&self, path: impl AsRef<std::path::Path>) -> Result<usize> {
+        let snapshot = match &self.backend {
+            Backend::Standard(db) => db.read().export_snapshot(),
+            Backend::Quantized(db) => db.read().export_snapshot(),
+            Backend::Persistent(db) => db.read().export_snapshot(),
+        };
+        crate::snapshot::write_collection(path, &self.config(), &snapshot)?;
+        Ok(snapshot.len())
+    }
+
+    /// Write a snapshot like [`snapshot`](Self::snapshot) and return the
+    /// position of the write log it covers
+    ///
+    /// A follower restores the snapshot, then replays the log from there with
+    /// [`log_after`](Self::log_after). Only on-disk collections keep a log.
+    #[cfg(feature = "persistence")]
+    pub fn snapshot_at(&self, path: impl AsRef<std::path::Path>) -> Result<crate::LogPosition> {
+        let Backend::Persistent(db) = &self.backend else {
+            return Err(Self::unlogged());
+        };
+        let (snapshot, position) = {
+            let db = db.read();
+            (db.export_snapshot(), db.log_position())
+        };
+        crate::snapshot::write_collection(path, &self.config(), &snapshot)?;
+        Ok(position)
+    }
+
+    /// Where the collection's write log stands; only on-disk collections keep one
+    #[cfg(feature = "persistence")]
+    pub fn log_position(&self) -> Result<crate::LogPosition> {
+        match &self.backend {
+            Backend::Persistent(db) => Ok(db.read().log_position()),
+            _ => Err(Self::unlogged()),
+        }
+    }
+
+    /// Up to `limit` entries of the write log after `from`, or `None` if
+    /// they are no longer in it
+    ///
+    /// See [`PersistentVectorDb::log_after`](crate::PersistentVectorDb::log_after).
+    #[cfg(feature = "persistence")]
+    pub fn log_after(
         &self,
-        query: &[f32],
-        k: usize,
-        filter: Option<&crate::filter::Filter>,
-    ) -> Result<Vec<(VectorId, f32, Option<Value>)>> {
-        match self {
-            Collection::Standard(db) => db.read().search(query, k, filter),
-            Collection::Quantized(db) => db.read().search(query, k, filter),
-            #[cfg(feature = "persistence")]
-            Collection::Persistent(db) => db.read().search(query, k, filter),
+        from: crate::LogPosition,
+        limit: usize,
+    ) -> Result<Option<Vec<(u64, crate::WalEntry)>>> {
+        match &self.backend {
+            Backend::Persistent(db) => db.read().log_after(from, limit),
+            _ => Err(Self::unlogged()),
         }
     }
 
-    pub fn search_ids(
+    /// Write entries read from another collection's log, in order
+    ///
+    /// See [`PersistentVectorDb::apply_logged`](crate::PersistentVectorDb::apply_logged).
+    #[cfg(feature = "persistence")]
+    pub fn apply_log(&self, entries: Vec<crate::WalEntry>) -> Result<()> {
+        let write = self.versions.begin_bulk();
+        match &self.backend {
+            Backend::Persistent(db) => db.write().apply_logged(entries),
+            _ => Err(Self::unlogged()),
+        }?;
+        write.written_all(self.write_seq());
+        Ok(())
+    }
+
+    #[cfg(feature = "persistence")]
+    fn unlogged() -> Error {
+        Error::InvalidConfig("Only on-disk collections keep a write log".to_string())
+    }
+
+    /// Write the collection's records to a Parquet file at `path`, returning
+    /// how many were written
+    ///
+    /// See [`crate::parquet_io`] for the layout.
+    #[cfg(feature = "parquet")]
+    pub fn export_parquet(&self, path: impl AsRef<std::path::Path>) -> Result<usize> {
+        let snapshot = match &self.backend {
+            Backend::Standard(db) => db.read().export_snapshot(),
+            Backend::Quantized(db) => db.read().export_snapshot(),
+            Backend::Persistent(db) => db.read().export_snapshot(),
+        };
+        crate::parquet_io::write(path, snapshot.dimensions, &snapshot.vectors)?;
+        Ok(snapshot.len())
+    }
+
+    /// Upsert the records of the Parquet file at `path`, returning how many
+    /// were imported
+    ///
+    /// Records are stored a batch at a time, so if a batch fails the ones
+    /// before it stay imported.
+    #[cfg(feature = "parquet")]
+    pub fn import_parquet(&self, path: impl AsRef<std::path::Path>) -> Result<usize> {
+        let _job = self.begin_job();
+        let mut imported = 0;
+        for batch in crate::parquet_io::read(path, PARQUET_IMPORT_BATCH_SIZE)? {
+            let batch = batch?;
+            let len = batch.len();
+            self.upsert_batch(batch)?;
+            imported += len;
+        }
+        Ok(imported)
+    }
+
+    #[cfg(feature = "persistence")]
+    fn restore(&self, snapshot: crate::snapshot::Snapshot) -> Result<()> {
+        let _job = self.begin_job();
+        let write = self.versions.begin_bulk();
+        match &self.backend {
+            Backend::Standard(db) => db.write().restore(snapshot),
+            Backend::Quantized(db) => db.write().restore(snapshot),
+            Backend::Persistent(db) => db.write().restore(snapshot),
+        }?;
+        write.written_all(self.write_seq());
+        Ok(())
+    }
+
+    /// Fill the empty collection from records and precomputed
+    /// nearest-neighbor lists, such as a kNN graph built offline
+    ///
+    /// `neighbors[i]` lists the positions in `items` of record `i`'s nearest
+    /// neighbors; the graph is linked from them instead of searching for
+    /// each record's neighbors. See [`HnswIndex::build_from_neighbors`].
+    ///
+    /// [`HnswIndex::build_from_neighbors`]: crate::HnswIndex::build_from_neighbors
+    pub fn bulk_load(
         &self,
-        query: &[f32],
-        k: usize,
-        filter: Option<&crate::filter::Filter>,
-    ) -> Result<Vec<(VectorId, f32)>> {
-        match self {
-            Collection::Standard(db) => db.read().search_ids(query, k, filter),
-            Collection::Quantized(db) => db.read().search_ids(query, k, filter),
+        items: Vec<(String, Vec<f32>, Option<Value>)>,
+        neighbors: Vec<Vec<usize>>,
+    ) -> Result<()> {
+        let _job = self.begin_job();
+        let _timer = self.latency.time(Operation::Insert).records(items.len());
+        self.check_norms(items.iter().map(|(id, v, _)| (id.as_str(), v.as_slice())))?;
+        let items: Vec<(VectorId, Vec<f32>, Option<Value>)> = self
+            .written_items(items)?
+            .into_iter()
+            .map(|(id, vec, meta)| (VectorId::from(id), vec, meta))
+            .collect();
+        let write = self.versions.begin_bulk();
+        match &self.backend {
+            Backend::Standard(db) => db.write().bulk_load(items, neighbors),
+            Backend::Quantized(db) => db.write().bulk_load(items, neighbors),
             #[cfg(feature = "persistence")]
-            Collection::Persistent(db) => db.read().search_ids(query, k, filter),
-        }
+            Backend::Persistent(db) => db.write().bulk_load(items, neighbors),
+        }?;
+        write.written_all(self.write_seq());
+        Ok(())
     }
 
-    pub fn list(&self, offset: usize, limit: usize) -> Vec<(VectorId, Option<Value>)> {
-        match self {
-            Collection::Standard(db) => db.read().list(offset, limit),
-            Collection::Quantized(db) => db.read().list(offset, limit),
+    /// Space held by deleted or overwritten records
+    pub fn garbage_stats(&self) -> GarbageStats {
+        match &self.backend {
+            Backend::Standard(db) => db.read().garbage_stats(),
+            Backend::Quantized(db) => db.read().garbage_stats(),
             #[cfg(feature = "persistence")]
-            Collection::Persistent(db) => db.read().list(offset, limit),
+            Backend::Persistent(db) => db.read().garbage_stats(),
+        }
+    }
+
+    /// Rebuild the collection from its live records, reclaiming the slots
+    /// of deleted and overwritten ones
+    ///
+    /// Writes and searches wait for the rebuild, and list cursors issued
+    /// before it expire.
+    #[cfg(feature = "persistence")]
+    pub fn compact(&self) -> Result<CompactionReport> {
+        let _job = self.begin_job();
+        match &self.backend {
+            Backend::Standard(db) => db.write().compact(),
+            Backend::Quantized(db) => db.write().compact(),
+            Backend::Persistent(db) => db.write().compact(),
         }
     }
 
+    /// Latency of the collection's inserts, searches and deletes
+    pub fn latency(&self) -> OperationLatencies {
+        self.latency.summary()
+    }
+
+    /// Operations of each of the last
+    /// [`ACTIVITY_MINUTES`](crate::activity::ACTIVITY_MINUTES) minutes,
+    /// oldest first
+    pub fn activity(&self) -> Vec<ActivityMinute> {
+        self.latency.activity()
+    }
+
     pub fn stats(&self) -> CollectionStats {
-        match self {
-            Collection::Standard(db) => {
+        match &self.backend {
+            Backend::Standard(db) => {
                 let db = db.read();
                 CollectionStats {
                     vector_count: db.len(),
+                    deleted_count: db.deleted_count(),
                     memory_usage_bytes: db.memory_usage(),
                     quantization: "None".to_string(),
                     dimensions: db.config().dimensions,
+                    id_type: db.config().id_type,
+                    memory_breakdown: db.memory_breakdown(),
+                    garbage: db.garbage_stats(),
+                    latency: self.latency.summary(),
+                    largest_payloads: db.largest_payloads(LARGEST_PAYLOADS),
+                    write_seq: db.write_seq(),
+                    log_id: None,
+                    norm_rejections: self.norm_rejections(),
                 }
             }
-            Collection::Quantized(db) => {
+            Backend::Quantized(db) => {
                 let db = db.read();
                 CollectionStats {
                     vector_count: db.len(),
+                    deleted_count: db.deleted_count(),
                     memory_usage_bytes: db.memory_usage(),
                     quantization: format!("{:?}", db.config().quantization),
                     dimensions: db.config().dimensions,
+                    id_type: db.config().id_type,
+                    memory_breakdown: db.memory_breakdown(),
+                    garbage: db.garbage_stats(),
+                    latency: self.latency.summary(),
+                    largest_payloads: db.largest_payloads(LARGEST_PAYLOADS),
+                    write_seq: db.write_seq(),
+                    log_id: None,
+                    norm_rejections: self.norm_rejections(),
                 }
             }
             #[cfg(feature = "persistence")]
-            Collection::Persistent(db) => {
+            Backend::Persistent(db) => {
                 let db = db.read();
                 let disk_usage = if let Ok(metadata) = std::fs::metadata(db.data_dir()) {
                     if metadata.is_dir() {
@@ -172,9 +1979,22 @@ impl Collection {
                 };
                 CollectionStats {
                     vector_count: db.len(),
+                    deleted_count: db.deleted_count(),
                     memory_usage_bytes: disk_usage as usize,
-                    quantization: "None".to_string(),
+                    // Only binary quantization applies to persistent collections
+                    quantization: match db.config().quantization {
+                        QuantizationType::Binary => "Binary".to_string(),
+                        _ => "None".to_string(),
+                    },
                     dimensions: db.config().dimensions,
+                    id_type: db.config().id_type,
+                    memory_breakdown: db.memory_breakdown(),
+                    garbage: db.garbage_stats(),
+                    latency: self.latency.summary(),
+                    largest_payloads: db.largest_payloads(LARGEST_PAYLOADS),
+                    write_seq: db.write_seq(),
+                    log_id: Some(db.log_position().log_id),
+                    norm_rejections: self.norm_rejections(),
                 }
             }
         }
@@ -196,10 +2016,31 @@ fn get_dir_size(path: impl AsRef<std::path::Path>) -> std::io::Result<u64> {
     Ok(size)
 }
 
+/// File in the database directory holding the alias map
+#[cfg(feature = "persistence")]
+const ALIASES_FILE: &str = "aliases.json";
+
+/// WAL entries replayed per collection write lock during recovery
+#[cfg(feature = "persistence")]
+const REPLAY_CHUNK: usize = 1000;
+
 pub struct Database {
     collections: RwLock<HashMap<String, Collection>>,
+    /// Alternative names resolving to a collection, alias -> collection
+    aliases: RwLock<HashMap<String, String>>,
+    /// Bumped whenever a collection or alias is added, removed or reconfigured
+    catalog_version: AtomicU64,
+    recovery: RecoveryProgress,
     #[cfg(feature = "persistence")]
     path: Option<std::path::PathBuf>,
+    /// Deleted on-disk collections whose files are still open, by name
+    #[cfg(feature = "persistence")]
+    dropping: RwLock<HashMap<String, std::sync::Weak<Lifecycle>>>,
+    /// How damaged WAL records are handled when collections are opened
+    #[cfg(feature = "persistence")]
+    recovery_mode: RecoveryMode,
+    /// Case policy applied to every collection and alias name given
+    name_case: RwLock<NameCase>,
 }
 
 impl Default for Database {
@@ -212,75 +2053,361 @@ impl Database {
     pub fn new() -> Self {
         Self {
             collections: RwLock::new(HashMap::new()),
+            aliases: RwLock::new(HashMap::new()),
+            catalog_version: AtomicU64::new(0),
+            recovery: RecoveryProgress::default(),
             #[cfg(feature = "persistence")]
             path: None,
+            #[cfg(feature = "persistence")]
+            dropping: RwLock::new(HashMap::new()),
+            #[cfg(feature = "persistence")]
+            recovery_mode: RecoveryMode::default(),
+            name_case: RwLock::new(NameCase::default()),
         }
     }
 
     #[cfg(feature = "persistence")]
     pub fn open(path: impl AsRef<std::path::Path>) -> Result<Self> {
-        let path = path.as_ref().to_path_buf();
-        std::fs::create_dir_all(&path)?;
+        Self::open_with_recovery(path, RecoveryMode::default())
+    }
 
-        let db = Self {
-            collections: RwLock::new(HashMap::new()),
-            path: Some(path.clone()),
+    /// Open the database, handling damaged WAL records as `mode` says
+    ///
+    /// Every collection's WAL is checked before it is replayed: a record torn
+    /// by a crash at the end is cut off, and damage before the end fails the
+    /// open or is removed depending on `mode`. What was removed is counted in
+    /// the [`recovery_status`](Self::recovery_status).
+    #[cfg(feature = "persistence")]
+    pub fn open_with_recovery(
+        path: impl AsRef<std::path::Path>,
+        mode: RecoveryMode,
+    ) -> Result<Self> {
+        let db = Self::open_empty(path.as_ref(), mode)?;
+        db.recover()?;
+        Ok(db)
+    }
+
+    /// Open the database and recover its collections on a background thread
+    ///
+    /// Returns immediately. [`recovery_status`](Self::recovery_status) reports
+    /// progress: collections appear as their snapshots load and can be read
+    /// while their WAL tails replay, but callers must hold off writes until
+    /// the status is ready.
+    #[cfg(all(feature = "persistence", feature = "parallel"))]
+    pub fn open_recovering(path: impl AsRef<std::path::Path>) -> Result<Arc<Self>> {
+        Self::open_recovering_with(path, RecoveryMode::default())
+    }
+
+    /// [`open_recovering`](Self::open_recovering), handling damaged WAL
+    /// records as `mode` says
+    #[cfg(all(feature = "persistence", feature = "parallel"))]
+    pub fn open_recovering_with(
+        path: impl AsRef<std::path::Path>,
+        mode: RecoveryMode,
+    ) -> Result<Arc<Self>> {
+        let db = Arc::new(Self::open_empty(path.as_ref(), mode)?);
+        db.recovery.begin(0);
+        let background = db.clone();
+        std::thread::Builder::new()
+            .name("surgedb-recovery".to_string())
+            .spawn(move || {
+                if let Err(e) = background.recover() {
+                    error!("Recovery failed: {}", e);
+                    background.recovery.fail(e.to_string());
+                }
+            })?;
+        Ok(db)
+    }
+
+    /// Treat collection and alias names according to `case` from now on
+    ///
+    /// Set it before collections are created: with [`NameCase::Lowercase`],
+    /// collections and aliases stored with upper-case letters can no longer
+    /// be found.
+    pub fn set_name_case(&self, case: NameCase) {
+        *self.name_case.write() = case;
+    }
+
+    pub fn name_case(&self) -> NameCase {
+        *self.name_case.read()
+    }
+
+    /// `name` as the database stores and looks it up
+    fn canonical<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        self.name_case.read().canonicalize(name)
+    }
+
+    /// Progress of startup recovery; always ready for in-memory databases
+    pub fn recovery_status(&self) -> RecoveryStatus {
+        self.recovery.status()
+    }
+
+    /// Database at `path` with its aliases loaded but no collections yet
+    #[cfg(feature = "persistence")]
+    fn open_empty(path: &std::path::Path, recovery_mode: RecoveryMode) -> Result<Self> {
+        std::fs::create_dir_all(path)?;
+
+        let aliases_path = path.join(ALIASES_FILE);
+        let aliases = if aliases_path.exists() {
+            let aliases_str = std::fs::read_to_string(aliases_path)?;
+            serde_json::from_str(&aliases_str).map_err(|e| Error::Deserialization {
+                message: e.to_string(),
+            })?
+        } else {
+            HashMap::new()
         };
 
+        Ok(Self {
+            collections: RwLock::new(HashMap::new()),
+            aliases: RwLock::new(aliases),
+            catalog_version: AtomicU64::new(0),
+            recovery: RecoveryProgress::default(),
+            path: Some(path.to_path_buf()),
+            dropping: RwLock::new(HashMap::new()),
+            recovery_mode,
+            name_case: RwLock::new(NameCase::default()),
+        })
+    }
+
+    /// Load every collection's snapshot, then replay their WAL tails
+    #[cfg(feature = "persistence")]
+    fn recover(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
         info!("Opening SurgeDB at {:?}", path);
 
-        for entry in std::fs::read_dir(&path)? {
+        let mut dirs = Vec::new();
+        for entry in std::fs::read_dir(path)? {
             let entry = entry?;
-            if entry.file_type()?.is_dir() {
-                let name = entry.file_name().to_string_lossy().into_owned();
-                let meta_path = entry.path().join("metadata.json");
-                if meta_path.exists() {
-                    debug!("Recovering collection: {}", name);
-                    let meta_str = std::fs::read_to_string(meta_path)?;
-                    let config: Config =
-                        serde_json::from_str(&meta_str).map_err(|e| Error::Serialization {
-                            message: e.to_string(),
-                        })?;
-                    let p_config = crate::persistent::PersistentConfig {
-                        dimensions: config.dimensions,
-                        distance_metric: config.distance_metric,
-                        hnsw: config.hnsw.clone(),
-                        ..Default::default()
-                    };
-                    let p_db = crate::persistent::PersistentVectorDb::open(entry.path(), p_config)?;
-                    info!("Collection {} recovered with {} vectors", name, p_db.len());
-                    db.collections
-                        .write()
-                        .insert(name, Collection::Persistent(Arc::new(RwLock::new(p_db))));
-                }
+            if entry.file_type()?.is_dir() && entry.path().join("metadata.json").exists() {
+                dirs.push((
+                    entry.file_name().to_string_lossy().into_owned(),
+                    entry.path(),
+                ));
             }
         }
-        Ok(db)
+        self.recovery.begin(dirs.len());
+
+        #[cfg(feature = "parallel")]
+        let pending: Vec<_> = dirs
+            .into_par_iter()
+            .map(|(name, dir)| self.load_collection(name, &dir))
+            .collect::<Result<_>>()?;
+        #[cfg(not(feature = "parallel"))]
+        let pending: Vec<_> = dirs
+            .into_iter()
+            .map(|(name, dir)| self.load_collection(name, &dir))
+            .collect::<Result<_>>()?;
+
+        self.recovery.begin_replay();
+        #[cfg(feature = "parallel")]
+        pending
+            .into_par_iter()
+            .try_for_each(|(name, db, tail)| self.replay_collection(&name, &db, tail))?;
+        #[cfg(not(feature = "parallel"))]
+        pending
+            .into_iter()
+            .try_for_each(|(name, db, tail)| self.replay_collection(&name, &db, tail))?;
+
+        self.recovery.finish();
+        Ok(())
+    }
+
+    /// Open a collection from its snapshot and make it readable
+    #[cfg(feature = "persistence")]
+    fn load_collection(
+        &self,
+        name: String,
+        dir: &std::path::Path,
+    ) -> Result<(
+        String,
+        Arc<RwLock<crate::persistent::PersistentVectorDb>>,
+        crate::persistent::PendingReplay,
+    )> {
+        debug!("Recovering collection: {}", name);
+        let meta_str = std::fs::read_to_string(dir.join("metadata.json"))?;
+        let config: Config = serde_json::from_str(&meta_str).map_err(|e| Error::Serialization {
+            message: e.to_string(),
+        })?;
+        let p_config = crate::persistent::PersistentConfig {
+            dimensions: config.dimensions,
+            distance_metric: config.distance_metric,
+            hnsw: config.hnsw.clone(),
+            id_type: config.id_type,
+            metadata_compression: config.metadata_compression,
+            partition_field: config.partition_field.clone(),
+            text_fields: config.text_fields.clone(),
+            group_commit: config.group_commit,
+            quantization: config.quantization,
+            named_vectors: config.named_vectors.clone(),
+            index: config.index,
+            metadata_limits: config.metadata_limits,
+            recovery_mode: self.recovery_mode,
+            ..Default::default()
+        };
+        let (p_db, tail) = crate::persistent::PersistentVectorDb::open_deferred(dir, p_config)?;
+        self.recovery.wal_repaired(p_db.wal_repair());
+        self.recovery.collection_loaded(tail.len());
+
+        let p_db = Arc::new(RwLock::new(p_db));
+        self.collections.write().insert(
+            name.clone(),
+            Collection::new(
+                Backend::Persistent(p_db.clone()),
+                config.transform,
+                config.norm_filter,
+                (config.dtype, config.dtype_conversion),
+            ),
+        );
+        self.bump_catalog();
+        Ok((name, p_db, tail))
+    }
+
+    /// Replay a collection's WAL tail in chunks, so reads can interleave
+    #[cfg(feature = "persistence")]
+    fn replay_collection(
+        &self,
+        name: &str,
+        db: &RwLock<crate::persistent::PersistentVectorDb>,
+        mut tail: crate::persistent::PendingReplay,
+    ) -> Result<()> {
+        loop {
+            let applied = db.write().replay(&mut tail, REPLAY_CHUNK)?;
+            if applied == 0 {
+                break;
+            }
+            self.recovery.replayed(applied);
+        }
+        // Vectors not written since share the sequence recovery ended at.
+        // The sequence is read first, as the version table's lock must not
+        // be taken while holding the collection's (see `versions`)
+        let versions = self
+            .collections
+            .read()
+            .get(name)
+            .map(|collection| collection.versions.clone());
+        if let Some(versions) = versions {
+            let seq = db.read().write_seq();
+            versions.begin_bulk().written_all(seq);
+        }
+        info!(
+            "Collection {} recovered with {} vectors",
+            name,
+            db.read().len()
+        );
+        Ok(())
     }
 
+    /// Create a collection; `name` must follow the
+    /// [naming rules](crate::naming)
     pub fn create_collection(&self, name: &str, config: Config) -> Result<()> {
+        let name = self.canonical(name);
+        let name = name.as_ref();
+        validate_name(name)?;
+        self.insert_collection(name, config)
+    }
+
+    /// System collection `name`, created on first use
+    ///
+    /// System collections hold records the database and server keep about
+    /// themselves, such as usage. Their names start with one of the
+    /// [reserved prefixes](crate::naming::RESERVED_PREFIXES), so no user
+    /// collection or alias can take them. Records carry their data as
+    /// metadata, with a one-dimensional zero vector, and are scanned rather
+    /// than indexed. System collections are left out of
+    /// [`list_collections`](Self::list_collections) and the database stats,
+    /// but otherwise behave like any other collection, so snapshots and
+    /// exports work on them.
+    pub fn system_collection(&self, name: &str) -> Result<Collection> {
+        if !is_reserved(name) {
+            return Err(Error::InvalidCollectionName {
+                name: name.to_string(),
+                reason: "is not a system collection name".to_string(),
+            });
+        }
+        let config = Config::builder(1)
+            .distance_metric(DistanceMetric::Euclidean)
+            .index(IndexKind::Flat)
+            .build()?;
+        match self.insert_collection(name, config) {
+            Ok(()) | Err(Error::DuplicateCollection(_)) => {}
+            Err(e) => return Err(e),
+        }
+        self.get_collection(name)
+    }
+
+    /// Names of the system collections created so far
+    pub fn list_system_collections(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .collections
+            .read()
+            .keys()
+            .filter(|name| is_reserved(name))
+            .cloned()
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Add collection `name`, already checked against the naming rules
+    fn insert_collection(&self, name: &str, config: Config) -> Result<()> {
         let mut collections = self.collections.write();
-        if collections.contains_key(name) {
+        if collections.contains_key(name) || self.aliases.read().contains_key(name) {
             return Err(Error::DuplicateCollection(name.to_string()));
         }
+        config.hnsw.validate()?;
+        NamedVectors::validate(&config.named_vectors)?;
+        if let Some(transform) = &config.transform {
+            transform.validate(config.dimensions)?;
+        }
+        if let Some(filter) = &config.norm_filter {
+            filter.validate()?;
+        }
 
         #[cfg(feature = "persistence")]
         let collection = if let Some(base_path) = &self.path {
+            // A deleted collection's files stay until its last handle is gone
+            let mut dropping = self.dropping.write();
+            dropping.retain(|_, lifecycle| lifecycle.strong_count() > 0);
+            if dropping.contains_key(name) {
+                return Err(Error::CollectionBusy(name.to_string()));
+            }
+            drop(dropping);
             let col_path = base_path.join(name);
+            // Left by a delete the process didn't live to finish
+            if col_path.exists() && !col_path.join("metadata.json").exists() {
+                std::fs::remove_dir_all(&col_path)?;
+            }
             std::fs::create_dir_all(&col_path)?;
             let meta_path = col_path.join("metadata.json");
             let meta_json = serde_json::to_string(&config).map_err(|e| Error::Serialization {
                 message: e.to_string(),
             })?;
             std::fs::write(meta_path, meta_json)?;
+            let transform = config.transform.clone();
             let p_config = crate::persistent::PersistentConfig {
                 dimensions: config.dimensions,
                 distance_metric: config.distance_metric,
                 hnsw: config.hnsw,
+                id_type: config.id_type,
+                metadata_compression: config.metadata_compression,
+                partition_field: config.partition_field.clone(),
+                text_fields: config.text_fields.clone(),
+                group_commit: config.group_commit,
+                quantization: config.quantization,
+                named_vectors: config.named_vectors,
+                index: config.index,
+                metadata_limits: config.metadata_limits,
                 ..Default::default()
             };
             let p_db = crate::persistent::PersistentVectorDb::open(col_path, p_config)?;
-            Collection::Persistent(Arc::new(RwLock::new(p_db)))
+            Collection::new(
+                Backend::Persistent(Arc::new(RwLock::new(p_db))),
+                transform,
+                config.norm_filter,
+                (config.dtype, config.dtype_conversion),
+            )
         } else {
             Self::create_in_memory_collection(config)?
         };
@@ -289,53 +2416,429 @@ impl Database {
         let collection = Self::create_in_memory_collection(config)?;
 
         collections.insert(name.to_string(), collection);
+        self.bump_catalog();
         Ok(())
     }
 
+    /// Create a collection unless a collection or alias named `name` already
+    /// exists; returns whether it was created
+    ///
+    /// An existing collection is left as it is, even if `config` differs.
+    pub fn create_collection_if_missing(&self, name: &str, config: Config) -> Result<bool> {
+        match self.create_collection(name, config) {
+            Ok(()) => Ok(true),
+            Err(Error::DuplicateCollection(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
     fn create_in_memory_collection(config: Config) -> Result<Collection> {
+        let transform = config.transform.clone();
+        let norm_filter = config.norm_filter;
+        let dtype = (config.dtype, config.dtype_conversion);
         if config.quantization == QuantizationType::None {
             let db = VectorDb::new(config)?;
-            Ok(Collection::Standard(Arc::new(RwLock::new(db))))
+            Ok(Collection::new(
+                Backend::Standard(Arc::new(RwLock::new(db))),
+                transform,
+                norm_filter,
+                dtype,
+            ))
         } else {
+            if config.partition_field.is_some() {
+                return Err(Error::InvalidConfig(
+                    "partition_field is not supported for quantized collections".to_string(),
+                ));
+            }
+            if !config.text_fields.is_empty() {
+                return Err(Error::InvalidConfig(
+                    "text_fields is not supported for quantized collections".to_string(),
+                ));
+            }
+            if !config.named_vectors.is_empty() {
+                return Err(Error::InvalidConfig(
+                    "named_vectors is not supported for quantized collections".to_string(),
+                ));
+            }
+            if config.index != IndexKind::Hnsw {
+                return Err(Error::InvalidConfig(
+                    "Quantized collections are always indexed with HNSW".to_string(),
+                ));
+            }
             let q_config = QuantizedConfig {
                 dimensions: config.dimensions,
                 distance_metric: config.distance_metric,
                 hnsw: config.hnsw,
                 quantization: config.quantization,
-                keep_originals: false,
+                // Sign codes alone rank too coarsely, so binary always rescores
+                keep_originals: config.quantization == QuantizationType::Binary,
                 rerank_multiplier: 3,
+                id_type: config.id_type,
+                metadata_compression: config.metadata_compression,
+                metadata_limits: config.metadata_limits,
             };
             let db = QuantizedVectorDb::new(q_config)?;
-            Ok(Collection::Quantized(Arc::new(RwLock::new(db))))
+            Ok(Collection::new(
+                Backend::Quantized(Arc::new(RwLock::new(db))),
+                transform,
+                norm_filter,
+                dtype,
+            ))
         }
     }
 
+    /// Delete a collection; fails while an alias still points to it, or
+    /// with [`Error::CollectionBusy`] while it has a job running
+    ///
+    /// Handles already taken keep working. An on-disk collection's files are
+    /// removed once the last of them is dropped; until then no collection of
+    /// the same name can be created.
     pub fn delete_collection(&self, name: &str) -> Result<()> {
+        self.remove_collection(name, false)
+    }
+
+    /// [`delete_collection`](Self::delete_collection) even while a job is
+    /// running; the job finishes on its own handle and its work is lost
+    pub fn force_delete_collection(&self, name: &str) -> Result<()> {
+        self.remove_collection(name, true)
+    }
+
+    fn remove_collection(&self, name: &str, force: bool) -> Result<()> {
+        let name = self.canonical(name);
+        let name = name.as_ref();
         let mut collections = self.collections.write();
-        if collections.remove(name).is_some() {
-            #[cfg(feature = "persistence")]
-            if let Some(base_path) = &self.path {
-                let col_path = base_path.join(name);
-                if col_path.exists() {
-                    let _ = std::fs::remove_dir_all(col_path);
-                }
+        if let Some((alias, _)) = self.aliases.read().iter().find(|(_, c)| *c == name) {
+            return Err(Error::InvalidConfig(format!(
+                "Collection {} is still referenced by alias {}",
+                name, alias
+            )));
+        }
+        let Some(collection) = collections.get(name) else {
+            return Err(Error::CollectionNotFound(name.to_string()));
+        };
+        if !force && collection.active_jobs() > 0 {
+            return Err(Error::CollectionBusy(name.to_string()));
+        }
+        let collection = collections.remove(name);
+        // Registered before the lock is released, so a create can't slip in
+        #[cfg(feature = "persistence")]
+        if let (Some(base_path), Some(collection)) = (&self.path, &collection) {
+            let col_path = base_path.join(name);
+            // Without its metadata the directory is not reopened after a crash
+            let _ = std::fs::remove_file(col_path.join("metadata.json"));
+            *collection
+                .lifecycle
+                .teardown
+                .lock()
+                .unwrap_or_else(|e| e.into_inner()) = Some(col_path);
+            self.dropping
+                .write()
+                .insert(name.to_string(), Arc::downgrade(&collection.lifecycle));
+        }
+        drop(collections);
+        self.bump_catalog();
+        // The files go now, unless another handle is still alive
+        drop(collection);
+        Ok(())
+    }
+
+    /// Create collection `name` from a file written by [`Collection::snapshot`]
+    ///
+    /// The collection gets the configuration stored in the file. Returns the
+    /// number of vectors restored.
+    #[cfg(feature = "persistence")]
+    pub fn restore(&self, name: &str, path: impl AsRef<std::path::Path>) -> Result<usize> {
+        let (config, snapshot) = crate::snapshot::read_collection(path)?;
+        let count = snapshot.len();
+        self.create_collection(name, config)?;
+
+        let collection = self.get_collection(name)?;
+        if let Err(e) = collection.restore(snapshot) {
+            let _ = self.delete_collection(name);
+            return Err(e);
+        }
+        self.bump_catalog();
+        info!("Restored collection {} with {} vectors", name, count);
+        Ok(count)
+    }
+
+    /// Change the default `ef_search` of collection `name`
+    ///
+    /// Takes effect immediately and, for on-disk databases, is kept in the
+    /// collection's metadata across restarts.
+    pub fn set_ef_search(&self, name: &str, ef_search: usize) -> Result<()> {
+        self.update_collection(
+            name,
+            CollectionUpdate {
+                ef_search: Some(ef_search),
+                ..CollectionUpdate::default()
+            },
+        )
+    }
+
+    /// Rename or reconfigure collection `name`, given by name or alias
+    ///
+    /// The rename comes first, so the other changes apply under the new name.
+    /// It fails with [`Error::DuplicateCollection`] if the new name is taken
+    /// and with [`Error::CollectionBusy`] while a job is running; handles
+    /// already taken keep working. A quantization change re-encodes every
+    /// record and rebuilds the graph before returning, with writes and
+    /// searches waiting for it. For on-disk databases the changes are kept in
+    /// the collection's metadata across restarts.
+    pub fn update_collection(&self, name: &str, update: CollectionUpdate) -> Result<()> {
+        if update.ef_search == Some(0) {
+            return Err(Error::InvalidConfig(
+                "ef_search must be at least 1".to_string(),
+            ));
+        }
+        let mut name = self.resolve_name(name);
+        let collection = self.get_collection(&name)?;
+        let quantization = update
+            .quantization
+            .filter(|&q| q != collection.config().quantization);
+        if quantization.is_some() && !collection.on_disk() {
+            return Err(Error::InvalidConfig(
+                "Quantization can only be changed on on-disk collections".to_string(),
+            ));
+        }
+
+        let new_name = update.name.map(|n| self.canonical(&n).into_owned());
+        if let Some(new_name) = new_name.filter(|n| *n != name) {
+            self.rename_collection(&name, &new_name)?;
+            name = new_name;
+        }
+        if let Some(ef_search) = update.ef_search {
+            self.update_metadata(&name, |config| config.hnsw.ef_search = ef_search)?;
+            collection.set_ef_search(ef_search);
+        }
+        if let Some(transform) = update.transform {
+            collection.set_transform(transform.clone())?;
+            self.update_metadata(&name, |config| config.transform = transform)?;
+        }
+        if let Some(filter) = update.norm_filter {
+            collection.set_norm_filter(filter)?;
+            self.update_metadata(&name, |config| config.norm_filter = filter)?;
+        }
+        #[cfg(feature = "persistence")]
+        if let Some(quantization) = quantization {
+            collection.set_quantization(quantization)?;
+            self.update_metadata(&name, |config| config.quantization = quantization)?;
+            info!("Re-encoded collection {} with {:?}", name, quantization);
+        }
+        self.bump_catalog();
+        Ok(())
+    }
+
+    /// Move collection `name` and the aliases pointing to it to `new_name`
+    fn rename_collection(&self, name: &str, new_name: &str) -> Result<()> {
+        validate_name(new_name)?;
+        let mut collections = self.collections.write();
+        if collections.contains_key(new_name) || self.aliases.read().contains_key(new_name) {
+            return Err(Error::DuplicateCollection(new_name.to_string()));
+        }
+        let Some(collection) = collections.get(name).cloned() else {
+            return Err(Error::CollectionNotFound(name.to_string()));
+        };
+        if collection.active_jobs() > 0 {
+            return Err(Error::CollectionBusy(name.to_string()));
+        }
+
+        #[cfg(feature = "persistence")]
+        if let (Some(base_path), Backend::Persistent(db)) = (&self.path, &collection.backend) {
+            let mut dropping = self.dropping.write();
+            dropping.retain(|_, lifecycle| lifecycle.strong_count() > 0);
+            if dropping.contains_key(new_name) {
+                return Err(Error::CollectionBusy(new_name.to_string()));
             }
-            Ok(())
-        } else {
-            Err(Error::CollectionNotFound(name.to_string()))
+            drop(dropping);
+            let new_path = base_path.join(new_name);
+            // Left by a delete the process didn't live to finish
+            if new_path.exists() && !new_path.join("metadata.json").exists() {
+                std::fs::remove_dir_all(&new_path)?;
+            }
+            db.write().relocate(new_path)?;
+        }
+
+        collections.remove(name);
+        collections.insert(new_name.to_string(), collection);
+        let mut aliases = self.aliases.write();
+        let mut repointed = false;
+        for target in aliases.values_mut().filter(|target| *target == name) {
+            *target = new_name.to_string();
+            repointed = true;
+        }
+        if repointed {
+            if let Err(e) = self.save_aliases(&aliases) {
+                error!("Failed to save aliases after renaming {}: {}", name, e);
+            }
+        }
+        info!("Renamed collection {} to {}", name, new_name);
+        Ok(())
+    }
+
+    /// Edit the configuration kept in collection `name`'s metadata, for
+    /// on-disk databases
+    fn update_metadata(&self, name: &str, edit: impl FnOnce(&mut Config)) -> Result<()> {
+        #[cfg(feature = "persistence")]
+        if let Some(base_path) = &self.path {
+            let meta_path = base_path.join(name).join("metadata.json");
+            let mut config: Config = serde_json::from_str(&std::fs::read_to_string(&meta_path)?)
+                .map_err(|e| Error::Serialization {
+                    message: e.to_string(),
+                })?;
+            edit(&mut config);
+            let meta_json = serde_json::to_string(&config).map_err(|e| Error::Serialization {
+                message: e.to_string(),
+            })?;
+            std::fs::write(meta_path, meta_json)?;
         }
+        #[cfg(not(feature = "persistence"))]
+        let _ = (name, edit);
+        Ok(())
+    }
+
+    /// Version of the set of collections, their configurations and aliases
+    ///
+    /// Changes whenever a collection is created, deleted, restored or
+    /// reconfigured, or an alias is set or removed. Together with a
+    /// collection's [`write_seq`](Collection::write_seq) it identifies what a
+    /// read through that name sees. Starts at 0 each time the database is
+    /// opened.
+    pub fn catalog_version(&self) -> u64 {
+        self.catalog_version.load(Ordering::SeqCst)
+    }
+
+    fn bump_catalog(&self) {
+        self.catalog_version.fetch_add(1, Ordering::SeqCst);
     }
 
+    /// Get a collection by name or alias
     pub fn get_collection(&self, name: &str) -> Result<Collection> {
+        let key = self.canonical(name);
         let collections = self.collections.read();
+        let target = self.aliases.read().get(key.as_ref()).cloned();
         collections
-            .get(name)
+            .get(target.as_deref().unwrap_or(&key))
             .cloned()
             .ok_or_else(|| Error::CollectionNotFound(name.to_string()))
     }
 
+    /// Name of the collection `name` refers to, following an alias
+    pub fn resolve_name(&self, name: &str) -> String {
+        let name = self.canonical(name);
+        self.aliases
+            .read()
+            .get(name.as_ref())
+            .cloned()
+            .unwrap_or_else(|| name.into_owned())
+    }
+
+    /// Point `alias` at `collection`, creating or repointing it
+    ///
+    /// Returns the collection the alias pointed to before.
+    pub fn set_alias(&self, alias: &str, collection: &str) -> Result<Option<String>> {
+        self.update_alias(alias, collection, None)
+    }
+
+    /// Repoint `alias` at `collection` only if it still points to `expected`
+    ///
+    /// `expected` of `None` means the alias must not exist yet. Fails with
+    /// [`Error::AliasConflict`] if the alias was changed in the meantime.
+    pub fn swap_alias(
+        &self,
+        alias: &str,
+        collection: &str,
+        expected: Option<&str>,
+    ) -> Result<Option<String>> {
+        self.update_alias(alias, collection, Some(expected))
+    }
+
+    fn update_alias(
+        &self,
+        alias: &str,
+        collection: &str,
+        expected: Option<Option<&str>>,
+    ) -> Result<Option<String>> {
+        let (alias, collection) = (self.canonical(alias), self.canonical(collection));
+        let (alias, collection) = (alias.as_ref(), collection.as_ref());
+        let expected = expected.map(|e| e.map(|e| self.canonical(e)));
+        validate_name(alias)?;
+        let collections = self.collections.read();
+        if collections.contains_key(alias) {
+            return Err(Error::InvalidConfig(format!(
+                "Alias name is already used by a collection: {}",
+                alias
+            )));
+        }
+        if !collections.contains_key(collection) {
+            return Err(Error::CollectionNotFound(collection.to_string()));
+        }
+
+        let mut aliases = self.aliases.write();
+        if let Some(expected) = expected {
+            if aliases.get(alias).map(String::as_str) != expected.as_deref() {
+                return Err(Error::AliasConflict(alias.to_string()));
+            }
+        }
+        let previous = aliases.insert(alias.to_string(), collection.to_string());
+        if let Err(e) = self.save_aliases(&aliases) {
+            match &previous {
+                Some(previous) => aliases.insert(alias.to_string(), previous.clone()),
+                None => aliases.remove(alias),
+            };
+            return Err(e);
+        }
+        self.bump_catalog();
+        Ok(previous)
+    }
+
+    /// Remove an alias; the collection it points to is kept
+    pub fn delete_alias(&self, alias: &str) -> Result<String> {
+        let alias = self.canonical(alias);
+        let alias = alias.as_ref();
+        let mut aliases = self.aliases.write();
+        let target = aliases
+            .remove(alias)
+            .ok_or_else(|| Error::AliasNotFound(alias.to_string()))?;
+        if let Err(e) = self.save_aliases(&aliases) {
+            aliases.insert(alias.to_string(), target);
+            return Err(e);
+        }
+        self.bump_catalog();
+        Ok(target)
+    }
+
+    /// All aliases as `(alias, collection)` pairs, sorted by alias
+    pub fn list_aliases(&self) -> Vec<(String, String)> {
+        let mut aliases: Vec<_> = self
+            .aliases
+            .read()
+            .iter()
+            .map(|(a, c)| (a.clone(), c.clone()))
+            .collect();
+        aliases.sort();
+        aliases
+    }
+
+    fn save_aliases(&self, aliases: &HashMap<String, String>) -> Result<()> {
+        #[cfg(feature = "persistence")]
+        if let Some(base_path) = &self.path {
+            let json = serde_json::to_string(aliases)?;
+            std::fs::write(base_path.join(ALIASES_FILE), json)?;
+        }
+        #[cfg(not(feature = "persistence"))]
+        let _ = aliases;
+        Ok(())
+    }
+
+    /// Names of the collections, without system collections
     pub fn list_collections(&self) -> Vec<String> {
-        self.collections.read().keys().cloned().collect()
+        self.collections
+            .read()
+            .keys()
+            .filter(|name| !is_reserved(name))
+            .cloned()
+            .collect()
     }
 
     pub fn get_stats(&self) -> DatabaseStats {
@@ -343,7 +2846,7 @@ impl Database {
         let mut stats_map = HashMap::new();
         let mut total_vectors = 0;
         let mut total_memory = 0;
-        for (name, collection) in collections.iter() {
+        for (name, collection) in collections.iter().filter(|(name, _)| !is_reserved(name)) {
             let stats = collection.stats();
             total_vectors += stats.vector_count;
             total_memory += stats.memory_usage_bytes;
@@ -355,15 +2858,14 @@ impl Database {
             total_memory_bytes: total_memory,
         }
     }
-}
 
-impl Clone for Collection {
-    fn clone(&self) -> Self {
-        match self {
-            Collection::Standard(db) => Collection::Standard(db.clone()),
-            Collection::Quantized(db) => Collection::Quantized(db.clone()),
-            #[cfg(feature = "persistence")]
-            Collection::Persistent(db) => Collection::Persistent(db.clone()),
-        }
+    /// Recent activity of every collection but the system ones, by name
+    pub fn activity(&self) -> HashMap<String, Vec<ActivityMinute>> {
+        self.collections
+            .read()
+            .iter()
+            .filter(|(name, _)| !is_reserved(name))
+            .map(|(name, collection)| (name.clone(), collection.activity()))
+            .collect()
     }
 }
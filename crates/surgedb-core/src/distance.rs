@@ -17,9 +17,23 @@ pub enum DistanceMetric {
     /// Good for geometric similarity
     Euclidean,
 
-    /// Dot product (inner product)
-    /// Fast but requires normalized vectors for proper similarity
+    /// Dot product (inner product), as 1 - dot(a, b)
+    /// Same ranking as cosine for normalized vectors; on unnormalized vectors
+    /// this is maximum inner product search. Also accepted as `InnerProduct`
+    #[serde(alias = "InnerProduct")]
     DotProduct,
+
+    /// Manhattan distance (L1 norm)
+    /// Less dominated by a few large per-dimension differences than Euclidean
+    Manhattan,
+
+    /// Hamming distance: number of dimensions positive in exactly one vector
+    /// For binary (0/1) or sign-encoded (-1/+1) vectors
+    Hamming,
+
+    /// Jaccard distance (1 - sum(min) / sum(max))
+    /// For non-negative vectors; on 0/1 vectors this is set Jaccard distance
+    Jaccard,
 }
 
 impl DistanceMetric {
@@ -30,6 +44,38 @@ impl DistanceMetric {
             DistanceMetric::Cosine => cosine_distance(a, b),
             DistanceMetric::Euclidean => euclidean_distance(a, b),
             DistanceMetric::DotProduct => dot_product_distance(a, b),
+            DistanceMetric::Manhattan => manhattan_distance(a, b),
+            DistanceMetric::Hamming => hamming_distance(a, b),
+            DistanceMetric::Jaccard => jaccard_distance(a, b),
+        }
+    }
+
+    /// Similarity score of a `distance` under this metric; higher is closer
+    ///
+    /// Metrics defined as `1 - similarity` score the similarity itself
+    /// (cosine, dot product or Jaccard). The others score
+    /// `1 / (1 + distance)`, which lies in (0, 1].
+    pub fn score(&self, distance: f32) -> f32 {
+        match self {
+            DistanceMetric::Cosine | DistanceMetric::DotProduct | DistanceMetric::Jaccard => {
+                1.0 - distance
+            }
+            DistanceMetric::Euclidean | DistanceMetric::Manhattan | DistanceMetric::Hamming => {
+                1.0 / (1.0 + distance)
+            }
+        }
+    }
+
+    /// Largest distance that scores at least `score`, or `None` if every
+    /// distance does
+    pub fn max_distance_for_score(&self, score: f32) -> Option<f32> {
+        match self {
+            DistanceMetric::Cosine | DistanceMetric::DotProduct | DistanceMetric::Jaccard => {
+                Some(1.0 - score)
+            }
+            DistanceMetric::Euclidean | DistanceMetric::Manhattan | DistanceMetric::Hamming => {
+                (score > 0.0).then(|| 1.0 / score - 1.0)
+            }
         }
     }
 }
@@ -134,6 +180,106 @@ pub fn dot_product_distance(a: &[f32], b: &[f32]) -> f32 {
     }
 }
 
+/// Manhattan distance (L1)
+#[inline]
+pub fn manhattan_distance(a: &[f32], b: &[f32]) -> f32 {
+    #[cfg(all(target_arch = "aarch64", feature = "simd"))]
+    {
+        manhattan_distance_neon(a, b)
+    }
+
+    #[cfg(all(target_arch = "x86_64", feature = "simd"))]
+    {
+        manhattan_distance_avx(a, b)
+    }
+
+    #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+    {
+        manhattan_distance_wasm(a, b)
+    }
+
+    #[cfg(not(feature = "simd"))]
+    {
+        manhattan_distance_scalar(a, b)
+    }
+
+    #[cfg(all(
+        feature = "simd",
+        not(any(target_arch = "aarch64", target_arch = "x86_64")),
+        not(all(target_arch = "wasm32", target_feature = "simd128"))
+    ))]
+    {
+        manhattan_distance_scalar(a, b)
+    }
+}
+
+/// Hamming distance: count of dimensions where exactly one value is positive
+#[inline]
+pub fn hamming_distance(a: &[f32], b: &[f32]) -> f32 {
+    #[cfg(all(target_arch = "aarch64", feature = "simd"))]
+    {
+        hamming_distance_neon(a, b)
+    }
+
+    #[cfg(all(target_arch = "x86_64", feature = "simd"))]
+    {
+        hamming_distance_avx(a, b)
+    }
+
+    #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+    {
+        hamming_distance_wasm(a, b)
+    }
+
+    #[cfg(not(feature = "simd"))]
+    {
+        hamming_distance_scalar(a, b)
+    }
+
+    #[cfg(all(
+        feature = "simd",
+        not(any(target_arch = "aarch64", target_arch = "x86_64")),
+        not(all(target_arch = "wasm32", target_feature = "simd128"))
+    ))]
+    {
+        hamming_distance_scalar(a, b)
+    }
+}
+
+/// Weighted Jaccard distance: 1 - sum(min) / sum(max)
+/// Returns 0 when both vectors are all zeros
+#[inline]
+pub fn jaccard_distance(a: &[f32], b: &[f32]) -> f32 {
+    #[cfg(all(target_arch = "aarch64", feature = "simd"))]
+    {
+        jaccard_distance_neon(a, b)
+    }
+
+    #[cfg(all(target_arch = "x86_64", feature = "simd"))]
+    {
+        jaccard_distance_avx(a, b)
+    }
+
+    #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+    {
+        jaccard_distance_wasm(a, b)
+    }
+
+    #[cfg(not(feature = "simd"))]
+    {
+        jaccard_distance_scalar(a, b)
+    }
+
+    #[cfg(all(
+        feature = "simd",
+        not(any(target_arch = "aarch64", target_arch = "x86_64")),
+        not(all(target_arch = "wasm32", target_feature = "simd128"))
+    ))]
+    {
+        jaccard_distance_scalar(a, b)
+    }
+}
+
 // =============================================================================
 // Scalar implementations (fallback / used on non-SIMD platforms)
 // =============================================================================
@@ -180,6 +326,48 @@ fn dot_product_scalar(a: &[f32], b: &[f32]) -> f32 {
     sum
 }
 
+#[inline]
+#[allow(dead_code)]
+fn manhattan_distance_scalar(a: &[f32], b: &[f32]) -> f32 {
+    let mut sum = 0.0f32;
+    for i in 0..a.len() {
+        sum += (a[i] - b[i]).abs();
+    }
+    sum
+}
+
+#[inline]
+#[allow(dead_code)]
+fn hamming_distance_scalar(a: &[f32], b: &[f32]) -> f32 {
+    let mut count = 0u32;
+    for i in 0..a.len() {
+        if (a[i] > 0.0) != (b[i] > 0.0) {
+            count += 1;
+        }
+    }
+    count as f32
+}
+
+#[inline]
+#[allow(dead_code)]
+fn jaccard_distance_scalar(a: &[f32], b: &[f32]) -> f32 {
+    let mut min_sum = 0.0f32;
+    let mut max_sum = 0.0f32;
+    for i in 0..a.len() {
+        min_sum += a[i].min(b[i]);
+        max_sum += a[i].max(b[i]);
+    }
+    jaccard_from_sums(min_sum, max_sum)
+}
+
+#[inline]
+fn jaccard_from_sums(min_sum: f32, max_sum: f32) -> f32 {
+    if max_sum <= 0.0 {
+        return 0.0;
+    }
+    1.0 - (min_sum / max_sum)
+}
+
 // =============================================================================
 // ARM NEON implementations (Apple Silicon M1/M2/M3)
 // =============================================================================
@@ -303,6 +491,108 @@ fn dot_product_neon(a: &[f32], b: &[f32]) -> f32 {
     }
 }
 
+#[cfg(all(target_arch = "aarch64", feature = "simd"))]
+#[inline]
+fn manhattan_distance_neon(a: &[f32], b: &[f32]) -> f32 {
+    use std::arch::aarch64::*;
+
+    debug_assert_eq!(a.len(), b.len());
+
+    let n = a.len();
+    let chunks = n / 4;
+
+    unsafe {
+        let mut sum_acc = vdupq_n_f32(0.0);
+
+        for i in 0..chunks {
+            let offset = i * 4;
+            let va = vld1q_f32(a.as_ptr().add(offset));
+            let vb = vld1q_f32(b.as_ptr().add(offset));
+            sum_acc = vaddq_f32(sum_acc, vabdq_f32(va, vb));
+        }
+
+        let mut sum = vaddvq_f32(sum_acc);
+
+        // Handle remainder
+        for i in (chunks * 4)..n {
+            sum += (a[i] - b[i]).abs();
+        }
+
+        sum
+    }
+}
+
+#[cfg(all(target_arch = "aarch64", feature = "simd"))]
+#[inline]
+fn hamming_distance_neon(a: &[f32], b: &[f32]) -> f32 {
+    use std::arch::aarch64::*;
+
+    debug_assert_eq!(a.len(), b.len());
+
+    let n = a.len();
+    let chunks = n / 4;
+
+    unsafe {
+        let zero = vdupq_n_f32(0.0);
+        let mut count_acc = vdupq_n_u32(0);
+
+        for i in 0..chunks {
+            let offset = i * 4;
+            let va = vld1q_f32(a.as_ptr().add(offset));
+            let vb = vld1q_f32(b.as_ptr().add(offset));
+            // Lanes are all ones where exactly one side is positive
+            let differ = veorq_u32(vcgtq_f32(va, zero), vcgtq_f32(vb, zero));
+            count_acc = vaddq_u32(count_acc, vshrq_n_u32::<31>(differ));
+        }
+
+        let mut count = vaddvq_u32(count_acc);
+
+        // Handle remainder
+        for i in (chunks * 4)..n {
+            if (a[i] > 0.0) != (b[i] > 0.0) {
+                count += 1;
+            }
+        }
+
+        count as f32
+    }
+}
+
+#[cfg(all(target_arch = "aarch64", feature = "simd"))]
+#[inline]
+fn jaccard_distance_neon(a: &[f32], b: &[f32]) -> f32 {
+    use std::arch::aarch64::*;
+
+    debug_assert_eq!(a.len(), b.len());
+
+    let n = a.len();
+    let chunks = n / 4;
+
+    unsafe {
+        let mut min_acc = vdupq_n_f32(0.0);
+        let mut max_acc = vdupq_n_f32(0.0);
+
+        for i in 0..chunks {
+            let offset = i * 4;
+            let va = vld1q_f32(a.as_ptr().add(offset));
+            let vb = vld1q_f32(b.as_ptr().add(offset));
+            min_acc = vaddq_f32(min_acc, vminq_f32(va, vb));
+            max_acc = vaddq_f32(max_acc, vmaxq_f32(va, vb));
+        }
+
+        let mut min_sum = vaddvq_f32(min_acc);
+        let mut max_sum = vaddvq_f32(max_acc);
+
+        // Handle remainder
+        for i in (chunks * 4)..n {
+            min_sum += a[i].min(b[i]);
+            max_sum += a[i].max(b[i]);
+        }
+
+        jaccard_from_sums(min_sum, max_sum)
+    }
+}
+
 // =============================================================================
 // x86_64 AVX implementations
 // =============================================================================
@@ -465,6 +755,147 @@ unsafe fn dot_product_avx_inner(a: &[f32], b: &[f32]) -> f32 {
     sum
 }
 
+/// Horizontal sum of the eight lanes
+#[cfg(all(target_arch = "x86_64", feature = "simd"))]
+#[target_feature(enable = "avx")]
+#[inline]
+unsafe fn hsum256_ps(v: std::arch::x86_64::__m256) -> f32 {
+    use std::arch::x86_64::*;
+
+    let high = _mm256_extractf128_ps(v, 1);
+    let low = _mm256_castps256_ps128(v);
+    let sum128 = _mm_add_ps(high, low);
+    let high64 = _mm_movehl_ps(sum128, sum128);
+    let sum64 = _mm_add_ps(sum128, high64);
+    let high32 = _mm_shuffle_ps(sum64, sum64, 1);
+    _mm_cvtss_f32(_mm_add_ss(sum64, high32))
+}
+
+#[cfg(all(target_arch = "x86_64", feature = "simd"))]
+#[inline]
+fn manhattan_distance_avx(a: &[f32], b: &[f32]) -> f32 {
+    if is_x86_feature_detected!("avx") {
+        unsafe { manhattan_distance_avx_inner(a, b) }
+    } else {
+        manhattan_distance_scalar(a, b)
+    }
+}
+
+#[cfg(all(target_arch = "x86_64", feature = "simd"))]
+#[target_feature(enable = "avx")]
+#[inline]
+unsafe fn manhattan_distance_avx_inner(a: &[f32], b: &[f32]) -> f32 {
+    use std::arch::x86_64::*;
+
+    let n = a.len();
+    let chunks = n / 8;
+
+    // Clearing the sign bit gives the absolute value
+    let sign_mask = _mm256_set1_ps(-0.0);
+    let mut sum_acc = _mm256_setzero_ps();
+
+    for i in 0..chunks {
+        let offset = i * 8;
+        let va = _mm256_loadu_ps(a.as_ptr().add(offset));
+        let vb = _mm256_loadu_ps(b.as_ptr().add(offset));
+        let diff = _mm256_sub_ps(va, vb);
+        sum_acc = _mm256_add_ps(sum_acc, _mm256_andnot_ps(sign_mask, diff));
+    }
+
+    let mut sum = hsum256_ps(sum_acc);
+
+    // Handle remainder
+    for i in (chunks * 8)..n {
+        sum += (a[i] - b[i]).abs();
+    }
+
+    sum
+}
+
+#[cfg(all(target_arch = "x86_64", feature = "simd"))]
+#[inline]
+fn hamming_distance_avx(a: &[f32], b: &[f32]) -> f32 {
+    if is_x86_feature_detected!("avx") {
+        unsafe { hamming_distance_avx_inner(a, b) }
+    } else {
+        hamming_distance_scalar(a, b)
+    }
+}
+
+#[cfg(all(target_arch = "x86_64", feature = "simd"))]
+#[target_feature(enable = "avx")]
+#[inline]
+unsafe fn hamming_distance_avx_inner(a: &[f32], b: &[f32]) -> f32 {
+    use std::arch::x86_64::*;
+
+    let n = a.len();
+    let chunks = n / 8;
+
+    let zero = _mm256_setzero_ps();
+    let mut count = 0u32;
+
+    for i in 0..chunks {
+        let offset = i * 8;
+        let va = _mm256_loadu_ps(a.as_ptr().add(offset));
+        let vb = _mm256_loadu_ps(b.as_ptr().add(offset));
+        let pos_a = _mm256_cmp_ps(va, zero, _CMP_GT_OQ);
+        let pos_b = _mm256_cmp_ps(vb, zero, _CMP_GT_OQ);
+        // One bit per lane where exactly one side is positive
+        count += (_mm256_movemask_ps(_mm256_xor_ps(pos_a, pos_b)) as u32).count_ones();
+    }
+
+    // Handle remainder
+    for i in (chunks * 8)..n {
+        if (a[i] > 0.0) != (b[i] > 0.0) {
+            count += 1;
+        }
+    }
+
+    count as f32
+}
+
+#[cfg(all(target_arch = "x86_64", feature = "simd"))]
+#[inline]
+fn jaccard_distance_avx(a: &[f32], b: &[f32]) -> f32 {
+    if is_x86_feature_detected!("avx") {
+        unsafe { jaccard_distance_avx_inner(a, b) }
+    } else {
+        jaccard_distance_scalar(a, b)
+    }
+}
+
+#[cfg(all(target_arch = "x86_64", feature = "simd"))]
+#[target_feature(enable = "avx")]
+#[inline]
+unsafe fn jaccard_distance_avx_inner(a: &[f32], b: &[f32]) -> f32 {
+    use std::arch::x86_64::*;
+
+    let n = a.len();
+    let chunks = n / 8;
+
+    let mut min_acc = _mm256_setzero_ps();
+    let mut max_acc = _mm256_setzero_ps();
+
+    for i in 0..chunks {
+        let offset = i * 8;
+        let va = _mm256_loadu_ps(a.as_ptr().add(offset));
+        let vb = _mm256_loadu_ps(b.as_ptr().add(offset));
+        min_acc = _mm256_add_ps(min_acc, _mm256_min_ps(va, vb));
+        max_acc = _mm256_add_ps(max_acc, _mm256_max_ps(va, vb));
+    }
+
+    let mut min_sum = hsum256_ps(min_acc);
+    let mut max_sum = hsum256_ps(max_acc);
+
+    // Handle remainder
+    for i in (chunks * 8)..n {
+        min_sum += a[i].min(b[i]);
+        max_sum += a[i].max(b[i]);
+    }
+
+    jaccard_from_sums(min_sum, max_sum)
+}
+
 // =============================================================================
 // WASM SIMD128 implementations
 // =============================================================================
@@ -599,6 +1030,110 @@ fn dot_product_wasm(a: &[f32], b: &[f32]) -> f32 {
     sum
 }
 
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+#[inline]
+fn hsum_wasm(v: core::arch::wasm32::v128) -> f32 {
+    use core::arch::wasm32::*;
+
+    f32x4_extract_lane::<0>(v)
+        + f32x4_extract_lane::<1>(v)
+        + f32x4_extract_lane::<2>(v)
+        + f32x4_extract_lane::<3>(v)
+}
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+#[inline]
+fn manhattan_distance_wasm(a: &[f32], b: &[f32]) -> f32 {
+    use core::arch::wasm32::*;
+
+    debug_assert_eq!(a.len(), b.len());
+
+    let n = a.len();
+    let chunks = n / 4;
+
+    let mut sum_acc = f32x4_splat(0.0);
+
+    for i in 0..chunks {
+        let offset = i * 4;
+        let va = unsafe { v128_load(a.as_ptr().add(offset) as *const v128) };
+        let vb = unsafe { v128_load(b.as_ptr().add(offset) as *const v128) };
+        sum_acc = f32x4_add(sum_acc, f32x4_abs(f32x4_sub(va, vb)));
+    }
+
+    let mut sum = hsum_wasm(sum_acc);
+
+    // Handle remainder
+    for i in (chunks * 4)..n {
+        sum += (a[i] - b[i]).abs();
+    }
+
+    sum
+}
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+#[inline]
+fn hamming_distance_wasm(a: &[f32], b: &[f32]) -> f32 {
+    use core::arch::wasm32::*;
+
+    debug_assert_eq!(a.len(), b.len());
+
+    let n = a.len();
+    let chunks = n / 4;
+
+    let zero = f32x4_splat(0.0);
+    let mut count = 0u32;
+
+    for i in 0..chunks {
+        let offset = i * 4;
+        let va = unsafe { v128_load(a.as_ptr().add(offset) as *const v128) };
+        let vb = unsafe { v128_load(b.as_ptr().add(offset) as *const v128) };
+        let differ = v128_xor(f32x4_gt(va, zero), f32x4_gt(vb, zero));
+        count += i32x4_bitmask(differ).count_ones();
+    }
+
+    // Handle remainder
+    for i in (chunks * 4)..n {
+        if (a[i] > 0.0) != (b[i] > 0.0) {
+            count += 1;
+        }
+    }
+
+    count as f32
+}
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+#[inline]
+fn jaccard_distance_wasm(a: &[f32], b: &[f32]) -> f32 {
+    use core::arch::wasm32::*;
+
+    debug_assert_eq!(a.len(), b.len());
+
+    let n = a.len();
+    let chunks = n / 4;
+
+    let mut min_acc = f32x4_splat(0.0);
+    let mut max_acc = f32x4_splat(0.0);
+
+    for i in 0..chunks {
+        let offset = i * 4;
+        let va = unsafe { v128_load(a.as_ptr().add(offset) as *const v128) };
+        let vb = unsafe { v128_load(b.as_ptr().add(offset) as *const v128) };
+        min_acc = f32x4_add(min_acc, f32x4_min(va, vb));
+        max_acc = f32x4_add(max_acc, f32x4_max(va, vb));
+    }
+
+    let mut min_sum = hsum_wasm(min_acc);
+    let mut max_sum = hsum_wasm(max_acc);
+
+    // Handle remainder
+    for i in (chunks * 4)..n {
+        min_sum += a[i].min(b[i]);
+        max_sum += a[i].max(b[i]);
+    }
+
+    jaccard_from_sums(min_sum, max_sum)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -615,6 +1150,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_score_inverts_max_distance() {
+        let cosine = DistanceMetric::Cosine;
+        assert_float_eq(cosine.score(0.25), 0.75);
+        assert_float_eq(cosine.max_distance_for_score(0.75).unwrap(), 0.25);
+
+        let euclidean = DistanceMetric::Euclidean;
+        assert_float_eq(euclidean.score(0.0), 1.0);
+        assert_float_eq(euclidean.score(3.0), 0.25);
+        assert_float_eq(euclidean.max_distance_for_score(0.25).unwrap(), 3.0);
+        assert_eq!(euclidean.max_distance_for_score(0.0), None);
+    }
+
     #[test]
     fn test_cosine_distance_identical() {
         let a = vec![1.0, 2.0, 3.0, 4.0];
@@ -650,6 +1198,53 @@ mod tests {
         assert_float_eq(dot_product_distance(&a, &b), 0.0);
     }
 
+    #[test]
+    fn test_manhattan_distance() {
+        let a = vec![1.0, -2.0, 3.0, 0.5];
+        let b = vec![0.0, 1.0, 3.0, -0.5];
+        assert_float_eq(manhattan_distance(&a, &b), 5.0);
+    }
+
+    #[test]
+    fn test_hamming_distance() {
+        let a = vec![1.0, 0.0, 1.0, 1.0, -1.0];
+        let b = vec![1.0, 1.0, 0.0, 1.0, -1.0];
+        assert_float_eq(hamming_distance(&a, &b), 2.0);
+        assert_float_eq(hamming_distance(&a, &a), 0.0);
+    }
+
+    #[test]
+    fn test_jaccard_distance() {
+        // Sets {0, 1, 2} and {1, 2, 3}: intersection 2, union 4
+        let a = vec![1.0, 1.0, 1.0, 0.0];
+        let b = vec![0.0, 1.0, 1.0, 1.0];
+        assert_float_eq(jaccard_distance(&a, &b), 0.5);
+        assert_float_eq(jaccard_distance(&a, &a), 0.0);
+        assert_float_eq(jaccard_distance(&[0.0; 4], &[0.0; 4]), 0.0);
+    }
+
+    #[test]
+    fn test_inner_product_alias() {
+        let metric: DistanceMetric = serde_json::from_str("\"InnerProduct\"").unwrap();
+        assert_eq!(metric, DistanceMetric::DotProduct);
+    }
+
+    #[test]
+    fn test_simd_matches_scalar() {
+        // Lengths that exercise both the vector loop and the remainder
+        let a: Vec<f32> = (0..37).map(|i| ((i * 7 % 11) as f32 - 5.0) / 3.0).collect();
+        let b: Vec<f32> = (0..37).map(|i| ((i * 5 % 13) as f32 - 6.0) / 4.0).collect();
+        assert_float_eq(
+            manhattan_distance(&a, &b),
+            manhattan_distance_scalar(&a, &b),
+        );
+        assert_float_eq(hamming_distance(&a, &b), hamming_distance_scalar(&a, &b));
+
+        let a: Vec<f32> = a.iter().map(|v| v.abs()).collect();
+        let b: Vec<f32> = b.iter().map(|v| v.abs()).collect();
+        assert_float_eq(jaccard_distance(&a, &b), jaccard_distance_scalar(&a, &b));
+    }
+
     #[test]
     fn test_large_vectors() {
         // Test with 384-dimensional vectors (MiniLM size)
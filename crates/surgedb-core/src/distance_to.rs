@@ -0,0 +1,141 @@
+//! Search results bounded by their distance to a second vector
+//!
+//! A search such as "similar to the query, but not to this vector" keeps
+//! only the nearest records whose distance to another vector is within, or
+//! beyond, a threshold. The search widens until `k` records pass, so clients
+//! don't over-fetch and re-score candidates themselves. Distances use the
+//! collection's metric.
+
+use crate::distance::DistanceMetric;
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+
+/// Most candidates a bounded search examines
+///
+/// Bounds that few of the records near the query meet can return fewer than
+/// `k` results.
+pub const MAX_DISTANCE_TO_CANDIDATES: usize = 10_000;
+
+/// A vector and a distance from it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DistanceTo {
+    /// In the same space as the query
+    pub vector: Vec<f32>,
+    pub threshold: f32,
+}
+
+/// Bounds on the distance of each result to vectors other than the query
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DistanceBounds {
+    /// Keep results at most `threshold` from `vector`
+    #[serde(default)]
+    pub max_distance_to: Option<DistanceTo>,
+    /// Keep results at least `threshold` from `vector`
+    #[serde(default)]
+    pub min_distance_to: Option<DistanceTo>,
+}
+
+impl DistanceBounds {
+    pub fn is_empty(&self) -> bool {
+        self.max_distance_to.is_none() && self.min_distance_to.is_none()
+    }
+
+    /// Check the bounds fit vectors of `dimensions`
+    pub fn validate(&self, dimensions: usize) -> Result<()> {
+        for bound in self.bounds() {
+            if bound.vector.len() != dimensions {
+                return Err(Error::DimensionMismatch {
+                    expected: dimensions,
+                    got: bound.vector.len(),
+                });
+            }
+            if !bound.threshold.is_finite() || bound.vector.iter().any(|x| !x.is_finite()) {
+                return Err(Error::InvalidConfig(
+                    "Distance bounds must be finite".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether a record stored as `vector` meets every bound
+    pub fn admits(&self, metric: DistanceMetric, vector: &[f32]) -> bool {
+        let distance = |bound: &DistanceTo| metric.distance(&bound.vector, vector);
+        self.max_distance_to
+            .as_ref()
+            .is_none_or(|b| distance(b) <= b.threshold)
+            && self
+                .min_distance_to
+                .as_ref()
+                .is_none_or(|b| distance(b) >= b.threshold)
+    }
+
+    /// The bounds with their vectors mapped by `f`
+    pub(crate) fn map_vectors(
+        &self,
+        f: impl Fn(&[f32]) -> Result<Vec<f32>>,
+    ) -> Result<DistanceBounds> {
+        let map = |bound: &Option<DistanceTo>| -> Result<Option<DistanceTo>> {
+            bound
+                .as_ref()
+                .map(|b| {
+                    Ok(DistanceTo {
+                        vector: f(&b.vector)?,
+                        threshold: b.threshold,
+                    })
+                })
+                .transpose()
+        };
+        Ok(DistanceBounds {
+            max_distance_to: map(&self.max_distance_to)?,
+            min_distance_to: map(&self.min_distance_to)?,
+        })
+    }
+
+    fn bounds(&self) -> impl Iterator<Item = &DistanceTo> {
+        self.max_distance_to.iter().chain(&self.min_distance_to)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to(vector: Vec<f32>, threshold: f32) -> Option<DistanceTo> {
+        Some(DistanceTo { vector, threshold })
+    }
+
+    #[test]
+    fn test_admits() {
+        let bounds = DistanceBounds {
+            max_distance_to: to(vec![0.0, 0.0], 5.0),
+            min_distance_to: to(vec![3.0, 0.0], 1.0),
+        };
+        let metric = DistanceMetric::Euclidean;
+        assert!(bounds.admits(metric, &[0.0, 1.0]));
+        // Too close to the excluded vector
+        assert!(!bounds.admits(metric, &[3.0, 0.5]));
+        assert!(bounds.admits(metric, &[2.0, 0.0]));
+        // Too far from the required one
+        assert!(!bounds.admits(metric, &[0.0, 6.0]));
+        assert!(DistanceBounds::default().admits(metric, &[9.0, 9.0]));
+    }
+
+    #[test]
+    fn test_validate() {
+        let bounds = DistanceBounds {
+            max_distance_to: to(vec![0.0; 3], 1.0),
+            ..DistanceBounds::default()
+        };
+        assert!(bounds.validate(3).is_ok());
+        assert!(matches!(
+            bounds.validate(2),
+            Err(Error::DimensionMismatch { .. })
+        ));
+        let infinite = DistanceBounds {
+            min_distance_to: to(vec![0.0; 3], f32::NAN),
+            ..DistanceBounds::default()
+        };
+        assert!(infinite.validate(3).is_err());
+    }
+}
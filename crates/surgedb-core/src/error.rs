@@ -5,6 +5,8 @@
 
 use thiserror::Error;
 
+use crate::types::VectorDtype;
+
 /// Result type alias for SurgeDB operations
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -35,6 +37,50 @@ pub enum Error {
     #[error("Index is empty, cannot search")]
     EmptyIndex,
 
+    /// An ID does not match the collection's key type
+    #[error("Invalid vector ID: {0}")]
+    InvalidId(String),
+
+    /// Metadata is larger or more deeply nested than the collection allows
+    #[error("Metadata of {id} exceeds {limit}: {actual} > {max}")]
+    MetadataLimitExceeded {
+        id: String,
+        /// `max_bytes` or `max_depth`
+        limit: &'static str,
+        actual: usize,
+        max: usize,
+    },
+
+    /// A vector's L2 norm is outside the collection's norm filter
+    #[error("Norm of {id} is {norm}, outside the accepted range [{min}, {max}]")]
+    NormOutOfRange {
+        id: String,
+        norm: f32,
+        min: f32,
+        max: f32,
+    },
+
+    /// A written value can't be stored as the collection's dtype allows
+    #[error("Value {value} at index {index} is not a valid {dtype}")]
+    UnrepresentableValue {
+        index: usize,
+        value: f64,
+        dtype: VectorDtype,
+    },
+
+    /// A conditional write found the vector missing, present or at another
+    /// version than it expected
+    #[error(
+        "Version conflict on {id}: expected {}, found {}",
+        expected.map_or("no vector".to_string(), |v| v.to_string()),
+        actual.map_or("no vector".to_string(), |v| v.to_string())
+    )]
+    VersionConflict {
+        id: String,
+        expected: Option<u64>,
+        actual: Option<u64>,
+    },
+
     // =========================================================================
     // Configuration Errors
     // =========================================================================
@@ -50,6 +96,10 @@ pub enum Error {
         reason: &'static str,
     },
 
+    /// Filter is malformed or uses an unsupported clause
+    #[error("Invalid filter: {0}")]
+    InvalidFilter(String),
+
     // =========================================================================
     // Storage/Collection Errors
     // =========================================================================
@@ -69,6 +119,22 @@ pub enum Error {
     #[error("Storage capacity exceeded: {message}")]
     CapacityExceeded { message: String },
 
+    /// A collection has a job running, or is still being deleted
+    #[error("Collection is busy: {0}")]
+    CollectionBusy(String),
+
+    /// A collection or alias name breaks the naming rules
+    #[error("Invalid collection name {name:?}: {reason}")]
+    InvalidCollectionName { name: String, reason: String },
+
+    /// Collection alias not found
+    #[error("Alias not found: {0}")]
+    AliasNotFound(String),
+
+    /// An alias no longer points where a compare-and-swap expected it to
+    #[error("Alias was changed concurrently: {0}")]
+    AliasConflict(String),
+
     // =========================================================================
     // Persistence/WAL Errors
     // =========================================================================
@@ -160,10 +226,20 @@ impl Error {
             Error::DimensionMismatch { .. }
                 | Error::VectorNotFound(_)
                 | Error::DuplicateId(_)
+                | Error::InvalidId(_)
+                | Error::MetadataLimitExceeded { .. }
+                | Error::NormOutOfRange { .. }
+                | Error::UnrepresentableValue { .. }
+                | Error::VersionConflict { .. }
                 | Error::InvalidConfig(_)
                 | Error::InvalidHnswParam { .. }
+                | Error::InvalidFilter(_)
                 | Error::CollectionNotFound(_)
                 | Error::DuplicateCollection(_)
+                | Error::AliasNotFound(_)
+                | Error::AliasConflict(_)
+                | Error::CollectionBusy(_)
+                | Error::InvalidCollectionName { .. }
         )
     }
 
@@ -175,16 +251,26 @@ impl Error {
             Error::VectorNotFound(_) => 1002,
             Error::DuplicateId(_) => 1003,
             Error::EmptyIndex => 1004,
+            Error::InvalidId(_) => 1005,
+            Error::MetadataLimitExceeded { .. } => 1006,
+            Error::NormOutOfRange { .. } => 1007,
+            Error::UnrepresentableValue { .. } => 1008,
+            Error::VersionConflict { .. } => 1009,
 
             // Config errors: 1100-1199
             Error::InvalidConfig(_) => 1100,
             Error::InvalidHnswParam { .. } => 1101,
+            Error::InvalidFilter(_) => 1102,
 
             // Storage errors: 1200-1299
             Error::Storage(_) => 1200,
             Error::CollectionNotFound(_) => 1201,
             Error::DuplicateCollection(_) => 1202,
             Error::CapacityExceeded { .. } => 1203,
+            Error::AliasNotFound(_) => 1204,
+            Error::AliasConflict(_) => 1205,
+            Error::CollectionBusy(_) => 1206,
+            Error::InvalidCollectionName { .. } => 1207,
 
             // Persistence errors: 1300-1399
             Error::Io(_) => 1300,
@@ -226,6 +312,25 @@ impl From<serde_json::Error> for Error {
     }
 }
 
+// Conversions from the Parquet reader and writer
+#[cfg(feature = "parquet")]
+impl From<parquet::errors::ParquetError> for Error {
+    fn from(err: parquet::errors::ParquetError) -> Self {
+        Error::Serialization {
+            message: err.to_string(),
+        }
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl From<arrow_schema::ArrowError> for Error {
+    fn from(err: arrow_schema::ArrowError) -> Self {
+        Error::Serialization {
+            message: err.to_string(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -241,10 +346,41 @@ mod tests {
             Error::VectorNotFound("test".into()),
             Error::DuplicateId("test".into()),
             Error::EmptyIndex,
+            Error::InvalidId("test".into()),
+            Error::MetadataLimitExceeded {
+                id: "test".into(),
+                limit: "max_bytes",
+                actual: 2,
+                max: 1,
+            },
+            Error::NormOutOfRange {
+                id: "test".into(),
+                norm: 0.0,
+                min: 0.5,
+                max: 2.0,
+            },
+            Error::UnrepresentableValue {
+                index: 0,
+                value: 0.5,
+                dtype: VectorDtype::Int8,
+            },
+            Error::VersionConflict {
+                id: "test".into(),
+                expected: Some(1),
+                actual: None,
+            },
             Error::InvalidConfig("test".into()),
+            Error::InvalidFilter("test".into()),
             Error::Storage("test".into()),
             Error::CollectionNotFound("test".into()),
             Error::DuplicateCollection("test".into()),
+            Error::AliasNotFound("test".into()),
+            Error::AliasConflict("test".into()),
+            Error::CollectionBusy("test".into()),
+            Error::InvalidCollectionName {
+                name: "test".into(),
+                reason: "test".into(),
+            },
             Error::WalCorrupted {
                 message: "test".into(),
             },
@@ -0,0 +1,285 @@
+//! Sandboxed Rhai expressions for `Filter::Expr`
+//!
+//! Expressions see the vector's metadata as the `metadata` variable and must
+//! evaluate to a boolean, e.g. `metadata.price * metadata.qty > 100`.
+//! Each evaluation is bounded by an operation budget and a wall-clock
+//! timeout, and all the evaluations of a query run in [`scoped`] share a
+//! budget of both. Any error (runtime, limit exceeded, non-boolean result)
+//! fails the query.
+
+use crate::error::{Error, Result};
+use rhai::packages::{Package, StandardPackage};
+use rhai::{Dynamic, Engine, Scope, AST};
+use serde_json::Value;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Max Rhai operations per evaluation
+pub const MAX_OPERATIONS: u64 = 50_000;
+
+/// Max wall-clock time per evaluation
+pub const TIMEOUT: Duration = Duration::from_millis(10);
+
+/// Max Rhai operations across the evaluations of one query
+pub const MAX_QUERY_OPERATIONS: u64 = 20_000_000;
+
+/// Max wall-clock time spent evaluating expressions in one query
+pub const QUERY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Max length of an expression's source
+pub const MAX_SOURCE_LEN: usize = 4096;
+
+/// Compiled expressions kept in the cache before it is cleared
+const MAX_CACHED: usize = 1024;
+
+/// How often (in operations) the timeout is checked
+const TIMEOUT_CHECK_INTERVAL: u64 = 256;
+
+/// What a query run in [`scoped`] has left to spend on expressions
+struct QueryBudget {
+    deadline: Instant,
+    operations: u64,
+    /// First evaluation error, returned by [`scoped`]
+    error: Option<Error>,
+}
+
+impl QueryBudget {
+    fn exhausted(&self) -> bool {
+        self.operations == 0 || Instant::now() >= self.deadline
+    }
+}
+
+thread_local! {
+    /// Deadline and max operations of the evaluation running on this thread
+    static LIMITS: Cell<Option<(Instant, u64)>> = const { Cell::new(None) };
+    /// Operations used by the evaluation running on this thread
+    static USED: Cell<u64> = const { Cell::new(0) };
+    static QUERY: RefCell<Option<QueryBudget>> = const { RefCell::new(None) };
+}
+
+fn engine() -> &'static Engine {
+    static ENGINE: OnceLock<Engine> = OnceLock::new();
+    ENGINE.get_or_init(|| {
+        let mut engine = Engine::new_raw();
+        engine.register_global_module(StandardPackage::new().as_shared_module());
+        engine
+            .set_max_operations(MAX_OPERATIONS)
+            .set_max_expr_depths(64, 32)
+            .set_max_call_levels(16)
+            .set_max_string_size(64 * 1024)
+            .set_max_array_size(10_000)
+            .set_max_map_size(10_000)
+            .set_strict_variables(true);
+        engine.on_progress(|ops| {
+            USED.set(ops);
+            let (deadline, max_operations) = LIMITS.get()?;
+            if ops > max_operations {
+                return Some(Dynamic::from("operations"));
+            }
+            if ops % TIMEOUT_CHECK_INTERVAL != 0 {
+                return None;
+            }
+            (Instant::now() >= deadline).then(|| Dynamic::from("timeout"))
+        });
+        engine
+    })
+}
+
+/// Compile `source`, reusing a cached AST when available
+fn compile(source: &str) -> Result<Arc<AST>> {
+    static CACHE: OnceLock<Mutex<HashMap<String, Arc<AST>>>> = OnceLock::new();
+    let cache = CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+
+    if let Some(ast) = cache.lock().ok().and_then(|c| c.get(source).cloned()) {
+        return Ok(ast);
+    }
+
+    if source.len() > MAX_SOURCE_LEN {
+        return Err(Error::InvalidFilter(format!(
+            "expression exceeds {} bytes",
+            MAX_SOURCE_LEN
+        )));
+    }
+
+    let mut scope = Scope::new();
+    scope.push("metadata", Dynamic::UNIT);
+    let ast = engine()
+        .compile_expression_with_scope(&scope, source)
+        .map(Arc::new)
+        .map_err(|e| Error::InvalidFilter(format!("invalid expression: {}", e)))?;
+
+    if let Ok(mut c) = cache.lock() {
+        if c.len() >= MAX_CACHED {
+            c.clear();
+        }
+        c.insert(source.to_string(), ast.clone());
+    }
+    Ok(ast)
+}
+
+/// Check that `source` is a valid expression
+pub fn validate(source: &str) -> Result<()> {
+    compile(source).map(|_| ())
+}
+
+/// Evaluate `source` against `metadata`
+///
+/// Within [`scoped`], the evaluation counts against the query's budget and
+/// fails once that is spent.
+pub fn evaluate(source: &str, metadata: &Value) -> Result<bool> {
+    let ast = compile(source)?;
+    let failed = |e: &dyn std::fmt::Display| {
+        Error::InvalidFilter(format!("expression {:?} failed: {}", source, e))
+    };
+    let metadata = rhai::serde::to_dynamic(metadata).map_err(|e| failed(&e))?;
+
+    let (query_deadline, query_operations) = QUERY.with_borrow(|query| match query {
+        // The query fails with the error already kept
+        Some(budget) if budget.error.is_some() => Err(Error::Cancelled),
+        Some(budget) if budget.exhausted() => Err(budget_spent()),
+        Some(budget) => Ok((Some(budget.deadline), budget.operations)),
+        None => Ok((None, MAX_OPERATIONS)),
+    })?;
+    let deadline = Instant::now() + TIMEOUT;
+    let deadline = query_deadline.map_or(deadline, |d| d.min(deadline));
+
+    let mut scope = Scope::new();
+    scope.push("metadata", metadata);
+
+    LIMITS.set(Some((deadline, query_operations.min(MAX_OPERATIONS))));
+    USED.set(0);
+    let result = engine().eval_ast_with_scope::<bool>(&mut scope, &ast);
+    LIMITS.set(None);
+
+    let spent = QUERY.with_borrow_mut(|query| match query {
+        Some(budget) => {
+            budget.operations = budget.operations.saturating_sub(USED.get());
+            budget.exhausted()
+        }
+        None => false,
+    });
+    match result {
+        Ok(matched) => Ok(matched),
+        Err(_) if spent => Err(budget_spent()),
+        Err(e) => Err(failed(&e)),
+    }
+}
+
+/// Keep `error` for the query running in [`scoped`] on this thread, if any
+pub(crate) fn record(error: Error) {
+    QUERY.with_borrow_mut(|query| {
+        if let Some(budget) = query {
+            budget.error.get_or_insert(error);
+        }
+    });
+}
+
+/// Run `query` with a budget of [`MAX_QUERY_OPERATIONS`] and
+/// [`QUERY_TIMEOUT`] for all the expressions it evaluates
+///
+/// Fails with the first error an expression ran into, even if the query
+/// counted it as no match and went on. Queries run within `query` share
+/// its budget.
+pub fn scoped<T>(query: impl FnOnce() -> Result<T>) -> Result<T> {
+    if QUERY.with_borrow(Option::is_some) {
+        return query();
+    }
+    struct Reset;
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            QUERY.set(None);
+        }
+    }
+    let _reset = Reset;
+    QUERY.set(Some(QueryBudget {
+        deadline: Instant::now() + QUERY_TIMEOUT,
+        operations: MAX_QUERY_OPERATIONS,
+        error: None,
+    }));
+    let result = query();
+    match QUERY.with_borrow_mut(|query| query.as_mut().and_then(|b| b.error.take())) {
+        Some(error) => Err(error),
+        None => result,
+    }
+}
+
+fn budget_spent() -> Error {
+    Error::InvalidFilter(format!(
+        "expressions exceeded the budget of a query ({} operations or {:?})",
+        MAX_QUERY_OPERATIONS, QUERY_TIMEOUT
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::Filter;
+    use serde_json::json;
+
+    #[test]
+    fn test_expression_matches() {
+        let meta = json!({"price": 12.5, "qty": 10, "tags": ["a", "b"]});
+        let matches = |source| evaluate(source, &meta).unwrap();
+        assert!(matches("metadata.price * metadata.qty > 100.0"));
+        assert!(!matches("metadata.qty < 5"));
+        assert!(matches("\"b\" in metadata.tags"));
+        // Missing fields compare as unequal; non-boolean results are errors
+        assert!(!matches("metadata.missing > 1"));
+        assert!(evaluate("metadata.qty", &meta).is_err());
+    }
+
+    #[test]
+    fn test_invalid_expressions_rejected() {
+        assert!(validate("metadata.qty >").is_err());
+        assert!(validate("other_var == 1").is_err());
+        assert!(validate(&"1 + ".repeat(MAX_SOURCE_LEN)).is_err());
+        assert!(validate("metadata.qty > 1").is_ok());
+    }
+
+    #[test]
+    fn test_resource_limits_enforced() {
+        let meta = json!({"n": 100_000_000});
+        assert!(validate("loop { }").is_err());
+        assert!(evaluate("\"x\".pad(metadata.n, \"y\").len() > 0", &meta).is_err());
+        assert!(evaluate(
+            "range(0, metadata.n).reduce(|sum, v| sum + v, 0) > 0",
+            &meta
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_query_budget_is_shared() {
+        let meta = json!({"n": 1});
+        // About 900 operations
+        let costly = format!("[{}].len() > 0", "metadata.n, ".repeat(300));
+        assert!(evaluate(&costly, &meta).unwrap());
+
+        // Each evaluation fits its own limits, but not all of them together
+        let mut evaluated = 0;
+        let result = scoped(|| {
+            for _ in 0..MAX_QUERY_OPERATIONS / 900 + 1 {
+                evaluate(&costly, &meta)?;
+                evaluated += 1;
+            }
+            Ok(())
+        });
+        assert!(result.is_err());
+        assert!(evaluated > 0);
+        // The budget ends with the query
+        assert!(evaluate(&costly, &meta).unwrap());
+    }
+
+    #[test]
+    fn test_errors_fail_the_query() {
+        let meta = json!({"n": 1});
+        let not_boolean = Filter::Not(Box::new(Filter::Expr("metadata.n".to_string())));
+        assert!(not_boolean.evaluate(&meta).is_err());
+        // Counted as no match, even under `Not`, but the query still fails
+        let result = scoped(|| Ok(not_boolean.matches(&meta)));
+        assert!(result.is_err());
+        assert!(!not_boolean.matches(&meta));
+    }
+}
@@ -1,9 +1,16 @@
-use serde::Deserialize;
-use serde::Serialize;
-use serde_json::Value;
+use crate::error::Result;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
 use std::cmp::Ordering;
 
+/// Metadata condition for searches, listings and the filter cache
+///
+/// Serialized as an externally tagged enum, e.g. `{"Exact": ["tag", "a"]}`.
+/// Deserializing also accepts the query syntax described at
+/// [`Filter::from_query`].
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(remote = "Self")]
 pub enum Filter {
     /// Exact match: key == value
     Exact(String, Value),
@@ -29,12 +36,125 @@ pub enum Filter {
         center: (f64, f64),
         radius_meters: f64,
     },
+    /// Sandboxed Rhai expression over `metadata`, e.g. `metadata.price * metadata.qty > 100`
+    ///
+    /// Requires the `expr` feature; without it the clause never matches.
+    Expr(String),
+}
+
+/// Variant names of the tagged form
+const VARIANTS: [&str; 8] = [
+    "Exact",
+    "OneOf",
+    "And",
+    "Or",
+    "Not",
+    "Range",
+    "GeoRadius",
+    "Expr",
+];
+
+impl Serialize for Filter {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        Filter::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Filter {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        let tagged = match &value {
+            Value::Object(map) => {
+                map.len() == 1 && map.keys().all(|k| VARIANTS.contains(&k.as_str()))
+            }
+            _ => false,
+        };
+        if tagged {
+            Filter::deserialize(value).map_err(D::Error::custom)
+        } else {
+            Filter::from_query(&value).map_err(D::Error::custom)
+        }
+    }
 }
 
 impl Filter {
+    /// Parse the query syntax: an object whose entries must all match
+    ///
+    /// - `"field": value` matches the value exactly (`field` may be a
+    ///   dot-notation path).
+    /// - `"field": {"$op": ...}` applies operators, all of which must match:
+    ///   `$eq`, `$ne`, `$in`, `$nin` (also matches a missing field), `$gt`,
+    ///   `$gte`, `$lt`, `$lte` and
+    ///   `$geo_radius: {"lat": .., "lon": .., "radius_meters": ..}`.
+    /// - `"must": [..]` needs every filter to match, `"should": [..]` at least
+    ///   one and `"must_not": [..]` none. Each element is a filter in either
+    ///   syntax.
+    ///
+    /// A single-key object named after a variant, such as `{"Range": ..}`, is
+    /// read as the tagged form instead.
+    pub fn from_query(query: &Value) -> std::result::Result<Filter, String> {
+        let Value::Object(map) = query else {
+            return Err(format!("Filter must be an object, got {}", query));
+        };
+        let mut clauses = Vec::with_capacity(map.len());
+        for (key, value) in map {
+            let clause = match key.as_str() {
+                "must" => Filter::And(sub_filters(key, value)?),
+                "should" => Filter::Or(sub_filters(key, value)?),
+                "must_not" => Filter::Not(Box::new(Filter::Or(sub_filters(key, value)?))),
+                field => match value {
+                    Value::Object(ops) if is_operators(ops) => operators(field, ops)?,
+                    _ => Filter::Exact(field.to_string(), value.clone()),
+                },
+            };
+            clauses.push(clause);
+        }
+        Ok(match clauses.len() {
+            1 => clauses.remove(0),
+            _ => Filter::And(clauses),
+        })
+    }
+
+    /// Check that every clause can be evaluated (e.g. expressions compile)
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            Filter::And(filters) | Filter::Or(filters) => {
+                filters.iter().try_for_each(|f| f.validate())
+            }
+            Filter::Not(filter) => filter.validate(),
+            #[cfg(feature = "expr")]
+            Filter::Expr(source) => crate::expr::validate(source),
+            #[cfg(not(feature = "expr"))]
+            Filter::Expr(_) => Err(crate::error::Error::InvalidFilter(
+                "expression filters require the `expr` feature".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
     /// Check if the metadata matches the filter
+    ///
+    /// An expression that fails to evaluate fails the whole filter, so it
+    /// doesn't match, even under [`Not`](Filter::Not). Within
+    /// [`expr::scoped`](crate::expr::scoped) the error is kept for the query
+    /// to fail with.
     pub fn matches(&self, metadata: &Value) -> bool {
-        match self {
+        match self.evaluate(metadata) {
+            Ok(matched) => matched,
+            #[cfg(feature = "expr")]
+            Err(e) => {
+                crate::expr::record(e);
+                false
+            }
+            #[cfg(not(feature = "expr"))]
+            Err(_) => false,
+        }
+    }
+
+    /// Like [`matches`](Self::matches), returning the error of an expression
+    /// that fails to evaluate
+    pub fn evaluate(&self, metadata: &Value) -> Result<bool> {
+        let matched = match self {
             Filter::Exact(key, expected_value) => {
                 if let Some(actual_value) = get_value_by_path(metadata, key) {
                     actual_value == expected_value
@@ -49,9 +169,23 @@ impl Filter {
                     false
                 }
             }
-            Filter::And(filters) => filters.iter().all(|f| f.matches(metadata)),
-            Filter::Or(filters) => filters.iter().any(|f| f.matches(metadata)),
-            Filter::Not(filter) => !filter.matches(metadata),
+            Filter::And(filters) => {
+                for filter in filters {
+                    if !filter.evaluate(metadata)? {
+                        return Ok(false);
+                    }
+                }
+                true
+            }
+            Filter::Or(filters) => {
+                for filter in filters {
+                    if filter.evaluate(metadata)? {
+                        return Ok(true);
+                    }
+                }
+                false
+            }
+            Filter::Not(filter) => !filter.evaluate(metadata)?,
             Filter::Range {
                 field,
                 gt,
@@ -63,7 +197,7 @@ impl Filter {
                     if let Some(num) = value.as_f64() {
                         if let Some(limit) = gt {
                             if num.partial_cmp(limit) != Some(Ordering::Greater) {
-                                return false;
+                                return Ok(false);
                             }
                         }
                         if let Some(limit) = gte {
@@ -71,12 +205,12 @@ impl Filter {
                                 num.partial_cmp(limit),
                                 Some(Ordering::Greater | Ordering::Equal)
                             ) {
-                                return false;
+                                return Ok(false);
                             }
                         }
                         if let Some(limit) = lt {
                             if num.partial_cmp(limit) != Some(Ordering::Less) {
-                                return false;
+                                return Ok(false);
                             }
                         }
                         if let Some(limit) = lte {
@@ -84,7 +218,7 @@ impl Filter {
                                 num.partial_cmp(limit),
                                 Some(Ordering::Less | Ordering::Equal)
                             ) {
-                                return false;
+                                return Ok(false);
                             }
                         }
                         true
@@ -113,8 +247,250 @@ impl Filter {
                     false
                 }
             }
+            #[cfg(feature = "expr")]
+            Filter::Expr(source) => crate::expr::evaluate(source, metadata)?,
+            #[cfg(not(feature = "expr"))]
+            Filter::Expr(_) => false,
+        };
+        Ok(matched)
+    }
+
+    /// Whether the filter has an expression clause
+    #[cfg(feature = "expr")]
+    pub(crate) fn has_expr(&self) -> bool {
+        match self {
+            Filter::And(filters) | Filter::Or(filters) => filters.iter().any(Filter::has_expr),
+            Filter::Not(filter) => filter.has_expr(),
+            Filter::Expr(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Building filters in code
+///
+/// ```rust
+/// use surgedb_core::filter::Filter;
+///
+/// let filter = Filter::field("category").eq("books")
+///     & Filter::field("price").between(10, 20)
+///     & !Filter::field("tags").one_of(["draft", "hidden"]);
+/// ```
+impl Filter {
+    /// Start a condition on the field at a dot-notation path
+    pub fn field(path: impl Into<String>) -> FieldFilter {
+        FieldFilter { field: path.into() }
+    }
+
+    /// Match when every filter matches; nothing always matches
+    pub fn all(filters: impl IntoIterator<Item = Filter>) -> Filter {
+        Filter::And(filters.into_iter().collect())
+    }
+
+    /// Match when at least one filter matches; nothing never matches
+    pub fn any(filters: impl IntoIterator<Item = Filter>) -> Filter {
+        Filter::Or(filters.into_iter().collect())
+    }
+
+    /// Match when both match, extending `self` if it's already an `And`
+    pub fn and(self, other: Filter) -> Filter {
+        match self {
+            Filter::And(mut filters) => {
+                filters.push(other);
+                Filter::And(filters)
+            }
+            filter => Filter::And(vec![filter, other]),
+        }
+    }
+
+    /// Match when either matches, extending `self` if it's already an `Or`
+    pub fn or(self, other: Filter) -> Filter {
+        match self {
+            Filter::Or(mut filters) => {
+                filters.push(other);
+                Filter::Or(filters)
+            }
+            filter => Filter::Or(vec![filter, other]),
+        }
+    }
+}
+
+impl std::ops::BitAnd for Filter {
+    type Output = Filter;
+
+    fn bitand(self, other: Filter) -> Filter {
+        self.and(other)
+    }
+}
+
+impl std::ops::BitOr for Filter {
+    type Output = Filter;
+
+    fn bitor(self, other: Filter) -> Filter {
+        self.or(other)
+    }
+}
+
+impl std::ops::Not for Filter {
+    type Output = Filter;
+
+    fn not(self) -> Filter {
+        match self {
+            Filter::Not(filter) => *filter,
+            filter => Filter::Not(Box::new(filter)),
+        }
+    }
+}
+
+/// A field to build a condition on, from [`Filter::field`]
+#[derive(Debug, Clone)]
+pub struct FieldFilter {
+    field: String,
+}
+
+impl FieldFilter {
+    /// The field equals `value`
+    pub fn eq(self, value: impl Into<Value>) -> Filter {
+        Filter::Exact(self.field, value.into())
+    }
+
+    /// The field is missing or differs from `value`
+    pub fn ne(self, value: impl Into<Value>) -> Filter {
+        !self.eq(value)
+    }
+
+    /// The field equals one of `values`
+    pub fn one_of<V: Into<Value>>(self, values: impl IntoIterator<Item = V>) -> Filter {
+        Filter::OneOf(self.field, values.into_iter().map(Into::into).collect())
+    }
+
+    /// The field is missing or equals none of `values`
+    pub fn none_of<V: Into<Value>>(self, values: impl IntoIterator<Item = V>) -> Filter {
+        !self.one_of(values)
+    }
+
+    pub fn gt(self, bound: impl Into<f64>) -> Filter {
+        self.range(Some(bound.into()), None, None, None)
+    }
+
+    pub fn gte(self, bound: impl Into<f64>) -> Filter {
+        self.range(None, Some(bound.into()), None, None)
+    }
+
+    pub fn lt(self, bound: impl Into<f64>) -> Filter {
+        self.range(None, None, Some(bound.into()), None)
+    }
+
+    pub fn lte(self, bound: impl Into<f64>) -> Filter {
+        self.range(None, None, None, Some(bound.into()))
+    }
+
+    /// The field is a number from `low` to `high`, both included
+    pub fn between(self, low: impl Into<f64>, high: impl Into<f64>) -> Filter {
+        self.range(None, Some(low.into()), None, Some(high.into()))
+    }
+
+    /// The field is a point within `radius_meters` of `(lat, lon)`
+    pub fn within(self, center: (f64, f64), radius_meters: f64) -> Filter {
+        Filter::GeoRadius {
+            field: self.field,
+            center,
+            radius_meters,
+        }
+    }
+
+    fn range(self, gt: Option<f64>, gte: Option<f64>, lt: Option<f64>, lte: Option<f64>) -> Filter {
+        Filter::Range {
+            field: self.field,
+            gt,
+            gte,
+            lt,
+            lte,
+        }
+    }
+}
+
+/// Filters of a `must`, `should` or `must_not` clause: an array, or one filter
+fn sub_filters(clause: &str, value: &Value) -> std::result::Result<Vec<Filter>, String> {
+    let items = match value {
+        Value::Array(items) => items.as_slice(),
+        Value::Object(_) => std::slice::from_ref(value),
+        _ => {
+            return Err(format!(
+                "`{}` must be a filter or an array of filters",
+                clause
+            ))
+        }
+    };
+    items
+        .iter()
+        .map(|item| serde_json::from_value(item.clone()).map_err(|e| e.to_string()))
+        .collect()
+}
+
+/// Whether a field's value is an operator object rather than a literal
+fn is_operators(map: &Map<String, Value>) -> bool {
+    !map.is_empty() && map.keys().all(|k| k.starts_with('$'))
+}
+
+/// Conditions on `field` from its operator object
+fn operators(field: &str, ops: &Map<String, Value>) -> std::result::Result<Filter, String> {
+    let number = |op: &str, value: &Value| {
+        value
+            .as_f64()
+            .ok_or_else(|| format!("`{}` on `{}` needs a number", op, field))
+    };
+    let list = |op: &str, value: &Value| match value {
+        Value::Array(values) => Ok(values.clone()),
+        _ => Err(format!("`{}` on `{}` needs an array", op, field)),
+    };
+
+    let mut clauses = Vec::new();
+    let (mut gt, mut gte, mut lt, mut lte) = (None, None, None, None);
+    for (op, value) in ops {
+        match op.as_str() {
+            "$eq" => clauses.push(Filter::Exact(field.to_string(), value.clone())),
+            "$ne" => clauses.push(Filter::Not(Box::new(Filter::Exact(
+                field.to_string(),
+                value.clone(),
+            )))),
+            "$in" => clauses.push(Filter::OneOf(field.to_string(), list(op, value)?)),
+            "$nin" => clauses.push(Filter::Not(Box::new(Filter::OneOf(
+                field.to_string(),
+                list(op, value)?,
+            )))),
+            "$gt" => gt = Some(number(op, value)?),
+            "$gte" => gte = Some(number(op, value)?),
+            "$lt" => lt = Some(number(op, value)?),
+            "$lte" => lte = Some(number(op, value)?),
+            "$geo_radius" => {
+                let get = |key: &str| {
+                    value.get(key).and_then(Value::as_f64).ok_or_else(|| {
+                        format!("`$geo_radius` on `{}` needs a numeric `{}`", field, key)
+                    })
+                };
+                clauses.push(Filter::GeoRadius {
+                    field: field.to_string(),
+                    center: (get("lat")?, get("lon")?),
+                    radius_meters: get("radius_meters")?,
+                });
+            }
+            _ => return Err(format!("Unknown operator `{}` on `{}`", op, field)),
         }
     }
+    if gt.is_some() || gte.is_some() || lt.is_some() || lte.is_some() {
+        clauses.push(Filter::Range {
+            field: field.to_string(),
+            gt,
+            gte,
+            lt,
+            lte,
+        });
+    }
+    Ok(match clauses.len() {
+        1 => clauses.remove(0),
+        _ => Filter::And(clauses),
+    })
 }
 
 fn parse_geo_point(value: &Value) -> Option<(f64, f64)> {
@@ -144,7 +520,7 @@ fn haversine_distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
 }
 
 /// Helper to get a value from a JSON object using a dot-notation path
-fn get_value_by_path<'a>(metadata: &'a Value, path: &str) -> Option<&'a Value> {
+pub fn get_value_by_path<'a>(metadata: &'a Value, path: &str) -> Option<&'a Value> {
     if path.is_empty() {
         return Some(metadata);
     }
@@ -218,4 +594,121 @@ mod tests {
 
         assert!(filter.matches(&meta));
     }
+
+    #[test]
+    fn test_query_syntax() {
+        let parse = |query: Value| serde_json::from_value::<Filter>(query).unwrap();
+        let book = json!({
+            "category": "books",
+            "price": 25,
+            "tags": "sale",
+            "store": { "location": { "lat": 52.52, "lon": 13.405 } }
+        });
+
+        assert!(parse(json!({ "category": "books" })).matches(&book));
+        assert!(parse(json!({ "price": { "$gte": 25, "$lt": 30 } })).matches(&book));
+        assert!(!parse(json!({ "price": { "$gt": 25 } })).matches(&book));
+        assert!(parse(json!({ "category": { "$in": ["books", "music"] } })).matches(&book));
+        assert!(!parse(json!({ "category": { "$nin": ["books"] } })).matches(&book));
+        assert!(parse(json!({ "missing": { "$nin": ["x"] } })).matches(&book));
+        assert!(parse(json!({ "category": { "$ne": "music" } })).matches(&book));
+        // Berlin to Potsdam is about 27 km
+        let near = |km: f64| {
+            json!({ "store.location": { "$geo_radius": {
+                "lat": 52.39, "lon": 13.06, "radius_meters": km * 1000.0
+            } } })
+        };
+        assert!(parse(near(30.0)).matches(&book));
+        assert!(!parse(near(20.0)).matches(&book));
+
+        let composed = parse(json!({
+            "must": [{ "category": "books" }, { "price": { "$lte": 30 } }],
+            "should": [{ "tags": "new" }, { "tags": "sale" }],
+            "must_not": { "Exact": ["store.location.lat", 0.0] }
+        }));
+        assert!(composed.matches(&book));
+        let excluded = parse(json!({ "must_not": [{ "tags": "sale" }] }));
+        assert!(!excluded.matches(&book));
+
+        // The tagged form still works, also nested in the query syntax
+        assert!(matches!(
+            parse(json!({ "Exact": ["category", "books"] })),
+            Filter::Exact(..)
+        ));
+        let round_trip = serde_json::to_value(&composed).unwrap();
+        assert!(round_trip.get("And").is_some());
+        assert!(parse(round_trip).matches(&book));
+
+        for invalid in [
+            json!({ "price": { "$gt": "ten" } }),
+            json!({ "price": { "$between": [1, 2] } }),
+            json!({ "category": { "$in": "books" } }),
+            json!({ "must": 1 }),
+            json!(["category", "books"]),
+        ] {
+            assert!(serde_json::from_value::<Filter>(invalid).is_err());
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "expr")]
+    fn test_expr_clause() {
+        let meta = json!({"category": "books", "price": 30, "discount": 0.25});
+
+        let filter = Filter::And(vec![
+            Filter::Exact("category".to_string(), json!("books")),
+            Filter::Expr("metadata.price * (1.0 - metadata.discount) < 25.0".to_string()),
+        ]);
+        assert!(filter.validate().is_ok());
+        assert!(filter.matches(&meta));
+
+        let invalid = Filter::Not(Box::new(Filter::Expr("metadata.price >".to_string())));
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn test_builder() {
+        let book = json!({
+            "category": "books",
+            "price": 15,
+            "tags": ["new"],
+            "store": { "location": { "lat": 48.8566, "lon": 2.3522 } }
+        });
+
+        let filter = Filter::field("category").eq("books")
+            & Filter::field("price").between(10, 20)
+            & Filter::field("tags").none_of(["draft"]);
+        // `&` extends the first `And` rather than nesting
+        assert!(matches!(&filter, Filter::And(clauses) if clauses.len() == 3));
+        assert!(filter.matches(&book));
+
+        assert!(Filter::field("price").gt(14.5).matches(&book));
+        assert!(!Filter::field("price").lt(15).matches(&book));
+        assert!(Filter::field("price").lte(15).matches(&book));
+        assert!(Filter::field("category").ne("movies").matches(&book));
+        assert!(Filter::field("missing").ne("movies").matches(&book));
+        assert!(
+            (Filter::field("category").eq("movies") | Filter::field("price").gte(15))
+                .matches(&book)
+        );
+        assert!(Filter::field("store.location")
+            .within((48.86, 2.35), 1000.0)
+            .matches(&book));
+        assert!(!Filter::any([]).matches(&book));
+        assert!(Filter::all([]).matches(&book));
+
+        // Double negation cancels out
+        let negated = !!Filter::field("category").eq("books");
+        assert!(matches!(negated, Filter::Exact(..)));
+
+        // Built filters match the same records as the query syntax
+        let parsed = Filter::from_query(
+            &json!({ "category": "books", "price": { "$gte": 10, "$lte": 20 } }),
+        )
+        .unwrap();
+        let built = Filter::field("category").eq("books") & Filter::field("price").between(10, 20);
+        for meta in [book.clone(), json!({ "category": "books", "price": 25 })] {
+            assert_eq!(parsed.matches(&meta), built.matches(&meta));
+        }
+    }
 }
@@ -0,0 +1,189 @@
+//! Materialized match sets for frequently used filters
+//!
+//! A cached filter keeps a bitmap of the live vectors whose metadata matches
+//! it. The bitmap is built once by scanning metadata and then kept up to date
+//! on every write, so filtered searches using the same filter skip per-node
+//! filter evaluation entirely, including for filters the [`BitmapIndex`]
+//! can't answer (ranges, negations, expressions). Each cached filter also
+//! provides a matching entry point that seeds layer-0 HNSW search, so
+//! selective filters (e.g. a single tenant) start inside their subset.
+//!
+//! [`BitmapIndex`]: crate::bitmap_index::BitmapIndex
+
+use crate::error::{Error, Result};
+use crate::filter::Filter;
+use crate::types::InternalId;
+use roaring::RoaringBitmap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Upper bound on cached filters per collection, since each one is evaluated on every write
+pub const MAX_CACHED_FILTERS: usize = 32;
+
+/// A cached filter as reported to callers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedFilterInfo {
+    /// Stable identifier derived from the filter
+    pub id: String,
+    pub filter: Filter,
+    /// Live vectors currently matching the filter
+    pub matches: u64,
+    /// Searches answered from the cache
+    pub hits: u64,
+}
+
+struct Entry {
+    id: String,
+    filter: Filter,
+    bitmap: Arc<RoaringBitmap>,
+    hits: AtomicU64,
+}
+
+impl Entry {
+    fn info(&self) -> CachedFilterInfo {
+        CachedFilterInfo {
+            id: self.id.clone(),
+            filter: self.filter.clone(),
+            matches: self.bitmap.len(),
+            hits: self.hits.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct FilterCache {
+    /// Serialized filter -> entry
+    entries: HashMap<String, Entry>,
+}
+
+fn key(filter: &Filter) -> String {
+    serde_json::to_string(filter).unwrap_or_default()
+}
+
+impl FilterCache {
+    /// Cache `filter` with the given matching IDs, replacing any previous entry
+    pub fn insert(
+        &mut self,
+        filter: Filter,
+        matching: impl IntoIterator<Item = InternalId>,
+    ) -> Result<CachedFilterInfo> {
+        filter.validate()?;
+        let key = key(&filter);
+        if !self.entries.contains_key(&key) && self.entries.len() >= MAX_CACHED_FILTERS {
+            return Err(Error::InvalidConfig(format!(
+                "At most {} filters can be cached per collection",
+                MAX_CACHED_FILTERS
+            )));
+        }
+
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        let entry = Entry {
+            id: format!("{:016x}", hasher.finish()),
+            filter,
+            bitmap: Arc::new(matching.into_iter().map(|id| id.as_u32()).collect()),
+            hits: AtomicU64::new(0),
+        };
+        let info = entry.info();
+        self.entries.insert(key, entry);
+        Ok(info)
+    }
+
+    /// Drop a cached filter by ID; returns whether it existed
+    pub fn remove(&mut self, id: &str) -> bool {
+        let before = self.entries.len();
+        self.entries.retain(|_, entry| entry.id != id);
+        self.entries.len() != before
+    }
+
+    pub fn list(&self) -> Vec<CachedFilterInfo> {
+        let mut list: Vec<_> = self.entries.values().map(Entry::info).collect();
+        list.sort_by(|a, b| a.id.cmp(&b.id));
+        list
+    }
+
+    /// Track a newly written vector
+    pub fn index(&mut self, internal_id: InternalId, metadata: &Value) {
+        for entry in self.entries.values_mut() {
+            if entry.filter.matches(metadata) {
+                Arc::make_mut(&mut entry.bitmap).insert(internal_id.as_u32());
+            }
+        }
+    }
+
+    /// Forget a deleted or superseded vector
+    pub fn remove_id(&mut self, internal_id: InternalId) {
+        for entry in self.entries.values_mut() {
+            if entry.bitmap.contains(internal_id.as_u32()) {
+                Arc::make_mut(&mut entry.bitmap).remove(internal_id.as_u32());
+            }
+        }
+    }
+
+    fn entry(&self, filter: &Filter) -> Option<&Entry> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        self.entries.get(&key(filter))
+    }
+
+    /// Matching set for `filter`, if it is cached
+    pub fn get(&self, filter: &Filter) -> Option<Arc<RoaringBitmap>> {
+        let entry = self.entry(filter)?;
+        entry.hits.fetch_add(1, Ordering::Relaxed);
+        Some(entry.bitmap.clone())
+    }
+
+    /// A live vector matching `filter`, if it is cached and matches anything
+    pub fn entry_point(&self, filter: &Filter) -> Option<InternalId> {
+        self.entry(filter)?
+            .bitmap
+            .min()
+            .map(|id| InternalId::from(id as usize))
+    }
+
+    /// Approximate bytes used by the cached bitmaps
+    pub fn memory_usage(&self) -> usize {
+        self.entries
+            .iter()
+            .map(|(key, entry)| key.capacity() + entry.bitmap.serialized_size())
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_cache_tracks_writes() {
+        let mut cache = FilterCache::default();
+        let filter = Filter::Range {
+            field: "n".to_string(),
+            gt: None,
+            gte: Some(10.0),
+            lt: None,
+            lte: None,
+        };
+        let info = cache.insert(filter.clone(), [InternalId::from(1)]).unwrap();
+        assert_eq!(info.matches, 1);
+
+        cache.index(InternalId::from(2), &json!({"n": 15}));
+        cache.index(InternalId::from(3), &json!({"n": 5}));
+        cache.remove_id(InternalId::from(1));
+
+        let bitmap = cache.get(&filter).unwrap();
+        assert_eq!(bitmap.iter().collect::<Vec<_>>(), vec![2]);
+        assert_eq!(cache.entry_point(&filter), Some(InternalId::from(2)));
+        assert_eq!(cache.list()[0].hits, 1);
+
+        assert!(cache.remove(&info.id));
+        assert!(cache.get(&filter).is_none());
+    }
+}
@@ -0,0 +1,157 @@
+//! Export of the HNSW adjacency for offline analysis
+//!
+//! A [`GraphExport`] is a detached copy of (part of) the index graph keyed by
+//! external vector IDs. It can be rendered as GraphML for visualization tools
+//! (Gephi, Cytoscape, networkx) or as a plain tab-separated edge list.
+//! [`GraphStats`] summarizes the graph's shape without copying it.
+
+use crate::types::VectorId;
+use serde::Serialize;
+use std::fmt::Write;
+
+/// A vector in the exported graph
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GraphNode {
+    pub id: VectorId,
+    /// Highest HNSW layer the node is present on
+    pub max_layer: usize,
+}
+
+/// A directed link from `source` to its neighbor `target` on `layer`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GraphEdge {
+    pub source: VectorId,
+    pub target: VectorId,
+    pub layer: usize,
+}
+
+/// Shape of an HNSW graph
+///
+/// Nodes of deleted vectors stay in the graph until it is compacted, so they
+/// are counted.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct GraphStats {
+    pub nodes: usize,
+    /// Highest layer, the entry point's
+    pub max_layer: usize,
+    /// From layer 0 up
+    pub layers: Vec<LayerStats>,
+}
+
+/// Nodes and links on one HNSW layer
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LayerStats {
+    pub nodes: usize,
+    /// Directed links from a node to its neighbors
+    pub edges: usize,
+    /// Mean neighbors per node
+    pub avg_degree: f64,
+    pub max_degree: usize,
+    /// Nodes without neighbors on the layer, which searches can't reach
+    /// through it
+    pub isolated: usize,
+}
+
+/// Snapshot of HNSW nodes and edges
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GraphExport {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+impl GraphExport {
+    /// Render as a GraphML document with `max_layer` node and `layer` edge attributes
+    pub fn to_graphml(&self) -> String {
+        let mut out = String::new();
+        out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        out.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+        out.push_str(
+            "  <key id=\"max_layer\" for=\"node\" attr.name=\"max_layer\" attr.type=\"int\"/>\n",
+        );
+        out.push_str("  <key id=\"layer\" for=\"edge\" attr.name=\"layer\" attr.type=\"int\"/>\n");
+        out.push_str("  <graph id=\"hnsw\" edgedefault=\"directed\">\n");
+
+        for node in &self.nodes {
+            let _ = writeln!(
+                out,
+                "    <node id=\"{}\"><data key=\"max_layer\">{}</data></node>",
+                escape_xml(&node.id.as_str()),
+                node.max_layer
+            );
+        }
+        for edge in &self.edges {
+            let _ = writeln!(
+                out,
+                "    <edge source=\"{}\" target=\"{}\"><data key=\"layer\">{}</data></edge>",
+                escape_xml(&edge.source.as_str()),
+                escape_xml(&edge.target.as_str()),
+                edge.layer
+            );
+        }
+
+        out.push_str("  </graph>\n</graphml>\n");
+        out
+    }
+
+    /// Render as one `source<TAB>target<TAB>layer` line per edge
+    pub fn to_edgelist(&self) -> String {
+        let mut out = String::new();
+        for edge in &self.edges {
+            let _ = writeln!(out, "{}\t{}\t{}", edge.source, edge.target, edge.layer);
+        }
+        out
+    }
+}
+
+fn escape_xml(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_graph() -> GraphExport {
+        GraphExport {
+            nodes: vec![
+                GraphNode {
+                    id: VectorId::from("a"),
+                    max_layer: 1,
+                },
+                GraphNode {
+                    id: VectorId::from("b<&>"),
+                    max_layer: 0,
+                },
+            ],
+            edges: vec![GraphEdge {
+                source: VectorId::from("a"),
+                target: VectorId::from("b<&>"),
+                layer: 0,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_graphml_escapes_ids() {
+        let xml = sample_graph().to_graphml();
+        assert!(xml.contains("<node id=\"b&lt;&amp;&gt;\">"));
+        assert!(xml.contains("<edge source=\"a\" target=\"b&lt;&amp;&gt;\">"));
+        assert!(xml.trim_end().ends_with("</graphml>"));
+    }
+
+    #[test]
+    fn test_edgelist() {
+        assert_eq!(sample_graph().to_edgelist(), "a\tb<&>\t0\n");
+    }
+}
@@ -0,0 +1,136 @@
+//! Search results grouped by a metadata field
+//!
+//! A grouped search returns the best few hits for each distinct value of a
+//! field, such as the top chunks of each document, so clients don't fetch
+//! extra results and deduplicate them. Records without the field, or whose
+//! value is an object, an array or null, belong to no group.
+
+use crate::error::{Error, Result};
+use crate::filter::get_value_by_path;
+use crate::types::SearchHit;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Most candidates a grouped search examines
+///
+/// The search widens until every group is full or the collection runs out of
+/// matches; if a few groups dominate the nearest records, fewer groups than
+/// asked for can come back.
+pub const MAX_GROUP_CANDIDATES: usize = 10_000;
+
+/// How a grouped search groups its hits
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GroupBy {
+    /// Metadata field (dot notation) whose value names a record's group
+    pub field: String,
+    /// Most groups returned
+    pub groups: usize,
+    /// Most hits per group
+    pub group_size: usize,
+}
+
+/// Hits sharing one value of the grouped field, nearest first
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchGroup {
+    pub key: Value,
+    pub hits: Vec<SearchHit>,
+}
+
+impl GroupBy {
+    pub fn validate(&self) -> Result<()> {
+        if self.field.is_empty() {
+            return Err(Error::InvalidConfig(
+                "Grouped search needs a field to group by".to_string(),
+            ));
+        }
+        if self.groups == 0 || self.group_size == 0 {
+            return Err(Error::InvalidConfig(
+                "groups and group_size must be positive".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Hits needed to fill every group
+    pub fn capacity(&self) -> usize {
+        self.groups.saturating_mul(self.group_size)
+    }
+
+    /// Group `hits`, nearest first, into at most `groups` groups ordered by
+    /// their nearest hit; returns the groups and whether all of them are full
+    pub(crate) fn collect(&self, hits: Vec<SearchHit>) -> (Vec<SearchGroup>, bool) {
+        let mut groups: Vec<SearchGroup> = Vec::new();
+        let mut index: HashMap<String, usize> = HashMap::new();
+        for hit in hits {
+            let Some(key) = hit.2.as_ref().and_then(|m| self.key(m)) else {
+                continue;
+            };
+            // Serialized, so 1 and "1" are different groups
+            let slot = match index.get(&key.to_string()) {
+                Some(&slot) => slot,
+                None if groups.len() < self.groups => {
+                    index.insert(key.to_string(), groups.len());
+                    groups.push(SearchGroup {
+                        key: key.clone(),
+                        hits: Vec::new(),
+                    });
+                    groups.len() - 1
+                }
+                None => continue,
+            };
+            if groups[slot].hits.len() < self.group_size {
+                groups[slot].hits.push(hit);
+            }
+        }
+        let full =
+            groups.len() == self.groups && groups.iter().all(|g| g.hits.len() == self.group_size);
+        (groups, full)
+    }
+
+    fn key<'a>(&self, metadata: &'a Value) -> Option<&'a Value> {
+        get_value_by_path(metadata, &self.field)
+            .filter(|v| matches!(v, Value::String(_) | Value::Number(_) | Value::Bool(_)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn hit(id: u64, doc: Value) -> SearchHit {
+        (id.into(), id as f32, Some(json!({ "doc": doc })))
+    }
+
+    #[test]
+    fn test_collect_groups() {
+        let group_by = GroupBy {
+            field: "doc".to_string(),
+            groups: 2,
+            group_size: 2,
+        };
+        let hits = vec![
+            hit(0, json!("a")),
+            hit(1, json!(1)),
+            hit(2, json!("a")),
+            hit(3, json!(null)),
+            hit(4, json!("a")),
+            hit(5, json!("c")),
+        ];
+        let (groups, full) = group_by.collect(hits.clone());
+        assert!(!full);
+        let keys: Vec<&Value> = groups.iter().map(|g| &g.key).collect();
+        assert_eq!(keys, vec![&json!("a"), &json!(1)]);
+        assert_eq!(groups[0].hits, vec![hits[0].clone(), hits[2].clone()]);
+        assert_eq!(groups[1].hits, vec![hits[1].clone()]);
+
+        let (_, full) = group_by.collect(vec![
+            hit(0, json!("a")),
+            hit(1, json!("1")),
+            hit(2, json!("a")),
+            hit(3, json!("1")),
+        ]);
+        assert!(full);
+    }
+}
@@ -6,19 +6,23 @@
 //! - Mmap mode (for disk-resident vectors) [TODO]
 //! - Hybrid mode (adaptive) [TODO]
 
+use crate::ann::IndexStorage;
 use crate::distance::DistanceMetric;
 use crate::error::{Error, Result};
 use crate::filter::Filter;
+use crate::graph_export::{GraphEdge, GraphExport, GraphNode, GraphStats, LayerStats};
+use crate::recovery::IntegrityReport;
 use crate::storage::VectorStorageTrait;
 use crate::sync::RwLock;
-use crate::types::InternalId;
+use crate::types::{GraphRepair, InternalId, SearchUsage, VectorId};
 #[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
 use rand::Rng;
 use roaring::RoaringBitmap;
-use std::sync::Arc;
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
-use std::collections::{BinaryHeap, HashSet};
+use std::collections::{BinaryHeap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicU32, Ordering as AtomicOrdering};
+use std::sync::Arc;
 use std::sync::OnceLock;
 
 fn bitmap_filter_enabled() -> bool {
@@ -82,6 +86,30 @@ impl HnswConfig {
             ml: 1.0 / (m as f64).ln(),
         }
     }
+
+    /// Set M, along with the layer 0 limit and level factor derived from it
+    pub fn with_m(mut self, m: usize) -> Self {
+        self.m = m;
+        self.m0 = m * 2;
+        self.ml = 1.0 / (m as f64).ln();
+        self
+    }
+
+    /// Reject parameters the graph can't be built with
+    pub fn validate(&self) -> Result<()> {
+        if self.m < 2 {
+            return Err(Error::InvalidConfig(format!(
+                "HNSW m must be at least 2, got {}",
+                self.m
+            )));
+        }
+        if self.ef_construction == 0 || self.ef_search == 0 {
+            return Err(Error::InvalidConfig(
+                "HNSW ef_construction and ef_search must be at least 1".to_string(),
+            ));
+        }
+        Ok(())
+    }
 }
 
 /// A node in the HNSW graph
@@ -173,6 +201,14 @@ struct SearchContext<'a> {
     layer: usize,
     filter: Option<&'a Filter>,
     filter_bitmap: Option<Arc<RoaringBitmap>>,
+    /// Extra entry point known to match the filter
+    seed: Option<InternalId>,
+    /// Results further than this are left out, and once one within it is
+    /// found, candidates further than this are not expanded
+    max_distance: f32,
+    /// Return deleted nodes too; new nodes must link to them when nothing
+    /// else is near, or they end up cut off from the entry point
+    include_deleted: bool,
 }
 
 /// State of the HNSW index for serialization
@@ -183,6 +219,13 @@ pub struct HnswState {
     pub max_layer: usize,
 }
 
+/// Most vectors linked together in one round of a parallel batch insert
+///
+/// Every vector of a round is compared with every other, which costs less
+/// than its search in the graph at this size.
+#[cfg(feature = "parallel")]
+const BATCH_ROUND: usize = 512;
+
 /// The HNSW index
 pub struct HnswIndex {
     config: HnswConfig,
@@ -196,6 +239,10 @@ pub struct HnswIndex {
 
     /// Maximum layer in the graph
     max_layer: RwLock<usize>,
+
+    /// Times each node was returned by a search, halved by every repair
+    /// pass; grown as results name new nodes
+    heat: RwLock<Vec<AtomicU32>>,
 }
 
 impl HnswIndex {
@@ -207,6 +254,7 @@ impl HnswIndex {
             nodes: RwLock::new(Vec::new()),
             entry_point: RwLock::new(None),
             max_layer: RwLock::new(0),
+            heat: RwLock::new(Vec::new()),
         }
     }
 
@@ -235,7 +283,14 @@ impl HnswIndex {
         }
     }
 
-    /// Insert multiple vectors in a batch
+    /// Insert multiple vectors in a batch, on all cores
+    ///
+    /// The batch is linked in rounds of at most [`BATCH_ROUND`] vectors. In
+    /// parallel, each vector of a round is searched for in the graph as it
+    /// was before the round and compared exactly with the other vectors of
+    /// the round; its neighbors are then picked from both, so vectors of one
+    /// round link to each other as well as to the graph. The links are
+    /// written under one write lock per round.
     #[cfg(feature = "parallel")]
     pub fn insert_batch(
         &self,
@@ -244,168 +299,144 @@ impl HnswIndex {
     ) -> Result<()> {
         use rayon::prelude::*; // Use inside function to avoid trait/impl conflict
 
-        if items.is_empty() {
-            return Ok(());
-        }
-
-        // Generate levels for all new nodes upfront
-        let mut new_nodes_data = Vec::with_capacity(items.len());
-        for (internal_id, _) in items {
-            let level = self.random_level();
-            new_nodes_data.push((*internal_id, level));
-        }
+        for round in items.chunks(BATCH_ROUND) {
+            let levels: Vec<usize> = round.iter().map(|_| self.random_level()).collect();
 
-        // Phase 1: Parallel Search for neighbors
-        // We acquire a Read lock on the graph to allow parallel reading
-        let search_results: Vec<_> = {
-            let nodes = self.nodes.read();
-            let entry_point = self.entry_point.read();
-            let max_layer = *self.max_layer.read();
-
-            if let Some(ep) = *entry_point {
-                // Parallel iterator over items
-                items
+            // Phase 1: find every vector's neighbors under a read lock
+            let selected: Vec<Vec<Vec<Candidate>>> = {
+                let nodes = self.nodes.read();
+                let entry_point = *self.entry_point.read();
+                let max_layer = *self.max_layer.read();
+                round
                     .par_iter()
-                    .zip(new_nodes_data.par_iter())
-                    .map(|(&(_, vector), &(_, node_level))| {
-                        let mut current_ep = ep;
-                        let mut neighbors_by_layer = vec![Vec::new(); node_level + 1];
-
-                        // Traverse from top layer to node_level + 1
-                        for layer in (node_level + 1..=max_layer).rev() {
-                            if let Ok(next_ep) =
-                                self.search_layer_single(vector, current_ep, layer, &nodes, storage)
-                            {
-                                current_ep = next_ep;
-                            }
+                    .zip(levels.par_iter())
+                    .enumerate()
+                    .map(|(i, (&(_, vector), &level))| {
+                        let mut found = vec![Vec::new(); level + 1];
+                        if let Some(ep) = entry_point {
+                            self.search_for_insert(
+                                vector, ep, level, max_layer, &nodes, storage, &mut found,
+                            )?;
                         }
-
-                        // For layers from min(node_level, max_layer) down to 0
-                        let start_layer = node_level.min(max_layer);
-                        for layer in (0..=start_layer).rev() {
-                            let ctx = SearchContext {
-                                query: vector,
-                                ef: self.config.ef_construction,
-                                layer,
-                                filter: None,
-                                filter_bitmap: None,
-                            };
-                            if let Ok(neighbors) =
-                                self.search_layer(ctx, current_ep, &nodes, storage)
+                        for (j, (&(peer, _), &peer_level)) in round.iter().zip(&levels).enumerate()
+                        {
+                            if j == i {
+                                continue;
+                            }
+                            if let Some(distance) =
+                                storage.distance(peer, vector, self.distance_metric)
                             {
-                                // Select best neighbors
-                                let m = if layer == 0 {
-                                    self.config.m0
-                                } else {
-                                    self.config.m
-                                };
-                                let selected = self.select_neighbors(&neighbors, m, storage);
-                                neighbors_by_layer[layer] = selected;
-
-                                if !neighbors_by_layer[layer].is_empty() {
-                                    current_ep = neighbors_by_layer[layer][0].id;
+                                for candidates in found.iter_mut().take(level.min(peer_level) + 1) {
+                                    candidates.push(Candidate { id: peer, distance });
                                 }
                             }
                         }
-                        Ok::<_, crate::error::Error>(neighbors_by_layer)
+                        Ok(found
+                            .into_iter()
+                            .enumerate()
+                            .map(|(layer, mut candidates)| {
+                                candidates.sort_by(|a, b| {
+                                    a.distance
+                                        .partial_cmp(&b.distance)
+                                        .unwrap_or(Ordering::Equal)
+                                });
+                                candidates.truncate(self.config.ef_construction);
+                                self.select_neighbors(&candidates, self.max_links(layer), storage)
+                            })
+                            .collect())
                     })
-                    .collect()
-            } else {
-                // Index is empty, nothing to search
-                (0..items.len()).map(|_| Ok(Vec::new())).collect()
-            }
-        };
-
-        // Phase 2: Sequential Update (Write Lock)
-        // We now acquire the Write lock to actually modify the graph
-        let mut nodes = self.nodes.write();
-        let mut entry_point = self.entry_point.write();
-        let mut max_layer = self.max_layer.write();
-
-        // Fix type mismatch by destructuring the tuple reference correctly
-        for (i, &(internal_id, level)) in new_nodes_data.iter().enumerate() {
-            let mut new_node = HnswNode::new(internal_id, level);
-
-            // If index was empty initially, the first item becomes entry point
-            if entry_point.is_none() {
-                *entry_point = Some(internal_id);
-                *max_layer = level;
-                nodes.push(new_node);
-                continue;
-            }
+                    .collect::<Result<_>>()?
+            };
 
-            // Assign pre-computed neighbors
-            if let Ok(neighbors_by_layer) = &search_results[i] {
-                for (layer, candidates) in neighbors_by_layer.iter().enumerate() {
-                    if layer < new_node.neighbors.len() {
-                        new_node.neighbors[layer] =
-                            candidates.iter().map(|c: &Candidate| c.id).collect();
-                    }
+            // Phase 2: add the nodes and their links under the write lock
+            let mut nodes = self.nodes.write();
+            let mut entry_point = self.entry_point.write();
+            let mut max_layer = self.max_layer.write();
+            for ((&(internal_id, _), &level), layers) in round.iter().zip(&levels).zip(&selected) {
+                let mut node = HnswNode::new(internal_id, level);
+                for (links, candidates) in node.neighbors.iter_mut().zip(layers) {
+                    *links = candidates.iter().map(|c| c.id).collect();
                 }
+                nodes.push(node);
             }
-
-            nodes.push(new_node);
-
-            // Update bidirectional connections
-            if let Ok(neighbors_by_layer) = &search_results[i] {
-                for (layer, candidates) in neighbors_by_layer.iter().enumerate() {
+            // Links back, once every node of the round exists
+            for (&(internal_id, _), layers) in round.iter().zip(&selected) {
+                for (layer, candidates) in layers.iter().enumerate() {
                     for neighbor in candidates {
-                        let neighbor_idx = neighbor.id.as_usize();
-
-                        if neighbor_idx < nodes.len() {
-                            let neighbor_node = &mut nodes[neighbor_idx];
-                            if neighbor_node.max_layer >= layer {
-                                neighbor_node.neighbors[layer].push(internal_id);
-
-                                // Prune connections if needed
-                                let max_connections = if layer == 0 {
-                                    self.config.m0
-                                } else {
-                                    self.config.m
-                                };
-
-                                if neighbor_node.neighbors[layer].len() > max_connections {
-                                    if let Some(nv) = storage.get_vector_data(neighbor.id) {
-                                        let mut candidates: Vec<Candidate> = neighbor_node
-                                            .neighbors[layer]
-                                            .iter()
-                                            .filter_map(|&n_id| {
-                                                storage
-                                                    .distance(n_id, &nv, self.distance_metric)
-                                                    .map(|dist| Candidate {
-                                                        id: n_id,
-                                                        distance: dist,
-                                                    })
-                                            })
-                                            .collect();
-                                        candidates.sort_by(|a, b| {
-                                            a.distance
-                                                .partial_cmp(&b.distance)
-                                                .unwrap_or(Ordering::Equal)
-                                        });
-                                        neighbor_node.neighbors[layer] = candidates
-                                            .into_iter()
-                                            .take(max_connections)
-                                            .map(|c| c.id)
-                                            .collect();
-                                    }
-                                }
-                            }
+                        let neighbor_node = &mut nodes[neighbor.id.as_usize()];
+                        if neighbor_node.max_layer >= layer
+                            && !neighbor_node.neighbors[layer].contains(&internal_id)
+                        {
+                            neighbor_node.neighbors[layer].push(internal_id);
+                            self.prune(neighbor_node, layer, storage);
                         }
                     }
                 }
             }
-
-            // Update entry point
-            if level > *max_layer {
-                *max_layer = level;
-                *entry_point = Some(internal_id);
+            for (&(internal_id, _), &level) in round.iter().zip(&levels) {
+                if entry_point.is_none() || level > *max_layer {
+                    *entry_point = Some(internal_id);
+                    *max_layer = level;
+                }
             }
         }
 
         Ok(())
     }
 
+    /// Descend from `entry` to `level` and collect candidate neighbors of
+    /// `vector` on each layer it shares with the graph into `found`
+    #[allow(clippy::too_many_arguments)]
+    fn search_for_insert(
+        &self,
+        vector: &[f32],
+        entry: InternalId,
+        level: usize,
+        max_layer: usize,
+        nodes: &[HnswNode],
+        storage: &impl VectorStorageTrait,
+        found: &mut [Vec<Candidate>],
+    ) -> Result<()> {
+        let mut current_ep = entry;
+        for layer in (level + 1..=max_layer).rev() {
+            current_ep = self.search_layer_single(
+                vector,
+                current_ep,
+                layer,
+                nodes,
+                storage,
+                &mut SearchUsage::default(),
+            )?;
+        }
+        for layer in (0..=level.min(max_layer)).rev() {
+            let ctx = SearchContext {
+                query: vector,
+                ef: self.config.ef_construction,
+                layer,
+                filter: None,
+                filter_bitmap: None,
+                seed: None,
+                max_distance: f32::INFINITY,
+                include_deleted: true,
+            };
+            let neighbors =
+                self.search_layer(ctx, current_ep, nodes, storage, &mut SearchUsage::default())?;
+            if let Some(nearest) = neighbors.first() {
+                current_ep = nearest.id;
+            }
+            found[layer] = neighbors;
+        }
+        Ok(())
+    }
+
+    /// Most links a node keeps on `layer`
+    fn max_links(&self, layer: usize) -> usize {
+        if layer == 0 {
+            self.config.m0
+        } else {
+            self.config.m
+        }
+    }
     /// Insert multiple vectors in a batch (sequential version for WASM)
     #[cfg(not(feature = "parallel"))]
     pub fn insert_batch(
@@ -447,91 +478,225 @@ impl HnswIndex {
         let ep = entry_point.expect("Entry point should be Some here");
         let current_max_layer = *max_layer;
 
-        // Search from top layer to node_level + 1, finding the closest node
-        let mut current_ep = ep;
-        for layer in (node_level + 1..=current_max_layer).rev() {
-            current_ep = self.search_layer_single(vector, current_ep, layer, &nodes, storage)?;
+        // Find candidates from the top layer down, then connect the M best
+        // of them on each layer using the heuristic
+        let mut found = vec![Vec::new(); node_level + 1];
+        self.search_for_insert(
+            vector,
+            ep,
+            node_level,
+            current_max_layer,
+            &nodes,
+            storage,
+            &mut found,
+        )?;
+        for (layer, neighbors) in found.iter().enumerate() {
+            let selected = self.select_neighbors(neighbors, self.max_links(layer), storage);
+            self.connect(&mut nodes, internal_id, &selected, layer, storage);
         }
 
-        // For layers from min(node_level, max_layer) down to 0, find and connect neighbors
-        let start_layer = node_level.min(current_max_layer);
-        for layer in (0..=start_layer).rev() {
-            let ctx = SearchContext {
-                query: vector,
-                ef: self.config.ef_construction,
-                layer,
-                filter: None,
-                filter_bitmap: None,
-            };
-            let neighbors = self.search_layer(ctx, current_ep, &nodes, storage)?;
+        // Update entry point if new node has higher layer
+        if node_level > current_max_layer {
+            *entry_point = Some(internal_id);
+            *max_layer = node_level;
+        }
 
-            // Select M best neighbors using heuristic
-            let m = if layer == 0 {
-                self.config.m0
-            } else {
-                self.config.m
-            };
-            let selected = self.select_neighbors(&neighbors, m, storage);
-
-            // Connect new node to selected neighbors
-            let node_idx = internal_id.as_usize();
-            nodes[node_idx].neighbors[layer] = selected.iter().map(|c| c.id).collect();
-
-            // Add bidirectional connections
-            for neighbor in &selected {
-                let neighbor_idx = neighbor.id.as_usize();
-                let neighbor_node = &mut nodes[neighbor_idx];
-
-                if neighbor_node.max_layer >= layer {
-                    neighbor_node.neighbors[layer].push(internal_id);
-
-                    // Prune if too many connections
-                    let max_connections = if layer == 0 {
-                        self.config.m0
-                    } else {
-                        self.config.m
-                    };
-
-                    if neighbor_node.neighbors[layer].len() > max_connections {
-                        // Get distances and prune
-                        if let Some(nv) = storage.get_vector_data(neighbor.id) {
-                            let mut candidates: Vec<Candidate> = neighbor_node.neighbors[layer]
-                                .iter()
-                                .filter_map(|&n_id| {
-                                    storage
-                                        .distance(n_id, &nv, self.distance_metric)
-                                        .map(|dist| Candidate {
-                                            id: n_id,
-                                            distance: dist,
-                                        })
-                                })
-                                .collect();
-                            candidates.sort_by(|a, b| {
-                                a.distance
-                                    .partial_cmp(&b.distance)
-                                    .unwrap_or(Ordering::Equal)
-                            });
-                            neighbor_node.neighbors[layer] = candidates
-                                .into_iter()
-                                .take(max_connections)
-                                .map(|c| c.id)
-                                .collect();
-                        }
-                    }
-                }
+        Ok(())
+    }
+
+    /// Link `internal_id` to `selected` on `layer`, adding the reverse links
+    /// and pruning neighbors left with too many
+    fn connect(
+        &self,
+        nodes: &mut [HnswNode],
+        internal_id: InternalId,
+        selected: &[Candidate],
+        layer: usize,
+        storage: &impl VectorStorageTrait,
+    ) {
+        // Connect new node to selected neighbors
+        let node_idx = internal_id.as_usize();
+        nodes[node_idx].neighbors[layer] = selected.iter().map(|c| c.id).collect();
+
+        // Add bidirectional connections
+        for neighbor in selected {
+            let neighbor_node = &mut nodes[neighbor.id.as_usize()];
+            if neighbor_node.max_layer >= layer {
+                neighbor_node.neighbors[layer].push(internal_id);
+                self.prune(neighbor_node, layer, storage);
             }
+        }
+    }
 
-            if !selected.is_empty() {
-                current_ep = selected[0].id;
+    /// Keep the closest of `node`'s neighbors on `layer` if it has too many
+    fn prune(&self, node: &mut HnswNode, layer: usize, storage: &impl VectorStorageTrait) {
+        let max_connections = self.max_links(layer);
+        if node.neighbors[layer].len() <= max_connections {
+            return;
+        }
+        // Get distances and prune
+        if let Some(nv) = storage.get_vector_data(node.id) {
+            let mut candidates: Vec<Candidate> = node.neighbors[layer]
+                .iter()
+                .filter_map(|&n_id| {
+                    storage
+                        .distance(n_id, &nv, self.distance_metric)
+                        .map(|dist| Candidate {
+                            id: n_id,
+                            distance: dist,
+                        })
+                })
+                .collect();
+            candidates.sort_by(|a, b| {
+                a.distance
+                    .partial_cmp(&b.distance)
+                    .unwrap_or(Ordering::Equal)
+            });
+            node.neighbors[layer] = candidates
+                .into_iter()
+                .take(max_connections)
+                .map(|c| c.id)
+                .collect();
+        }
+    }
+
+    /// Build the graph from precomputed nearest-neighbor lists, such as a
+    /// kNN graph computed offline, instead of searching for every node's
+    /// neighbors
+    ///
+    /// Node `i` is internal ID `i`, whose vector must be in `storage`, and
+    /// `neighbors[i]` lists its candidate neighbors in any order. On layer 0
+    /// each node keeps up to `m0` of its candidates, picked by the same
+    /// heuristic as an insert, and is linked back from them. The upper layers
+    /// hold about one node in `m` and are built by regular insertion. Fails
+    /// unless the index is empty.
+    pub fn build_from_neighbors(
+        &self,
+        neighbors: &[Vec<InternalId>],
+        storage: &impl IndexStorage,
+    ) -> Result<()> {
+        if !self.is_empty() {
+            return Err(Error::InvalidConfig(
+                "Neighbor lists can only be loaded into an empty index".to_string(),
+            ));
+        }
+        let n = neighbors.len();
+        let mut nodes: Vec<HnswNode> = (0..n)
+            .map(|i| HnswNode::new(InternalId::from(i), self.random_level()))
+            .collect();
+
+        let select = |i: usize| -> Vec<InternalId> {
+            let id = InternalId::from(i);
+            let Some(vector) = storage.get_vector_data(id) else {
+                return Vec::new();
+            };
+            let mut candidates: Vec<Candidate> = neighbors[i]
+                .iter()
+                .filter(|&&n_id| n_id != id && n_id.as_usize() < n)
+                .filter_map(|&n_id| {
+                    storage
+                        .distance(n_id, &vector, self.distance_metric)
+                        .map(|distance| Candidate { id: n_id, distance })
+                })
+                .collect();
+            candidates.sort_by(|a, b| {
+                a.distance
+                    .partial_cmp(&b.distance)
+                    .unwrap_or(Ordering::Equal)
+            });
+            candidates.dedup_by_key(|c| c.id);
+            self.select_neighbors(&candidates, self.config.m0, storage)
+                .into_iter()
+                .map(|c| c.id)
+                .collect()
+        };
+        #[cfg(feature = "parallel")]
+        let selected: Vec<Vec<InternalId>> = {
+            use rayon::prelude::*;
+            (0..n).into_par_iter().map(select).collect()
+        };
+        #[cfg(not(feature = "parallel"))]
+        let selected: Vec<Vec<InternalId>> = (0..n).map(select).collect();
+
+        for (i, forward) in selected.iter().enumerate() {
+            nodes[i].neighbors[0] = forward.clone();
+        }
+        for (i, forward) in selected.into_iter().enumerate() {
+            for neighbor in forward {
+                let links = &mut nodes[neighbor.as_usize()].neighbors[0];
+                let id = InternalId::from(i);
+                if !links.contains(&id) {
+                    links.push(id);
+                }
             }
         }
+        for node in nodes.iter_mut() {
+            self.prune(node, 0, storage);
+        }
 
-        // Update entry point if new node has higher layer
-        if node_level > current_max_layer {
-            *entry_point = Some(internal_id);
-            *max_layer = node_level;
+        // Only nodes above layer 0 are inserted, on their upper layers
+        let mut entry_point: Option<InternalId> = None;
+        let mut max_layer = 0;
+        for i in 0..n {
+            let id = InternalId::from(i);
+            let level = nodes[i].max_layer;
+            let Some(ep) = entry_point else {
+                entry_point = Some(id);
+                max_layer = level;
+                continue;
+            };
+            if level == 0 {
+                continue;
+            }
+            let Some(vector) = storage.get_vector_data(id) else {
+                continue;
+            };
+            let mut current_ep = ep;
+            for layer in (level + 1..=max_layer).rev() {
+                current_ep = self.search_layer_single(
+                    &vector,
+                    current_ep,
+                    layer,
+                    &nodes,
+                    storage,
+                    &mut SearchUsage::default(),
+                )?;
+            }
+            for layer in (1..=level.min(max_layer)).rev() {
+                let ctx = SearchContext {
+                    query: &vector,
+                    ef: self.config.ef_construction,
+                    layer,
+                    filter: None,
+                    filter_bitmap: None,
+                    seed: None,
+                    max_distance: f32::INFINITY,
+                    include_deleted: true,
+                };
+                let candidates = self.search_layer(
+                    ctx,
+                    current_ep,
+                    &nodes,
+                    storage,
+                    &mut SearchUsage::default(),
+                )?;
+                let selected = self.select_neighbors(&candidates, self.config.m, storage);
+                self.connect(&mut nodes, id, &selected, layer, storage);
+                if let Some(nearest) = selected.first() {
+                    current_ep = nearest.id;
+                }
+            }
+            if level > max_layer {
+                max_layer = level;
+                entry_point = Some(id);
+            }
         }
 
+        self.load_state(HnswState {
+            nodes,
+            entry_point,
+            max_layer,
+        });
         Ok(())
     }
 
@@ -543,17 +708,21 @@ impl HnswIndex {
         layer: usize,
         nodes: &[HnswNode],
         storage: &impl VectorStorageTrait,
+        usage: &mut SearchUsage,
     ) -> Result<InternalId> {
         let mut current = entry;
         let mut current_dist = storage
             .distance(entry, query, self.distance_metric)
             .unwrap_or(f32::MAX);
+        usage.vectors_scanned += 1;
 
         loop {
             let node = &nodes[current.as_usize()];
             let mut changed = false;
+            usage.graph_hops += 1;
 
             if node.max_layer >= layer {
+                usage.vectors_scanned += node.neighbors[layer].len() as u64;
                 for &neighbor_id in &node.neighbors[layer] {
                     if let Some(dist) = storage.distance(neighbor_id, query, self.distance_metric) {
                         if dist < current_dist {
@@ -580,54 +749,66 @@ impl HnswIndex {
         entry: InternalId,
         nodes: &[HnswNode],
         storage: &impl VectorStorageTrait,
+        usage: &mut SearchUsage,
     ) -> Result<Vec<Candidate>> {
         let visited_cap = ctx.ef.saturating_mul(4).max(64);
         let mut visited = HashSet::with_capacity(visited_cap);
         let mut candidates = BinaryHeap::with_capacity(ctx.ef + 1); // min-heap
         let mut results = BinaryHeap::with_capacity(ctx.ef + 1); // max-heap
 
-        let entry_dist = storage
-            .distance(entry, ctx.query, self.distance_metric)
-            .unwrap_or(f32::MAX);
-
-        visited.insert(entry);
-        candidates.push(Candidate {
-            id: entry,
-            distance: entry_dist,
-        });
-
-        // Check if entry point matches filter and is not deleted
-        let entry_matches = if let Some(ref bitmap) = ctx.filter_bitmap {
-            bitmap.contains(entry.as_u32())
-        } else if let Some(f) = ctx.filter {
-            storage
-                .get_metadata(entry)
-                .map(|m| f.matches(&m))
-                .unwrap_or(false)
-        } else {
-            true
-        };
-        let entry_valid = !storage.is_deleted(entry) && entry_matches;
+        for entry in std::iter::once(entry).chain(ctx.seed) {
+            if !visited.insert(entry) {
+                continue;
+            }
+            let entry_dist = storage
+                .distance(entry, ctx.query, self.distance_metric)
+                .unwrap_or(f32::MAX);
+            usage.vectors_scanned += 1;
 
-        if entry_valid {
-            results.push(MaxCandidate {
+            candidates.push(Candidate {
                 id: entry,
                 distance: entry_dist,
             });
+
+            // Check if entry point matches filter and is not deleted
+            let entry_matches = if let Some(ref bitmap) = ctx.filter_bitmap {
+                bitmap.contains(entry.as_u32())
+            } else if let Some(f) = ctx.filter {
+                storage
+                    .get_metadata(entry)
+                    .map(|m| f.matches(&m))
+                    .unwrap_or(false)
+            } else {
+                true
+            };
+            let entry_valid = (ctx.include_deleted || !storage.is_deleted(entry)) && entry_matches;
+
+            if entry_valid {
+                results.push(MaxCandidate {
+                    id: entry,
+                    distance: entry_dist,
+                });
+            }
         }
 
+        // Results beyond the cutoff still steer the search towards the query
+        // until it finds one within it. From then on every candidate left is
+        // beyond the cutoff once the closest one is.
+        let mut within = results.iter().any(|c| c.distance <= ctx.max_distance);
         while let Some(current) = candidates.pop() {
             // Get the furthest result
             let furthest = results.peek().map(|c| c.distance).unwrap_or(f32::MAX);
 
-            if current.distance > furthest {
+            if current.distance > furthest || (within && current.distance > ctx.max_distance) {
                 break;
             }
 
             let node = &nodes[current.id.as_usize()];
+            usage.graph_hops += 1;
             if node.max_layer >= ctx.layer {
                 for &neighbor_id in &node.neighbors[ctx.layer] {
                     if visited.insert(neighbor_id) {
+                        usage.vectors_scanned += 1;
                         if let Some(dist) =
                             storage.distance(neighbor_id, ctx.query, self.distance_metric)
                         {
@@ -650,10 +831,12 @@ impl HnswIndex {
                                 } else {
                                     true
                                 };
-                                let neighbor_valid =
-                                    !storage.is_deleted(neighbor_id) && matches_filter;
+                                let neighbor_valid = (ctx.include_deleted
+                                    || !storage.is_deleted(neighbor_id))
+                                    && matches_filter;
 
                                 if neighbor_valid {
+                                    within |= dist <= ctx.max_distance;
                                     results.push(MaxCandidate {
                                         id: neighbor_id,
                                         distance: dist,
@@ -673,6 +856,7 @@ impl HnswIndex {
         // Convert results to sorted vector
         let mut result_vec: Vec<Candidate> = results
             .into_iter()
+            .filter(|c| c.distance <= ctx.max_distance)
             .map(|c| Candidate {
                 id: c.id,
                 distance: c.distance,
@@ -758,6 +942,51 @@ impl HnswIndex {
         k: usize,
         storage: &impl VectorStorageTrait,
         filter: Option<&Filter>,
+    ) -> Result<Vec<(InternalId, f32)>> {
+        self.search_with_usage(query, k, storage, filter, &mut SearchUsage::default())
+    }
+
+    /// Search for k nearest neighbors, accumulating traversal cost into `usage`
+    pub fn search_with_usage(
+        &self,
+        query: &[f32],
+        k: usize,
+        storage: &impl VectorStorageTrait,
+        filter: Option<&Filter>,
+        usage: &mut SearchUsage,
+    ) -> Result<Vec<(InternalId, f32)>> {
+        self.search_with_ef(query, k, None, storage, filter, usage)
+    }
+
+    /// [`search_with_usage`](Self::search_with_usage) with `ef_search`
+    /// overriding the configured candidate list size
+    pub fn search_with_ef(
+        &self,
+        query: &[f32],
+        k: usize,
+        ef_search: Option<usize>,
+        storage: &impl VectorStorageTrait,
+        filter: Option<&Filter>,
+        usage: &mut SearchUsage,
+    ) -> Result<Vec<(InternalId, f32)>> {
+        self.search_within(query, k, ef_search, None, storage, filter, usage)
+    }
+
+    /// [`search_with_ef`](Self::search_with_ef) returning only neighbors
+    /// within `max_distance`
+    ///
+    /// The cutoff also ends the layer 0 traversal early: once a neighbor
+    /// within it is found, the search stops at the first candidate beyond it.
+    #[allow(clippy::too_many_arguments)]
+    pub fn search_within(
+        &self,
+        query: &[f32],
+        k: usize,
+        ef_search: Option<usize>,
+        max_distance: Option<f32>,
+        storage: &impl VectorStorageTrait,
+        filter: Option<&Filter>,
+        usage: &mut SearchUsage,
     ) -> Result<Vec<(InternalId, f32)>> {
         let nodes = self.nodes.read();
         let entry_point = self.entry_point.read();
@@ -771,31 +1000,225 @@ impl HnswIndex {
         // Traverse from top layer to layer 1
         let mut current_ep = ep;
         for layer in (1..=max_layer).rev() {
-            current_ep = self.search_layer_single(query, current_ep, layer, &nodes, storage)?;
+            current_ep =
+                self.search_layer_single(query, current_ep, layer, &nodes, storage, usage)?;
         }
 
         // Search in layer 0 with ef_search
-        let ef = self.config.ef_search.max(k);
+        let ef = ef_search.unwrap_or(self.config.ef_search).max(k);
         let filter_bitmap = if bitmap_filter_enabled() {
             filter.and_then(|f| storage.filter_bitmap(f))
         } else {
             None
         };
+        // A cached filter may know a matching node to start from, which keeps
+        // selective filters from stalling outside their subset
+        let seed = filter
+            .and_then(|f| storage.filter_entry(f))
+            .filter(|seed| seed.as_usize() < nodes.len());
         let ctx = SearchContext {
             query,
             ef,
             layer: 0,
             filter,
             filter_bitmap,
+            seed,
+            max_distance: max_distance.unwrap_or(f32::INFINITY),
+            include_deleted: false,
         };
-        let candidates = self.search_layer(ctx, current_ep, &nodes, storage)?;
+        let candidates = self.search_layer(ctx, current_ep, &nodes, storage, usage)?;
 
         // Return top k
-        Ok(candidates
+        let results: Vec<_> = candidates
             .into_iter()
             .take(k)
             .map(|c| (c.id, c.distance))
-            .collect())
+            .collect();
+        self.record_heat(&results);
+        Ok(results)
+    }
+
+    /// Count a search returning `results`
+    fn record_heat(&self, results: &[(InternalId, f32)]) {
+        let Some(top) = results.iter().map(|(id, _)| id.as_usize()).max() else {
+            return;
+        };
+        if top >= self.heat.read().len() {
+            self.heat.write().resize_with(top + 1, AtomicU32::default);
+        }
+        let heat = self.heat.read();
+        for (id, _) in results {
+            if let Some(count) = heat.get(id.as_usize()) {
+                count.fetch_add(1, AtomicOrdering::Relaxed);
+            }
+        }
+    }
+
+    /// Relink up to `budget` live nodes whose links lead to deleted or
+    /// missing nodes, those in the most searched regions first
+    ///
+    /// A region's heat is how often the node and its layer 0 neighbors were
+    /// returned by searches. A relinked node keeps its live neighbors and
+    /// gains the live nodes its deleted neighbors linked to, following chains
+    /// of deleted nodes, and keeps the best of them by the insert heuristic.
+    /// Deleted nodes keep their links, so searches still route through them.
+    /// Heat is halved after the pass, so priorities follow recent traffic.
+    pub fn repair(&self, budget: usize, storage: &impl VectorStorageTrait) -> GraphRepair {
+        let mut damaged: Vec<(u64, InternalId)> = {
+            let nodes = self.nodes.read();
+            let heat = self.heat.read();
+            let heat_of = |id: InternalId| {
+                heat.get(id.as_usize())
+                    .map_or(0, |count| count.load(AtomicOrdering::Relaxed) as u64)
+            };
+            nodes
+                .iter()
+                .filter(|node| {
+                    !storage.is_deleted(node.id) && self.is_damaged(node, &nodes, storage)
+                })
+                .map(|node| {
+                    let region = node.neighbors[0].iter().map(|&n| heat_of(n)).sum::<u64>();
+                    (heat_of(node.id) + region, node.id)
+                })
+                .collect()
+        };
+        damaged.sort_by_key(|&(heat, _)| std::cmp::Reverse(heat));
+
+        let relinked = damaged
+            .iter()
+            .take(budget)
+            .filter(|&&(_, id)| self.relink(id, storage))
+            .count();
+        for count in self.heat.read().iter() {
+            let halved = count.load(AtomicOrdering::Relaxed) / 2;
+            count.store(halved, AtomicOrdering::Relaxed);
+        }
+        // Relinking a node prunes its new neighbors, which can heal them too
+        let nodes = self.nodes.read();
+        let backlog = damaged
+            .iter()
+            .filter(|&&(_, id)| {
+                nodes
+                    .get(id.as_usize())
+                    .is_some_and(|node| self.is_damaged(node, &nodes, storage))
+            })
+            .count();
+        GraphRepair { relinked, backlog }
+    }
+
+    /// Whether `node` links to a deleted or missing node, or to itself
+    fn is_damaged(
+        &self,
+        node: &HnswNode,
+        nodes: &[HnswNode],
+        storage: &impl VectorStorageTrait,
+    ) -> bool {
+        node.neighbors.iter().enumerate().any(|(layer, neighbors)| {
+            neighbors.iter().any(|&n| {
+                n == node.id
+                    || storage.is_deleted(n)
+                    || nodes
+                        .get(n.as_usize())
+                        .is_none_or(|other| other.max_layer < layer)
+            })
+        })
+    }
+
+    /// Replace the links of `id` to deleted or missing nodes with links to
+    /// the live nodes behind them, or on an upper layer with none behind
+    /// them, to the closest live nodes of the layer, if any; returns whether
+    /// it was damaged and no longer is
+    fn relink(&self, id: InternalId, storage: &impl VectorStorageTrait) -> bool {
+        let Some(vector) = storage.get_vector_data(id) else {
+            return false;
+        };
+        let mut nodes = self.nodes.write();
+        let Some(node) = nodes.get(id.as_usize()) else {
+            return false;
+        };
+        if storage.is_deleted(id) || !self.is_damaged(node, &nodes, storage) {
+            return false;
+        }
+
+        let layers = node.neighbors.len().min(node.max_layer + 1);
+        for layer in 0..layers {
+            let max_links = self.max_links(layer);
+            // Walk past deleted nodes, looking at a few times the links kept
+            let mut seen = HashSet::from([id]);
+            let mut queue: VecDeque<InternalId> = nodes[id.as_usize()].neighbors[layer]
+                .iter()
+                .copied()
+                .collect();
+            let mut live = Vec::new();
+            while let Some(n) = queue.pop_front() {
+                if !seen.insert(n) {
+                    continue;
+                }
+                let Some(links) = nodes
+                    .get(n.as_usize())
+                    .filter(|other| other.max_layer >= layer)
+                    .and_then(|other| other.neighbors.get(layer))
+                else {
+                    continue;
+                };
+                if !storage.is_deleted(n) {
+                    live.push(n);
+                } else if seen.len() < max_links * 4 {
+                    queue.extend(links.iter().copied());
+                }
+            }
+            if live.is_empty() && layer > 0 {
+                // Upper layers are small enough to look through whole
+                live = nodes
+                    .iter()
+                    .filter(|other| {
+                        other.id != id && other.max_layer >= layer && !storage.is_deleted(other.id)
+                    })
+                    .map(|other| other.id)
+                    .collect();
+            }
+
+            let mut candidates: Vec<Candidate> = live
+                .into_iter()
+                .filter_map(|n| {
+                    storage
+                        .distance(n, &vector, self.distance_metric)
+                        .map(|distance| Candidate { id: n, distance })
+                })
+                .collect();
+            candidates.sort_by(|a, b| {
+                a.distance
+                    .partial_cmp(&b.distance)
+                    .unwrap_or(Ordering::Equal)
+            });
+            let selected = self.select_neighbors(&candidates, max_links, storage);
+            if selected.is_empty() && layer == 0 {
+                // Nothing live is reachable; the old links still route
+                continue;
+            }
+
+            let previous = std::mem::take(&mut nodes[id.as_usize()].neighbors[layer]);
+            nodes[id.as_usize()].neighbors[layer] = selected.iter().map(|c| c.id).collect();
+            for neighbor in selected.iter().filter(|c| !previous.contains(&c.id)) {
+                let neighbor_node = &mut nodes[neighbor.id.as_usize()];
+                match neighbor_node.neighbors.get_mut(layer) {
+                    Some(links) if !links.contains(&id) => links.push(id),
+                    _ => continue,
+                }
+                self.prune(neighbor_node, layer, storage);
+            }
+        }
+        !self.is_damaged(&nodes[id.as_usize()], &nodes, storage)
+    }
+
+    /// Settings the graph is built with
+    pub fn config(&self) -> &HnswConfig {
+        &self.config
+    }
+
+    /// Metric the graph is built with
+    pub fn distance_metric(&self) -> DistanceMetric {
+        self.distance_metric
     }
 
     /// Get the number of nodes in the index
@@ -830,6 +1253,187 @@ impl HnswIndex {
         *self_nodes = state.nodes;
         *self_entry_point = state.entry_point;
         *self_max_layer = state.max_layer;
+        self.heat.write().clear();
+    }
+
+    /// Record broken links, layers and entry points of the graph in `report`
+    pub fn check_integrity(&self, report: &mut IntegrityReport) {
+        let nodes = self.nodes.read();
+        let max_layer = *self.max_layer.read();
+        report.graph_nodes = nodes.len();
+
+        match *self.entry_point.read() {
+            None if !nodes.is_empty() => {
+                report.problem("The graph has nodes but no entry point".to_string())
+            }
+            None => {}
+            Some(entry) => match nodes.get(entry.as_usize()) {
+                None => report.problem(format!("Entry point {} is not a node", entry.as_usize())),
+                Some(node) if node.max_layer != max_layer => report.problem(format!(
+                    "Entry point {} tops out at layer {}, below the graph's {}",
+                    entry.as_usize(),
+                    node.max_layer,
+                    max_layer
+                )),
+                Some(_) => {}
+            },
+        }
+
+        for (slot, node) in nodes.iter().enumerate() {
+            if node.id.as_usize() != slot {
+                report.problem(format!(
+                    "Node {} is stored as node {}",
+                    node.id.as_usize(),
+                    slot
+                ));
+            }
+            if node.max_layer > max_layer {
+                report.problem(format!(
+                    "Node {} is on layer {}, above the graph's {}",
+                    slot, node.max_layer, max_layer
+                ));
+            }
+            if node.neighbors.len() != node.max_layer + 1 {
+                report.problem(format!(
+                    "Node {} has {} neighbor lists for {} layers",
+                    slot,
+                    node.neighbors.len(),
+                    node.max_layer + 1
+                ));
+            }
+            for (layer, neighbors) in node.neighbors.iter().enumerate() {
+                for neighbor in neighbors {
+                    let target = neighbor.as_usize();
+                    match nodes.get(target) {
+                        None => report.problem(format!(
+                            "Node {} links to missing node {} on layer {}",
+                            slot, target, layer
+                        )),
+                        Some(_) if target == slot => report
+                            .problem(format!("Node {} links to itself on layer {}", slot, layer)),
+                        Some(other) if other.max_layer < layer => report.problem(format!(
+                            "Node {} links to node {} on layer {}, which it is not on",
+                            slot, target, layer
+                        )),
+                        Some(_) => {}
+                    }
+                }
+            }
+        }
+    }
+
+    /// Node and link counts of every layer
+    pub fn graph_stats(&self) -> GraphStats {
+        let nodes = self.nodes.read();
+        let max_layer = *self.max_layer.read();
+        let mut layers =
+            vec![LayerStats::default(); if nodes.is_empty() { 0 } else { max_layer + 1 }];
+        for node in nodes.iter() {
+            for (layer, neighbors) in node.neighbors.iter().enumerate() {
+                let Some(stats) = layers.get_mut(layer) else {
+                    continue;
+                };
+                stats.nodes += 1;
+                stats.edges += neighbors.len();
+                stats.max_degree = stats.max_degree.max(neighbors.len());
+                if neighbors.is_empty() {
+                    stats.isolated += 1;
+                }
+            }
+        }
+        for stats in &mut layers {
+            stats.avg_degree = stats.edges as f64 / stats.nodes.max(1) as f64;
+        }
+        GraphStats {
+            nodes: nodes.len(),
+            max_layer,
+            layers,
+        }
+    }
+
+    /// Export the graph adjacency keyed by external IDs
+    ///
+    /// `resolve` maps internal IDs to external ones and returns `None` for
+    /// deleted vectors, which are left out. With `level`, only nodes present on
+    /// that layer and its edges are exported. With `sample`, at most that many
+    /// nodes are taken breadth-first from the entry point, so the subgraph
+    /// stays connected where the index is.
+    pub fn export_graph(
+        &self,
+        level: Option<usize>,
+        sample: Option<usize>,
+        resolve: impl Fn(InternalId) -> Option<VectorId>,
+    ) -> GraphExport {
+        let nodes = self.nodes.read();
+        let entry_point = *self.entry_point.read();
+        let on_level = |node: &HnswNode| level.is_none_or(|l| node.max_layer >= l);
+        let layers = |node: &HnswNode| match level {
+            Some(l) => l..l + 1,
+            None => 0..node.max_layer + 1,
+        };
+
+        // Pick nodes, breadth-first from the entry point when sampling
+        let mut ids: Vec<Option<VectorId>> = vec![None; nodes.len()];
+        let mut order = Vec::new();
+        let limit = sample.unwrap_or(usize::MAX);
+        let mut visited = vec![false; nodes.len()];
+        let mut queue = VecDeque::new();
+        let seeds = entry_point
+            .into_iter()
+            .chain((0..nodes.len()).map(InternalId::from));
+
+        'seeds: for seed in seeds {
+            queue.push_back(seed);
+            while let Some(id) = queue.pop_front() {
+                let idx = id.as_usize();
+                if order.len() >= limit {
+                    break 'seeds;
+                }
+                if idx >= nodes.len() || visited[idx] {
+                    continue;
+                }
+                visited[idx] = true;
+                let node = &nodes[idx];
+                if !on_level(node) {
+                    continue;
+                }
+                let Some(ext_id) = resolve(id) else {
+                    continue;
+                };
+                ids[idx] = Some(ext_id);
+                order.push(idx);
+
+                if sample.is_some() {
+                    for layer in layers(node) {
+                        queue.extend(node.neighbors[layer].iter().copied());
+                    }
+                }
+            }
+        }
+
+        let mut export = GraphExport::default();
+        for &idx in &order {
+            let node = &nodes[idx];
+            let Some(source) = &ids[idx] else {
+                continue;
+            };
+            export.nodes.push(GraphNode {
+                id: source.clone(),
+                max_layer: node.max_layer,
+            });
+            for layer in layers(node) {
+                for neighbor in &node.neighbors[layer] {
+                    if let Some(Some(target)) = ids.get(neighbor.as_usize()) {
+                        export.edges.push(GraphEdge {
+                            source: source.clone(),
+                            target: target.clone(),
+                            layer,
+                        });
+                    }
+                }
+            }
+        }
+        export
     }
 
     /// Get approximate memory usage in bytes
@@ -918,4 +1522,81 @@ mod tests {
         let first_id = storage.get_external_id(results[0].0).unwrap();
         assert_eq!(first_id.as_str(), "vec0");
     }
+
+    #[test]
+    fn test_build_from_neighbors() {
+        let index = HnswIndex::new(HnswConfig::default(), DistanceMetric::Euclidean);
+        let storage = create_test_storage();
+        // Points on a 4-D grid, each given its exact nearest neighbors
+        let vectors: Vec<[f32; 4]> = (0..256)
+            .map(|i| {
+                [
+                    (i % 4) as f32,
+                    (i / 4 % 4) as f32,
+                    (i / 16 % 4) as f32,
+                    (i / 64) as f32,
+                ]
+            })
+            .collect();
+        for (i, v) in vectors.iter().enumerate() {
+            storage.insert(format!("vec{i}").into(), v, None).unwrap();
+        }
+        let neighbors: Vec<Vec<InternalId>> = vectors
+            .iter()
+            .map(|v| {
+                let mut by_distance: Vec<usize> = (0..vectors.len()).collect();
+                by_distance.sort_by(|&a, &b| {
+                    let da = DistanceMetric::Euclidean.distance(v, &vectors[a]);
+                    let db = DistanceMetric::Euclidean.distance(v, &vectors[b]);
+                    da.partial_cmp(&db).unwrap()
+                });
+                by_distance[..12]
+                    .iter()
+                    .map(|&i| InternalId::from(i))
+                    .collect()
+            })
+            .collect();
+
+        index.build_from_neighbors(&neighbors, &storage).unwrap();
+        assert_eq!(index.len(), vectors.len());
+        for (i, v) in vectors.iter().enumerate() {
+            let results = index.search(v, 1, &storage, None).unwrap();
+            assert_eq!(results[0].0, InternalId::from(i));
+        }
+
+        // Only an empty index can be built this way
+        assert!(index.build_from_neighbors(&neighbors, &storage).is_err());
+    }
+
+    #[test]
+    fn test_insert_batch_links_within_batch() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let index = HnswIndex::new(HnswConfig::default(), DistanceMetric::Euclidean);
+        let storage = create_test_storage();
+        let mut rng = StdRng::seed_from_u64(7);
+        // Enough for several rounds of a parallel batch, into an empty index
+        let vectors: Vec<[f32; 4]> = (0..1500).map(|_| rng.gen()).collect();
+        let items: Vec<(InternalId, &[f32])> = vectors
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                let id = storage.insert(format!("vec{i}").into(), v, None).unwrap();
+                (id, v.as_slice())
+            })
+            .collect();
+        index.insert_batch(&items[..1], &storage).unwrap();
+        index.insert_batch(&items[1..], &storage).unwrap();
+
+        assert_eq!(index.len(), vectors.len());
+        assert_eq!(index.graph_stats().layers[0].isolated, 0);
+        let found = vectors
+            .iter()
+            .enumerate()
+            .filter(|(i, v)| {
+                index.search(*v, 1, &storage, None).unwrap()[0].0 == InternalId::from(*i)
+            })
+            .count();
+        assert!(found >= vectors.len() * 99 / 100, "found {found}");
+    }
 }
@@ -0,0 +1,258 @@
+//! Compact mapping between external IDs and internal slots
+//!
+//! Every slot's external ID is stored exactly once, in slot order. The lookup
+//! side is a hash table of slot numbers only, hashed by the ID they point at,
+//! so a string ID costs one shared allocation instead of a key copy per map.
+
+use crate::error::{Error, Result};
+use crate::types::{InternalId, ListCursor, VectorId};
+use hashbrown::HashTable;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, RandomState};
+
+pub(crate) struct IdMap {
+    /// External ID of every slot, including deleted and overwritten ones
+    ids: Vec<VectorId>,
+    /// Live slots, hashed by their external ID
+    index: HashTable<InternalId>,
+    hasher: RandomState,
+    /// Heap bytes owned by the IDs in `ids`
+    id_heap_bytes: usize,
+    /// Slot that replaced each overwritten slot, including slots whose ID
+    /// was removed and pushed again
+    successors: HashMap<InternalId, InternalId>,
+    /// Last slot of each removed ID, until the ID is pushed again
+    vacated: HashMap<VectorId, InternalId>,
+    /// Random tag of this slot numbering, checked by list cursors
+    epoch: u64,
+}
+
+impl IdMap {
+    pub fn new() -> Self {
+        Self {
+            ids: Vec::new(),
+            index: HashTable::new(),
+            hasher: RandomState::new(),
+            id_heap_bytes: 0,
+            successors: HashMap::new(),
+            vacated: HashMap::new(),
+            epoch: rand::random(),
+        }
+    }
+
+    /// Live slot currently mapped to `id`
+    pub fn get(&self, id: &VectorId) -> Option<InternalId> {
+        let hash = self.hasher.hash_one(id);
+        self.index
+            .find(hash, |slot| self.ids[slot.as_usize()] == *id)
+            .copied()
+    }
+
+    pub fn contains(&self, id: &VectorId) -> bool {
+        self.get(id).is_some()
+    }
+
+    /// External ID stored for a slot (also for deleted slots)
+    pub fn external(&self, internal_id: InternalId) -> Option<&VectorId> {
+        self.ids.get(internal_id.as_usize())
+    }
+
+    /// Append a slot for `id` and map `id` to it
+    ///
+    /// Returns the new slot and the slot `id` was previously mapped to.
+    pub fn push(&mut self, id: VectorId) -> (InternalId, Option<InternalId>) {
+        let internal_id = InternalId::from(self.ids.len());
+        let hash = self.hasher.hash_one(&id);
+        let Self {
+            ids,
+            index,
+            hasher,
+            id_heap_bytes,
+            successors,
+            vacated,
+            ..
+        } = self;
+
+        let previous = match index.find_mut(hash, |slot| ids[slot.as_usize()] == id) {
+            Some(slot) => Some(std::mem::replace(slot, internal_id)),
+            None => {
+                index.insert_unique(hash, internal_id, |slot| {
+                    hasher.hash_one(&ids[slot.as_usize()])
+                });
+                None
+            }
+        };
+
+        // Upserts that delete before inserting still count as overwrites
+        let replaced = match previous {
+            None if !vacated.is_empty() => vacated.remove(&id),
+            previous => previous,
+        };
+        if let Some(replaced) = replaced {
+            successors.insert(replaced, internal_id);
+        }
+        *id_heap_bytes += id.heap_size();
+        ids.push(id);
+        (internal_id, previous)
+    }
+
+    /// Unmap `id`, keeping its slot; returns the slot it was mapped to
+    pub fn remove(&mut self, id: &VectorId) -> Option<InternalId> {
+        let hash = self.hasher.hash_one(id);
+        let ids = &self.ids;
+        let slot = self
+            .index
+            .find_entry(hash, |slot| ids[slot.as_usize()] == *id)
+            .ok()
+            .map(|entry| entry.remove().0)?;
+        self.vacated.insert(id.clone(), slot);
+        Some(slot)
+    }
+
+    /// Number of live IDs
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Number of slots, including deleted and overwritten ones
+    pub fn slots(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Approximate bytes used by both directions of the mapping
+    pub fn memory_usage(&self) -> usize {
+        self.ids.capacity() * std::mem::size_of::<VectorId>()
+            + self.id_heap_bytes
+            + self.index.capacity() * (std::mem::size_of::<InternalId>() + 1)
+            + self.successors.capacity() * (2 * std::mem::size_of::<InternalId>() + 1)
+            + self.vacated.capacity() * (std::mem::size_of::<(VectorId, InternalId)>() + 1)
+    }
+
+    /// Cursor for a listing starting now
+    pub fn start_listing(&self) -> ListCursor {
+        ListCursor {
+            epoch: self.epoch,
+            frontier: self.ids.len() as u64,
+            next: 0,
+        }
+    }
+
+    /// Up to `limit` live slots from `cursor` on, and the cursor after them
+    ///
+    /// Each record live at the cursor's frontier is listed once, at the slot
+    /// it held then, even if it was overwritten since; the returned slot is
+    /// its current one. The next cursor is `None` after the last page.
+    pub fn page(
+        &self,
+        cursor: ListCursor,
+        limit: usize,
+    ) -> Result<(Vec<InternalId>, Option<ListCursor>)> {
+        if cursor.epoch != self.epoch
+            || cursor.frontier > self.ids.len() as u64
+            || cursor.next > cursor.frontier
+        {
+            return Err(Error::InvalidConfig(
+                "List cursor is no longer valid; start the listing again".to_string(),
+            ));
+        }
+
+        let frontier = cursor.frontier as usize;
+        let mut slot = cursor.next as usize;
+        let mut found = Vec::new();
+        while slot < frontier && found.len() < limit {
+            let internal_id = InternalId::from(slot);
+            if let Some(live) = self.get(&self.ids[slot]) {
+                // Overwritten past the frontier means it was live at the frontier
+                let listed_here = live == internal_id
+                    || self
+                        .successors
+                        .get(&internal_id)
+                        .is_some_and(|next| next.as_usize() >= frontier);
+                if listed_here {
+                    found.push(live);
+                }
+            }
+            slot += 1;
+        }
+
+        let next = (slot < frontier).then_some(ListCursor {
+            next: slot as u64,
+            ..cursor
+        });
+        Ok((found, next))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_get_remove() {
+        let mut map = IdMap::new();
+        let (a, prev) = map.push(VectorId::from("a"));
+        assert_eq!(prev, None);
+        let (b, _) = map.push(VectorId::from("b"));
+
+        assert_eq!(map.get(&VectorId::from("a")), Some(a));
+        assert_eq!(map.get(&VectorId::from("b")), Some(b));
+        assert_eq!(map.get(&VectorId::from("c")), None);
+        assert_eq!(map.external(b), Some(&VectorId::from("b")));
+
+        let (a2, prev) = map.push(VectorId::from("a"));
+        assert_eq!(prev, Some(a));
+        assert_eq!(map.get(&VectorId::from("a")), Some(a2));
+        assert_eq!((map.len(), map.slots()), (2, 3));
+
+        assert_eq!(map.remove(&VectorId::from("a")), Some(a2));
+        assert_eq!(map.remove(&VectorId::from("a")), None);
+        assert!(!map.contains(&VectorId::from("a")));
+        assert_eq!(map.external(a), Some(&VectorId::from("a")));
+        assert_eq!((map.len(), map.slots()), (1, 3));
+    }
+
+    #[test]
+    fn test_page_keeps_place_of_overwritten_ids() {
+        let mut map = IdMap::new();
+        for id in ["a", "b", "c", "d"] {
+            map.push(VectorId::from(id));
+        }
+        // Overwritten before the listing starts, so listed at its new slot
+        let (d, _) = map.push(VectorId::from("d"));
+        let cursor = map.start_listing();
+
+        let (first, cursor) = map.page(cursor, 2).unwrap();
+        assert_eq!(first, vec![InternalId::from(0), InternalId::from(1)]);
+        let cursor = cursor.unwrap();
+
+        // Writes after the listing started: a listed and an unlisted record
+        // are overwritten, one is deleted and one is new
+        map.push(VectorId::from("a"));
+        let (c, _) = map.push(VectorId::from("c"));
+        let (c2, _) = map.push(VectorId::from("c"));
+        map.remove(&VectorId::from("b"));
+        map.push(VectorId::from("e"));
+        assert_ne!(c, c2);
+
+        let (rest, next) = map.page(cursor, 10).unwrap();
+        assert_eq!(rest, vec![c2, d]);
+        assert_eq!(next, None);
+
+        let mut other = IdMap::new();
+        other.push(VectorId::from("a"));
+        assert!(other.page(cursor, 10).is_err());
+    }
+
+    #[test]
+    fn test_grows_past_initial_capacity() {
+        let mut map = IdMap::new();
+        for i in 0..10_000u64 {
+            map.push(VectorId::from(format!("id-{i}")));
+        }
+        for i in (0..10_000u64).step_by(997) {
+            let id = VectorId::from(format!("id-{i}"));
+            assert_eq!(map.get(&id), Some(InternalId::from(i as usize)));
+        }
+        assert!(map.memory_usage() > 10_000 * std::mem::size_of::<VectorId>());
+    }
+}
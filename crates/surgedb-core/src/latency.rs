@@ -0,0 +1,201 @@
+//! Per-operation latency histograms of a collection
+//!
+//! Every [`Collection`](crate::db::Collection) records how long its inserts,
+//! searches and deletes take, including waiting for the collection's lock, in
+//! HDR histograms with two significant digits. They live in the engine, so an
+//! embedded [`Database`](crate::db::Database) reports the same figures the
+//! server's `/stats` does. Histograms start empty when a collection is opened.
+//!
+//! A batch upsert is one insert sample; a batch search records each query.
+//! On WASM, which has no monotonic clock, nothing is recorded. Each sample is
+//! also counted in the collection's [recent activity](crate::activity).
+
+use crate::activity::{ActivityMinute, ActivityRecorder};
+use crate::sync::RwLock;
+use hdrhistogram::Histogram;
+use serde::Serialize;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Longest latency told apart from longer ones, in microseconds
+const MAX_TRACKED_MICROS: u64 = 60_000_000;
+
+/// Operations whose latency is tracked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// Inserts and upserts, single or batched
+    Insert,
+    /// Searches of every kind
+    Search,
+    Delete,
+}
+
+/// Latency distribution of one operation, in microseconds
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LatencySummary {
+    pub count: u64,
+    pub mean_us: f64,
+    pub p50_us: u64,
+    pub p90_us: u64,
+    pub p99_us: u64,
+    pub p999_us: u64,
+    pub max_us: u64,
+}
+
+/// Latency distributions of a collection's operations
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct OperationLatencies {
+    pub insert: LatencySummary,
+    pub search: LatencySummary,
+    pub delete: LatencySummary,
+}
+
+pub(crate) struct LatencyRecorder {
+    insert: RwLock<Histogram<u64>>,
+    search: RwLock<Histogram<u64>>,
+    delete: RwLock<Histogram<u64>>,
+    activity: ActivityRecorder,
+}
+
+impl Default for LatencyRecorder {
+    fn default() -> Self {
+        let histogram = || {
+            RwLock::new(
+                Histogram::new_with_bounds(1, MAX_TRACKED_MICROS, 2)
+                    .expect("latency histogram bounds are valid"),
+            )
+        };
+        Self {
+            insert: histogram(),
+            search: histogram(),
+            delete: histogram(),
+            activity: ActivityRecorder::default(),
+        }
+    }
+}
+
+impl LatencyRecorder {
+    fn histogram(&self, op: Operation) -> &RwLock<Histogram<u64>> {
+        match op {
+            Operation::Insert => &self.insert,
+            Operation::Search => &self.search,
+            Operation::Delete => &self.delete,
+        }
+    }
+
+    /// Record one `op` taking `micros`; longer than the tracked range counts
+    /// as the longest tracked latency
+    pub(crate) fn record(&self, op: Operation, micros: u64) {
+        self.histogram(op).write().saturating_record(micros.max(1));
+    }
+
+    /// Record `op` when the returned guard is dropped
+    pub(crate) fn time(&self, op: Operation) -> Timer<'_> {
+        Timer {
+            recorder: self,
+            op,
+            records: 1,
+            #[cfg(not(target_arch = "wasm32"))]
+            start: Instant::now(),
+        }
+    }
+
+    /// Activity of the last [`ACTIVITY_MINUTES`](crate::activity::ACTIVITY_MINUTES)
+    /// minutes, oldest first
+    pub(crate) fn activity(&self) -> Vec<ActivityMinute> {
+        #[cfg(not(target_arch = "wasm32"))]
+        return self.activity.minutes(unix_now());
+        #[cfg(target_arch = "wasm32")]
+        self.activity.minutes(0)
+    }
+
+    pub(crate) fn summary(&self) -> OperationLatencies {
+        let summarize = |op| {
+            let histogram = self.histogram(op).read();
+            if histogram.is_empty() {
+                return LatencySummary::default();
+            }
+            LatencySummary {
+                count: histogram.len(),
+                mean_us: histogram.mean(),
+                p50_us: histogram.value_at_quantile(0.5),
+                p90_us: histogram.value_at_quantile(0.9),
+                p99_us: histogram.value_at_quantile(0.99),
+                p999_us: histogram.value_at_quantile(0.999),
+                max_us: histogram.max(),
+            }
+        };
+        OperationLatencies {
+            insert: summarize(Operation::Insert),
+            search: summarize(Operation::Search),
+            delete: summarize(Operation::Delete),
+        }
+    }
+}
+
+/// Records an operation's latency when dropped
+pub(crate) struct Timer<'a> {
+    recorder: &'a LatencyRecorder,
+    op: Operation,
+    /// Records the operation writes, for the activity counts
+    records: u64,
+    #[cfg(not(target_arch = "wasm32"))]
+    start: Instant,
+}
+
+impl Timer<'_> {
+    /// Count the operation as writing `records` records
+    pub(crate) fn records(mut self, records: usize) -> Self {
+        self.records = records as u64;
+        self
+    }
+}
+
+impl Drop for Timer<'_> {
+    fn drop(&mut self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let micros = self.start.elapsed().as_micros() as u64;
+            self.recorder.record(self.op, micros);
+            self.recorder
+                .activity
+                .record(self.op, micros, self.records, unix_now());
+        }
+        #[cfg(target_arch = "wasm32")]
+        let _ = (self.recorder, self.op, self.records);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_per_operation() {
+        let recorder = LatencyRecorder::default();
+        for micros in 1..=1000 {
+            recorder.record(Operation::Search, micros);
+        }
+        recorder.record(Operation::Insert, 0);
+        recorder.record(Operation::Insert, u64::MAX);
+
+        let summary = recorder.summary();
+        assert_eq!(summary.search.count, 1000);
+        assert!((495..=505).contains(&summary.search.p50_us));
+        assert!((985..=995).contains(&summary.search.p99_us));
+        assert!((995..=1005).contains(&summary.search.max_us));
+
+        // Out of range samples are clamped rather than dropped
+        assert_eq!(summary.insert.count, 2);
+        assert_eq!(summary.insert.p50_us, 1);
+        assert!(summary.insert.max_us >= MAX_TRACKED_MICROS);
+        assert_eq!(summary.delete, LatencySummary::default());
+    }
+}
@@ -47,21 +47,76 @@
 //! db.insert("vec1", &[0.1, 0.2, 0.3, 0.4], None).unwrap();
 //! db.checkpoint().unwrap(); // Create a snapshot
 //! ```
+//!
+//! # Embedding
+//! Applications can link this crate and use SurgeDB in-process, without a
+//! server. [`Database`] holds named collections, in memory or in a directory
+//! it recovers from on open, and is what the server itself runs on.
+//! ```rust,no_run
+//! use serde_json::json;
+//! use surgedb_core::{Config, Database, DistanceMetric};
+//!
+//! let db = Database::open("./vectors").unwrap();
+//! if db.get_collection("docs").is_err() {
+//!     let config = Config::builder(4)
+//!         .distance_metric(DistanceMetric::Euclidean)
+//!         .build()
+//!         .unwrap();
+//!     db.create_collection("docs", config).unwrap();
+//! }
+//! let docs = db.get_collection("docs").unwrap();
+//! docs.upsert("a".into(), &[0.1, 0.2, 0.3, 0.4], Some(json!({ "lang": "en" })))
+//!     .unwrap();
+//!
+//! for (id, distance, _) in docs.search(&[0.1, 0.2, 0.3, 0.4], 5, None).unwrap() {
+//!     println!("{} at {}", id, distance);
+//! }
+//!
+//! // Walk every record without holding the collection locked
+//! for record in docs.scan() {
+//!     let (id, vector, metadata) = record.unwrap();
+//!     println!("{} {:?} {:?}", id, vector, metadata);
+//! }
+//! ```
 
 // Core modules (always available)
+pub mod activity;
+pub mod ann;
 pub mod bitmap_index;
 pub mod distance;
+pub mod distance_to;
 pub mod error;
 pub mod filter;
+mod filter_cache;
+pub mod graph_export;
+pub mod group;
 pub mod hnsw;
+mod id_map;
+pub mod latency;
+pub mod meta;
+mod metadata_store;
 pub mod multi_vector;
+pub mod named;
+pub mod naming;
+pub mod negatives;
+pub mod partition;
 pub mod pq;
 pub mod quantization;
 pub mod quantized_storage;
+pub mod recommend;
+pub mod recovery;
+pub mod scan;
+pub mod segment;
 pub mod sparse;
 pub mod storage;
+pub mod summary;
 pub mod sync;
+pub mod text_index;
+pub mod transform;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod tune;
 pub mod types;
+mod versions;
 
 // Persistence modules (native only, requires filesystem)
 #[cfg(feature = "persistence")]
@@ -70,6 +125,8 @@ pub mod diskann;
 pub mod mmap_db;
 #[cfg(feature = "persistence")]
 pub mod mmap_storage;
+#[cfg(feature = "parquet")]
+pub mod parquet_io;
 #[cfg(feature = "persistence")]
 pub mod persistent;
 #[cfg(feature = "persistence")]
@@ -77,17 +134,44 @@ pub mod snapshot;
 #[cfg(feature = "persistence")]
 pub mod wal;
 
+// Sandboxed filter expressions (optional)
+#[cfg(feature = "expr")]
+pub mod expr;
+
 // Multi-collection database (uses persistence features conditionally)
 pub mod db;
 
 // Re-exports - Core (always available)
+pub use activity::ActivityMinute;
+pub use ann::{AnnIndex, FlatIndex, IndexKind};
 pub use distance::DistanceMetric;
+pub use distance_to::{DistanceBounds, DistanceTo, MAX_DISTANCE_TO_CANDIDATES};
 pub use error::{Error, Result};
+pub use filter_cache::{CachedFilterInfo, MAX_CACHED_FILTERS};
+pub use graph_export::{GraphExport, GraphStats, LayerStats};
+pub use group::{GroupBy, SearchGroup};
 pub use hnsw::{HnswConfig, HnswIndex};
+pub use latency::{LatencySummary, OperationLatencies};
+pub use meta::MetaAccess;
+pub use named::{NamedVectorConfig, NamedVectors};
+pub use naming::NameCase;
+pub use negatives::{HardNegativeQuery, HardNegatives, MinedNegatives};
+pub use partition::PartitionedIndex;
 pub use quantization::{BinaryQuantizer, QuantizationType, SQ8Quantizer};
 pub use quantized_storage::QuantizedStorage;
+pub use recommend::{Recommend, RecommendStrategy};
+pub use recovery::{IntegrityReport, RecoveryMode, RecoveryPhase, RecoveryStatus};
+pub use scan::{Scan, ScanRecord, ScrollPage, MAX_SCROLL_SCANNED};
+pub use sparse::{Fusion, FusionExplanation, HybridHit, SparseVector};
 pub use storage::{VectorStorage, VectorStorageTrait};
-pub use types::{Vector, VectorId};
+pub use summary::{ClusterSummary, CollectionSummary};
+pub use transform::VectorTransform;
+pub use types::{
+    CompactionReport, DtypeConversion, FilterRecall, FilterStrategy, GarbageStats, GraphRepair,
+    GroupCommit, IdType, ListCursor, ListPage, MemoryBreakdown, MetadataCompression,
+    MetadataLimits, NormFilter, PayloadSize, SearchHit, SearchParams, SearchUsage, SyncMode,
+    Vector, VectorDtype, VectorId,
+};
 
 // Re-exports - Persistence (native only)
 #[cfg(feature = "persistence")]
@@ -95,14 +179,14 @@ pub use mmap_db::{MmapConfig, MmapVectorDb};
 #[cfg(feature = "persistence")]
 pub use mmap_storage::MmapStorage;
 #[cfg(feature = "persistence")]
-pub use persistent::{PersistentConfig, PersistentVectorDb};
+pub use persistent::{PendingReplay, PersistentConfig, PersistentVectorDb};
 #[cfg(feature = "persistence")]
 pub use snapshot::{Snapshot, SnapshotManager};
 #[cfg(feature = "persistence")]
-pub use wal::{Wal, WalEntry};
+pub use wal::{LogPosition, PendingSync, Wal, WalEntry, WalRepair};
 
 // Re-exports - Database (conditional based on features)
-pub use db::{Database, DatabaseStats};
+pub use db::{CollectionJob, CollectionUpdate, Database, DatabaseStats, VersionedRecord};
 
 /// Main database configuration (unquantized)
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -117,6 +201,46 @@ pub struct Config {
     pub max_vectors: usize,
     /// Quantization type (None by default)
     pub quantization: QuantizationType,
+    /// Key type of external IDs
+    #[serde(default)]
+    pub id_type: IdType,
+    /// How metadata payloads are stored
+    #[serde(default)]
+    pub metadata_compression: MetadataCompression,
+    /// Metadata field (e.g. `tenant_id`) whose values get their own HNSW
+    /// subgraph, used by searches that filter on a single value of it
+    #[serde(default)]
+    pub partition_field: Option<String>,
+    /// Batch WAL fsyncs of persistent collections (unsynced writes if `None`)
+    #[serde(default)]
+    pub group_commit: Option<GroupCommit>,
+    /// Metadata fields (dot paths) whose text is indexed for keyword search
+    #[serde(default)]
+    pub text_fields: Vec<String>,
+    /// Extra vector spaces records can carry a vector in, searched separately
+    #[serde(default)]
+    pub named_vectors: Vec<NamedVectorConfig>,
+    /// Index the primary vectors are searched with
+    #[serde(default)]
+    pub index: IndexKind,
+    /// Size and nesting limits metadata documents must meet to be written
+    #[serde(default)]
+    pub metadata_limits: MetadataLimits,
+    /// Linear transform of query vectors, and optionally written ones;
+    /// applied by [`Database`] collections
+    #[serde(default)]
+    pub transform: Option<VectorTransform>,
+    /// Band of L2 norms written vectors must fall in; applied by
+    /// [`Database`] collections
+    #[serde(default)]
+    pub norm_filter: Option<NormFilter>,
+    /// Number type written vectors are given in; converted by callers with
+    /// [`Collection::convert_vector`]
+    #[serde(default)]
+    pub dtype: VectorDtype,
+    /// How written values `f32` storage can't hold exactly are handled
+    #[serde(default)]
+    pub dtype_conversion: DtypeConversion,
 }
 
 impl Default for Config {
@@ -127,7 +251,174 @@ impl Default for Config {
             hnsw: HnswConfig::default(),
             max_vectors: 0,
             quantization: QuantizationType::None,
+            id_type: IdType::String,
+            metadata_compression: MetadataCompression::None,
+            partition_field: None,
+            group_commit: None,
+            text_fields: Vec::new(),
+            named_vectors: Vec::new(),
+            index: IndexKind::Hnsw,
+            metadata_limits: MetadataLimits::default(),
+            transform: None,
+            norm_filter: None,
+            dtype: VectorDtype::Float32,
+            dtype_conversion: DtypeConversion::Round,
+        }
+    }
+}
+
+impl Config {
+    /// Start building a configuration for vectors of `dimensions`, with
+    /// every other setting at its default
+    pub fn builder(dimensions: usize) -> ConfigBuilder {
+        ConfigBuilder {
+            config: Config {
+                dimensions,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Builds a [`Config`], checking it on [`build`](Self::build)
+///
+/// ```rust
+/// use surgedb_core::{Config, DistanceMetric, HnswConfig};
+///
+/// let config = Config::builder(384)
+///     .distance_metric(DistanceMetric::DotProduct)
+///     .hnsw(HnswConfig::default().with_m(32))
+///     .text_fields(["title"])
+///     .build()
+///     .unwrap();
+/// assert_eq!(config.hnsw.m, 32);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ConfigBuilder {
+    config: Config,
+}
+
+impl ConfigBuilder {
+    pub fn distance_metric(mut self, metric: DistanceMetric) -> Self {
+        self.config.distance_metric = metric;
+        self
+    }
+
+    pub fn hnsw(mut self, hnsw: HnswConfig) -> Self {
+        self.config.hnsw = hnsw;
+        self
+    }
+
+    /// Default `ef_search` of searches that don't set their own
+    pub fn ef_search(mut self, ef_search: usize) -> Self {
+        self.config.hnsw.ef_search = ef_search;
+        self
+    }
+
+    pub fn index(mut self, index: IndexKind) -> Self {
+        self.config.index = index;
+        self
+    }
+
+    pub fn quantization(mut self, quantization: QuantizationType) -> Self {
+        self.config.quantization = quantization;
+        self
+    }
+
+    /// Maximum number of vectors (0 = unlimited)
+    pub fn max_vectors(mut self, max_vectors: usize) -> Self {
+        self.config.max_vectors = max_vectors;
+        self
+    }
+
+    pub fn id_type(mut self, id_type: IdType) -> Self {
+        self.config.id_type = id_type;
+        self
+    }
+
+    pub fn metadata_compression(mut self, compression: MetadataCompression) -> Self {
+        self.config.metadata_compression = compression;
+        self
+    }
+
+    pub fn metadata_limits(mut self, limits: MetadataLimits) -> Self {
+        self.config.metadata_limits = limits;
+        self
+    }
+
+    /// Transform query vectors, and with `apply_on_insert` written ones, in
+    /// collections of a [`Database`]
+    pub fn transform(mut self, transform: VectorTransform) -> Self {
+        self.config.transform = Some(transform);
+        self
+    }
+
+    /// Reject vectors written to collections of a [`Database`] whose norm
+    /// falls outside `filter`
+    pub fn norm_filter(mut self, filter: NormFilter) -> Self {
+        self.config.norm_filter = Some(filter);
+        self
+    }
+
+    /// Take written vectors as `dtype`, converted under `conversion`
+    pub fn dtype(mut self, dtype: VectorDtype, conversion: DtypeConversion) -> Self {
+        self.config.dtype = dtype;
+        self.config.dtype_conversion = conversion;
+        self
+    }
+
+    pub fn partition_field(mut self, field: impl Into<String>) -> Self {
+        self.config.partition_field = Some(field.into());
+        self
+    }
+
+    /// Batch WAL fsyncs of collections in an on-disk [`Database`]
+    pub fn group_commit(mut self, group_commit: GroupCommit) -> Self {
+        self.config.group_commit = Some(group_commit);
+        self
+    }
+
+    pub fn text_fields<S: Into<String>>(mut self, fields: impl IntoIterator<Item = S>) -> Self {
+        self.config.text_fields = fields.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Add a vector space records can carry a vector in
+    pub fn named_vector(mut self, named: NamedVectorConfig) -> Self {
+        self.config.named_vectors.push(named);
+        self
+    }
+
+    /// The configuration, or why no collection can be created with it
+    pub fn build(self) -> Result<Config> {
+        let config = self.config;
+        if config.dimensions == 0 {
+            return Err(Error::InvalidConfig(
+                "dimensions must be at least 1".to_string(),
+            ));
+        }
+        config.hnsw.validate()?;
+        NamedVectors::validate(&config.named_vectors)?;
+        if let Some(transform) = &config.transform {
+            transform.validate(config.dimensions)?;
+        }
+        if let Some(filter) = &config.norm_filter {
+            filter.validate()?;
         }
+        if config.quantization != QuantizationType::None {
+            let unsupported = [
+                ("partition_field", config.partition_field.is_some()),
+                ("text_fields", !config.text_fields.is_empty()),
+                ("named_vectors", !config.named_vectors.is_empty()),
+            ];
+            if let Some((field, _)) = unsupported.iter().find(|(_, set)| *set) {
+                return Err(Error::InvalidConfig(format!(
+                    "{} is not supported for quantized collections",
+                    field
+                )));
+            }
+        }
+        Ok(config)
     }
 }
 
@@ -146,6 +437,12 @@ pub struct QuantizedConfig {
     pub keep_originals: bool,
     /// Number of candidates to fetch before re-ranking (if keep_originals is true)
     pub rerank_multiplier: usize,
+    /// Key type of external IDs
+    pub id_type: IdType,
+    /// How metadata payloads are stored
+    pub metadata_compression: MetadataCompression,
+    /// Size and nesting limits metadata documents must meet to be written
+    pub metadata_limits: MetadataLimits,
 }
 
 impl Default for QuantizedConfig {
@@ -157,6 +454,9 @@ impl Default for QuantizedConfig {
             quantization: QuantizationType::SQ8,
             keep_originals: false,
             rerank_multiplier: 3,
+            id_type: IdType::String,
+            metadata_compression: MetadataCompression::None,
+            metadata_limits: MetadataLimits::default(),
         }
     }
 }
@@ -167,22 +467,95 @@ use serde_json::Value;
 pub struct VectorDb {
     config: Config,
     storage: VectorStorage,
-    index: HnswIndex,
+    index: Box<dyn AnnIndex>,
+    partitions: Option<PartitionedIndex>,
+    sparse: sparse::SparseStore,
+    named: NamedVectors,
+    /// Bumped by every write
+    write_seq: u64,
 }
 
 impl VectorDb {
     /// Create a new vector database with the given configuration
     pub fn new(config: Config) -> Result<Self> {
-        let storage = VectorStorage::new(config.dimensions);
-        let index = HnswIndex::new(config.hnsw.clone(), config.distance_metric);
+        let index = config.index.build(&config.hnsw, config.distance_metric);
+        Self::with_index(config, index)
+    }
+
+    /// Create a new vector database searched with `index` instead of the one
+    /// `config.index` names
+    ///
+    /// The index must be empty. Compaction rebuilds into
+    /// [`AnnIndex::fresh`], so a custom index stays in place.
+    pub fn with_index(config: Config, index: Box<dyn AnnIndex>) -> Result<Self> {
+        let storage = VectorStorage::new(config.dimensions)
+            .with_metadata_compression(config.metadata_compression)?
+            .with_text_fields(config.text_fields.clone());
+        let partitions = config
+            .partition_field
+            .clone()
+            .map(|field| PartitionedIndex::new(field, config.hnsw.clone(), config.distance_metric));
+        let named = NamedVectors::new(&config.named_vectors, &config.hnsw)?;
 
         Ok(Self {
             config,
             storage,
             index,
+            partitions,
+            sparse: sparse::SparseStore::new(),
+            named,
+            write_seq: 0,
         })
     }
 
+    /// Drop the sparse and named vectors of `id` before the record is deleted
+    /// or overwritten
+    fn forget_attached(&mut self, id: &VectorId) {
+        if let Some(internal_id) = self.storage.get_internal_id(id) {
+            self.index.remove(internal_id);
+            self.sparse.remove(internal_id);
+            self.named.remove(internal_id);
+        }
+    }
+
+    /// Add a stored vector to the main graph and its partition graph
+    fn index_vector(&self, internal_id: types::InternalId, vector: &[f32]) -> Result<()> {
+        self.index.insert(internal_id, vector, &self.storage)?;
+        if let Some(partitions) = &self.partitions {
+            partitions.insert(internal_id, vector, &self.storage)?;
+        }
+        Ok(())
+    }
+
+    /// Search the partition graph pinned by `filter`, or the main graph
+    fn search_graph(
+        &self,
+        query: &[f32],
+        k: usize,
+        filter: Option<&filter::Filter>,
+        params: SearchParams,
+        usage: &mut SearchUsage,
+    ) -> Result<Vec<(types::InternalId, f32)>> {
+        let ef_search = Some(params.ef_search.unwrap_or(self.config.hnsw.ef_search));
+        let view = self.storage.search_view(filter);
+        let partitioned = self
+            .partitions
+            .as_ref()
+            .and_then(|p| p.search_with_usage(query, k, ef_search, &view, filter, usage));
+        match partitioned {
+            Some(results) => Ok(within(results?, params.max_distance)),
+            None => self.index.search_within(
+                query,
+                k,
+                ef_search,
+                params.max_distance,
+                &view,
+                filter,
+                usage,
+            ),
+        }
+    }
+
     /// Insert a vector with the given ID and optional metadata
     pub fn insert(
         &mut self,
@@ -190,7 +563,7 @@ impl VectorDb {
         vector: &[f32],
         metadata: Option<Value>,
     ) -> Result<()> {
-        let id = id.into();
+        let id = self.config.id_type.parse(id.into())?;
 
         if vector.len() != self.config.dimensions {
             return Err(Error::DimensionMismatch {
@@ -198,17 +571,50 @@ impl VectorDb {
                 got: vector.len(),
             });
         }
+        self.config.metadata_limits.check(&id, metadata.as_ref())?;
 
         let internal_id = self.storage.insert(id.clone(), vector, metadata)?;
-        self.index.insert(internal_id, vector, &self.storage)?;
+        self.index_vector(internal_id, vector)?;
 
+        self.write_seq += 1;
         Ok(())
     }
 
     /// Delete a vector by ID
     pub fn delete(&mut self, id: impl Into<VectorId>) -> Result<bool> {
-        let id = id.into();
-        self.storage.delete(&id)
+        let Ok(id) = self.config.id_type.parse(id.into()) else {
+            return Ok(false);
+        };
+        self.forget_attached(&id);
+        let deleted = self.storage.delete(&id)?;
+        self.write_seq += 1;
+        Ok(deleted)
+    }
+
+    /// Delete the vectors with the given IDs; returns those that existed
+    pub fn delete_batch(
+        &mut self,
+        ids: impl IntoIterator<Item = impl Into<VectorId>>,
+    ) -> Result<Vec<VectorId>> {
+        let mut deleted = Vec::new();
+        for id in ids {
+            let id = id.into();
+            if self.delete(id.clone())? {
+                deleted.push(id);
+            }
+        }
+        Ok(deleted)
+    }
+
+    /// Delete every vector whose metadata matches `filter`; returns their IDs
+    pub fn delete_by_filter(&mut self, filter: &filter::Filter) -> Result<Vec<VectorId>> {
+        let matching = self.storage.ids_matching(filter)?;
+        for id in &matching {
+            self.forget_attached(id);
+            self.storage.delete(id)?;
+        }
+        self.write_seq += 1;
+        Ok(matching)
     }
 
     /// Insert or update a vector with the given ID and optional metadata
@@ -218,7 +624,7 @@ impl VectorDb {
         vector: &[f32],
         metadata: Option<Value>,
     ) -> Result<()> {
-        let id = id.into();
+        let id = self.config.id_type.parse(id.into())?;
 
         if vector.len() != self.config.dimensions {
             return Err(Error::DimensionMismatch {
@@ -226,10 +632,13 @@ impl VectorDb {
                 got: vector.len(),
             });
         }
+        self.config.metadata_limits.check(&id, metadata.as_ref())?;
 
+        self.forget_attached(&id);
         let internal_id = self.storage.upsert(id.clone(), vector, metadata)?;
-        self.index.insert(internal_id, vector, &self.storage)?;
+        self.index_vector(internal_id, vector)?;
 
+        self.write_seq += 1;
         Ok(())
     }
 
@@ -239,14 +648,15 @@ impl VectorDb {
             return Ok(());
         }
 
-        // Validate dimensions
-        for (_, vector, _) in &items {
-            if vector.len() != self.config.dimensions {
-                return Err(Error::DimensionMismatch {
-                    expected: self.config.dimensions,
-                    got: vector.len(),
-                });
-            }
+        let items = validate_batch(
+            self.config.id_type,
+            self.config.dimensions,
+            self.config.metadata_limits,
+            items,
+        )?;
+
+        for (id, _, _) in &items {
+            self.forget_attached(id);
         }
 
         // 1. Batch Upsert into Storage (Single lock acquisition)
@@ -262,13 +672,238 @@ impl VectorDb {
             .collect();
 
         self.index.insert_batch(&hnsw_items, &self.storage)?;
+        if let Some(partitions) = &self.partitions {
+            for (internal_id, vector) in hnsw_items {
+                partitions.insert(internal_id, vector, &self.storage)?;
+            }
+        }
+
+        self.write_seq += 1;
+        Ok(())
+    }
+
+    /// Delete every vector matching `filter` and upsert `items` in one step
+    ///
+    /// All items are validated before anything is deleted. Returns the number
+    /// of vectors that matched `filter`.
+    pub fn replace(
+        &mut self,
+        filter: &filter::Filter,
+        items: Vec<(VectorId, Vec<f32>, Option<Value>)>,
+    ) -> Result<usize> {
+        let items = validate_batch(
+            self.config.id_type,
+            self.config.dimensions,
+            self.config.metadata_limits,
+            items,
+        )?;
+
+        let matching = self.storage.ids_matching(filter)?;
+        for id in &matching {
+            self.forget_attached(id);
+            self.storage.delete(id)?;
+        }
+        self.upsert_batch(items)?;
+        self.write_seq += 1;
+
+        Ok(matching.len())
+    }
+
+    /// Change the metadata of `id` without resending its vector; returns false
+    /// if there is no such vector
+    ///
+    /// `metadata` replaces the current metadata (`null` clears it), or with
+    /// `merge` is applied to it as a JSON merge patch. The record keeps its
+    /// slot and graph links, unless the partition field changes, in which case
+    /// it is re-inserted with its sparse and named vectors.
+    pub fn update_metadata(
+        &mut self,
+        id: impl Into<VectorId>,
+        metadata: Value,
+        merge: bool,
+    ) -> Result<bool> {
+        let Ok(id) = self.config.id_type.parse(id.into()) else {
+            return Ok(false);
+        };
+        let Some(internal_id) = self.storage.get_internal_id(&id) else {
+            return Ok(false);
+        };
+        let current = self.storage.get_metadata(internal_id);
+        let updated = metadata_store::updated_metadata(current.clone(), metadata, merge);
+        self.config.metadata_limits.check(&id, updated.as_ref())?;
+
+        if self
+            .partitions
+            .as_ref()
+            .is_some_and(|p| p.moves(current.as_ref(), updated.as_ref()))
+        {
+            let vector = self
+                .storage
+                .get(internal_id)
+                .ok_or(Error::VectorNotFound(id.to_string()))?;
+            self.index.remove(internal_id);
+            let sparse = self.sparse.remove(internal_id);
+            let named = self.named.remove(internal_id);
+            let internal_id = self.storage.upsert(id, &vector, updated)?;
+            self.index_vector(internal_id, &vector)?;
+            if let Some(sparse) = sparse {
+                self.sparse.set(internal_id, sparse);
+            }
+            for (name, vector) in named {
+                self.named.set(internal_id, &name, &vector, &self.storage)?;
+            }
+        } else {
+            self.storage.update_metadata(&id, updated)?;
+        }
+
+        self.write_seq += 1;
+        Ok(true)
+    }
+
+    /// Sequence number of the last write, bumped by every write
+    pub fn write_seq(&self) -> u64 {
+        self.write_seq
+    }
+
+    /// Snapshot of the current vectors and graph, for exporting the collection
+    #[cfg(feature = "persistence")]
+    pub(crate) fn export_snapshot(&self) -> snapshot::Snapshot {
+        let mut snapshot = snapshot::Snapshot::capture(
+            0,
+            0,
+            self.config.dimensions,
+            &self.storage,
+            Some(self.index.as_ref()),
+        );
+        snapshot.capture_sparse(&self.storage, &self.sparse);
+        snapshot.capture_named(&self.storage, &self.named);
+        snapshot
+    }
+
+    /// Fill an empty database from an exported snapshot
+    #[cfg(feature = "persistence")]
+    pub(crate) fn restore(&mut self, snapshot: snapshot::Snapshot) -> Result<()> {
+        if !self.storage.all_internal_ids().is_empty() {
+            return Err(Error::InvalidConfig(
+                "Snapshots can only be restored into an empty collection".to_string(),
+            ));
+        }
+        if snapshot.dimensions != self.config.dimensions {
+            return Err(Error::DimensionMismatch {
+                expected: self.config.dimensions,
+                got: snapshot.dimensions,
+            });
+        }
+
+        let mut internal_ids = Vec::with_capacity(snapshot.vectors.len());
+        for stored in snapshot.vectors {
+            let id = self.config.id_type.parse(stored.id)?;
+            internal_ids.push(self.storage.insert(id, &stored.vector, stored.metadata)?);
+        }
+        for (id, vector) in snapshot.sparse {
+            let id = self.config.id_type.parse(id)?;
+            if let Some(internal_id) = self.storage.get_internal_id(&id) {
+                self.sparse.set(internal_id, vector);
+            }
+        }
+        for (id, name, vector) in snapshot.named {
+            let id = self.config.id_type.parse(id)?;
+            if let Some(internal_id) = self.storage.get_internal_id(&id) {
+                self.named.set(internal_id, &name, &vector, &self.storage)?;
+            }
+        }
+
+        match (
+            snapshot.hnsw_state,
+            self.index.as_hnsw(),
+            snapshot.index_state,
+        ) {
+            (Some(state), Some(index), _) => index.load_state(state),
+            (_, None, Some(bytes)) => self.index.deserialize(&bytes)?,
+            _ => {
+                for internal_id in internal_ids {
+                    if let Some(vector) = self.storage.get_vector_data(internal_id) {
+                        self.index_vector(internal_id, &vector)?;
+                    }
+                }
+                self.write_seq += 1;
+                return Ok(());
+            }
+        }
+
+        // Partition graphs aren't snapshotted, so they are always rebuilt
+        if let Some(partitions) = &self.partitions {
+            for internal_id in internal_ids {
+                if let Some(vector) = self.storage.get_vector_data(internal_id) {
+                    partitions.insert(internal_id, &vector, &self.storage)?;
+                }
+            }
+        }
 
+        self.write_seq += 1;
         Ok(())
     }
 
+    /// Fill an empty database from records and precomputed nearest-neighbor
+    /// lists, without searching for each record's neighbors
+    ///
+    /// `neighbors[i]` lists the positions in `items` of record `i`'s nearest
+    /// neighbors, as from a kNN graph built offline. See
+    /// [`HnswIndex::build_from_neighbors`]. Only the HNSW index can be
+    /// loaded this way; partition graphs are built by insertion.
+    pub fn bulk_load(
+        &mut self,
+        items: Vec<(VectorId, Vec<f32>, Option<Value>)>,
+        neighbors: Vec<Vec<usize>>,
+    ) -> Result<()> {
+        let Some(index) = self.index.as_hnsw() else {
+            return Err(Error::InvalidConfig(
+                "Bulk loading needs an HNSW index".to_string(),
+            ));
+        };
+        if self.storage.total_slots() > 0 {
+            return Err(Error::InvalidConfig(
+                "Bulk loading needs an empty collection".to_string(),
+            ));
+        }
+        let items = validate_batch(
+            self.config.id_type,
+            self.config.dimensions,
+            self.config.metadata_limits,
+            items,
+        )?;
+        let neighbors = bulk_neighbors(&items, neighbors)?;
+
+        let mut internal_ids = Vec::with_capacity(items.len());
+        for (id, vector, metadata) in items {
+            internal_ids.push(self.storage.insert(id, &vector, metadata)?);
+        }
+        index.build_from_neighbors(&neighbors, &self.storage)?;
+        if let Some(partitions) = &self.partitions {
+            for internal_id in internal_ids {
+                if let Some(vector) = self.storage.get_vector_data(internal_id) {
+                    partitions.insert(internal_id, &vector, &self.storage)?;
+                }
+            }
+        }
+
+        self.write_seq += 1;
+        Ok(())
+    }
+
+    /// Whether a vector with this external ID exists, without copying it
+    pub fn contains(&self, id: &str) -> bool {
+        self.config
+            .id_type
+            .parse(VectorId::from(id))
+            .is_ok_and(|id| self.storage.get_internal_id(&id).is_some())
+    }
+
     /// Retrieve a vector by its external ID
     pub fn get(&self, id: &str) -> Result<Option<(Vec<f32>, Option<Value>)>> {
-        let id = VectorId::from(id);
+        let Ok(id) = self.config.id_type.parse(VectorId::from(id)) else {
+            return Ok(None);
+        };
         if let Some(internal_id) = self.storage.get_internal_id(&id) {
             let vector = self
                 .storage
@@ -281,6 +916,53 @@ impl VectorDb {
         }
     }
 
+    /// Retrieve metadata for several external IDs without copying vectors
+    ///
+    /// Unknown IDs are skipped; found IDs keep the order of `ids`.
+    pub fn get_metadata_batch(&self, ids: &[String]) -> Vec<(VectorId, Option<Value>)> {
+        let ids: Vec<VectorId> = ids
+            .iter()
+            .filter_map(|id| self.config.id_type.parse(VectorId::from(id.as_str())).ok())
+            .collect();
+        self.storage.get_metadata_batch(&ids)
+    }
+
+    /// Keep the matches of `filter` materialized for faster filtered search
+    ///
+    /// The match set is updated on every write. Cached filters are not
+    /// persisted.
+    pub fn cache_filter(&self, filter: filter::Filter) -> Result<CachedFilterInfo> {
+        self.storage.cache_filter(filter)
+    }
+
+    /// Drop a cached filter by ID; returns whether it existed
+    pub fn uncache_filter(&self, id: &str) -> bool {
+        self.storage.uncache_filter(id)
+    }
+
+    pub(crate) fn storage(&self) -> &VectorStorage {
+        &self.storage
+    }
+
+    /// Filters currently cached, with their match counts and hits
+    pub fn cached_filters(&self) -> Vec<CachedFilterInfo> {
+        self.storage.cached_filters()
+    }
+
+    /// Page through records with a cursor; see [`Collection::list_page`](db::Collection::list_page)
+    pub fn list_page(&self, cursor: Option<ListCursor>, limit: usize) -> Result<ListPage> {
+        self.storage.list_page(cursor, limit)
+    }
+
+    /// Iterate over every record with its vector and metadata, a page at a
+    /// time; see [`Scan`]
+    pub fn scan(&self) -> Scan<'_> {
+        Scan::new(
+            move |cursor, limit| self.list_page(cursor, limit),
+            move |id| self.get(id),
+        )
+    }
+
     /// List all vector IDs and metadata (pagination)
     pub fn list(&self, offset: usize, limit: usize) -> Vec<(VectorId, Option<Value>)> {
         let ids = self.storage.all_internal_ids();
@@ -307,6 +989,28 @@ impl VectorDb {
         k: usize,
         filter: Option<&filter::Filter>,
     ) -> Result<Vec<(VectorId, f32, Option<Value>)>> {
+        self.search_with_usage(query, k, filter)
+            .map(|(results, _)| results)
+    }
+
+    /// Search for the k nearest neighbors, reporting the work performed
+    pub fn search_with_usage(
+        &self,
+        query: &[f32],
+        k: usize,
+        filter: Option<&filter::Filter>,
+    ) -> Result<(Vec<SearchHit>, SearchUsage)> {
+        self.search_with_params(query, k, filter, SearchParams::default())
+    }
+
+    /// Search for the k nearest neighbors with per-search overrides
+    pub fn search_with_params(
+        &self,
+        query: &[f32],
+        k: usize,
+        filter: Option<&filter::Filter>,
+        params: SearchParams,
+    ) -> Result<(Vec<SearchHit>, SearchUsage)> {
         if query.len() != self.config.dimensions {
             return Err(Error::DimensionMismatch {
                 expected: self.config.dimensions,
@@ -317,9 +1021,8 @@ impl VectorDb {
         // We search for more candidates (2x k) to account for potential stale/deleted entries
         // that might be filtered out.
         let search_k = k * 2;
-        let results = self
-            .index
-            .search(query, search_k, &self.storage.view(), filter)?;
+        let mut usage = SearchUsage::default();
+        let results = self.search_graph(query, search_k, filter, params, &mut usage)?;
 
         // Map internal IDs back to external IDs and fetch metadata
         // Filter out stale entries (where internal_id doesn't match current mapping)
@@ -340,7 +1043,7 @@ impl VectorDb {
             .take(k)
             .collect();
 
-        Ok(mapped)
+        Ok((mapped, usage))
     }
 
     /// Search for the k nearest neighbors (without metadata)
@@ -350,6 +1053,28 @@ impl VectorDb {
         k: usize,
         filter: Option<&filter::Filter>,
     ) -> Result<Vec<(VectorId, f32)>> {
+        self.search_ids_with_usage(query, k, filter)
+            .map(|(results, _)| results)
+    }
+
+    /// Search for the k nearest neighbors (without metadata), reporting the work performed
+    pub fn search_ids_with_usage(
+        &self,
+        query: &[f32],
+        k: usize,
+        filter: Option<&filter::Filter>,
+    ) -> Result<(Vec<(VectorId, f32)>, SearchUsage)> {
+        self.search_ids_with_params(query, k, filter, SearchParams::default())
+    }
+
+    /// Search for the k nearest neighbors (without metadata) with per-search overrides
+    pub fn search_ids_with_params(
+        &self,
+        query: &[f32],
+        k: usize,
+        filter: Option<&filter::Filter>,
+        params: SearchParams,
+    ) -> Result<(Vec<(VectorId, f32)>, SearchUsage)> {
         if query.len() != self.config.dimensions {
             return Err(Error::DimensionMismatch {
                 expected: self.config.dimensions,
@@ -358,9 +1083,8 @@ impl VectorDb {
         }
 
         let search_k = k * 2;
-        let results = self
-            .index
-            .search(query, search_k, &self.storage.view(), filter)?;
+        let mut usage = SearchUsage::default();
+        let results = self.search_graph(query, search_k, filter, params, &mut usage)?;
 
         let mapped: Vec<(VectorId, f32)> = results
             .into_iter()
@@ -375,70 +1099,314 @@ impl VectorDb {
             .take(k)
             .collect();
 
-        Ok(mapped)
+        Ok((mapped, usage))
     }
 
-    /// Get the number of vectors in the database
-    pub fn len(&self) -> usize {
-        self.storage.len()
+    /// Set the sparse vector of record `id`; returns false if there is no such record
+    ///
+    /// The sparse vector belongs to the record as stored now: upserting or
+    /// deleting the record drops it.
+    pub fn set_sparse(&mut self, id: impl Into<VectorId>, vector: SparseVector) -> Result<bool> {
+        vector.validate()?;
+        let id = self.config.id_type.parse(id.into())?;
+        let Some(internal_id) = self.storage.get_internal_id(&id) else {
+            return Ok(false);
+        };
+        self.sparse.set(internal_id, vector);
+        self.write_seq += 1;
+        Ok(true)
     }
 
-    /// Check if the database is empty
-    pub fn is_empty(&self) -> bool {
-        self.storage.is_empty()
+    /// Sparse vector of record `id`, if it has one
+    pub fn get_sparse(&self, id: &str) -> Option<SparseVector> {
+        let id = self.config.id_type.parse(VectorId::from(id)).ok()?;
+        let internal_id = self.storage.get_internal_id(&id)?;
+        self.sparse.get(internal_id).cloned()
     }
 
-    /// Get configuration
-    pub fn config(&self) -> &Config {
-        &self.config
+    /// Set the vector of record `id` in the named space `name`; returns false
+    /// if there is no such record
+    ///
+    /// Like sparse vectors, named vectors belong to the record as stored now:
+    /// upserting or deleting the record drops them.
+    pub fn set_named_vector(
+        &mut self,
+        id: impl Into<VectorId>,
+        name: &str,
+        vector: &[f32],
+    ) -> Result<bool> {
+        self.named.check(name, vector)?;
+        let id = self.config.id_type.parse(id.into())?;
+        let Some(internal_id) = self.storage.get_internal_id(&id) else {
+            return Ok(false);
+        };
+        self.named.set(internal_id, name, vector, &self.storage)?;
+        self.write_seq += 1;
+        Ok(true)
     }
 
-    /// Get approximate memory usage in bytes
-    pub fn memory_usage(&self) -> usize {
-        self.storage.memory_usage() + self.index.memory_usage()
+    /// Named vectors of record `id`, by space
+    pub fn get_named_vectors(&self, id: &str) -> Vec<(String, Vec<f32>)> {
+        let Ok(id) = self.config.id_type.parse(VectorId::from(id)) else {
+            return Vec::new();
+        };
+        self.storage
+            .get_internal_id(&id)
+            .map_or_else(Vec::new, |internal_id| self.named.of(internal_id))
     }
-}
-
-/// Quantized vector database with configurable compression
-///
-/// Uses SQ8 (4x compression) or Binary (32x compression) quantization
-/// to dramatically reduce memory usage with minimal accuracy loss.
-pub struct QuantizedVectorDb {
-    config: QuantizedConfig,
-    storage: QuantizedStorage,
-    index: Option<HnswIndex>,
-}
-
-impl QuantizedVectorDb {
-    /// Create a new quantized vector database
-    pub fn new(config: QuantizedConfig) -> Result<Self> {
-        let storage = QuantizedStorage::new(
-            config.dimensions,
-            config.quantization,
-            config.keep_originals,
-        );
 
-        let index = if config.quantization == QuantizationType::Binary {
-            None
-        } else {
-            Some(HnswIndex::new(config.hnsw.clone(), config.distance_metric))
-        };
+    /// Search the named space `name` instead of the primary vectors
+    pub fn search_named(
+        &self,
+        name: &str,
+        query: &[f32],
+        k: usize,
+        filter: Option<&filter::Filter>,
+        params: SearchParams,
+    ) -> Result<(Vec<SearchHit>, SearchUsage)> {
+        let ef_search = Some(params.ef_search.unwrap_or(self.config.hnsw.ef_search));
+        let mut usage = SearchUsage::default();
+        let view = self.storage.search_view(filter);
+        let results = self
+            .named
+            .search(name, query, k, ef_search, &view, filter, &mut usage)?;
+        drop(view);
+        let results = within(results, params.max_distance);
 
-        Ok(Self {
-            config,
-            storage,
-            index,
-        })
+        let hits = results
+            .into_iter()
+            .filter_map(|(internal_id, distance)| {
+                let ext_id = self.storage.get_external_id(internal_id)?;
+                Some((ext_id, distance, self.storage.get_metadata(internal_id)))
+            })
+            .collect();
+        Ok((hits, usage))
     }
 
-    /// Insert a vector with the given ID and optional metadata
+    /// Search with a dense and a sparse query, fusing both rankings
+    ///
+    /// Each ranking contributes `k * HYBRID_CANDIDATES` candidates; `params`
+    /// apply to the dense search.
+    pub fn search_hybrid(
+        &self,
+        dense: &[f32],
+        sparse: &SparseVector,
+        k: usize,
+        filter: Option<&filter::Filter>,
+        fusion: Fusion,
+        params: SearchParams,
+    ) -> Result<(Vec<HybridHit>, SearchUsage)> {
+        sparse.validate()?;
+        fusion.validate()?;
+        let candidates = k * sparse::HYBRID_CANDIDATES;
+        let (dense_results, mut usage) =
+            self.search_ids_with_params(dense, candidates, filter, params)?;
+        let hits = sparse::hybrid::fuse_with_sparse(
+            &self.storage,
+            &self.sparse,
+            dense_results,
+            sparse,
+            k,
+            filter,
+            fusion,
+            &mut usage,
+        );
+        Ok((hits, usage))
+    }
+
+    /// Keyword search of the text fields; scores are BM25, higher is better
+    pub fn search_text(
+        &self,
+        query: &str,
+        k: usize,
+        filter: Option<&filter::Filter>,
+    ) -> Result<(Vec<SearchHit>, SearchUsage)> {
+        let mut usage = SearchUsage::default();
+        let hits = sparse::hybrid::search_text(&self.storage, query, k, filter, &mut usage)?;
+        Ok((hits, usage))
+    }
+
+    /// Search with a dense vector and a text query, fusing both rankings
+    ///
+    /// Each ranking contributes `k * HYBRID_CANDIDATES` candidates; `params`
+    /// apply to the dense search.
+    pub fn search_hybrid_text(
+        &self,
+        dense: &[f32],
+        query: &str,
+        k: usize,
+        filter: Option<&filter::Filter>,
+        fusion: Fusion,
+        params: SearchParams,
+    ) -> Result<(Vec<HybridHit>, SearchUsage)> {
+        fusion.validate()?;
+        let candidates = k * sparse::HYBRID_CANDIDATES;
+        let (dense_results, mut usage) =
+            self.search_ids_with_params(dense, candidates, filter, params)?;
+        let hits = sparse::hybrid::fuse_with_text(
+            &self.storage,
+            dense_results,
+            query,
+            k,
+            filter,
+            fusion,
+            &mut usage,
+        )?;
+        Ok((hits, usage))
+    }
+
+    /// Get the number of vectors in the database
+    pub fn len(&self) -> usize {
+        self.storage.len()
+    }
+
+    /// Number of live vectors, or of those whose metadata matches `filter`
+    pub fn count(&self, filter: Option<&filter::Filter>) -> Result<usize> {
+        self.storage.count(filter)
+    }
+
+    /// Get the number of deleted or overwritten vectors not yet reclaimed
+    pub fn deleted_count(&self) -> usize {
+        self.storage.deleted_count()
+    }
+
+    /// Space held by deleted or overwritten records
+    pub fn garbage_stats(&self) -> GarbageStats {
+        self.storage.garbage_stats()
+    }
+
+    /// Rebuild the database from its live records, dropping tombstoned slots
+    ///
+    /// The graph is rebuilt unless there is nothing to drop. Cached filters
+    /// are kept, list cursors expire and the write sequence is bumped.
+    #[cfg(feature = "persistence")]
+    pub fn compact(&mut self) -> Result<CompactionReport> {
+        let snapshot = self.export_snapshot();
+        let report = CompactionReport::new(snapshot.len(), &self.garbage_stats());
+        let mut compacted = Self::with_index(self.config.clone(), self.index.fresh())?;
+        compacted.restore(snapshot)?;
+        for info in self.storage.cached_filters() {
+            compacted.storage.cache_filter(info.filter)?;
+        }
+        compacted.write_seq = self.write_seq + 1;
+        *self = compacted;
+        Ok(report)
+    }
+
+    /// Shape of the HNSW graph, or `None` when the collection isn't indexed
+    /// with HNSW
+    pub fn graph_stats(&self) -> Option<GraphStats> {
+        self.index.as_hnsw().map(HnswIndex::graph_stats)
+    }
+
+    /// Relink up to `budget` nodes of the HNSW graph left linking to deleted
+    /// vectors; see [`HnswIndex::repair`]
+    pub fn repair_graph(&self, budget: usize) -> GraphRepair {
+        self.index
+            .as_hnsw()
+            .map(|index| index.repair(budget, &self.storage))
+            .unwrap_or_default()
+    }
+
+    /// Export the HNSW adjacency, optionally for one `level` or a `sample` of nodes
+    ///
+    /// Empty when the collection isn't indexed with HNSW.
+    pub fn export_graph(&self, level: Option<usize>, sample: Option<usize>) -> GraphExport {
+        let Some(index) = self.index.as_hnsw() else {
+            return GraphExport::default();
+        };
+        index.export_graph(level, sample, |id| {
+            (!self.storage.is_deleted(id))
+                .then(|| self.storage.get_external_id(id))
+                .flatten()
+        })
+    }
+
+    /// Check if the database is empty
+    pub fn is_empty(&self) -> bool {
+        self.storage.is_empty()
+    }
+
+    /// Get configuration
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Change the candidate list size searches use by default
+    pub fn set_ef_search(&mut self, ef_search: usize) {
+        self.config.hnsw.ef_search = ef_search;
+    }
+
+    /// Get approximate memory usage in bytes
+    pub fn memory_usage(&self) -> usize {
+        self.storage.memory_usage()
+            + self.index.memory_usage()
+            + self.partitions.as_ref().map_or(0, |p| p.memory_usage())
+            + self.sparse.memory_usage()
+            + self.named.vector_bytes()
+            + self.named.graph_bytes()
+    }
+
+    /// Get approximate memory usage split by component
+    pub fn memory_breakdown(&self) -> MemoryBreakdown {
+        MemoryBreakdown {
+            vectors: self.storage.vector_bytes() + self.named.vector_bytes(),
+            graph: self.index.memory_usage()
+                + self.partitions.as_ref().map_or(0, |p| p.memory_usage())
+                + self.named.graph_bytes(),
+            ids: self.storage.id_bytes(),
+            metadata: self.storage.metadata_bytes(),
+            sparse: self.sparse.memory_usage(),
+            metadata_compression_ratio: self.storage.metadata_compression_ratio(),
+        }
+    }
+
+    /// The `n` records with the largest metadata, largest first
+    pub fn largest_payloads(&self, n: usize) -> Vec<PayloadSize> {
+        self.storage.largest_payloads(n)
+    }
+}
+
+/// Quantized vector database with configurable compression
+///
+/// Uses SQ8 (4x compression) or Binary (32x compression) quantization
+/// to dramatically reduce memory usage with minimal accuracy loss.
+pub struct QuantizedVectorDb {
+    config: QuantizedConfig,
+    storage: QuantizedStorage,
+    index: HnswIndex,
+    /// Bumped by every write
+    write_seq: u64,
+}
+
+impl QuantizedVectorDb {
+    /// Create a new quantized vector database
+    pub fn new(config: QuantizedConfig) -> Result<Self> {
+        let storage = QuantizedStorage::new(
+            config.dimensions,
+            config.quantization,
+            config.keep_originals,
+        )
+        .with_metadata_compression(config.metadata_compression)?;
+
+        let index = HnswIndex::new(config.hnsw.clone(), config.distance_metric);
+
+        Ok(Self {
+            config,
+            storage,
+            index,
+            write_seq: 0,
+        })
+    }
+
+    /// Insert a vector with the given ID and optional metadata
     pub fn insert(
         &mut self,
         id: impl Into<VectorId>,
         vector: &[f32],
         metadata: Option<Value>,
     ) -> Result<()> {
-        let id = id.into();
+        let id = self.config.id_type.parse(id.into())?;
 
         if vector.len() != self.config.dimensions {
             return Err(Error::DimensionMismatch {
@@ -446,20 +1414,48 @@ impl QuantizedVectorDb {
                 got: vector.len(),
             });
         }
+        self.config.metadata_limits.check(&id, metadata.as_ref())?;
 
         let internal_id = self.storage.insert(id, vector, metadata)?;
+        self.index.insert(internal_id, vector, &self.storage)?;
 
-        if let Some(index) = &mut self.index {
-            index.insert(internal_id, vector, &self.storage)?;
-        }
-
+        self.write_seq += 1;
         Ok(())
     }
 
     /// Delete a vector by ID
     pub fn delete(&mut self, id: impl Into<VectorId>) -> Result<bool> {
-        let id = id.into();
-        self.storage.delete(&id)
+        let Ok(id) = self.config.id_type.parse(id.into()) else {
+            return Ok(false);
+        };
+        let deleted = self.storage.delete(&id)?;
+        self.write_seq += 1;
+        Ok(deleted)
+    }
+
+    /// Delete the vectors with the given IDs; returns those that existed
+    pub fn delete_batch(
+        &mut self,
+        ids: impl IntoIterator<Item = impl Into<VectorId>>,
+    ) -> Result<Vec<VectorId>> {
+        let mut deleted = Vec::new();
+        for id in ids {
+            let id = id.into();
+            if self.delete(id.clone())? {
+                deleted.push(id);
+            }
+        }
+        Ok(deleted)
+    }
+
+    /// Delete every vector whose metadata matches `filter`; returns their IDs
+    pub fn delete_by_filter(&mut self, filter: &filter::Filter) -> Result<Vec<VectorId>> {
+        let matching = self.storage.ids_matching(filter)?;
+        for id in &matching {
+            self.storage.delete(id)?;
+        }
+        self.write_seq += 1;
+        Ok(matching)
     }
 
     /// Insert or update a vector with the given ID and optional metadata
@@ -469,7 +1465,7 @@ impl QuantizedVectorDb {
         vector: &[f32],
         metadata: Option<Value>,
     ) -> Result<()> {
-        let id = id.into();
+        let id = self.config.id_type.parse(id.into())?;
 
         if vector.len() != self.config.dimensions {
             return Err(Error::DimensionMismatch {
@@ -477,13 +1473,12 @@ impl QuantizedVectorDb {
                 got: vector.len(),
             });
         }
+        self.config.metadata_limits.check(&id, metadata.as_ref())?;
 
         let internal_id = self.storage.upsert(id, vector, metadata)?;
+        self.index.insert(internal_id, vector, &self.storage)?;
 
-        if let Some(index) = &mut self.index {
-            index.insert(internal_id, vector, &self.storage)?;
-        }
-
+        self.write_seq += 1;
         Ok(())
     }
 
@@ -493,36 +1488,178 @@ impl QuantizedVectorDb {
             return Ok(());
         }
 
-        // Validate dimensions
-        for (_, vector, _) in &items {
-            if vector.len() != self.config.dimensions {
-                return Err(Error::DimensionMismatch {
-                    expected: self.config.dimensions,
-                    got: vector.len(),
-                });
-            }
-        }
+        let items = validate_batch(
+            self.config.id_type,
+            self.config.dimensions,
+            self.config.metadata_limits,
+            items,
+        )?;
 
         // 1. Batch Upsert into Storage (Single lock acquisition)
         let internal_ids = self.storage.upsert_batch(&items)?;
 
         // 2. Batch Insert into HNSW
-        if let Some(index) = &mut self.index {
-            let hnsw_items: Vec<(types::InternalId, &[f32])> = internal_ids
-                .iter()
-                .zip(items.iter())
-                .map(|(id, (_, vec, _))| (*id, vec.as_slice()))
-                .collect();
+        let hnsw_items: Vec<(types::InternalId, &[f32])> = internal_ids
+            .iter()
+            .zip(items.iter())
+            .map(|(id, (_, vec, _))| (*id, vec.as_slice()))
+            .collect();
+        self.index.insert_batch(&hnsw_items, &self.storage)?;
 
-            index.insert_batch(&hnsw_items, &self.storage)?;
+        self.write_seq += 1;
+        Ok(())
+    }
+
+    /// Delete every vector matching `filter` and upsert `items` in one step
+    ///
+    /// All items are validated before anything is deleted. Returns the number
+    /// of vectors that matched `filter`.
+    pub fn replace(
+        &mut self,
+        filter: &filter::Filter,
+        items: Vec<(VectorId, Vec<f32>, Option<Value>)>,
+    ) -> Result<usize> {
+        let items = validate_batch(
+            self.config.id_type,
+            self.config.dimensions,
+            self.config.metadata_limits,
+            items,
+        )?;
+
+        let matching = self.storage.ids_matching(filter)?;
+        for id in &matching {
+            self.storage.delete(id)?;
+        }
+        self.upsert_batch(items)?;
+        self.write_seq += 1;
+
+        Ok(matching.len())
+    }
+
+    /// Change the metadata of `id` without resending its vector; returns false
+    /// if there is no such vector
+    ///
+    /// `metadata` replaces the current metadata (`null` clears it), or with
+    /// `merge` is applied to it as a JSON merge patch.
+    pub fn update_metadata(
+        &mut self,
+        id: impl Into<VectorId>,
+        metadata: Value,
+        merge: bool,
+    ) -> Result<bool> {
+        let Ok(id) = self.config.id_type.parse(id.into()) else {
+            return Ok(false);
+        };
+        let Some(internal_id) = self.storage.get_internal_id(&id) else {
+            return Ok(false);
+        };
+        let current = self.storage.get_metadata(internal_id);
+        let updated = metadata_store::updated_metadata(current, metadata, merge);
+        self.config.metadata_limits.check(&id, updated.as_ref())?;
+        self.storage.update_metadata(&id, updated)?;
+
+        self.write_seq += 1;
+        Ok(true)
+    }
+
+    /// Sequence number of the last write, bumped by every write
+    pub fn write_seq(&self) -> u64 {
+        self.write_seq
+    }
+
+    /// Snapshot of the current vectors and graph, for exporting the collection
+    #[cfg(feature = "persistence")]
+    pub(crate) fn export_snapshot(&self) -> snapshot::Snapshot {
+        snapshot::Snapshot::capture(
+            0,
+            0,
+            self.config.dimensions,
+            &self.storage,
+            Some(&self.index as &dyn AnnIndex),
+        )
+    }
+
+    /// Fill an empty database from an exported snapshot
+    #[cfg(feature = "persistence")]
+    pub(crate) fn restore(&mut self, snapshot: snapshot::Snapshot) -> Result<()> {
+        if !self.storage.all_internal_ids().is_empty() {
+            return Err(Error::InvalidConfig(
+                "Snapshots can only be restored into an empty collection".to_string(),
+            ));
+        }
+        if snapshot.dimensions != self.config.dimensions {
+            return Err(Error::DimensionMismatch {
+                expected: self.config.dimensions,
+                got: snapshot.dimensions,
+            });
         }
 
+        let mut internal_ids = Vec::with_capacity(snapshot.vectors.len());
+        for stored in snapshot.vectors {
+            let id = self.config.id_type.parse(stored.id)?;
+            internal_ids.push(self.storage.insert(id, &stored.vector, stored.metadata)?);
+        }
+
+        match snapshot.hnsw_state {
+            Some(state) => self.index.load_state(state),
+            None => {
+                for internal_id in internal_ids {
+                    if let Some(vector) = self.storage.get_vector_data(internal_id) {
+                        self.index.insert(internal_id, &vector, &self.storage)?;
+                    }
+                }
+            }
+        }
+
+        self.write_seq += 1;
         Ok(())
     }
 
+    /// Fill an empty database from records and precomputed nearest-neighbor
+    /// lists
+    ///
+    /// See [`VectorDb::bulk_load`]; distances are taken on the quantized
+    /// vectors, as in an insert.
+    pub fn bulk_load(
+        &mut self,
+        items: Vec<(VectorId, Vec<f32>, Option<Value>)>,
+        neighbors: Vec<Vec<usize>>,
+    ) -> Result<()> {
+        if self.storage.len() + self.storage.deleted_count() > 0 {
+            return Err(Error::InvalidConfig(
+                "Bulk loading needs an empty collection".to_string(),
+            ));
+        }
+        let items = validate_batch(
+            self.config.id_type,
+            self.config.dimensions,
+            self.config.metadata_limits,
+            items,
+        )?;
+        let neighbors = bulk_neighbors(&items, neighbors)?;
+
+        for (id, vector, metadata) in items {
+            self.storage.insert(id, &vector, metadata)?;
+        }
+        self.index.build_from_neighbors(&neighbors, &self.storage)?;
+
+        self.write_seq += 1;
+        Ok(())
+    }
+
+    /// Whether a vector with this external ID exists, without copying it
+    pub fn contains(&self, id: &str) -> bool {
+        self.config
+            .id_type
+            .parse(VectorId::from(id))
+            .is_ok_and(|id| self.storage.get_internal_id(&id).is_some())
+    }
+
     /// Retrieve a vector by its external ID
     pub fn get(&self, id: &str) -> Result<Option<(Vec<f32>, Option<Value>)>> {
-        let id = VectorId::from(id);
+        let Ok(id) = self.config.id_type.parse(VectorId::from(id)) else {
+            return Ok(None);
+        };
         if let Some(internal_id) = self.storage.get_internal_id(&id) {
             let vector =
                 crate::storage::VectorStorageTrait::get_vector_data(&self.storage, internal_id)
@@ -534,6 +1671,49 @@ impl QuantizedVectorDb {
         }
     }
 
+    /// Retrieve metadata for several external IDs without copying vectors
+    ///
+    /// Unknown IDs are skipped; found IDs keep the order of `ids`.
+    pub fn get_metadata_batch(&self, ids: &[String]) -> Vec<(VectorId, Option<Value>)> {
+        let ids: Vec<VectorId> = ids
+            .iter()
+            .filter_map(|id| self.config.id_type.parse(VectorId::from(id.as_str())).ok())
+            .collect();
+        self.storage.get_metadata_batch(&ids)
+    }
+
+    /// Keep the matches of `filter` materialized for faster filtered search
+    ///
+    /// The match set is updated on every write. Cached filters are not
+    /// persisted.
+    pub fn cache_filter(&self, filter: filter::Filter) -> Result<CachedFilterInfo> {
+        self.storage.cache_filter(filter)
+    }
+
+    /// Drop a cached filter by ID; returns whether it existed
+    pub fn uncache_filter(&self, id: &str) -> bool {
+        self.storage.uncache_filter(id)
+    }
+
+    /// Filters currently cached, with their match counts and hits
+    pub fn cached_filters(&self) -> Vec<CachedFilterInfo> {
+        self.storage.cached_filters()
+    }
+
+    /// Page through records with a cursor; see [`Collection::list_page`](db::Collection::list_page)
+    pub fn list_page(&self, cursor: Option<ListCursor>, limit: usize) -> Result<ListPage> {
+        self.storage.list_page(cursor, limit)
+    }
+
+    /// Iterate over every record with its vector and metadata, a page at a
+    /// time; see [`Scan`]
+    pub fn scan(&self) -> Scan<'_> {
+        Scan::new(
+            move |cursor, limit| self.list_page(cursor, limit),
+            move |id| self.get(id),
+        )
+    }
+
     /// List all vector IDs and metadata (pagination)
     pub fn list(&self, offset: usize, limit: usize) -> Vec<(VectorId, Option<Value>)> {
         let ids = self.storage.all_internal_ids();
@@ -560,101 +1740,33 @@ impl QuantizedVectorDb {
         k: usize,
         filter: Option<&filter::Filter>,
     ) -> Result<Vec<(VectorId, f32, Option<Value>)>> {
-        if query.len() != self.config.dimensions {
-            return Err(Error::DimensionMismatch {
-                expected: self.config.dimensions,
-                got: query.len(),
-            });
-        }
-
-        if self.storage.is_empty() {
-            return Err(Error::EmptyIndex);
-        }
-
-        let metric = self.config.distance_metric;
-
-        // Increase K to account for stale entries and re-ranking
-        let multiplier =
-            if self.config.keep_originals && self.config.quantization != QuantizationType::None {
-                self.config.rerank_multiplier
-            } else {
-                1
-            };
-        // Buffer for stale entries (2x)
-        let search_k = k * multiplier * 2;
-
-        // Use HNSW if available
-        let results: Vec<(types::InternalId, f32)> = if let Some(index) = &self.index {
-            // HNSW Search
-            index.search(query, search_k, &self.storage.view(), filter)?
-        } else {
-            // Fallback to Brute Force
-            let storage_view = self.storage.view();
-            let quantized_query = self.storage.quantize_query(query);
-
-            let mut candidates: Vec<(types::InternalId, f32)> = self
-                .storage
-                .all_internal_ids()
-                .into_iter()
-                .filter(|&id| {
-                    if let Some(f) = filter {
-                        if let Some(meta) = self.storage.get_metadata(id) {
-                            f.matches(&meta)
-                        } else {
-                            false
-                        }
-                    } else {
-                        true
-                    }
-                })
-                .filter_map(|id| {
-                    storage_view
-                        .distance_quantized(query, &quantized_query, id, metric)
-                        .map(|dist| (id, dist))
-                })
-                .collect();
-            candidates.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
-            candidates.into_iter().take(search_k).collect()
-        };
+        self.search_with_usage(query, k, filter)
+            .map(|(results, _)| results)
+    }
 
-        // Filter stale results
-        let valid_candidates: Vec<(types::InternalId, f32)> = results
-            .into_iter()
-            .filter(|(internal_id, _)| {
-                if let Some(ext_id) = self.storage.get_external_id(*internal_id) {
-                    if let Some(current) = self.storage.get_internal_id(&ext_id) {
-                        return current == *internal_id;
-                    }
-                }
-                false
-            })
-            .collect();
+    /// Search for the k nearest neighbors, reporting the work performed
+    pub fn search_with_usage(
+        &self,
+        query: &[f32],
+        k: usize,
+        filter: Option<&filter::Filter>,
+    ) -> Result<(Vec<SearchHit>, SearchUsage)> {
+        self.search_with_params(query, k, filter, SearchParams::default())
+    }
 
-        // If re-ranking is enabled
-        let final_results: Vec<(types::InternalId, f32)> =
-            if self.config.keep_originals && self.config.quantization != QuantizationType::None {
-                let k_rerank = k * self.config.rerank_multiplier;
-                let top_candidates: Vec<_> = valid_candidates.into_iter().take(k_rerank).collect();
-
-                // Re-rank using original vectors
-                let mut reranked: Vec<_> = top_candidates
-                    .into_iter()
-                    .filter_map(|(id, _)| {
-                        self.storage.get_original(id).map(|orig| {
-                            let dist = metric.distance(query, &orig);
-                            (id, dist)
-                        })
-                    })
-                    .collect();
-
-                reranked.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
-                reranked.into_iter().take(k).collect()
-            } else {
-                valid_candidates.into_iter().take(k).collect()
-            };
+    /// Search for the k nearest neighbors with per-search overrides
+    pub fn search_with_params(
+        &self,
+        query: &[f32],
+        k: usize,
+        filter: Option<&filter::Filter>,
+        params: SearchParams,
+    ) -> Result<(Vec<SearchHit>, SearchUsage)> {
+        let mut usage = SearchUsage::default();
+        let results = self.search_internal(query, k, filter, params, &mut usage)?;
 
         // Map to external IDs and fetch metadata
-        let mapped: Vec<(VectorId, f32, Option<Value>)> = final_results
+        let mapped: Vec<(VectorId, f32, Option<Value>)> = results
             .into_iter()
             .filter_map(|(internal_id, distance)| {
                 self.storage.get_external_id(internal_id).map(|ext_id| {
@@ -664,7 +1776,7 @@ impl QuantizedVectorDb {
             })
             .collect();
 
-        Ok(mapped)
+        Ok((mapped, usage))
     }
 
     /// Search for the k nearest neighbors (without metadata)
@@ -674,6 +1786,52 @@ impl QuantizedVectorDb {
         k: usize,
         filter: Option<&filter::Filter>,
     ) -> Result<Vec<(VectorId, f32)>> {
+        self.search_ids_with_usage(query, k, filter)
+            .map(|(results, _)| results)
+    }
+
+    /// Search for the k nearest neighbors (without metadata), reporting the work performed
+    pub fn search_ids_with_usage(
+        &self,
+        query: &[f32],
+        k: usize,
+        filter: Option<&filter::Filter>,
+    ) -> Result<(Vec<(VectorId, f32)>, SearchUsage)> {
+        self.search_ids_with_params(query, k, filter, SearchParams::default())
+    }
+
+    /// Search for the k nearest neighbors (without metadata) with per-search overrides
+    pub fn search_ids_with_params(
+        &self,
+        query: &[f32],
+        k: usize,
+        filter: Option<&filter::Filter>,
+        params: SearchParams,
+    ) -> Result<(Vec<(VectorId, f32)>, SearchUsage)> {
+        let mut usage = SearchUsage::default();
+        let results = self.search_internal(query, k, filter, params, &mut usage)?;
+
+        let mapped: Vec<(VectorId, f32)> = results
+            .into_iter()
+            .filter_map(|(internal_id, distance)| {
+                self.storage
+                    .get_external_id(internal_id)
+                    .map(|ext_id| (ext_id, distance))
+            })
+            .collect();
+
+        Ok((mapped, usage))
+    }
+
+    /// Traverse the graph on quantized vectors, then re-rank with the originals
+    fn search_internal(
+        &self,
+        query: &[f32],
+        k: usize,
+        filter: Option<&filter::Filter>,
+        params: SearchParams,
+        usage: &mut SearchUsage,
+    ) -> Result<Vec<(types::InternalId, f32)>> {
         if query.len() != self.config.dimensions {
             return Err(Error::DimensionMismatch {
                 expected: self.config.dimensions,
@@ -685,89 +1843,55 @@ impl QuantizedVectorDb {
             return Err(Error::EmptyIndex);
         }
 
-        let metric = self.config.distance_metric;
-        let multiplier =
-            if self.config.keep_originals && self.config.quantization != QuantizationType::None {
-                self.config.rerank_multiplier
-            } else {
-                1
-            };
-        let search_k = k * multiplier * 2;
-
-        let results: Vec<(types::InternalId, f32)> = if let Some(index) = &self.index {
-            index.search(query, search_k, &self.storage.view(), filter)?
+        let rescore = self.config.keep_originals
+            && self.config.quantization != QuantizationType::None
+            && params.rescore.unwrap_or(true);
+        let candidates = if rescore {
+            params.candidates(k, self.config.rerank_multiplier as f32)
         } else {
-            let storage_view = self.storage.view();
-            let quantized_query = self.storage.quantize_query(query);
-
-            let mut candidates: Vec<(types::InternalId, f32)> = self
-                .storage
-                .all_internal_ids()
-                .into_iter()
-                .filter(|&id| {
-                    if let Some(f) = filter {
-                        if let Some(meta) = self.storage.get_metadata(id) {
-                            f.matches(&meta)
-                        } else {
-                            false
-                        }
-                    } else {
-                        true
-                    }
-                })
-                .filter_map(|id| {
-                    storage_view
-                        .distance_quantized(query, &quantized_query, id, metric)
-                        .map(|dist| (id, dist))
-                })
-                .collect();
-            candidates.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
-            candidates.into_iter().take(search_k).collect()
+            k
         };
 
-        let valid_candidates: Vec<(types::InternalId, f32)> = results
-            .into_iter()
-            .filter(|(internal_id, _)| {
-                if let Some(ext_id) = self.storage.get_external_id(*internal_id) {
-                    if let Some(current) = self.storage.get_internal_id(&ext_id) {
-                        return current == *internal_id;
-                    }
-                }
-                false
-            })
-            .collect();
+        // Buffer for stale entries (2x). Rescored candidates are cut on their
+        // full-precision distances below, not on the quantized ones.
+        let results = self.index.search_within(
+            query,
+            candidates * 2,
+            Some(params.ef_search.unwrap_or(self.config.hnsw.ef_search)),
+            params.max_distance.filter(|_| !rescore),
+            &self.storage.search_view(filter),
+            filter,
+            usage,
+        )?;
 
-        let final_results: Vec<(types::InternalId, f32)> =
-            if self.config.keep_originals && self.config.quantization != QuantizationType::None {
-                let k_rerank = k * self.config.rerank_multiplier;
-                let top_candidates: Vec<_> = valid_candidates.into_iter().take(k_rerank).collect();
-
-                let mut reranked: Vec<_> = top_candidates
-                    .into_iter()
-                    .filter_map(|(id, _)| {
-                        self.storage.get_original(id).map(|orig| {
-                            let dist = metric.distance(query, &orig);
-                            (id, dist)
-                        })
-                    })
-                    .collect();
-
-                reranked.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
-                reranked.into_iter().take(k).collect()
-            } else {
-                valid_candidates.into_iter().take(k).collect()
-            };
+        // Filter stale results
+        let valid_candidates = results.into_iter().filter(|(internal_id, _)| {
+            self.storage
+                .get_external_id(*internal_id)
+                .and_then(|ext_id| self.storage.get_internal_id(&ext_id))
+                == Some(*internal_id)
+        });
+
+        if !rescore {
+            return Ok(valid_candidates.take(k).collect());
+        }
 
-        let mapped: Vec<(VectorId, f32)> = final_results
-            .into_iter()
-            .filter_map(|(internal_id, distance)| {
+        // Re-rank using original vectors
+        let metric = self.config.distance_metric;
+        let mut reranked: Vec<_> = valid_candidates
+            .take(candidates)
+            .filter_map(|(id, _)| {
                 self.storage
-                    .get_external_id(internal_id)
-                    .map(|ext_id| (ext_id, distance))
+                    .get_original(id)
+                    .map(|orig| (id, metric.distance(query, &orig)))
             })
             .collect();
+        usage.rescored_candidates += reranked.len() as u64;
+        usage.vectors_scanned += reranked.len() as u64;
 
-        Ok(mapped)
+        reranked.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+        reranked.truncate(k);
+        Ok(within(reranked, params.max_distance))
     }
 
     /// Get the number of vectors in the database
@@ -775,6 +1899,59 @@ impl QuantizedVectorDb {
         self.storage.len()
     }
 
+    /// Number of live vectors, or of those whose metadata matches `filter`
+    pub fn count(&self, filter: Option<&filter::Filter>) -> Result<usize> {
+        self.storage.count(filter)
+    }
+
+    /// Get the number of deleted or overwritten vectors not yet reclaimed
+    pub fn deleted_count(&self) -> usize {
+        self.storage.deleted_count()
+    }
+
+    /// Space held by deleted or overwritten records
+    pub fn garbage_stats(&self) -> GarbageStats {
+        self.storage.garbage_stats()
+    }
+
+    /// Rebuild the database from its live records, dropping tombstoned slots
+    ///
+    /// Vectors are re-quantized from the stored originals, or from their
+    /// dequantized values if originals aren't kept.
+    #[cfg(feature = "persistence")]
+    pub fn compact(&mut self) -> Result<CompactionReport> {
+        let snapshot = self.export_snapshot();
+        let report = CompactionReport::new(snapshot.len(), &self.garbage_stats());
+        let mut compacted = Self::new(self.config.clone())?;
+        compacted.restore(snapshot)?;
+        for info in self.storage.cached_filters() {
+            compacted.storage.cache_filter(info.filter)?;
+        }
+        compacted.write_seq = self.write_seq + 1;
+        *self = compacted;
+        Ok(report)
+    }
+
+    /// Shape of the HNSW graph
+    pub fn graph_stats(&self) -> GraphStats {
+        self.index.graph_stats()
+    }
+
+    /// Relink up to `budget` nodes of the HNSW graph left linking to deleted
+    /// vectors; see [`HnswIndex::repair`]
+    pub fn repair_graph(&self, budget: usize) -> GraphRepair {
+        self.index.repair(budget, &self.storage)
+    }
+
+    /// Export the HNSW adjacency, optionally for one `level` or a `sample` of nodes
+    pub fn export_graph(&self, level: Option<usize>, sample: Option<usize>) -> Result<GraphExport> {
+        Ok(self.index.export_graph(level, sample, |id| {
+            (!self.storage.is_deleted(id))
+                .then(|| self.storage.get_external_id(id))
+                .flatten()
+        }))
+    }
+
     /// Check if the database is empty
     pub fn is_empty(&self) -> bool {
         self.storage.is_empty()
@@ -785,9 +1962,31 @@ impl QuantizedVectorDb {
         &self.config
     }
 
+    /// Change the candidate list size searches use by default
+    pub fn set_ef_search(&mut self, ef_search: usize) {
+        self.config.hnsw.ef_search = ef_search;
+    }
+
     /// Get approximate memory usage in bytes
     pub fn memory_usage(&self) -> usize {
-        self.storage.memory_usage() + self.index.as_ref().map(|i| i.memory_usage()).unwrap_or(0)
+        self.storage.memory_usage() + self.index.memory_usage()
+    }
+
+    /// Get approximate memory usage split by component
+    pub fn memory_breakdown(&self) -> MemoryBreakdown {
+        MemoryBreakdown {
+            vectors: self.storage.vector_bytes(),
+            graph: self.index.memory_usage(),
+            ids: self.storage.id_bytes(),
+            metadata: self.storage.metadata_bytes(),
+            sparse: 0,
+            metadata_compression_ratio: self.storage.metadata_compression_ratio(),
+        }
+    }
+
+    /// The `n` records with the largest metadata, largest first
+    pub fn largest_payloads(&self, n: usize) -> Vec<PayloadSize> {
+        self.storage.largest_payloads(n)
     }
 
     /// Get compression ratio compared to unquantized storage
@@ -796,6 +1995,77 @@ impl QuantizedVectorDb {
     }
 }
 
+/// An `(id, vector, metadata)` item of a batch write
+type BatchItem = (VectorId, Vec<f32>, Option<Value>);
+
+/// Normalize the IDs of a batch and check every vector's dimensions and
+/// metadata limits
+fn validate_batch(
+    id_type: IdType,
+    dimensions: usize,
+    limits: MetadataLimits,
+    items: Vec<BatchItem>,
+) -> Result<Vec<BatchItem>> {
+    items
+        .into_iter()
+        .map(|(id, vector, metadata)| {
+            if vector.len() != dimensions {
+                return Err(Error::DimensionMismatch {
+                    expected: dimensions,
+                    got: vector.len(),
+                });
+            }
+            let id = id_type.parse(id)?;
+            limits.check(&id, metadata.as_ref())?;
+            Ok((id, vector, metadata))
+        })
+        .collect()
+}
+
+/// Bulk-load neighbor lists as the internal IDs `items` will get in an empty
+/// collection, after checking the lists and that no ID repeats
+fn bulk_neighbors(
+    items: &[BatchItem],
+    neighbors: Vec<Vec<usize>>,
+) -> Result<Vec<Vec<types::InternalId>>> {
+    if neighbors.len() != items.len() {
+        return Err(Error::InvalidConfig(format!(
+            "Got {} neighbor lists for {} records",
+            neighbors.len(),
+            items.len()
+        )));
+    }
+    let mut seen = std::collections::HashSet::new();
+    if let Some((id, _, _)) = items.iter().find(|(id, _, _)| !seen.insert(id)) {
+        return Err(Error::DuplicateId(id.to_string()));
+    }
+    neighbors
+        .into_iter()
+        .map(|list| {
+            list.into_iter()
+                .map(|position| {
+                    if position < items.len() {
+                        Ok(types::InternalId::from(position))
+                    } else {
+                        Err(Error::InvalidConfig(format!(
+                            "Neighbor {position} is past the last of {} records",
+                            items.len()
+                        )))
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// `results` without those further than `max_distance`
+fn within<T>(mut results: Vec<(T, f32)>, max_distance: Option<f32>) -> Vec<(T, f32)> {
+    if let Some(max_distance) = max_distance {
+        results.retain(|(_, distance)| *distance <= max_distance);
+    }
+    results
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -910,6 +2180,147 @@ mod tests {
         assert_eq!(results[0].0.as_str(), "vec1");
     }
 
+    #[test]
+    fn test_search_usage() {
+        let config = Config {
+            dimensions: 4,
+            ..Default::default()
+        };
+
+        let mut db = VectorDb::new(config).unwrap();
+        db.insert("vec1", &[1.0, 0.0, 0.0, 0.0], None).unwrap();
+        db.insert("vec2", &[0.0, 1.0, 0.0, 0.0], None).unwrap();
+        db.insert("vec3", &[0.9, 0.1, 0.0, 0.0], None).unwrap();
+
+        let (results, usage) = db
+            .search_with_usage(&[1.0, 0.0, 0.0, 0.0], 2, None)
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(usage.vectors_scanned >= 3);
+        assert!(usage.graph_hops >= 1);
+        assert_eq!(usage.rescored_candidates, 0);
+
+        let config = QuantizedConfig {
+            dimensions: 4,
+            quantization: QuantizationType::SQ8,
+            keep_originals: true,
+            rerank_multiplier: 2,
+            ..Default::default()
+        };
+
+        let mut db = QuantizedVectorDb::new(config).unwrap();
+        db.insert("vec1", &[1.0, 0.0, 0.0, 0.0], None).unwrap();
+        db.insert("vec2", &[0.0, 1.0, 0.0, 0.0], None).unwrap();
+
+        let (_, usage) = db
+            .search_ids_with_usage(&[1.0, 0.0, 0.0, 0.0], 1, None)
+            .unwrap();
+        assert_eq!(usage.rescored_candidates, 2);
+    }
+
+    #[test]
+    fn test_search_params_ef_search() {
+        let config = Config {
+            dimensions: 8,
+            hnsw: HnswConfig::default().with_m(8),
+            ..Default::default()
+        };
+        let mut db = VectorDb::new(config).unwrap();
+        for i in 0..1000 {
+            let v: Vec<f32> = (0..8).map(|d| ((i * 8 + d) as f32 * 0.7).sin()).collect();
+            db.insert(format!("v{i}"), &v, None).unwrap();
+        }
+
+        let query = [0.3; 8];
+        let search = |ef_search| {
+            let params = SearchParams {
+                ef_search: Some(ef_search),
+                ..Default::default()
+            };
+            db.search_ids_with_params(&query, 5, None, params).unwrap()
+        };
+        let (narrow, narrow_usage) = search(10);
+        let (wide, wide_usage) = search(400);
+        assert_eq!(narrow.len(), 5);
+        assert_eq!(wide.len(), 5);
+        assert!(wide_usage.vectors_scanned > narrow_usage.vectors_scanned);
+
+        assert!(HnswConfig::default().with_m(1).validate().is_err());
+        let zero_ef = HnswConfig {
+            ef_search: 0,
+            ..Default::default()
+        };
+        assert!(zero_ef.validate().is_err());
+    }
+
+    #[test]
+    fn test_u64_ids() {
+        let config = Config {
+            dimensions: 4,
+            id_type: IdType::U64,
+            ..Default::default()
+        };
+
+        let mut db = VectorDb::new(config).unwrap();
+        db.insert(VectorId::from(7u64), &[1.0, 0.0, 0.0, 0.0], None)
+            .unwrap();
+        db.insert("42", &[0.0, 1.0, 0.0, 0.0], None).unwrap();
+
+        assert!(matches!(
+            db.insert("vec1", &[0.0, 0.0, 1.0, 0.0], None),
+            Err(Error::InvalidId(_))
+        ));
+        assert!(matches!(
+            db.insert("007", &[0.0, 0.0, 1.0, 0.0], None),
+            Err(Error::InvalidId(_))
+        ));
+
+        assert!(db.get("7").unwrap().is_some());
+        assert!(db.get("vec1").unwrap().is_none());
+
+        let results = db.search(&[0.0, 1.0, 0.0, 0.0], 1, None).unwrap();
+        assert_eq!(results[0].0.as_u64(), Some(42));
+        assert_eq!(results[0].0.to_string(), "42");
+
+        assert!(db.delete("42").unwrap());
+        assert!(!db.delete("vec1").unwrap());
+        assert_eq!(db.len(), 1);
+    }
+
+    #[test]
+    fn test_get_metadata_batch() {
+        let config = Config {
+            dimensions: 4,
+            ..Default::default()
+        };
+
+        let mut db = VectorDb::new(config).unwrap();
+        db.insert(
+            "a",
+            &[1.0, 0.0, 0.0, 0.0],
+            Some(serde_json::json!({"n": 1})),
+        )
+        .unwrap();
+        db.insert("b", &[0.0, 1.0, 0.0, 0.0], None).unwrap();
+        db.insert(
+            "c",
+            &[0.0, 0.0, 1.0, 0.0],
+            Some(serde_json::json!({"n": 3})),
+        )
+        .unwrap();
+        db.delete("c").unwrap();
+
+        let ids = ["b", "missing", "c", "a"].map(String::from);
+        let found = db.get_metadata_batch(&ids);
+        assert_eq!(
+            found,
+            vec![
+                (VectorId::from("b"), None),
+                (VectorId::from("a"), Some(serde_json::json!({"n": 1}))),
+            ]
+        );
+    }
+
     #[test]
     fn test_compression_ratio() {
         let config = QuantizedConfig {
@@ -0,0 +1,274 @@
+//! Typed access to record metadata
+//!
+//! Search hits, listed and scanned records carry their metadata as JSON.
+//! [`MetaAccess`] reads fields from it by dot-notation path, as filters do,
+//! converted to the type the caller expects; a missing field or one of
+//! another type reads as `None`.
+//!
+//! ```rust
+//! use serde_json::json;
+//! use surgedb_core::MetaAccess;
+//!
+//! let metadata = json!({ "title": "Dune", "year": 1965, "at": "2024-05-01T12:00:00Z" });
+//! assert_eq!(metadata.meta_str("title"), Some("Dune"));
+//! assert_eq!(metadata.meta_i64("year"), Some(1965));
+//! assert_eq!(metadata.meta_str("year"), None);
+//! assert!(metadata.meta_time("at").is_some());
+//! ```
+
+use crate::filter::get_value_by_path;
+use crate::types::{SearchHit, VectorId};
+use serde_json::Value;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Typed reads of a record's metadata fields
+pub trait MetaAccess {
+    /// The record's metadata, if it has any
+    fn metadata(&self) -> Option<&Value>;
+
+    /// The value at a dot-notation path
+    fn meta(&self, path: &str) -> Option<&Value> {
+        self.metadata()
+            .and_then(|metadata| get_value_by_path(metadata, path))
+    }
+
+    fn meta_str(&self, path: &str) -> Option<&str> {
+        self.meta(path)?.as_str()
+    }
+
+    /// An integer field; floats don't convert
+    fn meta_i64(&self, path: &str) -> Option<i64> {
+        self.meta(path)?.as_i64()
+    }
+
+    /// A numeric field, integer or float
+    fn meta_f64(&self, path: &str) -> Option<f64> {
+        self.meta(path)?.as_f64()
+    }
+
+    fn meta_bool(&self, path: &str) -> Option<bool> {
+        self.meta(path)?.as_bool()
+    }
+
+    /// A time stored as seconds since the Unix epoch or as an RFC 3339
+    /// string, such as `2024-05-01T12:00:00.5+02:00`
+    fn meta_time(&self, path: &str) -> Option<SystemTime> {
+        match self.meta(path)? {
+            Value::Number(n) => from_unix_seconds(n.as_f64()?),
+            Value::String(s) => parse_rfc3339(s),
+            _ => None,
+        }
+    }
+}
+
+impl MetaAccess for Value {
+    fn metadata(&self) -> Option<&Value> {
+        Some(self)
+    }
+}
+
+impl MetaAccess for Option<Value> {
+    fn metadata(&self) -> Option<&Value> {
+        self.as_ref()
+    }
+}
+
+/// A search hit: `(id, distance, metadata)`
+impl MetaAccess for SearchHit {
+    fn metadata(&self) -> Option<&Value> {
+        self.2.as_ref()
+    }
+}
+
+/// A scanned record: `(id, vector, metadata)`
+impl MetaAccess for (VectorId, Vec<f32>, Option<Value>) {
+    fn metadata(&self) -> Option<&Value> {
+        self.2.as_ref()
+    }
+}
+
+/// A listed record: `(id, metadata)`
+impl MetaAccess for (VectorId, Option<Value>) {
+    fn metadata(&self) -> Option<&Value> {
+        self.1.as_ref()
+    }
+}
+
+impl MetaAccess for crate::sparse::HybridHit {
+    fn metadata(&self) -> Option<&Value> {
+        self.metadata.as_ref()
+    }
+}
+
+fn from_unix_seconds(seconds: f64) -> Option<SystemTime> {
+    if !seconds.is_finite() {
+        return None;
+    }
+    let offset = Duration::try_from_secs_f64(seconds.abs()).ok()?;
+    if seconds < 0.0 {
+        UNIX_EPOCH.checked_sub(offset)
+    } else {
+        UNIX_EPOCH.checked_add(offset)
+    }
+}
+
+/// Parse `YYYY-MM-DDTHH:MM:SS[.fraction](Z|±HH:MM)`
+fn parse_rfc3339(s: &str) -> Option<SystemTime> {
+    let s = s.as_bytes();
+    if s.len() < 20 || s[4] != b'-' || s[7] != b'-' || s[13] != b':' || s[16] != b':' {
+        return None;
+    }
+    if !matches!(s[10], b'T' | b't' | b' ') {
+        return None;
+    }
+    let digits = |range: std::ops::Range<usize>| -> Option<i64> {
+        let part = &s[range];
+        part.iter()
+            .all(u8::is_ascii_digit)
+            .then(|| part.iter().fold(0i64, |n, &d| n * 10 + i64::from(d - b'0')))
+    };
+    let (year, month, day) = (digits(0..4)?, digits(5..7)?, digits(8..10)?);
+    let (hour, minute, second) = (digits(11..13)?, digits(14..16)?, digits(17..19)?);
+    if !(1..=12).contains(&month)
+        || day < 1
+        || day > days_in_month(year, month)
+        || hour > 23
+        || minute > 59
+        // 60 allows a leap second
+        || second > 60
+    {
+        return None;
+    }
+
+    let mut rest = &s[19..];
+    let mut nanos = 0u32;
+    if let Some(fraction) = rest.strip_prefix(b".") {
+        let len = fraction.iter().take_while(|b| b.is_ascii_digit()).count();
+        if len == 0 {
+            return None;
+        }
+        // Digits past nanoseconds are dropped
+        for (i, &d) in fraction[..len].iter().take(9).enumerate() {
+            nanos += u32::from(d - b'0') * 10u32.pow(8 - i as u32);
+        }
+        rest = &fraction[len..];
+    }
+    let offset = match rest {
+        b"Z" | b"z" => 0,
+        [sign @ (b'+' | b'-'), h1, h2, b':', m1, m2] => {
+            let hh = std::str::from_utf8(&[*h1, *h2]).ok()?.parse::<i64>().ok()?;
+            let mm = std::str::from_utf8(&[*m1, *m2]).ok()?.parse::<i64>().ok()?;
+            if hh > 23 || mm > 59 {
+                return None;
+            }
+            let offset = hh * 3600 + mm * 60;
+            if *sign == b'+' {
+                offset
+            } else {
+                -offset
+            }
+        }
+        _ => return None,
+    };
+
+    let seconds =
+        days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 + second - offset;
+    let whole = Duration::from_secs(seconds.unsigned_abs());
+    if seconds < 0 {
+        UNIX_EPOCH
+            .checked_sub(whole)?
+            .checked_add(Duration::from_nanos(nanos.into()))
+    } else {
+        UNIX_EPOCH.checked_add(whole + Duration::from_nanos(nanos.into()))
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days from 1970-01-01 to a date of the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_from_march = (month + 9) % 12;
+    let day_of_year = (153 * month_from_march + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn unix(time: SystemTime) -> f64 {
+        match time.duration_since(UNIX_EPOCH) {
+            Ok(d) => d.as_secs_f64(),
+            Err(e) => -e.duration().as_secs_f64(),
+        }
+    }
+
+    #[test]
+    fn test_typed_fields() {
+        let hit: SearchHit = (
+            VectorId::from("a"),
+            0.5,
+            Some(json!({
+                "title": "Dune",
+                "year": 1965,
+                "rating": 4.5,
+                "in_print": true,
+                "author": { "name": "Frank Herbert" }
+            })),
+        );
+        assert_eq!(hit.meta_str("title"), Some("Dune"));
+        assert_eq!(hit.meta_str("author.name"), Some("Frank Herbert"));
+        assert_eq!(hit.meta_i64("year"), Some(1965));
+        assert_eq!(hit.meta_f64("year"), Some(1965.0));
+        assert_eq!(hit.meta_f64("rating"), Some(4.5));
+        assert_eq!(hit.meta_i64("rating"), None);
+        assert_eq!(hit.meta_bool("in_print"), Some(true));
+        assert_eq!(hit.meta_str("year"), None);
+        assert_eq!(hit.meta_str("missing"), None);
+
+        let listed: (VectorId, Option<Value>) = (VectorId::from("b"), None);
+        assert_eq!(listed.meta("title"), None);
+    }
+
+    #[test]
+    fn test_meta_time() {
+        let metadata = json!({
+            "utc": "2024-05-01T12:00:00Z",
+            "offset": "2024-05-01T14:00:00.25+02:00",
+            "before_epoch": "1969-12-31T23:59:59Z",
+            "leap_day": "2024-02-29T00:00:00Z",
+            "seconds": 1714564800,
+            "fractional": 1714564800.5,
+            "bad_day": "2023-02-29T00:00:00Z",
+            "no_zone": "2024-05-01T12:00:00",
+            "date_only": "2024-05-01",
+        });
+        let time = |path| metadata.meta_time(path).map(unix);
+        assert_eq!(time("utc"), Some(1_714_564_800.0));
+        assert_eq!(time("offset"), Some(1_714_564_800.25));
+        assert_eq!(time("before_epoch"), Some(-1.0));
+        assert_eq!(time("leap_day"), Some(1_709_164_800.0));
+        assert_eq!(time("seconds"), Some(1_714_564_800.0));
+        assert_eq!(time("fractional"), Some(1_714_564_800.5));
+        assert_eq!(time("bad_day"), None);
+        assert_eq!(time("no_zone"), None);
+        assert_eq!(time("date_only"), None);
+        assert_eq!(time("missing"), None);
+    }
+}
@@ -0,0 +1,443 @@
+//! Per-vector metadata storage, optionally zstd-compressed
+//!
+//! With [`MetadataCompression::Zstd`] each payload is stored as a zstd frame
+//! of its JSON encoding. The first 256 payloads of a collection
+//! are used to train a shared dictionary; once it exists, every payload
+//! (including those stored before training) is compressed with it. Reads
+//! decompress transparently.
+
+use crate::error::{Error, Result};
+use crate::types::{json_len, value_heap_size, InternalId, MetadataCompression};
+use serde_json::Value;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+pub(crate) struct MetadataStore {
+    inner: Inner,
+}
+
+enum Inner {
+    Plain {
+        /// Values with the length of their JSON encoding
+        values: HashMap<InternalId, (Value, usize)>,
+        /// Estimated heap bytes of the stored values
+        heap_bytes: usize,
+    },
+    #[cfg(feature = "compression")]
+    Zstd(zstd_store::ZstdStore),
+}
+
+impl Default for MetadataStore {
+    fn default() -> Self {
+        Self {
+            inner: Inner::Plain {
+                values: HashMap::new(),
+                heap_bytes: 0,
+            },
+        }
+    }
+}
+
+impl MetadataStore {
+    pub fn new(compression: MetadataCompression) -> Result<Self> {
+        let inner = match compression {
+            MetadataCompression::None => return Ok(Self::default()),
+            #[cfg(feature = "compression")]
+            MetadataCompression::Zstd => Inner::Zstd(zstd_store::ZstdStore::default()),
+            #[cfg(not(feature = "compression"))]
+            MetadataCompression::Zstd => {
+                return Err(Error::InvalidConfig(
+                    "zstd metadata compression requires the `compression` feature".to_string(),
+                ))
+            }
+        };
+        Ok(Self { inner })
+    }
+
+    pub fn get(&self, id: InternalId) -> Option<Value> {
+        match &self.inner {
+            Inner::Plain { values, .. } => values.get(&id).map(|(value, _)| value.clone()),
+            #[cfg(feature = "compression")]
+            Inner::Zstd(store) => store.get(id),
+        }
+    }
+
+    pub fn insert(&mut self, id: InternalId, value: Value) -> Result<()> {
+        match &mut self.inner {
+            Inner::Plain { values, heap_bytes } => {
+                *heap_bytes += value_heap_size(&value);
+                let len = json_len(&value);
+                if let Some((old, _)) = values.insert(id, (value, len)) {
+                    *heap_bytes -= value_heap_size(&old);
+                }
+                Ok(())
+            }
+            #[cfg(feature = "compression")]
+            Inner::Zstd(store) => store.insert(id, &value),
+        }
+    }
+
+    pub fn remove(&mut self, id: InternalId) -> Option<Value> {
+        match &mut self.inner {
+            Inner::Plain { values, heap_bytes } => {
+                let (old, _) = values.remove(&id)?;
+                *heap_bytes -= value_heap_size(&old);
+                Some(old)
+            }
+            #[cfg(feature = "compression")]
+            Inner::Zstd(store) => store.remove(id),
+        }
+    }
+
+    /// Approximate bytes held, including any trained dictionary
+    pub fn memory_usage(&self) -> usize {
+        match &self.inner {
+            Inner::Plain { values, heap_bytes } => {
+                values.capacity() * (std::mem::size_of::<(InternalId, (Value, usize))>() + 1)
+                    + heap_bytes
+            }
+            #[cfg(feature = "compression")]
+            Inner::Zstd(store) => store.memory_usage(),
+        }
+    }
+
+    /// The `n` largest payloads by JSON length, largest first
+    ///
+    /// Keeps only `n` candidates while walking the store, so it is cheap
+    /// enough to run on every stats call.
+    pub fn largest(&self, n: usize) -> Vec<(InternalId, usize)> {
+        let sizes: Box<dyn Iterator<Item = (InternalId, usize)> + '_> = match &self.inner {
+            Inner::Plain { values, .. } => {
+                Box::new(values.iter().map(|(&id, &(_, len))| (id, len)))
+            }
+            #[cfg(feature = "compression")]
+            Inner::Zstd(store) => Box::new(store.sizes()),
+        };
+        let mut top = BinaryHeap::with_capacity(n + 1);
+        for (id, len) in sizes {
+            top.push(Reverse((len, id.0)));
+            if top.len() > n {
+                top.pop();
+            }
+        }
+        top.into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((len, id))| (InternalId(id), len))
+            .collect()
+    }
+
+    /// Uncompressed JSON bytes divided by stored bytes
+    ///
+    /// `None` when compression is off or nothing is stored.
+    pub fn compression_ratio(&self) -> Option<f64> {
+        match &self.inner {
+            Inner::Plain { .. } => None,
+            #[cfg(feature = "compression")]
+            Inner::Zstd(store) => store.compression_ratio(),
+        }
+    }
+}
+
+/// Metadata of a record after an update
+///
+/// `update` replaces `current`, or with `merge` is applied to it as a JSON
+/// merge patch (RFC 7396): objects merge recursively and `null` removes a key.
+/// A `null` result means no metadata.
+pub(crate) fn updated_metadata(
+    current: Option<Value>,
+    update: Value,
+    merge: bool,
+) -> Option<Value> {
+    let updated = if merge {
+        let mut target = current.unwrap_or(Value::Null);
+        merge_patch(&mut target, update);
+        target
+    } else {
+        update
+    };
+    (!updated.is_null()).then_some(updated)
+}
+
+fn merge_patch(target: &mut Value, patch: Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch;
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Default::default());
+    }
+    if let Value::Object(map) = target {
+        for (key, value) in patch {
+            if value.is_null() {
+                map.remove(&key);
+            } else {
+                merge_patch(map.entry(key).or_insert(Value::Null), value);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "compression")]
+mod zstd_store {
+    use super::*;
+    use std::cell::RefCell;
+    use zstd::zstd_safe::{self, CCtx, CDict, DCtx, DDict};
+
+    /// Payloads sampled for dictionary training
+    pub(super) const TRAIN_SAMPLES: usize = 256;
+    /// Maximum trained dictionary size in bytes
+    const DICT_SIZE: usize = 16 * 1024;
+    const LEVEL: i32 = 3;
+
+    fn encode_error(e: impl std::fmt::Display) -> Error {
+        Error::Storage(format!("Failed to compress metadata: {}", e))
+    }
+
+    thread_local! {
+        static CCTX: RefCell<CCtx<'static>> = RefCell::new(CCtx::create());
+        static DCTX: RefCell<DCtx<'static>> = RefCell::new(DCtx::create());
+    }
+
+    struct Dictionary {
+        len: usize,
+        cdict: CDict<'static>,
+        ddict: DDict<'static>,
+    }
+
+    #[derive(Default)]
+    pub(super) struct ZstdStore {
+        frames: HashMap<InternalId, Box<[u8]>>,
+        dict: Option<Dictionary>,
+        /// JSON payloads collected until the dictionary is trained
+        samples: Vec<Vec<u8>>,
+        /// Set once training has been attempted, successfully or not
+        trained: bool,
+        raw_bytes: usize,
+        stored_bytes: usize,
+    }
+
+    impl ZstdStore {
+        pub fn get(&self, id: InternalId) -> Option<Value> {
+            let json = decompress(self.frames.get(&id)?, self.dict.as_ref())?;
+            serde_json::from_slice(&json).ok()
+        }
+
+        pub fn insert(&mut self, id: InternalId, value: &Value) -> Result<()> {
+            let json = serde_json::to_vec(value)?;
+            let frame = compress(&json, self.dict.as_ref())?;
+            self.raw_bytes += json.len();
+            self.stored_bytes += frame.len();
+            if let Some(old) = self.frames.insert(id, frame) {
+                self.untrack(&old);
+            }
+
+            if !self.trained {
+                self.samples.push(json);
+                if self.samples.len() >= TRAIN_SAMPLES {
+                    self.train()?;
+                }
+            }
+            Ok(())
+        }
+
+        pub fn remove(&mut self, id: InternalId) -> Option<Value> {
+            let frame = self.frames.remove(&id)?;
+            self.untrack(&frame);
+            let json = decompress(&frame, self.dict.as_ref())?;
+            serde_json::from_slice(&json).ok()
+        }
+
+        pub fn memory_usage(&self) -> usize {
+            self.frames.capacity() * (std::mem::size_of::<(InternalId, Box<[u8]>)>() + 1)
+                + self.stored_bytes
+                + self.samples.iter().map(Vec::capacity).sum::<usize>()
+                + self.dict.as_ref().map_or(0, |d| d.len)
+        }
+
+        /// JSON length of each payload, read from its frame header
+        pub fn sizes(&self) -> impl Iterator<Item = (InternalId, usize)> + '_ {
+            self.frames
+                .iter()
+                .filter_map(|(&id, frame)| Some((id, content_size(frame)?)))
+        }
+
+        pub fn compression_ratio(&self) -> Option<f64> {
+            (self.stored_bytes > 0).then(|| self.raw_bytes as f64 / self.stored_bytes as f64)
+        }
+
+        fn untrack(&mut self, frame: &[u8]) {
+            self.stored_bytes -= frame.len();
+            if let Some(len) = content_size(frame) {
+                self.raw_bytes -= len;
+            }
+        }
+
+        /// Train the shared dictionary and recompress existing payloads with it
+        fn train(&mut self) -> Result<()> {
+            self.trained = true;
+            let samples = std::mem::take(&mut self.samples);
+            let Ok(dict) = zstd::dict::from_samples(&samples, DICT_SIZE) else {
+                // Too little or too uniform data; plain zstd is used instead
+                return Ok(());
+            };
+            let dict = Dictionary {
+                len: dict.len(),
+                cdict: CDict::try_create(&dict, LEVEL)
+                    .ok_or_else(|| encode_error("bad dictionary"))?,
+                ddict: DDict::create(&dict),
+            };
+
+            let mut stored_bytes = 0;
+            for frame in self.frames.values_mut() {
+                let json = decompress(frame, None)
+                    .ok_or_else(|| encode_error("corrupt frame during recompression"))?;
+                *frame = compress(&json, Some(&dict))?;
+                stored_bytes += frame.len();
+            }
+            self.stored_bytes = stored_bytes;
+            self.dict = Some(dict);
+            Ok(())
+        }
+    }
+
+    fn compress(src: &[u8], dict: Option<&Dictionary>) -> Result<Box<[u8]>> {
+        let mut dst = Vec::with_capacity(zstd_safe::compress_bound(src.len()));
+        CCTX.with(|cctx| {
+            let mut cctx = cctx.borrow_mut();
+            match dict {
+                Some(dict) => cctx.compress_using_cdict(&mut dst, src, &dict.cdict),
+                None => cctx.compress(&mut dst, src, LEVEL),
+            }
+        })
+        .map_err(|code| encode_error(zstd_safe::get_error_name(code)))?;
+        Ok(dst.into_boxed_slice())
+    }
+
+    fn decompress(frame: &[u8], dict: Option<&Dictionary>) -> Option<Vec<u8>> {
+        let mut dst = Vec::with_capacity(content_size(frame)?);
+        DCTX.with(|dctx| {
+            let mut dctx = dctx.borrow_mut();
+            match dict {
+                Some(dict) => dctx.decompress_using_ddict(&mut dst, frame, &dict.ddict),
+                None => dctx.decompress(&mut dst, frame),
+            }
+        })
+        .ok()?;
+        Some(dst)
+    }
+
+    fn content_size(frame: &[u8]) -> Option<usize> {
+        zstd_safe::get_frame_content_size(frame)
+            .ok()
+            .flatten()
+            .map(|n| n as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_plain_roundtrip() {
+        let mut store = MetadataStore::new(MetadataCompression::None).unwrap();
+        store.insert(InternalId::from(0), json!({"a": 1})).unwrap();
+        assert_eq!(store.get(InternalId::from(0)), Some(json!({"a": 1})));
+        assert_eq!(store.compression_ratio(), None);
+        assert_eq!(store.remove(InternalId::from(0)), Some(json!({"a": 1})));
+        assert_eq!(store.get(InternalId::from(0)), None);
+    }
+
+    #[test]
+    fn test_updated_metadata() {
+        let current = Some(json!({"a": 1, "b": {"c": 2, "d": 3}}));
+        assert_eq!(
+            updated_metadata(
+                current.clone(),
+                json!({"b": {"c": null, "e": 4}, "f": [1]}),
+                true
+            ),
+            Some(json!({"a": 1, "b": {"d": 3, "e": 4}, "f": [1]}))
+        );
+        assert_eq!(
+            updated_metadata(current.clone(), json!({"x": 1}), false),
+            Some(json!({"x": 1}))
+        );
+        assert_eq!(updated_metadata(current.clone(), json!(null), false), None);
+        assert_eq!(updated_metadata(current, json!(null), true), None);
+        assert_eq!(
+            updated_metadata(None, json!({"a": null, "b": 1}), true),
+            Some(json!({"b": 1}))
+        );
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_zstd_roundtrip_across_training() {
+        let mut store = MetadataStore::new(MetadataCompression::Zstd).unwrap();
+        let categories = ["news", "sports", "science"];
+        let payload = |i: usize| {
+            json!({
+                "category": categories[i % 3],
+                "title": format!("Article number {i} about a recurring topic"),
+                "tags": ["alpha", "beta"],
+                "views": i * 17,
+            })
+        };
+
+        for i in 0..zstd_store::TRAIN_SAMPLES * 2 {
+            store.insert(InternalId::from(i), payload(i)).unwrap();
+        }
+        for i in [
+            0,
+            1,
+            zstd_store::TRAIN_SAMPLES - 1,
+            zstd_store::TRAIN_SAMPLES * 2 - 1,
+        ] {
+            assert_eq!(store.get(InternalId::from(i)), Some(payload(i)));
+        }
+
+        let ratio = store.compression_ratio().unwrap();
+        assert!(ratio > 1.5, "compression ratio too low: {ratio}");
+
+        assert_eq!(store.remove(InternalId::from(5)), Some(payload(5)));
+        assert_eq!(store.get(InternalId::from(5)), None);
+    }
+
+    #[test]
+    fn test_largest_payloads() {
+        let mut compressions = vec![MetadataCompression::None];
+        if cfg!(feature = "compression") {
+            compressions.push(MetadataCompression::Zstd);
+        }
+        for compression in compressions {
+            let mut store = MetadataStore::new(compression).unwrap();
+            for i in 0..20 {
+                let payload = json!({ "text": "x".repeat(i * 10) });
+                store.insert(InternalId::from(i), payload).unwrap();
+            }
+            store.remove(InternalId::from(19));
+            store
+                .insert(InternalId::from(0), json!({ "text": "y".repeat(500) }))
+                .unwrap();
+
+            let largest = store.largest(3);
+            let ids: Vec<InternalId> = largest.iter().map(|&(id, _)| id).collect();
+            assert_eq!(
+                ids,
+                vec![0, 18, 17]
+                    .into_iter()
+                    .map(InternalId::from)
+                    .collect::<Vec<_>>()
+            );
+            assert_eq!(largest[1].1, json_len(&json!({ "text": "x".repeat(180) })));
+        }
+    }
+
+    #[cfg(not(feature = "compression"))]
+    #[test]
+    fn test_zstd_requires_feature() {
+        assert!(MetadataStore::new(MetadataCompression::Zstd).is_err());
+    }
+}
@@ -0,0 +1,346 @@
+//! Named vector spaces: extra vectors per record, each with its own graph
+//!
+//! A collection can declare named spaces (e.g. `title` and `body`) next to
+//! its primary vectors, each with its own dimensions and distance metric. A
+//! record carries at most one vector per space, attached after the record is
+//! written, and a search picks the space it queries. Overwriting or deleting
+//! the record drops its named vectors.
+//!
+//! Each space has an HNSW graph with dense node IDs, mapped to the
+//! collection's internal IDs like partition graphs. Replacing a record's
+//! vector in a space retires its old node, which stays in the graph but is
+//! never returned. The graphs are kept in memory only and rebuilt from the
+//! stored vectors when a persistent collection is opened.
+
+use crate::distance::DistanceMetric;
+use crate::error::{Error, Result};
+use crate::filter::Filter;
+use crate::hnsw::{HnswConfig, HnswIndex};
+use crate::storage::VectorStorageTrait;
+use crate::types::{InternalId, SearchUsage};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+/// A named vector space of a collection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NamedVectorConfig {
+    pub name: String,
+    pub dimensions: usize,
+    #[serde(default)]
+    pub distance_metric: DistanceMetric,
+}
+
+struct Space {
+    config: NamedVectorConfig,
+    index: HnswIndex,
+    /// Node ID -> vector, flattened
+    vectors: Vec<f32>,
+    /// Node ID -> collection internal ID
+    members: Vec<InternalId>,
+    /// Collection internal ID -> its live node
+    nodes: HashMap<InternalId, InternalId>,
+}
+
+impl Space {
+    fn vector(&self, node: InternalId) -> Option<&[f32]> {
+        let start = node.as_usize() * self.config.dimensions;
+        self.vectors.get(start..start + self.config.dimensions)
+    }
+
+    /// Retire the live node of `internal_id`, returning its vector
+    fn retire(&mut self, internal_id: InternalId) -> Option<Vec<f32>> {
+        let node = self.nodes.remove(&internal_id)?;
+        self.vector(node).map(<[f32]>::to_vec)
+    }
+}
+
+/// Collection storage seen through a space's node IDs
+struct SpaceView<'a, S> {
+    inner: &'a S,
+    space: &'a Space,
+}
+
+impl<S: VectorStorageTrait> SpaceView<'_, S> {
+    fn resolve(&self, node: InternalId) -> Option<InternalId> {
+        self.space.members.get(node.as_usize()).copied()
+    }
+}
+
+impl<S: VectorStorageTrait> VectorStorageTrait for SpaceView<'_, S> {
+    fn get_vector_data(&self, node: InternalId) -> Option<Vec<f32>> {
+        self.space.vector(node).map(<[f32]>::to_vec)
+    }
+
+    fn distance(&self, node: InternalId, query: &[f32], metric: DistanceMetric) -> Option<f32> {
+        self.space.vector(node).map(|v| metric.distance(query, v))
+    }
+
+    fn get_metadata(&self, node: InternalId) -> Option<Value> {
+        self.inner.get_metadata(self.resolve(node)?)
+    }
+
+    fn is_deleted(&self, node: InternalId) -> bool {
+        self.resolve(node).is_none_or(|internal_id| {
+            self.space.nodes.get(&internal_id) != Some(&node) || self.inner.is_deleted(internal_id)
+        })
+    }
+}
+
+/// The named vector spaces of a collection
+#[derive(Default)]
+pub struct NamedVectors {
+    spaces: Vec<Space>,
+}
+
+impl NamedVectors {
+    /// Check that the spaces have unique names and at least one dimension
+    pub fn validate(configs: &[NamedVectorConfig]) -> Result<()> {
+        let mut names = HashSet::new();
+        for config in configs {
+            if config.name.is_empty() || !names.insert(config.name.as_str()) {
+                return Err(Error::InvalidConfig(format!(
+                    "Named vector spaces need unique, non-empty names, got '{}'",
+                    config.name
+                )));
+            }
+            if config.dimensions == 0 {
+                return Err(Error::InvalidConfig(format!(
+                    "Named vector space '{}' needs at least one dimension",
+                    config.name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Empty spaces for `configs`, with graphs built like the collection's
+    pub fn new(configs: &[NamedVectorConfig], hnsw: &HnswConfig) -> Result<Self> {
+        Self::validate(configs)?;
+        let spaces = configs
+            .iter()
+            .map(|config| Space {
+                config: config.clone(),
+                index: HnswIndex::new(hnsw.clone(), config.distance_metric),
+                vectors: Vec::new(),
+                members: Vec::new(),
+                nodes: HashMap::new(),
+            })
+            .collect();
+        Ok(Self { spaces })
+    }
+
+    pub fn configs(&self) -> Vec<NamedVectorConfig> {
+        self.spaces.iter().map(|s| s.config.clone()).collect()
+    }
+
+    fn space(&self, name: &str) -> Result<&Space> {
+        self.spaces
+            .iter()
+            .find(|s| s.config.name == name)
+            .ok_or_else(|| Error::InvalidConfig(format!("Unknown named vector space: {}", name)))
+    }
+
+    /// Check that `vector` fits the space called `name`
+    pub fn check(&self, name: &str, vector: &[f32]) -> Result<()> {
+        let space = self.space(name)?;
+        if vector.len() != space.config.dimensions {
+            return Err(Error::DimensionMismatch {
+                expected: space.config.dimensions,
+                got: vector.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Set the vector of record `internal_id` in the space called `name`,
+    /// replacing any previous one
+    pub fn set(
+        &mut self,
+        internal_id: InternalId,
+        name: &str,
+        vector: &[f32],
+        storage: &impl VectorStorageTrait,
+    ) -> Result<()> {
+        self.check(name, vector)?;
+        let Some(space) = self.spaces.iter_mut().find(|s| s.config.name == name) else {
+            return Ok(());
+        };
+        space.retire(internal_id);
+        let node = InternalId::from(space.members.len());
+        space.members.push(internal_id);
+        space.vectors.extend_from_slice(vector);
+        space.nodes.insert(internal_id, node);
+
+        let space = &*space;
+        space.index.insert(
+            node,
+            vector,
+            &SpaceView {
+                inner: storage,
+                space,
+            },
+        )
+    }
+
+    /// Vector of record `internal_id` in the space called `name`
+    pub fn get(&self, internal_id: InternalId, name: &str) -> Option<Vec<f32>> {
+        let space = self.space(name).ok()?;
+        let node = space.nodes.get(&internal_id)?;
+        space.vector(*node).map(<[f32]>::to_vec)
+    }
+
+    /// Every named vector of record `internal_id`, by space
+    pub fn of(&self, internal_id: InternalId) -> Vec<(String, Vec<f32>)> {
+        self.spaces
+            .iter()
+            .filter_map(|space| {
+                let node = space.nodes.get(&internal_id)?;
+                Some((space.config.name.clone(), space.vector(*node)?.to_vec()))
+            })
+            .collect()
+    }
+
+    /// Drop the named vectors of record `internal_id`, returning them
+    pub fn remove(&mut self, internal_id: InternalId) -> Vec<(String, Vec<f32>)> {
+        self.spaces
+            .iter_mut()
+            .filter_map(|space| Some((space.config.name.clone(), space.retire(internal_id)?)))
+            .collect()
+    }
+
+    /// Every live named vector, as (record, space, vector)
+    pub fn iter(&self) -> impl Iterator<Item = (InternalId, &str, &[f32])> {
+        self.spaces.iter().flat_map(|space| {
+            space.nodes.iter().filter_map(move |(internal_id, node)| {
+                Some((
+                    *internal_id,
+                    space.config.name.as_str(),
+                    space.vector(*node)?,
+                ))
+            })
+        })
+    }
+
+    /// Nearest records to `query` in the space called `name`
+    ///
+    /// Returns collection internal IDs; `storage` supplies the metadata for
+    /// `filter` and which records are deleted.
+    #[allow(clippy::too_many_arguments)]
+    pub fn search(
+        &self,
+        name: &str,
+        query: &[f32],
+        k: usize,
+        ef_search: Option<usize>,
+        storage: &impl VectorStorageTrait,
+        filter: Option<&Filter>,
+        usage: &mut SearchUsage,
+    ) -> Result<Vec<(InternalId, f32)>> {
+        self.check(name, query)?;
+        let space = self.space(name)?;
+        if space.members.is_empty() {
+            return Ok(Vec::new());
+        }
+        let view = SpaceView {
+            inner: storage,
+            space,
+        };
+        let hits = space
+            .index
+            .search_with_ef(query, k, ef_search, &view, filter, usage)?;
+        Ok(hits
+            .into_iter()
+            .filter_map(|(node, distance)| Some((view.resolve(node)?, distance)))
+            .collect())
+    }
+
+    /// Approximate bytes of the stored vectors
+    pub fn vector_bytes(&self) -> usize {
+        self.spaces
+            .iter()
+            .map(|s| {
+                s.vectors.capacity() * std::mem::size_of::<f32>()
+                    + s.members.capacity() * std::mem::size_of::<InternalId>()
+                    + s.nodes.capacity() * std::mem::size_of::<(InternalId, InternalId)>()
+            })
+            .sum()
+    }
+
+    /// Approximate bytes of the graphs
+    pub fn graph_bytes(&self) -> usize {
+        self.spaces.iter().map(|s| s.index.memory_usage()).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::VectorStorage;
+    use crate::types::VectorId;
+    use serde_json::json;
+
+    #[test]
+    fn test_named_search_and_replace() {
+        let storage = VectorStorage::new(1);
+        let mut named = NamedVectors::new(
+            &[NamedVectorConfig {
+                name: "title".to_string(),
+                dimensions: 2,
+                distance_metric: DistanceMetric::Euclidean,
+            }],
+            &HnswConfig::default(),
+        )
+        .unwrap();
+
+        let mut ids = Vec::new();
+        for i in 0..20 {
+            let internal_id = storage
+                .insert(
+                    VectorId::from(format!("v{i}")),
+                    &[0.0],
+                    Some(json!({ "even": i % 2 == 0 })),
+                )
+                .unwrap();
+            named
+                .set(internal_id, "title", &[i as f32, 0.0], &storage)
+                .unwrap();
+            ids.push(internal_id);
+        }
+        assert!(named.set(ids[0], "body", &[0.0, 0.0], &storage).is_err());
+        assert!(named.set(ids[0], "title", &[0.0], &storage).is_err());
+
+        let mut usage = SearchUsage::default();
+        let hits = named
+            .search("title", &[5.2, 0.0], 1, None, &storage, None, &mut usage)
+            .unwrap();
+        assert_eq!(hits[0].0, ids[5]);
+
+        // The old node of a replaced vector is never returned
+        named.set(ids[5], "title", &[100.0, 0.0], &storage).unwrap();
+        let hits = named
+            .search("title", &[5.2, 0.0], 3, None, &storage, None, &mut usage)
+            .unwrap();
+        assert!(hits.iter().all(|(id, _)| *id != ids[5]));
+        assert_eq!(named.get(ids[5], "title"), Some(vec![100.0, 0.0]));
+
+        let odd = Filter::Exact("even".to_string(), json!(false));
+        let hits = named
+            .search(
+                "title",
+                &[4.2, 0.0],
+                2,
+                None,
+                &storage,
+                Some(&odd),
+                &mut usage,
+            )
+            .unwrap();
+        let found: Vec<_> = hits.iter().map(|h| h.0).collect();
+        assert_eq!(found, vec![ids[3], ids[7]]);
+
+        assert_eq!(named.remove(ids[3]).len(), 1);
+        assert!(named.of(ids[3]).is_empty());
+        assert_eq!(named.iter().count(), 19);
+    }
+}
@@ -0,0 +1,117 @@
+//! Rules for collection and alias names
+//!
+//! Names double as directory names of on-disk collections and appear in URL
+//! paths, so they are kept to a portable charset: ASCII letters, digits,
+//! `_`, `-` and `.`, starting with a letter, digit or `_`, and at most
+//! [`MAX_NAME_LEN`] bytes. Names starting with one of [`RESERVED_PREFIXES`],
+//! in any case, belong to collections the database keeps for itself.
+//!
+//! System collections are created with
+//! [`Database::system_collection`](crate::Database::system_collection).
+//!
+//! Collections and aliases share one namespace. With [`NameCase::Lowercase`]
+//! a [`Database`](crate::Database) folds every name it is given to lower
+//! case, so `Docs` and `docs` are the same collection.
+
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+/// Longest collection or alias name, in bytes
+pub const MAX_NAME_LEN: usize = 64;
+
+/// Prefixes of system collections: audit log, feedback and usage records
+pub const RESERVED_PREFIXES: &[&str] = &["_audit", "_feedback", "_usage"];
+
+/// How a database treats the case of names
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NameCase {
+    /// Names are kept as given; `Docs` and `docs` are different collections
+    #[default]
+    Preserve,
+    /// Names are folded to lower case wherever they are given
+    Lowercase,
+}
+
+impl NameCase {
+    /// `name` as this policy stores and looks it up
+    pub fn canonicalize<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        match self {
+            NameCase::Lowercase if name.bytes().any(|b| b.is_ascii_uppercase()) => {
+                Cow::Owned(name.to_ascii_lowercase())
+            }
+            _ => Cow::Borrowed(name),
+        }
+    }
+}
+
+/// Whether `name` belongs to the system collections' namespace
+pub fn is_reserved(name: &str) -> bool {
+    let lower = name.to_ascii_lowercase();
+    RESERVED_PREFIXES.iter().any(|p| lower.starts_with(p))
+}
+
+/// Check `name` against the naming rules, for a new collection or alias
+pub fn validate_name(name: &str) -> Result<()> {
+    let invalid = |reason: &str| {
+        Err(Error::InvalidCollectionName {
+            name: name.to_string(),
+            reason: reason.to_string(),
+        })
+    };
+    if name.is_empty() {
+        return invalid("must not be empty");
+    }
+    if name.len() > MAX_NAME_LEN {
+        return invalid(&format!("must be at most {} bytes", MAX_NAME_LEN));
+    }
+    if !name
+        .bytes()
+        .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'-' | b'.'))
+    {
+        return invalid("may only contain ASCII letters, digits, '_', '-' and '.'");
+    }
+    if !name.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_') {
+        return invalid("must start with a letter, digit or '_'");
+    }
+    let lower = name.to_ascii_lowercase();
+    if let Some(prefix) = RESERVED_PREFIXES.iter().find(|p| lower.starts_with(*p)) {
+        return invalid(&format!("the prefix {} is reserved", prefix));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_name() {
+        for name in ["docs", "Docs_v2", "a", "1st", "_scratch", "tenant-a.b"] {
+            assert!(validate_name(name).is_ok(), "{name}");
+        }
+        let long = "a".repeat(MAX_NAME_LEN + 1);
+        for name in ["", "..", ".hidden", "-x", "a/b", "a b", "é", &long] {
+            assert!(
+                matches!(
+                    validate_name(name),
+                    Err(Error::InvalidCollectionName { .. })
+                ),
+                "{name}"
+            );
+        }
+        assert!(validate_name("_audit").is_err());
+        assert!(validate_name("_Usage_2026").is_err());
+        assert!(validate_name(&"a".repeat(MAX_NAME_LEN)).is_ok());
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        assert_eq!(NameCase::Preserve.canonicalize("Docs"), "Docs");
+        assert_eq!(NameCase::Lowercase.canonicalize("Docs"), "docs");
+        assert!(matches!(
+            NameCase::Lowercase.canonicalize("docs"),
+            Cow::Borrowed(_)
+        ));
+    }
+}
@@ -0,0 +1,162 @@
+//! Hard negative mining for training retrieval models
+//!
+//! Given queries, each paired with the record that answers it (its
+//! positive), mining returns the records that come nearest each query
+//! without being its positive. Such near misses teach an embedding model
+//! more than random negatives do. Unlabeled records that also answer a query
+//! would be false negatives; to leave them out, the nearest `skip` records
+//! can be passed over, and with a `margin` only records scoring at least that
+//! much below the positive are kept.
+
+use crate::distance::DistanceMetric;
+use crate::error::{Error, Result};
+use crate::types::VectorId;
+use serde::{Deserialize, Serialize};
+
+/// Most candidates examined for one query
+///
+/// The search widens until `k` negatives are found or the collection runs
+/// out of matches; with a large `skip` or `margin`, fewer than `k` can come
+/// back.
+pub const MAX_NEGATIVE_CANDIDATES: usize = 10_000;
+
+/// A query and the record that answers it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HardNegativeQuery {
+    pub vector: Vec<f32>,
+    pub positive_id: String,
+}
+
+/// Which records count as hard negatives
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct HardNegatives {
+    /// Negatives per query
+    pub k: usize,
+    /// Nearest non-positive records passed over before negatives are taken
+    #[serde(default)]
+    pub skip: usize,
+    /// Keep only records whose score is at least this much below the
+    /// positive's
+    #[serde(default)]
+    pub margin: Option<f32>,
+}
+
+/// Negatives mined for one query
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MinedNegatives {
+    pub positive_id: String,
+    /// Distance from the query to its positive
+    pub positive_distance: f32,
+    /// Nearest first
+    pub negatives: Vec<(VectorId, f32)>,
+}
+
+impl HardNegatives {
+    pub fn validate(&self) -> Result<()> {
+        if self.k == 0 {
+            return Err(Error::InvalidConfig(
+                "Hard negative mining needs k of at least 1".to_string(),
+            ));
+        }
+        if self.margin.is_some_and(|m| !m.is_finite() || m < 0.0) {
+            return Err(Error::InvalidConfig(
+                "margin must be a non-negative number".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Candidates the first search for one query fetches
+    pub(crate) fn first_search(&self) -> usize {
+        self.k
+            .saturating_add(self.skip)
+            .saturating_add(1)
+            .min(MAX_NEGATIVE_CANDIDATES)
+    }
+
+    /// Negatives among `hits`, nearest first, and whether there are `k`
+    pub(crate) fn select(
+        &self,
+        hits: &[(VectorId, f32)],
+        positive_id: &str,
+        positive_distance: f32,
+        metric: DistanceMetric,
+    ) -> (Vec<(VectorId, f32)>, bool) {
+        let max_score = self
+            .margin
+            .map(|margin| metric.score(positive_distance) - margin);
+        let negatives: Vec<(VectorId, f32)> = hits
+            .iter()
+            .filter(|(id, _)| id.to_string() != positive_id)
+            .skip(self.skip)
+            .filter(|(_, distance)| max_score.is_none_or(|max| metric.score(*distance) <= max))
+            .take(self.k)
+            .cloned()
+            .collect();
+        let full = negatives.len() == self.k;
+        (negatives, full)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hits() -> Vec<(VectorId, f32)> {
+        ["a", "p", "b", "c", "d"]
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (VectorId::from(*id), i as f32 * 0.1))
+            .collect()
+    }
+
+    fn ids(negatives: &[(VectorId, f32)]) -> Vec<String> {
+        negatives.iter().map(|(id, _)| id.to_string()).collect()
+    }
+
+    #[test]
+    fn test_select_skips_positive_and_nearest() {
+        let options = HardNegatives {
+            k: 2,
+            skip: 1,
+            margin: None,
+        };
+        let (negatives, full) = options.select(&hits(), "p", 0.1, DistanceMetric::Cosine);
+        assert_eq!(ids(&negatives), ["b", "c"]);
+        assert!(full);
+
+        let options = HardNegatives { k: 5, ..options };
+        let (negatives, full) = options.select(&hits(), "p", 0.1, DistanceMetric::Cosine);
+        assert_eq!(ids(&negatives), ["b", "c", "d"]);
+        assert!(!full);
+    }
+
+    #[test]
+    fn test_select_margin() {
+        // The positive scores 0.9 under cosine; keep scores of at most 0.75
+        let options = HardNegatives {
+            k: 3,
+            skip: 0,
+            margin: Some(0.15),
+        };
+        let (negatives, _) = options.select(&hits(), "p", 0.1, DistanceMetric::Cosine);
+        assert_eq!(ids(&negatives), ["c", "d"]);
+    }
+
+    #[test]
+    fn test_validate() {
+        let options = HardNegatives {
+            k: 1,
+            skip: 0,
+            margin: None,
+        };
+        assert!(options.validate().is_ok());
+        assert!(HardNegatives { k: 0, ..options }.validate().is_err());
+        assert!(HardNegatives {
+            margin: Some(-0.1),
+            ..options
+        }
+        .validate()
+        .is_err());
+    }
+}
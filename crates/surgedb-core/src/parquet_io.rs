@@ -0,0 +1,313 @@
+//! Parquet import and export of collections
+//!
+//! Files have one row per record with three columns: `id` (string), `vector`
+//! (fixed-size list of float32) and `metadata` (the metadata as JSON text,
+//! null if the record has none). Imports are more lenient, so tables written
+//! by other tools load as they are: IDs may be integers, vectors variable-size
+//! lists of float32 or float64, and the `metadata` column may be missing.
+//! Other columns are ignored.
+//!
+//! Sparse and named vectors are not included; snapshots carry those.
+
+use crate::error::{Error, Result};
+use crate::snapshot::StoredVector;
+use arrow_array::cast::AsArray;
+use arrow_array::types::{Float32Type, Float64Type, Int32Type, Int64Type, UInt32Type, UInt64Type};
+use arrow_array::{
+    Array, ArrayRef, FixedSizeListArray, Float32Array, RecordBatch, RecordBatchReader, StringArray,
+};
+use arrow_schema::{DataType, Field, Schema};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use serde_json::Value;
+use std::fs::{self, File};
+use std::path::Path;
+use std::sync::Arc;
+
+pub const ID_COLUMN: &str = "id";
+pub const VECTOR_COLUMN: &str = "vector";
+pub const METADATA_COLUMN: &str = "metadata";
+
+/// Records per row group of exported files
+const ROW_GROUP_SIZE: usize = 8192;
+
+/// A record read from a file, as taken by `Collection::upsert_batch`
+pub type Record = (String, Vec<f32>, Option<Value>);
+
+fn invalid(message: impl Into<String>) -> Error {
+    Error::Serialization {
+        message: message.into(),
+    }
+}
+
+/// Write `vectors` of `dimensions` each to a Parquet file at `path`
+pub fn write(path: impl AsRef<Path>, dimensions: usize, vectors: &[StoredVector]) -> Result<()> {
+    let path = path.as_ref();
+    let item = Arc::new(Field::new("item", DataType::Float32, false));
+    let schema = Arc::new(Schema::new(vec![
+        Field::new(ID_COLUMN, DataType::Utf8, false),
+        Field::new(
+            VECTOR_COLUMN,
+            DataType::FixedSizeList(item.clone(), dimensions as i32),
+            false,
+        ),
+        Field::new(METADATA_COLUMN, DataType::Utf8, true),
+    ]));
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .set_max_row_group_size(ROW_GROUP_SIZE)
+        .build();
+
+    // Write next to the target and rename, so a failed write leaves no partial file
+    let tmp_path = path.with_extension("tmp");
+    let mut writer =
+        ArrowWriter::try_new(File::create(&tmp_path)?, schema.clone(), Some(properties))?;
+    for chunk in vectors.chunks(ROW_GROUP_SIZE) {
+        let ids: Vec<String> = chunk.iter().map(|v| v.id.to_string()).collect();
+        let values: Vec<f32> = chunk
+            .iter()
+            .flat_map(|v| v.vector.iter().copied())
+            .collect();
+        let metadata = chunk
+            .iter()
+            .map(|v| v.metadata.as_ref().map(serde_json::to_string).transpose())
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(StringArray::from(ids)),
+            Arc::new(FixedSizeListArray::try_new(
+                item.clone(),
+                dimensions as i32,
+                Arc::new(Float32Array::from(values)),
+                None,
+            )?),
+            Arc::new(StringArray::from(metadata)),
+        ];
+        writer.write(&RecordBatch::try_new(schema.clone(), columns)?)?;
+    }
+    writer.into_inner()?.sync_all()?;
+    fs::rename(tmp_path, path)?;
+    Ok(())
+}
+
+/// Records of the Parquet file at `path`, in batches of up to `batch_size`
+pub fn read(
+    path: impl AsRef<Path>,
+    batch_size: usize,
+) -> Result<impl Iterator<Item = Result<Vec<Record>>>> {
+    let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)?
+        .with_batch_size(batch_size.max(1))
+        .build()?;
+    let schema = reader.schema();
+    for column in [ID_COLUMN, VECTOR_COLUMN] {
+        if schema.column_with_name(column).is_none() {
+            return Err(invalid(format!("Parquet file has no '{}' column", column)));
+        }
+    }
+    Ok(reader.map(|batch| records(&batch?)))
+}
+
+fn records(batch: &RecordBatch) -> Result<Vec<Record>> {
+    let column = |name| {
+        batch
+            .column_by_name(name)
+            .filter(|c| c.data_type() != &DataType::Null)
+    };
+    let ids = ids(column(ID_COLUMN).ok_or_else(|| invalid("Missing 'id' column"))?)?;
+    let vectors =
+        vectors(column(VECTOR_COLUMN).ok_or_else(|| invalid("Missing 'vector' column"))?)?;
+    let metadata = match column(METADATA_COLUMN) {
+        Some(column) => metadata(column)?,
+        None => vec![None; batch.num_rows()],
+    };
+    Ok(ids
+        .into_iter()
+        .zip(vectors)
+        .zip(metadata)
+        .map(|((id, vector), metadata)| (id, vector, metadata))
+        .collect())
+}
+
+fn ids(column: &ArrayRef) -> Result<Vec<String>> {
+    if column.null_count() > 0 {
+        return Err(invalid("Parquet 'id' column has null values"));
+    }
+    let ids = match column.data_type() {
+        DataType::Utf8 => column
+            .as_string::<i32>()
+            .iter()
+            .flatten()
+            .map(String::from)
+            .collect(),
+        DataType::LargeUtf8 => column
+            .as_string::<i64>()
+            .iter()
+            .flatten()
+            .map(String::from)
+            .collect(),
+        DataType::Int32 => column
+            .as_primitive::<Int32Type>()
+            .values()
+            .iter()
+            .map(i32::to_string)
+            .collect(),
+        DataType::Int64 => column
+            .as_primitive::<Int64Type>()
+            .values()
+            .iter()
+            .map(i64::to_string)
+            .collect(),
+        DataType::UInt32 => column
+            .as_primitive::<UInt32Type>()
+            .values()
+            .iter()
+            .map(u32::to_string)
+            .collect(),
+        DataType::UInt64 => column
+            .as_primitive::<UInt64Type>()
+            .values()
+            .iter()
+            .map(u64::to_string)
+            .collect(),
+        other => {
+            return Err(invalid(format!(
+                "Parquet 'id' column must hold strings or integers, got {}",
+                other
+            )))
+        }
+    };
+    Ok(ids)
+}
+
+fn vectors(column: &ArrayRef) -> Result<Vec<Vec<f32>>> {
+    if column.null_count() > 0 {
+        return Err(invalid("Parquet 'vector' column has null values"));
+    }
+    let rows: Vec<ArrayRef> = match column.data_type() {
+        DataType::FixedSizeList(..) => column.as_fixed_size_list().iter().flatten().collect(),
+        DataType::List(_) => column.as_list::<i32>().iter().flatten().collect(),
+        DataType::LargeList(_) => column.as_list::<i64>().iter().flatten().collect(),
+        other => {
+            return Err(invalid(format!(
+                "Parquet 'vector' column must hold lists of floats, got {}",
+                other
+            )))
+        }
+    };
+    rows.iter()
+        .map(|values| {
+            if values.null_count() > 0 {
+                return Err(invalid("Parquet vectors can't contain null values"));
+            }
+            match values.data_type() {
+                DataType::Float32 => Ok(values.as_primitive::<Float32Type>().values().to_vec()),
+                DataType::Float64 => Ok(values
+                    .as_primitive::<Float64Type>()
+                    .values()
+                    .iter()
+                    .map(|&v| v as f32)
+                    .collect()),
+                other => Err(invalid(format!(
+                    "Parquet vectors must hold float32 or float64 values, got {}",
+                    other
+                ))),
+            }
+        })
+        .collect()
+}
+
+fn metadata(column: &ArrayRef) -> Result<Vec<Option<Value>>> {
+    let parse = |text: Option<&str>| {
+        text.map(serde_json::from_str)
+            .transpose()
+            .map_err(Error::from)
+    };
+    match column.data_type() {
+        DataType::Utf8 => column.as_string::<i32>().iter().map(parse).collect(),
+        DataType::LargeUtf8 => column.as_string::<i64>().iter().map(parse).collect(),
+        other => Err(invalid(format!(
+            "Parquet 'metadata' column must hold JSON text, got {}",
+            other
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::VectorId;
+    use arrow_array::types::Float64Type as F64;
+    use arrow_array::{Float64Array, Int64Array, ListArray};
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_round_trip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("c.parquet");
+        let vectors: Vec<StoredVector> = (0..20_000)
+            .map(|i| StoredVector {
+                id: VectorId::from(format!("v{i}")),
+                vector: vec![i as f32, -(i as f32)],
+                metadata: (i % 2 == 0).then(|| json!({ "n": i })),
+            })
+            .collect();
+        write(&path, 2, &vectors).unwrap();
+
+        let rows: Vec<Record> = read(&path, 4096)
+            .unwrap()
+            .flat_map(Result::unwrap)
+            .collect();
+        assert_eq!(rows.len(), 20_000);
+        assert_eq!(rows[7], ("v7".to_string(), vec![7.0, -7.0], None));
+        assert_eq!(
+            rows[8],
+            ("v8".to_string(), vec![8.0, -8.0], Some(json!({ "n": 8 })))
+        );
+    }
+
+    #[test]
+    fn test_reads_tables_from_other_tools() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("lake.parquet");
+        let vectors = ListArray::from_iter_primitive::<F64, _, _>(vec![
+            Some(vec![Some(1.0), Some(2.0)]),
+            Some(vec![Some(3.0), Some(4.0)]),
+        ]);
+        let batch = RecordBatch::try_from_iter(vec![
+            ("id", Arc::new(Int64Array::from(vec![10, 11])) as ArrayRef),
+            ("vector", Arc::new(vectors) as ArrayRef),
+            (
+                "score",
+                Arc::new(Float64Array::from(vec![0.5, 0.7])) as ArrayRef,
+            ),
+        ])
+        .unwrap();
+        let mut writer =
+            ArrowWriter::try_new(File::create(&path).unwrap(), batch.schema(), None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+
+        let rows: Vec<Record> = read(&path, 10).unwrap().flat_map(Result::unwrap).collect();
+        assert_eq!(
+            rows,
+            vec![
+                ("10".to_string(), vec![1.0, 2.0], None),
+                ("11".to_string(), vec![3.0, 4.0], None),
+            ]
+        );
+
+        // Without a vector column nothing is read
+        let batch = RecordBatch::try_from_iter(vec![(
+            "id",
+            Arc::new(Int64Array::from(vec![1])) as ArrayRef,
+        )])
+        .unwrap();
+        let mut writer =
+            ArrowWriter::try_new(File::create(&path).unwrap(), batch.schema(), None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+        assert!(read(&path, 10).is_err());
+    }
+}
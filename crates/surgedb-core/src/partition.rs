@@ -0,0 +1,274 @@
+//! Per-tenant HNSW subgraphs within one collection
+//!
+//! When a collection is configured with a partition field (e.g. `tenant_id`),
+//! every vector carrying that field is also inserted into a small HNSW graph
+//! built only from vectors with the same value. Searches whose filter pins the
+//! field to one value (`Exact`, alone or inside an `And`) run on that graph,
+//! so they never traverse other tenants' nodes and recall doesn't suffer when
+//! one tenant is much smaller than the rest. Other searches use the
+//! collection's main graph, which still holds every vector.
+//!
+//! Partition graphs use their own dense node IDs; [`PartitionView`] maps them
+//! to the collection's internal IDs. They are kept in memory only and rebuilt
+//! from storage when a persistent collection is opened.
+
+use crate::distance::DistanceMetric;
+use crate::error::Result;
+use crate::filter::{get_value_by_path, Filter};
+use crate::hnsw::{HnswConfig, HnswIndex};
+use crate::storage::VectorStorageTrait;
+use crate::sync::RwLock;
+use crate::types::{InternalId, SearchUsage};
+use serde_json::Value;
+use std::collections::HashMap;
+
+struct Partition {
+    index: HnswIndex,
+    /// Partition node ID -> collection internal ID
+    members: Vec<InternalId>,
+}
+
+/// Collection storage seen through a partition's node IDs
+struct PartitionView<'a, S> {
+    inner: &'a S,
+    members: &'a [InternalId],
+}
+
+impl<S: VectorStorageTrait> PartitionView<'_, S> {
+    fn resolve(&self, node: InternalId) -> Option<InternalId> {
+        self.members.get(node.as_usize()).copied()
+    }
+}
+
+impl<S: VectorStorageTrait> VectorStorageTrait for PartitionView<'_, S> {
+    fn get_vector_data(&self, node: InternalId) -> Option<Vec<f32>> {
+        self.inner.get_vector_data(self.resolve(node)?)
+    }
+
+    fn distance(&self, node: InternalId, query: &[f32], metric: DistanceMetric) -> Option<f32> {
+        self.inner.distance(self.resolve(node)?, query, metric)
+    }
+
+    fn get_metadata(&self, node: InternalId) -> Option<Value> {
+        self.inner.get_metadata(self.resolve(node)?)
+    }
+
+    fn is_deleted(&self, node: InternalId) -> bool {
+        self.resolve(node)
+            .is_none_or(|internal_id| self.inner.is_deleted(internal_id))
+    }
+}
+
+/// HNSW graphs partitioned by the value of one metadata field
+pub struct PartitionedIndex {
+    field: String,
+    config: HnswConfig,
+    metric: DistanceMetric,
+    /// Field value (as JSON) -> partition
+    partitions: RwLock<HashMap<String, Partition>>,
+}
+
+impl PartitionedIndex {
+    pub fn new(field: String, config: HnswConfig, metric: DistanceMetric) -> Self {
+        Self {
+            field,
+            config,
+            metric,
+            partitions: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Metadata field the graphs are partitioned by
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// Number of partitions
+    pub fn len(&self) -> usize {
+        self.partitions.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether changing a record's metadata from `old` to `new` moves it to
+    /// another partition (or into or out of all of them)
+    pub fn moves(&self, old: Option<&Value>, new: Option<&Value>) -> bool {
+        let value = |metadata: Option<&Value>| {
+            metadata.and_then(|m| get_value_by_path(m, &self.field).map(Value::to_string))
+        };
+        value(old) != value(new)
+    }
+
+    /// Add a stored vector to the partition of its field value, if it has one
+    pub fn insert(
+        &self,
+        internal_id: InternalId,
+        vector: &[f32],
+        storage: &impl VectorStorageTrait,
+    ) -> Result<()> {
+        let Some(metadata) = storage.get_metadata(internal_id) else {
+            return Ok(());
+        };
+        let Some(value) = get_value_by_path(&metadata, &self.field) else {
+            return Ok(());
+        };
+
+        let mut partitions = self.partitions.write();
+        let partition = partitions
+            .entry(value.to_string())
+            .or_insert_with(|| Partition {
+                index: HnswIndex::new(self.config.clone(), self.metric),
+                members: Vec::new(),
+            });
+        let node = InternalId::from(partition.members.len());
+        partition.members.push(internal_id);
+        let view = PartitionView {
+            inner: storage,
+            members: &partition.members,
+        };
+        partition.index.insert(node, vector, &view)
+    }
+
+    /// Search the partition pinned by `filter`
+    ///
+    /// Returns `None` when `filter` doesn't pin the partition field to a
+    /// single value, in which case the main graph has to be searched.
+    /// `ef_search` overrides the configured candidate list size.
+    pub fn search_with_usage(
+        &self,
+        query: &[f32],
+        k: usize,
+        ef_search: Option<usize>,
+        storage: &impl VectorStorageTrait,
+        filter: Option<&Filter>,
+        usage: &mut SearchUsage,
+    ) -> Option<Result<Vec<(InternalId, f32)>>> {
+        let (key, residual) = self.route(filter?)?;
+        let partitions = self.partitions.read();
+        let Some(partition) = partitions.get(&key) else {
+            return Some(Ok(Vec::new()));
+        };
+
+        let view = PartitionView {
+            inner: storage,
+            members: &partition.members,
+        };
+        let results = partition
+            .index
+            .search_with_ef(query, k, ef_search, &view, residual.as_ref(), usage)
+            .map(|hits| {
+                hits.into_iter()
+                    .filter_map(|(node, distance)| Some((view.resolve(node)?, distance)))
+                    .collect()
+            });
+        Some(results)
+    }
+
+    /// Partition key pinned by `filter`, plus whatever is left of the filter
+    fn route(&self, filter: &Filter) -> Option<(String, Option<Filter>)> {
+        let pins = |f: &Filter| matches!(f, Filter::Exact(field, _) if *field == self.field);
+        match filter {
+            Filter::Exact(_, value) if pins(filter) => Some((value.to_string(), None)),
+            Filter::And(filters) => {
+                let pos = filters.iter().position(pins)?;
+                let Filter::Exact(_, value) = &filters[pos] else {
+                    return None;
+                };
+                let mut rest: Vec<Filter> = filters
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| *i != pos)
+                    .map(|(_, f)| f.clone())
+                    .collect();
+                let residual = match rest.len() {
+                    0 => None,
+                    1 => rest.pop(),
+                    _ => Some(Filter::And(rest)),
+                };
+                Some((value.to_string(), residual))
+            }
+            _ => None,
+        }
+    }
+
+    /// Approximate bytes used by the partition graphs
+    pub fn memory_usage(&self) -> usize {
+        self.partitions
+            .read()
+            .iter()
+            .map(|(key, partition)| {
+                key.capacity()
+                    + partition.index.memory_usage()
+                    + partition.members.capacity() * std::mem::size_of::<InternalId>()
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::VectorStorage;
+    use crate::types::VectorId;
+    use serde_json::json;
+
+    #[test]
+    fn test_route() {
+        let index = PartitionedIndex::new(
+            "tenant".to_string(),
+            HnswConfig::default(),
+            DistanceMetric::Euclidean,
+        );
+        let pin = Filter::Exact("tenant".to_string(), json!("a"));
+        let other = Filter::Exact("kind".to_string(), json!("x"));
+
+        assert_eq!(index.route(&pin).unwrap().0, "\"a\"");
+        let (key, residual) = index
+            .route(&Filter::And(vec![other.clone(), pin.clone()]))
+            .unwrap();
+        assert_eq!(key, "\"a\"");
+        assert!(matches!(residual, Some(Filter::Exact(field, _)) if field == "kind"));
+        assert!(index.route(&other).is_none());
+        assert!(index.route(&Filter::Or(vec![pin])).is_none());
+    }
+
+    #[test]
+    fn test_partition_search_stays_in_tenant() {
+        let storage = VectorStorage::new(2);
+        let index = PartitionedIndex::new(
+            "tenant".to_string(),
+            HnswConfig::default(),
+            DistanceMetric::Euclidean,
+        );
+        for i in 0..40 {
+            let tenant = if i % 4 == 0 { "a" } else { "b" };
+            let vector = [i as f32, 0.0];
+            let internal_id = storage
+                .insert(
+                    VectorId::from(format!("v{i}")),
+                    &vector,
+                    Some(json!({ "tenant": tenant })),
+                )
+                .unwrap();
+            index.insert(internal_id, &vector, &storage).unwrap();
+        }
+        assert_eq!(index.len(), 2);
+
+        let filter = Filter::Exact("tenant".to_string(), json!("a"));
+        let mut usage = SearchUsage::default();
+        let results = index
+            .search_with_usage(&[5.0, 0.0], 3, None, &storage, Some(&filter), &mut usage)
+            .unwrap()
+            .unwrap();
+        let ids: Vec<usize> = results.iter().map(|(id, _)| id.as_usize()).collect();
+        assert_eq!(ids, vec![4, 8, 0]);
+        // Only tenant "a" nodes (10 of them) are visited, some on several layers
+        assert!(
+            usage.vectors_scanned < 30,
+            "scanned {}",
+            usage.vectors_scanned
+        );
+    }
+}
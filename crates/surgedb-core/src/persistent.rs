@@ -2,18 +2,41 @@
 //!
 //! Provides ACID-compliant persistence with crash recovery.
 
+use crate::ann::{AnnIndex, IndexKind};
 use crate::distance::DistanceMetric;
 use crate::error::{Error, Result};
+use crate::filter::Filter;
+use crate::filter_cache::CachedFilterInfo;
+use crate::graph_export::{GraphExport, GraphStats};
 use crate::hnsw::{HnswConfig, HnswIndex};
+use crate::metadata_store::updated_metadata;
+use crate::named::{NamedVectorConfig, NamedVectors};
+use crate::partition::PartitionedIndex;
+use crate::quantization::{BinaryQuantizer, QuantizationType};
+use crate::recovery::{IntegrityReport, RecoveryMode};
+use crate::scan::Scan;
 use crate::snapshot::{Snapshot, SnapshotManager};
+use crate::sparse::{Fusion, HybridHit, SparseStore, SparseVector};
 use crate::storage::{VectorStorage, VectorStorageTrait};
-use crate::types::VectorId;
-use crate::wal::{Wal, WalEntry};
+use crate::sync::{RwLock, RwLockReadGuard};
+use crate::types::{
+    CompactionReport, GarbageStats, GraphRepair, GroupCommit, IdType, InternalId, ListCursor,
+    ListPage, MemoryBreakdown, MetadataCompression, MetadataLimits, PayloadSize, SearchHit,
+    SearchParams, SearchUsage, VectorId,
+};
+use crate::wal::{LogPosition, PendingSync, Wal, WalEntry, WalRepair};
 use serde_json::Value;
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 use tracing::{debug, info};
 
+/// Candidates re-scored per requested result when a search doesn't say
+const DEFAULT_OVERSAMPLING: f32 = 3.0;
+
+/// File in the data directory holding the ID of the database's write log
+const LOG_ID_FILE: &str = "log_id";
+
 /// Configuration for persistent database
 #[derive(Debug, Clone)]
 pub struct PersistentConfig {
@@ -25,10 +48,31 @@ pub struct PersistentConfig {
     pub hnsw: HnswConfig,
     /// Sync WAL after every write (safer but slower)
     pub sync_writes: bool,
+    /// Share WAL syncs between writes instead; takes precedence over `sync_writes`
+    pub group_commit: Option<GroupCommit>,
     /// Auto-checkpoint when WAL exceeds this size (bytes)
     pub checkpoint_threshold: u64,
     /// Number of snapshots to retain
     pub snapshot_retain_count: usize,
+    /// Key type of external IDs
+    pub id_type: IdType,
+    /// How metadata payloads are stored
+    pub metadata_compression: MetadataCompression,
+    /// Metadata field whose values get their own HNSW subgraph
+    pub partition_field: Option<String>,
+    /// Metadata fields whose text is indexed for keyword search
+    pub text_fields: Vec<String>,
+    /// `Binary` traverses the graphs on 1-bit sign codes and rescores the
+    /// candidates with the stored vectors; other types keep full precision
+    pub quantization: QuantizationType,
+    /// Extra vector spaces each record can carry a vector in
+    pub named_vectors: Vec<NamedVectorConfig>,
+    /// Index the primary vectors are searched with
+    pub index: IndexKind,
+    /// Size and nesting limits metadata documents must meet to be written
+    pub metadata_limits: MetadataLimits,
+    /// What to do with damaged WAL records found on open
+    pub recovery_mode: RecoveryMode,
 }
 
 impl Default for PersistentConfig {
@@ -38,136 +82,633 @@ impl Default for PersistentConfig {
             distance_metric: DistanceMetric::Cosine,
             hnsw: HnswConfig::default(),
             sync_writes: false,
+            group_commit: None,
             checkpoint_threshold: 64 * 1024 * 1024, // 64MB
             snapshot_retain_count: 3,
+            id_type: IdType::String,
+            metadata_compression: MetadataCompression::None,
+            partition_field: None,
+            text_fields: Vec::new(),
+            quantization: QuantizationType::None,
+            named_vectors: Vec::new(),
+            index: IndexKind::Hnsw,
+            metadata_limits: MetadataLimits::default(),
+            recovery_mode: RecoveryMode::default(),
+        }
+    }
+}
+
+/// WAL entries logged after the last snapshot, not yet applied
+pub struct PendingReplay {
+    entries: std::vec::IntoIter<WalEntry>,
+}
+
+impl PendingReplay {
+    fn new(entries: Vec<WalEntry>) -> Self {
+        Self {
+            entries: entries.into_iter(),
         }
     }
+
+    /// Entries left to replay
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
+/// In-memory parts of a database, before anything is loaded into them
+type EmptyState = (
+    VectorStorage,
+    Box<dyn AnnIndex>,
+    Option<PartitionedIndex>,
+    Option<SignCodes>,
+);
+
 /// Persistent vector database with ACID guarantees
 pub struct PersistentVectorDb {
     config: PersistentConfig,
     storage: VectorStorage,
-    index: HnswIndex,
+    index: Box<dyn AnnIndex>,
+    partitions: Option<PartitionedIndex>,
+    /// Sign codes the graphs are traversed on, for binary quantization
+    signs: Option<SignCodes>,
+    sparse: SparseStore,
+    named: NamedVectors,
     wal: Wal,
     snapshot_manager: SnapshotManager,
     data_dir: PathBuf,
+    /// WAL sequence applied so far while the tail is being replayed
+    replayed_seq: Option<u64>,
+    /// WAL sequence of the last checkpoint's marker; later entries are
+    /// only in the WAL
+    checkpointed_seq: u64,
+    /// Random ID of the write log, kept for the life of the data directory
+    log_id: u64,
+    /// Damaged records removed from the WAL when it was opened
+    wal_repair: WalRepair,
+    /// Keep syncs of [`SyncMode::Always`](crate::SyncMode::Always) commits in
+    /// `pending_sync` instead of waiting for them
+    defer_syncs: bool,
+    pending_sync: Option<PendingSync>,
 }
 
 impl PersistentVectorDb {
     /// Open or create a persistent database at the given path
     pub fn open(path: impl AsRef<Path>, config: PersistentConfig) -> Result<Self> {
+        let (mut db, mut pending) = Self::open_deferred(path, config)?;
+        if !pending.is_empty() {
+            info!("Replaying {} WAL entries...", pending.len());
+        }
+        db.replay(&mut pending, usize::MAX)?;
+        Ok(db)
+    }
+
+    /// Open from the latest snapshot only, leaving the WAL tail to [`replay`](Self::replay)
+    ///
+    /// Until the tail is replayed the database reflects the snapshot plus
+    /// whatever has been replayed so far. Writes must wait until the tail is
+    /// fully replayed, or they would be applied out of order.
+    pub fn open_deferred(
+        path: impl AsRef<Path>,
+        config: PersistentConfig,
+    ) -> Result<(Self, PendingReplay)> {
         let data_dir = path.as_ref().to_path_buf();
         std::fs::create_dir_all(&data_dir)?;
 
         let wal_dir = data_dir.join("wal");
         let snapshot_dir = data_dir.join("snapshots");
 
-        let mut wal = Wal::open(&wal_dir)?;
+        let (mut wal, wal_repair) = Wal::open_with_recovery(&wal_dir, config.recovery_mode)?;
         wal.set_max_size(config.checkpoint_threshold);
+        wal.set_group_commit(config.group_commit)?;
 
         let mut snapshot_manager = SnapshotManager::new(&snapshot_dir)?;
         snapshot_manager.set_retain_count(config.snapshot_retain_count);
 
-        let storage = VectorStorage::new(config.dimensions);
-        let index = HnswIndex::new(config.hnsw.clone(), config.distance_metric);
+        let log_id_path = data_dir.join(LOG_ID_FILE);
+        let log_id = match std::fs::read_to_string(&log_id_path) {
+            Ok(s) => s
+                .trim()
+                .parse()
+                .map_err(|_| Error::Storage(format!("Invalid log ID in {:?}", log_id_path)))?,
+            Err(_) => {
+                // 53 bits, so the ID survives JSON parsers that use doubles
+                let log_id = rand::random::<u64>() >> 11;
+                std::fs::write(&log_id_path, log_id.to_string())?;
+                log_id
+            }
+        };
 
+        let (storage, index, partitions, signs) = Self::empty_state(&config)?;
+        let named = NamedVectors::new(&config.named_vectors, &config.hnsw)?;
         let mut db = Self {
             config,
             storage,
             index,
+            partitions,
+            signs,
+            sparse: SparseStore::new(),
+            named,
             wal,
             snapshot_manager,
             data_dir,
+            replayed_seq: None,
+            checkpointed_seq: 0,
+            log_id,
+            wal_repair,
+            defer_syncs: false,
+            pending_sync: None,
         };
 
-        // Recover from snapshot and WAL
-        db.recover()?;
+        let snapshot_seq = db.load_snapshot()?;
+        if let Some(seq) = snapshot_seq {
+            // A checkpoint logs an entry right after clearing the WAL; if a
+            // crash lost it, its sequence number must not be handed out again
+            db.wal.advance_to(seq + 1);
+            db.checkpointed_seq = seq + 1;
+        }
+        let last_wal_seq = snapshot_seq.unwrap_or(0);
+        let entries = db.wal.read_after(last_wal_seq)?;
+        if !entries.is_empty() {
+            db.replayed_seq = Some(last_wal_seq);
+        }
+        Ok((db, PendingReplay::new(entries)))
+    }
 
-        Ok(db)
+    /// Empty storage, graph, partition graphs and sign codes for `config`
+    fn empty_state(config: &PersistentConfig) -> Result<EmptyState> {
+        let storage = VectorStorage::new(config.dimensions)
+            .with_metadata_compression(config.metadata_compression)?
+            .with_text_fields(config.text_fields.clone());
+        let index = config.index.build(&config.hnsw, config.distance_metric);
+        let partitions = config
+            .partition_field
+            .clone()
+            .map(|field| PartitionedIndex::new(field, config.hnsw.clone(), config.distance_metric));
+        let signs = (config.quantization == QuantizationType::Binary)
+            .then(|| SignCodes::new(config.dimensions));
+        Ok((storage, index, partitions, signs))
+    }
+
+    /// Apply up to `max` pending WAL entries; returns how many were applied
+    pub fn replay(&mut self, pending: &mut PendingReplay, max: usize) -> Result<usize> {
+        let mut applied = 0;
+        while applied < max {
+            let Some(entry) = pending.entries.next() else {
+                break;
+            };
+            self.apply(entry)?;
+            applied += 1;
+            if let Some(seq) = &mut self.replayed_seq {
+                *seq += 1;
+            }
+        }
+        if pending.is_empty() {
+            self.replayed_seq = None;
+        }
+        Ok(applied)
+    }
+
+    /// Sequence number of the last write visible to reads
+    ///
+    /// This is the WAL sequence, so it keeps growing across restarts.
+    pub fn write_seq(&self) -> u64 {
+        self.replayed_seq.unwrap_or_else(|| self.wal.seq())
+    }
+
+    /// Where the write log stands
+    pub fn log_position(&self) -> LogPosition {
+        LogPosition {
+            log_id: self.log_id,
+            seq: self.write_seq(),
+        }
+    }
+
+    /// Up to `limit` logged entries after `from`, with their sequence numbers
+    ///
+    /// `None` when the entries can't be read from this log: `from` belongs to
+    /// another log or is ahead of this one, or a checkpoint has cleared
+    /// entries after it. A follower then has to copy the database afresh.
+    /// Fails while the WAL is still being replayed.
+    pub fn log_after(
+        &self,
+        from: LogPosition,
+        limit: usize,
+    ) -> Result<Option<Vec<(u64, WalEntry)>>> {
+        if self.replayed_seq.is_some() {
+            return Err(Error::InvalidConfig(
+                "Cannot read the log while the WAL is being replayed".to_string(),
+            ));
+        }
+        if from.log_id != self.log_id || from.seq > self.wal.seq() {
+            return Ok(None);
+        }
+        if from.seq == self.wal.seq() || limit == 0 {
+            return Ok(Some(Vec::new()));
+        }
+        let records = self.wal.read_records_after(from.seq, limit)?;
+        if records.first().map(|(seq, _)| *seq) != Some(from.seq + 1) {
+            return Ok(None);
+        }
+        Ok(Some(records))
+    }
+
+    /// Log and apply entries read from another database's log, in order
+    ///
+    /// For followers replaying a leader's [`log_after`](Self::log_after);
+    /// the entries were checked when the leader wrote them. Checkpoint
+    /// markers are skipped, as this database checkpoints on its own.
+    pub fn apply_logged(&mut self, entries: Vec<WalEntry>) -> Result<()> {
+        if self.replayed_seq.is_some() {
+            return Err(Error::InvalidConfig(
+                "Cannot write while the WAL is being replayed".to_string(),
+            ));
+        }
+        for entry in entries {
+            if matches!(entry, WalEntry::Checkpoint { .. }) {
+                continue;
+            }
+            self.wal.append(entry.clone())?;
+            self.commit_wal()?;
+            self.apply(entry)?;
+        }
+        if self.wal.needs_checkpoint() {
+            self.checkpoint()?;
+        }
+        Ok(())
     }
 
-    /// Recover database state from snapshot and WAL
-    fn recover(&mut self) -> Result<()> {
-        let mut last_wal_seq = 0u64;
+    /// Load the latest snapshot, if any, returning the WAL sequence it covers
+    fn load_snapshot(&mut self) -> Result<Option<u64>> {
+        let Some(snapshot) = self.snapshot_manager.load_latest()? else {
+            return Ok(None);
+        };
+        debug!("Loading snapshot for recovery...");
+        let wal_seq = snapshot.wal_seq;
+        self.load_vectors(snapshot)?;
+        Ok(Some(wal_seq))
+    }
 
-        // 1. Load latest snapshot if available
-        if let Some(snapshot) = self.snapshot_manager.load_latest()? {
-            debug!("Loading snapshot for recovery...");
-            last_wal_seq = snapshot.wal_seq;
+    /// Load a snapshot's vectors and graph into the empty in-memory state
+    fn load_vectors(&mut self, snapshot: Snapshot) -> Result<()> {
+        // Verify dimensions match
+        if snapshot.dimensions != self.config.dimensions {
+            return Err(Error::InvalidConfig(format!(
+                "Snapshot dimensions ({}) don't match config ({})",
+                snapshot.dimensions, self.config.dimensions
+            )));
+        }
 
-            // Verify dimensions match
-            if snapshot.dimensions != self.config.dimensions {
-                return Err(Error::InvalidConfig(format!(
-                    "Snapshot dimensions ({}) don't match config ({})",
-                    snapshot.dimensions, self.config.dimensions
-                )));
+        // Restore vectors from snapshot
+        for stored in snapshot.vectors {
+            let id = self.config.id_type.parse(stored.id)?;
+            let internal_id = self.storage.insert(id, &stored.vector, stored.metadata)?;
+            if let Some(signs) = &self.signs {
+                signs.set(internal_id, &stored.vector);
+            }
+        }
+        for (id, vector) in snapshot.sparse {
+            let id = self.config.id_type.parse(id)?;
+            if let Some(internal_id) = self.storage.get_internal_id(&id) {
+                self.sparse.set(internal_id, vector);
+            }
+        }
+        for (id, name, vector) in snapshot.named {
+            let id = self.config.id_type.parse(id)?;
+            if let Some(internal_id) = self.storage.get_internal_id(&id) {
+                self.named.set(internal_id, &name, &vector, &self.storage)?;
             }
+        }
 
-            // Restore vectors from snapshot
-            for stored in snapshot.vectors {
-                self.storage
-                    .insert(stored.id, &stored.vector, stored.metadata)?;
+        // Restore HNSW state if available
+        let view = self.graph_view(&self.storage);
+        if let (Some(state), Some(index)) = (snapshot.hnsw_state, self.index.as_hnsw()) {
+            index.load_state(state);
+        } else if let Some(bytes) = snapshot.index_state {
+            self.index.deserialize(&bytes)?;
+        } else {
+            // Fallback: rebuild index if state is missing
+            for internal_id in self.storage.all_internal_ids() {
+                if let Some(vector) = self.storage.get_vector_data(internal_id) {
+                    self.index.insert(internal_id, &vector, &view)?;
+                }
             }
+        }
 
-            // Restore HNSW state if available
-            if let Some(state) = snapshot.hnsw_state {
-                self.index.load_state(state);
-            } else {
-                // Fallback: rebuild index if state is missing
-                for internal_id in self.storage.all_internal_ids() {
-                    if let Some(vector) = self.storage.get_vector_data(internal_id) {
-                        self.index.insert(internal_id, &vector, &self.storage)?;
-                    }
+        // Partition graphs aren't snapshotted
+        if let Some(partitions) = &self.partitions {
+            for internal_id in self.storage.all_internal_ids() {
+                if let Some(vector) = self.storage.get_vector_data(internal_id) {
+                    partitions.insert(internal_id, &vector, &view)?;
                 }
             }
         }
+        Ok(())
+    }
 
-        // 2. Replay WAL entries after snapshot
-        let entries = self.wal.read_after(last_wal_seq)?;
-        let total = entries.len();
-        if total > 0 {
-            info!("Replaying {} WAL entries...", total);
+    /// Snapshot of the current vectors and graph, for exporting the collection
+    pub(crate) fn export_snapshot(&self) -> Snapshot {
+        let mut snapshot = Snapshot::capture(
+            0,
+            0,
+            self.config.dimensions,
+            &self.storage,
+            Some(self.index.as_ref()),
+        );
+        snapshot.capture_sparse(&self.storage, &self.sparse);
+        snapshot.capture_named(&self.storage, &self.named);
+        snapshot
+    }
+
+    /// Fill an empty database from an exported snapshot and checkpoint it
+    pub(crate) fn restore(&mut self, snapshot: Snapshot) -> Result<()> {
+        if self.storage.total_slots() > 0 {
+            return Err(Error::InvalidConfig(
+                "Snapshots can only be restored into an empty collection".to_string(),
+            ));
         }
+        self.load_vectors(snapshot)?;
+        self.checkpoint()
+    }
 
-        for (i, entry) in entries.into_iter().enumerate() {
-            if i > 0 && i % 5000 == 0 {
-                info!("Progress: {}/{} entries replayed...", i, total);
+    /// Fill an empty database from records and precomputed nearest-neighbor
+    /// lists, then checkpoint it
+    ///
+    /// See [`VectorDb::bulk_load`](crate::VectorDb::bulk_load). The records
+    /// skip the WAL; they are durable once the checkpoint is written.
+    pub fn bulk_load(
+        &mut self,
+        items: Vec<(VectorId, Vec<f32>, Option<Value>)>,
+        neighbors: Vec<Vec<usize>>,
+    ) -> Result<()> {
+        if self.index.as_hnsw().is_none() {
+            return Err(Error::InvalidConfig(
+                "Bulk loading needs an HNSW index".to_string(),
+            ));
+        }
+        if self.storage.total_slots() > 0 {
+            return Err(Error::InvalidConfig(
+                "Bulk loading needs an empty collection".to_string(),
+            ));
+        }
+        let items = crate::validate_batch(
+            self.config.id_type,
+            self.config.dimensions,
+            self.config.metadata_limits,
+            items,
+        )?;
+        let neighbors = crate::bulk_neighbors(&items, neighbors)?;
+
+        let mut internal_ids = Vec::with_capacity(items.len());
+        for (id, vector, metadata) in items {
+            let internal_id = self.storage.insert(id, &vector, metadata)?;
+            if let Some(signs) = &self.signs {
+                signs.set(internal_id, &vector);
+            }
+            internal_ids.push(internal_id);
+        }
+        {
+            let view = self.graph_view(&self.storage);
+            if let Some(index) = self.index.as_hnsw() {
+                index.build_from_neighbors(&neighbors, &view)?;
             }
-            match entry {
-                WalEntry::Insert {
-                    id,
-                    vector,
-                    metadata,
-                } => {
-                    // Skip if already in storage (duplicate)
-                    if self.storage.get_internal_id(&id).is_none() {
-                        let internal_id = self.storage.insert(id, &vector, metadata)?;
-                        self.index.insert(internal_id, &vector, &self.storage)?;
+            if let Some(partitions) = &self.partitions {
+                for internal_id in internal_ids {
+                    if let Some(vector) = self.storage.get_vector_data(internal_id) {
+                        partitions.insert(internal_id, &vector, &view)?;
                     }
                 }
-                WalEntry::Delete { id } => {
+            }
+        }
+
+        self.checkpoint()
+    }
+
+    /// Apply a logged entry to the in-memory state
+    fn apply(&mut self, entry: WalEntry) -> Result<()> {
+        match entry {
+            WalEntry::Insert {
+                id,
+                vector,
+                metadata,
+            } => {
+                let id = self.config.id_type.parse(id)?;
+                // Skip if already in storage (duplicate)
+                if self.storage.get_internal_id(&id).is_none() {
+                    let internal_id = self.storage.insert(id, &vector, metadata)?;
+                    self.index_vector(internal_id, &vector)?;
+                }
+            }
+            WalEntry::Delete { id } => {
+                if let Ok(id) = self.config.id_type.parse(id) {
+                    self.forget_attached(&id);
                     let _ = self.storage.delete(&id);
                 }
-                WalEntry::Checkpoint { .. } => {}
             }
+            WalEntry::Batch { entries } => {
+                // Inserts in a row are linked into the graph together
+                let mut inserted = Vec::new();
+                for entry in entries {
+                    match entry {
+                        WalEntry::Insert {
+                            id,
+                            vector,
+                            metadata,
+                        } => {
+                            let id = self.config.id_type.parse(id)?;
+                            if self.storage.get_internal_id(&id).is_none() {
+                                let internal_id = self.storage.insert(id, &vector, metadata)?;
+                                inserted.push((internal_id, vector));
+                            }
+                        }
+                        entry => {
+                            self.index_batch(&inserted)?;
+                            inserted.clear();
+                            self.apply(entry)?;
+                        }
+                    }
+                }
+                self.index_batch(&inserted)?;
+            }
+            WalEntry::Sparse { id, vector } => {
+                let id = self.config.id_type.parse(id)?;
+                if let Some(internal_id) = self.storage.get_internal_id(&id) {
+                    self.sparse.set(internal_id, vector);
+                }
+            }
+            WalEntry::Metadata { id, metadata } => {
+                let id = self.config.id_type.parse(id)?;
+                let Some(internal_id) = self.storage.get_internal_id(&id) else {
+                    return Ok(());
+                };
+                let current = self.storage.get_metadata(internal_id);
+                if self
+                    .partitions
+                    .as_ref()
+                    .is_some_and(|p| p.moves(current.as_ref(), metadata.as_ref()))
+                {
+                    // Re-link the record so it joins its new partition graph
+                    let vector = self
+                        .storage
+                        .get(internal_id)
+                        .ok_or(Error::VectorNotFound(id.to_string()))?;
+                    self.index.remove(internal_id);
+                    let sparse = self.sparse.remove(internal_id);
+                    let named = self.named.remove(internal_id);
+                    let internal_id = self.storage.upsert(id, &vector, metadata)?;
+                    self.index_vector(internal_id, &vector)?;
+                    if let Some(sparse) = sparse {
+                        self.sparse.set(internal_id, sparse);
+                    }
+                    for (name, vector) in named {
+                        self.named.set(internal_id, &name, &vector, &self.storage)?;
+                    }
+                } else {
+                    self.storage.update_metadata(&id, metadata)?;
+                }
+            }
+            WalEntry::Named { id, name, vector } => {
+                let id = self.config.id_type.parse(id)?;
+                if let Some(internal_id) = self.storage.get_internal_id(&id) {
+                    self.named.set(internal_id, &name, &vector, &self.storage)?;
+                }
+            }
+            WalEntry::Checkpoint { .. } => {}
+        }
+        Ok(())
+    }
+
+    /// Drop the sparse and named vectors of `id` before the record is deleted
+    fn forget_attached(&mut self, id: &VectorId) {
+        if let Some(internal_id) = self.storage.get_internal_id(id) {
+            self.index.remove(internal_id);
+            self.sparse.remove(internal_id);
+            self.named.remove(internal_id);
+        }
+    }
+
+    /// Add a stored vector to the main graph and its partition graph
+    fn index_vector(&self, internal_id: InternalId, vector: &[f32]) -> Result<()> {
+        if let Some(signs) = &self.signs {
+            signs.set(internal_id, vector);
+        }
+        let view = self.graph_view(&self.storage);
+        self.index.insert(internal_id, vector, &view)?;
+        if let Some(partitions) = &self.partitions {
+            partitions.insert(internal_id, vector, &view)?;
         }
+        Ok(())
+    }
 
+    /// Link stored vectors into the graphs in one batch
+    fn index_batch(&self, items: &[(InternalId, Vec<f32>)]) -> Result<()> {
+        if items.is_empty() {
+            return Ok(());
+        }
+        if let Some(signs) = &self.signs {
+            for (internal_id, vector) in items {
+                signs.set(*internal_id, vector);
+            }
+        }
+        let view = self.graph_view(&self.storage);
+        let batch: Vec<(InternalId, &[f32])> = items
+            .iter()
+            .map(|(internal_id, vector)| (*internal_id, vector.as_slice()))
+            .collect();
+        self.index.insert_batch(&batch, &view)?;
+        if let Some(partitions) = &self.partitions {
+            for &(internal_id, vector) in &batch {
+                partitions.insert(internal_id, vector, &view)?;
+            }
+        }
         Ok(())
     }
 
+    /// Storage as the graphs measure it: on sign codes if the collection has them
+    fn graph_view<'a, S: VectorStorageTrait>(&'a self, inner: &'a S) -> GraphView<'a, S> {
+        GraphView {
+            inner,
+            signs: self
+                .signs
+                .as_ref()
+                .map(|signs| (&signs.quantizer, signs.codes.read())),
+        }
+    }
+
+    /// Search the partition graph pinned by `filter`, or the main graph
+    ///
+    /// Binary-quantized collections fetch `oversampling * k` candidates on the
+    /// sign codes and rank them by their full-precision distance, unless
+    /// `params` turns rescoring off.
+    fn search_graph(
+        &self,
+        query: &[f32],
+        k: usize,
+        filter: Option<&Filter>,
+        params: SearchParams,
+        usage: &mut SearchUsage,
+    ) -> Result<Vec<(InternalId, f32)>> {
+        let rescore = self.signs.is_some() && params.rescore.unwrap_or(true);
+        let candidates = if rescore {
+            params.candidates(k, DEFAULT_OVERSAMPLING)
+        } else {
+            k
+        };
+
+        let ef_search = Some(params.ef_search.unwrap_or(self.config.hnsw.ef_search));
+        let view = self.storage.search_view(filter);
+        let graph_view = self.graph_view(&view);
+        let partitioned = self.partitions.as_ref().and_then(|p| {
+            p.search_with_usage(query, candidates, ef_search, &graph_view, filter, usage)
+        });
+        // Rescored candidates are cut on their full-precision distances below
+        let max_distance = params.max_distance.filter(|_| !rescore);
+        let results = match partitioned {
+            Some(results) => crate::within(results?, max_distance),
+            None => self.index.search_within(
+                query,
+                candidates,
+                ef_search,
+                max_distance,
+                &graph_view,
+                filter,
+                usage,
+            )?,
+        };
+        if !rescore {
+            return Ok(results);
+        }
+
+        let metric = self.config.distance_metric;
+        let mut rescored: Vec<_> = results
+            .into_iter()
+            .filter_map(|(id, _)| view.distance(id, query, metric).map(|dist| (id, dist)))
+            .collect();
+        usage.rescored_candidates += rescored.len() as u64;
+        usage.vectors_scanned += rescored.len() as u64;
+
+        rescored.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+        rescored.truncate(k);
+        Ok(crate::within(rescored, params.max_distance))
+    }
+
     /// Delete a vector by ID
     pub fn delete(&mut self, id: impl Into<VectorId>) -> Result<bool> {
-        let id = id.into();
+        let Ok(id) = self.config.id_type.parse(id.into()) else {
+            return Ok(false);
+        };
 
         // Write to WAL
         self.wal.append(WalEntry::Delete { id: id.clone() })?;
 
-        if self.config.sync_writes {
-            self.wal.sync()?;
-        }
+        self.commit_wal()?;
 
         // Apply to storage
+        self.forget_attached(&id);
         let deleted = self.storage.delete(&id)?;
 
         // Checkpoint if needed
@@ -178,6 +719,49 @@ impl PersistentVectorDb {
         Ok(deleted)
     }
 
+    /// Delete the vectors with the given IDs as one WAL record; returns
+    /// those that existed
+    pub fn delete_batch(
+        &mut self,
+        ids: impl IntoIterator<Item = impl Into<VectorId>>,
+    ) -> Result<Vec<VectorId>> {
+        let mut seen = HashSet::new();
+        let ids = ids
+            .into_iter()
+            .filter_map(|id| self.config.id_type.parse(id.into()).ok())
+            .filter(|id| self.storage.get_internal_id(id).is_some() && seen.insert(id.clone()))
+            .collect();
+        self.delete_logged(ids)
+    }
+
+    /// Delete every vector whose metadata matches `filter` as one WAL
+    /// record; returns their IDs
+    pub fn delete_by_filter(&mut self, filter: &Filter) -> Result<Vec<VectorId>> {
+        let matching = self.storage.ids_matching(filter)?;
+        self.delete_logged(matching)
+    }
+
+    /// Log the deletes of live vectors `ids` together, then apply them
+    fn delete_logged(&mut self, ids: Vec<VectorId>) -> Result<Vec<VectorId>> {
+        if ids.is_empty() {
+            return Ok(ids);
+        }
+        let batch = WalEntry::Batch {
+            entries: ids
+                .iter()
+                .map(|id| WalEntry::Delete { id: id.clone() })
+                .collect(),
+        };
+        self.wal.append(batch.clone())?;
+        self.commit_wal()?;
+        self.apply(batch)?;
+
+        if self.wal.needs_checkpoint() {
+            self.checkpoint()?;
+        }
+        Ok(ids)
+    }
+
     /// Insert a vector with the given ID and optional metadata
     pub fn insert(
         &mut self,
@@ -185,7 +769,7 @@ impl PersistentVectorDb {
         vector: &[f32],
         metadata: Option<Value>,
     ) -> Result<()> {
-        let id = id.into();
+        let id = self.config.id_type.parse(id.into())?;
 
         if vector.len() != self.config.dimensions {
             return Err(Error::DimensionMismatch {
@@ -193,6 +777,11 @@ impl PersistentVectorDb {
                 got: vector.len(),
             });
         }
+        self.config.metadata_limits.check(&id, metadata.as_ref())?;
+        // Refused inserts must not reach the WAL
+        if self.storage.get_internal_id(&id).is_some() {
+            return Err(Error::DuplicateId(id.to_string()));
+        }
 
         // Write to WAL first (durability)
         self.wal.append(WalEntry::Insert {
@@ -201,13 +790,11 @@ impl PersistentVectorDb {
             metadata: metadata.clone(),
         })?;
 
-        if self.config.sync_writes {
-            self.wal.sync()?;
-        }
+        self.commit_wal()?;
 
         // Then apply to in-memory structures
         let internal_id = self.storage.insert(id, vector, metadata)?;
-        self.index.insert(internal_id, vector, &self.storage)?;
+        self.index_vector(internal_id, vector)?;
 
         // Check if we need to checkpoint
         if self.wal.needs_checkpoint() {
@@ -217,6 +804,91 @@ impl PersistentVectorDb {
         Ok(())
     }
 
+    /// Insert or overwrite `items` atomically; later items win over earlier
+    /// ones with the same ID
+    ///
+    /// The batch is logged as a single WAL record, and its vectors are linked
+    /// into the graph together, on all cores with the `parallel` feature.
+    pub fn upsert_batch(&mut self, items: Vec<(VectorId, Vec<f32>, Option<Value>)>) -> Result<()> {
+        self.write_batch(None, items).map(|_| ())
+    }
+
+    /// Delete every vector matching `filter` and upsert `items` atomically
+    ///
+    /// The deletes and inserts are logged as a single WAL record, so recovery
+    /// replays either all of them or none. Returns the number of vectors that
+    /// matched `filter`.
+    pub fn replace(
+        &mut self,
+        filter: &Filter,
+        items: Vec<(VectorId, Vec<f32>, Option<Value>)>,
+    ) -> Result<usize> {
+        self.write_batch(Some(filter), items)
+    }
+
+    /// Log and apply the deletes of the vectors matching `filter` and the
+    /// upserts of `items` as one record; returns the number that matched
+    fn write_batch(
+        &mut self,
+        filter: Option<&Filter>,
+        items: Vec<(VectorId, Vec<f32>, Option<Value>)>,
+    ) -> Result<usize> {
+        let items = crate::validate_batch(
+            self.config.id_type,
+            self.config.dimensions,
+            self.config.metadata_limits,
+            items,
+        )?;
+
+        // Later items win, as in an upsert batch
+        let mut seen = HashSet::new();
+        let mut items: Vec<_> = items
+            .into_iter()
+            .rev()
+            .filter(|(id, _, _)| seen.insert(id.clone()))
+            .collect();
+        items.reverse();
+
+        let matching = filter
+            .map(|filter| self.storage.ids_matching(filter))
+            .transpose()?
+            .unwrap_or_default();
+        let matched = matching.len();
+        let mut deletes: HashSet<VectorId> = matching.into_iter().collect();
+        // Items overwriting a vector outside `filter` need their own delete
+        deletes.extend(
+            items
+                .iter()
+                .filter(|(id, _, _)| self.storage.get_internal_id(id).is_some())
+                .map(|(id, _, _)| id.clone()),
+        );
+
+        let entries = deletes
+            .into_iter()
+            .map(|id| WalEntry::Delete { id })
+            .chain(
+                items
+                    .into_iter()
+                    .map(|(id, vector, metadata)| WalEntry::Insert {
+                        id,
+                        vector,
+                        metadata,
+                    }),
+            )
+            .collect();
+        let batch = WalEntry::Batch { entries };
+
+        self.wal.append(batch.clone())?;
+        self.commit_wal()?;
+        self.apply(batch)?;
+
+        if self.wal.needs_checkpoint() {
+            self.checkpoint()?;
+        }
+
+        Ok(matched)
+    }
+
     /// Search for the k nearest neighbors
     pub fn search(
         &self,
@@ -224,6 +896,28 @@ impl PersistentVectorDb {
         k: usize,
         filter: Option<&crate::filter::Filter>,
     ) -> Result<Vec<(VectorId, f32, Option<Value>)>> {
+        self.search_with_usage(query, k, filter)
+            .map(|(results, _)| results)
+    }
+
+    /// Search for the k nearest neighbors, reporting the work performed
+    pub fn search_with_usage(
+        &self,
+        query: &[f32],
+        k: usize,
+        filter: Option<&crate::filter::Filter>,
+    ) -> Result<(Vec<SearchHit>, SearchUsage)> {
+        self.search_with_params(query, k, filter, SearchParams::default())
+    }
+
+    /// Search for the k nearest neighbors with per-search overrides
+    pub fn search_with_params(
+        &self,
+        query: &[f32],
+        k: usize,
+        filter: Option<&crate::filter::Filter>,
+        params: SearchParams,
+    ) -> Result<(Vec<SearchHit>, SearchUsage)> {
         if query.len() != self.config.dimensions {
             return Err(Error::DimensionMismatch {
                 expected: self.config.dimensions,
@@ -231,7 +925,8 @@ impl PersistentVectorDb {
             });
         }
 
-        let results = self.index.search(query, k, &self.storage, filter)?;
+        let mut usage = SearchUsage::default();
+        let results = self.search_graph(query, k, filter, params, &mut usage)?;
 
         let mapped: Vec<(VectorId, f32, Option<Value>)> = results
             .into_iter()
@@ -243,7 +938,7 @@ impl PersistentVectorDb {
             })
             .collect();
 
-        Ok(mapped)
+        Ok((mapped, usage))
     }
 
     /// Search for the k nearest neighbors (without metadata)
@@ -253,6 +948,28 @@ impl PersistentVectorDb {
         k: usize,
         filter: Option<&crate::filter::Filter>,
     ) -> Result<Vec<(VectorId, f32)>> {
+        self.search_ids_with_usage(query, k, filter)
+            .map(|(results, _)| results)
+    }
+
+    /// Search for the k nearest neighbors (without metadata), reporting the work performed
+    pub fn search_ids_with_usage(
+        &self,
+        query: &[f32],
+        k: usize,
+        filter: Option<&crate::filter::Filter>,
+    ) -> Result<(Vec<(VectorId, f32)>, SearchUsage)> {
+        self.search_ids_with_params(query, k, filter, SearchParams::default())
+    }
+
+    /// Search for the k nearest neighbors (without metadata) with per-search overrides
+    pub fn search_ids_with_params(
+        &self,
+        query: &[f32],
+        k: usize,
+        filter: Option<&crate::filter::Filter>,
+        params: SearchParams,
+    ) -> Result<(Vec<(VectorId, f32)>, SearchUsage)> {
         if query.len() != self.config.dimensions {
             return Err(Error::DimensionMismatch {
                 expected: self.config.dimensions,
@@ -260,10 +977,9 @@ impl PersistentVectorDb {
             });
         }
 
+        let mut usage = SearchUsage::default();
         let search_k = k * 2;
-        let results = self
-            .index
-            .search(query, search_k, &self.storage.view(), filter)?;
+        let results = self.search_graph(query, search_k, filter, params, &mut usage)?;
 
         let mapped: Vec<(VectorId, f32)> = results
             .into_iter()
@@ -278,7 +994,209 @@ impl PersistentVectorDb {
             .take(k)
             .collect();
 
-        Ok(mapped)
+        Ok((mapped, usage))
+    }
+
+    /// Set the sparse vector of record `id`; returns false if there is no such record
+    ///
+    /// The sparse vector belongs to the record as stored now: overwriting or
+    /// deleting the record drops it.
+    pub fn set_sparse(&mut self, id: impl Into<VectorId>, vector: SparseVector) -> Result<bool> {
+        vector.validate()?;
+        let id = self.config.id_type.parse(id.into())?;
+        if self.storage.get_internal_id(&id).is_none() {
+            return Ok(false);
+        }
+
+        let entry = WalEntry::Sparse { id, vector };
+        self.wal.append(entry.clone())?;
+        self.commit_wal()?;
+        self.apply(entry)?;
+
+        if self.wal.needs_checkpoint() {
+            self.checkpoint()?;
+        }
+        Ok(true)
+    }
+
+    /// Change the metadata of `id` without resending its vector; returns false
+    /// if there is no such vector
+    ///
+    /// `metadata` replaces the current metadata (`null` clears it), or with
+    /// `merge` is applied to it as a JSON merge patch. The WAL records the
+    /// resulting metadata. The record keeps its slot and graph links, unless
+    /// the partition field changes, in which case it is re-inserted.
+    pub fn update_metadata(
+        &mut self,
+        id: impl Into<VectorId>,
+        metadata: Value,
+        merge: bool,
+    ) -> Result<bool> {
+        let Ok(id) = self.config.id_type.parse(id.into()) else {
+            return Ok(false);
+        };
+        let Some(internal_id) = self.storage.get_internal_id(&id) else {
+            return Ok(false);
+        };
+        let current = self.storage.get_metadata(internal_id);
+        let metadata = updated_metadata(current, metadata, merge);
+        self.config.metadata_limits.check(&id, metadata.as_ref())?;
+
+        let entry = WalEntry::Metadata { id, metadata };
+        self.wal.append(entry.clone())?;
+        self.commit_wal()?;
+        self.apply(entry)?;
+
+        if self.wal.needs_checkpoint() {
+            self.checkpoint()?;
+        }
+        Ok(true)
+    }
+
+    /// Sparse vector of record `id`, if it has one
+    pub fn get_sparse(&self, id: &str) -> Option<SparseVector> {
+        let id = self.config.id_type.parse(VectorId::from(id)).ok()?;
+        let internal_id = self.storage.get_internal_id(&id)?;
+        self.sparse.get(internal_id).cloned()
+    }
+
+    /// Set the vector of record `id` in the named space `name`; returns false
+    /// if there is no such record
+    pub fn set_named_vector(
+        &mut self,
+        id: impl Into<VectorId>,
+        name: &str,
+        vector: &[f32],
+    ) -> Result<bool> {
+        self.named.check(name, vector)?;
+        let id = self.config.id_type.parse(id.into())?;
+        if self.storage.get_internal_id(&id).is_none() {
+            return Ok(false);
+        }
+
+        let entry = WalEntry::Named {
+            id,
+            name: name.to_string(),
+            vector: vector.to_vec(),
+        };
+        self.wal.append(entry.clone())?;
+        self.commit_wal()?;
+        self.apply(entry)?;
+
+        if self.wal.needs_checkpoint() {
+            self.checkpoint()?;
+        }
+        Ok(true)
+    }
+
+    /// Named vectors of record `id`, by space
+    pub fn get_named_vectors(&self, id: &str) -> Vec<(String, Vec<f32>)> {
+        let Ok(id) = self.config.id_type.parse(VectorId::from(id)) else {
+            return Vec::new();
+        };
+        self.storage
+            .get_internal_id(&id)
+            .map_or_else(Vec::new, |internal_id| self.named.of(internal_id))
+    }
+
+    /// Search the named space `name` instead of the primary vectors
+    pub fn search_named(
+        &self,
+        name: &str,
+        query: &[f32],
+        k: usize,
+        filter: Option<&Filter>,
+        params: SearchParams,
+    ) -> Result<(Vec<SearchHit>, SearchUsage)> {
+        let ef_search = Some(params.ef_search.unwrap_or(self.config.hnsw.ef_search));
+        let mut usage = SearchUsage::default();
+        let view = self.storage.search_view(filter);
+        let results = self
+            .named
+            .search(name, query, k, ef_search, &view, filter, &mut usage)?;
+        drop(view);
+        let results = crate::within(results, params.max_distance);
+
+        let hits = results
+            .into_iter()
+            .filter_map(|(internal_id, distance)| {
+                let ext_id = self.storage.get_external_id(internal_id)?;
+                Some((ext_id, distance, self.storage.get_metadata(internal_id)))
+            })
+            .collect();
+        Ok((hits, usage))
+    }
+
+    /// Search with a dense and a sparse query, fusing both rankings
+    ///
+    /// Each ranking contributes `k * HYBRID_CANDIDATES` candidates; `params`
+    /// apply to the dense search.
+    pub fn search_hybrid(
+        &self,
+        dense: &[f32],
+        sparse: &SparseVector,
+        k: usize,
+        filter: Option<&Filter>,
+        fusion: Fusion,
+        params: SearchParams,
+    ) -> Result<(Vec<HybridHit>, SearchUsage)> {
+        sparse.validate()?;
+        fusion.validate()?;
+        let candidates = k * crate::sparse::HYBRID_CANDIDATES;
+        let (dense_results, mut usage) =
+            self.search_ids_with_params(dense, candidates, filter, params)?;
+        let hits = crate::sparse::hybrid::fuse_with_sparse(
+            &self.storage,
+            &self.sparse,
+            dense_results,
+            sparse,
+            k,
+            filter,
+            fusion,
+            &mut usage,
+        );
+        Ok((hits, usage))
+    }
+
+    /// Keyword search of the text fields; scores are BM25, higher is better
+    pub fn search_text(
+        &self,
+        query: &str,
+        k: usize,
+        filter: Option<&Filter>,
+    ) -> Result<(Vec<SearchHit>, SearchUsage)> {
+        let mut usage = SearchUsage::default();
+        let hits = crate::sparse::hybrid::search_text(&self.storage, query, k, filter, &mut usage)?;
+        Ok((hits, usage))
+    }
+
+    /// Search with a dense vector and a text query, fusing both rankings
+    ///
+    /// Each ranking contributes `k * HYBRID_CANDIDATES` candidates; `params`
+    /// apply to the dense search.
+    pub fn search_hybrid_text(
+        &self,
+        dense: &[f32],
+        query: &str,
+        k: usize,
+        filter: Option<&Filter>,
+        fusion: Fusion,
+        params: SearchParams,
+    ) -> Result<(Vec<HybridHit>, SearchUsage)> {
+        fusion.validate()?;
+        let candidates = k * crate::sparse::HYBRID_CANDIDATES;
+        let (dense_results, mut usage) =
+            self.search_ids_with_params(dense, candidates, filter, params)?;
+        let hits = crate::sparse::hybrid::fuse_with_text(
+            &self.storage,
+            dense_results,
+            query,
+            k,
+            filter,
+            fusion,
+            &mut usage,
+        )?;
+        Ok((hits, usage))
     }
 
     /// Create a checkpoint (snapshot + clear WAL)
@@ -288,27 +1206,15 @@ impl PersistentVectorDb {
             .map(|d| d.as_millis() as u64)
             .unwrap_or(0);
 
-        let wal_seq = self.wal.seq();
-
-        let mut snapshot = Snapshot::new(snapshot_id, wal_seq, self.config.dimensions);
-
-        // Add all vectors to snapshot
-        for internal_id in self.storage.all_internal_ids() {
-            // Skip deleted vectors
-            if self.storage.is_deleted(internal_id) {
-                continue;
-            }
-
-            if let Some(ext_id) = self.storage.get_external_id(internal_id) {
-                if let Some(vector) = self.storage.get(internal_id) {
-                    let metadata = self.storage.get_metadata(internal_id);
-                    snapshot.add_vector(ext_id, vector, metadata);
-                }
-            }
-        }
-
-        // Add index state to snapshot
-        snapshot.set_hnsw_state(self.index.get_state());
+        let mut snapshot = Snapshot::capture(
+            snapshot_id,
+            self.wal.seq(),
+            self.config.dimensions,
+            &self.storage,
+            Some(self.index.as_ref()),
+        );
+        snapshot.capture_sparse(&self.storage, &self.sparse);
+        snapshot.capture_named(&self.storage, &self.named);
 
         // Save snapshot
         self.snapshot_manager.save(&snapshot)?;
@@ -318,10 +1224,106 @@ impl PersistentVectorDb {
 
         // Log checkpoint in new WAL
         self.wal.append(WalEntry::Checkpoint { snapshot_id })?;
+        self.checkpointed_seq = self.wal.seq();
 
         Ok(())
     }
 
+    /// Checkpoint if anything was written since the last checkpoint, or else
+    /// sync the WAL; returns whether a checkpoint was taken
+    ///
+    /// While the WAL tail is being replayed only the WAL is synced, as a
+    /// snapshot would miss the rest of the tail.
+    pub fn flush(&mut self) -> Result<bool> {
+        if self.replayed_seq.is_some() || self.wal.seq() <= self.checkpointed_seq {
+            self.sync()?;
+            return Ok(false);
+        }
+        self.checkpoint()?;
+        self.sync()?;
+        Ok(true)
+    }
+
+    /// Damaged records removed from the WAL when the database was opened
+    pub fn wal_repair(&self) -> WalRepair {
+        self.wal_repair
+    }
+
+    /// Check that IDs, vectors and the graph agree with each other
+    pub fn verify(&self) -> IntegrityReport {
+        let mut report = IntegrityReport::default();
+        for slot in 0..self.storage.total_slots() {
+            let internal_id = InternalId::from(slot);
+            let Some(id) = self.storage.get_external_id(internal_id) else {
+                continue;
+            };
+            report.vectors += 1;
+            if self.storage.get_internal_id(&id) != Some(internal_id) {
+                report.problem(format!("ID {} of slot {} maps to another slot", id, slot));
+            }
+            match self.storage.get(internal_id) {
+                None => report.problem(format!("{} has no vector", id)),
+                Some(vector) if vector.iter().any(|x| !x.is_finite()) => {
+                    report.problem(format!("Vector of {} is not finite", id))
+                }
+                Some(_) => {}
+            }
+        }
+        if report.vectors != self.storage.len() {
+            report.problem(format!(
+                "{} IDs are counted but {} have a slot",
+                self.storage.len(),
+                report.vectors
+            ));
+        }
+
+        if let Some(index) = self.index.as_hnsw() {
+            index.check_integrity(&mut report);
+            for internal_id in self.storage.all_internal_ids() {
+                if internal_id.as_usize() >= report.graph_nodes {
+                    report.problem(format!(
+                        "Slot {} is not in the graph",
+                        internal_id.as_usize()
+                    ));
+                }
+            }
+        }
+        report
+    }
+
+    /// Make the last WAL append durable as configured
+    fn commit_wal(&mut self) -> Result<()> {
+        if self.config.group_commit.is_some() {
+            match self.wal.commit_pending()? {
+                // Tickets grow, so waiting for the last append covers earlier ones
+                Some(pending) if self.defer_syncs => self.pending_sync = Some(pending),
+                Some(pending) => pending.wait()?,
+                None => {}
+            }
+            Ok(())
+        } else if self.config.sync_writes {
+            self.wal.sync()
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Run `write`, returning the WAL sync it still has to wait for instead
+    /// of waiting
+    ///
+    /// With [`SyncMode::Always`](crate::SyncMode::Always) a caller holding
+    /// this database behind a lock waits after releasing it, so concurrent
+    /// writers append while the sync runs and share the next one.
+    pub fn deferring_sync<T>(
+        &mut self,
+        write: impl FnOnce(&mut Self) -> Result<T>,
+    ) -> (Result<T>, Option<PendingSync>) {
+        self.defer_syncs = true;
+        let result = write(self);
+        self.defer_syncs = false;
+        (result, self.pending_sync.take())
+    }
+
     /// Force sync WAL to disk
     pub fn sync(&mut self) -> Result<()> {
         self.wal.sync()
@@ -332,9 +1334,24 @@ impl PersistentVectorDb {
         self.storage.len()
     }
 
+    /// Number of live vectors, or of those whose metadata matches `filter`
+    pub fn count(&self, filter: Option<&Filter>) -> Result<usize> {
+        self.storage.count(filter)
+    }
+
+    /// Whether a vector with this external ID exists, without copying it
+    pub fn contains(&self, id: &str) -> bool {
+        self.config
+            .id_type
+            .parse(VectorId::from(id))
+            .is_ok_and(|id| self.storage.get_internal_id(&id).is_some())
+    }
+
     /// Retrieve a vector by its external ID
     pub fn get(&self, id: &str) -> Result<Option<(Vec<f32>, Option<Value>)>> {
-        let id = VectorId::from(id);
+        let Ok(id) = self.config.id_type.parse(VectorId::from(id)) else {
+            return Ok(None);
+        };
         if let Some(internal_id) = self.storage.get_internal_id(&id) {
             let vector = self
                 .storage
@@ -347,6 +1364,53 @@ impl PersistentVectorDb {
         }
     }
 
+    /// Retrieve metadata for several external IDs without copying vectors
+    ///
+    /// Unknown IDs are skipped; found IDs keep the order of `ids`.
+    pub fn get_metadata_batch(&self, ids: &[String]) -> Vec<(VectorId, Option<Value>)> {
+        let ids: Vec<VectorId> = ids
+            .iter()
+            .filter_map(|id| self.config.id_type.parse(VectorId::from(id.as_str())).ok())
+            .collect();
+        self.storage.get_metadata_batch(&ids)
+    }
+
+    /// Keep the matches of `filter` materialized for faster filtered search
+    ///
+    /// The match set is updated on every write. Cached filters are not
+    /// persisted.
+    pub fn cache_filter(&self, filter: Filter) -> Result<CachedFilterInfo> {
+        self.storage.cache_filter(filter)
+    }
+
+    /// Drop a cached filter by ID; returns whether it existed
+    pub fn uncache_filter(&self, id: &str) -> bool {
+        self.storage.uncache_filter(id)
+    }
+
+    pub(crate) fn storage(&self) -> &VectorStorage {
+        &self.storage
+    }
+
+    /// Filters currently cached, with their match counts and hits
+    pub fn cached_filters(&self) -> Vec<CachedFilterInfo> {
+        self.storage.cached_filters()
+    }
+
+    /// Page through records with a cursor; see [`Collection::list_page`](crate::db::Collection::list_page)
+    pub fn list_page(&self, cursor: Option<ListCursor>, limit: usize) -> Result<ListPage> {
+        self.storage.list_page(cursor, limit)
+    }
+
+    /// Iterate over every record with its vector and metadata, a page at a
+    /// time; see [`Scan`]
+    pub fn scan(&self) -> Scan<'_> {
+        Scan::new(
+            move |cursor, limit| self.list_page(cursor, limit),
+            move |id| self.get(id),
+        )
+    }
+
     /// List all vector IDs and metadata (pagination)
     pub fn list(&self, offset: usize, limit: usize) -> Vec<(VectorId, Option<Value>)> {
         let ids = self.storage.all_internal_ids();
@@ -370,6 +1434,146 @@ impl PersistentVectorDb {
             .collect()
     }
 
+    /// Get the number of deleted or overwritten vectors not yet reclaimed
+    pub fn deleted_count(&self) -> usize {
+        self.storage.deleted_count()
+    }
+
+    /// Space held by deleted or overwritten records; segments are the WAL
+    /// plus the retained snapshots
+    pub fn garbage_stats(&self) -> GarbageStats {
+        let snapshots = self
+            .snapshot_manager
+            .list_snapshots()
+            .map_or(0, |list| list.len());
+        GarbageStats {
+            segments: snapshots + 1,
+            ..self.storage.garbage_stats()
+        }
+    }
+
+    /// Rebuild the in-memory state from the live records, dropping
+    /// tombstoned slots, and checkpoint it
+    ///
+    /// The graph is rebuilt unless there is nothing to drop. Cached filters
+    /// are kept and list cursors expire. Fails while the WAL is still being
+    /// replayed.
+    pub fn compact(&mut self) -> Result<CompactionReport> {
+        if self.replayed_seq.is_some() {
+            return Err(Error::InvalidConfig(
+                "Cannot compact while the WAL is being replayed".to_string(),
+            ));
+        }
+        let snapshot = self.export_snapshot();
+        let report = CompactionReport::new(snapshot.len(), &self.garbage_stats());
+        self.rebuild(snapshot)?;
+        Ok(report)
+    }
+
+    /// Switch to `quantization`, re-encoding the live records and
+    /// rebuilding the graph on the new codes
+    ///
+    /// Like [`compact`](Self::compact), reclaims deleted slots and expires
+    /// list cursors. Fails while the WAL is still being replayed.
+    pub fn set_quantization(&mut self, quantization: QuantizationType) -> Result<()> {
+        if self.replayed_seq.is_some() {
+            return Err(Error::InvalidConfig(
+                "Cannot change quantization while the WAL is being replayed".to_string(),
+            ));
+        }
+        let snapshot = self.export_snapshot();
+        self.config.quantization = quantization;
+        self.rebuild(snapshot)
+    }
+
+    /// Replace the in-memory state with `snapshot` loaded into an empty one,
+    /// keeping cached filters, then checkpoint
+    fn rebuild(&mut self, snapshot: Snapshot) -> Result<()> {
+        let filters = self.storage.cached_filters();
+
+        let (storage, index, partitions, signs) = Self::empty_state(&self.config)?;
+        self.storage = storage;
+        self.index = index;
+        self.partitions = partitions;
+        self.signs = signs;
+        self.sparse = SparseStore::new();
+        self.named = NamedVectors::new(&self.config.named_vectors, &self.config.hnsw)?;
+        self.load_vectors(snapshot)?;
+        for info in filters {
+            self.storage.cache_filter(info.filter)?;
+        }
+        self.checkpoint()
+    }
+
+    /// Move the database's files to `path`, which must not exist yet
+    ///
+    /// The WAL is synced and reopened at the new location, as if the
+    /// database had been closed and opened there.
+    pub fn relocate(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref().to_path_buf();
+        if self.replayed_seq.is_some() {
+            return Err(Error::InvalidConfig(
+                "Cannot move while the WAL is being replayed".to_string(),
+            ));
+        }
+        if path.exists() {
+            return Err(Error::InvalidConfig(format!("{:?} already exists", path)));
+        }
+        self.wal.sync()?;
+        std::fs::rename(&self.data_dir, &path)?;
+
+        let reopened = Wal::open(path.join("wal")).and_then(|mut wal| {
+            wal.set_max_size(self.config.checkpoint_threshold);
+            wal.set_group_commit(self.config.group_commit)?;
+            let mut snapshot_manager = SnapshotManager::new(path.join("snapshots"))?;
+            snapshot_manager.set_retain_count(self.config.snapshot_retain_count);
+            Ok((wal, snapshot_manager))
+        });
+        let (wal, snapshot_manager) = match reopened {
+            Ok(reopened) => reopened,
+            Err(e) => {
+                // The old handles still point at the moved files; move them back
+                std::fs::rename(&path, &self.data_dir)?;
+                return Err(e);
+            }
+        };
+        self.wal = wal;
+        self.snapshot_manager = snapshot_manager;
+        self.data_dir = path;
+        Ok(())
+    }
+
+    /// Shape of the HNSW graph, or `None` when the collection isn't indexed
+    /// with HNSW
+    pub fn graph_stats(&self) -> Option<GraphStats> {
+        self.index.as_hnsw().map(HnswIndex::graph_stats)
+    }
+
+    /// Relink up to `budget` nodes of the HNSW graph left linking to deleted
+    /// vectors; see [`HnswIndex::repair`]
+    ///
+    /// Relinked nodes are saved with the graph at the next checkpoint.
+    pub fn repair_graph(&self, budget: usize) -> GraphRepair {
+        self.index
+            .as_hnsw()
+            .map(|index| index.repair(budget, &self.storage))
+            .unwrap_or_default()
+    }
+
+    /// Export the HNSW adjacency, optionally for one `level` or a `sample` of nodes
+    ///
+    /// Empty when the collection isn't indexed with HNSW.
+    pub fn export_graph(&self, level: Option<usize>, sample: Option<usize>) -> GraphExport {
+        let Some(index) = self.index.as_hnsw() else {
+            return GraphExport::default();
+        };
+        index.export_graph(level, sample, |id| {
+            (!self.storage.is_deleted(id))
+                .then(|| self.storage.get_external_id(id))
+                .flatten()
+        })
+    }
+
     /// Check if the database is empty
     pub fn is_empty(&self) -> bool {
         self.storage.is_empty()
@@ -380,8 +1584,101 @@ impl PersistentVectorDb {
         &self.config
     }
 
+    /// Change the candidate list size searches use by default
+    pub fn set_ef_search(&mut self, ef_search: usize) {
+        self.config.hnsw.ef_search = ef_search;
+    }
+
+    /// Get approximate in-memory usage split by component
+    pub fn memory_breakdown(&self) -> MemoryBreakdown {
+        MemoryBreakdown {
+            vectors: self.storage.vector_bytes()
+                + self.signs.as_ref().map_or(0, |s| s.codes.read().capacity())
+                + self.named.vector_bytes(),
+            graph: self.index.memory_usage()
+                + self.partitions.as_ref().map_or(0, |p| p.memory_usage())
+                + self.named.graph_bytes(),
+            ids: self.storage.id_bytes(),
+            metadata: self.storage.metadata_bytes(),
+            sparse: self.sparse.memory_usage(),
+            metadata_compression_ratio: self.storage.metadata_compression_ratio(),
+        }
+    }
+
+    /// The `n` records with the largest metadata, largest first
+    pub fn largest_payloads(&self, n: usize) -> Vec<PayloadSize> {
+        self.storage.largest_payloads(n)
+    }
+
     /// Get data directory
     pub fn data_dir(&self) -> &Path {
         &self.data_dir
     }
 }
+
+/// 1-bit sign codes of the stored vectors, indexed by internal ID
+struct SignCodes {
+    quantizer: BinaryQuantizer,
+    codes: RwLock<Vec<u8>>,
+}
+
+impl SignCodes {
+    fn new(dimensions: usize) -> Self {
+        Self {
+            quantizer: BinaryQuantizer::new(dimensions),
+            codes: RwLock::new(Vec::new()),
+        }
+    }
+
+    fn set(&self, internal_id: InternalId, vector: &[f32]) {
+        let size = self.quantizer.byte_size();
+        let start = internal_id.as_usize() * size;
+        let mut codes = self.codes.write();
+        if codes.len() < start + size {
+            codes.resize(start + size, 0);
+        }
+        codes[start..start + size].copy_from_slice(&self.quantizer.quantize(vector));
+    }
+}
+
+/// Collection storage with distances taken on sign codes, when there are any
+struct GraphView<'a, S> {
+    inner: &'a S,
+    signs: Option<(&'a BinaryQuantizer, RwLockReadGuard<'a, Vec<u8>>)>,
+}
+
+impl<S: VectorStorageTrait> VectorStorageTrait for GraphView<'_, S> {
+    fn get_vector_data(&self, internal_id: InternalId) -> Option<Vec<f32>> {
+        self.inner.get_vector_data(internal_id)
+    }
+
+    fn distance(
+        &self,
+        internal_id: InternalId,
+        query: &[f32],
+        metric: DistanceMetric,
+    ) -> Option<f32> {
+        let Some((quantizer, codes)) = &self.signs else {
+            return self.inner.distance(internal_id, query, metric);
+        };
+        let start = internal_id.as_usize() * quantizer.byte_size();
+        let code = codes.get(start..start + quantizer.byte_size())?;
+        Some(quantizer.hamming_to_cosine(quantizer.hamming_distance_f32(query, code)))
+    }
+
+    fn get_metadata(&self, internal_id: InternalId) -> Option<Value> {
+        self.inner.get_metadata(internal_id)
+    }
+
+    fn filter_bitmap(&self, filter: &Filter) -> Option<std::sync::Arc<roaring::RoaringBitmap>> {
+        self.inner.filter_bitmap(filter)
+    }
+
+    fn filter_entry(&self, filter: &Filter) -> Option<InternalId> {
+        self.inner.filter_entry(filter)
+    }
+
+    fn is_deleted(&self, internal_id: InternalId) -> bool {
+        self.inner.is_deleted(internal_id)
+    }
+}
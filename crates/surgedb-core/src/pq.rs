@@ -185,6 +185,17 @@ impl PqCodebook {
                     DistanceMetric::DotProduct => {
                         crate::distance::dot_product_distance(sub_query, centroid)
                     }
+                    // L1 and Hamming are sums over dimensions, so they add up over sub-vectors
+                    DistanceMetric::Manhattan => {
+                        crate::distance::manhattan_distance(sub_query, centroid)
+                    }
+                    DistanceMetric::Hamming => {
+                        crate::distance::hamming_distance(sub_query, centroid)
+                    }
+                    // Jaccard is a ratio and doesn't split over sub-vectors; use L2 like Cosine
+                    DistanceMetric::Jaccard => {
+                        crate::distance::euclidean_distance(sub_query, centroid).powi(2)
+                    }
                 };
 
                 table.push(dist);
@@ -106,6 +106,9 @@ impl SQ8Quantizer {
             DistanceMetric::DotProduct => {
                 self.asymmetric_dot_product_distance(query, quantized, metadata)
             }
+            DistanceMetric::Manhattan | DistanceMetric::Hamming | DistanceMetric::Jaccard => {
+                metric.distance(query, &self.dequantize(quantized, metadata))
+            }
         }
     }
 
@@ -293,6 +296,34 @@ impl BinaryQuantizer {
         result
     }
 
+    /// Expand a binary vector to ±1.0 per dimension
+    ///
+    /// The magnitudes are lost, but the signs round-trip, so the result can
+    /// stand in for the original wherever only Hamming distances are taken.
+    pub fn dequantize(&self, binary: &[u8]) -> Vec<f32> {
+        (0..self.dimensions)
+            .map(|i| {
+                if binary[i / 8] & (1 << (i % 8)) != 0 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            })
+            .collect()
+    }
+
+    /// Hamming distance between a binary vector and an unquantized query
+    ///
+    /// Same result as quantizing `query` first, without allocating.
+    #[inline]
+    pub fn hamming_distance_f32(&self, query: &[f32], binary: &[u8]) -> u32 {
+        query
+            .iter()
+            .enumerate()
+            .filter(|&(i, &value)| (value > 0.0) != (binary[i / 8] & (1 << (i % 8)) != 0))
+            .count() as u32
+    }
+
     /// Calculate Hamming distance between two binary vectors
     #[inline]
     pub fn hamming_distance(&self, a: &[u8], b: &[u8]) -> u32 {
@@ -430,6 +461,20 @@ mod tests {
         assert_eq!(dist, 4);
     }
 
+    #[test]
+    fn test_binary_hamming_distance_f32() {
+        let quantizer = BinaryQuantizer::new(10);
+        let stored = vec![0.3, -0.2, 0.0, 1.0, -1.0, 0.5, 0.5, -0.5, 2.0, -2.0];
+        let query = vec![-0.1, -0.2, 0.4, 1.0, 1.0, 0.5, -0.5, -0.5, 2.0, 0.0];
+
+        let binary = quantizer.quantize(&stored);
+        let expected = quantizer.hamming_distance(&quantizer.quantize(&query), &binary);
+        assert_eq!(quantizer.hamming_distance_f32(&query, &binary), expected);
+
+        // The ±1 expansion keeps every sign
+        assert_eq!(quantizer.quantize(&quantizer.dequantize(&binary)), binary);
+    }
+
     #[test]
     fn test_compression_ratio() {
         // SQ8: 4 bytes -> 1 byte = 4x compression
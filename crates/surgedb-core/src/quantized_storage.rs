@@ -4,12 +4,18 @@
 
 use crate::distance::DistanceMetric;
 use crate::error::{Error, Result};
+use crate::filter_cache::{CachedFilterInfo, FilterCache};
+use crate::id_map::IdMap;
+use crate::metadata_store::MetadataStore;
 use crate::quantization::{BinaryQuantizer, QuantizationType, SQ8Metadata, SQ8Quantizer};
 use crate::storage::VectorStorageTrait;
 use crate::sync::RwLock;
-use crate::types::{InternalId, VectorId};
+use crate::types::{
+    value_heap_size, GarbageStats, InternalId, ListCursor, ListPage, MetadataCompression,
+    PayloadSize, VectorId,
+};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 /// Quantized vector storage with configurable compression
 pub struct QuantizedStorage {
@@ -41,17 +47,20 @@ pub struct QuantizedStorage {
     /// Whether to keep original vectors for re-ranking
     keep_originals: bool,
 
-    /// Map from external ID to internal ID
-    id_to_internal: RwLock<HashMap<VectorId, InternalId>>,
-
-    /// Map from internal ID to external ID
-    internal_to_id: RwLock<Vec<VectorId>>,
+    /// External ID <-> internal ID mapping
+    ids: RwLock<IdMap>,
 
     /// Optional metadata for each vector
-    metadata: RwLock<HashMap<InternalId, Value>>,
+    metadata: RwLock<MetadataStore>,
 
     /// Set of deleted internal IDs
     deleted: RwLock<std::collections::HashSet<InternalId>>,
+
+    /// Materialized match sets for hot filters
+    filter_cache: RwLock<FilterCache>,
+
+    /// Payload bytes of deleted or overwritten records, which stay stored
+    dead_payload_bytes: AtomicUsize,
 }
 
 impl QuantizedStorage {
@@ -85,19 +94,28 @@ impl QuantizedStorage {
             binary_vectors: RwLock::new(Vec::new()),
             original_vectors: RwLock::new(original_vectors),
             keep_originals,
-            id_to_internal: RwLock::new(HashMap::new()),
-            internal_to_id: RwLock::new(Vec::new()),
-            metadata: RwLock::new(HashMap::new()),
+            ids: RwLock::new(IdMap::new()),
+            metadata: RwLock::new(MetadataStore::default()),
             deleted: RwLock::new(std::collections::HashSet::new()),
+            filter_cache: RwLock::new(FilterCache::default()),
+            dead_payload_bytes: AtomicUsize::new(0),
         }
     }
 
+    /// Keep metadata with the given compression (call before inserting)
+    pub fn with_metadata_compression(self, compression: MetadataCompression) -> Result<Self> {
+        *self.metadata.write() = MetadataStore::new(compression)?;
+        Ok(self)
+    }
+
     /// Delete a vector by ID
     pub fn delete(&self, id: &VectorId) -> Result<bool> {
-        let mut id_to_internal = self.id_to_internal.write();
+        let mut ids = self.ids.write();
 
-        if let Some(internal_id) = id_to_internal.remove(id) {
+        if let Some(internal_id) = ids.remove(id) {
             self.deleted.write().insert(internal_id);
+            self.filter_cache.write().remove_id(internal_id);
+            self.bury_payload(&self.metadata.read(), internal_id);
             Ok(true)
         } else {
             Ok(false)
@@ -138,23 +156,20 @@ impl QuantizedStorage {
             });
         }
 
-        {
-            let id_to_internal = self.id_to_internal.read();
-            if !allow_update && id_to_internal.contains_key(&id) {
-                return Err(Error::DuplicateId(id.to_string()));
-            }
+        if !allow_update && self.ids.read().contains(&id) {
+            return Err(Error::DuplicateId(id.to_string()));
         }
 
-        let mut internal_to_id = self.internal_to_id.write();
-        let mut id_to_internal = self.id_to_internal.write();
+        let mut ids = self.ids.write();
         let mut metadata_store = self.metadata.write();
+        let mut filter_cache = self.filter_cache.write();
 
         // Double check
-        if !allow_update && id_to_internal.contains_key(&id) {
+        if !allow_update && ids.contains(&id) {
             return Err(Error::DuplicateId(id.to_string()));
         }
 
-        let internal_id = InternalId::from(internal_to_id.len());
+        let internal_id = InternalId::from(ids.slots());
 
         // Store quantized version
         match self.quantization {
@@ -192,12 +207,16 @@ impl QuantizedStorage {
         }
 
         // Update mappings
-        id_to_internal.insert(id.clone(), internal_id);
-        internal_to_id.push(id);
+        if let (_, Some(old_internal_id)) = ids.push(id) {
+            self.deleted.write().insert(old_internal_id);
+            filter_cache.remove_id(old_internal_id);
+            self.bury_payload(&metadata_store, old_internal_id);
+        }
 
         // Store metadata if present
         if let Some(meta) = metadata {
-            metadata_store.insert(internal_id, meta);
+            filter_cache.index(internal_id, &meta);
+            metadata_store.insert(internal_id, meta)?;
         }
 
         Ok(internal_id)
@@ -222,9 +241,9 @@ impl QuantizedStorage {
             }
         }
 
-        let mut internal_to_id = self.internal_to_id.write();
-        let mut id_to_internal = self.id_to_internal.write();
+        let mut ids = self.ids.write();
         let mut metadata_store = self.metadata.write();
+        let mut filter_cache = self.filter_cache.write();
 
         // Locks for vector data
         let mut sq8_vectors = if self.quantization == QuantizationType::SQ8 {
@@ -249,7 +268,7 @@ impl QuantizedStorage {
                 None
             };
 
-        let start_internal_id = internal_to_id.len();
+        let start_internal_id = ids.slots();
         let mut result_ids = Vec::with_capacity(items.len());
 
         for (i, (id, vector, metadata)) in items.iter().enumerate() {
@@ -293,18 +312,47 @@ impl QuantizedStorage {
             }
 
             // Update mappings
-            id_to_internal.insert(id.clone(), internal_id);
-            internal_to_id.push(id.clone());
+            if let (_, Some(old_internal_id)) = ids.push(id.clone()) {
+                self.deleted.write().insert(old_internal_id);
+                filter_cache.remove_id(old_internal_id);
+                self.bury_payload(&metadata_store, old_internal_id);
+            }
 
             // Metadata
             if let Some(meta) = metadata {
-                metadata_store.insert(internal_id, meta.clone());
+                filter_cache.index(internal_id, meta);
+                metadata_store.insert(internal_id, meta.clone())?;
             }
         }
 
         Ok(result_ids)
     }
 
+    /// Replace the metadata of `id` in place, keeping its slot and vector
+    ///
+    /// Returns the slot, or `None` if there is no such vector.
+    pub fn update_metadata(
+        &self,
+        id: &VectorId,
+        metadata: Option<Value>,
+    ) -> Result<Option<InternalId>> {
+        let ids = self.ids.read();
+        let Some(internal_id) = ids.get(id) else {
+            return Ok(None);
+        };
+        let mut metadata_store = self.metadata.write();
+        let mut filter_cache = self.filter_cache.write();
+
+        metadata_store.remove(internal_id);
+        filter_cache.remove_id(internal_id);
+        if let Some(meta) = metadata {
+            filter_cache.index(internal_id, &meta);
+            metadata_store.insert(internal_id, meta)?;
+        }
+
+        Ok(Some(internal_id))
+    }
+
     /// Calculate distance from query to stored vector
     #[inline]
     pub fn distance(
@@ -358,11 +406,8 @@ impl QuantizedStorage {
                     return None;
                 }
 
-                // Quantize query on the fly
-                let query_binary = quantizer.quantize(query);
                 let stored = &binary_vectors[start..end];
-
-                let hamming = quantizer.hamming_distance(&query_binary, stored);
+                let hamming = quantizer.hamming_distance_f32(query, stored);
                 Some(quantizer.hamming_to_cosine(hamming))
             }
         }
@@ -383,29 +428,153 @@ impl QuantizedStorage {
 
     /// Get metadata for a vector
     pub fn get_metadata(&self, internal_id: InternalId) -> Option<Value> {
-        self.metadata.read().get(&internal_id).cloned()
+        self.metadata.read().get(internal_id)
     }
 
     /// Get external ID from internal ID
     pub fn get_external_id(&self, internal_id: InternalId) -> Option<VectorId> {
-        let internal_to_id = self.internal_to_id.read();
-        internal_to_id.get(internal_id.as_usize()).cloned()
+        self.ids.read().external(internal_id).cloned()
     }
 
     /// Get internal ID from external ID
     pub fn get_internal_id(&self, id: &VectorId) -> Option<InternalId> {
-        self.id_to_internal.read().get(id).copied()
+        self.ids.read().get(id)
+    }
+
+    /// Page through live records in slot order, starting a listing if `cursor` is `None`
+    pub fn list_page(&self, cursor: Option<ListCursor>, limit: usize) -> Result<ListPage> {
+        let ids = self.ids.read();
+        let (slots, next) = ids.page(cursor.unwrap_or_else(|| ids.start_listing()), limit)?;
+        let metadata = self.metadata.read();
+        let records = slots
+            .into_iter()
+            .filter_map(|internal_id| {
+                Some((
+                    ids.external(internal_id)?.clone(),
+                    metadata.get(internal_id),
+                ))
+            })
+            .collect();
+        Ok(ListPage { records, next })
+    }
+
+    /// External IDs of live vectors whose metadata matches `filter`
+    pub fn ids_matching(&self, filter: &crate::filter::Filter) -> Result<Vec<VectorId>> {
+        let slots = self.slots_matching(filter)?;
+        let ids = self.ids.read();
+        Ok(slots
+            .into_iter()
+            .filter_map(|internal_id| ids.external(internal_id).cloned())
+            .collect())
+    }
+
+    /// Number of live vectors, or of those whose metadata matches `filter`
+    pub fn count(&self, filter: Option<&crate::filter::Filter>) -> Result<usize> {
+        Ok(match filter {
+            Some(filter) => self.slots_matching(filter)?.len(),
+            None => self.ids.read().len(),
+        })
+    }
+
+    /// Slots of live vectors whose metadata matches `filter`
+    fn slots_matching(&self, filter: &crate::filter::Filter) -> Result<Vec<InternalId>> {
+        let ids = self.ids.read();
+        let metadata = self.metadata.read();
+        let candidates: Vec<InternalId> = match self.filter_cache.read().get(filter) {
+            Some(bitmap) => bitmap
+                .iter()
+                .map(|i| InternalId::from(i as usize))
+                .collect(),
+            None => (0..ids.slots()).map(InternalId::from).collect(),
+        };
+        candidates
+            .into_iter()
+            .filter(|&internal_id| {
+                ids.external(internal_id)
+                    .is_some_and(|id| ids.get(id) == Some(internal_id))
+            })
+            .filter_map(|internal_id| {
+                let meta = metadata.get(internal_id)?;
+                let matched = filter.evaluate(&meta);
+                matched.map(|m| m.then_some(internal_id)).transpose()
+            })
+            .collect()
+    }
+
+    /// Materialize the matches of `filter` and keep them updated on writes
+    pub fn cache_filter(&self, filter: crate::filter::Filter) -> Result<CachedFilterInfo> {
+        let ids = self.ids.read();
+        let metadata = self.metadata.read();
+        let matching: Vec<_> = (0..ids.slots())
+            .map(InternalId::from)
+            .filter(|&internal_id| {
+                ids.external(internal_id)
+                    .is_some_and(|id| ids.get(id) == Some(internal_id))
+                    && metadata
+                        .get(internal_id)
+                        .is_some_and(|meta| filter.matches(&meta))
+            })
+            .collect();
+        self.filter_cache.write().insert(filter, matching)
+    }
+
+    /// Drop a cached filter by ID
+    pub fn uncache_filter(&self, id: &str) -> bool {
+        self.filter_cache.write().remove(id)
+    }
+
+    /// Currently cached filters
+    pub fn cached_filters(&self) -> Vec<CachedFilterInfo> {
+        self.filter_cache.read().list()
+    }
+
+    /// Get metadata for several external IDs, skipping unknown ones
+    pub fn get_metadata_batch(&self, ids: &[VectorId]) -> Vec<(VectorId, Option<Value>)> {
+        let found: Vec<_> = {
+            let id_map = self.ids.read();
+            ids.iter()
+                .filter_map(|id| Some((id.clone(), id_map.get(id)?)))
+                .collect()
+        };
+        let metadata = self.metadata.read();
+        found
+            .into_iter()
+            .map(|(id, internal_id)| (id, metadata.get(internal_id)))
+            .collect()
     }
 
     /// Get all internal IDs
     pub fn all_internal_ids(&self) -> Vec<InternalId> {
-        let internal_to_id = self.internal_to_id.read();
-        (0..internal_to_id.len()).map(InternalId::from).collect()
+        (0..self.len()).map(InternalId::from).collect()
     }
 
     /// Get the number of stored vectors
     pub fn len(&self) -> usize {
-        self.internal_to_id.read().len()
+        self.ids.read().slots()
+    }
+
+    /// Get the number of slots held by deleted or overwritten vectors
+    pub fn deleted_count(&self) -> usize {
+        let ids = self.ids.read();
+        ids.slots().saturating_sub(ids.len())
+    }
+
+    /// Count the payload of a deleted or overwritten slot as garbage
+    fn bury_payload(&self, metadata: &MetadataStore, internal_id: InternalId) {
+        if let Some(meta) = metadata.get(internal_id) {
+            self.dead_payload_bytes
+                .fetch_add(value_heap_size(&meta), Ordering::Relaxed);
+        }
+    }
+
+    /// Space held by deleted or overwritten records
+    pub fn garbage_stats(&self) -> GarbageStats {
+        GarbageStats::new(
+            self.len(),
+            self.deleted_count(),
+            self.vector_bytes(),
+            self.dead_payload_bytes.load(Ordering::Relaxed),
+        )
     }
 
     /// Check if storage is empty
@@ -415,6 +584,11 @@ impl QuantizedStorage {
 
     /// Get memory usage in bytes
     pub fn memory_usage(&self) -> usize {
+        self.vector_bytes() + self.id_bytes() + self.metadata_bytes()
+    }
+
+    /// Bytes used by quantized (and, if kept, original) vector data
+    pub fn vector_bytes(&self) -> usize {
         let quantized_size = match self.quantization {
             QuantizationType::None => 0,
             QuantizationType::SQ8 => {
@@ -434,6 +608,37 @@ impl QuantizedStorage {
         quantized_size + original_size
     }
 
+    /// Approximate bytes used by the ID mapping
+    pub fn id_bytes(&self) -> usize {
+        self.ids.read().memory_usage()
+    }
+
+    /// Approximate bytes used by metadata
+    pub fn metadata_bytes(&self) -> usize {
+        self.metadata.read().memory_usage() + self.filter_cache.read().memory_usage()
+    }
+
+    /// The `n` records with the largest metadata, largest first
+    pub fn largest_payloads(&self, n: usize) -> Vec<PayloadSize> {
+        let ids = self.ids.read();
+        self.metadata
+            .read()
+            .largest(n)
+            .into_iter()
+            .filter_map(|(internal_id, bytes)| {
+                Some(PayloadSize {
+                    id: ids.external(internal_id)?.clone(),
+                    bytes,
+                })
+            })
+            .collect()
+    }
+
+    /// Uncompressed over stored metadata size, if metadata is compressed
+    pub fn metadata_compression_ratio(&self) -> Option<f64> {
+        self.metadata.read().compression_ratio()
+    }
+
     /// Get compression ratio compared to f32 storage
     pub fn compression_ratio(&self) -> f32 {
         let count = self.len();
@@ -442,7 +647,7 @@ impl QuantizedStorage {
         }
 
         let f32_size = count * self.dimensions * 4;
-        let actual_size = self.memory_usage();
+        let actual_size = self.vector_bytes();
 
         if actual_size == 0 {
             return 1.0;
@@ -463,6 +668,16 @@ impl QuantizedStorage {
 
     /// Create a view of the storage that holds read locks
     pub fn view(&self) -> QuantizedStorageView<'_> {
+        self.view_inner(true)
+    }
+
+    /// Like [`view`](Self::view), but only locks metadata if `filter` needs it,
+    /// so unfiltered searches never touch the payload store
+    pub fn search_view(&self, filter: Option<&crate::filter::Filter>) -> QuantizedStorageView<'_> {
+        self.view_inner(filter.is_some())
+    }
+
+    fn view_inner(&self, with_metadata: bool) -> QuantizedStorageView<'_> {
         let (sq8_vectors, sq8_metadata) = if self.quantization == QuantizationType::SQ8 {
             (
                 Some(self.sq8_vectors.read()),
@@ -493,8 +708,9 @@ impl QuantizedStorage {
             sq8_metadata,
             binary_vectors,
             original_vectors,
-            metadata: Some(self.metadata.read()),
+            metadata: with_metadata.then(|| self.metadata.read()),
             deleted: Some(self.deleted.read()),
+            filter_cache: self.filter_cache.read(),
         }
     }
 
@@ -551,8 +767,12 @@ impl VectorStorageTrait for QuantizedStorage {
                 Some(quantizer.dequantize(&sq8_vectors[start..end], &sq8_metadata[idx]))
             }
             QuantizationType::Binary => {
-                // Binary cannot be easily dequantized to f32 without massive loss
-                None
+                // Only the signs survive, which is all the binary distance needs
+                let quantizer = self.binary_quantizer.as_ref()?;
+                let binary_vectors = self.binary_vectors.read();
+                let start = internal_id.as_usize() * quantizer.byte_size();
+                let stored = binary_vectors.get(start..start + quantizer.byte_size())?;
+                Some(quantizer.dequantize(stored))
             }
         }
     }
@@ -566,6 +786,10 @@ impl VectorStorageTrait for QuantizedStorage {
         self.distance(query, internal_id, metric)
     }
 
+    fn get_metadata(&self, internal_id: InternalId) -> Option<Value> {
+        self.metadata.read().get(internal_id)
+    }
+
     fn is_deleted(&self, internal_id: InternalId) -> bool {
         self.deleted.read().contains(&internal_id)
     }
@@ -589,8 +813,9 @@ pub struct QuantizedStorageView<'a> {
     sq8_metadata: Option<crate::sync::RwLockReadGuard<'a, Vec<SQ8Metadata>>>,
     binary_vectors: Option<crate::sync::RwLockReadGuard<'a, Vec<u8>>>,
     original_vectors: Option<crate::sync::RwLockReadGuard<'a, Option<Vec<f32>>>>,
-    metadata: Option<crate::sync::RwLockReadGuard<'a, HashMap<InternalId, Value>>>,
+    metadata: Option<crate::sync::RwLockReadGuard<'a, MetadataStore>>,
     deleted: Option<crate::sync::RwLockReadGuard<'a, std::collections::HashSet<InternalId>>>,
+    filter_cache: crate::sync::RwLockReadGuard<'a, FilterCache>,
 }
 
 impl<'a> QuantizedStorageView<'a> {
@@ -639,12 +864,6 @@ impl<'a> QuantizedStorageView<'a> {
                 let quantizer = self.binary_quantizer?;
                 let binary_vectors = self.binary_vectors.as_ref()?;
 
-                // Use pre-quantized query if available
-                let query_binary = match quantized_query {
-                    QuantizedQuery::Binary(b) => b,
-                    _ => return None, // Should not happen if logic is correct
-                };
-
                 let byte_size = quantizer.byte_size();
                 let start = internal_id.as_usize() * byte_size;
                 let end = start + byte_size;
@@ -654,7 +873,11 @@ impl<'a> QuantizedStorageView<'a> {
                 }
 
                 let stored = &binary_vectors[start..end];
-                let hamming = quantizer.hamming_distance(query_binary, stored);
+                // Use pre-quantized query if available
+                let hamming = match quantized_query {
+                    QuantizedQuery::Binary(b) => quantizer.hamming_distance(b, stored),
+                    _ => quantizer.hamming_distance_f32(query, stored),
+                };
                 Some(quantizer.hamming_to_cosine(hamming))
             }
         }
@@ -705,7 +928,13 @@ impl<'a> VectorStorageTrait for QuantizedStorageView<'a> {
 
                 Some(quantizer.dequantize(&sq8_vectors[start..end], &sq8_metadata[idx]))
             }
-            QuantizationType::Binary => None,
+            QuantizationType::Binary => {
+                let quantizer = self.binary_quantizer?;
+                let binary_vectors = self.binary_vectors.as_ref()?;
+                let start = internal_id.as_usize() * quantizer.byte_size();
+                let stored = binary_vectors.get(start..start + quantizer.byte_size())?;
+                Some(quantizer.dequantize(stored))
+            }
         }
     }
 
@@ -725,9 +954,18 @@ impl<'a> VectorStorageTrait for QuantizedStorageView<'a> {
                 return None;
             }
         }
-        self.metadata
-            .as_ref()
-            .and_then(|m| m.get(&internal_id).cloned())
+        self.metadata.as_ref().and_then(|m| m.get(internal_id))
+    }
+
+    fn filter_bitmap(
+        &self,
+        filter: &crate::filter::Filter,
+    ) -> Option<std::sync::Arc<roaring::RoaringBitmap>> {
+        self.filter_cache.get(filter)
+    }
+
+    fn filter_entry(&self, filter: &crate::filter::Filter) -> Option<InternalId> {
+        self.filter_cache.entry_point(filter)
     }
 
     fn is_deleted(&self, internal_id: InternalId) -> bool {
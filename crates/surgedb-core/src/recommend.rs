@@ -0,0 +1,158 @@
+//! Recommendations from example records
+//!
+//! A recommendation looks for records like the ones a caller liked (positive
+//! examples) and unlike the ones they didn't (negative examples), given by
+//! ID, so clients don't fetch and combine vectors themselves. The examples
+//! themselves are never recommended.
+
+use crate::distance::DistanceMetric;
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+/// How the examples are turned into results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecommendStrategy {
+    /// One search for `avg(positive) + (avg(positive) - avg(negative))`;
+    /// distances are to that target
+    #[default]
+    AverageVector,
+    /// A search near each positive example, then every candidate ranked by
+    /// its closest example. Candidates closer to a positive than to any
+    /// negative come first, nearest first; distances are to the closest
+    /// positive.
+    BestScore,
+}
+
+/// Examples a recommendation starts from, by record ID
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Recommend {
+    pub positive: Vec<String>,
+    #[serde(default)]
+    pub negative: Vec<String>,
+    #[serde(default)]
+    pub strategy: RecommendStrategy,
+}
+
+impl Recommend {
+    pub fn validate(&self) -> Result<()> {
+        if self.positive.is_empty() {
+            return Err(Error::InvalidConfig(
+                "A recommendation needs at least one positive example".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// IDs of every example, positive or negative
+    pub fn examples(&self) -> impl Iterator<Item = &str> {
+        self.positive
+            .iter()
+            .chain(&self.negative)
+            .map(String::as_str)
+    }
+}
+
+/// Query vector of [`RecommendStrategy::AverageVector`]
+pub(crate) fn average_target(positive: &[Vec<f32>], negative: &[Vec<f32>]) -> Vec<f32> {
+    let towards = mean(positive);
+    if negative.is_empty() {
+        return towards;
+    }
+    let away = mean(negative);
+    towards
+        .iter()
+        .zip(&away)
+        .map(|(t, a)| t + (t - a))
+        .collect()
+}
+
+fn mean(vectors: &[Vec<f32>]) -> Vec<f32> {
+    let mut sum = vec![0.0; vectors.first().map_or(0, Vec::len)];
+    for vector in vectors {
+        for (s, v) in sum.iter_mut().zip(vector) {
+            *s += v;
+        }
+    }
+    let n = vectors.len().max(1) as f32;
+    sum.iter().map(|s| s / n).collect()
+}
+
+/// The `k` best `candidates` under [`RecommendStrategy::BestScore`], each
+/// with its distance to the closest positive example
+///
+/// Candidates nearer a negative example than every positive one follow the
+/// others, furthest from the negatives first.
+pub(crate) fn rank_best_score<T>(
+    candidates: Vec<(T, Vec<f32>)>,
+    positive: &[Vec<f32>],
+    negative: &[Vec<f32>],
+    metric: DistanceMetric,
+    k: usize,
+) -> Vec<(T, f32)> {
+    let closest = |vector: &[f32], examples: &[Vec<f32>]| {
+        examples
+            .iter()
+            .map(|e| metric.distance(vector, e))
+            .fold(f32::INFINITY, f32::min)
+    };
+    let mut ranked: Vec<(T, f32, Option<f32>)> = candidates
+        .into_iter()
+        .map(|(item, vector)| {
+            let to_positive = closest(&vector, positive);
+            let to_negative = closest(&vector, negative);
+            let rejected = (to_negative < to_positive).then_some(to_negative);
+            (item, to_positive, rejected)
+        })
+        .collect();
+    ranked.sort_by(|a, b| match (a.2, b.2) {
+        (None, None) => a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal),
+        (None, Some(_)) => Ordering::Less,
+        (Some(_), None) => Ordering::Greater,
+        (Some(a), Some(b)) => b.partial_cmp(&a).unwrap_or(Ordering::Equal),
+    });
+    ranked
+        .into_iter()
+        .take(k)
+        .map(|(item, distance, _)| (item, distance))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_average_target_moves_away_from_negatives() {
+        let positive = vec![vec![1.0, 0.0], vec![3.0, 0.0]];
+        assert_eq!(average_target(&positive, &[]), vec![2.0, 0.0]);
+        let negative = vec![vec![2.0, 1.0]];
+        assert_eq!(average_target(&positive, &negative), vec![2.0, -1.0]);
+    }
+
+    #[test]
+    fn test_best_score_ranks_rejected_last() {
+        let positive = vec![vec![0.0, 0.0], vec![10.0, 0.0]];
+        let negative = vec![vec![5.0, 0.0]];
+        let candidates = vec![
+            ("near_negative", vec![5.0, 1.0]),
+            ("near_second", vec![9.0, 0.0]),
+            ("near_first", vec![0.5, 0.0]),
+            ("on_negative", vec![5.0, 0.0]),
+        ];
+        let ranked = rank_best_score(
+            candidates,
+            &positive,
+            &negative,
+            DistanceMetric::Euclidean,
+            10,
+        );
+        let order: Vec<&str> = ranked.iter().map(|(id, _)| *id).collect();
+        assert_eq!(
+            order,
+            vec!["near_first", "near_second", "near_negative", "on_negative"]
+        );
+        assert_eq!(ranked[1].1, 1.0);
+    }
+}
@@ -0,0 +1,275 @@
+//! Progress of database recovery at startup
+//!
+//! Recovery runs in two phases. First every collection loads its latest
+//! snapshot, then the WAL entries logged after it are replayed. Collections
+//! recover independently of each other, so with the `parallel` feature both
+//! phases run across collections in parallel; entries of one WAL are always
+//! replayed in order. Once the snapshots are loaded, collections can serve
+//! reads while their WAL tails replay, but writes have to wait for
+//! [`RecoveryPhase::Ready`].
+//!
+//! Before replay each WAL is scanned for damaged records. A record torn by a
+//! crash at the end of the log is always cut off; damage further in is
+//! handled as the [`RecoveryMode`] says.
+
+use crate::sync::RwLock;
+#[cfg(feature = "persistence")]
+use crate::wal::WalRepair;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "persistence")]
+use std::time::Instant;
+#[cfg(feature = "persistence")]
+use tracing::info;
+
+/// What to do with damaged WAL records found before the end of the log
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecoveryMode {
+    /// Refuse to open the collection
+    Strict,
+    /// Keep the records before the first damaged one and drop the rest,
+    /// so no write is replayed without the ones logged before it
+    #[default]
+    Repair,
+    /// Drop only the damaged records and replay everything else
+    Skip,
+}
+
+impl RecoveryMode {
+    /// Parse a mode name, ignoring case
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "strict" => Some(Self::Strict),
+            "repair" => Some(Self::Repair),
+            "skip" => Some(Self::Skip),
+            _ => None,
+        }
+    }
+}
+
+/// Most problems an [`IntegrityReport`] lists; the rest are only counted
+pub const MAX_REPORTED_PROBLEMS: usize = 100;
+
+/// Whether a collection's IDs, vectors and graph agree with each other
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct IntegrityReport {
+    /// Live records checked
+    pub vectors: usize,
+    /// Nodes of the HNSW graph, deleted ones included; zero for other indexes
+    pub graph_nodes: usize,
+    /// The first [`MAX_REPORTED_PROBLEMS`] problems found
+    pub problems: Vec<String>,
+    /// Problems found in all
+    pub problem_count: usize,
+}
+
+impl IntegrityReport {
+    pub fn is_ok(&self) -> bool {
+        self.problem_count == 0
+    }
+
+    pub(crate) fn problem(&mut self, problem: String) {
+        self.problem_count += 1;
+        if self.problems.len() < MAX_REPORTED_PROBLEMS {
+            self.problems.push(problem);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecoveryPhase {
+    /// Collections are loading their snapshots; nothing is served yet
+    LoadingSnapshots,
+    /// Snapshots are loaded and WAL tails are replaying; reads may be stale
+    ReplayingWal,
+    /// Fully recovered
+    Ready,
+    /// Recovery stopped on an error; collections recovered so far stay readable
+    Failed,
+}
+
+/// Point-in-time view of recovery progress
+#[derive(Debug, Clone, Serialize)]
+pub struct RecoveryStatus {
+    pub phase: RecoveryPhase,
+    pub collections_total: usize,
+    /// Collections whose snapshot is loaded
+    pub collections_loaded: usize,
+    pub wal_entries_total: u64,
+    pub wal_entries_replayed: u64,
+    /// WAL records dropped as damaged, or for following a damaged one
+    pub wal_records_dropped: u64,
+    /// Bytes of torn records cut off the end of WALs
+    pub wal_bytes_truncated: u64,
+    /// Share of WAL entries replayed, 0-100
+    pub percent: f64,
+    /// Estimated seconds until the WAL is replayed, from the replay rate so far
+    pub eta_secs: Option<f64>,
+    pub error: Option<String>,
+}
+
+impl RecoveryStatus {
+    /// Whether writes can be accepted
+    pub fn is_ready(&self) -> bool {
+        self.phase == RecoveryPhase::Ready
+    }
+
+    /// Whether collections can be read, possibly without their full WAL tail
+    pub fn is_readable(&self) -> bool {
+        self.phase != RecoveryPhase::LoadingSnapshots
+    }
+}
+
+struct State {
+    phase: RecoveryPhase,
+    collections_total: usize,
+    collections_loaded: usize,
+    wal_entries_total: u64,
+    wal_entries_replayed: u64,
+    wal_records_dropped: u64,
+    wal_bytes_truncated: u64,
+    error: Option<String>,
+    #[cfg(feature = "persistence")]
+    replay_started: Option<Instant>,
+    #[cfg(feature = "persistence")]
+    eta_secs: Option<f64>,
+    /// Last logged tenth of the replay
+    #[cfg(feature = "persistence")]
+    logged_decile: u64,
+}
+
+pub(crate) struct RecoveryProgress {
+    state: RwLock<State>,
+}
+
+impl Default for RecoveryProgress {
+    fn default() -> Self {
+        Self {
+            state: RwLock::new(State {
+                phase: RecoveryPhase::Ready,
+                collections_total: 0,
+                collections_loaded: 0,
+                wal_entries_total: 0,
+                wal_entries_replayed: 0,
+                wal_records_dropped: 0,
+                wal_bytes_truncated: 0,
+                error: None,
+                #[cfg(feature = "persistence")]
+                replay_started: None,
+                #[cfg(feature = "persistence")]
+                eta_secs: None,
+                #[cfg(feature = "persistence")]
+                logged_decile: 0,
+            }),
+        }
+    }
+}
+
+impl RecoveryProgress {
+    pub fn status(&self) -> RecoveryStatus {
+        let state = self.state.read();
+        let percent = if state.wal_entries_total == 0 {
+            if state.phase == RecoveryPhase::LoadingSnapshots {
+                0.0
+            } else {
+                100.0
+            }
+        } else {
+            state.wal_entries_replayed as f64 * 100.0 / state.wal_entries_total as f64
+        };
+        #[cfg(feature = "persistence")]
+        let eta_secs = state.eta_secs;
+        #[cfg(not(feature = "persistence"))]
+        let eta_secs = None;
+
+        RecoveryStatus {
+            phase: state.phase,
+            collections_total: state.collections_total,
+            collections_loaded: state.collections_loaded,
+            wal_entries_total: state.wal_entries_total,
+            wal_entries_replayed: state.wal_entries_replayed,
+            wal_records_dropped: state.wal_records_dropped,
+            wal_bytes_truncated: state.wal_bytes_truncated,
+            percent,
+            eta_secs,
+            error: state.error.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "persistence")]
+impl RecoveryProgress {
+    pub fn begin(&self, collections: usize) {
+        let mut state = self.state.write();
+        state.phase = RecoveryPhase::LoadingSnapshots;
+        state.collections_total = collections;
+    }
+
+    /// A collection loaded its snapshot and has `pending` WAL entries to replay
+    pub fn collection_loaded(&self, pending: usize) {
+        let mut state = self.state.write();
+        state.collections_loaded += 1;
+        state.wal_entries_total += pending as u64;
+    }
+
+    /// A collection's WAL had damaged records removed before replay
+    pub fn wal_repaired(&self, repair: WalRepair) {
+        let mut state = self.state.write();
+        state.wal_records_dropped += (repair.damaged_records + repair.dropped_records) as u64;
+        state.wal_bytes_truncated += repair.torn_bytes;
+    }
+
+    pub fn begin_replay(&self) {
+        let mut state = self.state.write();
+        state.phase = RecoveryPhase::ReplayingWal;
+        state.replay_started = Some(Instant::now());
+        if state.wal_entries_total > 0 {
+            info!(
+                "Snapshots loaded for {} collections, replaying {} WAL entries",
+                state.collections_loaded, state.wal_entries_total
+            );
+        }
+    }
+
+    pub fn replayed(&self, entries: usize) {
+        let mut state = self.state.write();
+        state.wal_entries_replayed += entries as u64;
+        let (done, total) = (state.wal_entries_replayed, state.wal_entries_total);
+        if total == 0 {
+            return;
+        }
+
+        let elapsed = state
+            .replay_started
+            .map_or(0.0, |started| started.elapsed().as_secs_f64());
+        if done > 0 {
+            state.eta_secs = Some(elapsed / done as f64 * total.saturating_sub(done) as f64);
+        }
+
+        let decile = done * 10 / total;
+        if decile > state.logged_decile {
+            state.logged_decile = decile;
+            info!(
+                "WAL replay {:.0}% ({}/{} entries), ETA {:.0}s",
+                done as f64 * 100.0 / total as f64,
+                done,
+                total,
+                state.eta_secs.unwrap_or(0.0)
+            );
+        }
+    }
+
+    pub fn finish(&self) {
+        let mut state = self.state.write();
+        state.phase = RecoveryPhase::Ready;
+        state.eta_secs = None;
+    }
+
+    pub fn fail(&self, error: String) {
+        let mut state = self.state.write();
+        state.phase = RecoveryPhase::Failed;
+        state.eta_secs = None;
+        state.error = Some(error);
+    }
+}
@@ -0,0 +1,151 @@
+//! Iterator-based scans over every record of a collection
+//!
+//! A [`Scan`] pages through records with a [`ListCursor`] and fetches each
+//! page only when the previous one is used up, so scanning a large collection
+//! holds one page in memory. Between pages no lock is held, so writes can go
+//! on during a scan. As with [`Collection::list_page`](crate::db::Collection::list_page),
+//! every record that exists when the scan starts and isn't deleted during it
+//! is yielded exactly once, and records inserted after the start are not.
+//!
+//! [`Collection::scroll`](crate::db::Collection::scroll) serves the same
+//! listing one [`ScrollPage`] per call, for callers such as the server that
+//! hand the cursor back to a client between pages.
+
+use crate::error::Result;
+use crate::filter::Filter;
+use crate::types::{ListCursor, ListPage, VectorId};
+use serde_json::Value;
+use std::collections::VecDeque;
+
+/// Records fetched per page unless [`Scan::batch_size`] says otherwise
+pub const DEFAULT_SCAN_BATCH: usize = 256;
+
+/// Most records a filtered scroll examines for one page
+///
+/// Bounds the work of a page whose filter matches few records; such a page
+/// can come back short, or empty, with a cursor to continue from.
+pub const MAX_SCROLL_SCANNED: usize = 10_000;
+
+/// A record yielded by a scan: its ID, vector and metadata
+pub type ScanRecord = (VectorId, Vec<f32>, Option<Value>);
+
+/// One page of records with their vectors
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScrollPage {
+    pub records: Vec<ScanRecord>,
+    /// Where the next page starts, or `None` after the last page
+    pub next: Option<ListCursor>,
+}
+
+type PageFn<'a> = Box<dyn FnMut(Option<ListCursor>, usize) -> Result<ListPage> + 'a>;
+type GetFn<'a> = Box<dyn Fn(&str) -> Result<Option<(Vec<f32>, Option<Value>)>> + 'a>;
+
+/// Iterator over a collection's live records, in storage order
+///
+/// Yields an error and stops if the collection is compacted, reopened or
+/// restored mid-scan, since its cursor no longer applies.
+pub struct Scan<'a> {
+    page: PageFn<'a>,
+    get: GetFn<'a>,
+    batch_size: usize,
+    cursor: Option<ListCursor>,
+    buffer: VecDeque<VectorId>,
+    done: bool,
+}
+
+impl<'a> Scan<'a> {
+    /// Scan over `page`, a collection's `list_page`, reading each listed
+    /// record with `get`
+    pub(crate) fn new(
+        page: impl FnMut(Option<ListCursor>, usize) -> Result<ListPage> + 'a,
+        get: impl Fn(&str) -> Result<Option<(Vec<f32>, Option<Value>)>> + 'a,
+    ) -> Self {
+        Self {
+            page: Box::new(page),
+            get: Box::new(get),
+            batch_size: DEFAULT_SCAN_BATCH,
+            cursor: None,
+            buffer: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    /// Fetch up to `batch_size` records per page
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+}
+
+impl Iterator for Scan<'_> {
+    type Item = Result<ScanRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            while let Some(id) = self.buffer.pop_front() {
+                // Skip records deleted since their page was listed
+                match (self.get)(&id.to_string()) {
+                    Ok(Some((vector, metadata))) => return Some(Ok((id, vector, metadata))),
+                    Ok(None) => continue,
+                    Err(e) => {
+                        self.done = true;
+                        self.buffer.clear();
+                        return Some(Err(e));
+                    }
+                }
+            }
+            if self.done {
+                return None;
+            }
+            match (self.page)(self.cursor, self.batch_size) {
+                Ok(page) => {
+                    self.cursor = page.next;
+                    self.done = page.next.is_none();
+                    self.buffer
+                        .extend(page.records.into_iter().map(|(id, _)| id));
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+/// Up to `limit` records from `cursor` on, of those matching `filter`
+///
+/// `page` and `get` are a collection's `list_page` and `get`, called while
+/// the caller holds the collection's lock so the page is read consistently.
+pub(crate) fn scroll(
+    page: impl Fn(Option<ListCursor>, usize) -> Result<ListPage>,
+    get: impl Fn(&str) -> Result<Option<(Vec<f32>, Option<Value>)>>,
+    mut cursor: Option<ListCursor>,
+    limit: usize,
+    filter: Option<&Filter>,
+) -> Result<ScrollPage> {
+    let limit = limit.max(1);
+    let mut records = Vec::new();
+    let mut scanned = 0;
+    loop {
+        // List no more than can still be returned, so the cursor stays exact
+        let listed = page(cursor, limit - records.len())?;
+        scanned += listed.records.len();
+        cursor = listed.next;
+        for (id, metadata) in listed.records {
+            let matches = filter.is_none_or(|f| metadata.as_ref().is_some_and(|m| f.matches(m)));
+            if !matches {
+                continue;
+            }
+            if let Some((vector, metadata)) = get(&id.to_string())? {
+                records.push((id, vector, metadata));
+            }
+        }
+        if cursor.is_none() || records.len() >= limit || scanned >= MAX_SCROLL_SCANNED {
+            return Ok(ScrollPage {
+                records,
+                next: cursor,
+            });
+        }
+    }
+}
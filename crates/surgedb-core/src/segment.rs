@@ -0,0 +1,758 @@
+//! Segmented index: a mutable segment and sealed, immutable ones
+//!
+//! [`IndexKind::Segmented`](crate::IndexKind::Segmented) splits the index of
+//! a collection the way log-structured stores split their data. New vectors go
+//! to the mutable segment, which is searched by exact scan and costs nothing to
+//! insert into. Once it holds [`SEAL_SIZE`] vectors it is sealed: its vectors
+//! are copied out and an HNSW graph is built over them in the background,
+//! while searches keep scanning the sealed segment. A sealed segment never
+//! changes. Whenever [`MERGE_FACTOR`] segments of one level have their graphs,
+//! a background merge replaces them with one segment of the next level,
+//! leaving out the vectors deleted since, so a search visits a number of graphs
+//! logarithmic in the collection size.
+//!
+//! Writes thus hold the collection for an append however large it grows, and
+//! never wait on graph construction; searches query every segment and merge
+//! the results. Sealed segments are encoded once, so a checkpoint only encodes
+//! the mutable segment and the segments sealed or merged since the previous
+//! one.
+//!
+//! Segments keep a copy of their vectors for merging without the collection,
+//! so a segmented collection holds its vectors twice. Without the `parallel`
+//! feature, graphs are built and merged during the write that seals a segment.
+
+use crate::ann::{exact_search, nearest, AnnIndex, IndexStorage};
+use crate::distance::DistanceMetric;
+use crate::error::Result;
+use crate::filter::Filter;
+use crate::hnsw::{HnswConfig, HnswIndex, HnswState};
+use crate::storage::VectorStorageTrait;
+use crate::sync::RwLock;
+use crate::types::{InternalId, SearchUsage};
+use roaring::RoaringBitmap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::sync::{Arc, OnceLock};
+
+/// Vectors the mutable segment holds before it is sealed
+pub const SEAL_SIZE: usize = 4096;
+
+/// Segments of one level merged into one of the next
+pub const MERGE_FACTOR: usize = 4;
+
+/// A sealed segment of a [`SegmentedIndex`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SegmentStats {
+    /// 0 when sealed, one more with every merge
+    pub level: u32,
+    /// Vectors in the segment, including ones deleted since it was sealed
+    pub size: usize,
+    /// Whether its graph is built; until then it is searched by exact scan
+    pub indexed: bool,
+}
+
+/// Vectors sealed together
+struct Segment {
+    /// Tells the segments apart across background jobs
+    serial: u64,
+    level: u32,
+    /// Internal IDs in the collection, ascending; graph nodes are positions
+    /// in this list
+    ids: Vec<InternalId>,
+    /// Vectors of `ids`, back to back
+    vectors: Vec<f32>,
+    /// Set once built in the background
+    graph: OnceLock<HnswIndex>,
+    /// Encoding of the segment with its graph, made at most once
+    encoded: OnceLock<Vec<u8>>,
+}
+
+/// Encoded form of a [`Segment`]
+#[derive(Serialize, Deserialize)]
+struct SegmentState {
+    level: u32,
+    ids: Vec<InternalId>,
+    vectors: Vec<f32>,
+    graph: Option<HnswState>,
+}
+
+impl Segment {
+    fn dimensions(&self) -> usize {
+        self.vectors.len() / self.ids.len().max(1)
+    }
+
+    fn vector(&self, position: usize) -> &[f32] {
+        let dims = self.dimensions();
+        &self.vectors[position * dims..(position + 1) * dims]
+    }
+
+    fn stats(&self) -> SegmentStats {
+        SegmentStats {
+            level: self.level,
+            size: self.ids.len(),
+            indexed: self.graph.get().is_some(),
+        }
+    }
+
+    fn encode(&self) -> Result<Vec<u8>> {
+        let state = SegmentState {
+            level: self.level,
+            ids: self.ids.clone(),
+            vectors: self.vectors.clone(),
+            graph: self.graph.get().map(HnswIndex::get_state),
+        };
+        Ok(bincode::serialize(&state)?)
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.ids.capacity() * std::mem::size_of::<InternalId>()
+            + self.vectors.capacity() * std::mem::size_of::<f32>()
+            + self.graph.get().map_or(0, HnswIndex::memory_usage)
+            + self.encoded.get().map_or(0, Vec::capacity)
+    }
+}
+
+/// Segment vectors by position, which graphs are built over
+struct SegmentVectors<'a>(&'a Segment);
+
+impl VectorStorageTrait for SegmentVectors<'_> {
+    fn get_vector_data(&self, internal_id: InternalId) -> Option<Vec<f32>> {
+        Some(self.0.vector(internal_id.as_usize()).to_vec())
+    }
+
+    fn distance(
+        &self,
+        internal_id: InternalId,
+        query: &[f32],
+        metric: DistanceMetric,
+    ) -> Option<f32> {
+        Some(metric.distance(self.0.vector(internal_id.as_usize()), query))
+    }
+}
+
+/// A segment searched with the collection's metadata and deletions
+struct SegmentView<'a> {
+    segment: &'a Segment,
+    storage: &'a dyn IndexStorage,
+}
+
+impl VectorStorageTrait for SegmentView<'_> {
+    fn get_vector_data(&self, internal_id: InternalId) -> Option<Vec<f32>> {
+        SegmentVectors(self.segment).get_vector_data(internal_id)
+    }
+
+    fn distance(
+        &self,
+        internal_id: InternalId,
+        query: &[f32],
+        metric: DistanceMetric,
+    ) -> Option<f32> {
+        SegmentVectors(self.segment).distance(internal_id, query, metric)
+    }
+
+    fn get_metadata(&self, internal_id: InternalId) -> Option<Value> {
+        self.storage
+            .get_metadata(self.segment.ids[internal_id.as_usize()])
+    }
+
+    fn filter_bitmap(&self, filter: &Filter) -> Option<Arc<RoaringBitmap>> {
+        let matching = self.storage.filter_bitmap(filter)?;
+        let ids = &self.segment.ids;
+        let (first, last) = (ids.first()?.as_u32(), ids.last()?.as_u32());
+        let positions = matching
+            .range(first..=last)
+            .filter_map(|id| ids.binary_search_by_key(&id, |i| i.as_u32()).ok())
+            .map(|position| position as u32);
+        Some(Arc::new(positions.collect()))
+    }
+
+    fn is_deleted(&self, internal_id: InternalId) -> bool {
+        self.storage
+            .is_deleted(self.segment.ids[internal_id.as_usize()])
+    }
+}
+
+/// Segments and the work pending on them
+struct Segments {
+    /// Vectors not sealed yet
+    mutable: RoaringBitmap,
+    /// Oldest first
+    sealed: Vec<Arc<Segment>>,
+    /// Sealed vectors deleted since, left out when their segment is merged
+    removed: RoaringBitmap,
+    /// Segments a background job is building or merging
+    busy: HashSet<u64>,
+    next_serial: u64,
+    /// Bumped when the contents are replaced, so merges of the old
+    /// contents are dropped
+    generation: u64,
+}
+
+/// Background work on sealed segments
+enum Job {
+    Build(Arc<Segment>),
+    Merge {
+        inputs: Vec<Arc<Segment>>,
+        removed: RoaringBitmap,
+        serial: u64,
+        generation: u64,
+    },
+}
+
+/// What background jobs share with the index
+struct Shared {
+    config: HnswConfig,
+    metric: DistanceMetric,
+    seal_size: usize,
+    segments: RwLock<Segments>,
+}
+
+/// HNSW graphs over sealed segments, built and merged in the background
+pub struct SegmentedIndex {
+    shared: Arc<Shared>,
+}
+
+impl SegmentedIndex {
+    pub fn new(config: HnswConfig, metric: DistanceMetric) -> Self {
+        Self::with_seal_size(config, metric, SEAL_SIZE)
+    }
+
+    fn with_seal_size(config: HnswConfig, metric: DistanceMetric, seal_size: usize) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                config,
+                metric,
+                seal_size,
+                segments: RwLock::new(Segments {
+                    mutable: RoaringBitmap::new(),
+                    sealed: Vec::new(),
+                    removed: RoaringBitmap::new(),
+                    busy: HashSet::new(),
+                    next_serial: 0,
+                    generation: 0,
+                }),
+            }),
+        }
+    }
+
+    /// Sealed segments, oldest first
+    pub fn segments(&self) -> Vec<SegmentStats> {
+        let segments = self.shared.segments.read();
+        segments.sealed.iter().map(|s| s.stats()).collect()
+    }
+
+    /// Vectors in the mutable segment
+    pub fn unsealed(&self) -> usize {
+        self.shared.segments.read().mutable.len() as usize
+    }
+
+    /// Add vectors to the mutable segment, sealing it whenever it is full
+    fn add(
+        &self,
+        internal_ids: impl Iterator<Item = InternalId>,
+        storage: &dyn IndexStorage,
+    ) -> Result<()> {
+        let jobs = {
+            let mut segments = self.shared.segments.write();
+            let mut sealed = false;
+            for internal_id in internal_ids {
+                segments.mutable.insert(internal_id.as_u32());
+                if segments.mutable.len() as usize >= self.shared.seal_size {
+                    segments.seal(storage);
+                    sealed = true;
+                }
+            }
+            if sealed {
+                segments.due_jobs()
+            } else {
+                Vec::new()
+            }
+        };
+        start(&self.shared, jobs);
+        Ok(())
+    }
+}
+
+impl Segments {
+    /// Copy the mutable segment into a new sealed one
+    fn seal(&mut self, storage: &dyn IndexStorage) {
+        let mutable = std::mem::take(&mut self.mutable);
+        let mut ids = Vec::with_capacity(mutable.len() as usize);
+        let mut vectors = Vec::new();
+        for internal_id in mutable.iter().map(InternalId) {
+            if storage.is_deleted(internal_id) {
+                continue;
+            }
+            if let Some(vector) = storage.get_vector_data(internal_id) {
+                ids.push(internal_id);
+                vectors.extend(vector);
+            }
+        }
+        if ids.is_empty() {
+            return;
+        }
+        let serial = self.serial();
+        self.sealed.push(Arc::new(Segment {
+            serial,
+            level: 0,
+            ids,
+            vectors,
+            graph: OnceLock::new(),
+            encoded: OnceLock::new(),
+        }));
+    }
+
+    fn serial(&mut self) -> u64 {
+        self.next_serial += 1;
+        self.next_serial
+    }
+
+    /// Claim the segments due for building or merging
+    fn due_jobs(&mut self) -> Vec<Job> {
+        let mut jobs = Vec::new();
+        for segment in &self.sealed {
+            if segment.graph.get().is_none() && self.busy.insert(segment.serial) {
+                jobs.push(Job::Build(segment.clone()));
+            }
+        }
+
+        let mut levels: Vec<u32> = self.sealed.iter().map(|s| s.level).collect();
+        levels.sort_unstable();
+        levels.dedup();
+        for level in levels {
+            let ready: Vec<Arc<Segment>> = self
+                .sealed
+                .iter()
+                .filter(|s| s.level == level && s.graph.get().is_some())
+                .filter(|s| !self.busy.contains(&s.serial))
+                .take(MERGE_FACTOR)
+                .cloned()
+                .collect();
+            if ready.len() < MERGE_FACTOR {
+                continue;
+            }
+            let mut removed = RoaringBitmap::new();
+            for segment in &ready {
+                self.busy.insert(segment.serial);
+                removed.extend(
+                    segment
+                        .ids
+                        .iter()
+                        .map(|id| id.as_u32())
+                        .filter(|id| self.removed.contains(*id)),
+                );
+            }
+            jobs.push(Job::Merge {
+                inputs: ready,
+                removed,
+                serial: self.serial(),
+                generation: self.generation,
+            });
+        }
+        jobs
+    }
+}
+
+/// Run `jobs` in the background
+fn start(shared: &Arc<Shared>, jobs: Vec<Job>) {
+    for job in jobs {
+        let shared = shared.clone();
+        #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+        rayon::spawn(move || run(&shared, job));
+        #[cfg(any(not(feature = "parallel"), target_arch = "wasm32"))]
+        run(&shared, job);
+    }
+}
+
+/// Run `job`, then start the jobs it made due
+fn run(shared: &Arc<Shared>, job: Job) {
+    let jobs = match job {
+        Job::Build(segment) => {
+            // Segments are only built here, and claimed before
+            if let Ok(graph) = build_graph(shared, &segment) {
+                let _ = segment.graph.set(graph);
+            }
+            let mut segments = shared.segments.write();
+            segments.busy.remove(&segment.serial);
+            segments.due_jobs()
+        }
+        Job::Merge {
+            inputs,
+            removed,
+            serial,
+            generation,
+        } => {
+            let merged = merge(shared, &inputs, &removed, serial);
+            let mut segments = shared.segments.write();
+            for input in &inputs {
+                segments.busy.remove(&input.serial);
+            }
+            if segments.generation == generation {
+                let serials: HashSet<u64> = inputs.iter().map(|s| s.serial).collect();
+                segments.sealed.retain(|s| !serials.contains(&s.serial));
+                if let Some(merged) = merged {
+                    segments.sealed.push(Arc::new(merged));
+                }
+                segments.removed -= removed;
+            }
+            segments.due_jobs()
+        }
+    };
+    start(shared, jobs);
+}
+
+fn build_graph(shared: &Shared, segment: &Segment) -> Result<HnswIndex> {
+    let graph = HnswIndex::new(shared.config.clone(), shared.metric);
+    let items: Vec<(InternalId, &[f32])> = (0..segment.ids.len())
+        .map(|position| (InternalId(position as u32), segment.vector(position)))
+        .collect();
+    graph.insert_batch(&items, &SegmentVectors(segment))?;
+    Ok(graph)
+}
+
+/// One segment of the next level holding the live vectors of `inputs`,
+/// with its graph built
+fn merge(
+    shared: &Shared,
+    inputs: &[Arc<Segment>],
+    removed: &RoaringBitmap,
+    serial: u64,
+) -> Option<Segment> {
+    let mut members: Vec<(InternalId, &[f32])> = inputs
+        .iter()
+        .flat_map(|segment| {
+            (0..segment.ids.len())
+                .map(move |position| (segment.ids[position], segment.vector(position)))
+        })
+        .filter(|(id, _)| !removed.contains(id.as_u32()))
+        .collect();
+    members.sort_unstable_by_key(|(id, _)| id.as_u32());
+
+    let segment = Segment {
+        serial,
+        level: inputs.iter().map(|s| s.level).max().unwrap_or(0) + 1,
+        ids: members.iter().map(|(id, _)| *id).collect(),
+        vectors: members
+            .iter()
+            .flat_map(|(_, v)| v.iter().copied())
+            .collect(),
+        graph: OnceLock::new(),
+        encoded: OnceLock::new(),
+    };
+    if segment.ids.is_empty() {
+        return None;
+    }
+    let graph = build_graph(shared, &segment).ok()?;
+    let _ = segment.graph.set(graph);
+    Some(segment)
+}
+
+/// Encoded form of a [`SegmentedIndex`]
+#[derive(Serialize)]
+struct IndexStateRef<'a> {
+    mutable: Vec<u32>,
+    removed: Vec<u32>,
+    segments: Vec<&'a [u8]>,
+}
+
+#[derive(Deserialize)]
+struct IndexState {
+    mutable: Vec<u32>,
+    removed: Vec<u32>,
+    segments: Vec<Vec<u8>>,
+}
+
+impl AnnIndex for SegmentedIndex {
+    fn insert(
+        &self,
+        internal_id: InternalId,
+        _vector: &[f32],
+        storage: &dyn IndexStorage,
+    ) -> Result<()> {
+        self.add(std::iter::once(internal_id), storage)
+    }
+
+    fn insert_batch(
+        &self,
+        items: &[(InternalId, &[f32])],
+        storage: &dyn IndexStorage,
+    ) -> Result<()> {
+        self.add(items.iter().map(|(id, _)| *id), storage)
+    }
+
+    /// Unsealed vectors are dropped; sealed ones are skipped in searches and
+    /// dropped when their segment is merged
+    fn remove(&self, internal_id: InternalId) {
+        let mut segments = self.shared.segments.write();
+        if !segments.mutable.remove(internal_id.as_u32()) {
+            segments.removed.insert(internal_id.as_u32());
+        }
+    }
+
+    fn search(
+        &self,
+        query: &[f32],
+        k: usize,
+        ef_search: Option<usize>,
+        storage: &dyn IndexStorage,
+        filter: Option<&Filter>,
+        usage: &mut SearchUsage,
+    ) -> Result<Vec<(InternalId, f32)>> {
+        self.search_within(query, k, ef_search, None, storage, filter, usage)
+    }
+
+    fn search_within(
+        &self,
+        query: &[f32],
+        k: usize,
+        ef_search: Option<usize>,
+        max_distance: Option<f32>,
+        storage: &dyn IndexStorage,
+        filter: Option<&Filter>,
+        usage: &mut SearchUsage,
+    ) -> Result<Vec<(InternalId, f32)>> {
+        let (mutable, sealed) = {
+            let segments = self.shared.segments.read();
+            (segments.mutable.clone(), segments.sealed.clone())
+        };
+        let metric = self.shared.metric;
+
+        // Vectors without a graph yet are scanned
+        let unindexed = sealed
+            .iter()
+            .filter(|s| s.graph.get().is_none())
+            .flat_map(|s| s.ids.iter().copied());
+        let scanned = mutable.iter().map(InternalId).chain(unindexed);
+        let mut hits = exact_search(scanned, query, k, metric, storage, filter, usage);
+
+        for segment in &sealed {
+            let Some(graph) = segment.graph.get() else {
+                continue;
+            };
+            let view = SegmentView { segment, storage };
+            let found =
+                graph.search_within(query, k, ef_search, max_distance, &view, filter, usage)?;
+            hits.extend(
+                found
+                    .into_iter()
+                    .map(|(position, distance)| (segment.ids[position.as_usize()], distance)),
+            );
+        }
+        nearest(&mut hits, k);
+        Ok(crate::within(hits, max_distance))
+    }
+
+    fn len(&self) -> usize {
+        let segments = self.shared.segments.read();
+        segments.mutable.len() as usize + segments.sealed.iter().map(|s| s.ids.len()).sum::<usize>()
+    }
+
+    fn memory_usage(&self) -> usize {
+        let segments = self.shared.segments.read();
+        segments.mutable.serialized_size()
+            + segments.removed.serialized_size()
+            + segments
+                .sealed
+                .iter()
+                .map(|s| s.memory_usage())
+                .sum::<usize>()
+    }
+
+    /// Segments with their graph are encoded once and the encoding reused
+    fn serialize(&self) -> Result<Vec<u8>> {
+        let (mutable, removed, sealed) = {
+            let segments = self.shared.segments.read();
+            (
+                segments.mutable.iter().collect(),
+                segments.removed.iter().collect(),
+                segments.sealed.clone(),
+            )
+        };
+        let mut fresh = Vec::new();
+        for segment in &sealed {
+            if segment.graph.get().is_none() {
+                fresh.push(segment.encode()?);
+            } else if segment.encoded.get().is_none() {
+                let _ = segment.encoded.set(segment.encode()?);
+            }
+        }
+        let mut fresh = fresh.iter();
+        let segments = sealed
+            .iter()
+            .filter_map(|segment| match segment.encoded.get() {
+                Some(encoded) => Some(encoded.as_slice()),
+                None => fresh.next().map(Vec::as_slice),
+            })
+            .collect();
+        Ok(bincode::serialize(&IndexStateRef {
+            mutable,
+            removed,
+            segments,
+        })?)
+    }
+
+    fn deserialize(&self, bytes: &[u8]) -> Result<()> {
+        let state: IndexState = bincode::deserialize(bytes)?;
+        let mut decoded = Vec::with_capacity(state.segments.len());
+        for bytes in state.segments {
+            let segment: SegmentState = bincode::deserialize(&bytes)?;
+            decoded.push((segment, bytes));
+        }
+        let jobs = {
+            let mut segments = self.shared.segments.write();
+            segments.mutable = state.mutable.into_iter().collect();
+            segments.removed = state.removed.into_iter().collect();
+            segments.generation += 1;
+            let mut sealed = Vec::with_capacity(decoded.len());
+            for (state, bytes) in decoded {
+                let segment = Segment {
+                    serial: segments.serial(),
+                    level: state.level,
+                    ids: state.ids,
+                    vectors: state.vectors,
+                    graph: OnceLock::new(),
+                    encoded: OnceLock::new(),
+                };
+                if let Some(graph_state) = state.graph {
+                    let graph = HnswIndex::new(self.shared.config.clone(), self.shared.metric);
+                    graph.load_state(graph_state);
+                    let _ = segment.graph.set(graph);
+                    let _ = segment.encoded.set(bytes);
+                }
+                sealed.push(Arc::new(segment));
+            }
+            segments.sealed = sealed;
+            segments.due_jobs()
+        };
+        start(&self.shared, jobs);
+        Ok(())
+    }
+
+    fn fresh(&self) -> Box<dyn AnnIndex> {
+        Box::new(SegmentedIndex::with_seal_size(
+            self.shared.config.clone(),
+            self.shared.metric,
+            self.shared.seal_size,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::VectorStorage;
+    use crate::types::VectorId;
+    use std::time::{Duration, Instant};
+
+    const SEAL: usize = 256;
+
+    fn index() -> SegmentedIndex {
+        SegmentedIndex::with_seal_size(HnswConfig::default(), DistanceMetric::Euclidean, SEAL)
+    }
+
+    /// Wait for the background jobs to finish
+    fn settle(index: &SegmentedIndex) {
+        let start = Instant::now();
+        while !index.shared.segments.read().busy.is_empty() {
+            assert!(start.elapsed() < Duration::from_secs(60));
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// Vector `v{i}` sits at `(i % 100, i / 100)`
+    fn fill(storage: &VectorStorage, index: &SegmentedIndex, range: std::ops::Range<usize>) {
+        for i in range {
+            let vector = [(i % 100) as f32, (i / 100) as f32];
+            let internal_id = storage
+                .insert(VectorId::from(format!("v{i}")), &vector, None)
+                .unwrap();
+            index.insert(internal_id, &vector, storage).unwrap();
+        }
+    }
+
+    fn nearest_id(index: &SegmentedIndex, storage: &VectorStorage, query: [f32; 2]) -> usize {
+        let mut usage = SearchUsage::default();
+        let hits = index
+            .search(&query, 1, Some(64), storage, None, &mut usage)
+            .unwrap();
+        storage.get_external_id(hits[0].0).unwrap().as_str()[1..]
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_segments_seal_and_merge() {
+        let storage = VectorStorage::new(2);
+        let index = index();
+        let total = SEAL * MERGE_FACTOR + 10;
+        fill(&storage, &index, 0..total);
+        settle(&index);
+
+        assert_eq!(
+            index.segments(),
+            vec![SegmentStats {
+                level: 1,
+                size: SEAL * MERGE_FACTOR,
+                indexed: true,
+            }]
+        );
+        assert_eq!(index.unsealed(), 10);
+        assert_eq!(index.len(), total);
+
+        // In the merged segment and in the mutable one
+        assert_eq!(nearest_id(&index, &storage, [37.0, 5.0]), 537);
+        assert_eq!(nearest_id(&index, &storage, [25.0, 10.0]), 1025);
+    }
+
+    #[test]
+    fn test_merge_drops_removed_vectors() {
+        let storage = VectorStorage::new(2);
+        let index = index();
+        fill(&storage, &index, 0..SEAL);
+        settle(&index);
+        let gone = VectorId::from("v137");
+        let internal_id = storage.get_internal_id(&gone).unwrap();
+        storage.delete(&gone).unwrap();
+        index.remove(internal_id);
+        assert_ne!(nearest_id(&index, &storage, [37.0, 1.0]), 137);
+
+        fill(&storage, &index, SEAL..SEAL * MERGE_FACTOR);
+        settle(&index);
+        assert_eq!(index.segments()[0].size, SEAL * MERGE_FACTOR - 1);
+        assert!(index.shared.segments.read().removed.is_empty());
+    }
+
+    #[test]
+    fn test_serialize_round_trip() {
+        let storage = VectorStorage::new(2);
+        let index = index();
+        fill(&storage, &index, 0..SEAL + 5);
+        settle(&index);
+        let bytes = index.serialize().unwrap();
+        // The sealed segment's encoding is kept for the next time
+        assert!(index.shared.segments.read().sealed[0]
+            .encoded
+            .get()
+            .is_some());
+        assert_eq!(index.serialize().unwrap(), bytes);
+
+        let restored = index.fresh();
+        restored.deserialize(&bytes).unwrap();
+        assert_eq!(restored.len(), SEAL + 5);
+        assert_eq!(
+            restored
+                .search(
+                    &[37.0, 1.0],
+                    1,
+                    None,
+                    &storage,
+                    None,
+                    &mut SearchUsage::default()
+                )
+                .unwrap()[0]
+                .0,
+            storage.get_internal_id(&VectorId::from("v137")).unwrap()
+        );
+    }
+}
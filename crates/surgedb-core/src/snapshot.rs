@@ -3,21 +3,36 @@
 //! Snapshots contain the complete database state at a point in time.
 //! Combined with WAL, they enable fast recovery without replaying the entire history.
 
+use crate::ann::AnnIndex;
 use crate::error::{Error, Result};
 use crate::hnsw::HnswState;
-use crate::types::VectorId;
+use crate::named::NamedVectors;
+use crate::quantized_storage::QuantizedStorage;
+use crate::sparse::{SparseStore, SparseVector};
+use crate::storage::{VectorStorage, VectorStorageTrait};
+use crate::types::{InternalId, VectorId};
+use crate::Config;
 use bincode::{deserialize_from, serialize_into};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::fs::{self, File};
-use std::io::{BufReader, BufWriter};
+use std::io::{BufReader, BufWriter, Read, Write};
 use std::path::{Path, PathBuf};
 
 /// Magic bytes for snapshot files
 const SNAPSHOT_MAGIC: &[u8; 4] = b"ZSNP";
 
 /// Snapshot format version
-const SNAPSHOT_VERSION: u8 = 2;
+const SNAPSHOT_VERSION: u8 = 5;
+
+/// Oldest snapshot version that can still be read; it has no sparse vectors
+const MIN_SNAPSHOT_VERSION: u8 = 2;
+
+/// Magic bytes for collection export files
+const EXPORT_MAGIC: &[u8; 4] = b"ZCOL";
+
+/// Collection export format version
+const EXPORT_VERSION: u8 = 1;
 
 /// Stored vector data
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -41,6 +56,15 @@ pub struct Snapshot {
     pub vectors: Vec<StoredVector>,
     /// HNSW index state
     pub hnsw_state: Option<HnswState>,
+    /// Sparse vectors of the records that have one
+    #[serde(default)]
+    pub sparse: Vec<(VectorId, SparseVector)>,
+    /// Named vectors of the records that have them, as (record, space, vector)
+    #[serde(default)]
+    pub named: Vec<(VectorId, String, Vec<f32>)>,
+    /// Contents of an index other than HNSW, from [`AnnIndex::serialize`]
+    #[serde(default)]
+    pub index_state: Option<Vec<u8>>,
 }
 
 impl Snapshot {
@@ -52,6 +76,9 @@ impl Snapshot {
             dimensions,
             vectors: Vec::new(),
             hnsw_state: None,
+            sparse: Vec::new(),
+            named: Vec::new(),
+            index_state: None,
         }
     }
 
@@ -69,6 +96,32 @@ impl Snapshot {
         self.hnsw_state = Some(state);
     }
 
+    /// Add the sparse vectors of the live records in `storage`
+    pub(crate) fn capture_sparse(&mut self, storage: &impl SnapshotSource, sparse: &SparseStore) {
+        self.sparse = sparse
+            .iter()
+            .filter(|(internal_id, _)| !storage.is_deleted(*internal_id))
+            .filter_map(|(internal_id, vector)| {
+                Some((storage.external_id(internal_id)?, vector.clone()))
+            })
+            .collect();
+    }
+
+    /// Add the named vectors of the live records in `storage`
+    pub(crate) fn capture_named(&mut self, storage: &impl SnapshotSource, named: &NamedVectors) {
+        self.named = named
+            .iter()
+            .filter(|(internal_id, _, _)| !storage.is_deleted(*internal_id))
+            .filter_map(|(internal_id, name, vector)| {
+                Some((
+                    storage.external_id(internal_id)?,
+                    name.to_string(),
+                    vector.to_vec(),
+                ))
+            })
+            .collect();
+    }
+
     /// Get the number of vectors
     pub fn len(&self) -> usize {
         self.vectors.len()
@@ -78,6 +131,231 @@ impl Snapshot {
     pub fn is_empty(&self) -> bool {
         self.vectors.is_empty()
     }
+
+    /// Capture the live vectors of `storage`, and the contents of `index`
+    pub(crate) fn capture(
+        id: u64,
+        wal_seq: u64,
+        dimensions: usize,
+        storage: &impl SnapshotSource,
+        index: Option<&dyn AnnIndex>,
+    ) -> Self {
+        let mut snapshot = Self::new(id, wal_seq, dimensions);
+        for internal_id in storage.slots() {
+            if storage.is_deleted(internal_id) {
+                continue;
+            }
+            if let (Some(ext_id), Some(vector)) = (
+                storage.external_id(internal_id),
+                storage.get_vector_data(internal_id),
+            ) {
+                let metadata = storage.get_metadata(internal_id);
+                snapshot.add_vector(ext_id, vector, metadata);
+            }
+        }
+
+        // Indexes refer to internal IDs, which only match the order vectors
+        // are restored in if no slot was skipped; otherwise the index is rebuilt
+        if storage.tombstones() == 0 && snapshot.len() == storage.slots().len() {
+            match index.map(|index| (index, index.as_hnsw())) {
+                Some((_, Some(hnsw))) => snapshot.set_hnsw_state(hnsw.get_state()),
+                Some((index, None)) => snapshot.index_state = index.serialize().ok(),
+                None => {}
+            }
+        }
+        snapshot
+    }
+
+    /// Write the header, graph and vectors
+    fn write_to(&self, writer: &mut impl Write) -> Result<()> {
+        let header = SnapshotHeader {
+            magic: *SNAPSHOT_MAGIC,
+            version: SNAPSHOT_VERSION,
+            id: self.id,
+            wal_seq: self.wal_seq,
+            dimensions: self.dimensions,
+            vector_count: self.vectors.len(),
+        };
+
+        serialize_into(&mut *writer, &header).map_err(|e| Error::Storage(e.to_string()))?;
+
+        // Write HNSW state
+        serialize_into(&mut *writer, &self.hnsw_state)
+            .map_err(|e| Error::Storage(e.to_string()))?;
+
+        // Write vectors in batches for efficiency
+        const BATCH_SIZE: usize = 1000;
+        for chunk in self.vectors.chunks(BATCH_SIZE) {
+            serialize_into(&mut *writer, &chunk.to_vec())
+                .map_err(|e| Error::Storage(e.to_string()))?;
+        }
+
+        serialize_into(&mut *writer, &self.sparse).map_err(|e| Error::Storage(e.to_string()))?;
+        serialize_into(&mut *writer, &self.named).map_err(|e| Error::Storage(e.to_string()))?;
+        serialize_into(&mut *writer, &self.index_state)
+            .map_err(|e| Error::Storage(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Read a snapshot written by [`write_to`](Self::write_to)
+    fn read_from(reader: &mut impl Read) -> Result<Self> {
+        // Read header
+        let header: SnapshotHeader =
+            deserialize_from(&mut *reader).map_err(|e| Error::Storage(e.to_string()))?;
+
+        // Verify magic
+        if header.magic != *SNAPSHOT_MAGIC {
+            return Err(Error::Storage("Invalid snapshot magic bytes".into()));
+        }
+
+        if !(MIN_SNAPSHOT_VERSION..=SNAPSHOT_VERSION).contains(&header.version) {
+            return Err(Error::Storage(format!(
+                "Unsupported snapshot version: {}",
+                header.version
+            )));
+        }
+
+        // Read HNSW state
+        let hnsw_state: Option<HnswState> =
+            deserialize_from(&mut *reader).map_err(|e| Error::Storage(e.to_string()))?;
+
+        // Read vectors
+        let mut vectors = Vec::with_capacity(header.vector_count);
+        let mut remaining = header.vector_count;
+
+        while remaining > 0 {
+            let batch: Vec<StoredVector> =
+                deserialize_from(&mut *reader).map_err(|e| Error::Storage(e.to_string()))?;
+            remaining = remaining.saturating_sub(batch.len());
+            vectors.extend(batch);
+        }
+
+        let sparse = if header.version >= 3 {
+            deserialize_from(&mut *reader).map_err(|e| Error::Storage(e.to_string()))?
+        } else {
+            Vec::new()
+        };
+        let named = if header.version >= 4 {
+            deserialize_from(&mut *reader).map_err(|e| Error::Storage(e.to_string()))?
+        } else {
+            Vec::new()
+        };
+        let index_state = if header.version >= 5 {
+            deserialize_from(&mut *reader).map_err(|e| Error::Storage(e.to_string()))?
+        } else {
+            None
+        };
+
+        Ok(Snapshot {
+            id: header.id,
+            wal_seq: header.wal_seq,
+            dimensions: header.dimensions,
+            vectors,
+            hnsw_state,
+            sparse,
+            named,
+            index_state,
+        })
+    }
+}
+
+/// Storage a [`Snapshot`] can be captured from
+pub(crate) trait SnapshotSource: VectorStorageTrait {
+    /// Every slot, including deleted ones, in internal ID order
+    fn slots(&self) -> Vec<InternalId>;
+    fn external_id(&self, internal_id: InternalId) -> Option<VectorId>;
+    /// Slots held by deleted or overwritten vectors
+    fn tombstones(&self) -> usize;
+}
+
+impl SnapshotSource for VectorStorage {
+    fn slots(&self) -> Vec<InternalId> {
+        self.all_internal_ids()
+    }
+
+    fn external_id(&self, internal_id: InternalId) -> Option<VectorId> {
+        self.get_external_id(internal_id)
+    }
+
+    fn tombstones(&self) -> usize {
+        self.deleted_count()
+    }
+}
+
+impl SnapshotSource for QuantizedStorage {
+    fn slots(&self) -> Vec<InternalId> {
+        self.all_internal_ids()
+    }
+
+    fn external_id(&self, internal_id: InternalId) -> Option<VectorId> {
+        self.get_external_id(internal_id)
+    }
+
+    fn tombstones(&self) -> usize {
+        self.deleted_count()
+    }
+}
+
+/// Write a collection's configuration and snapshot to one file
+///
+/// The file is self-contained, so it can be restored into another database
+/// with [`Database::restore`](crate::Database::restore).
+pub fn write_collection(
+    path: impl AsRef<Path>,
+    config: &Config,
+    snapshot: &Snapshot,
+) -> Result<()> {
+    let path = path.as_ref();
+    let config_json = serde_json::to_string(config).map_err(|e| Error::Serialization {
+        message: e.to_string(),
+    })?;
+
+    // Write next to the target and rename, so a failed write leaves no partial file
+    let tmp_path = path.with_extension("tmp");
+    let mut writer = BufWriter::new(File::create(&tmp_path)?);
+    writer.write_all(EXPORT_MAGIC)?;
+    writer.write_all(&[EXPORT_VERSION])?;
+    serialize_into(&mut writer, &config_json).map_err(|e| Error::Storage(e.to_string()))?;
+    snapshot.write_to(&mut writer)?;
+    writer.flush()?;
+    writer.get_ref().sync_all()?;
+    drop(writer);
+    fs::rename(tmp_path, path)?;
+    Ok(())
+}
+
+/// Read a file written by [`write_collection`]
+pub fn read_collection(path: impl AsRef<Path>) -> Result<(Config, Snapshot)> {
+    let mut reader = BufReader::new(File::open(path)?);
+
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != EXPORT_MAGIC {
+        return Err(Error::Storage("Not a collection snapshot file".into()));
+    }
+    let mut version = [0u8; 1];
+    reader.read_exact(&mut version)?;
+    if version[0] != EXPORT_VERSION {
+        return Err(Error::UnsupportedVersion {
+            version: version[0],
+            supported: "1",
+        });
+    }
+
+    let config_json: String =
+        deserialize_from(&mut reader).map_err(|e| Error::Storage(e.to_string()))?;
+    let config: Config =
+        serde_json::from_str(&config_json).map_err(|e| Error::Deserialization {
+            message: e.to_string(),
+        })?;
+    let snapshot = Snapshot::read_from(&mut reader)?;
+    if snapshot.dimensions != config.dimensions {
+        return Err(Error::Storage(format!(
+            "Snapshot dimensions ({}) don't match its configuration ({})",
+            snapshot.dimensions, config.dimensions
+        )));
+    }
+    Ok((config, snapshot))
 }
 
 /// Snapshot file header
@@ -122,29 +400,7 @@ impl SnapshotManager {
 
         let file = File::create(&path)?;
         let mut writer = BufWriter::new(file);
-
-        // Write header
-        let header = SnapshotHeader {
-            magic: *SNAPSHOT_MAGIC,
-            version: SNAPSHOT_VERSION,
-            id: snapshot.id,
-            wal_seq: snapshot.wal_seq,
-            dimensions: snapshot.dimensions,
-            vector_count: snapshot.vectors.len(),
-        };
-
-        serialize_into(&mut writer, &header).map_err(|e| Error::Storage(e.to_string()))?;
-
-        // Write HNSW state
-        serialize_into(&mut writer, &snapshot.hnsw_state)
-            .map_err(|e| Error::Storage(e.to_string()))?;
-
-        // Write vectors in batches for efficiency
-        const BATCH_SIZE: usize = 1000;
-        for chunk in snapshot.vectors.chunks(BATCH_SIZE) {
-            serialize_into(&mut writer, &chunk.to_vec())
-                .map_err(|e| Error::Storage(e.to_string()))?;
-        }
+        snapshot.write_to(&mut writer)?;
 
         // Cleanup old snapshots
         self.cleanup()?;
@@ -167,45 +423,7 @@ impl SnapshotManager {
     pub fn load(&self, path: &Path) -> Result<Snapshot> {
         let file = File::open(path)?;
         let mut reader = BufReader::new(file);
-
-        // Read header
-        let header: SnapshotHeader =
-            deserialize_from(&mut reader).map_err(|e| Error::Storage(e.to_string()))?;
-
-        // Verify magic
-        if header.magic != *SNAPSHOT_MAGIC {
-            return Err(Error::Storage("Invalid snapshot magic bytes".into()));
-        }
-
-        if header.version != SNAPSHOT_VERSION {
-            return Err(Error::Storage(format!(
-                "Unsupported snapshot version: {}",
-                header.version
-            )));
-        }
-
-        // Read HNSW state
-        let hnsw_state: Option<HnswState> =
-            deserialize_from(&mut reader).map_err(|e| Error::Storage(e.to_string()))?;
-
-        // Read vectors
-        let mut vectors = Vec::with_capacity(header.vector_count);
-        let mut remaining = header.vector_count;
-
-        while remaining > 0 {
-            let batch: Vec<StoredVector> =
-                deserialize_from(&mut reader).map_err(|e| Error::Storage(e.to_string()))?;
-            remaining = remaining.saturating_sub(batch.len());
-            vectors.extend(batch);
-        }
-
-        Ok(Snapshot {
-            id: header.id,
-            wal_seq: header.wal_seq,
-            dimensions: header.dimensions,
-            vectors,
-            hnsw_state,
-        })
+        Snapshot::read_from(&mut reader)
     }
 
     /// List all snapshots sorted by ID
@@ -0,0 +1,255 @@
+//! Fusion of dense and sparse rankings
+//!
+//! Dense results are distances (lower is better) and sparse results are dot
+//! products (higher is better), so they can't be compared directly. RRF only
+//! looks at ranks; the weighted sum first rescales both to 0..1.
+
+use super::rrf::rrf_term;
+use crate::error::{Error, Result};
+use crate::types::InternalId;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// How a hybrid search combines its dense and sparse rankings
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum Fusion {
+    /// Reciprocal rank fusion: each ranking adds `1 / (k + rank)`
+    Rrf {
+        #[serde(default = "default_rrf_k")]
+        k: f32,
+    },
+    /// `alpha * dense + (1 - alpha) * sparse`, both min-max normalized
+    WeightedSum {
+        #[serde(default = "default_alpha")]
+        alpha: f32,
+    },
+}
+
+fn default_rrf_k() -> f32 {
+    60.0
+}
+
+fn default_alpha() -> f32 {
+    0.5
+}
+
+/// How a record's fused score is made up
+///
+/// `dense_contribution + sparse_contribution` is the fused score.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct FusionExplanation {
+    /// 1-based position in the dense ranking, if the record was in it
+    pub dense_rank: Option<usize>,
+    /// 1-based position in the keyword ranking, if the record was in it
+    pub sparse_rank: Option<usize>,
+    /// Score from the dense ranking: its RRF term, or `alpha` times the
+    /// normalized dense score
+    pub dense_contribution: f32,
+    /// Score from the keyword ranking: its RRF term, or `1 - alpha` times the
+    /// normalized keyword score
+    pub sparse_contribution: f32,
+}
+
+impl Default for Fusion {
+    fn default() -> Self {
+        Fusion::Rrf { k: default_rrf_k() }
+    }
+}
+
+impl Fusion {
+    pub fn validate(&self) -> Result<()> {
+        match *self {
+            Fusion::Rrf { k } if !(k.is_finite() && k > 0.0) => Err(Error::InvalidConfig(format!(
+                "RRF k must be positive, got {}",
+                k
+            ))),
+            Fusion::WeightedSum { alpha } if !(0.0..=1.0).contains(&alpha) => Err(
+                Error::InvalidConfig(format!("alpha must be between 0 and 1, got {}", alpha)),
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fuse `dense` (ascending distance) and `sparse` (descending score) into
+    /// the top `limit` IDs by fused score, best first
+    pub fn fuse(
+        &self,
+        dense: &[(InternalId, f32)],
+        sparse: &[(InternalId, f32)],
+        limit: usize,
+    ) -> Vec<(InternalId, f32)> {
+        self.fuse_explained(dense, sparse, limit)
+            .into_iter()
+            .map(|(id, score, _)| (id, score))
+            .collect()
+    }
+
+    /// [`fuse`](Self::fuse), with how each fused score is made up
+    pub fn fuse_explained(
+        &self,
+        dense: &[(InternalId, f32)],
+        sparse: &[(InternalId, f32)],
+        limit: usize,
+    ) -> Vec<(InternalId, f32, FusionExplanation)> {
+        let (dense_terms, sparse_terms): (Vec<f32>, Vec<f32>) = match *self {
+            Fusion::Rrf { k } => (
+                (0..dense.len()).map(|rank| rrf_term(k, rank)).collect(),
+                (0..sparse.len()).map(|rank| rrf_term(k, rank)).collect(),
+            ),
+            // Closer is better for distances, so they are flipped
+            Fusion::WeightedSum { alpha } => (
+                normalize(dense, true).map(|(_, s)| alpha * s).collect(),
+                normalize(sparse, false)
+                    .map(|(_, s)| (1.0 - alpha) * s)
+                    .collect(),
+            ),
+        };
+
+        let mut parts: HashMap<InternalId, FusionExplanation> = HashMap::new();
+        for (rank, (&(id, _), term)) in dense.iter().zip(dense_terms).enumerate() {
+            let part = parts.entry(id).or_default();
+            part.dense_rank = Some(rank + 1);
+            part.dense_contribution += term;
+        }
+        for (rank, (&(id, _), term)) in sparse.iter().zip(sparse_terms).enumerate() {
+            let part = parts.entry(id).or_default();
+            part.sparse_rank = Some(rank + 1);
+            part.sparse_contribution += term;
+        }
+
+        let mut fused: Vec<_> = parts
+            .into_iter()
+            .map(|(id, part)| (id, part.dense_contribution + part.sparse_contribution, part))
+            .collect();
+        fused.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        fused.truncate(limit);
+        fused
+    }
+}
+
+/// Weighted sum of min-max normalized scores; IDs missing from a ranking get
+/// nothing from it
+pub fn weighted_sum_fusion(
+    dense: &[(InternalId, f32)],
+    sparse: &[(InternalId, f32)],
+    alpha: f32,
+    limit: usize,
+) -> Vec<(InternalId, f32)> {
+    let mut scores: HashMap<InternalId, f32> = HashMap::new();
+    // Closer is better for distances, so they are flipped
+    for (id, score) in normalize(dense, true) {
+        *scores.entry(id).or_default() += alpha * score;
+    }
+    for (id, score) in normalize(sparse, false) {
+        *scores.entry(id).or_default() += (1.0 - alpha) * score;
+    }
+
+    let mut fused: Vec<_> = scores.into_iter().collect();
+    fused.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    fused.truncate(limit);
+    fused
+}
+
+/// Rescale to 0..1 with 1 for the best entry; equal values all get 1
+fn normalize(
+    results: &[(InternalId, f32)],
+    lower_is_better: bool,
+) -> impl Iterator<Item = (InternalId, f32)> + '_ {
+    let min = results.iter().map(|r| r.1).fold(f32::INFINITY, f32::min);
+    let max = results
+        .iter()
+        .map(|r| r.1)
+        .fold(f32::NEG_INFINITY, f32::max);
+    let range = max - min;
+    results.iter().map(move |&(id, value)| {
+        let score = if range > 0.0 {
+            if lower_is_better {
+                (max - value) / range
+            } else {
+                (value - min) / range
+            }
+        } else {
+            1.0
+        };
+        (id, score)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(n: usize) -> InternalId {
+        InternalId::from(n)
+    }
+
+    #[test]
+    fn test_weighted_sum() {
+        let dense = vec![(id(1), 0.0), (id(2), 0.25), (id(3), 0.5)];
+        let sparse = vec![(id(3), 9.0), (id(4), 5.0), (id(1), 1.0)];
+
+        // dense: 1 -> 1.0, 2 -> 0.5, 3 -> 0.0
+        // sparse: 3 -> 1.0, 4 -> 0.5, 1 -> 0.0
+        let fused = weighted_sum_fusion(&dense, &sparse, 0.75, 10);
+        assert_eq!(fused[0], (id(1), 0.75));
+        assert_eq!(fused[1], (id(2), 0.375));
+        assert_eq!(fused[2], (id(3), 0.25));
+        assert_eq!(fused[3], (id(4), 0.125));
+
+        // alpha 0 is the sparse ranking alone
+        let fused = weighted_sum_fusion(&dense, &sparse, 0.0, 2);
+        assert_eq!(fused, vec![(id(3), 1.0), (id(4), 0.5)]);
+    }
+
+    #[test]
+    fn test_fuse_explained() {
+        let dense = vec![(id(1), 0.0), (id(2), 0.25), (id(3), 0.5)];
+        let sparse = vec![(id(3), 9.0), (id(4), 5.0), (id(1), 1.0)];
+
+        let weighted = Fusion::WeightedSum { alpha: 0.75 };
+        let explained = weighted.fuse_explained(&dense, &sparse, 10);
+        let plain = weighted_sum_fusion(&dense, &sparse, 0.75, 10);
+        for ((id, score, part), expected) in explained.iter().zip(&plain) {
+            assert_eq!((*id, *score), *expected);
+            assert_eq!(*score, part.dense_contribution + part.sparse_contribution);
+        }
+        let (_, _, third) = explained[2];
+        assert_eq!(
+            third,
+            FusionExplanation {
+                dense_rank: Some(3),
+                sparse_rank: Some(1),
+                dense_contribution: 0.0,
+                sparse_contribution: 0.25,
+            }
+        );
+
+        // Scores 1, 1/4 + 1/3, 1/3 and 1/4, so no ties
+        let sparse = vec![(id(1), 9.0), (id(3), 5.0), (id(4), 1.0)];
+        let rrf = Fusion::Rrf { k: 1.0 };
+        let explained = rrf.fuse_explained(&dense, &sparse, 10);
+        let order: Vec<InternalId> = explained.iter().map(|(id, _, _)| *id).collect();
+        assert_eq!(order, vec![id(1), id(3), id(2), id(4)]);
+        let (_, score, part) = explained[0];
+        assert_eq!((part.dense_rank, part.sparse_rank), (Some(1), Some(1)));
+        assert_eq!(score, 1.0);
+        let (_, score, part) = explained[3];
+        assert_eq!((part.dense_rank, part.sparse_rank), (None, Some(3)));
+        assert_eq!((part.dense_contribution, score), (0.0, 0.25));
+    }
+
+    #[test]
+    fn test_fusion_serde_and_validation() {
+        let rrf: Fusion = serde_json::from_str(r#"{ "method": "rrf" }"#).unwrap();
+        assert_eq!(rrf, Fusion::Rrf { k: 60.0 });
+        let weighted: Fusion =
+            serde_json::from_str(r#"{ "method": "weighted_sum", "alpha": 0.7 }"#).unwrap();
+        assert_eq!(weighted, Fusion::WeightedSum { alpha: 0.7 });
+
+        assert!(Fusion::Rrf { k: 0.0 }.validate().is_err());
+        assert!(Fusion::WeightedSum { alpha: 1.5 }.validate().is_err());
+        assert!(Fusion::WeightedSum { alpha: f32::NAN }.validate().is_err());
+        assert!(weighted.validate().is_ok());
+    }
+}
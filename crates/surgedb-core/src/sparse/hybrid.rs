@@ -0,0 +1,160 @@
+//! Hybrid search over a collection's dense graph and a keyword ranking
+//!
+//! The keyword side is either the collection's sparse vectors or its text index.
+
+use super::fusion::{Fusion, FusionExplanation};
+use super::index::SparseVector;
+use super::store::SparseStore;
+use crate::error::Result;
+use crate::filter::Filter;
+use crate::storage::{VectorStorage, VectorStorageTrait};
+use crate::types::{InternalId, SearchHit, SearchUsage, VectorId};
+use serde::Serialize;
+use serde_json::Value;
+use std::cell::Cell;
+use std::collections::HashMap;
+
+/// Candidates each ranking contributes per requested result
+pub const HYBRID_CANDIDATES: usize = 4;
+
+/// One result of a hybrid search
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HybridHit {
+    pub id: VectorId,
+    /// Fused score; higher is better
+    pub score: f32,
+    /// Distance to the dense query, if the record was a dense candidate
+    pub distance: Option<f32>,
+    /// Keyword score (dot product with the sparse query, or BM25 for a text
+    /// query), if the record was a keyword candidate
+    pub sparse_score: Option<f32>,
+    /// How `score` is made up of the two rankings
+    pub explanation: FusionExplanation,
+    pub metadata: Option<Value>,
+}
+
+/// Fuse the `dense` candidates of a search with the sparse ranking of `query`
+///
+/// `dense` holds external IDs and distances as returned by an ID search, so
+/// it is already filtered and free of stale entries.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn fuse_with_sparse(
+    storage: &VectorStorage,
+    sparse: &SparseStore,
+    dense: Vec<(VectorId, f32)>,
+    query: &SparseVector,
+    k: usize,
+    filter: Option<&Filter>,
+    fusion: Fusion,
+    usage: &mut SearchUsage,
+) -> Vec<HybridHit> {
+    let sparse_results = rank_filtered(storage, filter, usage, |accept| {
+        sparse.search(query, k * HYBRID_CANDIDATES, accept)
+    });
+    fuse_rankings(storage, dense, sparse_results, k, fusion)
+}
+
+/// BM25 search of the text index for `query`, best first
+///
+/// The scores in the hits are BM25 scores, so higher is better.
+pub(crate) fn search_text(
+    storage: &VectorStorage,
+    query: &str,
+    k: usize,
+    filter: Option<&Filter>,
+    usage: &mut SearchUsage,
+) -> Result<Vec<SearchHit>> {
+    let results = rank_filtered(storage, filter, usage, |accept| {
+        storage.search_text(query, k, accept)
+    })?;
+    Ok(results
+        .into_iter()
+        .filter_map(|(internal_id, score)| {
+            Some((
+                storage.get_external_id(internal_id)?,
+                score,
+                storage.get_metadata(internal_id),
+            ))
+        })
+        .collect())
+}
+
+/// Fuse the `dense` candidates of a search with the BM25 ranking of `query`
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn fuse_with_text(
+    storage: &VectorStorage,
+    dense: Vec<(VectorId, f32)>,
+    query: &str,
+    k: usize,
+    filter: Option<&Filter>,
+    fusion: Fusion,
+    usage: &mut SearchUsage,
+) -> Result<Vec<HybridHit>> {
+    let text_results = rank_filtered(storage, filter, usage, |accept| {
+        storage.search_text(query, k * HYBRID_CANDIDATES, accept)
+    })?;
+    Ok(fuse_rankings(storage, dense, text_results, k, fusion))
+}
+
+/// Run a keyword `rank`ing over the live records matching `filter`
+///
+/// `rank` gets the predicate to apply to its candidates; each one it checks
+/// counts as scanned.
+pub(crate) fn rank_filtered<R>(
+    storage: &VectorStorage,
+    filter: Option<&Filter>,
+    usage: &mut SearchUsage,
+    rank: impl FnOnce(&dyn Fn(InternalId) -> bool) -> R,
+) -> R {
+    let view = storage.search_view(filter);
+    let bitmap = filter.and_then(|f| view.filter_bitmap(f));
+    let scanned = Cell::new(0);
+    let accept = |internal_id: InternalId| {
+        scanned.set(scanned.get() + 1);
+        if view.is_deleted(internal_id) {
+            return false;
+        }
+        match (&bitmap, filter) {
+            (Some(bitmap), _) => bitmap.contains(internal_id.as_u32()),
+            (None, Some(f)) => view
+                .get_metadata(internal_id)
+                .is_some_and(|m| f.matches(&m)),
+            (None, None) => true,
+        }
+    };
+    let results = rank(&accept);
+    drop(view);
+    usage.vectors_scanned += scanned.get();
+    results
+}
+
+/// Fuse dense results (external IDs and distances) with a keyword ranking
+pub(crate) fn fuse_rankings(
+    storage: &VectorStorage,
+    dense: Vec<(VectorId, f32)>,
+    keyword: Vec<(InternalId, f32)>,
+    k: usize,
+    fusion: Fusion,
+) -> Vec<HybridHit> {
+    let dense: Vec<(InternalId, f32)> = dense
+        .into_iter()
+        .filter_map(|(id, distance)| Some((storage.get_internal_id(&id)?, distance)))
+        .collect();
+
+    let distances: HashMap<InternalId, f32> = dense.iter().copied().collect();
+    let keyword_scores: HashMap<InternalId, f32> = keyword.iter().copied().collect();
+    fusion
+        .fuse_explained(&dense, &keyword, k)
+        .into_iter()
+        .filter_map(|(internal_id, score, explanation)| {
+            Some(HybridHit {
+                id: storage.get_external_id(internal_id)?,
+                score,
+                distance: distances.get(&internal_id).copied(),
+                sparse_score: keyword_scores.get(&internal_id).copied(),
+                explanation,
+                metadata: storage.get_metadata(internal_id),
+            })
+        })
+        .collect()
+}
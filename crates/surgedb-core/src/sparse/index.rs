@@ -2,12 +2,13 @@
 //!
 //! Implements inverted index for sparse vectors (e.g., BM25 or SPLADE).
 
+use crate::error::{Error, Result};
 use crate::types::InternalId;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 /// Sparse vector representation
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
 pub struct SparseVector {
     /// Indices of non-zero elements (sorted)
     pub indices: Vec<u32>,
@@ -23,6 +24,38 @@ impl SparseVector {
         Self { indices, values }
     }
 
+    /// Reject vectors whose indices aren't strictly increasing, whose
+    /// lengths differ, or that hold non-finite values
+    pub fn validate(&self) -> Result<()> {
+        if self.indices.len() != self.values.len() {
+            return Err(Error::InvalidConfig(format!(
+                "Sparse vector has {} indices but {} values",
+                self.indices.len(),
+                self.values.len()
+            )));
+        }
+        if self.indices.windows(2).any(|w| w[0] >= w[1]) {
+            return Err(Error::InvalidConfig(
+                "Sparse vector indices must be unique and sorted".to_string(),
+            ));
+        }
+        if self.values.iter().any(|v| !v.is_finite()) {
+            return Err(Error::InvalidConfig(
+                "Sparse vector values must be finite".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Number of non-zero elements
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
     /// Calculate dot product with another sparse vector
     pub fn dot(&self, other: &SparseVector) -> f32 {
         let mut sum = 0.0;
@@ -79,13 +112,32 @@ impl InvertedIndex {
 
     /// Search for documents matching the sparse query
     pub fn search(&self, query: &SparseVector, k: usize) -> Vec<(InternalId, f32)> {
+        self.search_filtered(query, k, |_| true)
+    }
+
+    /// Like [`search`](Self::search), skipping documents `accept` rejects
+    pub fn search_filtered(
+        &self,
+        query: &SparseVector,
+        k: usize,
+        accept: impl Fn(InternalId) -> bool,
+    ) -> Vec<(InternalId, f32)> {
         let mut scores: HashMap<InternalId, f32> = HashMap::new();
+        let mut rejected: HashSet<InternalId> = HashSet::new();
 
         // Accumulate scores
         for (&token_idx, &query_val) in query.indices.iter().zip(query.values.iter()) {
             if let Some(posting_list) = self.postings.get(&token_idx) {
                 for (doc_id, doc_val) in posting_list {
-                    *scores.entry(*doc_id).or_default() += query_val * doc_val;
+                    if let Some(score) = scores.get_mut(doc_id) {
+                        *score += query_val * doc_val;
+                    } else if !rejected.contains(doc_id) {
+                        if accept(*doc_id) {
+                            scores.insert(*doc_id, query_val * doc_val);
+                        } else {
+                            rejected.insert(*doc_id);
+                        }
+                    }
                 }
             }
         }
@@ -96,6 +148,18 @@ impl InvertedIndex {
         results.truncate(k);
         results
     }
+
+    /// Approximate heap bytes of the posting lists
+    pub fn memory_usage(&self) -> usize {
+        self.postings
+            .values()
+            .map(|list| {
+                std::mem::size_of::<u32>()
+                    + std::mem::size_of::<Vec<(InternalId, f32)>>()
+                    + list.capacity() * std::mem::size_of::<(InternalId, f32)>()
+            })
+            .sum()
+    }
 }
 
 #[cfg(test)]
@@ -1,4 +1,10 @@
+pub mod fusion;
+pub mod hybrid;
 pub mod index;
 pub mod rrf;
+pub mod store;
+pub use fusion::{weighted_sum_fusion, Fusion, FusionExplanation};
+pub use hybrid::{HybridHit, HYBRID_CANDIDATES};
 pub use index::{InvertedIndex, SparseVector};
 pub use rrf::reciprocal_rank_fusion;
+pub use store::SparseStore;
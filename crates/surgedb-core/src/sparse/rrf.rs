@@ -20,14 +20,12 @@ pub fn reciprocal_rank_fusion(
 
     // Process list A
     for (rank, (id, _)) in results_a.iter().enumerate() {
-        let score = 1.0 / (k_constant + (rank as f32) + 1.0);
-        *scores.entry(*id).or_default() += score;
+        *scores.entry(*id).or_default() += rrf_term(k_constant, rank);
     }
 
     // Process list B
     for (rank, (id, _)) in results_b.iter().enumerate() {
-        let score = 1.0 / (k_constant + (rank as f32) + 1.0);
-        *scores.entry(*id).or_default() += score;
+        *scores.entry(*id).or_default() += rrf_term(k_constant, rank);
     }
 
     // Sort combined results
@@ -38,6 +36,11 @@ pub fn reciprocal_rank_fusion(
     fused
 }
 
+/// What the entry at 0-based `rank` of a list adds to its RRF score
+pub(crate) fn rrf_term(k_constant: f32, rank: usize) -> f32 {
+    1.0 / (k_constant + (rank as f32) + 1.0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,102 @@
+//! Sparse vectors of a collection's records
+//!
+//! Keeps each record's sparse vector next to the inverted index built from
+//! them, so a vector can be replaced or dropped without a full rebuild.
+
+use super::index::{InvertedIndex, SparseVector};
+use crate::types::InternalId;
+use std::collections::HashMap;
+
+/// Sparse vectors keyed by internal ID, with their inverted index
+#[derive(Default)]
+pub struct SparseStore {
+    vectors: HashMap<InternalId, SparseVector>,
+    index: InvertedIndex,
+}
+
+impl SparseStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the sparse vector of `id`, replacing any previous one
+    pub fn set(&mut self, id: InternalId, vector: SparseVector) {
+        self.remove(id);
+        self.index.insert(id, &vector);
+        self.vectors.insert(id, vector);
+    }
+
+    /// Drop the sparse vector of `id`, returning it
+    pub fn remove(&mut self, id: InternalId) -> Option<SparseVector> {
+        let vector = self.vectors.remove(&id)?;
+        self.index.remove(id, &vector);
+        Some(vector)
+    }
+
+    pub fn get(&self, id: InternalId) -> Option<&SparseVector> {
+        self.vectors.get(&id)
+    }
+
+    /// Every stored vector, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (InternalId, &SparseVector)> {
+        self.vectors.iter().map(|(id, vector)| (*id, vector))
+    }
+
+    /// Top `k` records by dot product with `query`, among those `accept` allows
+    pub fn search(
+        &self,
+        query: &SparseVector,
+        k: usize,
+        accept: impl Fn(InternalId) -> bool,
+    ) -> Vec<(InternalId, f32)> {
+        self.index.search_filtered(query, k, accept)
+    }
+
+    pub fn len(&self) -> usize {
+        self.vectors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vectors.is_empty()
+    }
+
+    /// Approximate heap bytes of the vectors and the inverted index
+    pub fn memory_usage(&self) -> usize {
+        let vectors: usize = self
+            .vectors
+            .values()
+            .map(|v| {
+                std::mem::size_of::<(InternalId, SparseVector)>()
+                    + v.indices.capacity() * std::mem::size_of::<u32>()
+                    + v.values.capacity() * std::mem::size_of::<f32>()
+            })
+            .sum();
+        vectors + self.index.memory_usage()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_replaces_postings() {
+        let mut store = SparseStore::new();
+        let a = InternalId::from(0);
+        let b = InternalId::from(1);
+        store.set(a, SparseVector::new(vec![(1, 1.0)]));
+        store.set(b, SparseVector::new(vec![(1, 0.5), (2, 1.0)]));
+        store.set(a, SparseVector::new(vec![(2, 2.0)]));
+
+        let query = SparseVector::new(vec![(1, 1.0)]);
+        assert_eq!(store.search(&query, 10, |_| true), vec![(b, 0.5)]);
+
+        let query = SparseVector::new(vec![(2, 1.0)]);
+        assert_eq!(store.search(&query, 10, |_| true), vec![(a, 2.0), (b, 1.0)]);
+        assert_eq!(store.search(&query, 10, |id| id != a), vec![(b, 1.0)]);
+
+        assert!(store.remove(a).is_some());
+        assert_eq!(store.search(&query, 10, |_| true), vec![(b, 1.0)]);
+        assert_eq!(store.len(), 1);
+    }
+}
@@ -6,12 +6,19 @@ use crate::bitmap_index::BitmapIndex;
 use crate::distance::DistanceMetric;
 use crate::error::{Error, Result};
 use crate::filter::Filter;
+use crate::filter_cache::{CachedFilterInfo, FilterCache};
+use crate::id_map::IdMap;
+use crate::metadata_store::MetadataStore;
 use crate::sync::RwLock;
-use crate::types::{InternalId, VectorId};
+use crate::text_index::TextIndex;
+use crate::types::{
+    value_heap_size, GarbageStats, InternalId, ListCursor, ListPage, MetadataCompression,
+    PayloadSize, VectorId,
+};
 use roaring::RoaringBitmap;
-use std::sync::Arc;
 use serde_json::Value;
-use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
 /// Trait for vector storage backends
 pub trait VectorStorageTrait {
@@ -38,12 +45,50 @@ pub trait VectorStorageTrait {
         None
     }
 
+    /// Optional vector known to match `filter`, used to seed filtered search
+    fn filter_entry(&self, _filter: &Filter) -> Option<InternalId> {
+        None
+    }
+
     /// Check if a vector is deleted
     fn is_deleted(&self, _internal_id: InternalId) -> bool {
         false
     }
 }
 
+/// Storage seen through a reference, so indexes taking `&dyn` storage can
+/// hand it on to generic code
+impl<T: VectorStorageTrait + ?Sized> VectorStorageTrait for &T {
+    fn get_vector_data(&self, internal_id: InternalId) -> Option<Vec<f32>> {
+        (**self).get_vector_data(internal_id)
+    }
+
+    fn distance(
+        &self,
+        internal_id: InternalId,
+        query: &[f32],
+        metric: DistanceMetric,
+    ) -> Option<f32> {
+        (**self).distance(internal_id, query, metric)
+    }
+
+    fn get_metadata(&self, internal_id: InternalId) -> Option<Value> {
+        (**self).get_metadata(internal_id)
+    }
+
+    fn filter_bitmap(&self, filter: &Filter) -> Option<Arc<RoaringBitmap>> {
+        (**self).filter_bitmap(filter)
+    }
+
+    fn filter_entry(&self, filter: &Filter) -> Option<InternalId> {
+        (**self).filter_entry(filter)
+    }
+
+    fn is_deleted(&self, internal_id: InternalId) -> bool {
+        (**self).is_deleted(internal_id)
+    }
+}
+
 /// In-memory vector storage with ID mapping
 pub struct VectorStorage {
     /// Dimensionality of stored vectors
@@ -52,20 +97,26 @@ pub struct VectorStorage {
     /// Flat storage of all vectors (contiguous memory for cache efficiency)
     vectors: RwLock<Vec<f32>>,
 
-    /// Map from external ID to internal ID
-    id_to_internal: RwLock<HashMap<VectorId, InternalId>>,
-
-    /// Map from internal ID to external ID
-    internal_to_id: RwLock<Vec<VectorId>>,
+    /// External ID <-> internal ID mapping
+    ids: RwLock<IdMap>,
 
     /// Optional metadata for each vector
-    metadata: RwLock<HashMap<InternalId, Value>>,
+    metadata: RwLock<MetadataStore>,
 
     /// Set of deleted internal IDs
     deleted: RwLock<std::collections::HashSet<InternalId>>,
 
     /// Bitmap index for metadata filtering
     bitmap_index: RwLock<BitmapIndex>,
+
+    /// Materialized match sets for hot filters
+    filter_cache: RwLock<FilterCache>,
+
+    /// Keyword index over the declared text fields
+    text_index: RwLock<TextIndex>,
+
+    /// Payload bytes of deleted or overwritten records
+    dead_payload_bytes: AtomicUsize,
 }
 
 impl VectorStorage {
@@ -74,25 +125,42 @@ impl VectorStorage {
         Self {
             dimensions,
             vectors: RwLock::new(Vec::new()),
-            id_to_internal: RwLock::new(HashMap::new()),
-            internal_to_id: RwLock::new(Vec::new()),
-            metadata: RwLock::new(HashMap::new()),
+            ids: RwLock::new(IdMap::new()),
+            metadata: RwLock::new(MetadataStore::default()),
             deleted: RwLock::new(std::collections::HashSet::new()),
             bitmap_index: RwLock::new(BitmapIndex::new()),
+            filter_cache: RwLock::new(FilterCache::default()),
+            text_index: RwLock::new(TextIndex::default()),
+            dead_payload_bytes: AtomicUsize::new(0),
         }
     }
 
+    /// Keep metadata with the given compression (call before inserting)
+    pub fn with_metadata_compression(self, compression: MetadataCompression) -> Result<Self> {
+        *self.metadata.write() = MetadataStore::new(compression)?;
+        Ok(self)
+    }
+
+    /// Index the text of the given metadata fields (call before inserting)
+    pub fn with_text_fields(self, fields: Vec<String>) -> Self {
+        *self.text_index.write() = TextIndex::new(fields);
+        self
+    }
+
     /// Delete a vector by ID
     /// Returns true if the vector existed and was deleted
     pub fn delete(&self, id: &VectorId) -> Result<bool> {
-        // We don't remove from vectors/internal_to_id to preserve indices for HNSW
-        // Just remove from id_to_internal map and add to deleted set
-        let mut id_to_internal = self.id_to_internal.write();
+        // We don't remove vectors or slot IDs to preserve indices for HNSW
+        // Just unmap the ID and add its slot to the deleted set
+        let mut ids = self.ids.write();
 
-        if let Some(internal_id) = id_to_internal.remove(id) {
+        if let Some(internal_id) = ids.remove(id) {
             self.deleted.write().insert(internal_id);
-            if let Some(meta) = self.metadata.write().remove(&internal_id) {
+            if let Some(meta) = self.metadata.write().remove(internal_id) {
                 self.bitmap_index.write().remove(internal_id, &meta);
+                self.filter_cache.write().remove_id(internal_id);
+                self.text_index.write().remove(internal_id, &meta);
+                self.bury_payload(&meta);
             }
             Ok(true)
         } else {
@@ -137,11 +205,8 @@ impl VectorStorage {
         }
 
         // Acquire lock once for checking existence
-        {
-            let id_to_internal = self.id_to_internal.read();
-            if !allow_update && id_to_internal.contains_key(&id) {
-                return Err(Error::DuplicateId(id.to_string()));
-            }
+        if !allow_update && self.ids.read().contains(&id) {
+            return Err(Error::DuplicateId(id.to_string()));
         }
 
         // Prepare data outside of lock? No, we need consistent state.
@@ -149,36 +214,40 @@ impl VectorStorage {
         // Here we just implement single insert efficiently.
 
         let mut vectors = self.vectors.write();
-        let mut internal_to_id = self.internal_to_id.write();
-        let mut id_to_internal = self.id_to_internal.write();
+        let mut ids = self.ids.write();
         let mut metadata_store = self.metadata.write();
         let mut bitmap_index = self.bitmap_index.write();
+        let mut filter_cache = self.filter_cache.write();
+        let mut text_index = self.text_index.write();
 
         // Double check duplicate under write lock to be safe?
         // Optimistic check above is fine if we assume single writer or accept race.
         // But strict correctness requires check under write lock.
-        if !allow_update && id_to_internal.contains_key(&id) {
+        if !allow_update && ids.contains(&id) {
             return Err(Error::DuplicateId(id.to_string()));
         }
 
-        let internal_id = InternalId::from(internal_to_id.len());
-
         // Append vector to flat storage
         vectors.extend_from_slice(vector);
 
         // Update mappings
-        if let Some(old_internal_id) = id_to_internal.insert(id.clone(), internal_id) {
+        let (internal_id, old_internal_id) = ids.push(id);
+        if let Some(old_internal_id) = old_internal_id {
             self.deleted.write().insert(old_internal_id);
-            if let Some(old_meta) = metadata_store.remove(&old_internal_id) {
+            if let Some(old_meta) = metadata_store.remove(old_internal_id) {
                 bitmap_index.remove(old_internal_id, &old_meta);
+                filter_cache.remove_id(old_internal_id);
+                text_index.remove(old_internal_id, &old_meta);
+                self.bury_payload(&old_meta);
             }
         }
-        internal_to_id.push(id);
 
         // Store metadata if present
         if let Some(meta) = metadata {
             bitmap_index.index(internal_id, &meta);
-            metadata_store.insert(internal_id, meta);
+            filter_cache.index(internal_id, &meta);
+            text_index.index(internal_id, &meta);
+            metadata_store.insert(internal_id, meta)?;
         }
 
         Ok(internal_id)
@@ -205,40 +274,133 @@ impl VectorStorage {
         }
 
         let mut vectors = self.vectors.write();
-        let mut internal_to_id = self.internal_to_id.write();
-        let mut id_to_internal = self.id_to_internal.write();
+        let mut ids = self.ids.write();
         let mut metadata_store = self.metadata.write();
         let mut bitmap_index = self.bitmap_index.write();
+        let mut filter_cache = self.filter_cache.write();
+        let mut text_index = self.text_index.write();
 
-        let start_internal_id = internal_to_id.len();
         let mut result_ids = Vec::with_capacity(items.len());
 
-        for (i, (id, vector, metadata)) in items.iter().enumerate() {
-            let internal_id = InternalId::from(start_internal_id + i);
-            result_ids.push(internal_id);
-
+        for (id, vector, metadata) in items {
             // Append vector
             vectors.extend_from_slice(vector);
 
             // Update mappings
-            if let Some(old_internal_id) = id_to_internal.insert(id.clone(), internal_id) {
+            let (internal_id, old_internal_id) = ids.push(id.clone());
+            result_ids.push(internal_id);
+            if let Some(old_internal_id) = old_internal_id {
                 self.deleted.write().insert(old_internal_id);
-                if let Some(old_meta) = metadata_store.remove(&old_internal_id) {
+                if let Some(old_meta) = metadata_store.remove(old_internal_id) {
                     bitmap_index.remove(old_internal_id, &old_meta);
+                    filter_cache.remove_id(old_internal_id);
+                    text_index.remove(old_internal_id, &old_meta);
+                    self.bury_payload(&old_meta);
                 }
             }
-            internal_to_id.push(id.clone());
 
             // Metadata
             if let Some(meta) = metadata {
                 bitmap_index.index(internal_id, meta);
-                metadata_store.insert(internal_id, meta.clone());
+                filter_cache.index(internal_id, meta);
+                text_index.index(internal_id, meta);
+                metadata_store.insert(internal_id, meta.clone())?;
             }
         }
 
         Ok(result_ids)
     }
 
+    /// Replace the metadata of `id` in place, keeping its slot and vector
+    ///
+    /// Returns the slot, or `None` if there is no such vector.
+    pub fn update_metadata(
+        &self,
+        id: &VectorId,
+        metadata: Option<Value>,
+    ) -> Result<Option<InternalId>> {
+        let ids = self.ids.read();
+        let Some(internal_id) = ids.get(id) else {
+            return Ok(None);
+        };
+        let mut metadata_store = self.metadata.write();
+        let mut bitmap_index = self.bitmap_index.write();
+        let mut filter_cache = self.filter_cache.write();
+        let mut text_index = self.text_index.write();
+
+        if let Some(old_meta) = metadata_store.remove(internal_id) {
+            bitmap_index.remove(internal_id, &old_meta);
+            filter_cache.remove_id(internal_id);
+            text_index.remove(internal_id, &old_meta);
+        }
+        if let Some(meta) = metadata {
+            bitmap_index.index(internal_id, &meta);
+            filter_cache.index(internal_id, &meta);
+            text_index.index(internal_id, &meta);
+            metadata_store.insert(internal_id, meta)?;
+        }
+
+        Ok(Some(internal_id))
+    }
+
+    /// Materialize the matches of `filter` and keep them updated on writes
+    pub fn cache_filter(&self, filter: Filter) -> Result<CachedFilterInfo> {
+        let ids = self.ids.read();
+        let metadata = self.metadata.read();
+        let matching = (0..ids.slots())
+            .map(InternalId::from)
+            .filter(|&internal_id| {
+                ids.external(internal_id)
+                    .is_some_and(|id| ids.get(id) == Some(internal_id))
+                    && metadata
+                        .get(internal_id)
+                        .is_some_and(|meta| filter.matches(&meta))
+            });
+        let matching: Vec<_> = matching.collect();
+        self.filter_cache.write().insert(filter, matching)
+    }
+
+    /// Drop a cached filter by ID
+    pub fn uncache_filter(&self, id: &str) -> bool {
+        self.filter_cache.write().remove(id)
+    }
+
+    /// Currently cached filters
+    pub fn cached_filters(&self) -> Vec<CachedFilterInfo> {
+        self.filter_cache.read().list()
+    }
+
+    /// Start rebuilding the metadata index of `field`, returning the slots
+    /// to go through with [`rebuild_index_chunk`](Self::rebuild_index_chunk)
+    ///
+    /// Filters on the field scan the payloads until
+    /// [`finish_index_rebuild`](Self::finish_index_rebuild). Writes meanwhile
+    /// index their own records, so slots added later need no rebuilding.
+    pub fn begin_index_rebuild(&self, field: &str) -> Result<usize> {
+        if !self.bitmap_index.write().begin_rebuild(field) {
+            return Err(Error::CollectionBusy(format!(
+                "The index of {} is already being rebuilt",
+                field
+            )));
+        }
+        Ok(self.ids.read().slots())
+    }
+
+    /// Index `field` for the records in `slots`, from their stored payloads
+    pub fn rebuild_index_chunk(&self, field: &str, slots: std::ops::Range<usize>) {
+        let metadata = self.metadata.read();
+        let mut bitmap_index = self.bitmap_index.write();
+        for internal_id in slots.map(InternalId::from) {
+            if let Some(meta) = metadata.get(internal_id) {
+                bitmap_index.index_field(internal_id, &meta, field);
+            }
+        }
+    }
+
+    pub fn finish_index_rebuild(&self, field: &str) {
+        self.bitmap_index.write().finish_rebuild(field);
+    }
+
     /// Get a vector by its internal ID
     #[inline]
     pub fn get(&self, internal_id: InternalId) -> Option<Vec<f32>> {
@@ -262,28 +424,144 @@ impl VectorStorage {
 
     /// Get metadata for a vector
     pub fn get_metadata(&self, internal_id: InternalId) -> Option<Value> {
-        self.metadata.read().get(&internal_id).cloned()
+        self.metadata.read().get(internal_id)
+    }
+
+    /// Top `k` records by BM25 score for `query`, among those `accept` allows
+    ///
+    /// Fails if the storage indexes no text fields.
+    pub fn search_text(
+        &self,
+        query: &str,
+        k: usize,
+        accept: impl Fn(InternalId) -> bool,
+    ) -> Result<Vec<(InternalId, f32)>> {
+        let index = self.text_index.read();
+        if !index.is_enabled() {
+            return Err(Error::InvalidConfig(
+                "Collection has no text fields; set text_fields when creating it".to_string(),
+            ));
+        }
+        Ok(index.search(query, k, accept))
     }
 
     /// Get internal ID from external ID
     pub fn get_internal_id(&self, id: &VectorId) -> Option<InternalId> {
-        self.id_to_internal.read().get(id).copied()
+        self.ids.read().get(id)
+    }
+
+    /// Page through live records in slot order, starting a listing if `cursor` is `None`
+    pub fn list_page(&self, cursor: Option<ListCursor>, limit: usize) -> Result<ListPage> {
+        let ids = self.ids.read();
+        let (slots, next) = ids.page(cursor.unwrap_or_else(|| ids.start_listing()), limit)?;
+        let metadata = self.metadata.read();
+        let records = slots
+            .into_iter()
+            .filter_map(|internal_id| {
+                Some((
+                    ids.external(internal_id)?.clone(),
+                    metadata.get(internal_id),
+                ))
+            })
+            .collect();
+        Ok(ListPage { records, next })
+    }
+
+    /// External IDs of live vectors whose metadata matches `filter`
+    pub fn ids_matching(&self, filter: &Filter) -> Result<Vec<VectorId>> {
+        let slots = self.slots_matching(filter)?;
+        let ids = self.ids.read();
+        Ok(slots
+            .into_iter()
+            .filter_map(|internal_id| ids.external(internal_id).cloned())
+            .collect())
+    }
+
+    /// Number of live vectors, or of those whose metadata matches `filter`
+    pub fn count(&self, filter: Option<&Filter>) -> Result<usize> {
+        Ok(match filter {
+            Some(filter) => self.slots_matching(filter)?.len(),
+            None => self.len(),
+        })
+    }
+
+    /// Slots of live vectors whose metadata matches `filter`
+    fn slots_matching(&self, filter: &Filter) -> Result<Vec<InternalId>> {
+        let ids = self.ids.read();
+        let metadata = self.metadata.read();
+        let cached = self.filter_cache.read().get(filter);
+        let candidates: Vec<InternalId> =
+            match cached.or_else(|| self.bitmap_index.read().filter(filter)) {
+                Some(bitmap) => bitmap
+                    .iter()
+                    .map(|i| InternalId::from(i as usize))
+                    .collect(),
+                None => (0..ids.slots()).map(InternalId::from).collect(),
+            };
+
+        candidates
+            .into_iter()
+            .filter(|&internal_id| {
+                ids.external(internal_id)
+                    .is_some_and(|id| ids.get(id) == Some(internal_id))
+            })
+            .filter_map(|internal_id| {
+                let meta = metadata.get(internal_id)?;
+                let matched = filter.evaluate(&meta);
+                matched.map(|m| m.then_some(internal_id)).transpose()
+            })
+            .collect()
+    }
+
+    /// Get metadata for several external IDs, skipping unknown ones
+    pub fn get_metadata_batch(&self, ids: &[VectorId]) -> Vec<(VectorId, Option<Value>)> {
+        let found: Vec<_> = {
+            let id_map = self.ids.read();
+            ids.iter()
+                .filter_map(|id| Some((id.clone(), id_map.get(id)?)))
+                .collect()
+        };
+        let metadata = self.metadata.read();
+        found
+            .into_iter()
+            .map(|(id, internal_id)| (id, metadata.get(internal_id)))
+            .collect()
     }
 
     /// Get external ID from internal ID
     pub fn get_external_id(&self, internal_id: InternalId) -> Option<VectorId> {
-        let internal_to_id = self.internal_to_id.read();
-        internal_to_id.get(internal_id.as_usize()).cloned()
+        self.ids.read().external(internal_id).cloned()
     }
 
     /// Get the number of active vectors
     pub fn len(&self) -> usize {
-        self.id_to_internal.read().len()
+        self.ids.read().len()
     }
 
     /// Get the total number of slots used (including stale/deleted)
     pub fn total_slots(&self) -> usize {
-        self.internal_to_id.read().len()
+        self.ids.read().slots()
+    }
+
+    /// Get the number of slots held by deleted or overwritten vectors
+    pub fn deleted_count(&self) -> usize {
+        self.total_slots().saturating_sub(self.len())
+    }
+
+    /// Count the payload of a deleted or overwritten record as garbage
+    fn bury_payload(&self, metadata: &Value) {
+        self.dead_payload_bytes
+            .fetch_add(value_heap_size(metadata), Ordering::Relaxed);
+    }
+
+    /// Space held by deleted or overwritten records
+    pub fn garbage_stats(&self) -> GarbageStats {
+        GarbageStats::new(
+            self.total_slots(),
+            self.deleted_count(),
+            self.vector_bytes(),
+            self.dead_payload_bytes.load(Ordering::Relaxed),
+        )
     }
 
     /// Check if storage is empty
@@ -293,8 +571,7 @@ impl VectorStorage {
 
     /// Get all internal IDs
     pub fn all_internal_ids(&self) -> Vec<InternalId> {
-        let internal_to_id = self.internal_to_id.read();
-        (0..internal_to_id.len()).map(InternalId::from).collect()
+        (0..self.total_slots()).map(InternalId::from).collect()
     }
 
     /// Get dimensionality
@@ -304,26 +581,67 @@ impl VectorStorage {
 
     /// Get approximate memory usage in bytes
     pub fn memory_usage(&self) -> usize {
-        let vectors_size = self.vectors.read().capacity() * 4;
-        // Approximation for maps:
-        // id_to_internal: capacity * (size_of<String> + heap_overhead + size_of<InternalId> + map_overhead)
-        // internal_to_id: capacity * (size_of<String> + heap_overhead)
-        // Assuming avg string len 16 + overhead
-        let count = self.len();
-        let map_overhead = count * (64 + 4);
-        let rev_map_overhead = count * 64;
+        self.vector_bytes() + self.id_bytes() + self.metadata_bytes()
+    }
 
-        vectors_size + map_overhead + rev_map_overhead
+    /// Bytes used by raw vector data
+    pub fn vector_bytes(&self) -> usize {
+        self.vectors.read().capacity() * std::mem::size_of::<f32>()
+    }
+
+    /// Approximate bytes used by the ID mapping
+    pub fn id_bytes(&self) -> usize {
+        self.ids.read().memory_usage()
+    }
+
+    /// Approximate bytes used by metadata and its filter and text indexes
+    pub fn metadata_bytes(&self) -> usize {
+        self.metadata.read().memory_usage()
+            + self.bitmap_index.read().memory_usage()
+            + self.text_index.read().memory_usage()
+            + self.filter_cache.read().memory_usage()
+    }
+
+    /// The `n` records with the largest metadata, largest first
+    pub fn largest_payloads(&self, n: usize) -> Vec<PayloadSize> {
+        let ids = self.ids.read();
+        self.metadata
+            .read()
+            .largest(n)
+            .into_iter()
+            .filter_map(|(internal_id, bytes)| {
+                Some(PayloadSize {
+                    id: ids.external(internal_id)?.clone(),
+                    bytes,
+                })
+            })
+            .collect()
+    }
+
+    /// Uncompressed over stored metadata size, if metadata is compressed
+    pub fn metadata_compression_ratio(&self) -> Option<f64> {
+        self.metadata.read().compression_ratio()
     }
 
     /// Create a view of the storage that holds a read lock
     /// This is optimized for bulk operations like search
     pub fn view(&self) -> VectorStorageView<'_> {
+        self.view_inner(true)
+    }
+
+    /// Like [`view`](Self::view), but only locks metadata if `filter` needs it,
+    /// so unfiltered searches never touch the payload store
+    pub fn search_view(&self, filter: Option<&Filter>) -> VectorStorageView<'_> {
+        self.view_inner(filter.is_some())
+    }
+
+    fn view_inner(&self, with_metadata: bool) -> VectorStorageView<'_> {
         VectorStorageView {
             guard: self.vectors.read(),
-            metadata_guard: self.metadata.read(),
+            metadata_guard: with_metadata.then(|| self.metadata.read()),
             deleted_guard: self.deleted.read(),
             bitmap_guard: self.bitmap_index.read(),
+            filter_cache_guard: self.filter_cache.read(),
             dimensions: self.dimensions,
         }
     }
@@ -333,9 +651,10 @@ impl VectorStorage {
 /// This avoids repeated locking during search
 pub struct VectorStorageView<'a> {
     guard: crate::sync::RwLockReadGuard<'a, Vec<f32>>,
-    metadata_guard: crate::sync::RwLockReadGuard<'a, HashMap<InternalId, Value>>,
+    metadata_guard: Option<crate::sync::RwLockReadGuard<'a, MetadataStore>>,
     deleted_guard: crate::sync::RwLockReadGuard<'a, std::collections::HashSet<InternalId>>,
     bitmap_guard: crate::sync::RwLockReadGuard<'a, BitmapIndex>,
+    filter_cache_guard: crate::sync::RwLockReadGuard<'a, FilterCache>,
     dimensions: usize,
 }
 
@@ -372,11 +691,17 @@ impl<'a> VectorStorageTrait for VectorStorageView<'a> {
         if self.deleted_guard.contains(&internal_id) {
             return None;
         }
-        self.metadata_guard.get(&internal_id).cloned()
+        self.metadata_guard.as_ref()?.get(internal_id)
     }
 
     fn filter_bitmap(&self, filter: &Filter) -> Option<Arc<RoaringBitmap>> {
-        self.bitmap_guard.filter(filter)
+        self.filter_cache_guard
+            .get(filter)
+            .or_else(|| self.bitmap_guard.filter(filter))
+    }
+
+    fn filter_entry(&self, filter: &Filter) -> Option<InternalId> {
+        self.filter_cache_guard.entry_point(filter)
     }
 
     fn is_deleted(&self, internal_id: InternalId) -> bool {
@@ -411,7 +736,7 @@ impl VectorStorageTrait for VectorStorage {
         if self.deleted.read().contains(&internal_id) {
             return None;
         }
-        self.metadata.read().get(&internal_id).cloned()
+        self.metadata.read().get(internal_id)
     }
 
     fn is_deleted(&self, internal_id: InternalId) -> bool {
@@ -0,0 +1,305 @@
+//! Cluster summaries of a collection
+//!
+//! A summary splits a collection into at most `k` clusters for a quick
+//! overview of what it holds, or to seed IVF lists and navigation UIs.
+//! Centroids are found with k-means (k-means++ seeding) on a sample of at most
+//! [`MAX_SUMMARY_SAMPLE`] records; then every record is assigned to its
+//! nearest centroid to count the cluster sizes and find the members nearest
+//! each centroid. The nearest member stands in for the cluster's medoid, which
+//! would take quadratic work to find exactly.
+//!
+//! Records are compared with the collection's metric, except under dot
+//! product, where clusters are formed by direction (cosine), since a mean
+//! vector would otherwise attract every record. Sampling is seeded, so the
+//! same records give the same summary.
+
+use crate::distance::DistanceMetric;
+use crate::error::{Error, Result};
+use crate::scan::ScanRecord;
+use crate::types::VectorId;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Serialize;
+
+/// Most clusters a summary may ask for
+pub const MAX_SUMMARY_CLUSTERS: usize = 256;
+
+/// Most records the centroids are fitted on
+pub const MAX_SUMMARY_SAMPLE: usize = 10_000;
+
+/// Members listed per cluster, nearest the centroid first
+pub const SUMMARY_REPRESENTATIVES: usize = 5;
+
+/// Most k-means iterations on the sample
+const MAX_ITERATIONS: usize = 25;
+
+/// One cluster of a [`CollectionSummary`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ClusterSummary {
+    /// Mean of the sampled members
+    pub centroid: Vec<f32>,
+    /// Records assigned to the cluster
+    pub size: usize,
+    /// Member nearest the centroid
+    pub medoid: VectorId,
+    /// Up to [`SUMMARY_REPRESENTATIVES`] members nearest the centroid,
+    /// starting with the medoid
+    pub representatives: Vec<VectorId>,
+}
+
+/// Clusters of a collection, largest first
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CollectionSummary {
+    pub clusters: Vec<ClusterSummary>,
+    /// Records assigned to a cluster
+    pub vector_count: usize,
+    /// Records the centroids were fitted on
+    pub sampled: usize,
+    /// Write sequence of the collection when the summary was computed
+    pub write_seq: u64,
+}
+
+/// Check the cluster count of a summary
+pub fn validate_k(k: usize) -> Result<()> {
+    if k == 0 || k > MAX_SUMMARY_CLUSTERS {
+        return Err(Error::InvalidConfig(format!(
+            "Summary cluster count must be between 1 and {}",
+            MAX_SUMMARY_CLUSTERS
+        )));
+    }
+    Ok(())
+}
+
+/// Summarize the records yielded by `scan` into at most `k` clusters
+///
+/// `scan` is called twice: once to sample, once to assign every record.
+pub(crate) fn summarize<I>(
+    mut scan: impl FnMut() -> I,
+    k: usize,
+    metric: DistanceMetric,
+    write_seq: u64,
+) -> Result<CollectionSummary>
+where
+    I: Iterator<Item = Result<ScanRecord>>,
+{
+    validate_k(k)?;
+    let metric = match metric {
+        DistanceMetric::DotProduct => DistanceMetric::Cosine,
+        metric => metric,
+    };
+    let mut rng = StdRng::seed_from_u64(k as u64);
+
+    // Reservoir sample of the vectors
+    let mut sample: Vec<Vec<f32>> = Vec::new();
+    for (seen, record) in scan().enumerate() {
+        let (_, vector, _) = record?;
+        if sample.len() < MAX_SUMMARY_SAMPLE {
+            sample.push(vector);
+        } else {
+            let slot = rng.gen_range(0..=seen);
+            if slot < MAX_SUMMARY_SAMPLE {
+                sample[slot] = vector;
+            }
+        }
+    }
+    let sampled = sample.len();
+    let centroids = kmeans(&sample, k, metric, &mut rng);
+    drop(sample);
+
+    // Assign every record, keeping the members nearest each centroid
+    let mut sizes = vec![0usize; centroids.len()];
+    let mut nearest: Vec<Vec<(f32, VectorId)>> = vec![Vec::new(); centroids.len()];
+    let mut vector_count = 0;
+    for record in scan() {
+        let (id, vector, _) = record?;
+        let Some((cluster, distance)) = closest(&vector, &centroids, metric) else {
+            break;
+        };
+        vector_count += 1;
+        sizes[cluster] += 1;
+        let members = &mut nearest[cluster];
+        if members.len() < SUMMARY_REPRESENTATIVES || distance < members[members.len() - 1].0 {
+            let at = members.partition_point(|(d, _)| *d <= distance);
+            members.insert(at, (distance, id));
+            members.truncate(SUMMARY_REPRESENTATIVES);
+        }
+    }
+
+    let mut clusters: Vec<ClusterSummary> = centroids
+        .into_iter()
+        .zip(sizes)
+        .zip(nearest)
+        .filter_map(|((centroid, size), members)| {
+            let representatives: Vec<VectorId> = members.into_iter().map(|(_, id)| id).collect();
+            Some(ClusterSummary {
+                centroid,
+                size,
+                medoid: representatives.first()?.clone(),
+                representatives,
+            })
+        })
+        .collect();
+    clusters.sort_by_key(|c| std::cmp::Reverse(c.size));
+    Ok(CollectionSummary {
+        clusters,
+        vector_count,
+        sampled,
+        write_seq,
+    })
+}
+
+/// Index of the centroid nearest `vector`, and its distance
+fn closest(vector: &[f32], centroids: &[Vec<f32>], metric: DistanceMetric) -> Option<(usize, f32)> {
+    centroids
+        .iter()
+        .map(|c| metric.distance(vector, c))
+        .enumerate()
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+/// Fit at most `k` centroids to `vectors`
+fn kmeans(
+    vectors: &[Vec<f32>],
+    k: usize,
+    metric: DistanceMetric,
+    rng: &mut StdRng,
+) -> Vec<Vec<f32>> {
+    let Some(first) = vectors.first() else {
+        return Vec::new();
+    };
+    let dim = first.len();
+
+    // k-means++: each further seed is drawn in proportion to its squared
+    // distance from the nearest seed so far; stop early once every vector
+    // coincides with a seed
+    let mut centroids = vec![vectors[rng.gen_range(0..vectors.len())].clone()];
+    let mut weights: Vec<f64> = vectors
+        .iter()
+        .map(|v| (metric.distance(v, &centroids[0]).max(0.0) as f64).powi(2))
+        .collect();
+    while centroids.len() < k {
+        let total: f64 = weights.iter().sum();
+        if total <= 0.0 || !total.is_finite() {
+            break;
+        }
+        let mut target = rng.gen_range(0.0..total);
+        let mut chosen = weights.len() - 1;
+        for (i, w) in weights.iter().enumerate() {
+            if target < *w {
+                chosen = i;
+                break;
+            }
+            target -= w;
+        }
+        let seed = vectors[chosen].clone();
+        for (w, v) in weights.iter_mut().zip(vectors) {
+            *w = w.min((metric.distance(v, &seed).max(0.0) as f64).powi(2));
+        }
+        centroids.push(seed);
+    }
+
+    let mut assignment = vec![usize::MAX; vectors.len()];
+    for _ in 0..MAX_ITERATIONS {
+        let mut changed = false;
+        for (slot, v) in assignment.iter_mut().zip(vectors) {
+            let (cluster, _) = closest(v, &centroids, metric).unwrap_or_default();
+            if *slot != cluster {
+                *slot = cluster;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+        let mut sums = vec![vec![0.0f64; dim]; centroids.len()];
+        let mut counts = vec![0usize; centroids.len()];
+        for (&cluster, v) in assignment.iter().zip(vectors) {
+            counts[cluster] += 1;
+            for (s, x) in sums[cluster].iter_mut().zip(v) {
+                *s += *x as f64;
+            }
+        }
+        for ((centroid, sum), count) in centroids.iter_mut().zip(sums).zip(counts) {
+            // An emptied cluster keeps its centroid
+            if count > 0 {
+                *centroid = sum.into_iter().map(|s| (s / count as f64) as f32).collect();
+            }
+        }
+    }
+    centroids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records(points: &[(f32, f32)]) -> Vec<ScanRecord> {
+        points
+            .iter()
+            .enumerate()
+            .map(|(i, &(x, y))| (VectorId::from(format!("v{}", i)), vec![x, y], None))
+            .collect()
+    }
+
+    #[test]
+    fn test_summarize_separated_clusters() {
+        let mut points = Vec::new();
+        for i in 0..30 {
+            let jitter = i as f32 * 0.01;
+            points.push((jitter, jitter));
+            points.push((10.0 + jitter, 10.0 - jitter));
+        }
+        points.push((10.145, 9.855));
+        let records = records(&points);
+        let summary = summarize(
+            || records.iter().cloned().map(Ok),
+            2,
+            DistanceMetric::Euclidean,
+            7,
+        )
+        .unwrap();
+
+        assert_eq!(summary.vector_count, 61);
+        assert_eq!(summary.sampled, 61);
+        assert_eq!(summary.write_seq, 7);
+        let sizes: Vec<usize> = summary.clusters.iter().map(|c| c.size).collect();
+        assert_eq!(sizes, vec![31, 30]);
+        let big = &summary.clusters[0];
+        assert!((big.centroid[0] - 10.145).abs() < 0.01);
+        assert_eq!(big.representatives.len(), SUMMARY_REPRESENTATIVES);
+        assert_eq!(big.medoid, big.representatives[0]);
+        // The added point sits right on the centroid of the far cluster
+        assert_eq!(big.medoid.to_string(), "v60");
+    }
+
+    #[test]
+    fn test_summarize_fewer_distinct_points_than_k() {
+        let records = records(&[(1.0, 1.0), (1.0, 1.0), (2.0, 2.0)]);
+        let summary = summarize(
+            || records.iter().cloned().map(Ok),
+            8,
+            DistanceMetric::Euclidean,
+            0,
+        )
+        .unwrap();
+        assert_eq!(summary.clusters.len(), 2);
+        assert_eq!(summary.vector_count, 3);
+
+        let empty: Vec<ScanRecord> = Vec::new();
+        let summary = summarize(
+            || empty.iter().cloned().map(Ok),
+            4,
+            DistanceMetric::Cosine,
+            0,
+        )
+        .unwrap();
+        assert!(summary.clusters.is_empty());
+    }
+
+    #[test]
+    fn test_validate_k() {
+        assert!(validate_k(0).is_err());
+        assert!(validate_k(1).is_ok());
+        assert!(validate_k(MAX_SUMMARY_CLUSTERS + 1).is_err());
+    }
+}
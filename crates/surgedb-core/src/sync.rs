@@ -70,3 +70,26 @@ mod single_threaded {
 
 #[cfg(any(not(feature = "parallel"), target_arch = "wasm32"))]
 pub use single_threaded::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// `Send` where collections are shared between threads; nothing on
+/// single-threaded targets, whose locks are neither `Send` nor `Sync`
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+pub trait MaybeSend: Send {}
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+impl<T: Send + ?Sized> MaybeSend for T {}
+
+/// `Sync` where collections are shared between threads, like [`MaybeSend`]
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+pub trait MaybeSync: Sync {}
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+impl<T: Sync + ?Sized> MaybeSync for T {}
+
+#[cfg(any(not(feature = "parallel"), target_arch = "wasm32"))]
+pub trait MaybeSend {}
+#[cfg(any(not(feature = "parallel"), target_arch = "wasm32"))]
+impl<T: ?Sized> MaybeSend for T {}
+
+#[cfg(any(not(feature = "parallel"), target_arch = "wasm32"))]
+pub trait MaybeSync {}
+#[cfg(any(not(feature = "parallel"), target_arch = "wasm32"))]
+impl<T: ?Sized> MaybeSync for T {}
@@ -0,0 +1,215 @@
+//! Full-text index over declared metadata fields, scored with BM25
+//!
+//! Each record's text is the concatenation of its indexed fields (strings,
+//! or arrays of strings), split into lowercase alphanumeric tokens. There is
+//! no stemming or stop word list.
+
+use crate::filter::get_value_by_path;
+use crate::types::InternalId;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Term frequency saturation
+const K1: f32 = 1.2;
+/// Document length normalization
+const B: f32 = 0.75;
+
+/// Lowercase alphanumeric tokens of `text`
+pub fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// Inverted index of the text in a fixed set of metadata fields
+#[derive(Default)]
+pub struct TextIndex {
+    /// Dot-notation paths of the indexed fields
+    fields: Vec<String>,
+    /// term -> record -> term frequency
+    postings: HashMap<String, HashMap<InternalId, u32>>,
+    /// Token count of each record with indexed text
+    lengths: HashMap<InternalId, u32>,
+    total_length: u64,
+}
+
+impl TextIndex {
+    pub fn new(fields: Vec<String>) -> Self {
+        Self {
+            fields,
+            ..Default::default()
+        }
+    }
+
+    /// Whether any fields are indexed
+    pub fn is_enabled(&self) -> bool {
+        !self.fields.is_empty()
+    }
+
+    pub fn fields(&self) -> &[String] {
+        &self.fields
+    }
+
+    /// Term frequencies of the indexed fields of `metadata`, and its length
+    fn terms(&self, metadata: &Value) -> (HashMap<String, u32>, u32) {
+        let mut terms = HashMap::new();
+        let mut length = 0;
+        for field in &self.fields {
+            let texts: Vec<&str> = match get_value_by_path(metadata, field) {
+                Some(Value::String(s)) => vec![s],
+                Some(Value::Array(items)) => items.iter().filter_map(Value::as_str).collect(),
+                _ => continue,
+            };
+            for token in texts.into_iter().flat_map(tokenize) {
+                *terms.entry(token).or_default() += 1;
+                length += 1;
+            }
+        }
+        (terms, length)
+    }
+
+    /// Index the text of a record's metadata
+    pub fn index(&mut self, internal_id: InternalId, metadata: &Value) {
+        if !self.is_enabled() {
+            return;
+        }
+        let (terms, length) = self.terms(metadata);
+        if length == 0 {
+            return;
+        }
+        for (term, tf) in terms {
+            self.postings
+                .entry(term)
+                .or_default()
+                .insert(internal_id, tf);
+        }
+        self.lengths.insert(internal_id, length);
+        self.total_length += u64::from(length);
+    }
+
+    /// Drop a record, given the metadata it was indexed with
+    pub fn remove(&mut self, internal_id: InternalId, metadata: &Value) {
+        let Some(length) = self.lengths.remove(&internal_id) else {
+            return;
+        };
+        self.total_length -= u64::from(length);
+        for term in self.terms(metadata).0.into_keys() {
+            if let Some(records) = self.postings.get_mut(&term) {
+                records.remove(&internal_id);
+                if records.is_empty() {
+                    self.postings.remove(&term);
+                }
+            }
+        }
+    }
+
+    /// Top `k` records by BM25 score for `query`, among those `accept` allows
+    ///
+    /// Only records containing at least one query term are considered, and
+    /// `accept` is asked about each of them once.
+    pub fn search(
+        &self,
+        query: &str,
+        k: usize,
+        accept: impl Fn(InternalId) -> bool,
+    ) -> Vec<(InternalId, f32)> {
+        let mut query_terms: Vec<String> = tokenize(query).collect();
+        query_terms.sort();
+        query_terms.dedup();
+
+        let documents = self.lengths.len() as f32;
+        let average_length = self.total_length as f32 / documents.max(1.0);
+        let mut scores: HashMap<InternalId, f32> = HashMap::new();
+        for term in &query_terms {
+            let Some(records) = self.postings.get(term) else {
+                continue;
+            };
+            let n = records.len() as f32;
+            let idf = (1.0 + (documents - n + 0.5) / (n + 0.5)).ln();
+            for (&id, &tf) in records {
+                let tf = tf as f32;
+                let length = self.lengths[&id] as f32;
+                let norm = K1 * (1.0 - B + B * length / average_length);
+                *scores.entry(id).or_default() += idf * tf * (K1 + 1.0) / (tf + norm);
+            }
+        }
+
+        let mut results: Vec<_> = scores.into_iter().filter(|&(id, _)| accept(id)).collect();
+        results.sort_by(|a, b| {
+            b.1.partial_cmp(&a.1)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(a.0.as_u32().cmp(&b.0.as_u32()))
+        });
+        results.truncate(k);
+        results
+    }
+
+    /// Records with indexed text
+    pub fn len(&self) -> usize {
+        self.lengths.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lengths.is_empty()
+    }
+
+    /// Approximate heap bytes of the postings and lengths
+    pub fn memory_usage(&self) -> usize {
+        let entry = std::mem::size_of::<(InternalId, u32)>();
+        let postings: usize = self
+            .postings
+            .iter()
+            .map(|(term, records)| term.capacity() + records.capacity() * entry)
+            .sum();
+        postings + self.lengths.capacity() * entry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn id(n: usize) -> InternalId {
+        InternalId::from(n)
+    }
+
+    #[test]
+    fn test_tokenize() {
+        let tokens: Vec<String> = tokenize("Rust's HNSW-index, v2 (Über)").collect();
+        assert_eq!(tokens, vec!["rust", "s", "hnsw", "index", "v2", "über"]);
+    }
+
+    #[test]
+    fn test_bm25_ranking() {
+        let mut index = TextIndex::new(vec!["title".into(), "meta.tags".into()]);
+        let docs = [
+            json!({ "title": "Rust vector database" }),
+            json!({ "title": "A database written in rust, in rust!" }),
+            json!({ "title": "Gardening tips", "meta": { "tags": ["rust", "fungus"] } }),
+            json!({ "body": "rust, but not in an indexed field" }),
+        ];
+        for (i, doc) in docs.iter().enumerate() {
+            index.index(id(i), doc);
+        }
+        assert_eq!(index.len(), 3);
+
+        // The rarer term decides the order
+        let results = index.search("vector RUST", 10, |_| true);
+        let order: Vec<_> = results.iter().map(|r| r.0).collect();
+        assert_eq!(order[0], id(0));
+        assert_eq!(order.len(), 3);
+        assert!(results.windows(2).all(|w| w[0].1 >= w[1].1));
+
+        // Higher term frequency wins among otherwise similar records
+        let results = index.search("rust", 10, |i| i != id(0));
+        assert_eq!(results[0].0, id(1));
+
+        assert!(index.search("missing", 10, |_| true).is_empty());
+
+        index.remove(id(1), &docs[1]);
+        let results = index.search("written", 10, |_| true);
+        assert!(results.is_empty());
+        assert_eq!(index.len(), 2);
+    }
+}
@@ -0,0 +1,138 @@
+//! Linear transforms of the vectors a collection is queried and written with
+//!
+//! Post-processing of embeddings such as centering, PCA whitening or removing
+//! the top principal directions (all-but-the-top) maps each vector `x` to
+//! `W (x - mean)`. Kept in the collection's configuration, the transform is
+//! applied to every query vector, and with `apply_on_insert` to every vector
+//! written, so clients send raw embeddings and the math can't drift out of
+//! sync with the stored data. Named vector spaces are not transformed.
+
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+
+/// `x ↦ matrix · (x - mean)`, with either part optional
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VectorTransform {
+    /// Subtracted first
+    #[serde(default)]
+    pub mean: Option<Vec<f32>>,
+    /// Square matrix applied after centering, as rows
+    #[serde(default)]
+    pub matrix: Option<Vec<Vec<f32>>>,
+    /// Transform written vectors as well as queries
+    ///
+    /// Stored vectors are then in the transformed space, and the transform
+    /// can only be changed while the collection is empty.
+    #[serde(default)]
+    pub apply_on_insert: bool,
+}
+
+impl VectorTransform {
+    /// Subtract `mean` from every vector
+    pub fn centering(mean: Vec<f32>) -> Self {
+        Self {
+            mean: Some(mean),
+            ..Self::default()
+        }
+    }
+
+    /// Check the transform fits vectors of `dimensions`
+    pub fn validate(&self, dimensions: usize) -> Result<()> {
+        let invalid = |message: String| Err(Error::InvalidConfig(message));
+        if let Some(mean) = &self.mean {
+            if mean.len() != dimensions {
+                return invalid(format!(
+                    "Transform mean has {} dimensions, expected {}",
+                    mean.len(),
+                    dimensions
+                ));
+            }
+            if mean.iter().any(|x| !x.is_finite()) {
+                return invalid("Transform mean must be finite".to_string());
+            }
+        }
+        if let Some(matrix) = &self.matrix {
+            if matrix.len() != dimensions || matrix.iter().any(|row| row.len() != dimensions) {
+                return invalid(format!(
+                    "Transform matrix must be {} x {}",
+                    dimensions, dimensions
+                ));
+            }
+            if matrix.iter().flatten().any(|x| !x.is_finite()) {
+                return invalid("Transform matrix must be finite".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// `vector` transformed
+    pub fn apply(&self, vector: &[f32]) -> Result<Vec<f32>> {
+        let dimensions = self
+            .mean
+            .as_ref()
+            .map(Vec::len)
+            .or_else(|| self.matrix.as_ref().map(Vec::len))
+            .unwrap_or(vector.len());
+        if vector.len() != dimensions {
+            return Err(Error::DimensionMismatch {
+                expected: dimensions,
+                got: vector.len(),
+            });
+        }
+        let centered: Vec<f32> = match &self.mean {
+            Some(mean) => vector.iter().zip(mean).map(|(x, m)| x - m).collect(),
+            None => vector.to_vec(),
+        };
+        Ok(match &self.matrix {
+            Some(matrix) => matrix
+                .iter()
+                .map(|row| row.iter().zip(&centered).map(|(w, x)| w * x).sum())
+                .collect(),
+            None => centered,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_centers_then_multiplies() {
+        let transform = VectorTransform {
+            mean: Some(vec![1.0, 2.0]),
+            matrix: Some(vec![vec![2.0, 0.0], vec![1.0, 1.0]]),
+            apply_on_insert: false,
+        };
+        assert!(transform.validate(2).is_ok());
+        assert_eq!(transform.apply(&[3.0, 5.0]).unwrap(), vec![4.0, 5.0]);
+        assert_eq!(
+            VectorTransform::centering(vec![1.0, 1.0])
+                .apply(&[1.0, 3.0])
+                .unwrap(),
+            vec![0.0, 2.0]
+        );
+        assert!(matches!(
+            transform.apply(&[1.0]),
+            Err(Error::DimensionMismatch {
+                expected: 2,
+                got: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn test_validate_shapes() {
+        assert!(VectorTransform::centering(vec![0.0; 3])
+            .validate(2)
+            .is_err());
+        let ragged = VectorTransform {
+            matrix: Some(vec![vec![1.0, 0.0], vec![1.0]]),
+            ..VectorTransform::default()
+        };
+        assert!(ragged.validate(2).is_err());
+        let infinite = VectorTransform::centering(vec![f32::NAN, 0.0]);
+        assert!(infinite.validate(2).is_err());
+        assert!(VectorTransform::default().validate(2).is_ok());
+    }
+}
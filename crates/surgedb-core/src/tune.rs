@@ -0,0 +1,250 @@
+//! HNSW parameter sweeps against exact ground truth
+//!
+//! [`sweep`] holds out a few sample vectors as queries, finds their exact
+//! nearest neighbors in the rest of the sample by brute force, then builds a
+//! throwaway index for every `m` x `ef_construction` pair and times searches
+//! at every `ef_search`. The recommended trial is the fastest one reaching
+//! the recall target (and the latency budget, if any), or the most accurate
+//! one when none does.
+
+use crate::distance::DistanceMetric;
+use crate::error::{Error, Result};
+use crate::hnsw::HnswConfig;
+use crate::types::SearchParams;
+use crate::{Config, VectorDb};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::time::Instant;
+
+/// What to sweep and what to aim for
+#[derive(Debug, Clone)]
+pub struct TuneOptions {
+    /// Vectors indexed by every trial
+    pub sample_size: usize,
+    /// Held-out vectors searched by every trial
+    pub queries: usize,
+    /// Neighbors per query that recall is measured on
+    pub k: usize,
+    /// Minimum recall@k (0.0-1.0) of an acceptable trial
+    pub target_recall: f64,
+    /// Maximum mean search latency of an acceptable trial
+    pub max_latency_us: Option<f64>,
+    pub m: Vec<usize>,
+    pub ef_construction: Vec<usize>,
+    pub ef_search: Vec<usize>,
+}
+
+impl Default for TuneOptions {
+    fn default() -> Self {
+        Self {
+            sample_size: 2000,
+            queries: 50,
+            k: 10,
+            target_recall: 0.95,
+            max_latency_us: None,
+            m: vec![8, 16, 32],
+            ef_construction: vec![100, 200, 400],
+            ef_search: vec![16, 32, 64, 128, 256],
+        }
+    }
+}
+
+/// One parameter combination and how it performed
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TuneTrial {
+    pub m: usize,
+    pub ef_construction: usize,
+    pub ef_search: usize,
+    /// Mean recall@k over the queries
+    pub recall: f64,
+    /// Mean search latency over the queries
+    pub mean_latency_us: f64,
+    /// Time to build the trial's index from the sample
+    pub build_ms: f64,
+}
+
+/// Outcome of a sweep
+#[derive(Debug, Clone, Serialize)]
+pub struct TuneReport {
+    /// Vectors indexed by each trial
+    pub sample_size: usize,
+    pub queries: usize,
+    pub k: usize,
+    pub trials: Vec<TuneTrial>,
+    pub recommended: TuneTrial,
+    /// Whether `recommended` meets the recall target and latency budget
+    pub target_met: bool,
+}
+
+/// Sweep HNSW parameters over `vectors`, which should be a random sample
+pub fn sweep(
+    metric: DistanceMetric,
+    mut vectors: Vec<Vec<f32>>,
+    options: &TuneOptions,
+) -> Result<TuneReport> {
+    if options.k == 0 || options.queries == 0 {
+        return Err(Error::InvalidConfig(
+            "k and queries must be at least 1".to_string(),
+        ));
+    }
+    if options.m.is_empty() || options.ef_construction.is_empty() || options.ef_search.is_empty() {
+        return Err(Error::InvalidConfig(
+            "m, ef_construction and ef_search need at least one value each".to_string(),
+        ));
+    }
+    if vectors.len() < options.queries + options.k {
+        return Err(Error::InvalidConfig(format!(
+            "Tuning needs at least {} vectors, the collection has {}",
+            options.queries + options.k,
+            vectors.len()
+        )));
+    }
+    let dimensions = vectors[0].len();
+
+    let queries = vectors.split_off(vectors.len() - options.queries);
+    vectors.truncate(options.sample_size);
+    let k = options.k.min(vectors.len());
+    let truth: Vec<HashSet<usize>> = queries
+        .iter()
+        .map(|query| {
+            let mut exact: Vec<(usize, f32)> = vectors
+                .iter()
+                .enumerate()
+                .map(|(i, v)| (i, metric.distance(query, v)))
+                .collect();
+            exact.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+            exact.into_iter().take(k).map(|(i, _)| i).collect()
+        })
+        .collect();
+    let mut trials = Vec::new();
+    for &m in &options.m {
+        for &ef_construction in &options.ef_construction {
+            let mut hnsw = HnswConfig::default().with_m(m);
+            hnsw.ef_construction = ef_construction;
+            hnsw.validate()?;
+
+            let build_start = Instant::now();
+            let mut db = VectorDb::new(Config {
+                dimensions,
+                distance_metric: metric,
+                hnsw,
+                ..Default::default()
+            })?;
+            // One at a time, like writes through a collection: a single batch
+            // into an empty graph leaves its nodes unlinked
+            for (i, vector) in vectors.iter().enumerate() {
+                db.insert(i.to_string(), vector, None)?;
+            }
+            let build_ms = build_start.elapsed().as_secs_f64() * 1000.0;
+
+            for &ef_search in &options.ef_search {
+                let params = SearchParams {
+                    ef_search: Some(ef_search.max(1)),
+                    ..Default::default()
+                };
+                let mut found = 0;
+                let search_start = Instant::now();
+                for (query, truth) in queries.iter().zip(&truth) {
+                    let (results, _) = db.search_ids_with_params(query, k, None, params)?;
+                    found += results
+                        .iter()
+                        .filter_map(|(id, _)| id.as_str().parse::<usize>().ok())
+                        .filter(|i| truth.contains(i))
+                        .count();
+                }
+                let elapsed_us = search_start.elapsed().as_secs_f64() * 1_000_000.0;
+
+                trials.push(TuneTrial {
+                    m,
+                    ef_construction,
+                    ef_search,
+                    recall: found as f64 / (queries.len() * k) as f64,
+                    mean_latency_us: elapsed_us / queries.len() as f64,
+                    build_ms,
+                });
+            }
+        }
+    }
+
+    let meets = |t: &TuneTrial| {
+        t.recall >= options.target_recall
+            && options
+                .max_latency_us
+                .is_none_or(|max| t.mean_latency_us <= max)
+    };
+    let fastest = trials
+        .iter()
+        .filter(|t| meets(t))
+        .min_by(|a, b| a.mean_latency_us.total_cmp(&b.mean_latency_us));
+    let most_accurate = || {
+        trials
+            .iter()
+            .max_by(|a, b| a.recall.total_cmp(&b.recall))
+            .expect("at least one trial")
+    };
+    let recommended = fastest.unwrap_or_else(most_accurate).clone();
+
+    Ok(TuneReport {
+        sample_size: vectors.len(),
+        queries: queries.len(),
+        k,
+        target_met: meets(&recommended),
+        recommended,
+        trials,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vector(i: usize) -> Vec<f32> {
+        (0..8).map(|d| ((i * 8 + d) as f32 * 0.37).sin()).collect()
+    }
+
+    #[test]
+    fn test_sweep_recommends_fastest_trial_meeting_target() {
+        let options = TuneOptions {
+            sample_size: 300,
+            queries: 20,
+            k: 5,
+            target_recall: 0.9,
+            m: vec![4, 16],
+            ef_construction: vec![50],
+            ef_search: vec![5, 100],
+            ..Default::default()
+        };
+        let vectors = (0..400).map(vector).collect();
+        let report = sweep(DistanceMetric::Euclidean, vectors, &options).unwrap();
+
+        assert_eq!(report.trials.len(), 4);
+        assert_eq!(report.sample_size, 300);
+        assert_eq!(report.queries, 20);
+        assert!(report.target_met);
+        assert!(report.recommended.recall >= 0.9);
+        for trial in report.trials.iter().filter(|t| t.recall >= 0.9) {
+            assert!(report.recommended.mean_latency_us <= trial.mean_latency_us);
+        }
+    }
+
+    #[test]
+    fn test_sweep_falls_back_to_most_accurate() {
+        let options = TuneOptions {
+            queries: 10,
+            k: 5,
+            target_recall: 1.1,
+            m: vec![8],
+            ef_construction: vec![50],
+            ef_search: vec![5, 50],
+            ..Default::default()
+        };
+        let vectors = (0..200).map(vector).collect();
+        let report = sweep(DistanceMetric::Cosine, vectors, &options).unwrap();
+        assert!(!report.target_met);
+        let best = report.trials.iter().map(|t| t.recall).fold(0.0, f64::max);
+        assert_eq!(report.recommended.recall, best);
+
+        let too_few = (0..10).map(vector).collect();
+        assert!(sweep(DistanceMetric::Cosine, too_few, &options).is_err());
+    }
+}
@@ -1,39 +1,124 @@
 //! Core types for SurgeDB
 
-use serde::{Deserialize, Serialize};
+use crate::error::{Error, Result};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
+use std::sync::Arc;
 
 /// A vector represented as a slice of f32 values
 pub type Vector = [f32];
 
 /// External vector identifier (user-facing)
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
-pub struct VectorId(String);
+///
+/// String IDs are shared (`Arc<str>`), so the copy held by a collection's ID
+/// map is reused by every clone handed out in results. Collections with
+/// [`IdType::U64`] keys hold integer IDs natively, without a heap allocation
+/// per key. IDs always serialize as strings, so persisted data does not depend
+/// on the key type.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct VectorId(IdRepr);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum IdRepr {
+    Str(Arc<str>),
+    Int(u64),
+}
 
 impl VectorId {
     pub fn new(id: impl Into<String>) -> Self {
-        Self(id.into())
+        Self(IdRepr::Str(Arc::from(id.into())))
+    }
+
+    pub fn as_str(&self) -> Cow<'_, str> {
+        match &self.0 {
+            IdRepr::Str(s) => Cow::Borrowed(&**s),
+            IdRepr::Int(n) => Cow::Owned(n.to_string()),
+        }
     }
 
-    pub fn as_str(&self) -> &str {
-        &self.0
+    /// Heap bytes owned by this ID (the shared string and its reference counts)
+    pub(crate) fn heap_size(&self) -> usize {
+        match &self.0 {
+            IdRepr::Str(s) => s.len() + 2 * std::mem::size_of::<usize>(),
+            IdRepr::Int(_) => 0,
+        }
+    }
+
+    /// The integer value of an ID held natively as `u64`
+    pub fn as_u64(&self) -> Option<u64> {
+        match self.0 {
+            IdRepr::Int(n) => Some(n),
+            IdRepr::Str(_) => None,
+        }
     }
 }
 
 impl From<&str> for VectorId {
     fn from(s: &str) -> Self {
-        Self(s.to_string())
+        Self(IdRepr::Str(Arc::from(s)))
     }
 }
 
 impl From<String> for VectorId {
     fn from(s: String) -> Self {
-        Self(s)
+        Self::new(s)
+    }
+}
+
+impl From<u64> for VectorId {
+    fn from(n: u64) -> Self {
+        Self(IdRepr::Int(n))
     }
 }
 
 impl std::fmt::Display for VectorId {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+        match &self.0 {
+            IdRepr::Str(s) => f.write_str(s),
+            IdRepr::Int(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+impl Serialize for VectorId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for VectorId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::new)
+    }
+}
+
+/// Key type of a collection's external IDs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IdType {
+    /// Arbitrary string IDs
+    #[default]
+    String,
+    /// Unsigned 64-bit integer IDs, stored natively
+    U64,
+}
+
+impl IdType {
+    /// Convert `id` to this key type
+    ///
+    /// Fails with [`Error::InvalidId`] if a `U64` collection is given an ID
+    /// that is not a canonical unsigned integer (no sign, no leading zeros).
+    pub fn parse(self, id: VectorId) -> Result<VectorId> {
+        match (self, &id.0) {
+            (IdType::String, IdRepr::Int(n)) => Ok(VectorId::new(n.to_string())),
+            (IdType::U64, IdRepr::Str(s)) => match s.parse::<u64>() {
+                Ok(n) if n.to_string() == **s => Ok(VectorId::from(n)),
+                _ => Err(Error::InvalidId(format!(
+                    "'{}' is not an unsigned 64-bit integer",
+                    s
+                ))),
+            },
+            _ => Ok(id),
+        }
     }
 }
 
@@ -72,6 +157,487 @@ pub mod metadata_serde {
     }
 }
 
+/// How a collection stores per-vector JSON metadata
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MetadataCompression {
+    /// Keep parsed JSON values in memory
+    #[default]
+    None,
+    /// zstd-compress payloads with a dictionary trained per collection
+    /// (requires the `compression` feature)
+    Zstd,
+}
+
+/// Caps on each record's metadata, checked whenever it is written
+///
+/// One oversized payload slows filtering, listing and returning results for
+/// every search of the collection, so collections can refuse them up front.
+/// Payloads already stored are not rechecked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MetadataLimits {
+    /// Largest JSON encoding of a payload, in bytes
+    #[serde(default)]
+    pub max_bytes: Option<usize>,
+    /// Deepest nesting of objects and arrays; `{"a": 1}` has depth 1
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+}
+
+impl MetadataLimits {
+    /// Reject `metadata`, to be stored for `id`, if it breaks a limit
+    pub fn check(&self, id: &VectorId, metadata: Option<&serde_json::Value>) -> Result<()> {
+        let Some(metadata) = metadata else {
+            return Ok(());
+        };
+        if let Some(max) = self.max_depth {
+            let depth = json_depth(metadata);
+            if depth > max {
+                return Err(Error::MetadataLimitExceeded {
+                    id: id.to_string(),
+                    limit: "max_depth",
+                    actual: depth,
+                    max,
+                });
+            }
+        }
+        if let Some(max) = self.max_bytes {
+            let bytes = json_len(metadata);
+            if bytes > max {
+                return Err(Error::MetadataLimitExceeded {
+                    id: id.to_string(),
+                    limit: "max_bytes",
+                    actual: bytes,
+                    max,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Band of L2 norms a collection accepts on write
+///
+/// A failing embedding model tends to emit all-zero, collapsed or exploding
+/// vectors, which land in the graph as garbage neighbors for every search.
+/// Vectors outside the band, or with non-finite components, are rejected
+/// before anything is written; vectors already stored are not rechecked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct NormFilter {
+    /// Smallest norm accepted, e.g. just above zero
+    #[serde(default)]
+    pub min_norm: Option<f32>,
+    /// Largest norm accepted
+    #[serde(default)]
+    pub max_norm: Option<f32>,
+}
+
+impl NormFilter {
+    pub fn validate(&self) -> Result<()> {
+        let min = self.min_norm.unwrap_or(0.0);
+        let max = self.max_norm.unwrap_or(f32::INFINITY);
+        if !(min >= 0.0 && min.is_finite()) || max.is_nan() || min > max {
+            return Err(Error::InvalidConfig(format!(
+                "norm_filter needs 0 <= min_norm <= max_norm, got {:?} and {:?}",
+                self.min_norm, self.max_norm
+            )));
+        }
+        Ok(())
+    }
+
+    /// Reject `vector`, to be stored for `id`, if its norm is out of the band
+    pub fn check(&self, id: &str, vector: &[f32]) -> Result<()> {
+        let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+        let min = self.min_norm.unwrap_or(0.0);
+        let max = self.max_norm.unwrap_or(f32::INFINITY);
+        // NaN or overflowing components never pass, even an unbounded band
+        if norm.is_finite() && norm >= min && norm <= max {
+            return Ok(());
+        }
+        Err(Error::NormOutOfRange {
+            id: id.to_string(),
+            norm,
+            min,
+            max,
+        })
+    }
+}
+
+/// Number type the vectors written to a collection are given in
+///
+/// Vectors are always stored and searched as `f32`. The type decides which
+/// written values are accepted and how they are converted; see
+/// [`DtypeConversion`]. Query vectors are taken as any number.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VectorDtype {
+    /// 32-bit floats, taken as they are
+    #[default]
+    Float32,
+    /// 64-bit floats, converted to `f32`
+    Float64,
+    /// Integers from -128 to 127, as int8 embedding models emit; stored exactly
+    Int8,
+}
+
+/// What happens to a written value that `f32` storage can't hold exactly
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DtypeConversion {
+    /// Round it: to the nearest `f32` for `Float64`, to the nearest integer
+    /// for `Int8`
+    #[default]
+    Round,
+    /// Reject the write
+    Exact,
+}
+
+impl VectorDtype {
+    /// `values`, given in this type, as the `f32` values they are stored as
+    ///
+    /// Fails on the first value out of the type's range, or that would
+    /// change under [`DtypeConversion::Exact`]. `Float32` values are taken
+    /// as they are under either policy.
+    pub fn convert(self, values: &[f64], conversion: DtypeConversion) -> Result<Vec<f32>> {
+        let exact = conversion == DtypeConversion::Exact;
+        values
+            .iter()
+            .enumerate()
+            .map(|(index, &value)| {
+                let converted = match self {
+                    VectorDtype::Float32 => Some(value as f32),
+                    VectorDtype::Float64 => {
+                        let rounded = value as f32;
+                        // Finite values too large for f32 would become infinite
+                        let in_range = rounded.is_finite() || !value.is_finite();
+                        (in_range && (!exact || rounded as f64 == value)).then_some(rounded)
+                    }
+                    VectorDtype::Int8 => {
+                        let rounded = if exact { value } else { value.round() };
+                        (rounded.fract() == 0.0 && (-128.0..=127.0).contains(&rounded))
+                            .then_some(rounded as f32)
+                    }
+                };
+                converted.ok_or(Error::UnrepresentableValue {
+                    index,
+                    value,
+                    dtype: self,
+                })
+            })
+            .collect()
+    }
+}
+
+impl std::fmt::Display for VectorDtype {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            VectorDtype::Float32 => "float32",
+            VectorDtype::Float64 => "float64",
+            VectorDtype::Int8 => "int8",
+        })
+    }
+}
+
+/// A record's metadata size, as listed among a collection's largest
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PayloadSize {
+    pub id: VectorId,
+    /// Length of the payload's JSON encoding
+    pub bytes: usize,
+}
+
+/// WAL group commit: writes close together share one fsync
+///
+/// How durable an acknowledged write is depends on `sync_mode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GroupCommit {
+    /// When writes are synced
+    #[serde(default)]
+    pub sync_mode: SyncMode,
+    /// Longest time a write waits for its fsync with [`SyncMode::Interval`]
+    #[serde(alias = "flush_interval_ms")]
+    pub commit_interval_ms: u64,
+    /// Sync right away once this many writes are waiting, with
+    /// [`SyncMode::Interval`]
+    pub max_batch: usize,
+}
+
+impl Default for GroupCommit {
+    fn default() -> Self {
+        Self {
+            sync_mode: SyncMode::Interval,
+            commit_interval_ms: 10,
+            max_batch: 256,
+        }
+    }
+}
+
+/// When the WAL appends of a write are fsynced
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncMode {
+    /// Before the write is acknowledged; writers waiting at the same time
+    /// share one fsync, so no acknowledged write is lost in a crash
+    Always,
+    /// By a background thread every `commit_interval_ms`, after the write is
+    /// acknowledged; a crash loses at most the writes of the last interval,
+    /// and never more than `max_batch` of them
+    #[default]
+    Interval,
+    /// Only at checkpoints; a crash loses every write since the last one
+    Never,
+}
+
+/// Approximate in-memory bytes of a collection, by component
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct MemoryBreakdown {
+    /// Raw or quantized vector data
+    pub vectors: usize,
+    /// HNSW adjacency lists
+    pub graph: usize,
+    /// External ID mapping
+    pub ids: usize,
+    /// Metadata values and their filter index
+    pub metadata: usize,
+    /// Sparse vectors and their inverted index
+    #[serde(default)]
+    pub sparse: usize,
+    /// Uncompressed over stored metadata size, if metadata is compressed
+    pub metadata_compression_ratio: Option<f64>,
+}
+
+impl MemoryBreakdown {
+    pub fn total(&self) -> usize {
+        self.vectors + self.graph + self.ids + self.metadata + self.sparse
+    }
+}
+
+/// Outcome of a pass of [`Collection::repair_graph`](crate::db::Collection::repair_graph)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphRepair {
+    /// Nodes relinked in the pass
+    pub relinked: usize,
+    /// Nodes still linking to deleted or missing nodes after it
+    pub backlog: usize,
+}
+
+/// Space held by deleted or overwritten records until the collection is compacted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct GarbageStats {
+    /// Slots of deleted or overwritten records, still in the vectors and graph
+    pub tombstones: usize,
+    /// Share of all slots that are tombstones (0.0-1.0)
+    pub tombstone_ratio: f64,
+    /// Vector bytes held by tombstones
+    pub dead_vector_bytes: usize,
+    /// Payload bytes of records deleted or overwritten since the collection
+    /// was last compacted or opened
+    pub dead_payload_bytes: usize,
+    /// Files the collection is stored in (its WAL and snapshots); 0 in memory
+    pub segments: usize,
+}
+
+impl GarbageStats {
+    /// Stats for `tombstones` of `slots`, which hold `vector_bytes` in all
+    pub(crate) fn new(
+        slots: usize,
+        tombstones: usize,
+        vector_bytes: usize,
+        dead_payload_bytes: usize,
+    ) -> Self {
+        let (tombstone_ratio, dead_vector_bytes) = if slots == 0 {
+            (0.0, 0)
+        } else {
+            (
+                tombstones as f64 / slots as f64,
+                (vector_bytes as u128 * tombstones as u128 / slots as u128) as usize,
+            )
+        };
+        Self {
+            tombstones,
+            tombstone_ratio,
+            dead_vector_bytes,
+            dead_payload_bytes,
+            segments: 0,
+        }
+    }
+}
+
+/// How searches filtered by one filter should be run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterStrategy {
+    /// Walk the HNSW graph, skipping records that don't match
+    Graph,
+    /// Measure the query against every matching record
+    Exact,
+}
+
+/// Estimated quality of searches filtered by one filter
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FilterRecall {
+    /// Live records matching the filter
+    pub matches: usize,
+    /// Matching records used as queries
+    pub queries: usize,
+    /// Neighbors per query that recall is measured on
+    pub k: usize,
+    /// Mean recall@k of filtered graph search against an exact scan of the
+    /// matching records
+    pub recall: f64,
+    /// Mean vectors graph search measured per query; an exact scan measures
+    /// `matches`
+    pub mean_vectors_scanned: f64,
+    /// Strategy searches with this filter should use
+    pub recommended: FilterStrategy,
+}
+
+/// Outcome of compacting a collection
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CompactionReport {
+    /// Live records kept
+    pub vectors: usize,
+    /// Tombstoned slots dropped
+    pub reclaimed_slots: usize,
+    /// Vector bytes of the dropped slots
+    pub reclaimed_vector_bytes: usize,
+    /// `dead_payload_bytes` of the collection before compacting
+    pub reclaimed_payload_bytes: usize,
+}
+
+impl CompactionReport {
+    /// Report for a compaction that starts from `garbage` and keeps `vectors`
+    pub(crate) fn new(vectors: usize, garbage: &GarbageStats) -> Self {
+        Self {
+            vectors,
+            reclaimed_slots: garbage.tombstones,
+            reclaimed_vector_bytes: garbage.dead_vector_bytes,
+            reclaimed_payload_bytes: garbage.dead_payload_bytes,
+        }
+    }
+}
+
+/// Length of a JSON value's compact encoding, without allocating it
+pub(crate) fn json_len(value: &serde_json::Value) -> usize {
+    struct Counter(usize);
+    impl std::io::Write for Counter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0 += buf.len();
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    let mut counter = Counter(0);
+    // Writing to a counter can't fail
+    let _ = serde_json::to_writer(&mut counter, value);
+    counter.0
+}
+
+/// Deepest nesting of objects and arrays in a JSON value; scalars are 0
+///
+/// Walks with an explicit stack, so hostile nesting can't overflow it.
+pub(crate) fn json_depth(value: &serde_json::Value) -> usize {
+    use serde_json::Value;
+    let mut deepest = 0;
+    let mut stack = vec![(value, 0)];
+    while let Some((value, depth)) = stack.pop() {
+        let children: Box<dyn Iterator<Item = &Value>> = match value {
+            Value::Array(items) => Box::new(items.iter()),
+            Value::Object(map) => Box::new(map.values()),
+            _ => continue,
+        };
+        deepest = deepest.max(depth + 1);
+        stack.extend(children.map(|child| (child, depth + 1)));
+    }
+    deepest
+}
+
+/// Estimated heap bytes owned by a JSON value
+pub(crate) fn value_heap_size(value: &serde_json::Value) -> usize {
+    use serde_json::Value;
+    match value {
+        Value::Null | Value::Bool(_) | Value::Number(_) => 0,
+        Value::String(s) => s.capacity(),
+        Value::Array(items) => {
+            items.capacity() * std::mem::size_of::<Value>()
+                + items.iter().map(value_heap_size).sum::<usize>()
+        }
+        Value::Object(map) => map
+            .iter()
+            .map(|(k, v)| {
+                k.capacity() + std::mem::size_of::<(String, Value)>() + value_heap_size(v)
+            })
+            .sum(),
+    }
+}
+
+/// A search result: external ID, distance and optional metadata
+pub type SearchHit = (VectorId, f32, Option<serde_json::Value>);
+
+/// Work performed by a single search, used for cost attribution
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchUsage {
+    /// Number of distance computations against stored vectors
+    pub vectors_scanned: u64,
+    /// Number of graph nodes whose neighbor lists were expanded
+    pub graph_hops: u64,
+    /// Number of candidates re-scored with full-precision vectors
+    pub rescored_candidates: u64,
+}
+
+/// Position in a cursor-paged listing of a collection
+///
+/// Listings run in slot order up to the slot count taken when they started,
+/// so records inserted later are not listed and deletes or overwrites don't
+/// shift later pages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListCursor {
+    /// Slot numbering the listing runs over; restarts and restores change it
+    pub epoch: u64,
+    /// Slot count when the listing started
+    pub frontier: u64,
+    /// Next slot to scan
+    pub next: u64,
+}
+
+/// One page of a cursor-paged listing
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListPage {
+    pub records: Vec<(VectorId, Option<serde_json::Value>)>,
+    /// Where the next page starts, or `None` after the last page
+    pub next: Option<ListCursor>,
+}
+
+/// Per-search overrides of a collection's defaults
+///
+/// Unset fields fall back to the collection's configuration.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct SearchParams {
+    /// Size of the HNSW candidate list; larger trades latency for recall
+    pub ef_search: Option<usize>,
+    /// Re-score the quantized candidates with full-precision vectors
+    /// (quantized collections only)
+    pub rescore: Option<bool>,
+    /// How many candidates to re-score, as a multiple of `k`
+    /// (quantized collections only)
+    pub oversampling: Option<f32>,
+    /// Leave out results further than this from the query
+    ///
+    /// HNSW searches on full-precision distances also stop traversing at the
+    /// cutoff. Quantized candidates are cut after re-scoring, on the distances
+    /// returned.
+    pub max_distance: Option<f32>,
+}
+
+impl SearchParams {
+    /// Number of first-pass candidates to fetch for `k` results
+    pub(crate) fn candidates(&self, k: usize, default_oversampling: f32) -> usize {
+        let oversampling = self.oversampling.unwrap_or(default_oversampling).max(1.0);
+        ((k as f32 * oversampling).ceil() as usize).max(k)
+    }
+}
+
 /// Internal vector identifier (for indexing)
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct InternalId(pub(crate) u32);
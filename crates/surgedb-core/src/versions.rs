@@ -0,0 +1,207 @@
+//! Per-vector version numbers for optimistic concurrency
+//!
+//! A vector's version is the collection's write sequence just after the last
+//! write that touched it, so it only grows. Vectors not written since the
+//! collection was opened share the sequence it was opened at, which for
+//! persistent collections is past every version handed out before a restart.
+//!
+//! A write marks its IDs while it runs. A conditional write checks the
+//! expected version and marks its ID in one step, so it fails if another
+//! write to the vector is running, and other conditional writes to it fail
+//! until it is done. The table is not locked while the data is written.
+//! Versions are kept in memory, so they are local to the process.
+//!
+//! Lock order: the table's lock is taken before the collection's data lock,
+//! as [`Versions::write_if`] checks that a vector exists while holding it.
+//! So the table must never be locked while a data lock is held; read what is
+//! needed from the collection (such as its write sequence) first.
+
+use crate::error::{Error, Result};
+use crate::sync::RwLock;
+use std::collections::HashMap;
+
+/// Versions of the vectors of one collection
+#[derive(Default)]
+pub(crate) struct Versions {
+    state: RwLock<VersionState>,
+}
+
+#[derive(Default)]
+struct VersionState {
+    /// Version of every vector not in `latest`
+    base: u64,
+    /// Versions of the vectors written since the collection was opened
+    latest: HashMap<String, u64>,
+    /// Writes running per vector
+    writing: HashMap<String, usize>,
+    /// Writes running that may touch any vector
+    bulk: usize,
+}
+
+impl VersionState {
+    fn version(&self, id: &str) -> u64 {
+        self.latest.get(id).copied().unwrap_or(self.base)
+    }
+
+    fn begin(&mut self, ids: &[String]) {
+        for id in ids {
+            *self.writing.entry(id.clone()).or_default() += 1;
+        }
+    }
+
+    fn record(&mut self, id: &str, seq: u64) {
+        let version = self.latest.entry(id.to_string()).or_insert(self.base);
+        *version = (*version).max(seq);
+    }
+}
+
+impl Versions {
+    pub(crate) fn new(base: u64) -> Self {
+        Self {
+            state: RwLock::new(VersionState {
+                base,
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Current version of `id`, whether or not it exists
+    pub(crate) fn version(&self, id: &str) -> u64 {
+        self.state.read().version(id)
+    }
+
+    /// Mark a write to `ids` as running until the returned guard is finished
+    /// or dropped
+    pub(crate) fn begin(&self, ids: Vec<String>) -> VersionedWrite<'_> {
+        self.state.write().begin(&ids);
+        VersionedWrite {
+            versions: self,
+            ids,
+            bulk: false,
+        }
+    }
+
+    /// Mark a write to vectors not known up front as running
+    ///
+    /// Conditional writes fail while it runs.
+    pub(crate) fn begin_bulk(&self) -> VersionedWrite<'_> {
+        self.state.write().bulk += 1;
+        VersionedWrite {
+            versions: self,
+            ids: Vec::new(),
+            bulk: true,
+        }
+    }
+
+    /// Run `write` on `id` only if it exists (as `exists` says) at version
+    /// `expected`, or doesn't exist if that is `None`, then record the
+    /// sequence `seq` returns as its version
+    ///
+    /// Fails with [`Error::VersionConflict`] otherwise, or if another write
+    /// to it is running. The check marks the write as running before the
+    /// table is unlocked, so the write itself doesn't hold up other writes
+    /// and reads of versions, while conditional writes to the same vector
+    /// conflict until it is done.
+    pub(crate) fn write_if<T>(
+        &self,
+        id: &str,
+        expected: Option<u64>,
+        exists: impl FnOnce() -> Result<bool>,
+        write: impl FnOnce() -> Result<T>,
+        seq: impl FnOnce() -> u64,
+    ) -> Result<T> {
+        let ids = vec![id.to_string()];
+        {
+            let mut state = self.state.write();
+            let busy = state.bulk > 0 || state.writing.get(id).is_some_and(|&n| n > 0);
+            let actual = if exists()? {
+                Some(state.version(id))
+            } else {
+                None
+            };
+            if busy || actual != expected {
+                return Err(Error::VersionConflict {
+                    id: id.to_string(),
+                    expected,
+                    actual,
+                });
+            }
+            state.begin(&ids);
+        }
+        let running = VersionedWrite {
+            versions: self,
+            ids,
+            bulk: false,
+        };
+        let value = write()?;
+        running.written(seq());
+        Ok(value)
+    }
+}
+
+/// A running write, recorded in the version table when finished
+pub(crate) struct VersionedWrite<'a> {
+    versions: &'a Versions,
+    ids: Vec<String>,
+    bulk: bool,
+}
+
+impl VersionedWrite<'_> {
+    /// Give the written vectors version `seq`
+    pub(crate) fn written(self, seq: u64) {
+        let mut state = self.versions.state.write();
+        for id in &self.ids {
+            state.record(id, seq);
+        }
+    }
+
+    /// Give `ids`, written by a bulk write, version `seq`
+    pub(crate) fn written_ids(self, ids: impl IntoIterator<Item = impl AsRef<str>>, seq: u64) {
+        let mut state = self.versions.state.write();
+        for id in ids {
+            state.record(id.as_ref(), seq);
+        }
+    }
+
+    /// Forget the versions of the deleted vectors, unless another write to
+    /// them is running
+    ///
+    /// A vector written again later gets a version past any it had before.
+    pub(crate) fn deleted(self, seq: u64) {
+        let mut state = self.versions.state.write();
+        let alone = state.bulk == 0;
+        for id in &self.ids {
+            if alone && state.writing.get(id) == Some(&1) {
+                state.latest.remove(id);
+            } else {
+                state.record(id, seq);
+            }
+        }
+    }
+
+    /// Give every vector version `seq`, after a bulk write whose vectors are
+    /// not known
+    pub(crate) fn written_all(self, seq: u64) {
+        let mut state = self.versions.state.write();
+        let newest = state.latest.values().copied().max().unwrap_or(0);
+        state.base = state.base.max(newest).max(seq);
+        state.latest.clear();
+    }
+}
+
+impl Drop for VersionedWrite<'_> {
+    fn drop(&mut self) {
+        let mut state = self.versions.state.write();
+        if self.bulk {
+            state.bulk -= 1;
+        }
+        for id in &self.ids {
+            if let Some(n) = state.writing.get_mut(id) {
+                *n -= 1;
+                if *n == 0 {
+                    state.writing.remove(id);
+                }
+            }
+        }
+    }
+}
@@ -7,15 +7,24 @@
 //! - Each operation is logged to disk before being applied
 //! - Periodic snapshots reduce recovery time
 //! - CRC32 checksums ensure data integrity
+//! - With [`GroupCommit`], writes share fsyncs, issued by a background thread
+//!   or by one of the writers waiting for them
 
 use crate::error::{Error, Result};
-use crate::types::VectorId;
+use crate::recovery::RecoveryMode;
+use crate::sparse::SparseVector;
+use crate::types::{GroupCommit, SyncMode, VectorId};
 use bincode::{deserialize, serialize};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::fs::{self, File, OpenOptions};
 use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tracing::warn;
 
 /// Magic bytes to identify WAL files
 const WAL_MAGIC: &[u8; 4] = b"ZWAL";
@@ -37,6 +46,33 @@ pub enum WalEntry {
     Delete { id: VectorId },
     /// Checkpoint marker (snapshot was taken)
     Checkpoint { snapshot_id: u64 },
+    /// Entries written as one record, so replay applies all of them or none
+    Batch { entries: Vec<WalEntry> },
+    /// Set the sparse vector of an existing record
+    Sparse { id: VectorId, vector: SparseVector },
+    /// Replace the metadata of an existing record, keeping its vector
+    Metadata {
+        id: VectorId,
+        #[serde(with = "crate::types::metadata_serde")]
+        metadata: Option<Value>,
+    },
+    /// Set the vector of an existing record in a named space
+    Named {
+        id: VectorId,
+        name: String,
+        vector: Vec<f32>,
+    },
+}
+
+/// A point in a database's write log, for followers replaying it
+///
+/// `log_id` tells logs apart: a database recreated under the same name
+/// starts a new log, whose sequence numbers mean something else.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogPosition {
+    pub log_id: u64,
+    /// Sequence number of the last entry written
+    pub seq: u64,
 }
 
 /// WAL record with checksum
@@ -83,6 +119,182 @@ fn crc32(data: &[u8]) -> u32 {
     !crc
 }
 
+/// State shared between a WAL, its group commit thread and writers waiting
+/// for their appends to be synced
+struct CommitShared {
+    /// Handle to the current WAL file, used only for syncing
+    file: Mutex<File>,
+    /// Appends not yet synced
+    pending: AtomicUsize,
+    stop: AtomicBool,
+    /// Appends committed with [`SyncMode::Always`], which number their tickets
+    appended: AtomicU64,
+    durable: Mutex<Durable>,
+    /// Signalled when a sync for waiting writers finishes
+    synced: Condvar,
+}
+
+/// Progress of the syncs writers wait for
+#[derive(Default)]
+struct Durable {
+    /// Every ticket up to this one is synced
+    synced: u64,
+    /// A waiting writer is syncing for all of them
+    syncing: bool,
+}
+
+impl CommitShared {
+    fn new(file: File) -> Self {
+        Self {
+            file: Mutex::new(file),
+            pending: AtomicUsize::new(0),
+            stop: AtomicBool::new(false),
+            appended: AtomicU64::new(0),
+            durable: Mutex::new(Durable::default()),
+            synced: Condvar::new(),
+        }
+    }
+
+    /// Block until the append numbered `ticket` is synced
+    ///
+    /// The first writer to wait syncs every append made so far while later
+    /// ones wait for it, so writers that arrive together share one fsync.
+    fn wait_synced(&self, ticket: u64) -> Result<()> {
+        let mut durable = self.durable.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            if durable.synced >= ticket {
+                return Ok(());
+            }
+            if durable.syncing {
+                durable = self.synced.wait(durable).unwrap_or_else(|e| e.into_inner());
+                continue;
+            }
+            durable.syncing = true;
+            let target = self.appended.load(Ordering::Acquire);
+            drop(durable);
+            let result = self
+                .file
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .sync_data();
+            durable = self.durable.lock().unwrap_or_else(|e| e.into_inner());
+            durable.syncing = false;
+            if result.is_ok() {
+                durable.synced = durable.synced.max(target);
+            }
+            // On failure the next waiter tries again
+            self.synced.notify_all();
+            result?;
+        }
+    }
+
+    /// Count every append so far as synced
+    fn mark_synced(&self) {
+        let mut durable = self.durable.lock().unwrap_or_else(|e| e.into_inner());
+        durable.synced = durable.synced.max(self.appended.load(Ordering::Acquire));
+        self.pending.store(0, Ordering::Release);
+        self.synced.notify_all();
+    }
+
+    fn sync_pending(&self) -> Result<()> {
+        let pending = self.pending.swap(0, Ordering::AcqRel);
+        if pending == 0 {
+            return Ok(());
+        }
+        let file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = file.sync_data() {
+            // Leave them pending so the next commit retries
+            self.pending.fetch_add(pending, Ordering::AcqRel);
+            return Err(e.into());
+        }
+        Ok(())
+    }
+}
+
+/// Group commit state of a WAL, with the thread syncing it once per commit
+/// interval in [`SyncMode::Interval`]
+struct GroupCommitter {
+    config: GroupCommit,
+    shared: Arc<CommitShared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl GroupCommitter {
+    fn start(config: GroupCommit, file: File) -> Result<Self> {
+        let shared = Arc::new(CommitShared::new(file));
+        if config.sync_mode != SyncMode::Interval {
+            return Ok(Self {
+                config,
+                shared,
+                thread: None,
+            });
+        }
+        let interval = Duration::from_millis(config.commit_interval_ms.max(1));
+        let thread_shared = shared.clone();
+        let thread = std::thread::Builder::new()
+            .name("surgedb-wal-commit".to_string())
+            .spawn(move || {
+                while !thread_shared.stop.load(Ordering::Acquire) {
+                    std::thread::park_timeout(interval);
+                    if let Err(e) = thread_shared.sync_pending() {
+                        warn!("WAL group commit failed: {}", e);
+                    }
+                }
+            })?;
+        Ok(Self {
+            config,
+            shared,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for GroupCommitter {
+    fn drop(&mut self) {
+        self.shared.stop.store(true, Ordering::Release);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+        let _ = self.shared.sync_pending();
+    }
+}
+
+/// An append committed with [`SyncMode::Always`] that may not be synced yet
+///
+/// Returned so a writer can release its locks before waiting, letting
+/// writers behind it append and share the same fsync.
+#[must_use = "the append is not durable until waited for"]
+pub struct PendingSync {
+    shared: Arc<CommitShared>,
+    ticket: u64,
+}
+
+impl PendingSync {
+    /// Block until the append is synced
+    pub fn wait(self) -> Result<()> {
+        self.shared.wait_synced(self.ticket)
+    }
+}
+
+/// Damaged records removed from a WAL when it was opened
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct WalRepair {
+    /// Records with a bad checksum or encoding before the end of the log
+    pub damaged_records: usize,
+    /// Intact records dropped because they were logged after a damaged one
+    pub dropped_records: usize,
+    /// Bytes cut off the end of the log, left there by a write torn by a crash
+    pub torn_bytes: u64,
+}
+
+impl WalRepair {
+    /// Whether the log was intact
+    pub fn is_clean(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
 /// Write-Ahead Log manager
 pub struct Wal {
     /// Directory containing WAL files
@@ -97,15 +309,33 @@ pub struct Wal {
     max_wal_size: u64,
     /// Current WAL file size
     current_size: u64,
+    /// Group commit thread, if syncs are batched
+    committer: Option<GroupCommitter>,
 }
 
 impl Wal {
     /// Create or open a WAL in the specified directory
     pub fn open(dir: impl AsRef<Path>) -> Result<Self> {
+        Self::open_with_recovery(dir, RecoveryMode::default()).map(|(wal, _)| wal)
+    }
+
+    /// Create or open a WAL, first removing damaged records as `mode` says
+    ///
+    /// A torn record at the end of the log is always cut off, so that new
+    /// records are appended where they can be read back.
+    pub fn open_with_recovery(
+        dir: impl AsRef<Path>,
+        mode: RecoveryMode,
+    ) -> Result<(Self, WalRepair)> {
         let dir = dir.as_ref().to_path_buf();
         fs::create_dir_all(&dir)?;
 
         let wal_path = dir.join("current.wal");
+        let repair = if wal_path.exists() {
+            Self::repair(&wal_path, mode)?
+        } else {
+            WalRepair::default()
+        };
         let (file, seq, size) = if wal_path.exists() {
             // Open existing WAL and find last sequence number
             let mut f = OpenOptions::new().read(true).append(true).open(&wal_path)?;
@@ -135,14 +365,111 @@ impl Wal {
             (Some(writer), 0, 5) // 5 bytes for header
         };
 
-        Ok(Self {
+        let wal = Self {
             dir,
             file,
             seq,
             last_checkpoint_seq: 0,
             max_wal_size: 64 * 1024 * 1024, // 64MB default
             current_size: size,
-        })
+            committer: None,
+        };
+        Ok((wal, repair))
+    }
+
+    /// Scan the WAL file at `path` and rewrite it without damaged records
+    fn repair(path: &Path, mode: RecoveryMode) -> Result<WalRepair> {
+        let bytes = fs::read(path)?;
+        if bytes.len() < 5 {
+            // The header itself was torn, so nothing was ever logged
+            let mut file = File::create(path)?;
+            file.write_all(WAL_MAGIC)?;
+            file.write_all(&[WAL_VERSION])?;
+            file.sync_all()?;
+            return Ok(WalRepair {
+                torn_bytes: bytes.len() as u64,
+                ..Default::default()
+            });
+        }
+        if &bytes[..4] != WAL_MAGIC {
+            return Err(Error::WalCorrupted {
+                message: format!("Invalid WAL magic bytes in {:?}", path),
+            });
+        }
+
+        // Split the log into frames, noting which hold an intact record
+        let mut frames = Vec::new();
+        let mut pos = 5;
+        while pos + 4 <= bytes.len() {
+            let len = u32::from_le_bytes(bytes[pos..pos + 4].try_into().unwrap()) as usize;
+            let Some(end) = (pos + 4).checked_add(len).filter(|&end| end <= bytes.len()) else {
+                break;
+            };
+            let intact =
+                deserialize::<WalRecord>(&bytes[pos + 4..end]).is_ok_and(|record| record.verify());
+            frames.push((pos..end, intact));
+            pos = end;
+        }
+
+        // Damaged frames with no intact one after them are part of the torn tail
+        let complete = frames
+            .iter()
+            .rposition(|(_, intact)| *intact)
+            .map_or(0, |i| i + 1);
+        let tail_start = frames.get(complete).map_or(pos, |(range, _)| range.start);
+        frames.truncate(complete);
+
+        let mut repair = WalRepair {
+            damaged_records: frames.iter().filter(|(_, intact)| !intact).count(),
+            dropped_records: 0,
+            torn_bytes: (bytes.len() - tail_start) as u64,
+        };
+        if let Some(first) = frames.iter().position(|(_, intact)| !intact) {
+            match mode {
+                RecoveryMode::Strict => {
+                    return Err(Error::WalCorrupted {
+                        message: format!(
+                            "Damaged record at byte {} of {:?}, followed by {} intact ones",
+                            frames[first].0.start,
+                            path,
+                            frames[first..].iter().filter(|(_, intact)| *intact).count()
+                        ),
+                    });
+                }
+                RecoveryMode::Repair => {
+                    repair.dropped_records =
+                        frames[first..].iter().filter(|(_, intact)| *intact).count();
+                    frames.truncate(first);
+                }
+                RecoveryMode::Skip => frames.retain(|(_, intact)| *intact),
+            }
+        }
+        if repair.is_clean() {
+            return Ok(repair);
+        }
+
+        if repair.damaged_records == 0 || mode == RecoveryMode::Repair {
+            // What is kept is a prefix of the file, so cut off the rest
+            let end = frames.last().map_or(5, |(range, _)| range.end);
+            let file = OpenOptions::new().write(true).open(path)?;
+            file.set_len(end as u64)?;
+            file.sync_all()?;
+        } else {
+            let tmp_path = path.with_extension("wal.tmp");
+            let mut file = File::create(&tmp_path)?;
+            file.write_all(&bytes[..5])?;
+            for (range, _) in &frames {
+                file.write_all(&bytes[range.clone()])?;
+            }
+            file.sync_all()?;
+            fs::rename(&tmp_path, path)?;
+        }
+
+        warn!(
+            "Repaired WAL {:?}: {} damaged records, {} later records dropped, {} torn bytes cut off",
+            path, repair.damaged_records, repair.dropped_records, repair.torn_bytes
+        );
+        Ok(repair)
     }
 
     /// Find the last sequence number in a WAL file
@@ -208,9 +535,60 @@ impl Wal {
             file.flush()?;
             file.get_ref().sync_all()?;
         }
+        if let Some(committer) = &self.committer {
+            committer.shared.mark_synced();
+        }
         Ok(())
     }
 
+    /// Batch syncs with group commit, or sync after every append if `None`
+    pub fn set_group_commit(&mut self, config: Option<GroupCommit>) -> Result<()> {
+        self.committer = None;
+        if let (Some(config), Some(file)) = (config, &self.file) {
+            self.committer = Some(GroupCommitter::start(config, file.get_ref().try_clone()?)?);
+        }
+        Ok(())
+    }
+
+    /// Make the last append durable
+    ///
+    /// Without group commit it is synced right away. Otherwise it is synced
+    /// as the [`SyncMode`] says: before this returns with `Always`, within
+    /// the commit interval or once `max_batch` appends are waiting with
+    /// `Interval`, and at the next checkpoint with `Never`.
+    pub fn commit(&mut self) -> Result<()> {
+        match self.commit_pending()? {
+            Some(pending) => pending.wait(),
+            None => Ok(()),
+        }
+    }
+
+    /// Like [`commit`](Self::commit), but with [`SyncMode::Always`] return
+    /// the sync to wait for instead of waiting
+    pub fn commit_pending(&mut self) -> Result<Option<PendingSync>> {
+        let Some(committer) = &self.committer else {
+            self.sync()?;
+            return Ok(None);
+        };
+        match committer.config.sync_mode {
+            SyncMode::Always => {
+                let ticket = committer.shared.appended.fetch_add(1, Ordering::AcqRel) + 1;
+                Ok(Some(PendingSync {
+                    shared: committer.shared.clone(),
+                    ticket,
+                }))
+            }
+            SyncMode::Interval => {
+                let pending = committer.shared.pending.fetch_add(1, Ordering::AcqRel) + 1;
+                if pending >= committer.config.max_batch {
+                    committer.shared.sync_pending()?;
+                }
+                Ok(None)
+            }
+            SyncMode::Never => Ok(None),
+        }
+    }
+
     /// Read all entries from the WAL (for recovery)
     pub fn read_all(&self) -> Result<Vec<WalEntry>> {
         let wal_path = self.dir.join("current.wal");
@@ -277,6 +655,12 @@ impl Wal {
 
     /// Read entries after a specific sequence number (for recovery after checkpoint)
     pub fn read_after(&self, after_seq: u64) -> Result<Vec<WalEntry>> {
+        let records = self.read_records_after(after_seq, usize::MAX)?;
+        Ok(records.into_iter().map(|(_, entry)| entry).collect())
+    }
+
+    /// Read up to `limit` entries after `after_seq`, with their sequence numbers
+    pub fn read_records_after(&self, after_seq: u64, limit: usize) -> Result<Vec<(u64, WalEntry)>> {
         let wal_path = self.dir.join("current.wal");
         if !wal_path.exists() {
             return Ok(Vec::new());
@@ -291,7 +675,7 @@ impl Wal {
 
         let mut entries = Vec::new();
 
-        loop {
+        while entries.len() < limit {
             let mut len_bytes = [0u8; 4];
             if reader.read_exact(&mut len_bytes).is_err() {
                 break;
@@ -306,7 +690,7 @@ impl Wal {
             match deserialize::<WalRecord>(&data) {
                 Ok(record) => {
                     if record.verify() && record.seq > after_seq {
-                        entries.push(record.entry);
+                        entries.push((record.seq, record.entry));
                     }
                 }
                 Err(_) => break,
@@ -335,6 +719,15 @@ impl Wal {
         writer.write_all(&[WAL_VERSION])?;
         writer.flush()?;
 
+        if let Some(committer) = &self.committer {
+            *committer
+                .shared
+                .file
+                .lock()
+                .unwrap_or_else(|e| e.into_inner()) = writer.get_ref().try_clone()?;
+            // The checkpoint made everything logged so far durable
+            committer.shared.mark_synced();
+        }
         self.file = Some(writer);
         self.last_checkpoint_seq = self.seq;
         self.current_size = 5;
@@ -347,6 +740,14 @@ impl Wal {
         self.seq
     }
 
+    /// Make `seq` the current sequence number if the log is behind it
+    ///
+    /// A log reopened with no entries starts from zero, so one cleared by a
+    /// checkpoint is moved past the sequence its snapshot covers.
+    pub fn advance_to(&mut self, seq: u64) {
+        self.seq = self.seq.max(seq);
+    }
+
     /// Get WAL directory
     pub fn dir(&self) -> &Path {
         &self.dir
@@ -533,6 +934,122 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_group_commit_batches_syncs() {
+        let dir = tempdir().unwrap();
+        let mut wal = Wal::open(dir.path()).unwrap();
+        wal.set_group_commit(Some(GroupCommit {
+            commit_interval_ms: 60_000,
+            max_batch: 3,
+            ..Default::default()
+        }))
+        .unwrap();
+        let pending = |wal: &Wal| {
+            wal.committer
+                .as_ref()
+                .unwrap()
+                .shared
+                .pending
+                .load(Ordering::Acquire)
+        };
+
+        for i in 0..5 {
+            wal.append(WalEntry::Delete {
+                id: format!("v{i}").into(),
+            })
+            .unwrap();
+            wal.commit().unwrap();
+        }
+        // The third commit synced the first batch
+        assert_eq!(pending(&wal), 2);
+
+        wal.clear().unwrap();
+        assert_eq!(pending(&wal), 0);
+        wal.append(WalEntry::Delete { id: "x".into() }).unwrap();
+        wal.commit().unwrap();
+        drop(wal);
+
+        let entries = Wal::open(dir.path()).unwrap().read_all().unwrap();
+        assert_eq!(entries.len(), 1);
+    }
+
+    #[test]
+    fn test_group_commit_syncs_within_interval() {
+        let dir = tempdir().unwrap();
+        let mut wal = Wal::open(dir.path()).unwrap();
+        wal.set_group_commit(Some(GroupCommit {
+            commit_interval_ms: 5,
+            max_batch: 1000,
+            ..Default::default()
+        }))
+        .unwrap();
+        wal.append(WalEntry::Delete { id: "v".into() }).unwrap();
+        wal.commit().unwrap();
+
+        let shared = wal.committer.as_ref().unwrap().shared.clone();
+        let start = std::time::Instant::now();
+        while shared.pending.load(Ordering::Acquire) > 0 {
+            assert!(start.elapsed().as_secs() < 10, "commit thread never synced");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn test_sync_mode_always_shares_syncs() {
+        let dir = tempdir().unwrap();
+        let mut wal = Wal::open(dir.path()).unwrap();
+        wal.set_group_commit(Some(GroupCommit {
+            sync_mode: SyncMode::Always,
+            ..Default::default()
+        }))
+        .unwrap();
+
+        let mut waiting = Vec::new();
+        for i in 0..3 {
+            wal.append(WalEntry::Delete {
+                id: format!("v{i}").into(),
+            })
+            .unwrap();
+            waiting.push(wal.commit_pending().unwrap().unwrap());
+        }
+        let shared = wal.committer.as_ref().unwrap().shared.clone();
+        let handles: Vec<_> = waiting
+            .into_iter()
+            .map(|pending| std::thread::spawn(move || pending.wait()))
+            .collect();
+        for handle in handles {
+            handle.join().unwrap().unwrap();
+        }
+        assert_eq!(shared.durable.lock().unwrap().synced, 3);
+
+        wal.append(WalEntry::Delete { id: "x".into() }).unwrap();
+        wal.commit().unwrap();
+        assert_eq!(shared.durable.lock().unwrap().synced, 4);
+    }
+
+    #[test]
+    fn test_sync_mode_never_skips_syncs() {
+        let dir = tempdir().unwrap();
+        let mut wal = Wal::open(dir.path()).unwrap();
+        wal.set_group_commit(Some(GroupCommit {
+            sync_mode: SyncMode::Never,
+            ..Default::default()
+        }))
+        .unwrap();
+        assert!(wal.committer.as_ref().unwrap().thread.is_none());
+        wal.append(WalEntry::Delete { id: "v".into() }).unwrap();
+        assert!(wal.commit_pending().unwrap().is_none());
+        assert_eq!(
+            wal.committer
+                .as_ref()
+                .unwrap()
+                .shared
+                .pending
+                .load(Ordering::Acquire),
+            0
+        );
+    }
+
     #[test]
     fn test_crc32() {
         let data = b"hello world";
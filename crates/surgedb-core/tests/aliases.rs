@@ -0,0 +1,102 @@
+use surgedb_core::{Config, Database, Error};
+use tempfile::tempdir;
+
+fn config() -> Config {
+    Config {
+        dimensions: 2,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_alias_resolution_and_swap() {
+    let db = Database::new();
+    db.create_collection("docs_v1", config()).unwrap();
+    db.create_collection("docs_v2", config()).unwrap();
+    db.get_collection("docs_v2")
+        .unwrap()
+        .insert("a".to_string(), &[1.0, 0.0], None)
+        .unwrap();
+
+    assert_eq!(db.set_alias("docs", "docs_v1").unwrap(), None);
+    assert_eq!(db.get_collection("docs").unwrap().stats().vector_count, 0);
+
+    // A stale expectation is rejected and leaves the alias unchanged
+    assert!(matches!(
+        db.swap_alias("docs", "docs_v2", None),
+        Err(Error::AliasConflict(_))
+    ));
+    let previous = db.swap_alias("docs", "docs_v2", Some("docs_v1")).unwrap();
+    assert_eq!(previous.as_deref(), Some("docs_v1"));
+    assert_eq!(db.get_collection("docs").unwrap().stats().vector_count, 1);
+    assert_eq!(db.resolve_name("docs"), "docs_v2");
+    assert_eq!(db.resolve_name("docs_v1"), "docs_v1");
+
+    // Aliased collections can't be dropped, and names can't be shared
+    assert!(db.delete_collection("docs_v2").is_err());
+    assert!(db.create_collection("docs", config()).is_err());
+    assert!(db.set_alias("docs_v1", "docs_v2").is_err());
+    assert!(db.delete_collection("docs_v1").is_ok());
+
+    assert_eq!(
+        db.list_aliases(),
+        vec![("docs".to_string(), "docs_v2".to_string())]
+    );
+    assert_eq!(db.delete_alias("docs").unwrap(), "docs_v2");
+    assert!(matches!(
+        db.delete_alias("docs"),
+        Err(Error::AliasNotFound(_))
+    ));
+}
+
+#[test]
+fn test_aliases_survive_reopen() {
+    let dir = tempdir().unwrap();
+    {
+        let db = Database::open(dir.path()).unwrap();
+        db.create_collection("docs_v1", config()).unwrap();
+        db.set_alias("docs", "docs_v1").unwrap();
+    }
+
+    let db = Database::open(dir.path()).unwrap();
+    assert_eq!(
+        db.list_aliases(),
+        vec![("docs".to_string(), "docs_v1".to_string())]
+    );
+    assert!(db.get_collection("docs").is_ok());
+}
+
+#[test]
+fn test_create_collection_if_missing() {
+    let db = Database::new();
+    assert!(db
+        .create_collection_if_missing("docs_v1", config())
+        .unwrap());
+    db.get_collection("docs_v1")
+        .unwrap()
+        .insert("a".to_string(), &[1.0, 0.0], None)
+        .unwrap();
+
+    // An existing collection or alias is left alone
+    let other = Config {
+        dimensions: 8,
+        ..config()
+    };
+    assert!(!db
+        .create_collection_if_missing("docs_v1", other.clone())
+        .unwrap());
+    assert_eq!(db.get_collection("docs_v1").unwrap().config().dimensions, 2);
+    assert_eq!(
+        db.get_collection("docs_v1").unwrap().stats().vector_count,
+        1
+    );
+    db.set_alias("docs", "docs_v1").unwrap();
+    assert!(!db.create_collection_if_missing("docs", other).unwrap());
+
+    // Invalid configurations still fail
+    let invalid = Config {
+        hnsw: surgedb_core::HnswConfig::default().with_m(0),
+        ..config()
+    };
+    assert!(db.create_collection_if_missing("new", invalid).is_err());
+}
@@ -0,0 +1,219 @@
+mod common;
+
+use common::ids;
+use serde_json::json;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use surgedb_core::ann::IndexStorage;
+use surgedb_core::filter::Filter;
+use surgedb_core::segment::SEAL_SIZE;
+use surgedb_core::types::InternalId;
+use surgedb_core::{
+    AnnIndex, Config, Database, DistanceMetric, FlatIndex, IndexKind, QuantizationType, Result,
+    SearchParams, SearchUsage, VectorDb,
+};
+use tempfile::tempdir;
+
+fn config() -> Config {
+    Config {
+        dimensions: 2,
+        distance_metric: DistanceMetric::Euclidean,
+        index: IndexKind::Flat,
+        ..Default::default()
+    }
+}
+
+fn search(db: &Database, query: &[f32], k: usize, filter: Option<&Filter>) -> Vec<String> {
+    let collection = db.get_collection("c").unwrap();
+    let (hits, _) = collection
+        .search_with_params(query, k, filter, SearchParams::default())
+        .unwrap();
+    ids(&hits)
+}
+
+fn fill(db: &Database) {
+    db.create_collection("c", config()).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    for i in 0..40 {
+        collection
+            .insert(
+                format!("v{i}"),
+                &[i as f32, 0.0],
+                Some(json!({ "even": i % 2 == 0 })),
+            )
+            .unwrap();
+    }
+}
+
+#[test]
+fn test_flat_collection_is_exact() {
+    let db = Database::new();
+    fill(&db);
+    let collection = db.get_collection("c").unwrap();
+
+    assert_eq!(
+        search(&db, &[20.3, 0.0], 3, None),
+        vec!["v20", "v21", "v19"]
+    );
+    let odd = Filter::Exact("even".into(), json!(false));
+    assert_eq!(search(&db, &[20.3, 0.0], 2, Some(&odd)), vec!["v21", "v19"]);
+
+    // Every live vector is scanned, and overwritten or deleted ones never found
+    collection
+        .upsert("v21".into(), &[100.0, 0.0], None)
+        .unwrap();
+    collection.delete("v19").unwrap();
+    assert_eq!(
+        search(&db, &[20.3, 0.0], 3, None),
+        vec!["v20", "v22", "v18"]
+    );
+    let (_, usage) = collection
+        .search_with_params(&[0.0, 0.0], 1, None, SearchParams::default())
+        .unwrap();
+    assert_eq!(usage.vectors_scanned, 39);
+
+    assert_eq!(collection.config().index, IndexKind::Flat);
+    assert!(collection
+        .export_graph(None, None)
+        .unwrap()
+        .nodes
+        .is_empty());
+}
+
+#[test]
+fn test_flat_collection_persists_and_compacts() {
+    let dir = tempdir().unwrap();
+    {
+        let db = Database::open(dir.path()).unwrap();
+        fill(&db);
+        let collection = db.get_collection("c").unwrap();
+        collection.delete("v5").unwrap();
+        collection.compact().unwrap();
+        assert_eq!(search(&db, &[5.1, 0.0], 2, None), vec!["v6", "v4"]);
+        collection.insert("late".into(), &[5.0, 0.0], None).unwrap();
+    }
+
+    let db = Database::open(dir.path()).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    assert_eq!(collection.config().index, IndexKind::Flat);
+    assert_eq!(search(&db, &[5.1, 0.0], 2, None), vec!["late", "v6"]);
+}
+
+#[test]
+fn test_segmented_collection_persists() {
+    let dir = tempdir().unwrap();
+    let config = Config {
+        index: IndexKind::Segmented,
+        ..config()
+    };
+    // One sealed segment and 40 vectors past it
+    let items = (0..SEAL_SIZE + 40)
+        .map(|i| (format!("v{i}"), vec![i as f32, 0.0], None))
+        .collect();
+    {
+        let db = Database::open(dir.path()).unwrap();
+        db.create_collection("c", config).unwrap();
+        let collection = db.get_collection("c").unwrap();
+        collection.upsert_batch(items).unwrap();
+        // Found whether or not the sealed segment's graph is built yet
+        assert_eq!(search(&db, &[17.2, 0.0], 2, None), vec!["v17", "v18"]);
+        assert_eq!(search(&db, &[4100.2, 0.0], 2, None), vec!["v4100", "v4101"]);
+        collection.delete("v17").unwrap();
+        assert_eq!(search(&db, &[17.2, 0.0], 2, None), vec!["v18", "v16"]);
+        collection.compact().unwrap();
+    }
+
+    let db = Database::open(dir.path()).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    assert_eq!(collection.config().index, IndexKind::Segmented);
+    assert_eq!(collection.len(), SEAL_SIZE + 39);
+    assert_eq!(search(&db, &[17.2, 0.0], 2, None), vec!["v18", "v16"]);
+    assert_eq!(search(&db, &[4100.2, 0.0], 2, None), vec!["v4100", "v4101"]);
+}
+
+#[test]
+fn test_quantized_collections_need_hnsw() {
+    let db = Database::new();
+    let quantized = Config {
+        quantization: QuantizationType::SQ8,
+        ..config()
+    };
+    assert!(db.create_collection("q", quantized).is_err());
+}
+
+/// Flat index that counts the searches it serves
+struct Counting {
+    inner: FlatIndex,
+    searches: Arc<AtomicUsize>,
+}
+
+impl AnnIndex for Counting {
+    fn insert(
+        &self,
+        internal_id: InternalId,
+        vector: &[f32],
+        storage: &dyn IndexStorage,
+    ) -> Result<()> {
+        self.inner.insert(internal_id, vector, storage)
+    }
+
+    fn remove(&self, internal_id: InternalId) {
+        self.inner.remove(internal_id)
+    }
+
+    fn search(
+        &self,
+        query: &[f32],
+        k: usize,
+        ef_search: Option<usize>,
+        storage: &dyn IndexStorage,
+        filter: Option<&Filter>,
+        usage: &mut SearchUsage,
+    ) -> Result<Vec<(InternalId, f32)>> {
+        self.searches.fetch_add(1, Ordering::Relaxed);
+        self.inner
+            .search(query, k, ef_search, storage, filter, usage)
+    }
+
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.inner.memory_usage()
+    }
+
+    fn serialize(&self) -> Result<Vec<u8>> {
+        self.inner.serialize()
+    }
+
+    fn deserialize(&self, bytes: &[u8]) -> Result<()> {
+        self.inner.deserialize(bytes)
+    }
+
+    fn fresh(&self) -> Box<dyn AnnIndex> {
+        Box::new(Counting {
+            inner: FlatIndex::new(DistanceMetric::Euclidean),
+            searches: self.searches.clone(),
+        })
+    }
+}
+
+#[test]
+fn test_custom_index_in_embedded_mode() {
+    let searches = Arc::new(AtomicUsize::new(0));
+    let index = Box::new(Counting {
+        inner: FlatIndex::new(DistanceMetric::Euclidean),
+        searches: searches.clone(),
+    });
+    let mut db = VectorDb::with_index(config(), index).unwrap();
+    for i in 0..10 {
+        db.insert(format!("v{i}"), &[i as f32, 1.0], None).unwrap();
+    }
+    db.delete("v3").unwrap();
+    db.compact().unwrap();
+
+    let hits = db.search(&[3.2, 1.0], 2, None).unwrap();
+    assert_eq!(ids(&hits), vec!["v4", "v2"]);
+    assert_eq!(searches.load(Ordering::Relaxed), 1);
+}
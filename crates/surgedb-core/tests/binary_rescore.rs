@@ -0,0 +1,146 @@
+mod common;
+
+use common::vector;
+use surgedb_core::{
+    Config, Database, DistanceMetric, PersistentConfig, PersistentVectorDb, QuantizationType,
+    QuantizedConfig, QuantizedVectorDb, SearchParams,
+};
+use tempfile::tempdir;
+
+const DIMS: usize = 32;
+
+/// IDs of the `k` nearest vectors by exact cosine distance
+fn exact_top(query: &[f32], n: usize, k: usize) -> Vec<String> {
+    let mut all: Vec<_> = (0..n)
+        .map(|i| {
+            (
+                format!("v{i}"),
+                DistanceMetric::Cosine.distance(query, &vector(i, DIMS)),
+            )
+        })
+        .collect();
+    all.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+    all.into_iter().take(k).map(|(id, _)| id).collect()
+}
+
+fn no_rescore() -> SearchParams {
+    SearchParams {
+        rescore: Some(false),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_persistent_binary_rescores_with_full_precision() {
+    let dir = tempdir().unwrap();
+    let config = PersistentConfig {
+        dimensions: DIMS,
+        quantization: QuantizationType::Binary,
+        ..Default::default()
+    };
+    let mut db = PersistentVectorDb::open(dir.path(), config.clone()).unwrap();
+    for i in 0..500 {
+        db.insert(format!("v{i}"), &vector(i, DIMS), None).unwrap();
+    }
+
+    let query = vector(1234, DIMS);
+    let (results, usage) = db
+        .search_with_params(&query, 5, None, SearchParams::default())
+        .unwrap();
+    assert_eq!(results.len(), 5);
+    assert_eq!(usage.rescored_candidates, 15);
+    // Returned distances are the exact ones, in order
+    for (id, distance, _) in &results {
+        let i: usize = id.as_str()[1..].parse().unwrap();
+        let exact = DistanceMetric::Cosine.distance(&query, &vector(i, DIMS));
+        assert!((distance - exact).abs() < 1e-5);
+    }
+    assert!(results.windows(2).all(|w| w[0].1 <= w[1].1));
+
+    // A wider rescoring pass finds the exact nearest neighbor
+    let params = SearchParams {
+        oversampling: Some(20.0),
+        ..Default::default()
+    };
+    let (results, usage) = db.search_ids_with_params(&query, 1, None, params).unwrap();
+    assert!(usage.rescored_candidates >= 20);
+    assert_eq!(results[0].0.as_str(), exact_top(&query, 500, 1)[0]);
+
+    // Without rescoring, distances are the sign-code approximation
+    let (_, usage) = db
+        .search_with_params(&query, 5, None, no_rescore())
+        .unwrap();
+    assert_eq!(usage.rescored_candidates, 0);
+
+    // The codes are rebuilt when the collection is reopened
+    db.checkpoint().unwrap();
+    drop(db);
+    let db = PersistentVectorDb::open(dir.path(), config).unwrap();
+    let (results, usage) = db.search_ids_with_params(&query, 1, None, params).unwrap();
+    assert!(usage.rescored_candidates >= 20);
+    assert_eq!(results[0].0.as_str(), exact_top(&query, 500, 1)[0]);
+}
+
+#[test]
+fn test_quantized_binary_uses_graph_and_rescores() {
+    let mut db = QuantizedVectorDb::new(QuantizedConfig {
+        dimensions: DIMS,
+        quantization: QuantizationType::Binary,
+        keep_originals: true,
+        rerank_multiplier: 4,
+        ..Default::default()
+    })
+    .unwrap();
+    for i in 0..1000 {
+        db.insert(format!("v{i}"), &vector(i, DIMS), None).unwrap();
+    }
+
+    let query = vector(7777, DIMS);
+    let (results, usage) = db
+        .search_with_params(&query, 3, None, SearchParams::default())
+        .unwrap();
+    assert_eq!(results.len(), 3);
+    assert_eq!(usage.rescored_candidates, 12);
+    // Traversed as a graph rather than scanned
+    assert!(usage.graph_hops > 0);
+    assert!(usage.vectors_scanned < 1000 + 12);
+
+    let (results, usage) = db
+        .search_ids_with_params(&query, 3, None, no_rescore())
+        .unwrap();
+    assert_eq!(results.len(), 3);
+    assert_eq!(usage.rescored_candidates, 0);
+}
+
+#[test]
+fn test_database_binary_collection_survives_reopen() {
+    let dir = tempdir().unwrap();
+    {
+        let db = Database::open(dir.path()).unwrap();
+        db.create_collection(
+            "docs",
+            Config {
+                dimensions: DIMS,
+                quantization: QuantizationType::Binary,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let collection = db.get_collection("docs").unwrap();
+        for i in 0..100 {
+            collection
+                .insert(format!("v{i}"), &vector(i, DIMS), None)
+                .unwrap();
+        }
+    }
+
+    let db = Database::open(dir.path()).unwrap();
+    let collection = db.get_collection("docs").unwrap();
+    assert_eq!(collection.stats().quantization, "Binary");
+    let (results, usage) = collection
+        .search_with_params(&vector(5, DIMS), 1, None, SearchParams::default())
+        .unwrap();
+    assert_eq!(results[0].0.as_str(), "v5");
+    assert!(results[0].1.abs() < 1e-5);
+    assert!(usage.rescored_candidates > 0);
+}
@@ -0,0 +1,113 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde_json::json;
+use surgedb_core::{Config, Database, DistanceMetric, Error};
+use tempfile::tempdir;
+
+const DIMS: usize = 8;
+
+fn config() -> Config {
+    Config::builder(DIMS)
+        .distance_metric(DistanceMetric::Euclidean)
+        .build()
+        .unwrap()
+}
+
+fn vectors(n: usize) -> Vec<Vec<f32>> {
+    let mut rng = StdRng::seed_from_u64(7);
+    (0..n)
+        .map(|_| (0..DIMS).map(|_| rng.gen::<f32>()).collect())
+        .collect()
+}
+
+/// Positions of each vector's `k` nearest others, found by brute force
+fn knn_graph(vectors: &[Vec<f32>], k: usize) -> Vec<Vec<usize>> {
+    vectors
+        .iter()
+        .enumerate()
+        .map(|(i, v)| {
+            let mut others: Vec<(usize, f32)> = vectors
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .map(|(j, w)| (j, DistanceMetric::Euclidean.distance(v, w)))
+                .collect();
+            others.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+            others.into_iter().take(k).map(|(j, _)| j).collect()
+        })
+        .collect()
+}
+
+fn items(vectors: &[Vec<f32>]) -> Vec<(String, Vec<f32>, Option<serde_json::Value>)> {
+    vectors
+        .iter()
+        .enumerate()
+        .map(|(i, v)| (format!("v{i}"), v.clone(), Some(json!({ "i": i }))))
+        .collect()
+}
+
+#[test]
+fn test_bulk_load_from_knn_graph() {
+    let dir = tempdir().unwrap();
+    let vectors = vectors(1000);
+    let neighbors = knn_graph(&vectors, 16);
+    {
+        let db = Database::open(dir.path()).unwrap();
+        db.create_collection("c", config()).unwrap();
+        let collection = db.get_collection("c").unwrap();
+        collection.bulk_load(items(&vectors), neighbors).unwrap();
+        assert_eq!(collection.len(), 1000);
+    }
+
+    // The checkpoint holds the records and graph
+    let db = Database::open(dir.path()).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    assert_eq!(collection.len(), 1000);
+    let found = (0..100)
+        .filter(|&i| {
+            let hits = collection.search(&vectors[i], 1, None).unwrap();
+            hits[0].0.to_string() == format!("v{i}")
+        })
+        .count();
+    assert!(found >= 95, "found {found} of 100 records");
+
+    // Later writes link into the loaded graph
+    collection
+        .insert("new".to_string(), &[2.0; DIMS], None)
+        .unwrap();
+    let hits = collection.search(&[2.0; DIMS], 1, None).unwrap();
+    assert_eq!(hits[0].0.to_string(), "new");
+}
+
+#[test]
+fn test_bulk_load_rejects_bad_input() {
+    let db = Database::new();
+    db.create_collection("c", config()).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    let vectors = vectors(3);
+
+    let err = collection
+        .bulk_load(items(&vectors), vec![vec![1], vec![0]])
+        .unwrap_err();
+    assert!(matches!(err, Error::InvalidConfig(_)));
+    let err = collection
+        .bulk_load(items(&vectors), vec![vec![1], vec![3], vec![0]])
+        .unwrap_err();
+    assert!(matches!(err, Error::InvalidConfig(_)));
+    let mut repeated = items(&vectors);
+    repeated[2].0 = "v0".to_string();
+    let err = collection
+        .bulk_load(repeated, vec![vec![1], vec![0], vec![0]])
+        .unwrap_err();
+    assert!(matches!(err, Error::DuplicateId(id) if id == "v0"));
+    assert_eq!(collection.len(), 0);
+
+    // Only an empty collection can be loaded
+    collection
+        .bulk_load(items(&vectors), vec![vec![1], vec![0], vec![]])
+        .unwrap();
+    let err = collection
+        .bulk_load(items(&vectors), vec![vec![], vec![], vec![]])
+        .unwrap_err();
+    assert!(matches!(err, Error::InvalidConfig(_)));
+}
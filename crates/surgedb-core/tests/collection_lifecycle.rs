@@ -0,0 +1,252 @@
+use serde_json::json;
+use surgedb_core::{
+    CollectionUpdate, Config, Database, DistanceMetric, Error, NameCase, QuantizationType,
+};
+use tempfile::tempdir;
+
+fn config() -> Config {
+    Config::builder(2)
+        .distance_metric(DistanceMetric::Euclidean)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn test_delete_refuses_busy_collection_unless_forced() {
+    let db = Database::new();
+    db.create_collection("c", config()).unwrap();
+    let collection = db.get_collection("c").unwrap();
+
+    let job = collection.begin_job();
+    assert_eq!(collection.active_jobs(), 1);
+    assert!(matches!(
+        db.delete_collection("c"),
+        Err(Error::CollectionBusy(name)) if name == "c"
+    ));
+    drop(job);
+    assert_eq!(collection.active_jobs(), 0);
+    db.delete_collection("c").unwrap();
+
+    db.create_collection("c", config()).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    let _job = collection.begin_job();
+    db.force_delete_collection("c").unwrap();
+    assert!(matches!(
+        db.get_collection("c"),
+        Err(Error::CollectionNotFound(_))
+    ));
+    // The job's handle still works
+    collection.insert("a".into(), &[1.0, 0.0], None).unwrap();
+}
+
+#[test]
+fn test_deleted_files_removed_with_last_handle() {
+    let dir = tempdir().unwrap();
+    let db = Database::open(dir.path()).unwrap();
+    db.create_collection("c", config()).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    collection.insert("a".into(), &[1.0, 0.0], None).unwrap();
+
+    db.delete_collection("c").unwrap();
+    let files = dir.path().join("c");
+    assert!(files.exists());
+    // The name is taken until the deleted collection's files are gone
+    assert!(matches!(
+        db.create_collection("c", config()),
+        Err(Error::CollectionBusy(_))
+    ));
+    collection.insert("b".into(), &[2.0, 0.0], None).unwrap();
+
+    drop(collection);
+    assert!(!files.exists());
+    db.create_collection("c", config()).unwrap();
+    assert_eq!(db.get_collection("c").unwrap().len(), 0);
+}
+
+#[test]
+fn test_interrupted_delete_not_recovered() {
+    let dir = tempdir().unwrap();
+    {
+        let db = Database::open(dir.path()).unwrap();
+        db.create_collection("c", config()).unwrap();
+        let collection = db.get_collection("c").unwrap();
+        collection.insert("a".into(), &[1.0, 0.0], None).unwrap();
+        db.delete_collection("c").unwrap();
+        // Simulate a crash before the last handle is dropped
+        std::mem::forget(collection);
+    }
+
+    let db = Database::open(dir.path()).unwrap();
+    assert!(db.get_collection("c").is_err());
+    db.create_collection("c", config()).unwrap();
+    assert_eq!(db.get_collection("c").unwrap().len(), 0);
+}
+
+#[test]
+fn test_rename_collection() {
+    let dir = tempdir().unwrap();
+    {
+        let db = Database::open(dir.path()).unwrap();
+        db.create_collection("a", config()).unwrap();
+        db.create_collection("b", config()).unwrap();
+        db.set_alias("live", "a").unwrap();
+        let collection = db.get_collection("a").unwrap();
+        collection.insert("x".into(), &[1.0, 0.0], None).unwrap();
+
+        let rename = |name: &str| CollectionUpdate {
+            name: Some(name.to_string()),
+            ..CollectionUpdate::default()
+        };
+        assert!(matches!(
+            db.update_collection("a", rename("b")),
+            Err(Error::DuplicateCollection(_))
+        ));
+        let job = collection.begin_job();
+        assert!(matches!(
+            db.update_collection("a", rename("c")),
+            Err(Error::CollectionBusy(_))
+        ));
+        drop(job);
+
+        // Through the alias, which follows the collection
+        db.update_collection("live", rename("c")).unwrap();
+        assert!(db.get_collection("a").is_err());
+        assert_eq!(db.resolve_name("live"), "c");
+        // Handles taken before the rename keep writing to the moved files
+        collection.insert("y".into(), &[2.0, 0.0], None).unwrap();
+        assert!(!dir.path().join("a").exists());
+    }
+
+    let db = Database::open(dir.path()).unwrap();
+    assert_eq!(db.get_collection("live").unwrap().len(), 2);
+    db.create_collection("a", config()).unwrap();
+}
+
+#[test]
+fn test_update_collection_config() {
+    let dir = tempdir().unwrap();
+    {
+        let db = Database::open(dir.path()).unwrap();
+        db.create_collection("c", config()).unwrap();
+        let collection = db.get_collection("c").unwrap();
+        for i in 0..50 {
+            collection
+                .insert(format!("v{i}"), &[i as f32, 1.0], None)
+                .unwrap();
+        }
+        db.update_collection(
+            "c",
+            CollectionUpdate {
+                ef_search: Some(77),
+                quantization: Some(QuantizationType::Binary),
+                ..CollectionUpdate::default()
+            },
+        )
+        .unwrap();
+        let config = collection.config();
+        assert_eq!(config.hnsw.ef_search, 77);
+        assert_eq!(config.quantization, QuantizationType::Binary);
+        // Ranked by full-precision distance after the sign codes
+        let hits = collection.search(&[10.0, 1.0], 50, None).unwrap();
+        assert_eq!(hits.len(), 50);
+        assert_eq!(hits[0].0.to_string(), "v10");
+    }
+
+    let db = Database::open(dir.path()).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    assert_eq!(collection.config().hnsw.ef_search, 77);
+    assert_eq!(collection.config().quantization, QuantizationType::Binary);
+    assert_eq!(collection.len(), 50);
+
+    // In-memory collections can't switch backends
+    let db = Database::new();
+    db.create_collection("c", config()).unwrap();
+    let err = db
+        .update_collection(
+            "c",
+            CollectionUpdate {
+                quantization: Some(QuantizationType::SQ8),
+                ..CollectionUpdate::default()
+            },
+        )
+        .unwrap_err();
+    assert!(matches!(err, Error::InvalidConfig(_)));
+}
+
+#[test]
+fn test_collection_name_rules() {
+    let db = Database::new();
+    for name in ["", "a/b", "../c", "_audit", "_Usage.2026"] {
+        assert!(
+            matches!(
+                db.create_collection(name, config()),
+                Err(Error::InvalidCollectionName { .. })
+            ),
+            "{name}"
+        );
+    }
+    db.create_collection("Docs", config()).unwrap();
+    assert!(matches!(
+        db.set_alias("a b", "Docs"),
+        Err(Error::InvalidCollectionName { .. })
+    ));
+    let rename = CollectionUpdate {
+        name: Some("_feedback".to_string()),
+        ..CollectionUpdate::default()
+    };
+    assert!(matches!(
+        db.update_collection("Docs", rename),
+        Err(Error::InvalidCollectionName { .. })
+    ));
+    // Preserved case by default
+    assert!(db.get_collection("docs").is_err());
+}
+
+#[test]
+fn test_lowercase_names() {
+    let db = Database::new();
+    db.set_name_case(NameCase::Lowercase);
+    db.create_collection("Docs", config()).unwrap();
+    assert_eq!(db.list_collections(), vec!["docs".to_string()]);
+    assert!(matches!(
+        db.create_collection("DOCS", config()),
+        Err(Error::DuplicateCollection(_))
+    ));
+    db.set_alias("Live", "DOCS").unwrap();
+    assert_eq!(db.resolve_name("LIVE"), "docs");
+    db.get_collection("live").unwrap();
+    db.delete_alias("LIVE").unwrap();
+    db.delete_collection("dOcS").unwrap();
+}
+
+#[test]
+fn test_system_collections() {
+    let dir = tempdir().unwrap();
+    {
+        let db = Database::open(dir.path()).unwrap();
+        db.create_collection("docs", config()).unwrap();
+        assert!(matches!(
+            db.system_collection("docs"),
+            Err(Error::InvalidCollectionName { .. })
+        ));
+        let usage = db.system_collection("_usage").unwrap();
+        usage
+            .insert("a".into(), &[0.0], Some(json!({ "searches": 3 })))
+            .unwrap();
+        // The same collection on later calls
+        assert_eq!(db.system_collection("_usage").unwrap().len(), 1);
+        assert_eq!(db.list_collections(), vec!["docs".to_string()]);
+        assert_eq!(db.list_system_collections(), vec!["_usage".to_string()]);
+        assert!(!db.get_stats().collections.contains_key("_usage"));
+    }
+
+    let db = Database::open(dir.path()).unwrap();
+    assert_eq!(db.list_system_collections(), vec!["_usage".to_string()]);
+    let (_, metadata) = db
+        .get_collection("_usage")
+        .unwrap()
+        .get("a")
+        .unwrap()
+        .unwrap();
+    assert_eq!(metadata, Some(json!({ "searches": 3 })));
+}
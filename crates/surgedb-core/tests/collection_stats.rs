@@ -0,0 +1,288 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde_json::json;
+use surgedb_core::activity::ACTIVITY_MINUTES;
+use surgedb_core::filter::Filter;
+use surgedb_core::{ActivityMinute, Config, Database, FilterStrategy, IndexKind};
+
+#[test]
+fn test_deleted_count_in_stats() {
+    let db = Database::new();
+    let config = Config {
+        dimensions: 4,
+        ..Default::default()
+    };
+    db.create_collection("docs", config).unwrap();
+    let collection = db.get_collection("docs").unwrap();
+
+    collection
+        .insert("a".to_string(), &[1.0, 0.0, 0.0, 0.0], None)
+        .unwrap();
+    collection
+        .insert("b".to_string(), &[0.0, 1.0, 0.0, 0.0], None)
+        .unwrap();
+    collection
+        .upsert("a".to_string(), &[0.5, 0.5, 0.0, 0.0], None)
+        .unwrap();
+    collection.delete("b").unwrap();
+
+    let stats = collection.stats();
+    assert_eq!(stats.vector_count, 1);
+    assert_eq!(stats.deleted_count, 2);
+}
+
+#[test]
+fn test_graph_stats() {
+    let db = Database::new();
+    let config = Config {
+        dimensions: 4,
+        ..Default::default()
+    };
+    db.create_collection("docs", config).unwrap();
+    let collection = db.get_collection("docs").unwrap();
+    let stats = collection.graph_stats().unwrap();
+    assert_eq!((stats.nodes, stats.layers.len()), (0, 0));
+
+    let mut rng = StdRng::seed_from_u64(7);
+    for i in 0..200 {
+        let vector: Vec<f32> = (0..4).map(|_| rng.gen::<f32>()).collect();
+        collection.insert(format!("v{i}"), &vector, None).unwrap();
+    }
+    collection.delete("v0").unwrap();
+
+    // Deleted nodes stay in the graph until compaction
+    let stats = collection.graph_stats().unwrap();
+    assert_eq!(stats.nodes, 200);
+    assert_eq!(stats.layers.len(), stats.max_layer + 1);
+    let base = &stats.layers[0];
+    assert_eq!((base.nodes, base.isolated), (200, 0));
+    assert!(base.max_degree <= 32);
+    assert_eq!(base.avg_degree, base.edges as f64 / 200.0);
+    assert!(stats.layers.windows(2).all(|w| w[0].nodes >= w[1].nodes));
+
+    let config = Config {
+        dimensions: 4,
+        index: IndexKind::Flat,
+        ..Default::default()
+    };
+    db.create_collection("flat", config).unwrap();
+    assert!(db.get_collection("flat").unwrap().graph_stats().is_none());
+}
+
+#[test]
+fn test_estimate_recall() {
+    let db = Database::new();
+    let config = Config {
+        dimensions: 8,
+        ..Default::default()
+    };
+    db.create_collection("docs", config).unwrap();
+    let collection = db.get_collection("docs").unwrap();
+
+    assert_eq!(collection.estimate_recall(10, 5).unwrap(), None);
+
+    let mut rng = StdRng::seed_from_u64(7);
+    for i in 0..200 {
+        let vector: Vec<f32> = (0..8).map(|_| rng.gen::<f32>()).collect();
+        collection.insert(format!("v{i}"), &vector, None).unwrap();
+    }
+
+    let recall = collection.estimate_recall(20, 5).unwrap().unwrap();
+    assert!((0.0..=1.0).contains(&recall));
+    assert!(recall > 0.8, "recall too low: {recall}");
+}
+
+#[test]
+fn test_estimate_filter_recall() {
+    let db = Database::new();
+    let config = Config {
+        dimensions: 8,
+        ..Default::default()
+    };
+    db.create_collection("docs", config).unwrap();
+    let collection = db.get_collection("docs").unwrap();
+
+    let mut rng = StdRng::seed_from_u64(7);
+    for i in 0..300 {
+        let vector: Vec<f32> = (0..8).map(|_| rng.gen::<f32>()).collect();
+        collection
+            .insert(format!("v{i}"), &vector, Some(json!({ "group": i % 10 })))
+            .unwrap();
+    }
+
+    // A selective filter is cheaper to scan than to search through the graph
+    let narrow = Filter::Exact("group".into(), json!(3));
+    let report = collection
+        .estimate_filter_recall(&narrow, 20, 5, 0.9)
+        .unwrap()
+        .unwrap();
+    assert_eq!((report.matches, report.queries, report.k), (30, 20, 5));
+    assert!((0.0..=1.0).contains(&report.recall));
+    assert!(report.mean_vectors_scanned > 30.0);
+    assert_eq!(report.recommended, FilterStrategy::Exact);
+
+    // Recall short of the target always switches to a scan
+    let broad = Filter::Not(Box::new(narrow));
+    let report = collection
+        .estimate_filter_recall(&broad, 10, 50, 1.1)
+        .unwrap()
+        .unwrap();
+    assert_eq!(report.matches, 270);
+    assert_eq!(report.recommended, FilterStrategy::Exact);
+
+    let none = Filter::Exact("group".into(), json!(42));
+    assert_eq!(
+        collection
+            .estimate_filter_recall(&none, 10, 5, 0.9)
+            .unwrap(),
+        None
+    );
+}
+
+#[test]
+fn test_export_graph() {
+    let db = Database::new();
+    let config = Config {
+        dimensions: 4,
+        ..Default::default()
+    };
+    db.create_collection("docs", config).unwrap();
+    let collection = db.get_collection("docs").unwrap();
+
+    let mut rng = StdRng::seed_from_u64(11);
+    for i in 0..50 {
+        let vector: Vec<f32> = (0..4).map(|_| rng.gen::<f32>()).collect();
+        collection.insert(format!("v{i}"), &vector, None).unwrap();
+    }
+    collection.delete("v3").unwrap();
+
+    let full = collection.export_graph(None, None).unwrap();
+    assert_eq!(full.nodes.len(), 49);
+    assert!(!full.edges.is_empty());
+    assert!(full
+        .edges
+        .iter()
+        .all(|e| e.source.as_str() != "v3" && e.target.as_str() != "v3"));
+
+    let top = full.nodes.iter().map(|n| n.max_layer).max().unwrap();
+    let level = collection.export_graph(Some(top), None).unwrap();
+    assert!(level.nodes.iter().all(|n| n.max_layer == top));
+    assert!(level.edges.iter().all(|e| e.layer == top));
+
+    let sampled = collection.export_graph(None, Some(10)).unwrap();
+    assert_eq!(sampled.nodes.len(), 10);
+    let ids: Vec<_> = sampled.nodes.iter().map(|n| &n.id).collect();
+    assert!(sampled
+        .edges
+        .iter()
+        .all(|e| ids.contains(&&e.source) && ids.contains(&&e.target)));
+}
+
+#[test]
+fn test_memory_breakdown() {
+    let db = Database::new();
+    let config = Config {
+        dimensions: 8,
+        ..Default::default()
+    };
+    db.create_collection("docs", config).unwrap();
+    let collection = db.get_collection("docs").unwrap();
+
+    let empty = collection.stats().memory_breakdown;
+    for i in 0..100 {
+        let vector: Vec<f32> = (0..8).map(|d| (i * 8 + d) as f32).collect();
+        let metadata = serde_json::json!({ "title": format!("document number {i}") });
+        collection
+            .insert(format!("doc-{i}"), &vector, Some(metadata))
+            .unwrap();
+    }
+
+    let stats = collection.stats();
+    let breakdown = stats.memory_breakdown;
+    assert!(breakdown.vectors >= 100 * 8 * 4);
+    assert!(breakdown.graph > empty.graph);
+    assert!(breakdown.ids > empty.ids);
+    assert!(breakdown.metadata > empty.metadata);
+    assert_eq!(breakdown.total(), stats.memory_usage_bytes);
+
+    for i in 0..100 {
+        collection.delete(&format!("doc-{i}")).unwrap();
+    }
+    assert!(collection.stats().memory_breakdown.metadata < breakdown.metadata);
+}
+
+#[test]
+fn test_operation_latency_in_stats() {
+    let db = Database::new();
+    let config = Config {
+        dimensions: 4,
+        ..Default::default()
+    };
+    db.create_collection("docs", config).unwrap();
+    let collection = db.get_collection("docs").unwrap();
+
+    for i in 0..20 {
+        collection
+            .insert(format!("doc-{i}"), &[i as f32, 1.0, 0.0, 0.0], None)
+            .unwrap();
+    }
+    collection
+        .upsert_batch(vec![("doc-20".to_string(), vec![1.0; 4], None)])
+        .unwrap();
+    collection.delete("doc-3").unwrap();
+    collection.search(&[1.0, 1.0, 0.0, 0.0], 5, None).unwrap();
+    collection
+        .search_batch(
+            &[vec![1.0; 4], vec![0.0, 1.0, 0.0, 0.0]],
+            5,
+            None,
+            Default::default(),
+        )
+        .unwrap();
+
+    // Recall estimates search internally without showing up here
+    collection.estimate_recall(10, 5).unwrap();
+
+    let latency = db.get_stats().collections["docs"].latency.clone();
+    assert_eq!(latency.insert.count, 21);
+    assert_eq!(latency.search.count, 3);
+    assert_eq!(latency.delete.count, 1);
+    assert!(latency.search.p50_us <= latency.search.p99_us);
+    assert!(latency.search.p99_us <= latency.search.max_us);
+    assert_eq!(latency, collection.latency());
+
+    // Handles to the same collection share its histograms
+    let other = db.get_collection("docs").unwrap();
+    other.delete("doc-4").unwrap();
+    assert_eq!(collection.latency().delete.count, 2);
+}
+
+#[test]
+fn test_recent_activity() {
+    let db = Database::new();
+    let config = Config {
+        dimensions: 4,
+        ..Default::default()
+    };
+    db.create_collection("docs", config).unwrap();
+    let collection = db.get_collection("docs").unwrap();
+    assert_eq!(collection.activity().len(), ACTIVITY_MINUTES);
+
+    collection
+        .insert("a".to_string(), &[1.0, 0.0, 0.0, 0.0], None)
+        .unwrap();
+    let batch = (0..3)
+        .map(|i| (format!("b{i}"), vec![0.0, 1.0, i as f32, 0.0], None))
+        .collect();
+    collection.upsert_batch(batch).unwrap();
+    collection.search(&[1.0, 0.0, 0.0, 0.0], 2, None).unwrap();
+    collection.delete("a").unwrap();
+
+    // Summed in case the operations straddle a minute
+    let minutes = &db.activity()["docs"];
+    assert!(minutes.windows(2).all(|w| w[1].start == w[0].start + 60));
+    let total = |f: fn(&ActivityMinute) -> u64| minutes.iter().map(f).sum::<u64>();
+    assert_eq!(total(|m| m.inserts), 2);
+    assert_eq!(total(|m| m.inserted_records), 4);
+    assert_eq!(total(|m| m.searches), 1);
+    assert_eq!(total(|m| m.deletes), 1);
+}
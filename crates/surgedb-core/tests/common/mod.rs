@@ -0,0 +1,38 @@
+//! Fixtures shared by the integration tests
+//!
+//! Each test file includes this with `mod common;` and uses only part of it.
+#![allow(dead_code)]
+
+use surgedb_core::{Config, Database, QuantizationType, SearchHit};
+
+/// Test vector number `i`: deterministic, spread out points in
+/// `[-0.5, 0.5)`, so no two are near duplicates
+pub fn vector(i: usize, dims: usize) -> Vec<f32> {
+    let mut state = (i as u64 + 1).wrapping_mul(6364136223846793005);
+    (0..dims)
+        .map(|_| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 33) as f32 / (1u64 << 31) as f32 - 0.5
+        })
+        .collect()
+}
+
+/// IDs of `hits`, best first
+pub fn ids(hits: &[SearchHit]) -> Vec<String> {
+    hits.iter().map(|h| h.0.to_string()).collect()
+}
+
+/// Run `check` on a fresh in-memory database, once with a standard and once
+/// with an SQ8-quantized collection config of `dims` dimensions
+pub fn for_each_backend(dims: usize, check: impl Fn(&Database, Config)) {
+    for quantization in [QuantizationType::None, QuantizationType::SQ8] {
+        let config = Config {
+            dimensions: dims,
+            quantization,
+            ..Default::default()
+        };
+        check(&Database::new(), config);
+    }
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use common::{for_each_backend, ids, vector};
+use serde_json::json;
+use surgedb_core::filter::Filter;
+use surgedb_core::{Config, Database};
+use tempfile::tempdir;
+
+const DIMS: usize = 8;
+
+/// 100 records, then 30 deleted and 20 overwritten
+fn churn(db: &Database, config: Config) {
+    db.create_collection("c", config).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    for i in 0..100 {
+        collection
+            .insert(
+                format!("v{i}"),
+                &vector(i, DIMS),
+                Some(json!({ "group": i % 2 })),
+            )
+            .unwrap();
+    }
+    for i in 0..30 {
+        collection.delete(&format!("v{i}")).unwrap();
+    }
+    for i in 30..50 {
+        collection
+            .upsert(
+                format!("v{i}"),
+                &vector(i, DIMS),
+                Some(json!({ "group": 2 })),
+            )
+            .unwrap();
+    }
+}
+
+fn check_compaction(db: &Database) {
+    let collection = db.get_collection("c").unwrap();
+    let odd = Filter::Exact("group".into(), json!(1));
+    collection.cache_filter(odd.clone()).unwrap();
+
+    let garbage = collection.garbage_stats();
+    assert_eq!(garbage.tombstones, 50);
+    assert!(garbage.tombstone_ratio > 0.3);
+    assert!(garbage.dead_vector_bytes > 0);
+    assert!(garbage.dead_payload_bytes > 0);
+    let before = collection.search(&vector(60, DIMS), 5, Some(&odd)).unwrap();
+
+    let report = collection.compact().unwrap();
+    assert_eq!(report.vectors, 70);
+    assert_eq!(report.reclaimed_slots, 50);
+    assert_eq!(report.reclaimed_vector_bytes, garbage.dead_vector_bytes);
+
+    let garbage = collection.garbage_stats();
+    assert_eq!(garbage.tombstones, 0);
+    assert_eq!(garbage.dead_vector_bytes, 0);
+    assert_eq!(garbage.dead_payload_bytes, 0);
+    assert_eq!(collection.stats().deleted_count, 0);
+
+    // Records, metadata, cached filters and search results are unchanged
+    assert!(collection.get("v3").unwrap().is_none());
+    let (_, meta) = collection.get("v40").unwrap().unwrap();
+    assert_eq!(meta, Some(json!({ "group": 2 })));
+    assert_eq!(collection.cached_filters().len(), 1);
+    let after = collection.search(&vector(60, DIMS), 5, Some(&odd)).unwrap();
+    assert_eq!(ids(&after), ids(&before));
+    assert_eq!(collection.list_page(None, 1000).unwrap().records.len(), 70);
+}
+
+#[test]
+fn test_compact_in_memory() {
+    for_each_backend(DIMS, |db, config| {
+        churn(db, config);
+        check_compaction(db);
+    });
+}
+
+#[test]
+fn test_compact_persistent_survives_reopen() {
+    let dir = tempdir().unwrap();
+    {
+        let db = Database::open(dir.path()).unwrap();
+        churn(
+            &db,
+            Config {
+                dimensions: DIMS,
+                ..Default::default()
+            },
+        );
+        // The WAL is the only segment until a checkpoint
+        assert_eq!(db.get_collection("c").unwrap().garbage_stats().segments, 1);
+        check_compaction(&db);
+        let collection = db.get_collection("c").unwrap();
+        assert_eq!(collection.garbage_stats().segments, 2);
+        collection.delete("v99").unwrap();
+    }
+
+    let db = Database::open(dir.path()).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    assert_eq!(collection.stats().vector_count, 69);
+    assert_eq!(collection.garbage_stats().tombstones, 1);
+    assert!(collection.get("v99").unwrap().is_none());
+    assert!(collection.get("v98").unwrap().is_some());
+}
@@ -1,4 +1,6 @@
-use surgedb_core::{PersistentConfig, PersistentVectorDb};
+use serde_json::json;
+use surgedb_core::filter::Filter;
+use surgedb_core::{Config, Database, PersistentConfig, PersistentVectorDb, QuantizationType};
 use tempfile::tempdir;
 
 #[test]
@@ -64,3 +66,94 @@ fn test_delete_persistence() {
         }
     }
 }
+
+fn check_bulk_deletes(db: &Database, name: &str) {
+    let collection = db.get_collection(name).unwrap();
+    for i in 0..30 {
+        let tenant = ["a", "b", "c"][i % 3];
+        collection
+            .insert(
+                format!("v{i}"),
+                &[i as f32, 1.0],
+                Some(json!({ "tenant": tenant })),
+            )
+            .unwrap();
+    }
+
+    let tenant_a = Filter::Exact("tenant".into(), json!("a"));
+    let mut deleted: Vec<String> = collection
+        .delete_by_filter(&tenant_a)
+        .unwrap()
+        .iter()
+        .map(|id| id.to_string())
+        .collect();
+    deleted.sort_by_key(|id| id[1..].parse::<usize>().unwrap());
+    let expected: Vec<String> = (0..30).step_by(3).map(|i| format!("v{i}")).collect();
+    assert_eq!(deleted, expected);
+    assert!(collection
+        .search(&[0.0, 1.0], 30, Some(&tenant_a))
+        .unwrap()
+        .is_empty());
+    assert!(collection.delete_by_filter(&tenant_a).unwrap().is_empty());
+
+    // Unknown and repeated IDs are skipped
+    let ids = ["v1", "v1", "v2", "v3", "missing"].map(String::from);
+    let deleted = collection.delete_batch(&ids).unwrap();
+    assert_eq!(deleted.len(), 2);
+    assert_eq!(collection.scan().count(), 18);
+}
+
+#[test]
+fn test_bulk_deletes() {
+    let db = Database::new();
+    let config = Config::builder(2).build().unwrap();
+    db.create_collection("c", config.clone()).unwrap();
+    check_bulk_deletes(&db, "c");
+
+    let quantized = Config {
+        quantization: QuantizationType::SQ8,
+        ..config
+    };
+    db.create_collection("q", quantized).unwrap();
+    check_bulk_deletes(&db, "q");
+}
+
+#[test]
+fn test_bulk_deletes_persist() {
+    let dir = tempdir().unwrap();
+    {
+        let db = Database::open(dir.path()).unwrap();
+        db.create_collection("c", Config::builder(2).build().unwrap())
+            .unwrap();
+        check_bulk_deletes(&db, "c");
+    }
+
+    let db = Database::open(dir.path()).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    assert_eq!(collection.scan().count(), 18);
+    assert!(collection.get("v2").unwrap().is_none());
+    assert!(collection.get("v4").unwrap().is_some());
+}
+
+#[test]
+fn test_overwriting_every_vector_keeps_it_searchable() {
+    let dir = tempdir().unwrap();
+    let db = Database::open(dir.path()).unwrap();
+    let config = Config::builder(2)
+        .distance_metric(surgedb_core::DistanceMetric::Euclidean)
+        .build()
+        .unwrap();
+    db.create_collection("c", config).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    let items: Vec<_> = (0..26)
+        .map(|i| (format!("v{}", i), vec![i as f32, 0.0], None))
+        .collect();
+    collection.upsert_batch(items.clone()).unwrap();
+
+    // Each new node is linked while every other one is deleted
+    collection.upsert_batch(items).unwrap();
+    let results = collection.search(&[5.0, 0.0], 2, None).unwrap();
+    let ids: Vec<String> = results.iter().map(|(id, _, _)| id.to_string()).collect();
+    assert_eq!(ids.len(), 2);
+    assert_eq!(ids[0], "v5");
+}
@@ -0,0 +1,111 @@
+use surgedb_core::{
+    Config, Database, DistanceBounds, DistanceMetric, DistanceTo, Error, SearchParams,
+    VectorTransform,
+};
+
+fn collection(db: &Database, config: Config) -> surgedb_core::db::Collection {
+    db.create_collection("c", config).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    // A row of points along the x axis
+    collection
+        .upsert_batch(
+            (0..100)
+                .map(|i| (format!("p{}", i), vec![i as f32, 0.0], None))
+                .collect(),
+        )
+        .unwrap();
+    collection
+}
+
+fn config() -> Config {
+    Config::builder(2)
+        .distance_metric(DistanceMetric::Euclidean)
+        .build()
+        .unwrap()
+}
+
+fn ids(
+    collection: &surgedb_core::db::Collection,
+    query: &[f32],
+    bounds: &DistanceBounds,
+) -> Vec<String> {
+    let (hits, _) = collection
+        .search_bounded(query, 3, None, SearchParams::default(), bounds)
+        .unwrap();
+    hits.iter().map(|(id, _, _)| id.to_string()).collect()
+}
+
+#[test]
+fn test_excludes_records_near_a_second_vector() {
+    let db = Database::new();
+    let collection = collection(&db, config());
+
+    // Near 10, but not within 5 of 8: the search widens past the nearest
+    let bounds = DistanceBounds {
+        min_distance_to: Some(DistanceTo {
+            vector: vec![8.0, 0.0],
+            threshold: 5.0,
+        }),
+        ..DistanceBounds::default()
+    };
+    assert_eq!(
+        ids(&collection, &[10.0, 0.0], &bounds),
+        ["p13", "p14", "p15"]
+    );
+
+    // Near 10, but within 1 of 20
+    let bounds = DistanceBounds {
+        max_distance_to: Some(DistanceTo {
+            vector: vec![20.0, 0.0],
+            threshold: 1.0,
+        }),
+        ..DistanceBounds::default()
+    };
+    assert_eq!(
+        ids(&collection, &[10.0, 0.0], &bounds),
+        ["p19", "p20", "p21"]
+    );
+    // Nothing qualifies once the candidates are used up
+    let far = DistanceBounds {
+        max_distance_to: Some(DistanceTo {
+            vector: vec![0.0, 50.0],
+            threshold: 1.0,
+        }),
+        ..DistanceBounds::default()
+    };
+    assert!(ids(&collection, &[10.0, 0.0], &far).is_empty());
+
+    let mismatched = DistanceBounds {
+        max_distance_to: Some(DistanceTo {
+            vector: vec![1.0],
+            threshold: 1.0,
+        }),
+        ..DistanceBounds::default()
+    };
+    assert!(matches!(
+        collection.search_bounded(&[0.0, 0.0], 3, None, SearchParams::default(), &mismatched),
+        Err(Error::DimensionMismatch { .. })
+    ));
+}
+
+#[test]
+fn test_bound_vectors_are_transformed_like_the_query() {
+    let db = Database::new();
+    let config = Config {
+        transform: Some(VectorTransform::centering(vec![-50.0, 0.0])),
+        ..config()
+    };
+    let collection = collection(&db, config);
+    // Query 10 and bound 8 are searched as 60 and 58
+    let bounds = DistanceBounds {
+        min_distance_to: Some(DistanceTo {
+            vector: vec![8.0, 0.0],
+            threshold: 5.0,
+        }),
+        ..DistanceBounds::default()
+    };
+    assert_eq!(
+        ids(&collection, &[10.0, 0.0], &bounds),
+        ["p63", "p64", "p65"]
+    );
+}
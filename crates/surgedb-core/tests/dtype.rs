@@ -0,0 +1,56 @@
+use surgedb_core::{Config, Database, DtypeConversion, Error, VectorDtype};
+use tempfile::tempdir;
+
+#[test]
+fn test_float64_conversion() {
+    let round = VectorDtype::Float64.convert(&[0.1, -2.5], DtypeConversion::Round);
+    assert_eq!(round.unwrap(), vec![0.1f32, -2.5]);
+
+    // 0.1 has no exact f32, 0.5 does
+    let err = VectorDtype::Float64
+        .convert(&[0.5, 0.1], DtypeConversion::Exact)
+        .unwrap_err();
+    assert!(matches!(err, Error::UnrepresentableValue { index: 1, .. }));
+    assert_eq!(err.error_code(), 1008);
+    assert!(VectorDtype::Float64
+        .convert(&[0.5, 1e300], DtypeConversion::Round)
+        .is_err());
+}
+
+#[test]
+fn test_int8_conversion() {
+    let round = VectorDtype::Int8.convert(&[-128.0, 1.4, 126.6], DtypeConversion::Round);
+    assert_eq!(round.unwrap(), vec![-128.0, 1.0, 127.0]);
+    assert!(VectorDtype::Int8
+        .convert(&[1.4], DtypeConversion::Exact)
+        .is_err());
+    for out_of_range in [128.0, -129.0, f64::NAN] {
+        assert!(VectorDtype::Int8
+            .convert(&[out_of_range], DtypeConversion::Round)
+            .is_err());
+    }
+}
+
+#[test]
+fn test_collection_dtype_persisted() {
+    let dir = tempdir().unwrap();
+    let config = Config::builder(2)
+        .dtype(VectorDtype::Int8, DtypeConversion::Exact)
+        .build()
+        .unwrap();
+    {
+        let db = Database::open(dir.path()).unwrap();
+        db.create_collection("c", config).unwrap();
+        let collection = db.get_collection("c").unwrap();
+        let vector = collection.convert_vector(&[3.0, -4.0]).unwrap();
+        collection.insert("a".into(), &vector, None).unwrap();
+        assert!(collection.convert_vector(&[0.5, 1.0]).is_err());
+    }
+
+    let db = Database::open(dir.path()).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    assert_eq!(collection.dtype(), VectorDtype::Int8);
+    assert_eq!(collection.config().dtype_conversion, DtypeConversion::Exact);
+    assert_eq!(collection.get("a").unwrap().unwrap().0, vec![3.0, -4.0]);
+    assert!(collection.convert_vector(&[0.5, 1.0]).is_err());
+}
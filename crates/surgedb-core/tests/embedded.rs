@@ -0,0 +1,119 @@
+use serde_json::json;
+use surgedb_core::{
+    Config, Database, DistanceMetric, HnswConfig, IdType, IndexKind, QuantizationType, VectorDb,
+};
+use tempfile::tempdir;
+
+#[test]
+fn test_config_builder() {
+    let config = Config::builder(3)
+        .distance_metric(DistanceMetric::Euclidean)
+        .hnsw(HnswConfig::default().with_m(12))
+        .ef_search(80)
+        .id_type(IdType::U64)
+        .index(IndexKind::Flat)
+        .text_fields(["title", "body"])
+        .build()
+        .unwrap();
+    assert_eq!(config.dimensions, 3);
+    assert_eq!(config.hnsw.m, 12);
+    assert_eq!(config.hnsw.ef_search, 80);
+    assert_eq!(config.text_fields, vec!["title", "body"]);
+
+    assert!(Config::builder(0).build().is_err());
+    assert!(Config::builder(3).ef_search(0).build().is_err());
+    assert!(Config::builder(3)
+        .quantization(QuantizationType::SQ8)
+        .partition_field("tenant")
+        .build()
+        .is_err());
+}
+
+#[test]
+fn test_scan_yields_every_record_once() {
+    let config = Config::builder(2)
+        .distance_metric(DistanceMetric::Euclidean)
+        .build()
+        .unwrap();
+    let mut db = VectorDb::new(config).unwrap();
+    for i in 0..100 {
+        db.insert(format!("v{i}"), &[i as f32, 0.0], Some(json!({ "i": i })))
+            .unwrap();
+    }
+    db.delete("v10").unwrap();
+    db.upsert("v20", &[-1.0, 0.0], None).unwrap();
+
+    let mut records: Vec<_> = db.scan().batch_size(7).map(Result::unwrap).collect();
+    records.sort_by_key(|(_, vector, _)| vector[0] as i32);
+    assert_eq!(records.len(), 99);
+    assert_eq!(records[0].0.to_string(), "v20");
+    assert_eq!(records[0].2, None);
+    assert_eq!(
+        records[1],
+        ("v0".into(), vec![0.0, 0.0], Some(json!({ "i": 0 })))
+    );
+}
+
+#[test]
+fn test_compaction_ends_a_scan() {
+    let db = Database::new();
+    db.create_collection("c", Config::builder(2).build().unwrap())
+        .unwrap();
+    let collection = db.get_collection("c").unwrap();
+    for i in 0..20 {
+        collection
+            .insert(format!("v{i}"), &[i as f32, 1.0], None)
+            .unwrap();
+    }
+    collection.delete("v3").unwrap();
+
+    let mut scan = collection.scan().batch_size(10);
+    assert!(scan.next().unwrap().is_ok());
+    collection.compact().unwrap();
+    let rest: Vec<_> = scan.collect();
+    assert!(rest.last().unwrap().is_err());
+    assert_eq!(collection.scan().count(), 19);
+}
+
+#[test]
+fn test_embedded_database_persists() {
+    let dir = tempdir().unwrap();
+    {
+        let db = Database::open(dir.path()).unwrap();
+        let config = Config::builder(2)
+            .distance_metric(DistanceMetric::Euclidean)
+            .id_type(IdType::U64)
+            .build()
+            .unwrap();
+        db.create_collection("c", config).unwrap();
+        let collection = db.get_collection("c").unwrap();
+        for i in 0..50u64 {
+            collection
+                .insert(i.to_string(), &[i as f32, 1.0], None)
+                .unwrap();
+        }
+
+        // Writes during a scan don't disturb it
+        let mut seen = 0;
+        for record in collection.scan().batch_size(8) {
+            let (id, _, _) = record.unwrap();
+            if id.to_string() == "0" {
+                collection.delete("49").unwrap();
+                collection.insert("50".into(), &[50.0, 1.0], None).unwrap();
+            }
+            seen += 1;
+        }
+        assert_eq!(seen, 49);
+    }
+
+    let db = Database::open(dir.path()).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    let ids: Vec<String> = collection
+        .scan()
+        .map(|r| r.unwrap().0.to_string())
+        .collect();
+    assert_eq!(ids.len(), 50);
+    assert!(ids.contains(&"50".to_string()) && !ids.contains(&"49".to_string()));
+    let hits = collection.search(&[49.9, 1.0], 1, None).unwrap();
+    assert_eq!(hits[0].0.to_string(), "50");
+}
@@ -0,0 +1,122 @@
+use serde_json::json;
+use surgedb_core::filter::Filter;
+use surgedb_core::{
+    Config, DistanceMetric, PersistentConfig, PersistentVectorDb, QuantizationType,
+    QuantizedConfig, QuantizedVectorDb, VectorDb,
+};
+
+fn vector(i: usize) -> Vec<f32> {
+    vec![(i % 17) as f32, (i % 5) as f32, 1.0, (i / 50) as f32]
+}
+
+fn tenant_filter(tenant: &str) -> Filter {
+    Filter::Exact("tenant_id".to_string(), json!(tenant))
+}
+
+#[test]
+fn test_cached_filter_follows_writes() {
+    let mut db = VectorDb::new(Config {
+        dimensions: 4,
+        distance_metric: DistanceMetric::Euclidean,
+        ..Default::default()
+    })
+    .unwrap();
+    for i in 0..500 {
+        let tenant = if i % 50 == 0 { "small" } else { "big" };
+        db.insert(
+            format!("v{i}"),
+            &vector(i),
+            Some(json!({ "tenant_id": tenant, "n": i })),
+        )
+        .unwrap();
+    }
+
+    let info = db.cache_filter(tenant_filter("small")).unwrap();
+    assert_eq!(info.matches, 10);
+
+    db.insert("new", &vector(7), Some(json!({ "tenant_id": "small" })))
+        .unwrap();
+    db.delete("v0").unwrap();
+    db.upsert("v50", &vector(50), Some(json!({ "tenant_id": "big" })))
+        .unwrap();
+
+    let results = db
+        .search(&vector(7), 20, Some(&tenant_filter("small")))
+        .unwrap();
+    let mut ids: Vec<String> = results.iter().map(|(id, _, _)| id.to_string()).collect();
+    ids.sort();
+    let mut expected: Vec<String> = (2..10).map(|i| format!("v{}", i * 50)).collect();
+    expected.push("new".to_string());
+    expected.sort();
+    assert_eq!(ids, expected);
+
+    let cached = db.cached_filters();
+    assert_eq!(cached.len(), 1);
+    assert_eq!(cached[0].matches, 9);
+    assert_eq!(cached[0].hits, 1);
+
+    assert!(db.uncache_filter(&info.id));
+    assert!(db.cached_filters().is_empty());
+}
+
+#[test]
+fn test_cached_range_filter_on_quantized() {
+    let mut db = QuantizedVectorDb::new(QuantizedConfig {
+        dimensions: 4,
+        quantization: QuantizationType::SQ8,
+        ..Default::default()
+    })
+    .unwrap();
+    for i in 0..200 {
+        db.insert(format!("v{i}"), &vector(i), Some(json!({ "n": i })))
+            .unwrap();
+    }
+
+    let filter = Filter::Range {
+        field: "n".to_string(),
+        gt: None,
+        gte: Some(190.0),
+        lt: None,
+        lte: None,
+    };
+    assert_eq!(db.cache_filter(filter.clone()).unwrap().matches, 10);
+
+    let results = db.search(&vector(3), 20, Some(&filter)).unwrap();
+    assert_eq!(results.len(), 10);
+    for (_, _, meta) in results {
+        assert!(meta.unwrap()["n"].as_u64().unwrap() >= 190);
+    }
+    assert_eq!(db.cached_filters()[0].hits, 1);
+}
+
+#[test]
+fn test_cached_filter_used_by_persistent_search() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut db = PersistentVectorDb::open(
+        dir.path(),
+        PersistentConfig {
+            dimensions: 4,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    for i in 0..100 {
+        let tenant = if i % 10 == 0 { "small" } else { "big" };
+        db.insert(
+            format!("v{i}"),
+            &vector(i),
+            Some(json!({ "tenant_id": tenant })),
+        )
+        .unwrap();
+    }
+
+    db.cache_filter(tenant_filter("small")).unwrap();
+    let results = db
+        .search(&vector(3), 5, Some(&tenant_filter("small")))
+        .unwrap();
+    assert_eq!(results.len(), 5);
+    for (_, _, meta) in results {
+        assert_eq!(meta.unwrap()["tenant_id"], "small");
+    }
+    assert_eq!(db.cached_filters()[0].hits, 1);
+}
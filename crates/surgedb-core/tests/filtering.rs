@@ -1,6 +1,6 @@
 use serde_json::json;
 use surgedb_core::filter::Filter;
-use surgedb_core::{Config, VectorDb};
+use surgedb_core::{Config, Database, QuantizationType, VectorDb};
 
 #[test]
 fn test_metadata_filtering() {
@@ -79,3 +79,116 @@ fn test_complex_filtering() {
     assert_eq!(results.len(), 1);
     assert_eq!(results[0].0.as_str(), "v1");
 }
+
+#[test]
+fn test_query_syntax_filters_during_search() {
+    let config = Config {
+        dimensions: 2,
+        ..Default::default()
+    };
+    let mut db = VectorDb::new(config).unwrap();
+    for i in 0..200 {
+        let angle = i as f32 * 0.01;
+        db.insert(
+            format!("v{i}"),
+            &[angle.cos(), angle.sin()],
+            Some(json!({
+                "tag": if i % 2 == 0 { "A" } else { "B" },
+                "val": i,
+                "loc": { "lat": 48.0 + i as f64 * 0.01, "lon": 11.0 },
+            })),
+        )
+        .unwrap();
+    }
+
+    // Mixing clauses the metadata index can answer with ones it can't
+    let filter: Filter = serde_json::from_value(json!({
+        "tag": "A",
+        "val": { "$gte": 100, "$nin": [100, 102] },
+        "must_not": [{ "val": { "$gt": 150 } }]
+    }))
+    .unwrap();
+    let results = db.search(&[1.0, 0.0], 100, Some(&filter)).unwrap();
+    let mut vals: Vec<u64> = results
+        .iter()
+        .map(|(_, _, meta)| meta.as_ref().unwrap()["val"].as_u64().unwrap())
+        .collect();
+    vals.sort();
+    let expected: Vec<u64> = (104..=150).step_by(2).collect();
+    assert_eq!(vals, expected);
+
+    let filter: Filter = serde_json::from_value(json!({
+        "should": [{ "tag": "B" }, { "val": { "$lt": 4 } }],
+        "loc": { "$geo_radius": { "lat": 48.0, "lon": 11.0, "radius_meters": 5000.0 } }
+    }))
+    .unwrap();
+    let results = db.search(&[1.0, 0.0], 100, Some(&filter)).unwrap();
+    let mut ids: Vec<String> = results.iter().map(|r| r.0.to_string()).collect();
+    ids.sort();
+    // 0.01 degrees of latitude is about 1.1 km, so v0 to v4 are within 5 km
+    assert_eq!(ids, vec!["v0", "v1", "v2", "v3"]);
+}
+
+#[test]
+fn test_count_with_filter() {
+    let db = Database::new();
+    let standard = Config::builder(2).build().unwrap();
+    let quantized = Config {
+        quantization: QuantizationType::SQ8,
+        ..standard.clone()
+    };
+    db.create_collection("c", standard).unwrap();
+    db.create_collection("q", quantized).unwrap();
+
+    for name in ["c", "q"] {
+        let collection = db.get_collection(name).unwrap();
+        for i in 0..10 {
+            let tenant = if i % 3 == 0 { "acme" } else { "globex" };
+            collection
+                .insert(
+                    format!("v{i}"),
+                    &[i as f32, 1.0],
+                    Some(json!({ "tenant": tenant })),
+                )
+                .unwrap();
+        }
+        collection.insert("bare".into(), &[0.0, 1.0], None).unwrap();
+        collection.delete("v0").unwrap();
+
+        let acme = Filter::Exact("tenant".into(), json!("acme"));
+        assert_eq!(collection.count(None).unwrap(), 10, "{name}");
+        assert_eq!(collection.count(Some(&acme)).unwrap(), 3, "{name}");
+        // As in search, a record without metadata matches no filter
+        let not_acme = Filter::Not(Box::new(acme));
+        assert_eq!(collection.count(Some(&not_acme)).unwrap(), 6, "{name}");
+    }
+}
+
+#[test]
+#[cfg(feature = "expr")]
+fn test_failing_expression_fails_the_query() {
+    let db = Database::new();
+    db.create_collection("c", Config::builder(2).build().unwrap())
+        .unwrap();
+    let collection = db.get_collection("c").unwrap();
+    for i in 0..10 {
+        collection
+            .insert(
+                format!("v{i}"),
+                &[i as f32, 1.0],
+                Some(json!({ "price": i })),
+            )
+            .unwrap();
+    }
+
+    // Not a boolean; under `Not` it must not match everything
+    let failing = Filter::Not(Box::new(Filter::Expr("metadata.price".into())));
+    assert!(collection.delete_by_filter(&failing).is_err());
+    assert!(collection.search(&[0.0, 1.0], 5, Some(&failing)).is_err());
+    assert!(collection.count(Some(&failing)).is_err());
+    assert_eq!(collection.len(), 10);
+
+    let cheap = Filter::Expr("metadata.price < 3".into());
+    assert_eq!(collection.count(Some(&cheap)).unwrap(), 3);
+    assert_eq!(collection.delete_by_filter(&cheap).unwrap().len(), 3);
+}
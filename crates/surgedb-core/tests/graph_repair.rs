@@ -0,0 +1,47 @@
+mod common;
+
+use common::vector;
+use surgedb_core::{Config, Database, DistanceMetric};
+
+#[test]
+fn test_repair_relinks_around_deleted_nodes() {
+    let db = Database::new();
+    let config = Config::builder(8)
+        .distance_metric(DistanceMetric::Euclidean)
+        .build()
+        .unwrap();
+    db.create_collection("c", config).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    for i in 0..400 {
+        collection
+            .insert(i.to_string(), &vector(i, 8), None)
+            .unwrap();
+    }
+    assert_eq!(collection.repair_graph(100).backlog, 0);
+
+    for i in (0..400).step_by(2) {
+        collection.delete(&i.to_string()).unwrap();
+    }
+    let damaged = collection.repair_graph(0);
+    assert_eq!(damaged.relinked, 0);
+    assert!(damaged.backlog > 0);
+
+    // The budget bounds a pass
+    let first = collection.repair_graph(5);
+    assert_eq!(first.relinked, 5);
+    assert!(first.backlog <= damaged.backlog - 5);
+
+    let rest = collection.repair_graph(usize::MAX);
+    assert!(rest.relinked > 0 && rest.relinked <= first.backlog);
+    assert_eq!(rest.backlog, 0);
+
+    // Every live vector is still found through the relinked graph
+    let found = (1..400)
+        .step_by(2)
+        .filter(|&i| {
+            let hits = collection.search(&vector(i, 8), 1, None).unwrap();
+            hits[0].0.as_str() == i.to_string()
+        })
+        .count();
+    assert!(found >= 195, "found {} of 200", found);
+}
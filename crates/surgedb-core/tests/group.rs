@@ -0,0 +1,96 @@
+use serde_json::json;
+use surgedb_core::filter::Filter;
+use surgedb_core::{Config, Database, DistanceMetric, Error, GroupBy, SearchParams};
+
+fn group_by(groups: usize, group_size: usize) -> GroupBy {
+    GroupBy {
+        field: "doc.id".to_string(),
+        groups,
+        group_size,
+    }
+}
+
+fn keys(groups: &[surgedb_core::SearchGroup]) -> Vec<serde_json::Value> {
+    groups.iter().map(|g| g.key.clone()).collect()
+}
+
+#[test]
+fn test_grouped_search() {
+    let db = Database::new();
+    let config = Config::builder(2)
+        .distance_metric(DistanceMetric::Euclidean)
+        .build()
+        .unwrap();
+    db.create_collection("c", config).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    // Ten chunks per document, on a line so `v{i}` is `i` from the origin
+    for i in 0..100 {
+        collection
+            .insert(
+                format!("v{i}"),
+                &[i as f32, 0.0],
+                Some(json!({ "doc": { "id": i / 10 }, "even": i % 2 == 0 })),
+            )
+            .unwrap();
+    }
+    let params = SearchParams::default();
+
+    let (groups, usage) = collection
+        .search_grouped(&[0.0, 0.0], &group_by(3, 2), None, params)
+        .unwrap();
+    assert_eq!(keys(&groups), vec![json!(0), json!(1), json!(2)]);
+    let ids: Vec<String> = groups[1].hits.iter().map(|h| h.0.to_string()).collect();
+    assert_eq!(ids, vec!["v10", "v11"]);
+    assert!(usage.vectors_scanned > 0);
+
+    // The filter applies before grouping
+    let even = Filter::Exact("even".into(), json!(false));
+    let (groups, _) = collection
+        .search_grouped(&[0.0, 0.0], &group_by(1, 3), Some(&even), params)
+        .unwrap();
+    let ids: Vec<String> = groups[0].hits.iter().map(|h| h.0.to_string()).collect();
+    assert_eq!(ids, vec!["v1", "v3", "v5"]);
+
+    let err = collection
+        .search_grouped(&[0.0, 0.0], &group_by(0, 2), None, params)
+        .unwrap_err();
+    assert!(matches!(err, Error::InvalidConfig(_)));
+}
+
+#[test]
+fn test_grouped_search_widens_past_large_groups() {
+    let db = Database::new();
+    let config = Config::builder(2)
+        .distance_metric(DistanceMetric::Euclidean)
+        .build()
+        .unwrap();
+    db.create_collection("c", config).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    // The 50 nearest records share a document; the rest have one each
+    for i in 0..100 {
+        let doc = if i < 50 { json!("big") } else { json!(i) };
+        collection
+            .insert(
+                format!("v{i}"),
+                &[i as f32, 0.0],
+                Some(json!({ "doc": { "id": doc } })),
+            )
+            .unwrap();
+    }
+
+    let (groups, _) = collection
+        .search_grouped(&[0.0, 0.0], &group_by(3, 1), None, SearchParams::default())
+        .unwrap();
+    assert_eq!(keys(&groups), vec![json!("big"), json!(50), json!(51)]);
+
+    // Fewer groups than asked for once the records run out
+    let (groups, _) = collection
+        .search_grouped(
+            &[0.0, 0.0],
+            &group_by(100, 1),
+            None,
+            SearchParams::default(),
+        )
+        .unwrap();
+    assert_eq!(groups.len(), 51);
+}
@@ -0,0 +1,76 @@
+use surgedb_core::{
+    Config, Database, DistanceMetric, Error, HardNegativeQuery, HardNegatives, SearchParams,
+};
+
+fn ids(negatives: &[(surgedb_core::VectorId, f32)]) -> Vec<String> {
+    negatives.iter().map(|(id, _)| id.to_string()).collect()
+}
+
+#[test]
+fn test_hard_negatives() {
+    let db = Database::new();
+    let config = Config::builder(2)
+        .distance_metric(DistanceMetric::Euclidean)
+        .build()
+        .unwrap();
+    db.create_collection("c", config).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    // On a line, so `v{i}` is `i` from the origin
+    for i in 0..100 {
+        collection
+            .insert(format!("v{i}"), &[i as f32, 0.0], None)
+            .unwrap();
+    }
+    let queries = vec![
+        HardNegativeQuery {
+            vector: vec![0.0, 0.0],
+            positive_id: "v1".to_string(),
+        },
+        HardNegativeQuery {
+            vector: vec![50.2, 0.0],
+            positive_id: "v50".to_string(),
+        },
+    ];
+    let options = HardNegatives {
+        k: 3,
+        skip: 0,
+        margin: None,
+    };
+
+    let (mined, _) = collection
+        .hard_negatives(&queries, &options, None, SearchParams::default())
+        .unwrap();
+    assert_eq!(mined.len(), 2);
+    assert_eq!(mined[0].positive_id, "v1");
+    assert_eq!(mined[0].positive_distance, 1.0);
+    assert_eq!(ids(&mined[0].negatives), ["v0", "v2", "v3"]);
+    assert_eq!(ids(&mined[1].negatives), ["v51", "v49", "v52"]);
+
+    // Passing over likely false negatives
+    let skipping = HardNegatives { skip: 2, ..options };
+    let (mined, _) = collection
+        .hard_negatives(&queries, &skipping, None, SearchParams::default())
+        .unwrap();
+    assert_eq!(ids(&mined[0].negatives), ["v3", "v4", "v5"]);
+
+    // Far past the first search, the search widens
+    let far = HardNegatives {
+        k: 3,
+        skip: 0,
+        margin: Some(0.45),
+    };
+    let (mined, _) = collection
+        .hard_negatives(&queries[..1], &far, None, SearchParams::default())
+        .unwrap();
+    // Scores are 1 / (1 + distance): the positive scores 0.5
+    assert_eq!(ids(&mined[0].negatives), ["v19", "v20", "v21"]);
+
+    let missing = [HardNegativeQuery {
+        vector: vec![0.0, 0.0],
+        positive_id: "nope".to_string(),
+    }];
+    assert!(matches!(
+        collection.hard_negatives(&missing, &options, None, SearchParams::default()),
+        Err(Error::VectorNotFound(id)) if id == "nope"
+    ));
+}
@@ -0,0 +1,227 @@
+mod common;
+
+use common::vector;
+use serde_json::json;
+use surgedb_core::filter::Filter;
+use surgedb_core::{
+    Config, Database, Fusion, PersistentConfig, PersistentVectorDb, QuantizationType, SearchParams,
+    SparseVector,
+};
+use tempfile::tempdir;
+
+const DIMS: usize = 8;
+
+/// Term `i % 10` with weight 1, plus a rare term only record 42 has
+fn sparse(i: usize) -> SparseVector {
+    let mut pairs = vec![((i % 10) as u32, 1.0)];
+    if i == 42 {
+        pairs.push((1000, 5.0));
+    }
+    SparseVector::new(pairs)
+}
+
+fn rare_term() -> SparseVector {
+    SparseVector::new(vec![(1000, 1.0)])
+}
+
+fn ids(hits: &[surgedb_core::HybridHit]) -> Vec<String> {
+    hits.iter().map(|h| h.id.to_string()).collect()
+}
+
+fn collection_with_sparse(db: &Database) -> surgedb_core::db::Collection {
+    db.create_collection(
+        "docs",
+        Config {
+            dimensions: DIMS,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let collection = db.get_collection("docs").unwrap();
+    for i in 0..100 {
+        collection
+            .insert(
+                format!("v{i}"),
+                &vector(i, DIMS),
+                Some(json!({ "group": i % 2 })),
+            )
+            .unwrap();
+        assert!(collection.set_sparse(&format!("v{i}"), sparse(i)).unwrap());
+    }
+    collection
+}
+
+#[test]
+fn test_hybrid_search_fuses_dense_and_sparse() {
+    let db = Database::new();
+    let collection = collection_with_sparse(&db);
+    let params = SearchParams::default();
+
+    // The dense query is record 7, the sparse query only matches record 42
+    let (hits, _) = collection
+        .search_hybrid(
+            &vector(7, DIMS),
+            &rare_term(),
+            5,
+            None,
+            Fusion::default(),
+            params,
+        )
+        .unwrap();
+    assert_eq!(hits.len(), 5);
+    let found = ids(&hits);
+    assert!(found.contains(&"v7".to_string()));
+    assert!(found.contains(&"v42".to_string()));
+    let v42 = hits.iter().find(|h| h.id.as_str() == "v42").unwrap();
+    assert_eq!(v42.sparse_score, Some(5.0));
+    assert!(hits.windows(2).all(|w| w[0].score >= w[1].score));
+    // Both lead one ranking, so they tie for first place
+    let mut top = found[..2].to_vec();
+    top.sort();
+    assert_eq!(top, vec!["v42", "v7"]);
+
+    // alpha 1 is the dense ranking, alpha 0 the sparse one
+    let (dense_only, _) = collection
+        .search_with_params(&vector(7, DIMS), 3, None, params)
+        .unwrap();
+    let (hits, _) = collection
+        .search_hybrid(
+            &vector(7, DIMS),
+            &rare_term(),
+            3,
+            None,
+            Fusion::WeightedSum { alpha: 1.0 },
+            params,
+        )
+        .unwrap();
+    let dense_ids: Vec<String> = dense_only.iter().map(|h| h.0.to_string()).collect();
+    assert_eq!(ids(&hits), dense_ids);
+    let (hits, _) = collection
+        .search_hybrid(
+            &vector(7, DIMS),
+            &rare_term(),
+            1,
+            None,
+            Fusion::WeightedSum { alpha: 0.0 },
+            params,
+        )
+        .unwrap();
+    assert_eq!(ids(&hits), vec!["v42"]);
+
+    // Filters apply to both rankings
+    let odd = Filter::Exact("group".into(), json!(1));
+    let (hits, _) = collection
+        .search_hybrid(
+            &vector(7, DIMS),
+            &rare_term(),
+            5,
+            Some(&odd),
+            Fusion::default(),
+            params,
+        )
+        .unwrap();
+    assert!(!ids(&hits).contains(&"v42".to_string()));
+    assert!(hits
+        .iter()
+        .all(|h| h.metadata == Some(json!({ "group": 1 }))));
+
+    // Overwriting or deleting a record drops its sparse vector
+    collection
+        .upsert("v42".into(), &vector(42, DIMS), None)
+        .unwrap();
+    assert!(collection.get_sparse("v42").is_none());
+    assert_eq!(collection.get_sparse("v3"), Some(sparse(3)));
+    collection.delete("v3").unwrap();
+    assert!(collection.get_sparse("v3").is_none());
+    let (hits, _) = collection
+        .search_hybrid(
+            &vector(7, DIMS),
+            &rare_term(),
+            1,
+            None,
+            Fusion::WeightedSum { alpha: 0.0 },
+            params,
+        )
+        .unwrap();
+    assert_ne!(ids(&hits), vec!["v42"]);
+
+    assert!(!collection.set_sparse("missing", sparse(1)).unwrap());
+    let unsorted = SparseVector {
+        indices: vec![3, 1],
+        values: vec![1.0, 1.0],
+    };
+    assert!(collection.set_sparse("v1", unsorted).is_err());
+}
+
+#[test]
+fn test_quantized_collection_rejects_sparse() {
+    let db = Database::new();
+    db.create_collection(
+        "q",
+        Config {
+            dimensions: DIMS,
+            quantization: QuantizationType::SQ8,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let collection = db.get_collection("q").unwrap();
+    collection
+        .insert("a".into(), &vector(1, DIMS), None)
+        .unwrap();
+    assert!(collection.set_sparse("a", sparse(1)).is_err());
+}
+
+#[test]
+fn test_persistent_sparse_vectors_survive_reopen() {
+    let dir = tempdir().unwrap();
+    let config = PersistentConfig {
+        dimensions: DIMS,
+        ..Default::default()
+    };
+    {
+        let mut db = PersistentVectorDb::open(dir.path(), config.clone()).unwrap();
+        for i in 0..50 {
+            db.insert(format!("v{i}"), &vector(i, DIMS), None).unwrap();
+        }
+        for i in 0..25 {
+            db.set_sparse(format!("v{i}"), sparse(i)).unwrap();
+        }
+        db.checkpoint().unwrap();
+        // Logged after the snapshot, so recovered from the WAL
+        for i in 25..50 {
+            db.set_sparse(format!("v{i}"), sparse(i)).unwrap();
+        }
+        db.delete("v3").unwrap();
+    }
+
+    let db = PersistentVectorDb::open(dir.path(), config).unwrap();
+    assert_eq!(db.get_sparse("v4"), Some(sparse(4)));
+    assert_eq!(db.get_sparse("v42"), Some(sparse(42)));
+    assert!(db.get_sparse("v3").is_none());
+    let (hits, _) = db
+        .search_hybrid(
+            &vector(0, DIMS),
+            &rare_term(),
+            1,
+            None,
+            Fusion::WeightedSum { alpha: 0.0 },
+            SearchParams::default(),
+        )
+        .unwrap();
+    assert_eq!(ids(&hits), vec!["v42"]);
+}
+
+#[test]
+fn test_collection_snapshot_keeps_sparse_vectors() {
+    let dir = tempdir().unwrap();
+    let db = Database::open(dir.path().join("db")).unwrap();
+    let collection = collection_with_sparse(&db);
+    let file = dir.path().join("docs.snap");
+    collection.snapshot(&file).unwrap();
+
+    db.restore("copy", &file).unwrap();
+    let copy = db.get_collection("copy").unwrap();
+    assert_eq!(copy.get_sparse("v42"), Some(sparse(42)));
+    assert_eq!(copy.stats().vector_count, 100);
+}
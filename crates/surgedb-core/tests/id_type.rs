@@ -0,0 +1,29 @@
+use surgedb_core::{IdType, PersistentConfig, PersistentVectorDb};
+use tempfile::tempdir;
+
+#[test]
+fn test_u64_ids_survive_reopen() {
+    let dir = tempdir().unwrap();
+    let config = PersistentConfig {
+        dimensions: 4,
+        id_type: IdType::U64,
+        ..Default::default()
+    };
+
+    {
+        let mut db = PersistentVectorDb::open(dir.path(), config.clone()).unwrap();
+        db.insert("1", &[1.0, 0.0, 0.0, 0.0], None).unwrap();
+        db.insert("2", &[0.0, 1.0, 0.0, 0.0], None).unwrap();
+        db.checkpoint().unwrap();
+        db.insert("3", &[0.0, 0.0, 1.0, 0.0], None).unwrap();
+        db.delete("2").unwrap();
+    }
+
+    let db = PersistentVectorDb::open(dir.path(), config).unwrap();
+    assert_eq!(db.len(), 2);
+    assert!(db.get("1").unwrap().is_some());
+    assert!(db.get("2").unwrap().is_none());
+
+    let results = db.search(&[0.0, 0.0, 1.0, 0.0], 1, None).unwrap();
+    assert_eq!(results[0].0.as_u64(), Some(3));
+}
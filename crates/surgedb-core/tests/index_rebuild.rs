@@ -0,0 +1,96 @@
+use serde_json::json;
+use surgedb_core::filter::Filter;
+use surgedb_core::{Config, Database, DistanceMetric, Error, QuantizationType};
+
+fn colour(colour: &str) -> Filter {
+    Filter::Exact("colour".to_string(), json!(colour))
+}
+
+fn config() -> surgedb_core::ConfigBuilder {
+    Config::builder(2).distance_metric(DistanceMetric::Euclidean)
+}
+
+#[test]
+fn test_rebuild_keeps_answering_filters_and_taking_writes() {
+    let db = Database::new();
+    db.create_collection("c", config().build().unwrap())
+        .unwrap();
+    let collection = db.get_collection("c").unwrap();
+    collection
+        .upsert_batch(
+            (0..5_000)
+                .map(|i| {
+                    let colour = if i % 4 == 0 { "red" } else { "blue" };
+                    (
+                        format!("v{}", i),
+                        vec![i as f32, 0.0],
+                        Some(json!({ "colour": colour, "n": i })),
+                    )
+                })
+                .collect(),
+        )
+        .unwrap();
+
+    let mut reports = Vec::new();
+    collection
+        .rebuild_field_index("colour", |done, total| {
+            if reports.len() == 1 {
+                // Writes go on between chunks
+                collection
+                    .upsert(
+                        "v1".to_string(),
+                        &[1.0, 0.0],
+                        Some(json!({ "colour": "red" })),
+                    )
+                    .unwrap();
+                collection
+                    .insert(
+                        "new".to_string(),
+                        &[0.5, 0.0],
+                        Some(json!({ "colour": "red" })),
+                    )
+                    .unwrap();
+                assert!(matches!(
+                    collection.rebuild_field_index("colour", |_, _| {}),
+                    Err(Error::CollectionBusy(_))
+                ));
+            }
+            // Filters on the field scan the payloads meanwhile
+            let expected = if reports.is_empty() { 1250 } else { 1252 };
+            assert_eq!(collection.count(Some(&colour("red"))).unwrap(), expected);
+            reports.push((done, total));
+        })
+        .unwrap();
+    assert_eq!(reports, [(0, 5000), (4096, 5000), (5000, 5000)]);
+
+    assert_eq!(collection.count(Some(&colour("red"))).unwrap(), 1252);
+    assert_eq!(collection.count(Some(&colour("blue"))).unwrap(), 3749);
+    let hits = collection
+        .search(&[1.0, 0.0], 2, Some(&colour("red")))
+        .unwrap();
+    let ids: Vec<_> = hits.iter().map(|(id, _, _)| id.to_string()).collect();
+    assert_eq!(ids, ["v1", "new"]);
+
+    // Fields that were never indexed can be rebuilt too, and rebuilds repeat
+    collection
+        .rebuild_field_index("missing", |_, _| {})
+        .unwrap();
+    collection.rebuild_field_index("colour", |_, _| {}).unwrap();
+    assert_eq!(collection.count(Some(&colour("red"))).unwrap(), 1252);
+}
+
+#[test]
+fn test_quantized_collections_have_no_index_to_rebuild() {
+    let db = Database::new();
+    let config = config()
+        .quantization(QuantizationType::SQ8)
+        .build()
+        .unwrap();
+    db.create_collection("q", config).unwrap();
+    let collection = db.get_collection("q").unwrap();
+    assert!(!collection.has_metadata_index());
+    assert!(matches!(
+        collection.rebuild_field_index("colour", |_, _| {}),
+        Err(Error::InvalidConfig(_))
+    ));
+}
@@ -0,0 +1,181 @@
+mod common;
+
+use common::{for_each_backend, vector};
+use serde_json::json;
+use std::collections::HashSet;
+use surgedb_core::filter::Filter;
+use surgedb_core::{Config, Database, IndexKind, ListPage, MAX_SCROLL_SCANNED};
+use tempfile::tempdir;
+
+const DIMS: usize = 4;
+
+fn ids(page: &ListPage) -> Vec<String> {
+    page.records.iter().map(|(id, _)| id.to_string()).collect()
+}
+
+fn check_listing_under_writes(db: &Database, config: Config) {
+    db.create_collection("c", config).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    for i in 0..100 {
+        collection
+            .insert(format!("v{i}"), &vector(i, DIMS), Some(json!({ "v": 0 })))
+            .unwrap();
+    }
+
+    let first = collection.list_page(None, 30).unwrap();
+    assert_eq!(first.records.len(), 30);
+    let mut seen: Vec<String> = ids(&first);
+    let mut cursor = first.next;
+
+    // Overwrite listed and unlisted records, delete unlisted ones, add new ones
+    for i in (0..100).step_by(7) {
+        collection
+            .upsert(format!("v{i}"), &vector(i, DIMS), Some(json!({ "v": 1 })))
+            .unwrap();
+    }
+    for i in [50, 51, 52] {
+        collection.delete(&format!("v{i}")).unwrap();
+    }
+    collection
+        .insert("new".to_string(), &vector(500, DIMS), None)
+        .unwrap();
+
+    while let Some(c) = cursor {
+        let page = collection.list_page(Some(c), 30).unwrap();
+        assert!(page.records.len() <= 30);
+        // Rewritten records show their current metadata
+        assert!(page
+            .records
+            .iter()
+            .all(
+                |(id, meta)| id.as_str()[1..].parse::<usize>().unwrap() % 7 != 0
+                    || meta == &Some(json!({ "v": 1 }))
+            ));
+        seen.extend(ids(&page));
+        cursor = page.next;
+    }
+
+    let unique: HashSet<&String> = seen.iter().collect();
+    assert_eq!(unique.len(), seen.len(), "a record was listed twice");
+    let expected: HashSet<String> = (0..100)
+        .filter(|i| ![50, 51, 52].contains(i))
+        .map(|i| format!("v{i}"))
+        .collect();
+    assert_eq!(
+        unique.into_iter().cloned().collect::<HashSet<_>>(),
+        expected
+    );
+
+    // The last page was already handed out; a fresh listing sees the new record
+    let all = collection.list_page(None, 1000).unwrap();
+    assert_eq!(all.records.len(), 98);
+    assert!(all.next.is_none());
+    assert!(ids(&all).contains(&"new".to_string()));
+}
+
+#[test]
+fn test_cursor_listing_in_memory() {
+    for_each_backend(DIMS, check_listing_under_writes);
+}
+
+#[test]
+fn test_cursor_listing_persistent_expires_on_reopen() {
+    let dir = tempdir().unwrap();
+    let config = Config {
+        dimensions: DIMS,
+        ..Default::default()
+    };
+    let cursor = {
+        let db = Database::open(dir.path()).unwrap();
+        check_listing_under_writes(&db, config.clone());
+        let collection = db.get_collection("c").unwrap();
+        let page = collection.list_page(None, 10).unwrap();
+
+        // A cursor is tied to the collection that issued it
+        db.create_collection("other", config).unwrap();
+        let other = db.get_collection("other").unwrap();
+        other
+            .insert("a".to_string(), &vector(1, DIMS), None)
+            .unwrap();
+        assert!(other.list_page(page.next, 10).is_err());
+        page.next.unwrap()
+    };
+
+    let db = Database::open(dir.path()).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    assert!(collection.list_page(Some(cursor), 10).is_err());
+    assert_eq!(collection.list_page(None, 1000).unwrap().records.len(), 98);
+}
+
+#[test]
+fn test_scroll_returns_full_records() {
+    let db = Database::new();
+    db.create_collection(
+        "c",
+        Config {
+            dimensions: DIMS,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let collection = db.get_collection("c").unwrap();
+    for i in 0..100 {
+        collection
+            .insert(
+                format!("v{i}"),
+                &vector(i, DIMS),
+                Some(json!({ "odd": i % 2 == 1 })),
+            )
+            .unwrap();
+    }
+
+    let odd = Filter::Exact("odd".into(), json!(true));
+    let mut seen = HashSet::new();
+    let mut cursor = None;
+    loop {
+        let page = collection.scroll(cursor, 7, Some(&odd)).unwrap();
+        for (id, stored, metadata) in &page.records {
+            let i: usize = id.as_str()[1..].parse().unwrap();
+            assert_eq!(stored, &collection.get(&id.to_string()).unwrap().unwrap().0);
+            assert_eq!(metadata, &Some(json!({ "odd": true })));
+            assert!(i % 2 == 1 && seen.insert(i), "v{i} listed wrongly");
+        }
+        cursor = page.next;
+        if cursor.is_none() {
+            break;
+        }
+        assert_eq!(page.records.len(), 7);
+    }
+    assert_eq!(seen.len(), 50);
+
+    let all = collection.scroll(None, 1000, None).unwrap();
+    assert_eq!(all.records.len(), 100);
+    assert!(all.next.is_none());
+}
+
+#[test]
+fn test_scroll_bounds_filtered_work() {
+    let db = Database::new();
+    db.create_collection(
+        "c",
+        Config {
+            dimensions: DIMS,
+            index: IndexKind::Flat,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let collection = db.get_collection("c").unwrap();
+    let items = (0..=MAX_SCROLL_SCANNED)
+        .map(|i| (format!("v{i}"), vector(i, DIMS), Some(json!({ "i": i }))))
+        .collect();
+    collection.upsert_batch(items).unwrap();
+
+    // Only the last record matches, past what one page examines
+    let last = Filter::Exact("i".into(), json!(MAX_SCROLL_SCANNED));
+    let first = collection.scroll(None, 10, Some(&last)).unwrap();
+    assert!(first.records.is_empty());
+    let second = collection.scroll(first.next, 10, Some(&last)).unwrap();
+    assert_eq!(second.records.len(), 1);
+    assert!(second.next.is_none());
+}
@@ -0,0 +1,83 @@
+use surgedb_core::{Config, Database, DistanceMetric, IndexKind, QuantizationType, SearchParams};
+use tempfile::tempdir;
+
+fn config() -> Config {
+    Config::builder(2)
+        .distance_metric(DistanceMetric::Euclidean)
+        .build()
+        .unwrap()
+}
+
+/// Vectors on a line, so `v{i}` is `i` away from the origin
+fn fill(db: &Database, name: &str) {
+    let collection = db.get_collection(name).unwrap();
+    for i in 0..500 {
+        collection
+            .insert(format!("v{i}"), &[i as f32, 0.0], None)
+            .unwrap();
+    }
+}
+
+fn check_cutoff(db: &Database, name: &str) {
+    let collection = db.get_collection(name).unwrap();
+    let params = SearchParams {
+        max_distance: Some(10.5),
+        ..Default::default()
+    };
+    let (hits, _) = collection
+        .search_with_params(&[0.0, 0.0], 50, None, params)
+        .unwrap();
+    assert_eq!(hits.len(), 11, "{name}");
+    assert!(hits.iter().all(|(_, distance, _)| *distance <= 10.5));
+    assert_eq!(hits[0].0.to_string(), "v0");
+}
+
+#[test]
+fn test_max_distance_cuts_results() {
+    let db = Database::new();
+    db.create_collection("hnsw", config()).unwrap();
+    let flat = Config {
+        index: IndexKind::Flat,
+        ..config()
+    };
+    db.create_collection("flat", flat).unwrap();
+    let quantized = Config {
+        quantization: QuantizationType::SQ8,
+        ..config()
+    };
+    db.create_collection("sq8", quantized).unwrap();
+    for name in ["hnsw", "flat", "sq8"] {
+        fill(&db, name);
+        check_cutoff(&db, name);
+    }
+
+    let dir = tempdir().unwrap();
+    let db = Database::open(dir.path()).unwrap();
+    db.create_collection("persistent", config()).unwrap();
+    fill(&db, "persistent");
+    check_cutoff(&db, "persistent");
+}
+
+#[test]
+fn test_max_distance_ends_traversal() {
+    let db = Database::new();
+    db.create_collection("c", config()).unwrap();
+    fill(&db, "c");
+    let collection = db.get_collection("c").unwrap();
+
+    let search = |max_distance| {
+        let params = SearchParams {
+            ef_search: Some(400),
+            max_distance,
+            ..Default::default()
+        };
+        collection
+            .search_with_params(&[0.0, 0.0], 400, None, params)
+            .unwrap()
+    };
+    let (all, full) = search(None);
+    let (near, cut) = search(Some(5.0));
+    assert_eq!(all.len(), 400);
+    assert_eq!(near.len(), 6);
+    assert!(cut.vectors_scanned < full.vectors_scanned / 4);
+}
@@ -0,0 +1,47 @@
+#![cfg(feature = "compression")]
+
+use serde_json::json;
+use surgedb_core::filter::Filter;
+use surgedb_core::{MetadataCompression, PersistentConfig, PersistentVectorDb};
+use tempfile::tempdir;
+
+#[test]
+fn test_compressed_metadata_survives_reopen() {
+    let dir = tempdir().unwrap();
+    let config = PersistentConfig {
+        dimensions: 4,
+        metadata_compression: MetadataCompression::Zstd,
+        ..Default::default()
+    };
+    let metadata = |i: usize| {
+        json!({
+            "kind": if i.is_multiple_of(2) { "even" } else { "odd" },
+            "description": format!("Record {i} with a fairly repetitive description"),
+        })
+    };
+
+    {
+        let mut db = PersistentVectorDb::open(dir.path(), config.clone()).unwrap();
+        for i in 0..600 {
+            let vector = [i as f32, 1.0, (i % 7) as f32, 0.5];
+            db.insert(format!("v{i}"), &vector, Some(metadata(i)))
+                .unwrap();
+        }
+        db.checkpoint().unwrap();
+        db.delete("v1").unwrap();
+
+        let ratio = db.memory_breakdown().metadata_compression_ratio.unwrap();
+        assert!(ratio > 1.0, "compression ratio too low: {ratio}");
+    }
+
+    let db = PersistentVectorDb::open(dir.path(), config).unwrap();
+    assert_eq!(db.get("v42").unwrap().unwrap().1, Some(metadata(42)));
+    assert!(db.get("v1").unwrap().is_none());
+
+    let filter = Filter::Exact("kind".to_string(), json!("odd"));
+    let results = db.search(&[3.0, 1.0, 3.0, 0.5], 5, Some(&filter)).unwrap();
+    assert!(!results.is_empty());
+    for (_, _, meta) in results {
+        assert_eq!(meta.unwrap()["kind"], "odd");
+    }
+}
@@ -0,0 +1,123 @@
+use serde_json::{json, Value};
+use surgedb_core::{Config, Database, Error, MetadataLimits, QuantizationType};
+use tempfile::tempdir;
+
+fn config() -> Config {
+    Config::builder(2)
+        .metadata_limits(MetadataLimits {
+            max_bytes: Some(64),
+            max_depth: Some(2),
+        })
+        .build()
+        .unwrap()
+}
+
+fn nested(depth: usize) -> Value {
+    (0..depth).fold(json!(1), |inner, _| json!({ "a": inner }))
+}
+
+fn check_limits(db: &Database, name: &str) {
+    let collection = db.get_collection(name).unwrap();
+    collection
+        .insert("ok".into(), &[1.0, 0.0], Some(nested(2)))
+        .unwrap();
+
+    let err = collection
+        .insert("deep".into(), &[1.0, 0.0], Some(nested(3)))
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        Error::MetadataLimitExceeded {
+            limit: "max_depth",
+            actual: 3,
+            max: 2,
+            ..
+        }
+    ));
+    assert_eq!(err.error_code(), 1006);
+
+    let big = json!({ "text": "x".repeat(100) });
+    let err = collection
+        .upsert("ok".into(), &[0.0, 1.0], Some(big.clone()))
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        Error::MetadataLimitExceeded {
+            limit: "max_bytes",
+            actual: 111,
+            max: 64,
+            ..
+        }
+    ));
+
+    // One oversized item fails the whole batch
+    let batch = vec![
+        ("b1".to_string(), vec![1.0, 1.0], None),
+        ("b2".to_string(), vec![1.0, 1.0], Some(big)),
+    ];
+    assert!(collection.upsert_batch(batch).is_err());
+    assert!(collection.get("b1").unwrap().is_none());
+
+    // Merged metadata is checked, not just the patch
+    collection
+        .update_metadata("ok", json!({ "b": "y".repeat(30) }), true)
+        .unwrap();
+    assert!(collection
+        .update_metadata("ok", json!({ "c": "z".repeat(30) }), true)
+        .is_err());
+    assert_eq!(collection.stats().vector_count, 1);
+}
+
+#[test]
+fn test_limits_reject_writes() {
+    let db = Database::new();
+    db.create_collection("c", config()).unwrap();
+    check_limits(&db, "c");
+
+    let quantized = Config {
+        quantization: QuantizationType::SQ8,
+        ..config()
+    };
+    db.create_collection("q", quantized).unwrap();
+    check_limits(&db, "q");
+}
+
+#[test]
+fn test_limits_persist() {
+    let dir = tempdir().unwrap();
+    {
+        let db = Database::open(dir.path()).unwrap();
+        db.create_collection("c", config()).unwrap();
+        check_limits(&db, "c");
+    }
+
+    // Rejected writes never reached the WAL, and the limits still apply
+    let db = Database::open(dir.path()).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    assert_eq!(collection.stats().vector_count, 1);
+    assert_eq!(collection.config().metadata_limits.max_depth, Some(2));
+    assert!(collection
+        .insert("deep".into(), &[1.0, 0.0], Some(nested(3)))
+        .is_err());
+}
+
+#[test]
+fn test_largest_payloads_in_stats() {
+    let db = Database::new();
+    db.create_collection("c", Config::builder(2).build().unwrap())
+        .unwrap();
+    let collection = db.get_collection("c").unwrap();
+    for i in 0..10 {
+        let metadata = json!({ "text": "x".repeat(i * 10) });
+        collection
+            .insert(format!("v{i}"), &[i as f32, 1.0], Some(metadata))
+            .unwrap();
+    }
+    collection.insert("none".into(), &[0.0, 0.0], None).unwrap();
+    collection.delete("v9").unwrap();
+
+    let largest = collection.stats().largest_payloads;
+    let ids: Vec<String> = largest.iter().map(|p| p.id.to_string()).collect();
+    assert_eq!(ids, vec!["v8", "v7", "v6", "v5", "v4"]);
+    assert_eq!(largest[0].bytes, r#"{"text":""}"#.len() + 80);
+}
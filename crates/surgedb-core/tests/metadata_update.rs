@@ -0,0 +1,198 @@
+mod common;
+
+use common::{for_each_backend, ids, vector};
+use serde_json::json;
+use surgedb_core::filter::Filter;
+use surgedb_core::{Config, Database};
+use tempfile::tempdir;
+
+const DIMS: usize = 4;
+
+fn fill(db: &Database, name: &str, config: Config) {
+    db.create_collection(name, config).unwrap();
+    let collection = db.get_collection(name).unwrap();
+    for i in 0..20 {
+        let tenant = if i % 2 == 0 { "a" } else { "b" };
+        collection
+            .insert(
+                format!("v{i}"),
+                &vector(i, DIMS),
+                Some(json!({ "tenant": tenant, "n": i, "tags": { "x": 1 } })),
+            )
+            .unwrap();
+    }
+}
+
+fn check_updates(db: &Database) {
+    let collection = db.get_collection("c").unwrap();
+    let red = Filter::Exact("color".into(), json!("red"));
+    collection.cache_filter(red.clone()).unwrap();
+
+    assert!(collection
+        .update_metadata(
+            "v3",
+            json!({ "n": null, "color": "red", "tags": { "y": 2 } }),
+            true
+        )
+        .unwrap());
+    let (_, meta) = collection.get("v3").unwrap().unwrap();
+    assert_eq!(
+        meta,
+        Some(json!({ "tenant": "b", "color": "red", "tags": { "x": 1, "y": 2 } }))
+    );
+
+    // Filters, cached or not, see the new metadata
+    let hits = collection
+        .search(&vector(10, DIMS), 20, Some(&red))
+        .unwrap();
+    assert_eq!(ids(&hits), vec!["v3"]);
+    let three = Filter::Exact("n".into(), json!(3));
+    assert!(collection
+        .search(&vector(3, DIMS), 5, Some(&three))
+        .unwrap()
+        .is_empty());
+
+    // Replacing drops the old fields, null clears the metadata
+    collection
+        .update_metadata("v5", json!({ "color": "red" }), false)
+        .unwrap();
+    assert_eq!(
+        collection.get("v5").unwrap().unwrap().1,
+        Some(json!({ "color": "red" }))
+    );
+    collection
+        .update_metadata("v3", json!(null), false)
+        .unwrap();
+    assert_eq!(collection.get("v3").unwrap().unwrap().1, None);
+    let hits = collection
+        .search(&vector(10, DIMS), 20, Some(&red))
+        .unwrap();
+    assert_eq!(ids(&hits), vec!["v5"]);
+
+    // The vectors keep their slots
+    assert_eq!(collection.stats().deleted_count, 0);
+    assert_eq!(collection.get("v5").unwrap().unwrap().0.len(), DIMS);
+    assert!(!collection
+        .update_metadata("missing", json!({ "a": 1 }), true)
+        .unwrap());
+}
+
+#[test]
+fn test_update_metadata_in_memory() {
+    for_each_backend(DIMS, |db, config| {
+        fill(db, "c", config);
+        check_updates(db);
+    });
+}
+
+#[test]
+fn test_update_metadata_reindexes_text() {
+    let db = Database::new();
+    fill(
+        &db,
+        "c",
+        Config {
+            dimensions: DIMS,
+            text_fields: vec!["title".into()],
+            ..Default::default()
+        },
+    );
+    let collection = db.get_collection("c").unwrap();
+    collection
+        .update_metadata("v1", json!({ "title": "Cast iron cooking" }), true)
+        .unwrap();
+    let (hits, _) = collection.search_text("cooking", 10, None).unwrap();
+    assert_eq!(ids(&hits), vec!["v1"]);
+
+    collection
+        .update_metadata("v1", json!({ "title": "Gardening" }), true)
+        .unwrap();
+    assert!(collection
+        .search_text("cooking", 10, None)
+        .unwrap()
+        .0
+        .is_empty());
+}
+
+#[test]
+fn test_update_metadata_moves_partition() {
+    let db = Database::new();
+    fill(
+        &db,
+        "c",
+        Config {
+            dimensions: DIMS,
+            partition_field: Some("tenant".into()),
+            ..Default::default()
+        },
+    );
+    let collection = db.get_collection("c").unwrap();
+
+    // Other fields change in place
+    collection
+        .update_metadata("v2", json!({ "n": 100 }), true)
+        .unwrap();
+    assert_eq!(collection.stats().deleted_count, 0);
+
+    // A new partition value re-links the record into that tenant's graph
+    collection
+        .update_metadata("v2", json!({ "tenant": "b" }), true)
+        .unwrap();
+    assert_eq!(collection.stats().deleted_count, 1);
+    let tenant_b = Filter::Exact("tenant".into(), json!("b"));
+    let hits = collection
+        .search(&vector(2, DIMS), 1, Some(&tenant_b))
+        .unwrap();
+    assert_eq!(ids(&hits), vec!["v2"]);
+    let tenant_a = Filter::Exact("tenant".into(), json!("a"));
+    let hits = collection
+        .search(&vector(2, DIMS), 20, Some(&tenant_a))
+        .unwrap();
+    assert_eq!(hits.len(), 9);
+    assert!(!ids(&hits).contains(&"v2".to_string()));
+}
+
+#[test]
+fn test_update_metadata_persistent_survives_reopen() {
+    let dir = tempdir().unwrap();
+    let config = Config {
+        dimensions: DIMS,
+        ..Default::default()
+    };
+    {
+        let db = Database::open(dir.path()).unwrap();
+        fill(&db, "c", config.clone());
+        check_updates(&db);
+        fill(
+            &db,
+            "p",
+            Config {
+                partition_field: Some("tenant".into()),
+                ..config
+            },
+        );
+        db.get_collection("p")
+            .unwrap()
+            .update_metadata("v4", json!({ "tenant": "b" }), true)
+            .unwrap();
+    }
+
+    let db = Database::open(dir.path()).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    assert_eq!(
+        collection.get("v5").unwrap().unwrap().1,
+        Some(json!({ "color": "red" }))
+    );
+    assert_eq!(collection.get("v3").unwrap().unwrap().1, None);
+
+    let partitioned = db.get_collection("p").unwrap();
+    assert_eq!(
+        partitioned.get("v4").unwrap().unwrap().1,
+        Some(json!({ "tenant": "b", "n": 4, "tags": { "x": 1 } }))
+    );
+    let tenant_b = Filter::Exact("tenant".into(), json!("b"));
+    let hits = partitioned
+        .search(&vector(4, DIMS), 1, Some(&tenant_b))
+        .unwrap();
+    assert_eq!(ids(&hits), vec!["v4"]);
+}
@@ -0,0 +1,170 @@
+mod common;
+
+use common::{ids, vector};
+use serde_json::json;
+use surgedb_core::filter::Filter;
+use surgedb_core::{
+    Config, Database, DistanceMetric, NamedVectorConfig, QuantizationType, SearchParams,
+};
+use tempfile::tempdir;
+
+const DIMS: usize = 4;
+
+/// Title vectors are 2-dimensional and far apart, in reverse record order
+fn title(i: usize) -> Vec<f32> {
+    vec![(30 - i) as f32, 1.0]
+}
+
+fn config() -> Config {
+    Config {
+        dimensions: DIMS,
+        named_vectors: vec![
+            NamedVectorConfig {
+                name: "title".into(),
+                dimensions: 2,
+                distance_metric: DistanceMetric::Euclidean,
+            },
+            NamedVectorConfig {
+                name: "body".into(),
+                dimensions: 3,
+                distance_metric: DistanceMetric::Cosine,
+            },
+        ],
+        ..Default::default()
+    }
+}
+
+fn fill(db: &Database) {
+    db.create_collection("c", config()).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    for i in 0..30 {
+        collection
+            .insert(
+                format!("v{i}"),
+                &vector(i, DIMS),
+                Some(json!({ "n": i % 3 })),
+            )
+            .unwrap();
+        collection
+            .set_named_vector(&format!("v{i}"), "title", &title(i))
+            .unwrap();
+    }
+}
+
+fn search_title(db: &Database, i: usize, k: usize, filter: Option<&Filter>) -> Vec<String> {
+    let collection = db.get_collection("c").unwrap();
+    let (hits, _) = collection
+        .search_named("title", &title(i), k, filter, SearchParams::default())
+        .unwrap();
+    ids(&hits)
+}
+
+#[test]
+fn test_named_vectors_searched_by_space() {
+    let db = Database::new();
+    fill(&db);
+    let collection = db.get_collection("c").unwrap();
+
+    assert_eq!(search_title(&db, 7, 1, None), vec!["v7"]);
+    let zero = Filter::Exact("n".into(), json!(0));
+    assert_eq!(search_title(&db, 7, 2, Some(&zero)), vec!["v6", "v9"]);
+    let (hits, _) = collection
+        .search_named("title", &title(7), 1, None, SearchParams::default())
+        .unwrap();
+    assert_eq!(hits[0].2, Some(json!({ "n": 1 })));
+
+    // Spaces are independent: records without a body vector are never found
+    collection
+        .set_named_vector("v4", "body", &[1.0, 0.0, 0.0])
+        .unwrap();
+    let (hits, _) = collection
+        .search_named("body", &[0.0, 1.0, 0.0], 10, None, SearchParams::default())
+        .unwrap();
+    assert_eq!(ids(&hits), vec!["v4"]);
+    assert_eq!(collection.get_named_vectors("v4").len(), 2);
+
+    // Unknown spaces, wrong dimensions and missing records
+    assert!(collection.set_named_vector("v1", "tags", &[1.0]).is_err());
+    assert!(collection.set_named_vector("v1", "title", &[1.0]).is_err());
+    assert!(collection
+        .search_named("title", &[1.0], 1, None, SearchParams::default())
+        .is_err());
+    assert!(!collection
+        .set_named_vector("missing", "title", &[1.0, 1.0])
+        .unwrap());
+}
+
+#[test]
+fn test_named_vectors_follow_the_record() {
+    let db = Database::new();
+    fill(&db);
+    let collection = db.get_collection("c").unwrap();
+
+    // Replacing moves the record in that space only
+    collection
+        .set_named_vector("v7", "title", &[10.5, 1.0])
+        .unwrap();
+    assert_eq!(search_title(&db, 20, 2, None), vec!["v20", "v7"]);
+    assert!(!search_title(&db, 7, 3, None).contains(&"v7".to_string()));
+
+    // Upserting or deleting the record drops its named vectors
+    collection
+        .upsert("v8".into(), &vector(8, DIMS), None)
+        .unwrap();
+    collection.delete("v9").unwrap();
+    assert!(collection.get_named_vectors("v8").is_empty());
+    let found = search_title(&db, 8, 3, None);
+    assert!(!found.contains(&"v8".to_string()) && !found.contains(&"v9".to_string()));
+
+    // Metadata updates keep them
+    collection
+        .update_metadata("v10", json!({ "n": 5 }), true)
+        .unwrap();
+    assert_eq!(
+        collection.get_named_vectors("v10"),
+        vec![("title".to_string(), title(10))]
+    );
+}
+
+#[test]
+fn test_named_vectors_persist_and_survive_compaction() {
+    let dir = tempdir().unwrap();
+    {
+        let db = Database::open(dir.path()).unwrap();
+        fill(&db);
+        let collection = db.get_collection("c").unwrap();
+        collection.delete("v5").unwrap();
+        collection.compact().unwrap();
+        assert_eq!(search_title(&db, 12, 1, None), vec!["v12"]);
+        // Logged after the checkpoint compaction takes
+        collection
+            .set_named_vector("v3", "body", &[0.0, 0.0, 1.0])
+            .unwrap();
+    }
+
+    let db = Database::open(dir.path()).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    assert_eq!(collection.config().named_vectors, config().named_vectors);
+    assert_eq!(search_title(&db, 12, 1, None), vec!["v12"]);
+    assert!(!search_title(&db, 5, 3, None).contains(&"v5".to_string()));
+    let (hits, _) = collection
+        .search_named("body", &[0.0, 0.0, 1.0], 5, None, SearchParams::default())
+        .unwrap();
+    assert_eq!(ids(&hits), vec!["v3"]);
+}
+
+#[test]
+fn test_named_vectors_need_full_precision() {
+    let db = Database::new();
+    let quantized = Config {
+        quantization: QuantizationType::SQ8,
+        ..config()
+    };
+    assert!(db.create_collection("q", quantized).is_err());
+
+    let duplicate = Config {
+        named_vectors: vec![config().named_vectors[0].clone(); 2],
+        ..config()
+    };
+    assert!(db.create_collection("d", duplicate).is_err());
+}
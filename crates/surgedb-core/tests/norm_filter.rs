@@ -0,0 +1,105 @@
+use surgedb_core::{CollectionUpdate, Config, Database, Error, NormFilter};
+use tempfile::tempdir;
+
+fn config() -> Config {
+    Config::builder(2)
+        .norm_filter(NormFilter {
+            min_norm: Some(0.5),
+            max_norm: Some(10.0),
+        })
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn test_norm_filter_rejects_writes() {
+    let db = Database::new();
+    db.create_collection("c", config()).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    collection.insert("ok".into(), &[3.0, 4.0], None).unwrap();
+
+    let err = collection
+        .insert("zero".into(), &[0.0, 0.0], None)
+        .unwrap_err();
+    assert!(matches!(err, Error::NormOutOfRange { norm, .. } if norm == 0.0));
+    assert_eq!(err.error_code(), 1007);
+    assert!(collection.upsert("ok".into(), &[30.0, 40.0], None).is_err());
+    assert!(collection
+        .insert("nan".into(), &[f32::NAN, 1.0], None)
+        .is_err());
+
+    // One bad vector fails the whole batch, and each bad one is counted
+    let batch = vec![
+        ("b1".to_string(), vec![1.0, 0.0], None),
+        ("b2".to_string(), vec![0.0, 0.01], None),
+        ("b3".to_string(), vec![f32::INFINITY, 0.0], None),
+    ];
+    assert!(collection.upsert_batch(batch).is_err());
+    assert!(collection.get("b1").unwrap().is_none());
+    assert_eq!(collection.get("ok").unwrap().unwrap().0, vec![3.0, 4.0]);
+    assert_eq!(collection.stats().norm_rejections, 5);
+}
+
+#[test]
+fn test_norm_filter_validated() {
+    let inverted = NormFilter {
+        min_norm: Some(2.0),
+        max_norm: Some(1.0),
+    };
+    assert!(Config::builder(2).norm_filter(inverted).build().is_err());
+
+    let db = Database::new();
+    let config = Config {
+        norm_filter: Some(inverted),
+        ..Config::builder(2).build().unwrap()
+    };
+    assert!(matches!(
+        db.create_collection("c", config),
+        Err(Error::InvalidConfig(_))
+    ));
+}
+
+#[test]
+fn test_norm_filter_updated_and_persisted() {
+    let dir = tempdir().unwrap();
+    {
+        let db = Database::open(dir.path()).unwrap();
+        db.create_collection("c", Config::builder(2).build().unwrap())
+            .unwrap();
+        let collection = db.get_collection("c").unwrap();
+        collection.insert("zero".into(), &[0.0, 0.0], None).unwrap();
+
+        db.update_collection(
+            "c",
+            CollectionUpdate {
+                norm_filter: Some(config().norm_filter),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(collection
+            .insert("zero2".into(), &[0.0, 0.0], None)
+            .is_err());
+    }
+
+    let db = Database::open(dir.path()).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    // Vectors already stored are kept
+    assert!(collection.get("zero").unwrap().is_some());
+    assert_eq!(collection.norm_filter(), config().norm_filter);
+    assert!(collection
+        .insert("zero2".into(), &[0.0, 0.0], None)
+        .is_err());
+
+    db.update_collection(
+        "c",
+        CollectionUpdate {
+            norm_filter: Some(None),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    collection
+        .insert("zero2".into(), &[0.0, 0.0], None)
+        .unwrap();
+}
@@ -0,0 +1,64 @@
+#![cfg(feature = "parquet")]
+
+use serde_json::json;
+use surgedb_core::{Config, Database, DistanceMetric, IdType};
+use tempfile::tempdir;
+
+fn config(id_type: IdType) -> Config {
+    Config {
+        dimensions: 3,
+        distance_metric: DistanceMetric::Euclidean,
+        id_type,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_parquet_moves_records_between_databases() {
+    let dir = tempdir().unwrap();
+    let file = dir.path().join("c.parquet");
+    {
+        let db = Database::open(dir.path().join("a")).unwrap();
+        db.create_collection("c", config(IdType::U64)).unwrap();
+        let collection = db.get_collection("c").unwrap();
+        for i in 0..2500u64 {
+            collection
+                .insert(
+                    i.to_string(),
+                    &[i as f32, 1.0, 0.0],
+                    Some(json!({ "n": i % 3 })),
+                )
+                .unwrap();
+        }
+        collection.delete("7").unwrap();
+        assert_eq!(collection.export_parquet(&file).unwrap(), 2499);
+    }
+
+    let db = Database::new();
+    db.create_collection("copy", config(IdType::U64)).unwrap();
+    let copy = db.get_collection("copy").unwrap();
+    copy.insert("7".into(), &[0.0, 0.0, 9.0], None).unwrap();
+    assert_eq!(copy.import_parquet(&file).unwrap(), 2499);
+    assert_eq!(copy.len(), 2500);
+    assert_eq!(
+        copy.get("42").unwrap(),
+        Some((vec![42.0, 1.0, 0.0], Some(json!({ "n": 0 }))))
+    );
+    let hits = copy.search(&[8.1, 1.0, 0.0], 1, None).unwrap();
+    assert_eq!(hits[0].0.to_string(), "8");
+
+    // Records must fit the collection
+    db.create_collection(
+        "narrow",
+        Config {
+            dimensions: 2,
+            ..config(IdType::String)
+        },
+    )
+    .unwrap();
+    let narrow = db.get_collection("narrow").unwrap();
+    assert!(narrow.import_parquet(&file).is_err());
+    assert!(narrow
+        .import_parquet(dir.path().join("missing.parquet"))
+        .is_err());
+}
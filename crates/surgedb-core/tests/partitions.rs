@@ -0,0 +1,130 @@
+mod common;
+
+use common::vector;
+use serde_json::json;
+use surgedb_core::filter::Filter;
+use surgedb_core::{
+    Config, DistanceMetric, PersistentConfig, PersistentVectorDb, QuantizationType, VectorDb,
+};
+use tempfile::tempdir;
+
+/// Every 100th vector belongs to the small tenant
+fn tenant(i: usize) -> &'static str {
+    if i.is_multiple_of(100) {
+        "small"
+    } else {
+        "big"
+    }
+}
+
+fn tenant_filter(tenant: &str) -> Filter {
+    Filter::Exact("tenant_id".to_string(), json!(tenant))
+}
+
+#[test]
+fn test_small_tenant_search_stays_in_partition() {
+    let mut db = VectorDb::new(Config {
+        dimensions: 4,
+        distance_metric: DistanceMetric::Euclidean,
+        partition_field: Some("tenant_id".to_string()),
+        ..Default::default()
+    })
+    .unwrap();
+    for i in 0..2000 {
+        db.insert(
+            format!("v{i}"),
+            &vector(i, 4),
+            Some(json!({ "tenant_id": tenant(i), "i": i })),
+        )
+        .unwrap();
+    }
+
+    let query = vector(555, 4);
+    let (results, usage) = db
+        .search_with_usage(&query, 20, Some(&tenant_filter("small")))
+        .unwrap();
+    assert_eq!(results.len(), 20);
+    assert!(
+        usage.vectors_scanned < 100,
+        "scanned {}",
+        usage.vectors_scanned
+    );
+    for (_, _, meta) in &results {
+        assert_eq!(meta.as_ref().unwrap()["tenant_id"], "small");
+    }
+
+    // Residual conditions still apply inside the partition
+    let filter = Filter::And(vec![
+        tenant_filter("small"),
+        Filter::Range {
+            field: "i".to_string(),
+            gt: None,
+            gte: Some(1000.0),
+            lt: None,
+            lte: None,
+        },
+    ]);
+    let results = db.search(&query, 20, Some(&filter)).unwrap();
+    assert_eq!(results.len(), 10);
+
+    // Unknown tenants have no partition and no results
+    assert!(db
+        .search(&query, 5, Some(&tenant_filter("nobody")))
+        .unwrap()
+        .is_empty());
+
+    // Deleted and moved vectors drop out of their old partition
+    db.delete("v0").unwrap();
+    db.upsert("v100", &vector(100, 4), Some(json!({ "tenant_id": "big" })))
+        .unwrap();
+    let results = db
+        .search(&query, 20, Some(&tenant_filter("small")))
+        .unwrap();
+    assert_eq!(results.len(), 18);
+}
+
+#[test]
+fn test_partitions_rebuilt_on_reopen() {
+    let dir = tempdir().unwrap();
+    let config = PersistentConfig {
+        dimensions: 4,
+        distance_metric: DistanceMetric::Euclidean,
+        partition_field: Some("tenant_id".to_string()),
+        ..Default::default()
+    };
+
+    {
+        let mut db = PersistentVectorDb::open(dir.path(), config.clone()).unwrap();
+        for i in 0..500 {
+            db.insert(
+                format!("v{i}"),
+                &vector(i, 4),
+                Some(json!({ "tenant_id": tenant(i) })),
+            )
+            .unwrap();
+        }
+        db.checkpoint().unwrap();
+        db.insert("late", &vector(7, 4), Some(json!({ "tenant_id": "small" })))
+            .unwrap();
+    }
+
+    let db = PersistentVectorDb::open(dir.path(), config).unwrap();
+    let (results, usage) = db
+        .search_with_usage(&vector(7, 4), 10, Some(&tenant_filter("small")))
+        .unwrap();
+    assert_eq!(results.len(), 6);
+    assert_eq!(results[0].0.as_str(), "late");
+    assert!(usage.vectors_scanned < 50);
+}
+
+#[test]
+fn test_quantized_collections_reject_partitions() {
+    let db = surgedb_core::Database::new();
+    let config = Config {
+        dimensions: 4,
+        quantization: QuantizationType::SQ8,
+        partition_field: Some("tenant_id".to_string()),
+        ..Default::default()
+    };
+    assert!(db.create_collection("q", config).is_err());
+}
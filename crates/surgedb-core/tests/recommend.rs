@@ -0,0 +1,105 @@
+use serde_json::json;
+use surgedb_core::filter::Filter;
+use surgedb_core::{
+    Config, Database, DistanceMetric, Error, Recommend, RecommendStrategy, SearchParams,
+};
+
+/// Records on a line, `v{i}` at `i`, with every tenth in tenant "b"
+fn collection(db: &Database) -> surgedb_core::db::Collection {
+    let config = Config::builder(2)
+        .distance_metric(DistanceMetric::Euclidean)
+        .build()
+        .unwrap();
+    db.create_collection("c", config).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    for i in 0..100 {
+        let tenant = if i % 10 == 0 { "b" } else { "a" };
+        collection
+            .insert(
+                format!("v{i}"),
+                &[i as f32, 0.0],
+                Some(json!({ "tenant": tenant })),
+            )
+            .unwrap();
+    }
+    collection
+}
+
+fn ids(hits: &[(surgedb_core::VectorId, f32, Option<serde_json::Value>)]) -> Vec<String> {
+    hits.iter().map(|(id, _, _)| id.to_string()).collect()
+}
+
+#[test]
+fn test_average_vector_recommendation() {
+    let db = Database::new();
+    let collection = collection(&db);
+    let params = SearchParams::default();
+
+    // Target is 20 + (20 - 10) = 30, away from the negative
+    let examples = Recommend {
+        positive: vec!["v18".into(), "v22".into()],
+        negative: vec!["v10".into()],
+        strategy: RecommendStrategy::AverageVector,
+    };
+    let (hits, _) = collection.recommend(&examples, 3, None, params).unwrap();
+    let mut found = ids(&hits);
+    found.sort();
+    assert_eq!(found, vec!["v29", "v30", "v31"]);
+    assert_eq!(hits[0].1, 0.0);
+
+    // Examples are never recommended, and the filter applies
+    let examples = Recommend {
+        positive: vec!["v50".into()],
+        ..Default::default()
+    };
+    let tenant_b = Filter::Exact("tenant".into(), json!("b"));
+    let (hits, _) = collection
+        .recommend(&examples, 2, Some(&tenant_b), params)
+        .unwrap();
+    let mut found = ids(&hits);
+    found.sort();
+    assert_eq!(found, vec!["v40", "v60"]);
+    assert!(hits.iter().all(|(_, distance, _)| *distance == 10.0));
+}
+
+#[test]
+fn test_best_score_recommendation() {
+    let db = Database::new();
+    let collection = collection(&db);
+    let examples = Recommend {
+        positive: vec!["v10".into(), "v80".into()],
+        negative: vec!["v12".into()],
+        strategy: RecommendStrategy::BestScore,
+    };
+    let (hits, usage) = collection
+        .recommend(&examples, 4, None, SearchParams::default())
+        .unwrap();
+    // v11 is as close to v12 as to v10, so it is not rejected
+    let mut found = ids(&hits);
+    found.sort();
+    assert_eq!(found, vec!["v11", "v79", "v81", "v9"]);
+    assert!(hits.iter().all(|(_, distance, _)| *distance == 1.0));
+    assert!(usage.vectors_scanned > 0);
+}
+
+#[test]
+fn test_recommend_needs_existing_positive_examples() {
+    let db = Database::new();
+    let collection = collection(&db);
+    let params = SearchParams::default();
+
+    let err = collection
+        .recommend(&Recommend::default(), 3, None, params)
+        .unwrap_err();
+    assert!(matches!(err, Error::InvalidConfig(_)));
+
+    let examples = Recommend {
+        positive: vec!["v1".into()],
+        negative: vec!["missing".into()],
+        ..Default::default()
+    };
+    let err = collection
+        .recommend(&examples, 3, None, params)
+        .unwrap_err();
+    assert!(matches!(err, Error::VectorNotFound(id) if id == "missing"));
+}
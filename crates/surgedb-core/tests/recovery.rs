@@ -0,0 +1,198 @@
+use std::io::Write;
+use surgedb_core::{
+    Config, Database, Error, PersistentConfig, PersistentVectorDb, RecoveryMode, RecoveryPhase,
+};
+use tempfile::{tempdir, TempDir};
+
+fn config() -> PersistentConfig {
+    PersistentConfig {
+        dimensions: 2,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_replay_in_chunks() {
+    let dir = tempdir().unwrap();
+    {
+        let mut db = PersistentVectorDb::open(dir.path(), config()).unwrap();
+        db.insert("a", &[1.0, 0.0], None).unwrap();
+        db.checkpoint().unwrap();
+        for i in 0..25 {
+            db.insert(format!("v{i}"), &[i as f32, 1.0], None).unwrap();
+        }
+        db.delete("a").unwrap();
+    }
+
+    let (mut db, mut pending) = PersistentVectorDb::open_deferred(dir.path(), config()).unwrap();
+    // 25 inserts and a delete, plus the checkpoint marker logged after the snapshot
+    assert_eq!(pending.len(), 27);
+    // The snapshot is readable before the tail is replayed
+    assert_eq!(db.len(), 1);
+
+    assert_eq!(db.replay(&mut pending, 10).unwrap(), 10);
+    assert!(db.len() > 1);
+    assert_eq!(db.replay(&mut pending, 100).unwrap(), 17);
+    assert_eq!(db.replay(&mut pending, 100).unwrap(), 0);
+    assert!(pending.is_empty());
+    assert_eq!(db.len(), 25);
+    assert!(db.get("a").unwrap().is_none());
+}
+
+#[test]
+fn test_flush_checkpoints_unsaved_writes() {
+    let dir = tempdir().unwrap();
+    {
+        let mut db = PersistentVectorDb::open(dir.path(), config()).unwrap();
+        db.insert("a", &[1.0, 0.0], None).unwrap();
+        assert!(db.flush().unwrap());
+        // Nothing written since
+        assert!(!db.flush().unwrap());
+        db.insert("b", &[0.0, 1.0], None).unwrap();
+        assert!(db.flush().unwrap());
+    }
+
+    let (mut db, pending) = PersistentVectorDb::open_deferred(dir.path(), config()).unwrap();
+    // Only the checkpoint marker is left to replay
+    assert_eq!(pending.len(), 1);
+    assert_eq!(db.len(), 2);
+    assert!(!db.flush().unwrap());
+}
+
+#[test]
+fn test_background_recovery_reaches_ready() {
+    let dir = tempdir().unwrap();
+    {
+        let db = Database::open(dir.path()).unwrap();
+        for name in ["one", "two", "three"] {
+            db.create_collection(
+                name,
+                Config {
+                    dimensions: 2,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            let collection = db.get_collection(name).unwrap();
+            for i in 0..50 {
+                collection
+                    .insert(format!("v{i}"), &[i as f32, 0.0], None)
+                    .unwrap();
+            }
+        }
+    }
+
+    let db = Database::open_recovering(dir.path()).unwrap();
+    let start = std::time::Instant::now();
+    while !db.recovery_status().is_ready() {
+        assert!(start.elapsed().as_secs() < 30, "recovery did not finish");
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    let status = db.recovery_status();
+    assert_eq!(status.phase, RecoveryPhase::Ready);
+    assert_eq!(status.collections_total, 3);
+    assert_eq!(status.collections_loaded, 3);
+    assert_eq!(status.wal_entries_total, status.wal_entries_replayed);
+    assert_eq!(status.percent, 100.0);
+    for name in ["one", "two", "three"] {
+        assert_eq!(db.get_collection(name).unwrap().stats().vector_count, 50);
+    }
+
+    // In-memory databases have nothing to recover
+    assert!(Database::new().recovery_status().is_ready());
+}
+
+#[test]
+fn test_torn_wal_tail_cut_off() {
+    let dir = tempdir().unwrap();
+    {
+        let mut db = PersistentVectorDb::open(dir.path(), config()).unwrap();
+        db.insert("a", &[1.0, 0.0], None).unwrap();
+        db.insert("b", &[0.0, 1.0], None).unwrap();
+    }
+    // A record whose length made it to disk but most of whose body did not
+    let mut wal = std::fs::OpenOptions::new()
+        .append(true)
+        .open(dir.path().join("wal/current.wal"))
+        .unwrap();
+    wal.write_all(&[100, 0, 0, 0, 1, 2, 3]).unwrap();
+    drop(wal);
+
+    let strict = PersistentConfig {
+        recovery_mode: RecoveryMode::Strict,
+        ..config()
+    };
+    {
+        let mut db = PersistentVectorDb::open(dir.path(), strict.clone()).unwrap();
+        assert_eq!(db.wal_repair().torn_bytes, 7);
+        assert_eq!(db.len(), 2);
+        db.insert("c", &[1.0, 1.0], None).unwrap();
+    }
+
+    // Writes after the repair land where they can be read back
+    let db = PersistentVectorDb::open(dir.path(), strict).unwrap();
+    assert!(db.wal_repair().is_clean());
+    assert_eq!(db.len(), 3);
+    assert!(db.get("c").unwrap().is_some());
+}
+
+/// Database with records `a`, `b` and `c` in collection `c`, the WAL record
+/// of `b` having a bad checksum
+fn damaged_database() -> TempDir {
+    let dir = tempdir().unwrap();
+    {
+        let db = Database::open(dir.path()).unwrap();
+        db.create_collection("c", Config::builder(2).build().unwrap())
+            .unwrap();
+        let collection = db.get_collection("c").unwrap();
+        for (i, id) in ["a", "b", "c"].into_iter().enumerate() {
+            collection
+                .insert(id.into(), &[1.0, i as f32], None)
+                .unwrap();
+        }
+    }
+
+    let path = dir.path().join("c/wal/current.wal");
+    let mut bytes = std::fs::read(&path).unwrap();
+    let frame_len = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap()) as usize;
+    let second = 5 + 4 + frame_len(5);
+    // The checksum is the last field of a record
+    let checksum_end = second + 4 + frame_len(second);
+    bytes[checksum_end - 1] ^= 0xFF;
+    std::fs::write(&path, bytes).unwrap();
+    dir
+}
+
+#[test]
+fn test_damaged_wal_record_handled_by_mode() {
+    let dir = damaged_database();
+    assert!(matches!(
+        Database::open_with_recovery(dir.path(), RecoveryMode::Strict),
+        Err(Error::WalCorrupted { .. })
+    ));
+
+    // Repair keeps what was logged before the damage
+    let db = Database::open_with_recovery(dir.path(), RecoveryMode::Repair).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    assert_eq!(collection.len(), 1);
+    assert!(collection.get("a").unwrap().is_some());
+    assert_eq!(db.recovery_status().wal_records_dropped, 2);
+    drop((collection, db));
+    let db = Database::open_with_recovery(dir.path(), RecoveryMode::Strict).unwrap();
+    assert_eq!(db.recovery_status().wal_records_dropped, 0);
+
+    // Skip drops only the damaged record
+    let dir = damaged_database();
+    let db = Database::open_with_recovery(dir.path(), RecoveryMode::Skip).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    assert!(collection.get("a").unwrap().is_some());
+    assert!(collection.get("b").unwrap().is_none());
+    assert!(collection.get("c").unwrap().is_some());
+    assert_eq!(db.recovery_status().wal_records_dropped, 1);
+
+    let report = collection.verify().unwrap();
+    assert!(report.is_ok(), "{:?}", report.problems);
+    assert_eq!(report.vectors, 2);
+    assert_eq!(report.graph_nodes, 2);
+}
@@ -0,0 +1,97 @@
+use serde_json::json;
+use std::fs::OpenOptions;
+use surgedb_core::filter::Filter;
+use surgedb_core::{Config, Database, PersistentConfig, PersistentVectorDb, VectorId};
+use tempfile::tempdir;
+
+fn chunks(
+    doc: &str,
+    prefix: &str,
+    n: usize,
+) -> Vec<(VectorId, Vec<f32>, Option<serde_json::Value>)> {
+    (0..n)
+        .map(|i| {
+            (
+                VectorId::from(format!("{prefix}-{i}")),
+                vec![i as f32, 1.0, 0.0, 0.0],
+                Some(json!({ "doc_id": doc, "chunk": i })),
+            )
+        })
+        .collect()
+}
+
+fn doc_filter(doc: &str) -> Filter {
+    Filter::Exact("doc_id".to_string(), json!(doc))
+}
+
+#[test]
+fn test_replace_swaps_document_chunks() {
+    let db = Database::new();
+    let config = Config {
+        dimensions: 4,
+        ..Default::default()
+    };
+    db.create_collection("docs", config).unwrap();
+    let collection = db.get_collection("docs").unwrap();
+
+    let as_strings = |items: Vec<(VectorId, Vec<f32>, Option<serde_json::Value>)>| {
+        items
+            .into_iter()
+            .map(|(id, v, m)| (id.to_string(), v, m))
+            .collect::<Vec<_>>()
+    };
+    collection
+        .upsert_batch(as_strings(chunks("a", "a-v1", 3)))
+        .unwrap();
+    collection
+        .upsert_batch(as_strings(chunks("b", "b", 2)))
+        .unwrap();
+
+    let deleted = collection
+        .replace(&doc_filter("a"), as_strings(chunks("a", "a-v2", 2)))
+        .unwrap();
+    assert_eq!(deleted, 3);
+    assert!(collection.get("a-v1-0").unwrap().is_none());
+    assert!(collection.get("a-v2-1").unwrap().is_some());
+    assert!(collection.get("b-1").unwrap().is_some());
+    assert_eq!(collection.stats().vector_count, 4);
+
+    // Nothing is deleted when an item is invalid
+    let mut bad = as_strings(chunks("a", "a-v3", 2));
+    bad[1].1 = vec![1.0];
+    assert!(collection.replace(&doc_filter("a"), bad).is_err());
+    assert!(collection.get("a-v2-0").unwrap().is_some());
+}
+
+#[test]
+fn test_replace_is_atomic_in_wal() {
+    let dir = tempdir().unwrap();
+    let config = PersistentConfig {
+        dimensions: 4,
+        ..Default::default()
+    };
+
+    {
+        let mut db = PersistentVectorDb::open(dir.path(), config.clone()).unwrap();
+        db.replace(&doc_filter("a"), chunks("a", "v1", 3)).unwrap();
+        db.replace(&doc_filter("a"), chunks("a", "v2", 2)).unwrap();
+    }
+    {
+        let db = PersistentVectorDb::open(dir.path(), config.clone()).unwrap();
+        assert_eq!(db.len(), 2);
+        assert!(db.get("v1-0").unwrap().is_none());
+        assert!(db.get("v2-1").unwrap().is_some());
+    }
+
+    // A torn final record drops the whole replacement, not part of it
+    let wal_path = dir.path().join("wal").join("current.wal");
+    let len = std::fs::metadata(&wal_path).unwrap().len();
+    let file = OpenOptions::new().write(true).open(&wal_path).unwrap();
+    file.set_len(len - 8).unwrap();
+    drop(file);
+
+    let db = PersistentVectorDb::open(dir.path(), config).unwrap();
+    assert_eq!(db.len(), 3);
+    assert!(db.get("v1-2").unwrap().is_some());
+    assert!(db.get("v2-0").unwrap().is_none());
+}
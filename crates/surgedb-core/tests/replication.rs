@@ -0,0 +1,99 @@
+use serde_json::json;
+use surgedb_core::db::Collection;
+use surgedb_core::{Config, Database, DistanceMetric};
+use tempfile::tempdir;
+
+fn config() -> Config {
+    Config::builder(2)
+        .distance_metric(DistanceMetric::Euclidean)
+        .build()
+        .unwrap()
+}
+
+fn records(collection: &Collection) -> Vec<(String, Vec<f32>, Option<serde_json::Value>)> {
+    let mut records: Vec<_> = collection
+        .list(0, usize::MAX)
+        .into_iter()
+        .map(|(id, _)| {
+            let (vector, metadata) = collection.get(&id.to_string()).unwrap().unwrap();
+            (id.to_string(), vector, metadata)
+        })
+        .collect();
+    records.sort_by(|a, b| a.0.cmp(&b.0));
+    records
+}
+
+#[test]
+fn test_follower_replays_leader_log() {
+    let (leader_dir, follower_dir) = (tempdir().unwrap(), tempdir().unwrap());
+    let leader = Database::open(leader_dir.path()).unwrap();
+    leader.create_collection("c", config()).unwrap();
+    let source = leader.get_collection("c").unwrap();
+    source.insert("a".into(), &[1.0, 0.0], None).unwrap();
+    source.insert("b".into(), &[2.0, 0.0], None).unwrap();
+
+    // The follower starts from a snapshot
+    let file = follower_dir.path().join("c.snap");
+    let mut position = source.snapshot_at(&file).unwrap();
+    let follower = Database::open(follower_dir.path().join("db")).unwrap();
+    follower.restore("c", &file).unwrap();
+    let replica = follower.get_collection("c").unwrap();
+
+    source
+        .upsert_batch(vec![
+            ("a".into(), vec![1.0, 1.0], Some(json!({"v": 2}))),
+            ("c".into(), vec![3.0, 0.0], None),
+        ])
+        .unwrap();
+    source.delete("b").unwrap();
+    source
+        .update_metadata("c", json!({"tag": "x"}), false)
+        .unwrap();
+
+    // Read in pages, as a follower polling the leader does
+    loop {
+        let entries = source.log_after(position, 2).unwrap().unwrap();
+        let Some((seq, _)) = entries.last() else {
+            break;
+        };
+        position.seq = *seq;
+        replica
+            .apply_log(entries.into_iter().map(|(_, entry)| entry).collect())
+            .unwrap();
+    }
+    assert_eq!(position, source.log_position().unwrap());
+    assert_eq!(records(&replica), records(&source));
+    let hits = replica.search(&[3.0, 0.0], 1, None).unwrap();
+    assert_eq!(hits[0].0.to_string(), "c");
+
+    // A recreated collection starts another log
+    let stale = position;
+    leader.delete_collection("c").unwrap();
+    drop(source);
+    leader.create_collection("c", config()).unwrap();
+    let source = leader.get_collection("c").unwrap();
+    for i in 0..10 {
+        source
+            .insert(format!("v{i}"), &[i as f32, 0.0], None)
+            .unwrap();
+    }
+    assert!(source.log_after(stale, 100).unwrap().is_none());
+
+    // Entries cleared by a checkpoint can't be replayed
+    let position = source.log_position().unwrap();
+    assert!(source.log_after(position, 100).unwrap().unwrap().is_empty());
+    source.delete("v0").unwrap();
+    source.compact().unwrap();
+    assert!(source.log_after(position, 100).unwrap().is_none());
+    let now = source.log_position().unwrap();
+    assert!(source.log_after(now, 100).unwrap().unwrap().is_empty());
+}
+
+#[test]
+fn test_in_memory_collections_keep_no_log() {
+    let db = Database::new();
+    db.create_collection("c", config()).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    assert!(collection.log_position().is_err());
+    assert!(collection.apply_log(Vec::new()).is_err());
+}
@@ -0,0 +1,58 @@
+mod common;
+
+use common::vector;
+use surgedb_core::filter::Filter;
+use surgedb_core::{Config, Database, SearchParams};
+
+const DIMS: usize = 8;
+
+#[test]
+fn test_search_batch_matches_single_searches() {
+    let db = Database::new();
+    db.create_collection(
+        "docs",
+        Config {
+            dimensions: DIMS,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let collection = db.get_collection("docs").unwrap();
+    for i in 0..200usize {
+        let metadata = serde_json::json!({ "even": i.is_multiple_of(2) });
+        collection
+            .insert(format!("v{i}"), &vector(i, DIMS), Some(metadata))
+            .unwrap();
+    }
+
+    let queries: Vec<Vec<f32>> = (0..20).map(|i| vector(i * 7, DIMS)).collect();
+    let params = SearchParams::default();
+    let batch = collection.search_batch(&queries, 5, None, params).unwrap();
+    assert_eq!(batch.len(), queries.len());
+    for (query, (hits, _)) in queries.iter().zip(&batch) {
+        let (single, _) = collection
+            .search_with_params(query, 5, None, params)
+            .unwrap();
+        assert_eq!(hits, &single);
+    }
+    assert_eq!(batch[3].0[0].0.as_str(), "v21");
+
+    let filter = Filter::Exact("even".into(), serde_json::json!(true));
+    let batch = collection
+        .search_ids_batch(&queries, 3, Some(&filter), params)
+        .unwrap();
+    assert!(batch.iter().all(|(ids, _)| ids.len() == 3));
+    assert!(batch.iter().flat_map(|(ids, _)| ids).all(|(id, _)| {
+        let i: usize = id.as_str()[1..].parse().unwrap();
+        i.is_multiple_of(2)
+    }));
+
+    // One bad query fails the whole batch
+    let mut bad = queries.clone();
+    bad.push(vec![0.0; DIMS + 1]);
+    assert!(collection.search_batch(&bad, 5, None, params).is_err());
+    assert!(collection
+        .search_batch(&[], 5, None, params)
+        .unwrap()
+        .is_empty());
+}
@@ -0,0 +1,158 @@
+mod common;
+
+use common::vector;
+use serde_json::json;
+use surgedb_core::{
+    Config, Database, DistanceMetric, PersistentConfig, PersistentVectorDb, QuantizationType,
+};
+use tempfile::tempdir;
+
+fn config() -> Config {
+    Config {
+        dimensions: 4,
+        distance_metric: DistanceMetric::Euclidean,
+        ..Default::default()
+    }
+}
+
+fn fill(db: &Database, name: &str, count: usize) {
+    let collection = db.get_collection(name).unwrap();
+    for i in 0..count {
+        collection
+            .insert(format!("v{i}"), &vector(i, 4), Some(json!({ "i": i })))
+            .unwrap();
+    }
+}
+
+fn top_ids(db: &Database, name: &str, query: usize) -> Vec<String> {
+    db.get_collection(name)
+        .unwrap()
+        .search(&vector(query, 4), 10, None)
+        .unwrap()
+        .into_iter()
+        .map(|(id, _, _)| id.to_string())
+        .collect()
+}
+
+#[test]
+fn test_snapshot_moves_collection_between_databases() {
+    let files = tempdir().unwrap();
+    let path = files.path().join("docs.snap");
+
+    let source = Database::new();
+    source.create_collection("docs", config()).unwrap();
+    fill(&source, "docs", 300);
+    assert_eq!(
+        source
+            .get_collection("docs")
+            .unwrap()
+            .snapshot(&path)
+            .unwrap(),
+        300
+    );
+
+    let data = tempdir().unwrap();
+    {
+        let target = Database::open(data.path()).unwrap();
+        assert_eq!(target.restore("docs_copy", &path).unwrap(), 300);
+        assert_eq!(
+            top_ids(&target, "docs_copy", 42),
+            top_ids(&source, "docs", 42)
+        );
+        assert!(target.restore("docs_copy", &path).is_err());
+    }
+
+    // The restored collection is persisted
+    let target = Database::open(data.path()).unwrap();
+    let restored = target.get_collection("docs_copy").unwrap();
+    assert_eq!(restored.stats().vector_count, 300);
+    assert_eq!(restored.config().distance_metric, DistanceMetric::Euclidean);
+    let (_, metadata) = restored.get("v7").unwrap().unwrap();
+    assert_eq!(metadata.unwrap()["i"], 7);
+}
+
+#[test]
+fn test_snapshot_with_deleted_vectors_rebuilds_graph() {
+    let files = tempdir().unwrap();
+    let path = files.path().join("docs.snap");
+
+    let source = Database::new();
+    source.create_collection("docs", config()).unwrap();
+    fill(&source, "docs", 200);
+    let collection = source.get_collection("docs").unwrap();
+    for i in (0..200).step_by(3) {
+        collection.delete(&format!("v{i}")).unwrap();
+    }
+    collection
+        .upsert("v1".to_string(), &vector(500, 4), None)
+        .unwrap();
+    assert_eq!(collection.snapshot(&path).unwrap(), 133);
+
+    let target = Database::new();
+    target.restore("docs", &path).unwrap();
+    let restored = target.get_collection("docs").unwrap();
+    assert_eq!(restored.stats().vector_count, 133);
+    let hits = restored.search(&vector(500, 4), 1, None).unwrap();
+    assert_eq!(hits[0].0.as_str(), "v1");
+    let hits = restored.search(&vector(100, 4), 1, None).unwrap();
+    assert_eq!(hits[0].0.as_str(), "v100");
+}
+
+#[test]
+fn test_snapshot_quantized_collection() {
+    let files = tempdir().unwrap();
+    let path = files.path().join("q.snap");
+    let quantized = Config {
+        quantization: QuantizationType::SQ8,
+        ..config()
+    };
+
+    let source = Database::new();
+    source.create_collection("q", quantized).unwrap();
+    fill(&source, "q", 200);
+    source.get_collection("q").unwrap().snapshot(&path).unwrap();
+
+    let target = Database::new();
+    target.restore("q", &path).unwrap();
+    let restored = target.get_collection("q").unwrap();
+    assert_eq!(restored.config().quantization, QuantizationType::SQ8);
+    let hits = restored.search(&vector(17, 4), 1, None).unwrap();
+    assert_eq!(hits[0].0.as_str(), "v17");
+}
+
+#[test]
+fn test_restore_rejects_other_files() {
+    let files = tempdir().unwrap();
+    let path = files.path().join("junk.snap");
+    std::fs::write(&path, b"not a snapshot").unwrap();
+
+    let db = Database::new();
+    assert!(db.restore("docs", &path).is_err());
+    assert!(db.list_collections().is_empty());
+}
+
+#[test]
+fn test_checkpoint_after_deletes_reopens_searchable() {
+    let dir = tempdir().unwrap();
+    let config = PersistentConfig {
+        dimensions: 4,
+        distance_metric: DistanceMetric::Euclidean,
+        ..Default::default()
+    };
+    {
+        let mut db = PersistentVectorDb::open(dir.path(), config.clone()).unwrap();
+        for i in 0..100 {
+            db.insert(format!("v{i}"), &vector(i, 4), None).unwrap();
+        }
+        for i in 0..50 {
+            db.delete(format!("v{i}")).unwrap();
+        }
+        db.checkpoint().unwrap();
+    }
+
+    let db = PersistentVectorDb::open(dir.path(), config).unwrap();
+    for i in [50, 75, 99] {
+        let hits = db.search(&vector(i, 4), 1, None).unwrap();
+        assert_eq!(hits[0].0.as_str(), format!("v{i}"));
+    }
+}
@@ -0,0 +1,49 @@
+use std::sync::Arc;
+use surgedb_core::{Config, Database, DistanceMetric, Error};
+
+#[test]
+fn test_collection_summary_cached_until_write() {
+    let db = Database::new();
+    let config = Config::builder(2)
+        .distance_metric(DistanceMetric::Euclidean)
+        .build()
+        .unwrap();
+    db.create_collection("c", config).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    // Three blobs of 20 points around (0, 0), (50, 0) and (0, 50)
+    for i in 0..60 {
+        let (cx, cy) = [(0.0, 0.0), (50.0, 0.0), (0.0, 50.0)][i % 3];
+        let jitter = (i / 3) as f32 * 0.05;
+        collection
+            .insert(format!("v{i}"), &[cx + jitter, cy - jitter], None)
+            .unwrap();
+    }
+
+    let summary = collection.summary(3).unwrap();
+    assert_eq!(summary.vector_count, 60);
+    assert_eq!(summary.clusters.len(), 3);
+    for cluster in &summary.clusters {
+        assert_eq!(cluster.size, 20);
+        // Every member of a blob has the same `i % 3`
+        let blob = |id: &str| id[1..].parse::<usize>().unwrap() % 3;
+        let first = blob(&cluster.medoid.to_string());
+        assert!(cluster
+            .representatives
+            .iter()
+            .all(|id| blob(&id.to_string()) == first));
+    }
+
+    assert!(Arc::ptr_eq(&summary, &collection.summary(3).unwrap()));
+    collection
+        .insert("extra".into(), &[50.0, 0.0], None)
+        .unwrap();
+    let updated = collection.summary(3).unwrap();
+    assert!(!Arc::ptr_eq(&summary, &updated));
+    assert_eq!(updated.vector_count, 61);
+    assert_eq!(updated.clusters[0].size, 21);
+
+    assert!(matches!(
+        collection.summary(0),
+        Err(Error::InvalidConfig(_))
+    ));
+}
@@ -0,0 +1,85 @@
+use serde_json::json;
+use std::sync::Arc;
+use surgedb_core::{Config, Database, GroupCommit, SyncMode};
+use tempfile::tempdir;
+
+fn config(sync_mode: SyncMode) -> Config {
+    Config::builder(2)
+        .group_commit(GroupCommit {
+            sync_mode,
+            ..Default::default()
+        })
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn test_sync_mode_always_concurrent_writers() {
+    let dir = tempdir().unwrap();
+    {
+        let db = Database::open(dir.path()).unwrap();
+        db.create_collection("c", config(SyncMode::Always)).unwrap();
+        let collection = Arc::new(db.get_collection("c").unwrap());
+
+        let writers: Vec<_> = (0..4)
+            .map(|t| {
+                let collection = collection.clone();
+                std::thread::spawn(move || {
+                    for i in 0..25 {
+                        collection
+                            .insert(format!("t{t}-{i}"), &[t as f32, i as f32], None)
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        collection
+            .update_metadata("t0-0", json!({"k": 1}), false)
+            .unwrap();
+        assert!(collection.delete("t3-24").unwrap());
+        assert_eq!(collection.len(), 99);
+    }
+
+    let db = Database::open(dir.path()).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    assert_eq!(collection.len(), 99);
+    assert_eq!(
+        collection.get("t0-0").unwrap().unwrap().1,
+        Some(json!({"k": 1}))
+    );
+    assert!(collection.get("t3-24").unwrap().is_none());
+    assert_eq!(
+        collection.config().group_commit.unwrap().sync_mode,
+        SyncMode::Always
+    );
+}
+
+#[test]
+fn test_sync_mode_parsed() {
+    let parsed: GroupCommit =
+        serde_json::from_value(json!({"flush_interval_ms": 5, "max_batch": 8})).unwrap();
+    assert_eq!(parsed.sync_mode, SyncMode::Interval);
+    assert_eq!(parsed.commit_interval_ms, 5);
+
+    let parsed: GroupCommit = serde_json::from_value(
+        json!({"sync_mode": "never", "commit_interval_ms": 5, "max_batch": 8}),
+    )
+    .unwrap();
+    assert_eq!(parsed.sync_mode, SyncMode::Never);
+}
+
+#[test]
+fn test_sync_mode_never_recovers_after_clean_close() {
+    let dir = tempdir().unwrap();
+    {
+        let db = Database::open(dir.path()).unwrap();
+        db.create_collection("c", config(SyncMode::Never)).unwrap();
+        let collection = db.get_collection("c").unwrap();
+        collection.insert("a".into(), &[1.0, 0.0], None).unwrap();
+    }
+    let db = Database::open(dir.path()).unwrap();
+    assert!(db.get_collection("c").unwrap().get("a").unwrap().is_some());
+}
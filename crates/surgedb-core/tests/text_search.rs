@@ -0,0 +1,165 @@
+mod common;
+
+use common::{ids, vector};
+use serde_json::json;
+use surgedb_core::filter::Filter;
+use surgedb_core::{Config, Database, Fusion, QuantizationType, SearchParams};
+use tempfile::tempdir;
+
+const DIMS: usize = 4;
+
+fn config() -> Config {
+    Config {
+        dimensions: DIMS,
+        text_fields: vec!["title".into(), "tags".into()],
+        ..Default::default()
+    }
+}
+
+fn fill(db: &Database) {
+    db.create_collection("docs", config()).unwrap();
+    let collection = db.get_collection("docs").unwrap();
+    let docs = [
+        (
+            "a",
+            json!({ "title": "Graph indexes for vector search", "lang": "en" }),
+        ),
+        (
+            "b",
+            json!({ "title": "Cooking with cast iron", "tags": ["kitchen"] }),
+        ),
+        (
+            "c",
+            json!({ "title": "Vector quantization", "tags": ["search", "vector"], "lang": "de" }),
+        ),
+        ("d", json!({ "body": "vector vector vector" })),
+    ];
+    for (i, (id, meta)) in docs.into_iter().enumerate() {
+        collection
+            .insert(id.to_string(), &vector(i, DIMS), Some(meta))
+            .unwrap();
+    }
+}
+
+#[test]
+fn test_text_search_ranks_and_filters() {
+    let db = Database::new();
+    fill(&db);
+    let collection = db.get_collection("docs").unwrap();
+
+    let (hits, usage) = collection.search_text("vector search", 10, None).unwrap();
+    assert_eq!(ids(&hits), vec!["c", "a"]);
+    assert!(hits[0].1 > hits[1].1);
+    assert_eq!(usage.vectors_scanned, 2);
+    assert!(hits[0].2.is_some());
+
+    let german = Filter::Exact("lang".into(), json!("de"));
+    let (hits, _) = collection
+        .search_text("vector search", 10, Some(&german))
+        .unwrap();
+    assert_eq!(ids(&hits), vec!["c"]);
+
+    // Overwrites reindex the new text, deletes drop it
+    collection
+        .upsert(
+            "b".into(),
+            &vector(1, DIMS),
+            Some(json!({ "title": "Vector soup" })),
+        )
+        .unwrap();
+    collection.delete("a").unwrap();
+    let (hits, _) = collection.search_text("vector", 10, None).unwrap();
+    let mut found = ids(&hits);
+    found.sort();
+    assert_eq!(found, vec!["b", "c"]);
+    assert!(collection
+        .search_text("cooking", 10, None)
+        .unwrap()
+        .0
+        .is_empty());
+}
+
+#[test]
+fn test_text_fused_with_vector_search() {
+    let db = Database::new();
+    fill(&db);
+    let collection = db.get_collection("docs").unwrap();
+    let params = SearchParams::default();
+
+    // alpha 0 is the text ranking, alpha 1 the dense one
+    let (hits, _) = collection
+        .search_hybrid_text(
+            &vector(3, DIMS),
+            "cast iron",
+            1,
+            None,
+            Fusion::WeightedSum { alpha: 0.0 },
+            params,
+        )
+        .unwrap();
+    assert_eq!(hits[0].id.as_str(), "b");
+    assert!(hits[0].sparse_score.is_some());
+
+    let (hits, _) = collection
+        .search_hybrid_text(
+            &vector(3, DIMS),
+            "cast iron",
+            1,
+            None,
+            Fusion::WeightedSum { alpha: 1.0 },
+            params,
+        )
+        .unwrap();
+    assert_eq!(hits[0].id.as_str(), "d");
+    assert_eq!(hits[0].sparse_score, None);
+
+    let (hits, _) = collection
+        .search_hybrid_text(
+            &vector(3, DIMS),
+            "cast iron",
+            4,
+            None,
+            Fusion::default(),
+            params,
+        )
+        .unwrap();
+    assert_eq!(hits.len(), 4);
+}
+
+#[test]
+fn test_text_search_needs_text_fields() {
+    let db = Database::new();
+    db.create_collection(
+        "plain",
+        Config {
+            dimensions: DIMS,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let collection = db.get_collection("plain").unwrap();
+    assert!(collection.search_text("anything", 10, None).is_err());
+
+    let quantized = Config {
+        quantization: QuantizationType::SQ8,
+        ..config()
+    };
+    assert!(db.create_collection("q", quantized).is_err());
+}
+
+#[test]
+fn test_text_index_rebuilt_on_reopen() {
+    let dir = tempdir().unwrap();
+    {
+        let db = Database::open(dir.path()).unwrap();
+        fill(&db);
+        let collection = db.get_collection("docs").unwrap();
+        collection.delete("c").unwrap();
+    }
+
+    let db = Database::open(dir.path()).unwrap();
+    let collection = db.get_collection("docs").unwrap();
+    assert_eq!(collection.config().text_fields, vec!["title", "tags"]);
+    let (hits, _) = collection.search_text("vector search", 10, None).unwrap();
+    assert_eq!(ids(&hits), vec!["a"]);
+}
@@ -0,0 +1,113 @@
+use surgedb_core::{
+    CollectionUpdate, Config, Database, DistanceMetric, Error, SearchParams, VectorTransform,
+};
+use tempfile::tempdir;
+
+fn config() -> Config {
+    Config::builder(2)
+        .distance_metric(DistanceMetric::Euclidean)
+        .build()
+        .unwrap()
+}
+
+fn nearest(db: &Database, query: &[f32]) -> (String, f32) {
+    let collection = db.get_collection("c").unwrap();
+    let (hits, _) = collection
+        .search_with_params(query, 1, None, SearchParams::default())
+        .unwrap();
+    (hits[0].0.to_string(), hits[0].1)
+}
+
+#[test]
+fn test_query_transform() {
+    let db = Database::new();
+    db.create_collection("c", config()).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    collection
+        .insert("origin".into(), &[0.0, 0.0], None)
+        .unwrap();
+    collection.insert("far".into(), &[10.0, 0.0], None).unwrap();
+    assert_eq!(nearest(&db, &[9.0, 0.0]).0, "far");
+
+    // Queries are centered; stored vectors stay as they are
+    let centering = VectorTransform::centering(vec![9.0, 0.0]);
+    db.update_collection(
+        "c",
+        CollectionUpdate {
+            transform: Some(Some(centering.clone())),
+            ..CollectionUpdate::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(nearest(&db, &[9.0, 0.0]), ("origin".to_string(), 0.0));
+    assert_eq!(collection.get("far").unwrap().unwrap().0, vec![10.0, 0.0]);
+    assert_eq!(collection.config().transform, Some(centering));
+
+    // Applying it on insert would leave the stored vectors untransformed
+    let on_insert = VectorTransform {
+        apply_on_insert: true,
+        ..VectorTransform::centering(vec![1.0, 0.0])
+    };
+    assert!(matches!(
+        db.update_collection(
+            "c",
+            CollectionUpdate {
+                transform: Some(Some(on_insert)),
+                ..CollectionUpdate::default()
+            },
+        ),
+        Err(Error::InvalidConfig(_))
+    ));
+
+    db.update_collection(
+        "c",
+        CollectionUpdate {
+            transform: Some(None),
+            ..CollectionUpdate::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(nearest(&db, &[9.0, 0.0]).0, "far");
+}
+
+#[test]
+fn test_transform_on_insert_persists() {
+    let dir = tempdir().unwrap();
+    // Swaps the axes of centered vectors
+    let transform = VectorTransform {
+        mean: Some(vec![1.0, 1.0]),
+        matrix: Some(vec![vec![0.0, 1.0], vec![1.0, 0.0]]),
+        apply_on_insert: true,
+    };
+    {
+        let db = Database::open(dir.path()).unwrap();
+        let config = Config {
+            transform: Some(transform.clone()),
+            ..config()
+        };
+        db.create_collection("c", config).unwrap();
+        let collection = db.get_collection("c").unwrap();
+        collection.insert("a".into(), &[4.0, 1.0], None).unwrap();
+        collection
+            .upsert_batch(vec![("b".into(), vec![1.0, 4.0], None)])
+            .unwrap();
+        assert_eq!(collection.get("a").unwrap().unwrap().0, vec![0.0, 3.0]);
+        assert_eq!(nearest(&db, &[4.0, 1.0]), ("a".to_string(), 0.0));
+    }
+
+    let db = Database::open(dir.path()).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    assert_eq!(collection.config().transform, Some(transform));
+    assert_eq!(collection.get("b").unwrap().unwrap().0, vec![3.0, 0.0]);
+    assert_eq!(nearest(&db, &[1.0, 4.0]), ("b".to_string(), 0.0));
+    assert!(matches!(
+        collection.search(&[1.0], 1, None),
+        Err(Error::DimensionMismatch { .. })
+    ));
+
+    let mismatched = Config {
+        transform: Some(VectorTransform::centering(vec![0.0; 3])),
+        ..config()
+    };
+    assert!(db.create_collection("d", mismatched).is_err());
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use common::vector;
+use surgedb_core::tune::TuneOptions;
+use surgedb_core::{Config, Database};
+use tempfile::tempdir;
+
+const DIMS: usize = 16;
+
+#[test]
+fn test_tune_collection_and_apply_ef_search() {
+    let dir = tempdir().unwrap();
+    {
+        let db = Database::open(dir.path()).unwrap();
+        db.create_collection(
+            "docs",
+            Config {
+                dimensions: DIMS,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        db.set_alias("live", "docs").unwrap();
+        let collection = db.get_collection("docs").unwrap();
+        for i in 0..300 {
+            collection
+                .insert(format!("v{i}"), &vector(i, DIMS), None)
+                .unwrap();
+        }
+
+        let options = TuneOptions {
+            sample_size: 200,
+            queries: 10,
+            k: 5,
+            target_recall: 0.8,
+            m: vec![8],
+            ef_construction: vec![64],
+            ef_search: vec![8, 64],
+            ..Default::default()
+        };
+        let report = collection.tune(&options).unwrap();
+        assert_eq!(report.trials.len(), 2);
+        assert_eq!(report.sample_size, 200);
+        assert_eq!(report.queries, 10);
+        // Tuning leaves the collection alone
+        assert_eq!(collection.stats().vector_count, 300);
+
+        db.set_ef_search("live", report.recommended.ef_search)
+            .unwrap();
+        assert_eq!(
+            collection.config().hnsw.ef_search,
+            report.recommended.ef_search
+        );
+        assert!(db.set_ef_search("docs", 0).is_err());
+        db.set_ef_search("docs", 77).unwrap();
+    }
+
+    // The new default survives a restart
+    let db = Database::open(dir.path()).unwrap();
+    let collection = db.get_collection("docs").unwrap();
+    assert_eq!(collection.config().hnsw.ef_search, 77);
+    assert_eq!(
+        collection.search(&vector(3, DIMS), 1, None).unwrap()[0]
+            .0
+            .as_str(),
+        "v3"
+    );
+}
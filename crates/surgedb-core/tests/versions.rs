@@ -0,0 +1,179 @@
+use serde_json::json;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use surgedb_core::{Config, Database, Error};
+use tempfile::tempdir;
+
+fn config() -> Config {
+    Config::builder(2).build().unwrap()
+}
+
+#[test]
+fn test_versions_grow_with_writes() {
+    let db = Database::new();
+    db.create_collection("c", config()).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    assert_eq!(collection.version("a").unwrap(), None);
+
+    collection.insert("a".into(), &[1.0, 0.0], None).unwrap();
+    collection.insert("b".into(), &[0.0, 1.0], None).unwrap();
+    let (vector, _, v1) = collection.get_versioned("a").unwrap().unwrap();
+    assert_eq!(vector, vec![1.0, 0.0]);
+
+    // Writes to other vectors leave the version alone
+    let b = collection.version("b").unwrap().unwrap();
+    collection.upsert("b".into(), &[0.0, 2.0], None).unwrap();
+    assert_eq!(collection.version("a").unwrap(), Some(v1));
+    assert!(collection.version("b").unwrap().unwrap() > b);
+
+    collection
+        .update_metadata("a", json!({"k": 1}), false)
+        .unwrap();
+    let v2 = collection.version("a").unwrap().unwrap();
+    assert!(v2 > v1);
+
+    // A vector deleted and written again gets a newer version
+    collection.delete("a").unwrap();
+    assert_eq!(collection.version("a").unwrap(), None);
+    collection.insert("a".into(), &[1.0, 1.0], None).unwrap();
+    assert!(collection.version("a").unwrap().unwrap() > v2);
+}
+
+#[test]
+fn test_conditional_writes() {
+    let db = Database::new();
+    db.create_collection("c", config()).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    collection.insert("a".into(), &[1.0, 0.0], None).unwrap();
+    let version = collection.version("a").unwrap().unwrap();
+
+    collection
+        .upsert_if_version("a".into(), &[2.0, 0.0], None, version)
+        .unwrap();
+    let err = collection
+        .upsert_if_version("a".into(), &[3.0, 0.0], None, version)
+        .unwrap_err();
+    let current = collection.version("a").unwrap().unwrap();
+    assert!(matches!(
+        err,
+        Error::VersionConflict { expected, actual: Some(actual), .. }
+            if expected == Some(version) && actual == current
+    ));
+    assert_eq!(err.error_code(), 1009);
+    assert_eq!(collection.get("a").unwrap().unwrap().0, vec![2.0, 0.0]);
+
+    assert!(collection.delete_if_version("a", version).is_err());
+    collection.delete_if_version("a", current).unwrap();
+    assert!(matches!(
+        collection.delete_if_version("a", current),
+        Err(Error::VersionConflict { actual: None, .. })
+    ));
+    assert!(collection
+        .upsert_if_version("a".into(), &[1.0, 0.0], None, current)
+        .is_err());
+}
+
+#[test]
+fn test_concurrent_conditional_writers() {
+    let db = Database::new();
+    db.create_collection("c", config()).unwrap();
+    let collection = Arc::new(db.get_collection("c").unwrap());
+    collection
+        .insert("counter".into(), &[1.0, 0.0], Some(json!({"n": 0})))
+        .unwrap();
+
+    // Each writer increments the counter with read-modify-write; retries on
+    // conflict mean no increment is lost
+    let conflicts = Arc::new(AtomicUsize::new(0));
+    let writers: Vec<_> = (0..4)
+        .map(|_| {
+            let collection = collection.clone();
+            let conflicts = conflicts.clone();
+            std::thread::spawn(move || {
+                for _ in 0..25 {
+                    loop {
+                        let (vector, metadata, version) =
+                            collection.get_versioned("counter").unwrap().unwrap();
+                        let n = metadata.unwrap()["n"].as_u64().unwrap();
+                        match collection.upsert_if_version(
+                            "counter".into(),
+                            &vector,
+                            Some(json!({ "n": n + 1 })),
+                            version,
+                        ) {
+                            Ok(()) => break,
+                            Err(Error::VersionConflict { .. }) => {
+                                conflicts.fetch_add(1, Ordering::Relaxed);
+                            }
+                            Err(e) => panic!("{e}"),
+                        }
+                    }
+                }
+            })
+        })
+        .collect();
+    for writer in writers {
+        writer.join().unwrap();
+    }
+    let metadata = collection.get("counter").unwrap().unwrap().1.unwrap();
+    assert_eq!(metadata["n"], 100);
+}
+
+#[test]
+fn test_versions_stay_ahead_across_restarts() {
+    let dir = tempdir().unwrap();
+    let before = {
+        let db = Database::open(dir.path()).unwrap();
+        db.create_collection("c", config()).unwrap();
+        let collection = db.get_collection("c").unwrap();
+        collection.insert("a".into(), &[1.0, 0.0], None).unwrap();
+        collection.insert("b".into(), &[0.0, 1.0], None).unwrap();
+        collection.version("a").unwrap().unwrap()
+    };
+
+    let db = Database::open(dir.path()).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    let after = collection.version("a").unwrap().unwrap();
+    assert!(after >= before, "{after} < {before}");
+    collection
+        .upsert_if_version("a".into(), &[2.0, 0.0], None, after)
+        .unwrap();
+    assert!(collection.version("a").unwrap().unwrap() > after);
+}
+
+#[test]
+fn test_insert_if_absent() {
+    let dir = tempdir().unwrap();
+    let db = Database::open(dir.path()).unwrap();
+    db.create_collection("c", config()).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    assert!(!collection.contains("a"));
+
+    assert!(collection
+        .insert_if_absent("a".into(), &[1.0, 0.0], Some(json!({"k": 1})))
+        .unwrap());
+    assert!(collection.contains("a"));
+    let version = collection.version("a").unwrap().unwrap();
+
+    // An existing vector is left alone
+    assert!(!collection
+        .insert_if_absent("a".into(), &[0.0, 1.0], None)
+        .unwrap());
+    let (vector, metadata, after) = collection.get_versioned("a").unwrap().unwrap();
+    assert_eq!(vector, vec![1.0, 0.0]);
+    assert_eq!(metadata, Some(json!({"k": 1})));
+    assert_eq!(after, version);
+
+    // Skipped inserts don't reach the WAL
+    drop(collection);
+    drop(db);
+    let db = Database::open(dir.path()).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    assert_eq!(collection.len(), 1);
+    assert_eq!(collection.get("a").unwrap().unwrap().0, vec![1.0, 0.0]);
+    collection.delete("a").unwrap();
+    assert!(!collection.contains("a"));
+    assert!(collection
+        .insert_if_absent("a".into(), &[0.0, 1.0], None)
+        .unwrap());
+}
@@ -0,0 +1,137 @@
+use serde_json::json;
+use surgedb_core::filter::Filter;
+use surgedb_core::{Config, Database, PersistentConfig, PersistentVectorDb};
+use tempfile::tempdir;
+
+fn config() -> Config {
+    Config {
+        dimensions: 2,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_every_write_advances_seq() {
+    let db = Database::new();
+    db.create_collection("c", config()).unwrap();
+    let collection = db.get_collection("c").unwrap();
+    assert_eq!(collection.write_seq(), 0);
+
+    let mut last = 0;
+    let mut check = |collection: &surgedb_core::db::Collection| {
+        let seq = collection.write_seq();
+        assert!(seq > last, "{seq} did not advance past {last}");
+        last = seq;
+    };
+
+    collection
+        .insert("a".to_string(), &[1.0, 0.0], Some(json!({ "doc": 1 })))
+        .unwrap();
+    check(&collection);
+    collection
+        .upsert("a".to_string(), &[0.0, 1.0], Some(json!({ "doc": 1 })))
+        .unwrap();
+    check(&collection);
+    collection
+        .upsert_batch(vec![("b".to_string(), vec![1.0, 1.0], None)])
+        .unwrap();
+    check(&collection);
+    collection
+        .replace(&Filter::Exact("doc".to_string(), json!(1)), Vec::new())
+        .unwrap();
+    check(&collection);
+    collection.delete("b").unwrap();
+    check(&collection);
+
+    // Reads leave it alone
+    collection.search(&[1.0, 0.0], 5, None).unwrap();
+    assert_eq!(collection.write_seq(), last);
+    assert_eq!(collection.stats().write_seq, last);
+    assert_eq!(collection.stats().log_id, None);
+}
+
+#[test]
+fn test_persistent_seq_survives_restart() {
+    let dir = tempdir().unwrap();
+    let config = PersistentConfig {
+        dimensions: 2,
+        ..Default::default()
+    };
+
+    let seq_before = {
+        let mut db = PersistentVectorDb::open(dir.path(), config.clone()).unwrap();
+        db.insert("a", &[1.0, 0.0], None).unwrap();
+        db.checkpoint().unwrap();
+        for i in 0..5 {
+            db.insert(format!("v{i}"), &[i as f32, 1.0], None).unwrap();
+        }
+        db.write_seq()
+    };
+
+    // While the tail replays, only the replayed writes count as visible
+    let (mut db, mut pending) = PersistentVectorDb::open_deferred(dir.path(), config).unwrap();
+    let replaying = db.write_seq();
+    assert!(replaying < seq_before);
+    db.replay(&mut pending, 2).unwrap();
+    assert_eq!(db.write_seq(), replaying + 2);
+    db.replay(&mut pending, usize::MAX).unwrap();
+    assert_eq!(db.write_seq(), seq_before);
+
+    db.insert("b", &[0.0, 0.0], None).unwrap();
+    assert!(db.write_seq() > seq_before);
+}
+
+#[test]
+fn test_catalog_changes_advance_version() {
+    let db = Database::new();
+    let mut last = db.catalog_version();
+    let mut check = |db: &Database| {
+        let version = db.catalog_version();
+        assert!(version > last, "{version} did not advance past {last}");
+        last = version;
+    };
+
+    db.create_collection("c", config()).unwrap();
+    check(&db);
+    db.set_alias("live", "c").unwrap();
+    check(&db);
+    db.set_ef_search("c", 50).unwrap();
+    check(&db);
+    db.delete_alias("live").unwrap();
+    check(&db);
+    db.delete_collection("c").unwrap();
+    check(&db);
+
+    // Failed changes leave it alone
+    assert!(db.delete_collection("c").is_err());
+    assert!(db.set_alias("live", "c").is_err());
+    assert_eq!(db.catalog_version(), last);
+}
+
+#[test]
+fn test_persistent_seq_survives_a_cleared_log() {
+    let dir = tempdir().unwrap();
+    let config = PersistentConfig {
+        dimensions: 2,
+        ..Default::default()
+    };
+
+    let seq_before = {
+        let mut db = PersistentVectorDb::open(dir.path(), config.clone()).unwrap();
+        for i in 0..5 {
+            db.insert(format!("v{i}"), &[i as f32, 1.0], None).unwrap();
+        }
+        db.checkpoint().unwrap();
+        db.write_seq()
+    };
+    // As if the process died right after the checkpoint cleared the log
+    let wal = dir.path().join("wal").join("current.wal");
+    let header = std::fs::read(&wal).unwrap()[..5].to_vec();
+    std::fs::write(&wal, header).unwrap();
+
+    let mut db = PersistentVectorDb::open(dir.path(), config).unwrap();
+    assert_eq!(db.len(), 5);
+    assert!(db.write_seq() >= seq_before);
+    db.insert("b", &[0.0, 0.0], None).unwrap();
+    assert!(db.write_seq() > seq_before);
+}
@@ -39,6 +39,9 @@ fn main() {
         }
     }
 
+    #[cfg(feature = "grpc")]
+    compile_protos();
+
     // 2. Ensure the dist directory exists so rust-embed doesn't crash the build
     if !dist_dir.exists() {
         fs::create_dir_all(dist_dir).unwrap();
@@ -50,3 +53,17 @@ fn main() {
         fs::write(index_path, "<html><body><h1>SurgeDB UI not built</h1><p>Please run 'npm install && npm run build' in the ui directory.</p></body></html>").unwrap();
     }
 }
+
+/// Generate the gRPC service from `proto/`, with a bundled protoc so no
+/// system install is needed
+#[cfg(feature = "grpc")]
+fn compile_protos() {
+    println!("cargo:rerun-if-changed=proto/surgedb.proto");
+    let protoc =
+        protoc_bin_vendored::protoc_bin_path().expect("no bundled protoc for this platform");
+    std::env::set_var("PROTOC", protoc);
+    tonic_build::configure()
+        .build_client(false)
+        .compile_protos(&["proto/surgedb.proto"], &["proto"])
+        .expect("failed to compile proto/surgedb.proto");
+}
@@ -0,0 +1,391 @@
+//! Backups to object storage
+//!
+//! A backup is a snapshot file of each collection plus a manifest, uploaded
+//! under `<BACKUP_URL>/<id>/`. Any store `object_store` supports can hold
+//! them: S3 and S3-compatible stores such as MinIO (`s3://bucket/prefix`),
+//! Google Cloud Storage (`gs://bucket/prefix`) or a local directory
+//! (`file:///path`). Credentials and endpoints come from the usual `AWS_*`
+//! and `GOOGLE_*` variables. Each collection is snapshotted under its own
+//! read lock, so it is consistent in itself, at the moment its turn comes.
+//!
+//! The manifest is uploaded last and deleted first, so a backup that was cut
+//! short is never listed or restored. With `BACKUP_INTERVAL_SECS` set,
+//! backups are also taken on a schedule, and the oldest scheduled ones
+//! beyond `BACKUP_RETAIN` are deleted. Backups taken on request are kept
+//! until deleted by hand.
+
+use chrono::{DateTime, Utc};
+use futures_util::{StreamExt, TryStreamExt};
+use object_store::buffered::BufWriter;
+use object_store::path::Path;
+use object_store::{ObjectStore, PutPayload};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use surgedb_core::Database;
+use tokio::io::AsyncWriteExt;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Where backups go and how often they are taken
+#[derive(Clone, Debug)]
+pub struct BackupConfig {
+    /// Object store URL backups are written under
+    pub url: String,
+    /// Seconds between scheduled backups; none are scheduled if zero
+    pub interval_secs: u64,
+    /// Scheduled backups kept; all are kept if zero
+    pub retain: usize,
+}
+
+impl BackupConfig {
+    /// Settings from the variables `var` looks up; `None` without `BACKUP_URL`
+    pub fn from_vars(var: impl Fn(&str) -> Result<String, std::env::VarError>) -> Option<Self> {
+        let url = var("BACKUP_URL").ok().filter(|v| !v.trim().is_empty())?;
+        Some(Self {
+            url,
+            interval_secs: var("BACKUP_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0),
+            retain: var("BACKUP_RETAIN")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(7),
+        })
+    }
+}
+
+/// A collection as saved in a backup
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct BackedUpCollection {
+    pub name: String,
+    pub vectors: usize,
+    /// Size of its snapshot file
+    pub bytes: u64,
+}
+
+/// What a backup holds; stored next to its snapshot files
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct BackupManifest {
+    /// Creation time in UTC, as `YYYYMMDDTHHMMSSmmmZ`, so IDs sort by age
+    pub id: String,
+    pub created_at: DateTime<Utc>,
+    /// Whether the backup was taken on the `BACKUP_INTERVAL_SECS` schedule
+    pub scheduled: bool,
+    pub collections: Vec<BackedUpCollection>,
+}
+
+/// A collection created from a backup
+#[derive(Serialize, ToSchema)]
+pub struct RestoredCollection {
+    pub name: String,
+    pub vectors: usize,
+}
+
+/// Uploads backups to an object store and restores them
+pub struct BackupStore {
+    store: Arc<dyn ObjectStore>,
+    /// Path under which each backup has its own directory
+    prefix: Path,
+    /// Where snapshot files are staged on their way to and from the store
+    scratch_dir: PathBuf,
+    /// Held while a backup or restore runs, so they never overlap
+    running: tokio::sync::Mutex<()>,
+}
+
+impl BackupStore {
+    /// Store at `config.url`, staging files in `scratch_dir`
+    pub fn new(config: &BackupConfig, scratch_dir: PathBuf) -> Result<Self, String> {
+        let url = Url::parse(&config.url).map_err(|e| format!("Invalid BACKUP_URL: {}", e))?;
+        // Builders take their options in lower case, like `aws_endpoint`
+        let options = std::env::vars()
+            .filter(|(key, _)| key.starts_with("AWS_") || key.starts_with("GOOGLE_"))
+            .map(|(key, value)| (key.to_ascii_lowercase(), value));
+        let (store, prefix) = object_store::parse_url_opts(&url, options)
+            .map_err(|e| format!("Invalid BACKUP_URL: {}", e))?;
+        Ok(Self {
+            store: Arc::from(store),
+            prefix,
+            scratch_dir,
+            running: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    /// Back up `names`, or every collection if `None`
+    pub async fn create(
+        &self,
+        db: &Arc<Database>,
+        names: Option<Vec<String>>,
+        scheduled: bool,
+    ) -> Result<BackupManifest, String> {
+        let _running = self.running.lock().await;
+        let created_at = Utc::now();
+        let id = created_at.format("%Y%m%dT%H%M%S%3fZ").to_string();
+        let names = names.unwrap_or_else(|| db.list_collections());
+        tokio::fs::create_dir_all(&self.scratch_dir)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let mut collections = Vec::with_capacity(names.len());
+        for name in names {
+            let collection = db.get_collection(&name).map_err(|e| e.to_string())?;
+            let file = self
+                .scratch_dir
+                .join(format!(".backup-{}-{}.snap", id, name));
+            let staged = file.clone();
+            let vectors = tokio::task::spawn_blocking(move || collection.snapshot(&staged))
+                .await
+                .map_err(|e| e.to_string())?
+                .map_err(|e| e.to_string());
+            let uploaded = match vectors {
+                Ok(vectors) => self
+                    .upload(&file, &self.object(&id, &format!("{}.snap", name)))
+                    .await
+                    .map(|bytes| BackedUpCollection {
+                        name: name.clone(),
+                        vectors,
+                        bytes,
+                    }),
+                Err(e) => Err(e),
+            };
+            let _ = tokio::fs::remove_file(&file).await;
+            collections.push(uploaded.map_err(|e| format!("Backup of {} failed: {}", name, e))?);
+        }
+
+        let manifest = BackupManifest {
+            id,
+            created_at,
+            scheduled,
+            collections,
+        };
+        let bytes = serde_json::to_vec_pretty(&manifest).map_err(|e| e.to_string())?;
+        self.store
+            .put(
+                &self.object(&manifest.id, MANIFEST_FILE),
+                PutPayload::from(bytes),
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+        info!(
+            "Backup {} written with {} collections",
+            manifest.id,
+            manifest.collections.len()
+        );
+        Ok(manifest)
+    }
+
+    /// Every complete backup, oldest first
+    pub async fn list(&self) -> Result<Vec<BackupManifest>, String> {
+        let listing = self
+            .store
+            .list_with_delimiter(Some(&self.prefix))
+            .await
+            .map_err(|e| e.to_string())?;
+        let mut backups = Vec::new();
+        for dir in listing.common_prefixes {
+            let Some(id) = dir.filename() else {
+                continue;
+            };
+            if let Some(manifest) = self.get(id).await? {
+                backups.push(manifest);
+            }
+        }
+        backups.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(backups)
+    }
+
+    /// Manifest of backup `id`, if it exists and is complete
+    pub async fn get(&self, id: &str) -> Result<Option<BackupManifest>, String> {
+        if !is_backup_id(id) {
+            return Ok(None);
+        }
+        let bytes = match self.store.get(&self.object(id, MANIFEST_FILE)).await {
+            Ok(result) => result.bytes().await.map_err(|e| e.to_string())?,
+            Err(object_store::Error::NotFound { .. }) => return Ok(None),
+            Err(e) => return Err(e.to_string()),
+        };
+        serde_json::from_slice(&bytes)
+            .map(Some)
+            .map_err(|e| format!("Invalid manifest of backup {}: {}", id, e))
+    }
+
+    /// Create each collection of `manifest` in `names`, or all of them,
+    /// from its snapshot
+    ///
+    /// Collections are restored under their own names, which must be free.
+    /// Stops at the first collection that fails; those restored before it
+    /// are kept.
+    pub async fn restore(
+        &self,
+        db: &Arc<Database>,
+        manifest: &BackupManifest,
+        names: Option<Vec<String>>,
+    ) -> Result<Vec<RestoredCollection>, String> {
+        let _running = self.running.lock().await;
+        tokio::fs::create_dir_all(&self.scratch_dir)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let mut restored = Vec::new();
+        for saved in &manifest.collections {
+            if names
+                .as_ref()
+                .is_some_and(|names| !names.contains(&saved.name))
+            {
+                continue;
+            }
+            let file = self
+                .scratch_dir
+                .join(format!(".restore-{}-{}.snap", manifest.id, saved.name));
+            let result = match self
+                .download(
+                    &self.object(&manifest.id, &format!("{}.snap", saved.name)),
+                    &file,
+                )
+                .await
+            {
+                Ok(()) => {
+                    let (db, name, staged) = (db.clone(), saved.name.clone(), file.clone());
+                    tokio::task::spawn_blocking(move || db.restore(&name, &staged))
+                        .await
+                        .map_err(|e| e.to_string())
+                        .and_then(|result| result.map_err(|e| e.to_string()))
+                }
+                Err(e) => Err(e),
+            };
+            let _ = tokio::fs::remove_file(&file).await;
+            let vectors = result.map_err(|e| format!("Restore of {} failed: {}", saved.name, e))?;
+            info!(
+                "Collection {} restored from backup {} ({} vectors)",
+                saved.name, manifest.id, vectors
+            );
+            restored.push(RestoredCollection {
+                name: saved.name.clone(),
+                vectors,
+            });
+        }
+        Ok(restored)
+    }
+
+    /// Delete the oldest scheduled backups beyond the newest `retain`
+    pub async fn prune(&self, retain: usize) -> Result<(), String> {
+        let scheduled: Vec<_> = self
+            .list()
+            .await?
+            .into_iter()
+            .filter(|backup| backup.scheduled)
+            .collect();
+        let excess = scheduled.len().saturating_sub(retain);
+        for backup in &scheduled[..excess] {
+            self.delete(&backup.id).await?;
+            info!("Backup {} deleted", backup.id);
+        }
+        Ok(())
+    }
+
+    /// Delete backup `id`, its manifest first
+    async fn delete(&self, id: &str) -> Result<(), String> {
+        let manifest = self.object(id, MANIFEST_FILE);
+        self.store
+            .delete(&manifest)
+            .await
+            .map_err(|e| e.to_string())?;
+        let objects: Vec<_> = self
+            .store
+            .list(Some(&self.prefix.child(id)))
+            .map_ok(|meta| meta.location)
+            .try_collect()
+            .await
+            .map_err(|e| e.to_string())?;
+        for object in objects {
+            self.store
+                .delete(&object)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// Take a backup every `interval`, keeping the newest `retain` of them
+    pub fn start(
+        self: &Arc<Self>,
+        db: Arc<Database>,
+        interval: Duration,
+        retain: usize,
+    ) -> JoinHandle<()> {
+        let backups = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                if !db.recovery_status().is_ready() {
+                    continue;
+                }
+                if let Err(e) = backups.create(&db, None, true).await {
+                    warn!("Scheduled backup failed: {}", e);
+                    continue;
+                }
+                if retain > 0 {
+                    if let Err(e) = backups.prune(retain).await {
+                        warn!("Failed to delete old backups: {}", e);
+                    }
+                }
+            }
+        })
+    }
+
+    /// Path of `file` in backup `id`
+    fn object(&self, id: &str, file: &str) -> Path {
+        self.prefix.child(id).child(file)
+    }
+
+    /// Stream the local file at `from` to `to`; returns its size
+    async fn upload(&self, from: &std::path::Path, to: &Path) -> Result<u64, String> {
+        let mut file = tokio::fs::File::open(from)
+            .await
+            .map_err(|e| e.to_string())?;
+        let mut writer = BufWriter::new(self.store.clone(), to.clone());
+        match tokio::io::copy(&mut file, &mut writer).await {
+            Ok(bytes) => {
+                writer.shutdown().await.map_err(|e| e.to_string())?;
+                Ok(bytes)
+            }
+            Err(e) => {
+                let _ = writer.abort().await;
+                Err(e.to_string())
+            }
+        }
+    }
+
+    /// Stream `from` to the local file at `to`
+    async fn download(&self, from: &Path, to: &std::path::Path) -> Result<(), String> {
+        let mut stream = self
+            .store
+            .get(from)
+            .await
+            .map_err(|e| e.to_string())?
+            .into_stream();
+        let mut file = tokio::fs::File::create(to)
+            .await
+            .map_err(|e| e.to_string())?;
+        while let Some(chunk) = stream.next().await {
+            file.write_all(&chunk.map_err(|e| e.to_string())?)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        file.flush().await.map_err(|e| e.to_string())
+    }
+}
+
+/// Whether `id` has the shape of the IDs [`BackupStore::create`] assigns
+fn is_backup_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_digit() || b == b'T' || b == b'Z')
+}
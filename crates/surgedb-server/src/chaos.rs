@@ -0,0 +1,141 @@
+//! Fault injection for testing client retry and failover logic
+//!
+//! Only compiled with the `chaos` feature. Faults are configured at runtime
+//! through `/admin/chaos` and applied by a middleware in front of the API
+//! routes. Never enable this feature in production builds.
+
+use axum::{
+    extract::{Json, Request, State},
+    http::{Method, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use parking_lot::RwLock;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::ErrorResponse;
+
+/// Active fault configuration
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default)]
+#[serde(default)]
+pub struct ChaosConfig {
+    /// Extra latency added to every API request
+    pub latency_ms: u64,
+    /// Percentage (0-100) of write requests that fail with 503
+    pub write_failure_percent: u8,
+    /// Reject every write with 507 as if the disk were full
+    pub disk_full: bool,
+    /// Drop outgoing replication traffic
+    pub drop_replication: bool,
+}
+
+/// Shared handle to the current fault configuration
+#[derive(Default)]
+pub struct Chaos {
+    config: RwLock<ChaosConfig>,
+}
+
+impl Chaos {
+    pub fn config(&self) -> ChaosConfig {
+        *self.config.read()
+    }
+
+    /// Whether replication traffic should currently be dropped
+    pub fn drop_replication(&self) -> bool {
+        self.config.read().drop_replication
+    }
+}
+
+/// Add the `/admin/chaos` routes and the fault middleware to `router`
+///
+/// Must be applied before the auth layer so the admin routes stay protected.
+pub fn install<S>(router: Router<S>, chaos: Arc<Chaos>) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    warn!("Fault injection is enabled (chaos feature); do not use in production");
+
+    let admin = Router::new()
+        .route(
+            "/admin/chaos",
+            get(get_chaos).put(set_chaos).delete(reset_chaos),
+        )
+        .with_state(chaos.clone());
+
+    router
+        .merge(admin)
+        .layer(middleware::from_fn_with_state(chaos, fault_middleware))
+}
+
+async fn fault_middleware(State(chaos): State<Arc<Chaos>>, req: Request, next: Next) -> Response {
+    let path = req.uri().path();
+    if path.starts_with("/admin") {
+        return next.run(req).await;
+    }
+
+    let config = chaos.config();
+    if config.latency_ms > 0 {
+        tokio::time::sleep(Duration::from_millis(config.latency_ms)).await;
+    }
+
+    if is_write(req.method(), path) {
+        if config.disk_full {
+            return fault(StatusCode::INSUFFICIENT_STORAGE, "disk full");
+        }
+        if config.write_failure_percent > 0
+            && rand::thread_rng().gen_range(0..100) < config.write_failure_percent
+        {
+            return fault(StatusCode::SERVICE_UNAVAILABLE, "write failed");
+        }
+    }
+
+    next.run(req).await
+}
+
+/// Every mutating request except search, which is a POST but reads only
+fn is_write(method: &Method, path: &str) -> bool {
+    !matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS) && !path.ends_with("/search")
+}
+
+fn fault(status: StatusCode, what: &str) -> Response {
+    (
+        status,
+        Json(ErrorResponse {
+            error: format!("Injected fault: {}", what),
+        }),
+    )
+        .into_response()
+}
+
+async fn get_chaos(State(chaos): State<Arc<Chaos>>) -> Json<ChaosConfig> {
+    Json(chaos.config())
+}
+
+async fn set_chaos(
+    State(chaos): State<Arc<Chaos>>,
+    Json(payload): Json<ChaosConfig>,
+) -> Result<Json<ChaosConfig>, (StatusCode, Json<ErrorResponse>)> {
+    if payload.write_failure_percent > 100 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "write_failure_percent must be 0-100".to_string(),
+            }),
+        ));
+    }
+    *chaos.config.write() = payload;
+    info!("Fault injection updated: {:?}", payload);
+    Ok(Json(payload))
+}
+
+async fn reset_chaos(State(chaos): State<Arc<Chaos>>) -> Json<ChaosConfig> {
+    *chaos.config.write() = ChaosConfig::default();
+    info!("Fault injection cleared");
+    Json(ChaosConfig::default())
+}
@@ -0,0 +1,719 @@
+//! Collections sharded over a cluster of servers
+//!
+//! Only compiled with the `cluster` feature. Every server is started with the
+//! same `CLUSTER_NODES` list of `id=url` pairs and its own `CLUSTER_NODE_ID`.
+//! Clustered collections are managed under `/cluster`: writes are split by
+//! shard and committed through the Raft group of each shard, and a search
+//! asks one replica of every shard and merges the answers. Servers send each
+//! other Raft traffic with the `API_KEY` they all share.
+//!
+//! Servers can be labelled with their rack or zone in `CLUSTER_ZONES`, so the
+//! replicas of a shard are kept in distinct failure domains where possible,
+//! or, with `CLUSTER_PLACEMENT=strict`, always.
+
+use axum::{
+    extract::{DefaultBodyLimit, Extension, Json, Path, Query, State},
+    http::StatusCode,
+    routing::{post, put},
+    Router,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use surgedb_cluster::{
+    merge, ClusterNode, Command, Message, NodeId, Placement, PlacementViolation, Record, ShardHit,
+    ShardId, ShardMap, ShardStatus,
+};
+use surgedb_core::filter::Filter;
+use surgedb_core::Database;
+use tokio::sync::Notify;
+use tokio::task::{spawn_blocking, JoinHandle, JoinSet};
+use tracing::{debug, warn};
+
+use crate::{check_limit, collection_config, require_admin, AppState, Caller};
+use crate::{CollectionSettings, ErrorResponse};
+
+/// Interval of the Raft clock; elections time out after 10 to 20 ticks
+const TICK: Duration = Duration::from_millis(50);
+/// Longest a write waits to be committed and applied
+const COMMIT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Timeout of requests to other servers
+const PEER_TIMEOUT: Duration = Duration::from_secs(5);
+/// Timeout of Raft messages carrying a whole shard to a lagging replica
+const SNAPSHOT_TIMEOUT: Duration = Duration::from_secs(120);
+/// How many times a write is passed on looking for a shard's leader
+const MAX_HOPS: u8 = 2;
+
+type ApiError = (StatusCode, Json<ErrorResponse>);
+
+/// Cluster settings, from `CLUSTER_*` variables
+#[derive(Clone, Debug)]
+pub struct ClusterConfig {
+    node_id: NodeId,
+    /// URL of every server, this one included
+    nodes: BTreeMap<NodeId, String>,
+    shards: u32,
+    replication: usize,
+    /// Failure domain of each labelled server
+    zones: BTreeMap<NodeId, String>,
+    placement: Placement,
+}
+
+impl ClusterConfig {
+    /// Settings from the variables `var` looks up; `None` without `CLUSTER_NODE_ID`
+    pub fn from_vars(var: impl Fn(&str) -> Result<String, std::env::VarError>) -> Option<Self> {
+        let node_id = var("CLUSTER_NODE_ID").ok()?.trim().parse().ok()?;
+        let nodes: BTreeMap<NodeId, String> = var("CLUSTER_NODES")
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|entry| {
+                let (id, url) = entry.split_once('=')?;
+                Some((
+                    id.trim().parse().ok()?,
+                    url.trim().trim_end_matches('/').to_string(),
+                ))
+            })
+            .collect();
+        let replication = var("CLUSTER_REPLICATION")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(nodes.len().clamp(1, 3));
+        Some(Self {
+            node_id,
+            nodes,
+            shards: var("CLUSTER_SHARDS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(8),
+            replication,
+            zones: var("CLUSTER_ZONES")
+                .unwrap_or_default()
+                .split(',')
+                .filter_map(|entry| {
+                    let (id, zone) = entry.split_once('=')?;
+                    Some((id.trim().parse().ok()?, zone.trim().to_string()))
+                })
+                .filter(|(_, zone)| !zone.is_empty())
+                .collect(),
+            placement: match var("CLUSTER_PLACEMENT").as_deref().map(str::trim) {
+                Ok("strict") => Placement::Strict,
+                _ => Placement::Spread,
+            },
+        })
+    }
+}
+
+/// Raft traffic from one server to another
+#[derive(Serialize, Deserialize)]
+struct RaftBatch {
+    from: NodeId,
+    messages: Vec<(ShardId, Message<Command>)>,
+}
+
+#[derive(Deserialize)]
+struct ProposeQuery {
+    /// Servers the write already passed through
+    #[serde(default)]
+    hops: u8,
+}
+
+#[derive(Deserialize)]
+struct UpsertRequest {
+    vectors: Vec<Record>,
+}
+
+#[derive(Serialize)]
+struct UpsertResponse {
+    upserted: usize,
+}
+
+#[derive(Deserialize)]
+struct DeleteRequest {
+    ids: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ShardSearchRequest {
+    vector: Vec<f32>,
+    k: usize,
+    #[serde(default)]
+    filter: Option<Filter>,
+}
+
+#[derive(Serialize)]
+struct ClusterStatus {
+    node_id: NodeId,
+    nodes: BTreeMap<NodeId, String>,
+    shards: u32,
+    replication: usize,
+    /// Failure domain of each labelled server
+    zones: BTreeMap<NodeId, String>,
+    /// Shards with a replica on this server
+    local_shards: Vec<ShardStatus>,
+}
+
+#[derive(Serialize)]
+struct PlacementReport {
+    placement: Placement,
+    zones: BTreeMap<NodeId, String>,
+    /// Servers keeping each shard
+    shards: Vec<ShardPlacement>,
+    /// Shards with several replicas in one failure domain
+    violations: Vec<PlacementViolation>,
+}
+
+#[derive(Serialize)]
+struct ShardPlacement {
+    shard: ShardId,
+    replicas: Vec<NodeId>,
+}
+
+/// This server's place in the cluster
+pub struct Cluster {
+    node: ClusterNode,
+    urls: BTreeMap<NodeId, String>,
+    placement: Placement,
+    api_key: Option<String>,
+    client: reqwest::Client,
+    /// Wakes the message loop to send replies and new entries right away
+    outbox: Notify,
+    /// Wakes the loop applying committed entries
+    committed: Notify,
+}
+
+impl Cluster {
+    /// This server's node, keeping the Raft state of its shards in `raft_dir`
+    pub fn new(
+        config: ClusterConfig,
+        api_key: Option<String>,
+        db: Arc<Database>,
+        raft_dir: PathBuf,
+    ) -> surgedb_cluster::Result<Self> {
+        let map = ShardMap::new(
+            config.nodes.keys().copied().collect(),
+            config.shards,
+            config.replication,
+        )?
+        .with_domains(config.zones, config.placement)?;
+        Ok(Self {
+            node: ClusterNode::new(config.node_id, map, db, Some(raft_dir))?,
+            urls: config.nodes,
+            placement: config.placement,
+            api_key,
+            client: reqwest::Client::new(),
+            outbox: Notify::new(),
+            committed: Notify::new(),
+        })
+    }
+
+    /// Start the Raft clock and message delivery, and applying committed writes
+    pub fn start(self: &Arc<Self>) -> [JoinHandle<()>; 2] {
+        let cluster = self.clone();
+        let messages = tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(TICK);
+            loop {
+                tokio::select! {
+                    _ = ticks.tick() => cluster.node.tick(),
+                    _ = cluster.outbox.notified() => {}
+                }
+                cluster.send_messages();
+                cluster.committed.notify_one();
+            }
+        });
+        let cluster = self.clone();
+        let apply = tokio::spawn(async move {
+            loop {
+                cluster.committed.notified().await;
+                let node = cluster.clone();
+                if let Err(e) = spawn_blocking(move || node.node.apply_committed()).await {
+                    warn!("Applying cluster writes failed: {}", e);
+                }
+            }
+        });
+        [messages, apply]
+    }
+
+    /// Post the queued Raft messages, one batch per server, without waiting
+    fn send_messages(&self) {
+        let mut batches: BTreeMap<NodeId, Vec<(ShardId, Message<Command>)>> = BTreeMap::new();
+        for outgoing in self.node.take_messages() {
+            batches
+                .entry(outgoing.to)
+                .or_default()
+                .push((outgoing.shard, outgoing.message));
+        }
+        for (to, messages) in batches {
+            let Some(url) = self.urls.get(&to) else {
+                continue;
+            };
+            let timeout = if messages
+                .iter()
+                .any(|(_, m)| matches!(m, Message::Snapshot { .. }))
+            {
+                SNAPSHOT_TIMEOUT
+            } else {
+                TICK * 10
+            };
+            let request = self
+                .request(format!("{}/cluster/raft", url))
+                .timeout(timeout)
+                .json(&RaftBatch {
+                    from: self.node.id(),
+                    messages,
+                });
+            tokio::spawn(async move {
+                // Lost messages are resent by Raft
+                if let Err(e) = request.send().await.and_then(|r| r.error_for_status()) {
+                    debug!("Raft message to node {} failed: {}", to, e);
+                }
+            });
+        }
+    }
+
+    fn request(&self, url: String) -> reqwest::RequestBuilder {
+        let request = self.client.post(url);
+        match &self.api_key {
+            Some(key) => request.header("x-api-key", key),
+            None => request,
+        }
+    }
+
+    /// Commit `command` to `shard` and wait until it is applied here, or pass
+    /// it on to the shard's leader
+    async fn commit(&self, shard: ShardId, command: Command, hops: u8) -> Result<(), ApiError> {
+        let deadline = Instant::now() + COMMIT_TIMEOUT;
+        loop {
+            let leader = match self.node.propose(shard, command.clone()) {
+                Ok((term, index)) => {
+                    self.outbox.notify_one();
+                    match self.wait(shard, term, index, deadline).await? {
+                        Some(result) => {
+                            return result.map_err(|e| error(StatusCode::BAD_REQUEST, e))
+                        }
+                        // Lost to a new leader before it was committed
+                        None => continue,
+                    }
+                }
+                Err(surgedb_cluster::Error::NotLeader { leader }) => leader,
+                Err(surgedb_cluster::Error::UnknownShard(_)) => {
+                    self.node.map().replicas(shard).first().copied()
+                }
+                Err(e) => return Err(error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+            };
+            match leader {
+                Some(leader) if leader != self.node.id() && hops < MAX_HOPS => {
+                    return self.forward(leader, shard, &command, hops + 1).await;
+                }
+                _ if Instant::now() >= deadline => {
+                    return Err(error(
+                        StatusCode::SERVICE_UNAVAILABLE,
+                        format!("Shard {} has no leader; retry the write", shard),
+                    ));
+                }
+                // Wait for an election
+                _ => tokio::time::sleep(TICK).await,
+            }
+        }
+    }
+
+    /// Result of the entry at `term` and `index` once applied, or `None` if
+    /// it was replaced
+    async fn wait(
+        &self,
+        shard: ShardId,
+        term: u64,
+        index: u64,
+        deadline: Instant,
+    ) -> Result<Option<Result<(), String>>, ApiError> {
+        loop {
+            match self.node.outcome(shard, term, index) {
+                Ok(Some(result)) => return Ok(Some(result)),
+                Ok(None) if Instant::now() < deadline => {
+                    tokio::time::sleep(Duration::from_millis(5)).await
+                }
+                Ok(None) => {
+                    return Err(error(
+                        StatusCode::SERVICE_UNAVAILABLE,
+                        format!("Shard {} did not commit the write in time", shard),
+                    ))
+                }
+                Err(surgedb_cluster::Error::NotLeader { .. }) => return Ok(None),
+                Err(e) => return Err(error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+            }
+        }
+    }
+
+    async fn forward(
+        &self,
+        to: NodeId,
+        shard: ShardId,
+        command: &Command,
+        hops: u8,
+    ) -> Result<(), ApiError> {
+        let url = self.urls.get(&to).ok_or_else(|| {
+            error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("No URL for node {}", to),
+            )
+        })?;
+        let response = self
+            .request(format!("{}/cluster/shards/{}/propose", url, shard))
+            .query(&[("hops", hops)])
+            .timeout(COMMIT_TIMEOUT + PEER_TIMEOUT)
+            .json(command)
+            .send()
+            .await
+            .map_err(|e| unavailable(to, e))?;
+        if response.status().is_success() {
+            return Ok(());
+        }
+        let status =
+            StatusCode::from_u16(response.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY);
+        let message = match response.json::<ErrorResponse>().await {
+            Ok(body) => body.error,
+            Err(e) => e.to_string(),
+        };
+        Err(error(status, message))
+    }
+
+    /// Commit one command per shard, in parallel
+    async fn commit_all(
+        self: &Arc<Self>,
+        commands: impl IntoIterator<Item = (ShardId, Command)>,
+    ) -> Result<(), ApiError> {
+        let mut writes = JoinSet::new();
+        for (shard, command) in commands {
+            let cluster = self.clone();
+            writes.spawn(async move { cluster.commit(shard, command, 0).await });
+        }
+        let mut result = Ok(());
+        while let Some(write) = writes.join_next().await {
+            let write = write.map_err(|e| error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+            if let Err(e) = write.and_then(|w| w) {
+                result = result.and(Err(e));
+            }
+        }
+        result
+    }
+
+    /// Nearest neighbors in `shard`, from this server's replica if it has one
+    async fn search_shard(
+        self: &Arc<Self>,
+        collection: String,
+        shard: ShardId,
+        request: Arc<ShardSearchRequest>,
+    ) -> Result<Vec<ShardHit>, ApiError> {
+        let mut replicas = self.node.map().replicas(shard);
+        replicas.sort_by_key(|&id| id != self.node.id());
+        let mut last_error = None;
+        for replica in replicas {
+            let result = if replica == self.node.id() {
+                let (cluster, collection, request) =
+                    (self.clone(), collection.clone(), request.clone());
+                spawn_blocking(move || {
+                    cluster.node.search_shard(
+                        &collection,
+                        shard,
+                        &request.vector,
+                        request.k,
+                        request.filter.as_ref(),
+                    )
+                })
+                .await
+                .map_err(|e| error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+                .map_err(|e| error(StatusCode::BAD_REQUEST, e.to_string()))
+            } else {
+                self.search_remote(replica, &collection, shard, &request)
+                    .await
+            };
+            match result {
+                Ok(hits) => return Ok(hits),
+                // Every replica would reject the request
+                Err(e) if e.0 == StatusCode::BAD_REQUEST => return Err(e),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| {
+            error(
+                StatusCode::SERVICE_UNAVAILABLE,
+                format!("Shard {} has no replicas", shard),
+            )
+        }))
+    }
+
+    async fn search_remote(
+        &self,
+        replica: NodeId,
+        collection: &str,
+        shard: ShardId,
+        request: &ShardSearchRequest,
+    ) -> Result<Vec<ShardHit>, ApiError> {
+        let url = self.urls.get(&replica).ok_or_else(|| {
+            error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("No URL for node {}", replica),
+            )
+        })?;
+        let response = self
+            .request(format!(
+                "{}/cluster/collections/{}/shards/{}/search",
+                url, collection, shard
+            ))
+            .timeout(PEER_TIMEOUT)
+            .json(request)
+            .send()
+            .await
+            .map_err(|e| unavailable(replica, e))?;
+        if response.status() == reqwest::StatusCode::BAD_REQUEST {
+            let body = response
+                .json::<ErrorResponse>()
+                .await
+                .map_err(|e| unavailable(replica, e))?;
+            return Err(error(StatusCode::BAD_REQUEST, body.error));
+        }
+        response
+            .error_for_status()
+            .map_err(|e| unavailable(replica, e))?
+            .json()
+            .await
+            .map_err(|e| unavailable(replica, e))
+    }
+}
+
+/// Add the `/cluster` routes to `router`
+///
+/// Must be applied before the auth layer; the routes servers use to talk to
+/// each other need the admin key.
+pub fn install(router: Router<AppState>, cluster: Arc<Cluster>) -> Router<AppState> {
+    let routes = Router::new()
+        .route("/cluster/status", axum::routing::get(status))
+        .route("/cluster/placement", axum::routing::get(placement))
+        .route(
+            "/cluster/collections/:name",
+            put(create_collection).delete(delete_collection),
+        )
+        .route("/cluster/collections/:name/vectors", post(upsert_vectors))
+        .route(
+            "/cluster/collections/:name/vectors/delete",
+            post(delete_vectors),
+        )
+        .route("/cluster/collections/:name/search", post(search))
+        // Snapshots for lagging replicas carry a whole shard
+        .route(
+            "/cluster/raft",
+            post(receive_raft).layer(DefaultBodyLimit::disable()),
+        )
+        .route("/cluster/shards/:shard/propose", post(propose))
+        .route(
+            "/cluster/collections/:name/shards/:shard/search",
+            post(search_shard),
+        )
+        .layer(Extension(cluster));
+    router.merge(routes)
+}
+
+async fn status(Extension(cluster): Extension<Arc<Cluster>>) -> Json<ClusterStatus> {
+    let map = cluster.node.map();
+    Json(ClusterStatus {
+        node_id: cluster.node.id(),
+        nodes: cluster.urls.clone(),
+        shards: map.shards(),
+        replication: map.replication(),
+        zones: map.domains().clone(),
+        local_shards: cluster.node.status(),
+    })
+}
+
+async fn placement(Extension(cluster): Extension<Arc<Cluster>>) -> Json<PlacementReport> {
+    let map = cluster.node.map();
+    Json(PlacementReport {
+        placement: cluster.placement,
+        zones: map.domains().clone(),
+        shards: (0..map.shards())
+            .map(|shard| ShardPlacement {
+                shard,
+                replicas: map.replicas(shard),
+            })
+            .collect(),
+        violations: map.violations(),
+    })
+}
+
+async fn create_collection(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Extension(cluster): Extension<Arc<Cluster>>,
+    Path(name): Path<String>,
+    Json(settings): Json<CollectionSettings>,
+) -> Result<(StatusCode, &'static str), ApiError> {
+    let limits = state.limits.effective(caller.key_name.as_deref());
+    let config = collection_config(settings, &limits)?;
+    let shards = cluster.node.map().shards();
+    cluster
+        .commit_all((0..shards).map(|shard| {
+            let command = Command::CreateCollection {
+                name: name.clone(),
+                config: Box::new(config.clone()),
+            };
+            (shard, command)
+        }))
+        .await?;
+    Ok((StatusCode::CREATED, "Created"))
+}
+
+async fn delete_collection(
+    Extension(cluster): Extension<Arc<Cluster>>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    let shards = cluster.node.map().shards();
+    cluster
+        .commit_all((0..shards).map(|shard| {
+            let command = Command::DeleteCollection { name: name.clone() };
+            (shard, command)
+        }))
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn upsert_vectors(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Extension(cluster): Extension<Arc<Cluster>>,
+    Path(name): Path<String>,
+    Json(payload): Json<UpsertRequest>,
+) -> Result<Json<UpsertResponse>, ApiError> {
+    let limits = state.limits.effective(caller.key_name.as_deref());
+    check_limit("Batch size", payload.vectors.len(), limits.max_batch_size)?;
+    let upserted = payload.vectors.len();
+    let mut by_shard: BTreeMap<ShardId, Vec<Record>> = BTreeMap::new();
+    for record in payload.vectors {
+        let shard = cluster.node.map().shard_of(&record.id);
+        by_shard.entry(shard).or_default().push(record);
+    }
+    cluster
+        .commit_all(by_shard.into_iter().map(|(shard, items)| {
+            let command = Command::Upsert {
+                collection: name.clone(),
+                items,
+            };
+            (shard, command)
+        }))
+        .await?;
+    Ok(Json(UpsertResponse { upserted }))
+}
+
+async fn delete_vectors(
+    Extension(cluster): Extension<Arc<Cluster>>,
+    Path(name): Path<String>,
+    Json(payload): Json<DeleteRequest>,
+) -> Result<StatusCode, ApiError> {
+    let mut by_shard: BTreeMap<ShardId, Vec<String>> = BTreeMap::new();
+    for id in payload.ids {
+        by_shard
+            .entry(cluster.node.map().shard_of(&id))
+            .or_default()
+            .push(id);
+    }
+    cluster
+        .commit_all(by_shard.into_iter().map(|(shard, ids)| {
+            let command = Command::Delete {
+                collection: name.clone(),
+                ids,
+            };
+            (shard, command)
+        }))
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Search every shard and merge the results
+///
+/// Replicas that don't lead their shard may not have applied the latest writes.
+async fn search(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Extension(cluster): Extension<Arc<Cluster>>,
+    Path(name): Path<String>,
+    Json(request): Json<ShardSearchRequest>,
+) -> Result<Json<Vec<ShardHit>>, ApiError> {
+    let limits = state.limits.effective(caller.key_name.as_deref());
+    check_limit("k", request.k, limits.max_k)?;
+    let k = request.k;
+    let request = Arc::new(request);
+    let mut searches = JoinSet::new();
+    for shard in 0..cluster.node.map().shards() {
+        let (cluster, name, request) = (cluster.clone(), name.clone(), request.clone());
+        searches.spawn(async move { cluster.search_shard(name, shard, request).await });
+    }
+    let mut results = Vec::new();
+    while let Some(search) = searches.join_next().await {
+        let hits = search.map_err(|e| error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        results.push(hits?);
+    }
+    Ok(Json(merge(results, k)))
+}
+
+async fn receive_raft(
+    Extension(caller): Extension<Caller>,
+    Extension(cluster): Extension<Arc<Cluster>>,
+    Json(batch): Json<RaftBatch>,
+) -> Result<StatusCode, ApiError> {
+    require_admin(&caller)?;
+    for (shard, message) in batch.messages {
+        if let Err(e) = cluster.node.step(shard, batch.from, message) {
+            warn!("Dropped Raft message from node {}: {}", batch.from, e);
+        }
+    }
+    cluster.outbox.notify_one();
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn propose(
+    Extension(caller): Extension<Caller>,
+    Extension(cluster): Extension<Arc<Cluster>>,
+    Path(shard): Path<ShardId>,
+    Query(query): Query<ProposeQuery>,
+    Json(command): Json<Command>,
+) -> Result<StatusCode, ApiError> {
+    require_admin(&caller)?;
+    cluster.commit(shard, command, query.hops).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn search_shard(
+    Extension(caller): Extension<Caller>,
+    Extension(cluster): Extension<Arc<Cluster>>,
+    Path((name, shard)): Path<(String, ShardId)>,
+    Json(request): Json<ShardSearchRequest>,
+) -> Result<Json<Vec<ShardHit>>, ApiError> {
+    require_admin(&caller)?;
+    spawn_blocking(move || {
+        cluster.node.search_shard(
+            &name,
+            shard,
+            &request.vector,
+            request.k,
+            request.filter.as_ref(),
+        )
+    })
+    .await
+    .map_err(|e| error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .map(Json)
+    .map_err(|e| match e {
+        surgedb_cluster::Error::UnknownShard(_) => error(StatusCode::NOT_FOUND, e.to_string()),
+        e => error(StatusCode::BAD_REQUEST, e.to_string()),
+    })
+}
+
+fn error(status: StatusCode, error: String) -> ApiError {
+    (status, Json(ErrorResponse { error }))
+}
+
+fn unavailable(node: NodeId, e: reqwest::Error) -> ApiError {
+    error(
+        StatusCode::SERVICE_UNAVAILABLE,
+        format!("Node {} is unreachable: {}", node, e),
+    )
+}
@@ -0,0 +1,357 @@
+//! Compaction scheduling
+//!
+//! Deleted and overwritten records keep their slots until the collection is
+//! compacted. A background task checks every collection periodically and,
+//! inside the configured low-traffic window (UTC), compacts those whose
+//! garbage passes the policy thresholds, one at a time. Collections can opt
+//! out of automatic runs or be compacted on demand. Overrides and the last run
+//! of each collection are saved to `compaction.json` in the data directory.
+
+use chrono::{DateTime, Timelike, Utc};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Instant;
+use surgedb_core::{Database, GarbageStats};
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+/// Daily time range in UTC, as `HH:MM-HH:MM`; may wrap past midnight
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CompactionWindow {
+    /// Minutes after midnight
+    start: u32,
+    end: u32,
+}
+
+impl CompactionWindow {
+    /// Whether `time` falls inside the window; equal bounds span the whole day
+    pub fn contains(&self, time: DateTime<Utc>) -> bool {
+        let minute = time.hour() * 60 + time.minute();
+        if self.start < self.end {
+            (self.start..self.end).contains(&minute)
+        } else {
+            minute >= self.start || minute < self.end
+        }
+    }
+}
+
+impl FromStr for CompactionWindow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let minutes = |t: &str| -> Option<u32> {
+            let (h, m) = t.trim().split_once(':')?;
+            let (h, m): (u32, u32) = (h.parse().ok()?, m.parse().ok()?);
+            (h < 24 && m < 60).then_some(h * 60 + m)
+        };
+        let invalid = || format!("Invalid compaction window '{}', expected HH:MM-HH:MM", s);
+        let (start, end) = s.split_once('-').ok_or_else(invalid)?;
+        Ok(Self {
+            start: minutes(start).ok_or_else(invalid)?,
+            end: minutes(end).ok_or_else(invalid)?,
+        })
+    }
+}
+
+impl std::fmt::Display for CompactionWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:02}:{:02}-{:02}:{:02}",
+            self.start / 60,
+            self.start % 60,
+            self.end / 60,
+            self.end % 60
+        )
+    }
+}
+
+/// When collections are compacted automatically
+#[derive(Clone, Debug)]
+pub struct CompactionPolicy {
+    /// Automatic runs only start inside this window; none without one
+    pub window: Option<CompactionWindow>,
+    /// Least share of slots held by tombstones worth compacting
+    pub min_tombstone_ratio: f64,
+    /// Least number of tombstones worth compacting
+    pub min_tombstones: usize,
+}
+
+impl CompactionPolicy {
+    /// Whether `garbage` is enough to compact for
+    fn warrants(&self, garbage: &GarbageStats) -> bool {
+        garbage.tombstones > 0
+            && garbage.tombstones >= self.min_tombstones
+            && garbage.tombstone_ratio >= self.min_tombstone_ratio
+    }
+}
+
+/// What started a compaction
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CompactionTrigger {
+    Manual,
+    Scheduled,
+}
+
+/// Outcome of one compaction
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct CompactionRun {
+    pub trigger: CompactionTrigger,
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: f64,
+    /// Live vectors kept
+    pub vectors: usize,
+    pub reclaimed_slots: usize,
+    pub reclaimed_vector_bytes: usize,
+    pub reclaimed_payload_bytes: usize,
+    /// Why the run failed, if it did
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Saved settings and history of one collection
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+struct CollectionCompaction {
+    /// Overrides whether automatic runs apply; they do by default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    auto: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_run: Option<CompactionRun>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct SetCompactionRequest {
+    /// Whether scheduled compaction applies to the collection; `null` restores
+    /// the default (enabled)
+    #[schema(example = false)]
+    pub auto: Option<bool>,
+}
+
+/// Garbage and compaction state of a collection
+#[derive(Serialize, ToSchema)]
+pub struct CompactionStatus {
+    pub garbage: GarbageStats,
+    /// Whether scheduled compaction applies to the collection
+    pub auto: bool,
+    /// Configured window in UTC, if automatic runs are scheduled at all
+    #[schema(example = "02:00-05:00")]
+    pub window: Option<String>,
+    pub min_tombstone_ratio: f64,
+    pub min_tombstones: usize,
+    /// Whether the garbage passes the thresholds, so the next check inside
+    /// the window compacts the collection
+    pub due: bool,
+    pub running: bool,
+    pub last_run: Option<CompactionRun>,
+}
+
+pub struct CompactionRegistry {
+    policy: CompactionPolicy,
+    collections: RwLock<HashMap<String, CollectionCompaction>>,
+    /// Collections being compacted right now
+    running: Mutex<HashSet<String>>,
+    path: Option<PathBuf>,
+}
+
+impl CompactionRegistry {
+    /// Create a registry, loading the settings saved at `path`
+    pub fn new(policy: CompactionPolicy, path: Option<PathBuf>) -> Self {
+        let collections = path
+            .as_ref()
+            .and_then(|p| std::fs::read(p).ok())
+            .and_then(|bytes| match serde_json::from_slice(&bytes) {
+                Ok(collections) => Some(collections),
+                Err(e) => {
+                    warn!("Ignoring unreadable compaction file: {}", e);
+                    None
+                }
+            })
+            .unwrap_or_default();
+
+        Self {
+            policy,
+            collections: RwLock::new(collections),
+            running: Mutex::new(HashSet::new()),
+            path,
+        }
+    }
+
+    fn auto(&self, collection: &str) -> bool {
+        self.collections
+            .read()
+            .get(collection)
+            .and_then(|c| c.auto)
+            .unwrap_or(true)
+    }
+
+    pub fn status(&self, collection: &str, garbage: GarbageStats) -> CompactionStatus {
+        let auto = self.auto(collection);
+        CompactionStatus {
+            due: auto && self.policy.warrants(&garbage),
+            garbage,
+            auto,
+            window: self.policy.window.map(|w| w.to_string()),
+            min_tombstone_ratio: self.policy.min_tombstone_ratio,
+            min_tombstones: self.policy.min_tombstones,
+            running: self.running.lock().contains(collection),
+            last_run: self
+                .collections
+                .read()
+                .get(collection)
+                .and_then(|c| c.last_run.clone()),
+        }
+    }
+
+    /// Override whether scheduled compaction applies to `collection`
+    pub fn set_auto(&self, collection: &str, auto: Option<bool>) -> Result<(), String> {
+        self.collections
+            .write()
+            .entry(collection.to_string())
+            .or_default()
+            .auto = auto;
+        self.save()
+    }
+
+    /// Compact `collection` now; `None` if it is already being compacted
+    ///
+    /// Failed runs are recorded too, and returned as the error.
+    pub async fn compact(
+        &self,
+        db: &Arc<Database>,
+        collection: &str,
+        trigger: CompactionTrigger,
+    ) -> Option<Result<CompactionRun, String>> {
+        if !self.running.lock().insert(collection.to_string()) {
+            return None;
+        }
+
+        let started_at = Utc::now();
+        let start = Instant::now();
+        let handle = db.clone();
+        let name = collection.to_string();
+        let result = tokio::task::spawn_blocking(move || {
+            handle
+                .get_collection(&name)
+                .and_then(|c| c.compact())
+                .map_err(|e| e.to_string())
+        })
+        .await
+        .unwrap_or_else(|e| Err(e.to_string()));
+        self.running.lock().remove(collection);
+
+        let mut run = CompactionRun {
+            trigger,
+            started_at,
+            duration_ms: start.elapsed().as_secs_f64() * 1000.0,
+            vectors: 0,
+            reclaimed_slots: 0,
+            reclaimed_vector_bytes: 0,
+            reclaimed_payload_bytes: 0,
+            error: None,
+        };
+        match &result {
+            Ok(report) => {
+                run.vectors = report.vectors;
+                run.reclaimed_slots = report.reclaimed_slots;
+                run.reclaimed_vector_bytes = report.reclaimed_vector_bytes;
+                run.reclaimed_payload_bytes = report.reclaimed_payload_bytes;
+                info!(
+                    "Compacted {} ({:?}): reclaimed {} slots in {:.1}ms",
+                    collection, trigger, report.reclaimed_slots, run.duration_ms
+                );
+            }
+            Err(e) => {
+                warn!("Compaction of {} failed: {}", collection, e);
+                run.error = Some(e.clone());
+            }
+        }
+
+        // The collection may have been deleted meanwhile
+        if db.get_collection(collection).is_ok() {
+            self.collections
+                .write()
+                .entry(collection.to_string())
+                .or_default()
+                .last_run = Some(run.clone());
+            if let Err(e) = self.save() {
+                warn!("{}", e);
+            }
+        }
+        Some(result.map(|_| run))
+    }
+
+    /// Compact the collections that are due, if the window is open
+    pub async fn run_due(&self, db: &Arc<Database>) {
+        let Some(window) = self.policy.window else {
+            return;
+        };
+        if !window.contains(Utc::now()) || crate::recovery_error(db, false).is_some() {
+            return;
+        }
+
+        let names = db.list_collections();
+        let db_clone = db.clone();
+        let garbage = tokio::task::spawn_blocking(move || {
+            names
+                .into_iter()
+                .filter_map(|name| {
+                    let garbage = db_clone.get_collection(&name).ok()?.garbage_stats();
+                    Some((name, garbage))
+                })
+                .collect::<Vec<_>>()
+        })
+        .await
+        .unwrap_or_default();
+
+        for (name, garbage) in garbage {
+            if !self.auto(&name) || !self.policy.warrants(&garbage) {
+                continue;
+            }
+            // A long run may outlast the window
+            if !window.contains(Utc::now()) {
+                break;
+            }
+            self.compact(db, &name, CompactionTrigger::Scheduled).await;
+        }
+    }
+
+    /// Keep the settings of a renamed collection under its new name
+    pub fn rename_collection(&self, from: &str, to: &str) -> Result<(), String> {
+        let moved = {
+            let mut collections = self.collections.write();
+            match collections.remove(from) {
+                Some(settings) => {
+                    collections.insert(to.to_string(), settings);
+                    true
+                }
+                None => false,
+            }
+        };
+        if moved {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Forget a deleted collection
+    pub fn remove_collection(&self, collection: &str) -> Result<(), String> {
+        if self.collections.write().remove(collection).is_some() {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let bytes =
+            serde_json::to_vec_pretty(&*self.collections.read()).map_err(|e| e.to_string())?;
+        std::fs::write(path, bytes).map_err(|e| format!("Failed to save compaction state: {}", e))
+    }
+}
@@ -0,0 +1,271 @@
+//! Response shapes of other vector databases, for migrating clients
+//!
+//! A client written for Qdrant or Pinecone can talk to SurgeDB's search and
+//! upsert endpoints while it moves to the native schema: the server reshapes
+//! the JSON of those responses into the other API's envelope, field names
+//! and score orientation. `camel` keeps the native shapes with camelCase
+//! field names. Metadata is passed through as stored.
+//!
+//! The format is set server-wide with `RESPONSE_FORMAT` and per request with
+//! the `X-Response-Format` header. Requests are read in the native schema
+//! either way; only responses change.
+
+use axum::http::{Method, StatusCode};
+use serde_json::{json, Map, Value};
+use std::time::Duration;
+use surgedb_core::DistanceMetric;
+
+/// Request header choosing the response format, overriding `RESPONSE_FORMAT`
+pub const FORMAT_HEADER: &str = "x-response-format";
+
+/// Shape of the search and upsert responses
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ResponseFormat {
+    #[default]
+    Native,
+    /// Native shapes with camelCase field names
+    Camel,
+    Qdrant,
+    Pinecone,
+}
+
+impl ResponseFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "native" => Some(Self::Native),
+            "camel" => Some(Self::Camel),
+            "qdrant" => Some(Self::Qdrant),
+            "pinecone" => Some(Self::Pinecone),
+            _ => None,
+        }
+    }
+}
+
+/// A reshaped endpoint
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Endpoint {
+    /// `POST /collections/:name/search`
+    Search,
+    /// `POST /collections/:name/vectors` and `/upsert`, one record
+    Upsert,
+    /// `POST /collections/:name/vectors/batch`
+    UpsertBatch,
+}
+
+/// The endpoint `path` is, and its collection
+pub fn endpoint<'a>(method: &Method, path: &'a str) -> Option<(Endpoint, &'a str)> {
+    if method != Method::POST {
+        return None;
+    }
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match segments.as_slice() {
+        ["collections", name, "search"] => Some((Endpoint::Search, name)),
+        ["collections", name, "vectors" | "upsert"] => Some((Endpoint::Upsert, name)),
+        ["collections", name, "vectors", "batch"] => Some((Endpoint::UpsertBatch, name)),
+        _ => None,
+    }
+}
+
+/// What a response is reshaped with besides its body
+pub struct Context {
+    pub status: StatusCode,
+    /// Metric of the searched space, so scores keep its orientation
+    pub metric: DistanceMetric,
+    /// Commit sequence of the collection after a write
+    pub commit_seq: Option<u64>,
+    /// Time spent handling the request
+    pub elapsed: Duration,
+}
+
+/// The native response `body` of `endpoint` in `format`
+///
+/// `body` is the response as JSON, or as a string if it is plain text.
+/// Returns `None` to send the response unchanged.
+pub fn reshape(
+    format: ResponseFormat,
+    endpoint: Endpoint,
+    body: Option<Value>,
+    context: &Context,
+) -> Option<Value> {
+    if !context.status.is_success() {
+        let body = body?;
+        let message = body
+            .get("error")
+            .and_then(Value::as_str)
+            .or_else(|| body.as_str())?
+            .to_string();
+        return error(format, context, message);
+    }
+    match format {
+        ResponseFormat::Native => None,
+        ResponseFormat::Camel => body
+            .filter(|body| body.is_object() || body.is_array())
+            .map(camel_case),
+        ResponseFormat::Qdrant => Some(qdrant(endpoint, body, context)),
+        ResponseFormat::Pinecone => Some(pinecone(endpoint, body, context)),
+    }
+}
+
+fn error(format: ResponseFormat, context: &Context, message: String) -> Option<Value> {
+    match format {
+        ResponseFormat::Native | ResponseFormat::Camel => None,
+        ResponseFormat::Qdrant => Some(json!({
+            "status": { "error": message },
+            "time": context.elapsed.as_secs_f64(),
+        })),
+        ResponseFormat::Pinecone => Some(json!({
+            "code": grpc_code(context.status),
+            "message": message,
+            "details": [],
+        })),
+    }
+}
+
+fn qdrant(endpoint: Endpoint, body: Option<Value>, context: &Context) -> Value {
+    let result = match endpoint {
+        Endpoint::Search => Value::Array(
+            search_results(body)
+                .into_iter()
+                .map(|hit| {
+                    let mut point = Map::new();
+                    point.insert("id".to_string(), qdrant_id(&hit));
+                    point.insert("version".to_string(), json!(0));
+                    point.insert("score".to_string(), score(context.metric, &hit));
+                    if let Some(metadata) = hit.get("metadata") {
+                        point.insert("payload".to_string(), metadata.clone());
+                    }
+                    if let Some(vector) = hit.get("vector") {
+                        point.insert("vector".to_string(), vector.clone());
+                    }
+                    Value::Object(point)
+                })
+                .collect(),
+        ),
+        Endpoint::Upsert | Endpoint::UpsertBatch => json!({
+            "operation_id": context.commit_seq,
+            "status": "completed",
+        }),
+    };
+    json!({
+        "result": result,
+        "status": "ok",
+        "time": context.elapsed.as_secs_f64(),
+    })
+}
+
+fn pinecone(endpoint: Endpoint, body: Option<Value>, context: &Context) -> Value {
+    match endpoint {
+        Endpoint::Search => {
+            let matches: Vec<Value> = search_results(body)
+                .into_iter()
+                .map(|hit| {
+                    let mut matched = Map::new();
+                    matched.insert("id".to_string(), hit["id"].clone());
+                    matched.insert("score".to_string(), score(context.metric, &hit));
+                    if let Some(vector) = hit.get("vector") {
+                        matched.insert("values".to_string(), vector.clone());
+                    }
+                    if let Some(metadata) = hit.get("metadata") {
+                        matched.insert("metadata".to_string(), metadata.clone());
+                    }
+                    Value::Object(matched)
+                })
+                .collect();
+            json!({ "matches": matches, "namespace": "" })
+        }
+        Endpoint::Upsert => json!({ "upsertedCount": 1 }),
+        Endpoint::UpsertBatch => {
+            let count = body
+                .as_ref()
+                .and_then(|body| body.as_u64().or_else(|| body.get("upserted")?.as_u64()))
+                .unwrap_or(0);
+            json!({ "upsertedCount": count })
+        }
+    }
+}
+
+/// Results of a native search response, with or without usage
+fn search_results(body: Option<Value>) -> Vec<Value> {
+    match body {
+        Some(Value::Array(results)) => results,
+        Some(Value::Object(mut response)) => match response.remove("results") {
+            Some(Value::Array(results)) => results,
+            _ => Vec::new(),
+        },
+        _ => Vec::new(),
+    }
+}
+
+/// The score as Qdrant and Pinecone orient it: the similarity for metrics
+/// measuring one, where higher is closer, and the distance for the others,
+/// where lower is
+fn score(metric: DistanceMetric, hit: &Value) -> Value {
+    match metric {
+        DistanceMetric::Cosine | DistanceMetric::DotProduct | DistanceMetric::Jaccard => {
+            hit["score"].clone()
+        }
+        DistanceMetric::Euclidean | DistanceMetric::Manhattan | DistanceMetric::Hamming => {
+            hit["distance"].clone()
+        }
+    }
+}
+
+/// Qdrant point IDs are unsigned integers or UUIDs, so numeric IDs are sent
+/// as numbers
+fn qdrant_id(hit: &Value) -> Value {
+    match hit["id"].as_str().and_then(|id| id.parse::<u64>().ok()) {
+        Some(id) => json!(id),
+        None => hit["id"].clone(),
+    }
+}
+
+/// gRPC status code Pinecone reports for an HTTP status
+fn grpc_code(status: StatusCode) -> u32 {
+    match status {
+        StatusCode::BAD_REQUEST | StatusCode::PAYLOAD_TOO_LARGE => 3,
+        StatusCode::REQUEST_TIMEOUT => 4,
+        StatusCode::NOT_FOUND => 5,
+        StatusCode::CONFLICT => 6,
+        StatusCode::FORBIDDEN => 7,
+        StatusCode::TOO_MANY_REQUESTS => 8,
+        StatusCode::SERVICE_UNAVAILABLE => 14,
+        StatusCode::UNAUTHORIZED => 16,
+        _ => 13,
+    }
+}
+
+/// `value` with its field names in camelCase, leaving metadata as stored
+fn camel_case(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| {
+                    let value = if key == "metadata" {
+                        value
+                    } else {
+                        camel_case(value)
+                    };
+                    (to_camel(&key), value)
+                })
+                .collect(),
+        ),
+        Value::Array(values) => Value::Array(values.into_iter().map(camel_case).collect()),
+        value => value,
+    }
+}
+
+fn to_camel(key: &str) -> String {
+    let mut camel = String::with_capacity(key.len());
+    let mut upper = false;
+    for c in key.chars() {
+        if c == '_' && !camel.is_empty() {
+            upper = true;
+        } else if upper {
+            camel.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            camel.push(c);
+        }
+    }
+    camel
+}
@@ -0,0 +1,546 @@
+//! Blue/green collection deployments behind an alias
+//!
+//! A deployment creates a new collection with the configuration of a template
+//! collection, imports vectors into it, checks it against the requested
+//! assertions and then repoints an alias to it, optionally dropping the
+//! collection the alias pointed to before. It runs as a background job. The
+//! alias swap is optimistic: it only happens if the alias still points where
+//! it did when the job started. Any failure rolls the job back, restoring the
+//! alias and dropping the new collection.
+//!
+//! Jobs are kept in memory. A shutdown interrupts imports at the next batch
+//! and saves them, with the vectors still to import, so the next start can
+//! resume them once the database has recovered. Jobs running when the
+//! process crashes are lost.
+
+use crate::webhooks::WebhookRegistry;
+use crate::{set_named_vectors, set_sparse_vectors, InsertRequest};
+use chrono::{DateTime, Utc};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use surgedb_core::Database;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+/// Vectors written per batch during the import step
+const IMPORT_BATCH_SIZE: usize = 1000;
+/// Finished jobs kept for inspection
+const MAX_FINISHED_JOBS: usize = 100;
+/// Queries sampled for the recall assertion
+const RECALL_SAMPLE_SIZE: usize = 50;
+/// k used for the recall assertion
+const RECALL_K: usize = 10;
+/// How long shutdown waits for jobs past their import to finish
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DeploymentRequest {
+    /// Alias to repoint to the new collection
+    #[schema(example = "docs")]
+    pub alias: String,
+    /// Name of the collection to create
+    #[schema(example = "docs_v2")]
+    pub target: String,
+    /// Collection whose configuration is copied; defaults to the alias's current collection
+    pub template: Option<String>,
+    /// Vectors imported into the new collection
+    #[serde(default)]
+    pub vectors: Vec<InsertRequest>,
+    #[serde(default)]
+    pub assertions: DeploymentAssertions,
+    /// Delete the previous collection once the alias points to the new one
+    #[serde(default)]
+    pub drop_previous: bool,
+}
+
+/// Checks the new collection must pass before the alias is swapped
+#[derive(Serialize, Deserialize, Default, ToSchema)]
+pub struct DeploymentAssertions {
+    /// Minimum number of vectors
+    pub min_count: Option<usize>,
+    /// Minimum vector count relative to the previous collection (e.g. 0.95)
+    pub min_count_ratio: Option<f64>,
+    /// Minimum estimated recall@10 (0.0-1.0)
+    pub min_recall: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DeploymentStatus {
+    Running,
+    Succeeded,
+    RolledBack,
+    /// Stopped by a shutdown; resumes when the server starts again
+    Interrupted,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DeploymentStep {
+    Creating,
+    Importing,
+    Validating,
+    Swapping,
+    DroppingPrevious,
+    Done,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct Deployment {
+    pub id: String,
+    pub alias: String,
+    pub target: String,
+    /// Collection the alias pointed to when the job started
+    pub previous: Option<String>,
+    pub status: DeploymentStatus,
+    /// Current step, or the step that failed for rolled back jobs
+    pub step: DeploymentStep,
+    pub imported: usize,
+    /// Recall measured by the `min_recall` assertion
+    pub recall: Option<f64>,
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// Everything a job needs, resolved before it is started
+struct Plan {
+    id: String,
+    request: DeploymentRequest,
+    config: surgedb_core::Config,
+    previous: Option<String>,
+    /// Whether the job was interrupted by a shutdown and is picking up again
+    resumed: bool,
+}
+
+/// A job interrupted by a shutdown, saved to be resumed
+#[derive(Serialize, Deserialize)]
+struct SavedDeployment {
+    job: Deployment,
+    /// The request, with only the vectors not imported yet
+    request: DeploymentRequest,
+    config: surgedb_core::Config,
+    previous: Option<String>,
+}
+
+/// Why a job stopped before finishing
+enum Halt {
+    /// Shutdown stopped the import after this many of the request's vectors
+    Interrupted(usize),
+    Failed(String),
+}
+
+impl From<String> for Halt {
+    fn from(error: String) -> Self {
+        Halt::Failed(error)
+    }
+}
+
+pub struct DeploymentRegistry {
+    jobs: RwLock<Vec<Deployment>>,
+    /// Where interrupted jobs are saved on shutdown
+    path: Option<PathBuf>,
+    /// Set on shutdown; imports stop before their next batch
+    stopping: AtomicBool,
+    /// Interrupted jobs, waiting to be saved or resumed
+    saved: Mutex<Vec<SavedDeployment>>,
+}
+
+impl DeploymentRegistry {
+    /// Create a registry, loading the jobs a shutdown saved at `path`
+    ///
+    /// The jobs are listed as interrupted until [`resume`](Self::resume)
+    /// restarts them. The file is removed once loaded.
+    pub fn new(path: Option<PathBuf>) -> Self {
+        let saved: Vec<SavedDeployment> = path
+            .as_ref()
+            .and_then(|p| std::fs::read(p).ok())
+            .and_then(|bytes| match serde_json::from_slice(&bytes) {
+                Ok(saved) => Some(saved),
+                Err(e) => {
+                    warn!("Ignoring unreadable deployments file: {}", e);
+                    None
+                }
+            })
+            .unwrap_or_default();
+        if let (Some(path), false) = (&path, saved.is_empty()) {
+            let _ = std::fs::remove_file(path);
+        }
+
+        Self {
+            jobs: RwLock::new(saved.iter().map(|s| s.job.clone()).collect()),
+            path,
+            stopping: AtomicBool::new(false),
+            saved: Mutex::new(saved),
+        }
+    }
+
+    /// Restart the interrupted jobs once `db` has recovered
+    pub fn resume(self: &Arc<Self>, db: Arc<Database>, webhooks: Arc<WebhookRegistry>) {
+        if self.saved.lock().is_empty() {
+            return;
+        }
+        let registry = self.clone();
+        tokio::spawn(async move {
+            if !crate::wait_for_recovery(&db).await {
+                warn!("Database recovery failed; interrupted deployments were not resumed");
+                return;
+            }
+            // Jobs still saved when a shutdown starts are saved again as they are
+            if registry.stopping.load(Ordering::SeqCst) {
+                return;
+            }
+            let saved = std::mem::take(&mut *registry.saved.lock());
+            for saved in saved {
+                let plan = Plan {
+                    id: saved.job.id.clone(),
+                    request: saved.request,
+                    config: saved.config,
+                    previous: saved.previous,
+                    resumed: true,
+                };
+                registry.update(&plan.id, |job| job.status = DeploymentStatus::Running);
+                let registry = registry.clone();
+                let db = db.clone();
+                let webhooks = webhooks.clone();
+                tokio::task::spawn_blocking(move || registry.run(&db, &webhooks, plan));
+            }
+        });
+    }
+
+    /// Interrupt running imports and save the interrupted jobs to be resumed
+    ///
+    /// Jobs past their import are given some time to finish; any still
+    /// running after that are lost.
+    pub async fn shutdown(&self) {
+        self.stopping.store(true, Ordering::SeqCst);
+        let deadline = Instant::now() + SHUTDOWN_TIMEOUT;
+        while self
+            .jobs
+            .read()
+            .iter()
+            .any(|j| j.status == DeploymentStatus::Running)
+        {
+            if Instant::now() >= deadline {
+                warn!("Deployments still running at shutdown will not be resumed");
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+
+        let Some(path) = &self.path else {
+            return;
+        };
+        let saved = self.saved.lock();
+        if saved.is_empty() {
+            return;
+        }
+        let result = serde_json::to_vec(&*saved)
+            .map_err(|e| e.to_string())
+            .and_then(|bytes| std::fs::write(path, bytes).map_err(|e| e.to_string()));
+        match result {
+            Ok(()) => info!("Saved {} interrupted deployments", saved.len()),
+            Err(e) => warn!("Failed to save interrupted deployments: {}", e),
+        }
+    }
+
+    /// Validate a deployment request and start it in the background
+    pub fn start(
+        self: &Arc<Self>,
+        db: Arc<Database>,
+        webhooks: Arc<WebhookRegistry>,
+        request: DeploymentRequest,
+    ) -> Result<Deployment, String> {
+        let aliases = db.list_aliases();
+        if db.list_collections().contains(&request.alias) {
+            return Err(format!(
+                "Alias name is already used by a collection: {}",
+                request.alias
+            ));
+        }
+        if db.get_collection(&request.target).is_ok() {
+            return Err(format!("Collection already exists: {}", request.target));
+        }
+        let previous = aliases
+            .iter()
+            .find(|(alias, _)| *alias == request.alias)
+            .map(|(_, collection)| collection.clone());
+
+        let template = request
+            .template
+            .as_deref()
+            .or(previous.as_deref())
+            .ok_or("A template is required when the alias does not exist yet")?;
+        let config = db
+            .get_collection(template)
+            .map_err(|e| e.to_string())?
+            .config();
+
+        if let (true, Some(previous)) = (request.drop_previous, &previous) {
+            if let Some((other, _)) = aliases
+                .iter()
+                .find(|(alias, c)| c == previous && *alias != request.alias)
+            {
+                return Err(format!(
+                    "Previous collection {} is also referenced by alias {}",
+                    previous, other
+                ));
+            }
+        }
+
+        let job = Deployment {
+            id: format!(
+                "dep_{:x}",
+                Utc::now().timestamp_nanos_opt().unwrap_or_default()
+            ),
+            alias: request.alias.clone(),
+            target: request.target.clone(),
+            previous: previous.clone(),
+            status: DeploymentStatus::Running,
+            step: DeploymentStep::Creating,
+            imported: 0,
+            recall: None,
+            error: None,
+            started_at: Utc::now(),
+            finished_at: None,
+        };
+        self.insert(job.clone());
+
+        let plan = Plan {
+            id: job.id.clone(),
+            request,
+            config,
+            previous,
+            resumed: false,
+        };
+        let registry = self.clone();
+        tokio::task::spawn_blocking(move || registry.run(&db, &webhooks, plan));
+        Ok(job)
+    }
+
+    pub fn get(&self, id: &str) -> Option<Deployment> {
+        self.jobs.read().iter().find(|j| j.id == id).cloned()
+    }
+
+    /// All known jobs, newest first
+    pub fn list(&self) -> Vec<Deployment> {
+        self.jobs.read().iter().rev().cloned().collect()
+    }
+
+    fn insert(&self, job: Deployment) {
+        let mut jobs = self.jobs.write();
+        let finished = jobs
+            .iter()
+            .filter(|j| j.status != DeploymentStatus::Running)
+            .count();
+        if finished >= MAX_FINISHED_JOBS {
+            if let Some(pos) = jobs
+                .iter()
+                .position(|j| j.status != DeploymentStatus::Running)
+            {
+                jobs.remove(pos);
+            }
+        }
+        jobs.push(job);
+    }
+
+    fn update(&self, id: &str, f: impl FnOnce(&mut Deployment)) {
+        if let Some(job) = self.jobs.write().iter_mut().find(|j| j.id == id) {
+            f(job);
+        }
+    }
+
+    fn set_step(&self, id: &str, step: DeploymentStep) {
+        self.update(id, |job| job.step = step);
+    }
+
+    fn run(&self, db: &Database, webhooks: &WebhookRegistry, plan: Plan) {
+        let Plan {
+            id,
+            mut request,
+            config,
+            previous,
+            resumed,
+        } = plan;
+        if resumed {
+            info!(
+                "Resuming deployment {}: {} -> {}",
+                id, request.alias, request.target
+            );
+        } else {
+            info!("Deployment {}: {} -> {}", id, request.alias, request.target);
+        }
+
+        let result = self.deploy(
+            db,
+            &id,
+            &request,
+            config.clone(),
+            previous.as_deref(),
+            resumed,
+        );
+        let (status, error) = match result {
+            Ok(()) => {
+                info!("Deployment {} succeeded", id);
+                if request.drop_previous {
+                    if let Some(previous) = &previous {
+                        if let Err(e) = webhooks.remove_collection(previous) {
+                            warn!("{}", e);
+                        }
+                    }
+                }
+                (DeploymentStatus::Succeeded, None)
+            }
+            Err(Halt::Interrupted(imported)) => {
+                info!("Deployment {} interrupted by shutdown", id);
+                request.vectors.drain(..imported);
+                self.update(&id, |job| job.status = DeploymentStatus::Interrupted);
+                if let Some(job) = self.get(&id) {
+                    self.saved.lock().push(SavedDeployment {
+                        job,
+                        request,
+                        config,
+                        previous,
+                    });
+                }
+                return;
+            }
+            Err(Halt::Failed(e)) => {
+                warn!("Deployment {} rolled back: {}", id, e);
+                // Nothing was created if the job failed while creating the collection
+                if self
+                    .get(&id)
+                    .is_some_and(|j| j.step != DeploymentStep::Creating)
+                {
+                    self.rollback(db, &request, previous.as_deref());
+                }
+                (DeploymentStatus::RolledBack, Some(e))
+            }
+        };
+
+        self.update(&id, |job| {
+            job.status = status;
+            if status == DeploymentStatus::Succeeded {
+                job.step = DeploymentStep::Done;
+            }
+            job.error = error;
+            job.finished_at = Some(Utc::now());
+        });
+    }
+
+    fn deploy(
+        &self,
+        db: &Database,
+        id: &str,
+        request: &DeploymentRequest,
+        config: surgedb_core::Config,
+        previous: Option<&str>,
+        resumed: bool,
+    ) -> Result<(), Halt> {
+        // An interrupted job usually created its collection before it stopped
+        if !(resumed && db.get_collection(&request.target).is_ok()) {
+            db.create_collection(&request.target, config)
+                .map_err(|e| e.to_string())?;
+        }
+        self.set_step(id, DeploymentStep::Importing);
+        let collection = db
+            .get_collection(&request.target)
+            .map_err(|e| e.to_string())?;
+
+        for (i, batch) in request.vectors.chunks(IMPORT_BATCH_SIZE).enumerate() {
+            if self.stopping.load(Ordering::SeqCst) {
+                return Err(Halt::Interrupted(i * IMPORT_BATCH_SIZE));
+            }
+            let items = batch
+                .iter()
+                .map(|v| {
+                    let vector = collection.convert_vector(&v.vector)?;
+                    Ok((v.id.clone(), vector, v.metadata.clone()))
+                })
+                .collect::<surgedb_core::Result<_>>()
+                .map_err(|e| e.to_string())?;
+            collection.upsert_batch(items).map_err(|e| e.to_string())?;
+            let sparse = batch
+                .iter()
+                .filter_map(|v| Some((v.id.clone(), v.sparse.clone()?)));
+            set_sparse_vectors(&collection, sparse).map_err(|e| e.to_string())?;
+            let named = batch
+                .iter()
+                .filter_map(|v| Some((v.id.clone(), v.vectors.clone()?)));
+            set_named_vectors(&collection, named).map_err(|e| e.to_string())?;
+            self.update(id, |job| job.imported += batch.len());
+        }
+
+        self.set_step(id, DeploymentStep::Validating);
+        let count = collection.stats().vector_count;
+        let assertions = &request.assertions;
+        if let Some(min) = assertions.min_count {
+            if count < min {
+                return Err(format!("Vector count {} is below min_count {}", count, min).into());
+            }
+        }
+        if let (Some(ratio), Some(previous)) = (assertions.min_count_ratio, previous) {
+            let previous_count = db
+                .get_collection(previous)
+                .map_err(|e| e.to_string())?
+                .stats()
+                .vector_count;
+            if (count as f64) < previous_count as f64 * ratio {
+                return Err(format!(
+                    "Vector count {} is below {} of the previous {}",
+                    count, ratio, previous_count
+                )
+                .into());
+            }
+        }
+        if let Some(min) = assertions.min_recall {
+            let recall = collection
+                .estimate_recall(RECALL_SAMPLE_SIZE, RECALL_K)
+                .map_err(|e| e.to_string())?
+                .unwrap_or(1.0);
+            self.update(id, |job| job.recall = Some(recall));
+            if recall < min {
+                return Err(
+                    format!("Estimated recall {:.3} is below min_recall {}", recall, min).into(),
+                );
+            }
+        }
+
+        self.set_step(id, DeploymentStep::Swapping);
+        db.swap_alias(&request.alias, &request.target, previous)
+            .map_err(|e| e.to_string())?;
+
+        if let (true, Some(previous)) = (request.drop_previous, previous) {
+            self.set_step(id, DeploymentStep::DroppingPrevious);
+            db.delete_collection(previous).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// Undo a failed deployment: point the alias back and drop the new collection
+    fn rollback(&self, db: &Database, request: &DeploymentRequest, previous: Option<&str>) {
+        let now_points_to_target = db
+            .list_aliases()
+            .iter()
+            .any(|(alias, c)| *alias == request.alias && *c == request.target);
+        if now_points_to_target {
+            let restored = match previous {
+                Some(previous) => db
+                    .swap_alias(&request.alias, previous, Some(&request.target))
+                    .map(|_| ()),
+                None => db.delete_alias(&request.alias).map(|_| ()),
+            };
+            if let Err(e) = restored {
+                warn!("Failed to restore alias {}: {}", request.alias, e);
+                return;
+            }
+        }
+        if let Err(e) = db.delete_collection(&request.target) {
+            warn!("Failed to drop {}: {}", request.target, e);
+        }
+    }
+}
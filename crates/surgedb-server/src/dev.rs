@@ -0,0 +1,222 @@
+//! Synthetic data for demos, load tests and UI development
+//!
+//! Only compiled with the `dev` feature. `POST /collections/:name/dev/seed`
+//! fills a collection with random vectors and metadata generated in the
+//! server, so no external data generator is needed. Seeded records go through
+//! the same limits, quota and mirrors as an upsert.
+
+use axum::{
+    extract::{Extension, Json, Path, State},
+    http::StatusCode,
+    routing::post,
+    Router,
+};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::task::spawn_blocking;
+use tracing::warn;
+
+use crate::mirror::Change;
+use crate::{
+    check_limit, mirror_target, store_items, write_limits, AppState, Caller, ErrorResponse,
+};
+use surgedb_core::IdType;
+
+/// Most records one seed request generates
+const MAX_SEED_COUNT: usize = 1_000_000;
+
+/// Records upserted per batch, unless the caller's `max_batch_size` is lower
+const SEED_BATCH_SIZE: usize = 1_000;
+
+/// Values of the synthetic `category` field
+const CATEGORIES: [&str; 5] = ["news", "sports", "science", "travel", "food"];
+
+/// How seeded vectors are spread out
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Distribution {
+    /// Every component uniform in [-1, 1]
+    #[default]
+    Uniform,
+    /// Gaussian blobs around `clusters` random centers, `spread` being the
+    /// standard deviation of each component
+    Clusters {
+        #[serde(default = "default_clusters")]
+        clusters: usize,
+        #[serde(default = "default_spread")]
+        spread: f32,
+    },
+}
+
+fn default_clusters() -> usize {
+    8
+}
+
+fn default_spread() -> f32 {
+    0.1
+}
+
+#[derive(Deserialize)]
+struct SeedRequest {
+    /// Records to generate
+    count: usize,
+    #[serde(default)]
+    distribution: Distribution,
+    /// Seed for the generator, to generate the same records again
+    seed: Option<u64>,
+    /// Prefix of the generated IDs, followed by the record's index; defaults
+    /// to `seed-`, or none for collections with `u64` IDs
+    id_prefix: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SeedResponse {
+    seeded: usize,
+}
+
+/// Add the seed route to `router`
+///
+/// Must be applied before the auth layer so seeding needs a write key.
+pub fn install(router: Router<AppState>) -> Router<AppState> {
+    warn!("Dev endpoints are enabled (dev feature); do not use in production");
+    router.route("/collections/:name/dev/seed", post(seed_collection))
+}
+
+async fn seed_collection(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path(name): Path<String>,
+    Json(payload): Json<SeedRequest>,
+) -> Result<Json<SeedResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let bad_request = |error: String| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error }));
+    let limits = write_limits(&state, &caller, &name);
+    check_limit("count", payload.count, MAX_SEED_COUNT)?;
+    if let Distribution::Clusters { clusters, spread } = payload.distribution {
+        if clusters == 0 || !spread.is_finite() || spread < 0.0 {
+            return Err(bad_request(
+                "clusters must be positive and spread non-negative".to_string(),
+            ));
+        }
+    }
+
+    let collection = state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    let config = collection.config();
+    let prefix = payload.id_prefix.unwrap_or_else(|| match config.id_type {
+        IdType::String => "seed-".to_string(),
+        IdType::U64 => String::new(),
+    });
+    let batch_size = SEED_BATCH_SIZE.min(limits.max_batch_size).max(1);
+    let mirror = mirror_target(&state, &name);
+    let mirrored = mirror.is_some();
+    let mirrors = state.mirrors.clone();
+
+    // Batches are mirrored as they are stored, so a failed batch leaves the
+    // mirrors with the same records as this collection
+    let count = payload.count;
+    let result = spawn_blocking(move || {
+        let _job = collection.begin_job();
+        let mut rng = match payload.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let generator = Generator::new(&mut rng, config.dimensions, payload.distribution);
+        let mut start = 0;
+        while start < count {
+            let end = (start + batch_size).min(count);
+            let items: Vec<_> = (start..end)
+                .map(|i| {
+                    let (vector, metadata) = generator.record(&mut rng, i);
+                    (format!("{}{}", prefix, i), vector, Some(metadata))
+                })
+                .collect();
+            let attached = vec![(None, None); items.len()];
+            let changed = store_items(&collection, items, attached, limits.max_vectors, mirrored)?;
+            if let (Some(target), Some(vectors)) = (&mirror, changed) {
+                mirrors.publish(target, Change::Upsert(vectors));
+            }
+            start = end;
+        }
+        Ok::<_, surgedb_core::Error>(())
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    result.map_err(|e| bad_request(e.to_string()))?;
+    Ok(Json(SeedResponse { seeded: count }))
+}
+
+/// Generates the vectors and metadata of seeded records
+struct Generator {
+    dimensions: usize,
+    distribution: Distribution,
+    centers: Vec<Vec<f32>>,
+}
+
+impl Generator {
+    fn new(rng: &mut impl Rng, dimensions: usize, distribution: Distribution) -> Self {
+        let centers = match distribution {
+            Distribution::Uniform => Vec::new(),
+            Distribution::Clusters { clusters, .. } => {
+                (0..clusters).map(|_| uniform(rng, dimensions)).collect()
+            }
+        };
+        Self {
+            dimensions,
+            distribution,
+            centers,
+        }
+    }
+
+    /// Vector and metadata of the `index`th record
+    fn record(&self, rng: &mut impl Rng, index: usize) -> (Vec<f32>, Value) {
+        let category = CATEGORIES[rng.gen_range(0..CATEGORIES.len())];
+        let score = (rng.gen::<f64>() * 100.0).round() / 100.0;
+        match self.distribution {
+            Distribution::Uniform => (
+                uniform(rng, self.dimensions),
+                json!({ "index": index, "category": category, "score": score }),
+            ),
+            Distribution::Clusters { spread, .. } => {
+                let cluster = rng.gen_range(0..self.centers.len());
+                let vector = self.centers[cluster]
+                    .iter()
+                    .map(|c| c + spread * gaussian(rng))
+                    .collect();
+                let metadata = json!({
+                    "index": index,
+                    "category": category,
+                    "score": score,
+                    "cluster": cluster,
+                });
+                (vector, metadata)
+            }
+        }
+    }
+}
+
+fn uniform(rng: &mut impl Rng, dimensions: usize) -> Vec<f32> {
+    (0..dimensions).map(|_| rng.gen_range(-1.0..=1.0)).collect()
+}
+
+/// Standard normal sample, by the Box-Muller transform
+fn gaussian(rng: &mut impl Rng) -> f32 {
+    let u1: f32 = 1.0 - rng.gen::<f32>();
+    let u2: f32 = rng.gen();
+    (-2.0 * u1.ln()).sqrt() * (std::f32::consts::TAU * u2).cos()
+}
@@ -0,0 +1,79 @@
+//! Conditional GETs with ETags built from version counters
+//!
+//! A tag combines the server's boot ID, the database's catalog version and,
+//! for reads of one collection, its commit sequence. It is taken before the
+//! data is read, so it may lag the body but never labels older data with a
+//! newer version.
+//!
+//! Conditional writes of a vector take its version in `If-Match` instead, or
+//! `If-None-Match: *` to write only a vector that doesn't exist yet.
+
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+
+/// Quoted ETag for the given version counters
+pub fn tag(versions: &[u64]) -> String {
+    let parts: Vec<String> = versions.iter().map(|v| format!("{:x}", v)).collect();
+    format!("\"{}\"", parts.join("-"))
+}
+
+/// Whether `If-None-Match` in `headers` lists `etag`
+///
+/// Uses the weak comparison RFC 9110 prescribes for `If-None-Match`.
+pub fn matches(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == etag)
+}
+
+/// Vector version a write is conditional on, from `If-Match`
+///
+/// The version may be quoted, as ETags are. `*` and a missing header
+/// make the write unconditional.
+pub fn if_match_version(headers: &HeaderMap) -> Result<Option<u64>, String> {
+    let Some(value) = headers.get(header::IF_MATCH) else {
+        return Ok(None);
+    };
+    let value = value
+        .to_str()
+        .map_err(|_| "If-Match is not valid text".to_string())?
+        .trim();
+    if value == "*" {
+        return Ok(None);
+    }
+    value
+        .trim_start_matches("W/")
+        .trim_matches('"')
+        .parse()
+        .map(Some)
+        .map_err(|_| format!("If-Match must be a vector version, got {}", value))
+}
+
+/// Whether `If-None-Match: *` in `headers` asks for a write only if the
+/// vector doesn't exist yet
+pub fn if_none_match_any(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.trim() == "*")
+}
+
+/// `304 Not Modified` for a client already holding `etag`
+pub fn not_modified(etag: &str) -> Response {
+    with_tag(StatusCode::NOT_MODIFIED, etag)
+}
+
+/// `body` tagged with `etag`; clients and caches must revalidate before reuse
+pub fn with_tag(body: impl IntoResponse, etag: &str) -> Response {
+    let mut response = body.into_response();
+    let headers = response.headers_mut();
+    if let Ok(value) = HeaderValue::from_str(etag) {
+        headers.insert(header::ETAG, value);
+    }
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    response
+}
@@ -0,0 +1,514 @@
+//! gRPC API next to the REST API
+//!
+//! Only compiled with the `grpc` feature and served on `GRPC_PORT`. The
+//! service works on the same state as the REST handlers, so API keys, limits,
+//! recovery, mirrors and commit sequences behave the same way. Vectors travel
+//! as packed floats instead of JSON arrays, which is the main cost of the REST
+//! API for bulk clients. Unauthenticated public search is REST only, and so
+//! are signed requests: the server won't start gRPC with a request signing
+//! secret set, and the service refuses every call if it is.
+
+// `Status` is large, but it is what every tonic handler returns
+#![allow(clippy::result_large_err)]
+
+use crate::mirror::Change;
+use crate::tenants::in_namespace;
+use crate::{
+    authenticate, check_limit, check_vector_quota, check_writable, mirror_target, recovery_error,
+    search_params, wait_for_seq, write_limits, AppState, Caller, ErrorResponse, InsertRequest,
+    KeyRole,
+};
+use axum::http::{Method, StatusCode};
+use axum::Json;
+use serde_json::Value;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use surgedb_core::db::Collection;
+use surgedb_core::filter::Filter;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status, Streaming};
+use tracing::{info, warn};
+
+mod proto {
+    tonic::include_proto!("surgedb.v1");
+}
+
+use proto::surge_db_server::{SurgeDb, SurgeDbServer};
+use proto::{
+    BatchUpsertRequest, DeleteRequest, DeleteResponse, SearchHit, SearchRequest, SearchResponse,
+    Vector, WriteRequest, WriteResponse,
+};
+
+/// Most vectors a `StreamUpsert` buffers before writing them
+const STREAM_BATCH_SIZE: usize = 1000;
+/// Searches of a `SearchStream` answered ahead of the client reading them
+const SEARCH_STREAM_BUFFER: usize = 16;
+
+/// Serve the gRPC API on `addr` until a shutdown signal
+///
+/// Like the REST ports, calls in flight then get `drain_timeout` to finish
+/// before their connections are dropped.
+pub async fn serve(
+    state: AppState,
+    addr: SocketAddr,
+    drain_timeout: Duration,
+) -> Result<(), tonic::transport::Error> {
+    let max_message_size = state.config.max_request_size_bytes;
+    let service = SurgeDbServer::new(GrpcService { state })
+        .max_decoding_message_size(max_message_size)
+        .max_encoding_message_size(max_message_size);
+    info!("gRPC Server listening on {}", addr);
+    let (signalled, mut on_signal) = tokio::sync::watch::channel(false);
+    let server = tonic::transport::Server::builder()
+        .add_service(service)
+        .serve_with_shutdown(addr, async move {
+            crate::shutdown_signal().await;
+            let _ = signalled.send(true);
+        });
+    let deadline = async move {
+        if on_signal.wait_for(|signalled| *signalled).await.is_err() {
+            std::future::pending::<()>().await;
+        }
+        tokio::time::sleep(drain_timeout).await;
+    };
+    tokio::select! {
+        res = server => res,
+        _ = deadline => {
+            warn!("Dropping gRPC calls still running after {:?}", drain_timeout);
+            Ok(())
+        }
+    }
+}
+
+#[derive(Clone)]
+struct GrpcService {
+    state: AppState,
+}
+
+/// Map a REST handler error onto the matching gRPC status
+fn status((code, Json(body)): (StatusCode, Json<ErrorResponse>)) -> Status {
+    match code {
+        StatusCode::BAD_REQUEST => Status::invalid_argument(body.error),
+        StatusCode::UNAUTHORIZED => Status::unauthenticated(body.error),
+        StatusCode::FORBIDDEN => Status::permission_denied(body.error),
+        StatusCode::NOT_FOUND => Status::not_found(body.error),
+        StatusCode::SERVICE_UNAVAILABLE => Status::unavailable(body.error),
+        _ => Status::internal(body.error),
+    }
+}
+
+fn internal(e: impl ToString) -> Status {
+    Status::internal(e.to_string())
+}
+
+impl TryFrom<Vector> for InsertRequest {
+    type Error = Status;
+
+    fn try_from(vector: Vector) -> Result<Self, Status> {
+        let metadata = vector
+            .metadata_json
+            .map(|json| serde_json::from_str::<Value>(&json))
+            .transpose()
+            .map_err(|e| {
+                Status::invalid_argument(format!("Invalid metadata of {}: {}", vector.id, e))
+            })?;
+        Ok(InsertRequest {
+            id: vector.id,
+            vector: vector.values.into_iter().map(f64::from).collect(),
+            metadata,
+            sparse: None,
+            vectors: None,
+        })
+    }
+}
+
+impl GrpcService {
+    /// Authenticate from the `x-api-key` metadata entry
+    fn caller<T>(&self, request: &Request<T>) -> Result<Caller, Status> {
+        if self.state.signer.is_some() {
+            return Err(Status::unauthenticated(
+                "Request signing is on; gRPC calls can't be signed",
+            ));
+        }
+        if !self.state.config.auth_enabled() {
+            return Ok(Caller {
+                key_name: None,
+                admin: true,
+                role: KeyRole::Write,
+                namespace: None,
+                token: None,
+            });
+        }
+        request
+            .metadata()
+            .get("x-api-key")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|key| authenticate(&self.state, key))
+            .ok_or_else(|| Status::unauthenticated("Invalid or missing API key"))
+    }
+
+    fn collection(&self, caller: &Caller, name: &str, read: bool) -> Result<Collection, Status> {
+        if let Some(namespace) = &caller.namespace {
+            if !in_namespace(namespace, &self.state.db.resolve_name(name)) {
+                return Err(Status::permission_denied(format!(
+                    "Tenant keys can only use collections named {}.<name>",
+                    namespace
+                )));
+            }
+        }
+        if caller.role == KeyRole::Read && !read {
+            return Err(Status::permission_denied("Read-only API key"));
+        }
+        if !read {
+            check_writable(&self.state.db, name).map_err(status)?;
+        }
+        if let Some(error) = recovery_error(&self.state.db, read) {
+            return Err(Status::unavailable(error));
+        }
+        if let (Some(leader), false) = (self.state.replication.leader_url(), read) {
+            return Err(Status::failed_precondition(format!(
+                "Read-only replica; send writes to the leader at {}",
+                leader
+            )));
+        }
+        self.state
+            .db
+            .get_collection(name)
+            .map_err(|e| Status::not_found(e.to_string()))
+    }
+
+    fn record(&self, write: bool, start: Instant) {
+        let method = if write { Method::POST } else { Method::GET };
+        self.state
+            .metrics
+            .record_request(&method, start.elapsed().as_secs_f64() * 1000.0);
+    }
+
+    /// Write `items`, inserting a single item if `insert_only`, otherwise upserting
+    async fn write(
+        &self,
+        caller: &Caller,
+        name: &str,
+        items: Vec<InsertRequest>,
+        insert_only: bool,
+    ) -> Result<WriteResponse, Status> {
+        let limits = write_limits(&self.state, caller, name);
+        check_limit("batch size", items.len(), limits.max_batch_size).map_err(status)?;
+        let collection = self.collection(caller, name, false)?;
+        let mirrored = mirror_target(&self.state, name).map(|target| (target, items.clone()));
+
+        let count = items.len();
+        let max_vectors = limits.max_vectors;
+        let commit_seq = tokio::task::spawn_blocking(move || {
+            check_vector_quota(&collection, items.iter().map(|item| &item.id), max_vectors)?;
+            let mut items: Vec<(String, Vec<f32>, Option<Value>)> = items
+                .into_iter()
+                .map(|item| {
+                    let vector = collection.convert_vector(&item.vector)?;
+                    Ok((item.id, vector, item.metadata))
+                })
+                .collect::<surgedb_core::Result<_>>()?;
+            match items.pop() {
+                Some((id, vector, metadata)) if insert_only => {
+                    collection.insert(id, &vector, metadata)?
+                }
+                Some(last) => {
+                    items.push(last);
+                    collection.upsert_batch(items)?
+                }
+                None => {}
+            }
+            Ok::<_, surgedb_core::Error>(collection.write_seq())
+        })
+        .await
+        .map_err(internal)?
+        .map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+        if let Some((target, vectors)) = mirrored {
+            self.state.mirrors.publish(&target, Change::Upsert(vectors));
+        }
+        Ok(WriteResponse {
+            count: count as u64,
+            commit_seq,
+        })
+    }
+
+    async fn write_one(
+        &self,
+        request: Request<WriteRequest>,
+        insert_only: bool,
+    ) -> Result<Response<WriteResponse>, Status> {
+        let start = Instant::now();
+        let caller = self.caller(&request)?;
+        let request = request.into_inner();
+        let vector = request
+            .vector
+            .ok_or_else(|| Status::invalid_argument("Missing vector"))?;
+        let response = self
+            .write(
+                &caller,
+                &request.collection,
+                vec![vector.try_into()?],
+                insert_only,
+            )
+            .await?;
+        self.record(true, start);
+        Ok(Response::new(response))
+    }
+
+    async fn run_search(
+        &self,
+        caller: &Caller,
+        request: SearchRequest,
+    ) -> Result<SearchResponse, Status> {
+        let limits = self.state.limits.effective(caller.key_name.as_deref());
+        let k = request.k as usize;
+        check_limit("k", k, limits.max_k).map_err(status)?;
+        let params = search_params(
+            k,
+            request.ef_search.map(|ef| ef as usize),
+            request.rescore,
+            request.oversampling,
+            &limits,
+        )
+        .map_err(status)?;
+        let filter = request
+            .filter_json
+            .map(|json| serde_json::from_str::<Filter>(&json))
+            .transpose()
+            .map_err(|e| Status::invalid_argument(format!("Invalid filter: {}", e)))?;
+        if let Some(filter) = &filter {
+            filter
+                .validate()
+                .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        }
+
+        let collection = self.collection(caller, &request.collection, true)?;
+        if let Some(min_seq) = request.min_seq {
+            let timeout = Duration::from_millis(self.state.config.min_seq_timeout_ms);
+            wait_for_seq(&collection, min_seq, timeout)
+                .await
+                .map_err(status)?;
+        }
+
+        let vector = request.vector;
+        let include_metadata = request.include_metadata.unwrap_or(true);
+        let hits = tokio::task::spawn_blocking(move || {
+            if include_metadata {
+                collection
+                    .search_with_params(&vector, k, filter.as_ref(), params)
+                    .map(|(results, _)| {
+                        results
+                            .into_iter()
+                            .map(|(id, distance, metadata)| SearchHit {
+                                id: id.to_string(),
+                                distance,
+                                metadata_json: metadata.map(|m| m.to_string()),
+                            })
+                            .collect()
+                    })
+            } else {
+                collection
+                    .search_ids_with_params(&vector, k, filter.as_ref(), params)
+                    .map(|(results, _)| {
+                        results
+                            .into_iter()
+                            .map(|(id, distance)| SearchHit {
+                                id: id.to_string(),
+                                distance,
+                                metadata_json: None,
+                            })
+                            .collect()
+                    })
+            }
+        })
+        .await
+        .map_err(internal)?
+        .map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+        Ok(SearchResponse { hits })
+    }
+}
+
+#[tonic::async_trait]
+impl SurgeDb for GrpcService {
+    async fn insert(
+        &self,
+        request: Request<WriteRequest>,
+    ) -> Result<Response<WriteResponse>, Status> {
+        self.write_one(request, true).await
+    }
+
+    async fn upsert(
+        &self,
+        request: Request<WriteRequest>,
+    ) -> Result<Response<WriteResponse>, Status> {
+        self.write_one(request, false).await
+    }
+
+    async fn batch_upsert(
+        &self,
+        request: Request<BatchUpsertRequest>,
+    ) -> Result<Response<WriteResponse>, Status> {
+        let start = Instant::now();
+        let caller = self.caller(&request)?;
+        let request = request.into_inner();
+        let items = request
+            .vectors
+            .into_iter()
+            .map(InsertRequest::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        let response = self
+            .write(&caller, &request.collection, items, false)
+            .await?;
+        self.record(true, start);
+        Ok(Response::new(response))
+    }
+
+    async fn stream_upsert(
+        &self,
+        request: Request<Streaming<WriteRequest>>,
+    ) -> Result<Response<WriteResponse>, Status> {
+        let start = Instant::now();
+        let caller = self.caller(&request)?;
+        let limits = self.state.limits.effective(caller.key_name.as_deref());
+        let batch_size = STREAM_BATCH_SIZE.min(limits.max_batch_size).max(1);
+        let mut stream = request.into_inner();
+
+        // Consecutive vectors for the same collection are written together
+        let mut total = WriteResponse::default();
+        let mut collection = String::new();
+        let mut batch = Vec::new();
+        while let Some(message) = stream.message().await? {
+            let vector = message
+                .vector
+                .ok_or_else(|| Status::invalid_argument("Missing vector"))?;
+            if message.collection != collection || batch.len() >= batch_size {
+                if !batch.is_empty() {
+                    let written = self
+                        .write(&caller, &collection, std::mem::take(&mut batch), false)
+                        .await?;
+                    total.count += written.count;
+                    total.commit_seq = written.commit_seq;
+                }
+                collection = message.collection;
+            }
+            batch.push(vector.try_into()?);
+        }
+        if !batch.is_empty() {
+            let written = self.write(&caller, &collection, batch, false).await?;
+            total.count += written.count;
+            total.commit_seq = written.commit_seq;
+        }
+
+        self.record(true, start);
+        Ok(Response::new(total))
+    }
+
+    async fn delete(
+        &self,
+        request: Request<DeleteRequest>,
+    ) -> Result<Response<DeleteResponse>, Status> {
+        let start = Instant::now();
+        let caller = self.caller(&request)?;
+        let request = request.into_inner();
+        let collection = self.collection(&caller, &request.collection, false)?;
+
+        let id = request.id.clone();
+        let (deleted, commit_seq) = tokio::task::spawn_blocking(move || {
+            let deleted = collection.delete(&id)?;
+            Ok::<_, surgedb_core::Error>((deleted, collection.write_seq()))
+        })
+        .await
+        .map_err(internal)?
+        .map_err(internal)?;
+
+        if deleted {
+            if let Some(target) = mirror_target(&self.state, &request.collection) {
+                self.state
+                    .mirrors
+                    .publish(&target, Change::Delete(request.id));
+            }
+        }
+        self.record(true, start);
+        Ok(Response::new(DeleteResponse {
+            deleted,
+            commit_seq,
+        }))
+    }
+
+    async fn search(
+        &self,
+        request: Request<SearchRequest>,
+    ) -> Result<Response<SearchResponse>, Status> {
+        let start = Instant::now();
+        let caller = self.caller(&request)?;
+        let response = self.run_search(&caller, request.into_inner()).await?;
+        self.record(false, start);
+        Ok(Response::new(response))
+    }
+
+    type SearchStreamStream = ReceiverStream<Result<SearchResponse, Status>>;
+
+    async fn search_stream(
+        &self,
+        request: Request<Streaming<SearchRequest>>,
+    ) -> Result<Response<Self::SearchStreamStream>, Status> {
+        let caller = self.caller(&request)?;
+        let mut stream = request.into_inner();
+        let (tx, rx) = mpsc::channel(SEARCH_STREAM_BUFFER);
+        let service = self.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let message = match stream.message().await {
+                    Ok(Some(message)) => message,
+                    Ok(None) => return,
+                    Err(e) => {
+                        let _ = tx.send(Err(e)).await;
+                        return;
+                    }
+                };
+                let start = Instant::now();
+                let result = service.run_search(&caller, message).await;
+                service.record(false, start);
+                let failed = result.is_err();
+                // Stop once the client went away or a search failed
+                if tx.send(result).await.is_err() || failed {
+                    return;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AppConfig;
+    use std::sync::Arc;
+    use surgedb_core::Database;
+
+    #[tokio::test]
+    async fn test_system_collections_are_read_only() {
+        let dir = std::env::temp_dir().join(format!("surgedb-grpc-test-{}", std::process::id()));
+        let mut config = AppConfig::from_vars(|_| Err(std::env::VarError::NotPresent));
+        config.data_dir = dir.to_string_lossy().into_owned();
+        let db = Database::new();
+        db.system_collection("_audit").unwrap();
+        let service = GrpcService {
+            state: AppState::new(Arc::new(db), config),
+        };
+        let caller = service.caller(&Request::new(())).unwrap();
+
+        assert!(service.collection(&caller, "_audit", true).is_ok());
+        let Err(err) = service.collection(&caller, "_audit", false) else {
+            panic!("Wrote a system collection");
+        };
+        assert_eq!(err.code(), tonic::Code::PermissionDenied);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,133 @@
+//! Vector norms and anomaly checks for batch ingest
+//!
+//! Computed in the same pass that prepares a batch for insertion, so callers
+//! can spot broken embeddings (all zeros, exploding or collapsed norms)
+//! without validating the batch separately. Flagged records are still stored.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Norms further than this factor from the batch median are flagged by default
+const DEFAULT_OUTLIER_FACTOR: f32 = 10.0;
+
+/// How batch norms are checked
+#[derive(Deserialize, ToSchema, Default, Clone, Copy)]
+pub struct NormChecks {
+    /// Flag norms below this value
+    #[schema(example = 0.5)]
+    pub min_norm: Option<f32>,
+    /// Flag norms above this value
+    #[schema(example = 2.0)]
+    pub max_norm: Option<f32>,
+    /// Without explicit bounds, flag norms more than this many times above or
+    /// below the batch median (default 10)
+    pub outlier_factor: Option<f32>,
+}
+
+/// Why a record was flagged
+#[derive(Serialize, ToSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Anomaly {
+    /// Every component is zero, so the vector has no direction
+    ZeroVector,
+    /// The norm overflowed
+    NonFinite,
+    /// Below `min_norm`, or far below the batch median
+    NormTooLow,
+    /// Above `max_norm`, or far above the batch median
+    NormTooHigh,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct FlaggedRecord {
+    /// Position in the request's `vectors`
+    pub index: usize,
+    pub id: String,
+    pub norm: f32,
+    pub reason: Anomaly,
+}
+
+/// Norm distribution of a batch
+#[derive(Serialize, ToSchema)]
+pub struct NormStats {
+    pub min: f32,
+    pub max: f32,
+    pub mean: f32,
+    pub median: f32,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct BatchStats {
+    /// L2 norm of each record, in request order, before any normalization
+    pub norms: Vec<f32>,
+    /// Finite norms only; absent for an empty batch
+    pub summary: Option<NormStats>,
+    pub flagged: Vec<FlaggedRecord>,
+}
+
+/// L2 norm of `vector`
+pub fn l2_norm(vector: &[f32]) -> f32 {
+    vector.iter().map(|x| x * x).sum::<f32>().sqrt()
+}
+
+/// Scale `vector` to unit length; zero and non-finite vectors are left as is
+pub fn normalize(vector: &mut [f32], norm: f32) {
+    if norm > 0.0 && norm.is_finite() {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+/// Summarize `norms` and flag the records that look broken
+pub fn analyze<'a>(
+    ids: impl Iterator<Item = &'a str>,
+    norms: Vec<f32>,
+    checks: NormChecks,
+) -> BatchStats {
+    let mut finite: Vec<f32> = norms.iter().copied().filter(|n| n.is_finite()).collect();
+    finite.sort_by(f32::total_cmp);
+    let summary = (!finite.is_empty()).then(|| NormStats {
+        min: finite[0],
+        max: finite[finite.len() - 1],
+        mean: finite.iter().sum::<f32>() / finite.len() as f32,
+        median: finite[finite.len() / 2],
+    });
+
+    // Explicit bounds win; otherwise bound by a factor around the median
+    let (low, high) = match (checks.min_norm, checks.max_norm, &summary) {
+        (None, None, Some(summary)) if summary.median > 0.0 => {
+            let factor = checks.outlier_factor.unwrap_or(DEFAULT_OUTLIER_FACTOR);
+            (summary.median / factor, summary.median * factor)
+        }
+        (min, max, _) => (min.unwrap_or(0.0), max.unwrap_or(f32::INFINITY)),
+    };
+
+    let flagged = ids
+        .zip(&norms)
+        .enumerate()
+        .filter_map(|(index, (id, &norm))| {
+            let reason = if !norm.is_finite() {
+                Anomaly::NonFinite
+            } else if norm == 0.0 {
+                Anomaly::ZeroVector
+            } else if norm < low {
+                Anomaly::NormTooLow
+            } else if norm > high {
+                Anomaly::NormTooHigh
+            } else {
+                return None;
+            };
+            Some(FlaggedRecord {
+                index,
+                id: id.to_string(),
+                norm,
+                reason,
+            })
+        })
+        .collect();
+
+    BatchStats {
+        norms,
+        summary,
+        flagged,
+    }
+}
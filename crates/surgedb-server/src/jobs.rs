@@ -0,0 +1,152 @@
+//! Background jobs on collections, with their progress
+//!
+//! Jobs run on the blocking thread pool and are kept in memory: a restart
+//! forgets them, and only the most recent finished ones are kept. The only
+//! kind so far rebuilds the metadata index of a field.
+
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use serde::Serialize;
+use std::sync::Arc;
+use surgedb_core::db::Collection;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+/// Finished jobs kept for inspection
+const MAX_FINISHED_JOBS: usize = 100;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    IndexRebuild,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Running,
+    Succeeded,
+    Failed,
+}
+
+#[derive(Serialize, Clone, Debug, ToSchema)]
+pub struct Job {
+    pub id: String,
+    pub kind: JobKind,
+    pub collection: String,
+    /// Metadata field of an index rebuild
+    pub field: Option<String>,
+    pub status: JobStatus,
+    /// Records gone through so far, out of `total`
+    pub processed: usize,
+    pub total: usize,
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// Shared registry of recent jobs
+#[derive(Default)]
+pub struct JobRegistry {
+    jobs: RwLock<Vec<Job>>,
+}
+
+impl JobRegistry {
+    /// Rebuild the index of `field` of `collection` (named `name`) in the
+    /// background; fails if a rebuild of it is already running
+    pub fn start_index_rebuild(
+        self: &Arc<Self>,
+        collection: Collection,
+        name: &str,
+        field: &str,
+    ) -> Result<Job, String> {
+        let job = Job {
+            id: format!(
+                "job_{:x}",
+                Utc::now().timestamp_nanos_opt().unwrap_or_default()
+            ),
+            kind: JobKind::IndexRebuild,
+            collection: name.to_string(),
+            field: Some(field.to_string()),
+            status: JobStatus::Running,
+            processed: 0,
+            total: collection.len(),
+            error: None,
+            started_at: Utc::now(),
+            finished_at: None,
+        };
+        {
+            let mut jobs = self.jobs.write();
+            let running = jobs.iter().any(|j| {
+                j.status == JobStatus::Running
+                    && j.kind == JobKind::IndexRebuild
+                    && j.collection == job.collection
+                    && j.field == job.field
+            });
+            if running {
+                return Err(format!(
+                    "The index of {} in {} is already being rebuilt",
+                    field, name
+                ));
+            }
+            Self::insert(&mut jobs, job.clone());
+        }
+
+        let registry = self.clone();
+        let (id, field) = (job.id.clone(), field.to_string());
+        tokio::task::spawn_blocking(move || {
+            let result = collection.rebuild_field_index(&field, |processed, total| {
+                registry.update(&id, |job| {
+                    job.processed = processed;
+                    job.total = total;
+                });
+            });
+            registry.update(&id, |job| {
+                job.finished_at = Some(Utc::now());
+                match result {
+                    Ok(()) => {
+                        job.status = JobStatus::Succeeded;
+                        info!("Rebuilt the index of {} in {}", field, job.collection);
+                    }
+                    Err(e) => {
+                        job.status = JobStatus::Failed;
+                        job.error = Some(e.to_string());
+                        warn!(
+                            "Failed to rebuild the index of {} in {}: {}",
+                            field, job.collection, e
+                        );
+                    }
+                }
+            });
+        });
+        Ok(job)
+    }
+
+    pub fn get(&self, id: &str) -> Option<Job> {
+        self.jobs.read().iter().find(|j| j.id == id).cloned()
+    }
+
+    /// All known jobs, newest first
+    pub fn list(&self) -> Vec<Job> {
+        self.jobs.read().iter().rev().cloned().collect()
+    }
+
+    fn insert(jobs: &mut Vec<Job>, job: Job) {
+        let finished = jobs
+            .iter()
+            .filter(|j| j.status != JobStatus::Running)
+            .count();
+        if finished >= MAX_FINISHED_JOBS {
+            if let Some(pos) = jobs.iter().position(|j| j.status != JobStatus::Running) {
+                jobs.remove(pos);
+            }
+        }
+        jobs.push(job);
+    }
+
+    fn update(&self, id: &str, f: impl FnOnce(&mut Job)) {
+        if let Some(job) = self.jobs.write().iter_mut().find(|j| j.id == id) {
+            f(job);
+        }
+    }
+}
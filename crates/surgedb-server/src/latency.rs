@@ -0,0 +1,119 @@
+//! Request latency histogram with trace exemplars
+//!
+//! Exposed in OpenMetrics format on `/metrics`. Requests that arrive with a
+//! W3C `traceparent` header leave their trace ID as the exemplar of the
+//! bucket they land in, so a dashboard can jump from a latency spike to a
+//! trace of one of the slow requests.
+
+use axum::http::HeaderMap;
+use parking_lot::Mutex;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Bucket upper bounds in seconds; the implicit last bucket is `+Inf`
+const BUCKETS: [f64; 12] = [
+    0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+];
+
+const NAME: &str = "surgedb_http_request_duration_seconds";
+
+/// The latest traced request that landed in a bucket
+struct Exemplar {
+    trace_id: String,
+    seconds: f64,
+    timestamp: f64,
+}
+
+struct Bucket {
+    count: AtomicU64,
+    exemplar: Mutex<Option<Exemplar>>,
+}
+
+pub struct LatencyHistogram {
+    /// One per bound in `BUCKETS`, then `+Inf`; counts are not cumulative
+    buckets: Vec<Bucket>,
+    sum_us: AtomicU64,
+}
+
+impl LatencyHistogram {
+    pub fn new() -> Self {
+        Self {
+            buckets: (0..=BUCKETS.len())
+                .map(|_| Bucket {
+                    count: AtomicU64::new(0),
+                    exemplar: Mutex::new(None),
+                })
+                .collect(),
+            sum_us: AtomicU64::new(0),
+        }
+    }
+
+    pub fn observe(&self, latency: Duration, trace_id: Option<String>) {
+        let seconds = latency.as_secs_f64();
+        let index = BUCKETS
+            .iter()
+            .position(|&bound| seconds <= bound)
+            .unwrap_or(BUCKETS.len());
+        let bucket = &self.buckets[index];
+        bucket.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_us
+            .fetch_add(latency.as_micros() as u64, Ordering::Relaxed);
+
+        if let Some(trace_id) = trace_id {
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64();
+            *bucket.exemplar.lock() = Some(Exemplar {
+                trace_id,
+                seconds,
+                timestamp,
+            });
+        }
+    }
+
+    /// The histogram as an OpenMetrics text exposition, including `# EOF`
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# TYPE {NAME} histogram");
+        let _ = writeln!(out, "# UNIT {NAME} seconds");
+        let _ = writeln!(out, "# HELP {NAME} HTTP request latency.");
+
+        let mut cumulative = 0;
+        for (i, bucket) in self.buckets.iter().enumerate() {
+            cumulative += bucket.count.load(Ordering::Relaxed);
+            let le = BUCKETS
+                .get(i)
+                .map_or_else(|| "+Inf".to_string(), |bound| bound.to_string());
+            let _ = write!(out, "{NAME}_bucket{{le=\"{le}\"}} {cumulative}");
+            if let Some(exemplar) = &*bucket.exemplar.lock() {
+                let _ = write!(
+                    out,
+                    " # {{trace_id=\"{}\"}} {} {:.3}",
+                    exemplar.trace_id, exemplar.seconds, exemplar.timestamp
+                );
+            }
+            out.push('\n');
+        }
+
+        let sum = self.sum_us.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+        let _ = writeln!(out, "{NAME}_count {cumulative}");
+        let _ = writeln!(out, "{NAME}_sum {sum}");
+        out.push_str("# EOF\n");
+        out
+    }
+}
+
+/// Trace ID of a W3C `traceparent` header (`00-<trace id>-<span id>-<flags>`)
+pub fn trace_id(headers: &HeaderMap) -> Option<String> {
+    let header = headers.get("traceparent")?.to_str().ok()?;
+    let mut parts = header.split('-');
+    let (_version, trace_id) = (parts.next()?, parts.next()?);
+    let valid = trace_id.len() == 32
+        && trace_id
+            .bytes()
+            .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+        && trace_id.bytes().any(|b| b != b'0');
+    valid.then(|| trace_id.to_string())
+}
@@ -0,0 +1,9163 @@
+//! HTTP API server for SurgeDB
+//!
+//! The `surgedb-server` binary calls [`run`]. To serve the API from another
+//! Rust service instead, create an [`AppState`] and mount [`build_router`]
+//! in the host's axum app:
+//!
+//! ```no_run
+//! # async fn host() {
+//! use surgedb_server::{build_router, AppConfig, AppState};
+//!
+//! let config = AppConfig::from_env();
+//! let db = surgedb_core::Database::open_recovering("./data").unwrap();
+//! let app = axum::Router::new().nest("/vectors", build_router(AppState::new(db, config)));
+//! # let _: axum::Router = app;
+//! # }
+//! ```
+//!
+//! [`test::spawn_ephemeral`] runs a throwaway server for integration tests.
+
+mod backup;
+#[cfg(feature = "chaos")]
+mod chaos;
+#[cfg(feature = "cluster")]
+mod cluster;
+mod compaction;
+mod compat;
+mod deployments;
+#[cfg(feature = "dev")]
+mod dev;
+mod etag;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod ingest;
+mod jobs;
+mod latency;
+mod limits;
+mod logging;
+mod mirror;
+mod rate_limit;
+mod repair;
+mod replication;
+mod signing;
+mod tenants;
+pub mod test;
+mod tls;
+mod tokens;
+mod usage;
+mod webhooks;
+
+use axum::{
+    extract::{ConnectInfo, Json, Path, Query, Request, State},
+    http::{header, header::HeaderName, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{delete, get, patch, post, put},
+    Extension, Router,
+};
+use axum_server::tls_rustls::RustlsConfig;
+use backup::{BackedUpCollection, BackupConfig, BackupManifest, BackupStore, RestoredCollection};
+use compaction::{
+    CompactionPolicy, CompactionRegistry, CompactionRun, CompactionStatus, CompactionTrigger,
+    SetCompactionRequest,
+};
+use compat::ResponseFormat;
+use deployments::{
+    Deployment, DeploymentAssertions, DeploymentRegistry, DeploymentRequest, DeploymentStatus,
+    DeploymentStep,
+};
+use ingest::{Anomaly, BatchStats, FlaggedRecord, NormChecks, NormStats};
+use jobs::{Job, JobKind, JobRegistry, JobStatus};
+use latency::LatencyHistogram;
+use limits::{LimitOverrides, Limits, LimitsRegistry, LimitsSnapshot};
+use logging::LogFilterState;
+use mirror::{Change, Mirror, MirrorRegistry, MirrorRequest, MirrorState};
+use rate_limit::RateLimiter;
+use repair::{GraphRepairs, RepairConfig};
+use replication::{
+    Catalog, CatalogEntry, CollectionReplication, FollowerState, LogPage, LogQuery, LoggedEntry,
+    Replication, ReplicationRole, ReplicationStatus, LOG_ID_HEADER, LOG_SEQ_HEADER, MAX_LOG_PAGE,
+};
+use rust_embed::RustEmbed;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use signing::{RequestSigner, SIGNATURE_HEADER, TIMESTAMP_HEADER};
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use surgedb_core::activity::ACTIVITY_MINUTES;
+use surgedb_core::db::Collection;
+use surgedb_core::filter::{get_value_by_path, Filter};
+use surgedb_core::naming;
+use surgedb_core::tune::TuneOptions;
+use surgedb_core::{
+    ActivityMinute, CachedFilterInfo, CollectionSummary, CollectionUpdate, Config as DbConfig,
+    Database, DistanceBounds, DistanceMetric, DistanceTo, DtypeConversion, Fusion,
+    FusionExplanation, GraphStats, GroupBy, GroupCommit, HardNegativeQuery, HardNegatives,
+    HnswConfig, HybridHit, IdType, IndexKind, ListCursor, LogPosition, MemoryBreakdown,
+    MetadataCompression, MetadataLimits, NameCase, NamedVectorConfig, NormFilter, QuantizationType,
+    Recommend, RecommendStrategy, RecoveryMode, RecoveryPhase, SearchHit, SearchParams,
+    SearchUsage, SparseVector, VectorDtype, VectorId, VectorTransform, MAX_CACHED_FILTERS,
+};
+use sysinfo::System;
+use tenants::{TenantInfo, TenantQuotas, TenantRegistry, TenantUsage};
+use tokens::{TokenClaims, TokenScope, TokenSigner};
+use tower_http::{
+    compression::CompressionLayer, cors::CorsLayer, limit::RequestBodyLimitLayer,
+    timeout::TimeoutLayer, trace::TraceLayer,
+};
+use tracing::{debug, error, info, warn};
+use usage::UsageRecorder;
+use utoipa::{IntoParams, OpenApi, ToSchema};
+use utoipa_swagger_ui::SwaggerUi;
+use webhooks::{CreateWebhookRequest, ThresholdMetric, Webhook, WebhookRegistry};
+
+#[derive(RustEmbed)]
+#[folder = "dist/"]
+struct Assets;
+
+async fn static_handler(Path(path): Path<String>) -> impl IntoResponse {
+    let path = if path.is_empty() || path == "/" {
+        "index.html".to_string()
+    } else {
+        path
+    };
+
+    match Assets::get(&path) {
+        Some(content) => {
+            let mime = mime_guess::from_path(&path).first_or_octet_stream();
+            (
+                [(axum::http::header::CONTENT_TYPE, mime.as_ref())],
+                content.data,
+            )
+                .into_response()
+        }
+        None => {
+            // Fallback to index.html for SPA routing
+            if let Some(content) = Assets::get("index.html") {
+                (
+                    [(axum::http::header::CONTENT_TYPE, "text/html")],
+                    content.data,
+                )
+                    .into_response()
+            } else {
+                (StatusCode::NOT_FOUND, "Not Found").into_response()
+            }
+        }
+    }
+}
+
+async fn index_handler() -> impl IntoResponse {
+    static_handler(Path("index.html".to_string())).await
+}
+
+// =============================================================================
+// Configuration
+// =============================================================================
+
+/// Server settings, read from environment variables
+#[derive(Clone)]
+pub struct AppConfig {
+    port: u16,
+    web_port: u16,
+    api_key: Option<String>,
+    /// Additional named API keys (name -> secret and role) without admin rights
+    api_keys: HashMap<String, (String, KeyRole)>,
+    /// Secret scoped tokens are signed with; `api_key` if unset
+    token_secret: Option<String>,
+    /// Secret API requests must be signed with, and webhooks are signed with
+    signing_secret: Option<String>,
+    /// Largest accepted difference between a request signature's timestamp
+    /// and the server clock; signatures are remembered this long against replays
+    signature_tolerance_secs: u64,
+    log_level: String,
+    cors_allow_origin: String,
+    request_timeout_secs: u64,
+    max_request_size_bytes: usize,
+    data_dir: String,
+    /// Collections exposed for unauthenticated, search-only access
+    public_collections: HashSet<String>,
+    /// Max unauthenticated search requests per client IP per minute
+    public_rate_limit_per_min: u32,
+    /// Ceilings that no caller can exceed
+    hard_limits: Limits,
+    /// Defaults applied to every caller unless overridden per key
+    soft_limits: Limits,
+    /// Share of a `max_vectors` quota past which writes carry a warning
+    quota_warning_ratio: f64,
+    /// How often collection webhooks are evaluated
+    webhook_check_interval_secs: u64,
+    /// Longest time a search waits for its `min_seq` write to become visible
+    min_seq_timeout_ms: u64,
+    /// Directory collection snapshots are written to and restored from
+    snapshot_dir: String,
+    /// Object store backups are uploaded to; backups are off when unset
+    backup: Option<BackupConfig>,
+    /// When collections are compacted automatically
+    compaction: CompactionPolicy,
+    /// How often collections are checked for compaction
+    compaction_check_interval_secs: u64,
+    /// How often and how much HNSW graphs are repaired in the background
+    graph_repair: RepairConfig,
+    /// Case policy of collection and alias names
+    name_case: NameCase,
+    /// How long the `_usage` history is kept; not recorded if zero
+    usage_retention_days: u64,
+    /// URL of the leader this server follows as a read-only replica
+    replica_of: Option<String>,
+    /// API key sent to the leader; its admin key when it has auth enabled
+    replica_api_key: Option<String>,
+    /// How often a follower polls its leader
+    replication_poll_interval_ms: u64,
+    /// Certificate the API and web ports serve HTTPS with; plain HTTP if unset
+    tls: Option<tls::TlsConfig>,
+    /// Shape of the search and upsert responses unless a request picks one
+    response_format: ResponseFormat,
+    /// How long a shutdown waits for in-flight requests, then for jobs on
+    /// collections, before flushing them
+    shutdown_timeout_secs: u64,
+    /// What to do with damaged WAL records found while recovering
+    recovery_mode: RecoveryMode,
+    /// Port of the gRPC API; disabled when unset
+    #[cfg(feature = "grpc")]
+    grpc_port: Option<u16>,
+    /// This server's place in a cluster of sharded collections
+    #[cfg(feature = "cluster")]
+    cluster: Option<cluster::ClusterConfig>,
+}
+
+impl AppConfig {
+    /// Read the settings from the environment (and a `.env` file), with defaults
+    pub fn from_env() -> Self {
+        dotenvy::dotenv().ok();
+        Self::from_vars(|name| std::env::var(name))
+    }
+
+    /// Settings from the variables `var` looks up by name; unset ones get defaults
+    fn from_vars(var: impl Fn(&str) -> Result<String, std::env::VarError>) -> Self {
+        let env_or = |name: &str, default: usize| -> usize {
+            var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+        let name_case = match var("COLLECTION_NAME_CASE").as_deref() {
+            Ok(v) if v.eq_ignore_ascii_case("lowercase") => NameCase::Lowercase,
+            _ => NameCase::Preserve,
+        };
+        Self {
+            port: var("PORT")
+                .unwrap_or_else(|_| "3000".to_string())
+                .parse()
+                .unwrap_or(3000),
+            web_port: var("WEB_PORT")
+                .unwrap_or_else(|_| "3001".to_string())
+                .parse()
+                .unwrap_or(3001),
+            api_key: var("API_KEY").ok(),
+            token_secret: var("TOKEN_SECRET").ok(),
+            signing_secret: var("REQUEST_SIGNING_SECRET").ok(),
+            signature_tolerance_secs: var("SIGNATURE_TOLERANCE_SECS")
+                .unwrap_or_else(|_| "300".to_string())
+                .parse()
+                .unwrap_or(300),
+            api_keys: var("API_KEYS")
+                .map(|v| {
+                    v.split(',')
+                        .filter_map(|entry| entry.trim().split_once(':'))
+                        .map(|(name, rest)| {
+                            let (key, role) = match rest.rsplit_once(':') {
+                                Some((key, "read")) => (key, KeyRole::Read),
+                                Some((key, "write")) => (key, KeyRole::Write),
+                                _ => (rest, KeyRole::Write),
+                            };
+                            (name.trim().to_string(), (key.trim().to_string(), role))
+                        })
+                        .filter(|(name, (key, _))| !name.is_empty() && !key.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            log_level: var("LOG_LEVEL").unwrap_or_else(|_| "info".to_string()),
+            cors_allow_origin: var("CORS_ALLOW_ORIGIN").unwrap_or_else(|_| "*".to_string()),
+            request_timeout_secs: var("REQUEST_TIMEOUT_SECS")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .unwrap_or(30),
+            max_request_size_bytes: var("MAX_REQUEST_SIZE_BYTES")
+                .unwrap_or_else(|_| "10485760".to_string()) // 10MB
+                .parse()
+                .unwrap_or(10 * 1024 * 1024),
+            data_dir: var("DATA_DIR").unwrap_or_else(|_| "./data".to_string()),
+            public_collections: var("PUBLIC_COLLECTIONS")
+                .map(|v| {
+                    v.split(',')
+                        .map(|s| name_case.canonicalize(s.trim()).into_owned())
+                        .filter(|s| !s.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            public_rate_limit_per_min: var("PUBLIC_RATE_LIMIT_PER_MIN")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .unwrap_or(60),
+            hard_limits: Limits {
+                max_k: env_or("HARD_MAX_K", 10_000),
+                max_batch_size: env_or("HARD_MAX_BATCH_SIZE", 100_000),
+                max_dimensions: env_or("HARD_MAX_DIMENSIONS", 65_536),
+                max_vectors: var("HARD_MAX_VECTORS").ok().and_then(|v| v.parse().ok()),
+            },
+            soft_limits: Limits {
+                max_k: env_or("MAX_K", 1_000),
+                max_batch_size: env_or("MAX_BATCH_SIZE", 10_000),
+                max_dimensions: env_or("MAX_DIMENSIONS", 8_192),
+                max_vectors: var("MAX_VECTORS").ok().and_then(|v| v.parse().ok()),
+            },
+            quota_warning_ratio: var("QUOTA_WARNING_RATIO")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0.9),
+            webhook_check_interval_secs: var("WEBHOOK_CHECK_INTERVAL_SECS")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .unwrap_or(30),
+            min_seq_timeout_ms: var("MIN_SEQ_TIMEOUT_MS")
+                .unwrap_or_else(|_| "5000".to_string())
+                .parse()
+                .unwrap_or(5000),
+            snapshot_dir: var("SNAPSHOT_DIR").unwrap_or_else(|_| "./snapshots".to_string()),
+            backup: BackupConfig::from_vars(&var),
+            compaction: CompactionPolicy {
+                window: var("COMPACTION_WINDOW").ok().and_then(|v| v.parse().ok()),
+                min_tombstone_ratio: var("COMPACTION_MIN_TOMBSTONE_RATIO")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(0.2),
+                min_tombstones: env_or("COMPACTION_MIN_TOMBSTONES", 1_000),
+            },
+            compaction_check_interval_secs: var("COMPACTION_CHECK_INTERVAL_SECS")
+                .unwrap_or_else(|_| "300".to_string())
+                .parse()
+                .unwrap_or(300),
+            graph_repair: RepairConfig::from_vars(&var),
+            name_case,
+            usage_retention_days: var("USAGE_RETENTION_DAYS")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .unwrap_or(30),
+            replica_of: var("REPLICA_OF").ok().filter(|v| !v.trim().is_empty()),
+            replica_api_key: var("REPLICA_API_KEY").ok(),
+            replication_poll_interval_ms: var("REPLICATION_POLL_INTERVAL_MS")
+                .unwrap_or_else(|_| "500".to_string())
+                .parse()
+                .unwrap_or(500),
+            tls: tls::TlsConfig::from_vars(&var),
+            response_format: var("RESPONSE_FORMAT")
+                .ok()
+                .and_then(|v| ResponseFormat::parse(&v))
+                .unwrap_or_default(),
+            shutdown_timeout_secs: var("SHUTDOWN_TIMEOUT_SECS")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .unwrap_or(30),
+            recovery_mode: var("RECOVERY_MODE")
+                .ok()
+                .and_then(|v| RecoveryMode::parse(&v))
+                .unwrap_or_default(),
+            #[cfg(feature = "grpc")]
+            grpc_port: var("GRPC_PORT").ok().and_then(|v| v.parse().ok()),
+            #[cfg(feature = "cluster")]
+            cluster: cluster::ClusterConfig::from_vars(&var),
+        }
+    }
+
+    fn auth_enabled(&self) -> bool {
+        self.api_key.is_some() || !self.api_keys.is_empty()
+    }
+}
+
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock as PRwLock;
+use std::collections::VecDeque;
+
+// =============================================================================
+// Configuration
+// =============================================================================
+
+#[derive(Serialize, Clone, Debug, ToSchema)]
+struct MetricsSnapshot {
+    timestamp: DateTime<Utc>,
+    memory_usage_mb: u64,
+    read_requests: u64,
+    write_requests: u64,
+    avg_latency_ms: f64,
+    storage_usage_bytes: u64,
+}
+
+struct MetricsRegistry {
+    history: PRwLock<VecDeque<MetricsSnapshot>>,
+    current_reads: std::sync::atomic::AtomicU64,
+    current_writes: std::sync::atomic::AtomicU64,
+    total_latency_us: std::sync::atomic::AtomicU64,
+    latency_count: std::sync::atomic::AtomicU64,
+    latency: LatencyHistogram,
+    /// Quota warnings sent, by collection
+    quota_warnings: PRwLock<HashMap<String, u64>>,
+}
+
+impl MetricsRegistry {
+    fn new() -> Self {
+        Self {
+            history: PRwLock::new(VecDeque::with_capacity(600)),
+            current_reads: std::sync::atomic::AtomicU64::new(0),
+            current_writes: std::sync::atomic::AtomicU64::new(0),
+            total_latency_us: std::sync::atomic::AtomicU64::new(0),
+            latency_count: std::sync::atomic::AtomicU64::new(0),
+            latency: LatencyHistogram::new(),
+            quota_warnings: PRwLock::new(HashMap::new()),
+        }
+    }
+
+    fn record_quota_warning(&self, collection: &str) {
+        *self
+            .quota_warnings
+            .write()
+            .entry(collection.to_string())
+            .or_default() += 1;
+    }
+
+    /// The quota warning counters in OpenMetrics format
+    fn render_quota_warnings(&self) -> String {
+        const NAME: &str = "surgedb_quota_warnings";
+        let mut out = format!(
+            "# TYPE {NAME} counter\n# HELP {NAME} Writes that left a collection near its vector quota.\n"
+        );
+        for (collection, count) in self.quota_warnings.read().iter() {
+            out.push_str(&format!(
+                "{NAME}_total{{collection=\"{}\"}} {}\n",
+                collection, count
+            ));
+        }
+        out
+    }
+
+    fn record_request(&self, method: &Method, latency_ms: f64) {
+        match *method {
+            Method::GET => {
+                self.current_reads
+                    .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            }
+            Method::POST | Method::PUT | Method::DELETE | Method::PATCH => {
+                self.current_writes
+                    .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            }
+            _ => {}
+        }
+        let latency_us = (latency_ms * 1000.0) as u64;
+        self.total_latency_us
+            .fetch_add(latency_us, std::sync::atomic::Ordering::Relaxed);
+        self.latency_count
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+}
+
+/// State shared by all API handlers
+#[derive(Clone)]
+pub struct AppState {
+    db: Arc<Database>,
+    config: AppConfig,
+    start_time: Instant,
+    /// Part of every ETag, so tags from a previous run never match
+    boot_id: u64,
+    metrics: Arc<MetricsRegistry>,
+    public_limiter: Arc<RateLimiter<IpAddr>>,
+    limits: Arc<LimitsRegistry>,
+    tenants: Arc<TenantRegistry>,
+    webhooks: Arc<WebhookRegistry>,
+    deployments: Arc<DeploymentRegistry>,
+    jobs: Arc<JobRegistry>,
+    mirrors: Arc<MirrorRegistry>,
+    compaction: Arc<CompactionRegistry>,
+    /// Backlog of the background graph repair
+    repairs: Arc<GraphRepairs>,
+    /// Where backups are uploaded to and restored from, if configured
+    backups: Option<Arc<BackupStore>>,
+    /// Whether this server follows a leader, and how far it got
+    replication: Arc<Replication>,
+    /// Mints and checks scoped tokens; `None` without a secret to sign with
+    tokens: Option<Arc<TokenSigner>>,
+    /// Checks API request signatures and signs webhooks; `None` when unsigned
+    signer: Option<Arc<RequestSigner>>,
+    /// Periodic tasks with no state to save, cancelled on shutdown
+    background: Arc<parking_lot::Mutex<Vec<tokio::task::AbortHandle>>>,
+    #[cfg(feature = "chaos")]
+    chaos: Arc<chaos::Chaos>,
+    #[cfg(feature = "cluster")]
+    cluster: Option<Arc<cluster::Cluster>>,
+}
+
+/// Name of the primary `API_KEY`, which is also the only admin key
+const ADMIN_KEY_NAME: &str = "admin";
+
+/// What an API key may do
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyRole {
+    /// Search, get and list, but not change anything
+    Read,
+    #[default]
+    Write,
+}
+
+/// Identity of the authenticated caller, attached to each request by `auth_middleware`
+#[derive(Clone)]
+struct Caller {
+    /// Name of the API key used, if any; `<namespace>.<name>` for tenant keys
+    key_name: Option<String>,
+    /// Whether the caller may use the `/admin` endpoints
+    admin: bool,
+    role: KeyRole,
+    /// Namespace of the tenant whose key was used, if any
+    namespace: Option<String>,
+    /// Claims of the scoped token used instead of an API key, if any
+    token: Option<Arc<TokenClaims>>,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct CreateCollectionRequest {
+    /// ASCII letters, digits, `_`, `-` and `.`, at most 64 bytes
+    #[schema(example = "my_collection")]
+    name: String,
+    #[serde(flatten)]
+    settings: CollectionSettings,
+}
+
+/// Configuration of a new collection
+#[derive(Deserialize, ToSchema)]
+struct CollectionSettings {
+    #[schema(example = 384)]
+    dimensions: usize,
+    #[serde(default)]
+    #[schema(example = "Cosine")]
+    distance_metric: DistanceMetric,
+    #[serde(default)]
+    quantization: Option<QuantizationType>,
+    /// `String` (default) or `U64` for native integer IDs
+    #[serde(default)]
+    #[schema(example = "String")]
+    id_type: Option<IdType>,
+    /// `None` (default) or `Zstd` to store metadata compressed
+    #[serde(default)]
+    #[schema(example = "None")]
+    metadata_compression: Option<MetadataCompression>,
+    /// Metadata field (e.g. `tenant_id`) whose values get their own HNSW subgraph.
+    /// Searches filtering on one value of it only traverse that subgraph.
+    #[schema(example = "tenant_id")]
+    partition_field: Option<String>,
+    /// Metadata fields (dot notation) whose text is indexed for
+    /// `/search/text`. Strings and arrays of strings are indexed.
+    #[serde(default)]
+    #[schema(example = "[\"title\", \"body\"]")]
+    text_fields: Option<Vec<String>>,
+    /// Extra vector spaces each record can carry a vector in, e.g.
+    /// `[{ "name": "title", "dimensions": 384, "distance_metric": "Cosine" }]`.
+    /// Searches pick one with `using`.
+    #[serde(default)]
+    named_vectors: Option<Vec<NamedVectorConfig>>,
+    /// `Hnsw` (default), `Flat` for exact search by scanning every vector,
+    /// for small collections, or `Segmented` for HNSW over segments built in
+    /// the background, for heavy ingest. Quantized collections only support
+    /// `Hnsw`.
+    #[serde(default)]
+    #[schema(example = "Hnsw")]
+    index: Option<IndexKind>,
+    /// Share WAL fsyncs between writes, e.g.
+    /// `{ "sync_mode": "interval", "commit_interval_ms": 10, "max_batch": 256 }`.
+    /// `sync_mode` is `always`, `interval` (the default) or `never`.
+    /// Without it writes are not synced until the next checkpoint.
+    group_commit: Option<GroupCommit>,
+    /// Reject writes whose metadata is larger or deeper than this, e.g.
+    /// `{ "max_bytes": 65536, "max_depth": 8 }`. Unlimited by default.
+    #[serde(default)]
+    metadata_limits: Option<MetadataLimits>,
+    /// Reject writes of vectors whose L2 norm is outside this band, or that
+    /// have NaN or infinite components, e.g. `{ "min_norm": 0.01, "max_norm": 100 }`.
+    /// Protects the index from garbage embeddings of a failing model.
+    #[serde(default)]
+    norm_filter: Option<NormFilter>,
+    /// Number type written vectors are given in: `float32` (default),
+    /// `float64`, converted to 32-bit floats for storage, or `int8` for
+    /// integers from -128 to 127, stored exactly. Applies to the primary
+    /// vector; query and named vectors are always floats.
+    #[serde(default)]
+    #[schema(value_type = Option<String>, example = "float64")]
+    dtype: Option<VectorDtype>,
+    /// What happens to written values storage can't hold exactly: `round`
+    /// (default) to the nearest float, or for `int8` the nearest integer, or
+    /// `exact` to reject the write. Values out of range are always rejected.
+    #[serde(default)]
+    #[schema(value_type = Option<String>, example = "exact")]
+    dtype_conversion: Option<DtypeConversion>,
+    /// Linear transform `matrix · (x - mean)` applied to query vectors, e.g.
+    /// `{ "mean": [...], "matrix": [[...], ...], "apply_on_insert": false }`
+    /// for centering or whitening. With `apply_on_insert` written vectors are
+    /// transformed too.
+    #[serde(default)]
+    #[schema(value_type = Option<Object>)]
+    transform: Option<VectorTransform>,
+    /// HNSW links per node (default 16). Higher improves recall at the cost
+    /// of memory and insert time.
+    #[serde(default)]
+    #[schema(example = 16)]
+    m: Option<usize>,
+    /// Candidate list size while building the graph (default 200)
+    #[serde(default)]
+    #[schema(example = 200)]
+    ef_construction: Option<usize>,
+    /// Default candidate list size of searches (default 100)
+    #[serde(default)]
+    #[schema(example = 100)]
+    ef_search: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
+struct InsertRequest {
+    /// String, or a JSON integer for `U64` collections
+    #[serde(deserialize_with = "string_or_u64")]
+    #[schema(example = "vec1")]
+    id: String,
+    /// In the collection's `dtype`: floats, or integers for `int8`
+    #[schema(example = "[0.1, 0.2, 0.3]")]
+    vector: Vec<f64>,
+    metadata: Option<Value>,
+    /// Sparse vector for hybrid search, as parallel `indices` and `values`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sparse: Option<SparseVector>,
+    /// Vectors in the collection's named spaces, by space name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = json!({ "title": [0.1, 0.2] }))]
+    vectors: Option<HashMap<String, Vec<f32>>>,
+}
+
+/// Accept an ID given either as a string or as an unsigned JSON integer
+fn string_or_u64<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum RawId {
+        Str(String),
+        Int(u64),
+    }
+
+    Ok(match RawId::deserialize(deserializer)? {
+        RawId::Str(s) => s,
+        RawId::Int(n) => n.to_string(),
+    })
+}
+
+/// Tell a field given as `null` (`Some(None)`) from a missing one (`None`)
+fn present<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+#[derive(Deserialize, ToSchema)]
+struct BatchInsertRequest {
+    vectors: Vec<InsertRequest>,
+    /// Scale every vector to unit length before storing it
+    #[serde(default)]
+    normalize: Option<bool>,
+    /// Return per-record norms and flag suspicious records
+    #[serde(default)]
+    with_stats: Option<bool>,
+    /// Bounds used to flag records when `with_stats` is set
+    #[serde(default)]
+    norm_checks: Option<NormChecks>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct BatchInsertWithStats {
+    upserted: usize,
+    #[serde(flatten)]
+    stats: BatchStats,
+}
+
+/// Upserted count, or the count plus norm stats when `with_stats` is set
+#[derive(Serialize, ToSchema)]
+#[serde(untagged)]
+enum BatchInsertResponse {
+    Count(usize),
+    WithStats(BatchInsertWithStats),
+}
+
+#[derive(Deserialize, ToSchema)]
+struct SetAliasRequest {
+    #[schema(example = "docs_v2")]
+    collection: String,
+}
+
+#[derive(Serialize, ToSchema)]
+struct AliasEntry {
+    alias: String,
+    collection: String,
+}
+
+/// Lifetime of a scoped token unless the request sets one
+const DEFAULT_TOKEN_TTL_SECS: u64 = 900;
+/// Longest lifetime a scoped token can be minted with
+const MAX_TOKEN_TTL_SECS: u64 = 86_400;
+
+#[derive(Deserialize, ToSchema)]
+struct MintTokenRequest {
+    /// Operations the token allows
+    #[schema(example = json!(["search"]))]
+    scopes: Vec<TokenScope>,
+    /// Collections or aliases the token applies to
+    #[schema(example = json!(["docs"]))]
+    collections: Vec<String>,
+    /// Seconds until the token expires; 900 by default, at most 86400
+    #[schema(example = 900)]
+    ttl_secs: Option<u64>,
+    /// Note on who the token is for, carried in the token
+    #[schema(example = "notebook")]
+    label: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct MintTokenResponse {
+    /// Sent as `Authorization: Bearer <token>` or `x-api-key`
+    token: String,
+    expires_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct CreateTenantKeyRequest {
+    /// Lowercase letters, digits, `_` and `-`, unique within the tenant
+    #[schema(example = "ci")]
+    name: String,
+    /// `read` keys can search, get and list but not write
+    #[serde(default)]
+    role: KeyRole,
+}
+
+#[derive(Serialize, ToSchema)]
+struct TenantKeyResponse {
+    /// Name of the key for `/admin/limits/keys`, as `<namespace>.<name>`
+    #[schema(example = "acme.ci")]
+    key_name: String,
+    /// Sent as `x-api-key` or `Authorization: Bearer <key>`; only shown once
+    key: String,
+}
+
+/// Metadata field tying chunk vectors to their document
+const DOC_ID_FIELD: &str = "doc_id";
+
+#[derive(Deserialize, ToSchema)]
+struct ReplaceDocumentRequest {
+    /// The document's new chunk set; each chunk's metadata gets `doc_id` set
+    vectors: Vec<InsertRequest>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct ReplaceDocumentResponse {
+    /// Previous chunks of the document that were removed
+    deleted: usize,
+    inserted: usize,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct StreamSearchRequest {
+    #[schema(example = "[0.1, 0.2, 0.3]")]
+    vector: Vec<f32>,
+    #[schema(example = 10000)]
+    k: usize,
+    filter: Option<Filter>,
+    /// When false, results carry no metadata and it is not read at all.
+    #[serde(default, alias = "with_payload")]
+    include_metadata: Option<bool>,
+    /// Include each result's stored vector.
+    #[serde(default)]
+    with_vector: Option<bool>,
+    /// Commit sequence returned by a write; see `SearchRequest::min_seq`.
+    #[serde(default)]
+    min_seq: Option<u64>,
+    /// HNSW candidate list size for this search, overriding the collection's
+    /// `ef_search`.
+    #[serde(default)]
+    ef_search: Option<usize>,
+    /// Leave out results whose `score` is below this.
+    #[serde(default)]
+    score_threshold: Option<f32>,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct SearchRequest {
+    #[schema(example = "[0.1, 0.2, 0.3]")]
+    vector: Vec<f32>,
+    #[schema(example = 10)]
+    k: usize,
+    filter: Option<Filter>,
+    /// When false, exclude metadata from response to reduce serialization overhead.
+    /// Metadata is then not read at all; fetch it later via `/payloads` if needed.
+    #[serde(default, alias = "with_payload")]
+    include_metadata: Option<bool>,
+    /// When true, wrap results with a `usage` block describing the work performed.
+    #[serde(default)]
+    with_usage: Option<bool>,
+    /// Embed a related record, referenced from each result's metadata, in the response.
+    #[serde(default)]
+    lookup: Option<LookupRequest>,
+    /// Commit sequence returned by a write (`x-commit-seq` header). The search
+    /// waits until that write is visible, for at most `MIN_SEQ_TIMEOUT_MS`.
+    #[serde(default)]
+    #[schema(example = 42)]
+    min_seq: Option<u64>,
+    /// HNSW candidate list size for this search, overriding the collection's
+    /// `ef_search`. Higher improves recall at the cost of latency.
+    #[serde(default)]
+    #[schema(example = 200)]
+    ef_search: Option<usize>,
+    /// Binary-quantized collections only: re-rank the candidates found on the
+    /// sign codes by their full-precision distance. Defaults to true.
+    #[serde(default)]
+    rescore: Option<bool>,
+    /// Binary-quantized collections only: candidates to re-rank, as a multiple
+    /// of `k` (at least 1). Defaults to 3.
+    #[serde(default)]
+    #[schema(example = 3.0)]
+    oversampling: Option<f32>,
+    /// Named vector space to search instead of the primary vectors; `vector`
+    /// must then have that space's dimensions
+    #[serde(default)]
+    #[schema(example = "title")]
+    using: Option<String>,
+    /// Leave out results whose `score` is below this. The cutoff is applied
+    /// during the graph search, which stops once it is out of range.
+    #[serde(default)]
+    #[schema(example = 0.8)]
+    score_threshold: Option<f32>,
+    /// Include each result's stored vector, or its vector in the `using` space
+    #[serde(default)]
+    with_vector: Option<bool>,
+    /// Keep only results at most `threshold` from `vector`, in the
+    /// collection's metric. The search looks past the nearest candidates
+    /// until `k` results qualify.
+    #[serde(default)]
+    #[schema(value_type = Option<Object>, example = json!({ "vector": [0.1, 0.2, 0.3], "threshold": 0.5 }))]
+    max_distance_to: Option<DistanceTo>,
+    /// Keep only results at least `threshold` from `vector`, such as to leave
+    /// out records like one the caller has already seen
+    #[serde(default)]
+    #[schema(value_type = Option<Object>, example = json!({ "vector": [0.1, 0.2, 0.3], "threshold": 0.2 }))]
+    min_distance_to: Option<DistanceTo>,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct GroupedSearchRequest {
+    #[schema(example = "[0.1, 0.2, 0.3]")]
+    vector: Vec<f32>,
+    /// Metadata field (dot notation) to group by; records without it, or
+    /// whose value isn't a string, number or boolean, are left out
+    #[schema(example = "document_id")]
+    group_by: String,
+    /// Most groups returned
+    #[schema(example = 10)]
+    groups: usize,
+    /// Most hits per group
+    #[schema(example = 3)]
+    group_size: usize,
+    filter: Option<Filter>,
+    #[serde(default, alias = "with_payload")]
+    include_metadata: Option<bool>,
+    #[serde(default)]
+    with_usage: Option<bool>,
+    #[serde(default)]
+    #[schema(example = 42)]
+    min_seq: Option<u64>,
+    #[serde(default)]
+    #[schema(example = 200)]
+    ef_search: Option<usize>,
+    #[serde(default)]
+    rescore: Option<bool>,
+    #[serde(default)]
+    #[schema(example = 3.0)]
+    oversampling: Option<f32>,
+    /// Leave out results whose `score` is below this
+    #[serde(default)]
+    #[schema(example = 0.8)]
+    score_threshold: Option<f32>,
+    #[serde(default)]
+    with_vector: Option<bool>,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct RecommendRequest {
+    /// IDs of records to find more like
+    #[schema(example = json!(["doc1", "doc7"]))]
+    positive: Vec<String>,
+    /// IDs of records to steer away from
+    #[serde(default)]
+    #[schema(example = json!(["doc3"]))]
+    negative: Vec<String>,
+    /// `average_vector` (default) searches once, near the average of the
+    /// positives pushed away from the negatives; `best_score` searches near
+    /// each positive and ranks by the closest example
+    #[serde(default)]
+    strategy: RecommendStrategy,
+    #[schema(example = 10)]
+    k: usize,
+    filter: Option<Filter>,
+    #[serde(default, alias = "with_payload")]
+    include_metadata: Option<bool>,
+    #[serde(default)]
+    with_usage: Option<bool>,
+    #[serde(default)]
+    #[schema(example = 200)]
+    ef_search: Option<usize>,
+    /// Leave out results whose `score` is below this
+    #[serde(default)]
+    #[schema(example = 0.8)]
+    score_threshold: Option<f32>,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct HardNegativesRequest {
+    /// Query vectors, each with the ID of the record that answers it
+    #[schema(value_type = Vec<Object>, example = json!([{ "vector": [0.1, 0.2, 0.3], "positive_id": "doc1" }]))]
+    queries: Vec<HardNegativeQuery>,
+    /// Negatives per query
+    #[schema(example = 5)]
+    k: usize,
+    /// Nearest non-positive records to pass over first, as likely false
+    /// negatives
+    #[serde(default)]
+    #[schema(example = 3)]
+    skip: usize,
+    /// Keep only records whose `score` is at least this much below the
+    /// positive's
+    #[serde(default)]
+    #[schema(example = 0.05)]
+    margin: Option<f32>,
+    /// Applied to every query
+    filter: Option<Filter>,
+    #[serde(default, alias = "with_payload")]
+    include_metadata: Option<bool>,
+    #[serde(default)]
+    with_usage: Option<bool>,
+    #[serde(default)]
+    #[schema(example = 42)]
+    min_seq: Option<u64>,
+    #[serde(default)]
+    #[schema(example = 200)]
+    ef_search: Option<usize>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct MinedNegativesResult {
+    positive_id: String,
+    /// Score of the positive for the query
+    positive_score: f32,
+    /// Nearest first
+    negatives: Vec<SearchResult>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct HardNegativesResponse {
+    /// One entry per query, in query order
+    results: Vec<MinedNegativesResult>,
+    /// Work of all queries together, if `with_usage` was set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    usage: Option<SearchUsageResponse>,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct BatchSearchRequest {
+    /// Query vectors, searched in parallel
+    #[schema(example = "[[0.1, 0.2, 0.3], [0.3, 0.2, 0.1]]")]
+    queries: Vec<Vec<f32>>,
+    #[schema(example = 10)]
+    k: usize,
+    /// Applied to every query
+    filter: Option<Filter>,
+    #[serde(default, alias = "with_payload")]
+    include_metadata: Option<bool>,
+    #[serde(default)]
+    with_usage: Option<bool>,
+    #[serde(default)]
+    #[schema(example = 42)]
+    min_seq: Option<u64>,
+    #[serde(default)]
+    #[schema(example = 200)]
+    ef_search: Option<usize>,
+    #[serde(default)]
+    rescore: Option<bool>,
+    #[serde(default)]
+    #[schema(example = 3.0)]
+    oversampling: Option<f32>,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct HybridSearchRequest {
+    /// Dense query vector
+    #[schema(example = "[0.1, 0.2, 0.3]")]
+    vector: Vec<f32>,
+    /// Sparse query vector, e.g. BM25 term weights or SPLADE output
+    sparse: SparseVector,
+    #[schema(example = 10)]
+    k: usize,
+    filter: Option<Filter>,
+    /// `{"method": "rrf", "k": 60}` (default) or `{"method": "weighted_sum", "alpha": 0.5}`
+    #[serde(default)]
+    fusion: Option<Fusion>,
+    #[serde(default, alias = "with_payload")]
+    include_metadata: Option<bool>,
+    #[serde(default)]
+    with_usage: Option<bool>,
+    #[serde(default)]
+    #[schema(example = 42)]
+    min_seq: Option<u64>,
+    #[serde(default)]
+    #[schema(example = 200)]
+    ef_search: Option<usize>,
+    #[serde(default)]
+    rescore: Option<bool>,
+    #[serde(default)]
+    #[schema(example = 3.0)]
+    oversampling: Option<f32>,
+    /// Report how each result's score is made up
+    #[serde(default)]
+    explain: Option<bool>,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct TextSearchRequest {
+    /// Keywords matched against the collection's `text_fields`
+    #[schema(example = "rust vector database")]
+    query: String,
+    #[schema(example = 10)]
+    k: usize,
+    filter: Option<Filter>,
+    /// Dense query vector; when given, the keyword ranking is fused with
+    /// the vector search
+    #[serde(default)]
+    #[schema(example = "[0.1, 0.2, 0.3]")]
+    vector: Option<Vec<f32>>,
+    /// How to fuse with `vector`: `{"method": "rrf", "k": 60}` (default) or
+    /// `{"method": "weighted_sum", "alpha": 0.5}`
+    #[serde(default)]
+    fusion: Option<Fusion>,
+    #[serde(default, alias = "with_payload")]
+    include_metadata: Option<bool>,
+    #[serde(default)]
+    with_usage: Option<bool>,
+    #[serde(default)]
+    #[schema(example = 42)]
+    min_seq: Option<u64>,
+    #[serde(default)]
+    #[schema(example = 200)]
+    ef_search: Option<usize>,
+    #[serde(default)]
+    rescore: Option<bool>,
+    #[serde(default)]
+    #[schema(example = 3.0)]
+    oversampling: Option<f32>,
+    /// Report how each result's score is made up
+    #[serde(default)]
+    explain: Option<bool>,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct LookupRequest {
+    /// Metadata field (dot notation) holding the related record's ID
+    #[schema(example = "parent_id")]
+    field: String,
+    /// Collection holding the related records; defaults to the searched collection
+    collection: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct LookupResult {
+    id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<Value>,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct PayloadsRequest {
+    #[schema(example = "[\"doc1\", \"doc2\"]")]
+    ids: Vec<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct Payload {
+    id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<Value>,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct CacheFilterRequest {
+    /// Filter whose matches are kept materialized
+    filter: Filter,
+}
+
+#[derive(Serialize, ToSchema)]
+struct CachedFilter {
+    id: String,
+    filter: Filter,
+    /// Live vectors currently matching the filter
+    matches: u64,
+    /// Searches answered from the cache
+    hits: u64,
+}
+
+/// Records sampled as queries by a filter recall estimate unless the request says
+const DEFAULT_FILTER_RECALL_QUERIES: usize = 50;
+/// Most queries a filter recall estimate may sample
+const MAX_FILTER_RECALL_QUERIES: usize = 1_000;
+
+#[derive(Deserialize, IntoParams)]
+struct FilterRecallParams {
+    /// Matching records used as queries (default 50, at most 1000)
+    #[param(example = 50)]
+    sample_size: Option<usize>,
+    /// Neighbors per query that recall is measured on (default 10)
+    #[param(example = 10)]
+    k: Option<usize>,
+    /// Recall below which an exact scan is recommended (default 0.95)
+    #[param(example = 0.95)]
+    target_recall: Option<f64>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct FilterRecallResponse {
+    /// Cached filter ID
+    id: String,
+    #[serde(flatten)]
+    #[schema(value_type = Object)]
+    report: surgedb_core::FilterRecall,
+}
+
+impl From<CachedFilterInfo> for CachedFilter {
+    fn from(info: CachedFilterInfo) -> Self {
+        Self {
+            id: info.id,
+            filter: info.filter,
+            matches: info.matches,
+            hits: info.hits,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct SearchResult {
+    id: String,
+    distance: f32,
+    /// Similarity, higher is closer: cosine, dot product or Jaccard similarity
+    /// for those metrics, `1 / (1 + distance)` for the others
+    score: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    vector: Option<Vec<f32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<Value>,
+    /// Related record fetched via `lookup`, if requested and found
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lookup: Option<LookupResult>,
+}
+
+/// Work performed by a search, for cost attribution
+#[derive(Serialize, ToSchema)]
+struct SearchUsageResponse {
+    vectors_scanned: u64,
+    graph_hops: u64,
+    rescored_candidates: u64,
+    /// Time spent executing the search on the worker thread, in microseconds
+    cpu_time_us: u64,
+}
+
+#[derive(Serialize, ToSchema)]
+struct SearchWithUsageResponse {
+    results: Vec<SearchResult>,
+    usage: SearchUsageResponse,
+}
+
+/// Plain result list, or results plus usage when `with_usage` is set
+#[derive(Serialize, ToSchema)]
+#[serde(untagged)]
+enum SearchResponse {
+    Results(Vec<SearchResult>),
+    WithUsage(SearchWithUsageResponse),
+}
+
+#[derive(Serialize, ToSchema)]
+struct SearchGroupResult {
+    /// Value of the `group_by` field shared by the hits
+    key: Value,
+    /// Nearest first
+    hits: Vec<SearchResult>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct GroupedSearchResponse {
+    /// Ordered by each group's nearest hit
+    groups: Vec<SearchGroupResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    usage: Option<SearchUsageResponse>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct HybridSearchResult {
+    id: String,
+    /// Fused score; higher is better
+    score: f32,
+    /// Distance to the dense query, if the record was a dense candidate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    distance: Option<f32>,
+    /// Keyword score, if the record was a keyword candidate: the dot product
+    /// with the sparse query, or the BM25 score of the text query
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sparse_score: Option<f32>,
+    /// Ranks in the dense and keyword rankings and what each adds to
+    /// `score`, when `explain` is set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    explanation: Option<FusionExplanation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<Value>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct HybridSearchWithUsageResponse {
+    results: Vec<HybridSearchResult>,
+    usage: SearchUsageResponse,
+}
+
+/// Plain result list, or results plus usage when `with_usage` is set
+#[derive(Serialize, ToSchema)]
+#[serde(untagged)]
+enum HybridSearchResponse {
+    Results(Vec<HybridSearchResult>),
+    WithUsage(HybridSearchWithUsageResponse),
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+struct ErrorResponse {
+    error: String,
+}
+
+#[derive(Serialize, ToSchema)]
+struct HealthResponse {
+    status: String,
+    version: String,
+    uptime_seconds: u64,
+    memory_usage_mb: u64,
+}
+
+/// What the server supports, so clients can adapt without hardcoding it
+#[derive(Serialize, ToSchema)]
+struct CapabilitiesResponse {
+    #[schema(example = "1.0.0")]
+    version: String,
+    /// Accepted values of a collection's `distance_metric`
+    distance_metrics: Vec<DistanceMetric>,
+    /// Values of `quantization` that take effect; `SQ8` is accepted but
+    /// collections keep full precision
+    quantizations: Vec<QuantizationType>,
+    /// Indexes collections can be searched with
+    #[schema(example = json!(["HNSW", "Flat"]))]
+    index_types: Vec<&'static str>,
+    id_types: Vec<IdType>,
+    metadata_compression: Vec<MetadataCompression>,
+    features: FeatureFlags,
+    limits: RequestLimits,
+}
+
+#[derive(Serialize, ToSchema)]
+struct FeatureFlags {
+    /// Dense plus sparse or full-text search with fused rankings
+    hybrid: bool,
+    /// Sparse vectors on records
+    sparse: bool,
+    /// Extra vectors per record in declared `named_vectors` spaces
+    named_vectors: bool,
+    /// BM25 search over a collection's `text_fields`
+    text_search: bool,
+    /// Results grouped by a metadata field
+    grouping: bool,
+    /// Per-tenant subgraphs via `partition_field`
+    partitions: bool,
+    /// `Expr` filters in Rhai
+    filter_expressions: bool,
+    /// Unauthenticated search on some collections
+    public_search: bool,
+    /// Short-lived tokens scoped to some operations and collections
+    scoped_tokens: bool,
+    /// API key requests must carry an HMAC signature
+    signed_requests: bool,
+    /// Backups to object storage under `/backups`
+    backups: bool,
+    grpc: bool,
+}
+
+/// Request limits that apply to the caller
+#[derive(Serialize, ToSchema)]
+struct RequestLimits {
+    /// Max `k` of a search, and of `ef_search` and `k * oversampling`
+    max_k: usize,
+    /// Max records of a batch insert, and queries of a batch search
+    max_batch_size: usize,
+    /// Max dimensions of a new collection
+    max_dimensions: usize,
+    /// Max vectors a collection may hold after a write by the caller
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_vectors: Option<usize>,
+    max_request_size_bytes: usize,
+    request_timeout_secs: u64,
+    /// Longest a search waits for its `min_seq`
+    min_seq_timeout_ms: u64,
+    /// Max cached filters per collection
+    max_cached_filters: usize,
+    /// Max length of an `Expr` filter's source
+    max_expr_length: usize,
+}
+
+#[derive(Serialize, ToSchema)]
+struct ReadinessResponse {
+    /// Whether recovery finished and writes are accepted
+    ready: bool,
+    recovery: surgedb_core::RecoveryStatus,
+}
+
+#[derive(Serialize, ToSchema)]
+struct SystemCollectionInfo {
+    #[schema(example = "_usage")]
+    name: String,
+    vector_count: usize,
+}
+
+#[derive(Serialize, ToSchema)]
+struct ActivityResponse {
+    /// Minutes in each series
+    minutes: usize,
+    /// Per collection, one entry a minute, oldest first and ending with the
+    /// current minute
+    collections: HashMap<String, Vec<ActivityMinute>>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct StatsResponse {
+    uptime_seconds: u64,
+    database: surgedb_core::DatabaseStats,
+}
+
+#[derive(Serialize, ToSchema)]
+struct CollectionInfo {
+    /// Collection name, after following an alias
+    name: String,
+    config: DbConfig,
+    vector_count: usize,
+    /// Deleted or overwritten vectors whose slots have not been reclaimed
+    deleted_count: usize,
+    /// Commit sequence of the last write, as in the `x-commit-seq` header
+    write_seq: u64,
+    /// ID of the write log `write_seq` counts in, as in the `x-log-id`
+    /// header; only on-disk collections keep one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    log_id: Option<u64>,
+    /// Estimated in-memory bytes of the vectors, graph, IDs and metadata
+    memory_usage_bytes: usize,
+    memory_breakdown: MemoryBreakdown,
+    /// Records refused by `norm_filter` since the server started
+    norm_rejections: u64,
+    /// Nodes and links of each HNSW layer; absent for the flat index
+    #[serde(skip_serializing_if = "Option::is_none")]
+    graph: Option<GraphStats>,
+}
+
+#[derive(Deserialize, IntoParams)]
+struct PaginationParams {
+    /// Records to skip; ignored when `cursor` is given
+    #[param(example = 0)]
+    offset: Option<usize>,
+    #[param(example = 10)]
+    limit: Option<usize>,
+    /// Page with a cursor instead: empty to start, then the previous page's
+    /// `next_cursor`
+    #[param(example = "")]
+    cursor: Option<String>,
+}
+
+/// Output format of an index export
+#[derive(Deserialize, Clone, Copy, Default, ToSchema)]
+#[serde(rename_all = "lowercase")]
+enum GraphFormat {
+    #[default]
+    Graphml,
+    Edgelist,
+}
+
+#[derive(Deserialize, IntoParams)]
+struct IndexExportParams {
+    /// `graphml` (default) or `edgelist`
+    format: Option<GraphFormat>,
+    /// Only export this HNSW layer
+    #[param(example = 0)]
+    level: Option<usize>,
+    /// Export at most this many nodes, taken breadth-first from the entry point
+    #[param(example = 1000)]
+    sample: Option<usize>,
+}
+
+/// Clusters in a collection summary unless `k` says otherwise
+const DEFAULT_SUMMARY_CLUSTERS: usize = 8;
+
+#[derive(Deserialize, IntoParams)]
+struct SummaryParams {
+    /// Most clusters, up to 256; defaults to 8
+    #[param(example = 8)]
+    k: Option<usize>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct SummaryResponse {
+    /// Clusters asked for; fewer come back when the records allow no more
+    k: usize,
+    /// `clusters` (each with `centroid`, `size`, `medoid` and
+    /// `representatives`), `vector_count`, `sampled` and `write_seq`
+    #[serde(flatten)]
+    #[schema(value_type = Object)]
+    summary: CollectionSummary,
+}
+
+#[derive(Deserialize, ToSchema, Default)]
+struct SnapshotRequest {
+    /// File name inside `SNAPSHOT_DIR`; defaults to `<name>.snap`, or
+    /// `<name>.parquet` for Parquet files
+    #[schema(example = "docs.snap")]
+    file: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct SnapshotResponse {
+    /// File name inside `SNAPSHOT_DIR`
+    file: String,
+    /// Vectors written or restored
+    vectors: usize,
+}
+
+#[derive(Deserialize, ToSchema, Default)]
+struct BackupRequest {
+    /// Collections to back up or restore; all of them if omitted
+    collections: Option<Vec<String>>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct RestoreBackupResponse {
+    /// ID of the backup restored from
+    id: String,
+    collections: Vec<RestoredCollection>,
+}
+
+/// Largest sample a tuning sweep may index per trial
+const MAX_TUNE_SAMPLE: usize = 50_000;
+/// Most `m` x `ef_construction` indexes a tuning sweep may build
+const MAX_TUNE_BUILDS: usize = 16;
+
+#[derive(Deserialize, ToSchema, Default)]
+struct TuneRequest {
+    /// Vectors indexed by every trial (default 2000)
+    #[schema(example = 2000)]
+    sample_size: Option<usize>,
+    /// Held-out vectors searched by every trial (default 50)
+    #[schema(example = 50)]
+    queries: Option<usize>,
+    /// Neighbors per query that recall is measured on (default 10)
+    #[schema(example = 10)]
+    k: Option<usize>,
+    /// Minimum recall@k of an acceptable trial (default 0.95)
+    #[schema(example = 0.95)]
+    target_recall: Option<f64>,
+    /// Maximum mean search latency of an acceptable trial
+    #[schema(example = 500.0)]
+    max_latency_us: Option<f64>,
+    /// Values to try (default `[8, 16, 32]`)
+    m: Option<Vec<usize>>,
+    /// Values to try (default `[100, 200, 400]`)
+    ef_construction: Option<Vec<usize>>,
+    /// Values to try (default `[16, 32, 64, 128, 256]`)
+    ef_search: Option<Vec<usize>>,
+    /// Make the recommended `ef_search` the collection's default. `m` and
+    /// `ef_construction` only apply to a new collection.
+    #[serde(default)]
+    apply: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+struct TuneResponse {
+    #[serde(flatten)]
+    report: surgedb_core::tune::TuneReport,
+    /// Whether the recommended `ef_search` became the collection's default
+    applied: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+struct VectorResponse {
+    id: String,
+    vector: Vec<f32>,
+    metadata: Option<Value>,
+    /// Grows with every write to the vector; send it in `If-Match` to
+    /// upsert or delete only if nobody else has written it since. Only
+    /// returned when a single vector is fetched.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<u64>,
+    /// Vectors in the collection's named spaces, by space name
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    vectors: HashMap<String, Vec<f32>>,
+}
+
+// =============================================================================
+// OpenAPI Documentation
+// =============================================================================
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        health_check,
+        readiness_check,
+        get_stats,
+        get_activity,
+        list_system_collections,
+        list_system_records,
+        get_metrics,
+        get_metrics_history,
+        get_capabilities,
+        create_collection,
+        put_collection,
+        collection_exists,
+        list_collections,
+        get_collection_info,
+        delete_collection,
+        update_collection,
+        insert_vector,
+        list_vectors,
+        scroll_vectors,
+        count_vectors,
+        count_vectors_post,
+        export_index,
+        rebuild_field_index,
+        collection_summary,
+        snapshot_collection,
+        restore_collection,
+        create_backup,
+        list_backups,
+        restore_backup,
+        export_parquet,
+        import_parquet,
+        tune_collection,
+        batch_insert_vector,
+        import_vectors,
+        bulk_load_vectors,
+        upsert_vector,
+        replace_document,
+        get_vector,
+        vector_exists,
+        delete_vector,
+        delete_vectors,
+        update_metadata,
+        search_vector,
+        search_stream,
+        mine_hard_negatives,
+        recommend_vectors,
+        search_grouped,
+        search_batch,
+        search_hybrid,
+        search_text,
+        get_payloads,
+        cache_filter,
+        list_cached_filters,
+        uncache_filter,
+        estimate_filter_recall,
+        create_webhook,
+        list_webhooks,
+        delete_webhook,
+        get_compaction,
+        compact_collection,
+        set_compaction,
+        create_mirror,
+        list_mirrors,
+        delete_mirror,
+        replication_status,
+        replication_catalog,
+        replication_log,
+        replication_snapshot,
+        list_aliases,
+        set_alias,
+        delete_alias,
+        create_deployment,
+        list_deployments,
+        get_deployment,
+        list_jobs,
+        get_job,
+        get_limits,
+        update_soft_limits,
+        set_key_limits,
+        delete_key_limits,
+        get_log_filter,
+        set_log_filter,
+        reset_log_filter,
+        mint_token,
+        list_tenants,
+        put_tenant,
+        delete_tenant,
+        create_tenant_key,
+        revoke_tenant_key,
+    ),
+    components(
+        schemas(
+            CreateCollectionRequest, CollectionSettings, PutCollectionResponse, UpdateCollectionRequest, UpdateCollectionResponse, InsertRequest, BatchInsertRequest, BatchInsertResponse, ImportResponse, BulkLoadRecord, BulkLoadResponse,
+            BatchInsertWithStats, NormChecks, BatchStats, NormStats, FlaggedRecord, Anomaly,
+            ReplaceDocumentRequest, ReplaceDocumentResponse, DeleteVectorsRequest, DeleteVectorsResponse, UpdateMetadataRequest,
+            SearchRequest, StreamSearchRequest, RecommendRequest, HardNegativesRequest, MinedNegativesResult, HardNegativesResponse, GroupedSearchRequest, SearchGroupResult, GroupedSearchResponse, BatchSearchRequest, SearchResult, SearchResponse, LookupRequest, LookupResult, SearchWithUsageResponse,
+            HybridSearchRequest, TextSearchRequest, HybridSearchResult, HybridSearchResponse, HybridSearchWithUsageResponse,
+            SearchUsageResponse, PayloadsRequest, Payload, CacheFilterRequest, CachedFilter, FilterRecallResponse, ErrorResponse, HealthResponse,
+            ReadinessResponse, CapabilitiesResponse, FeatureFlags, RequestLimits,
+            StatsResponse, ActivityResponse, SystemCollectionInfo, CollectionInfo, VectorResponse, SnapshotRequest, SnapshotResponse, BackupRequest, BackupManifest, BackedUpCollection, RestoreBackupResponse, RestoredCollection, TuneRequest, TuneResponse, MetricsSnapshot, VectorListEntry, VectorListPage, ScrollRequest, ScrollResponse, CountRequest, CountResponse, GraphFormat, SummaryResponse,
+            Limits, LimitOverrides, LimitsSnapshot, SetLogFilterRequest, LogFilterState, MintTokenRequest, MintTokenResponse, TokenScope,
+            TenantQuotas, TenantInfo, TenantUsage, CreateTenantKeyRequest, TenantKeyResponse, KeyRole,
+            CreateWebhookRequest, Webhook, ThresholdMetric, CompactionStatus, CompactionRun,
+            CompactionTrigger, SetCompactionRequest, MirrorRequest, Mirror, MirrorState,
+            ReplicationStatus, ReplicationRole, FollowerState, CollectionReplication, Catalog, CatalogEntry, LogPage,
+            SetAliasRequest, AliasEntry, DeploymentRequest, DeploymentAssertions, Deployment,
+            DeploymentStatus, DeploymentStep, Job, JobKind, JobStatus
+        )
+    ),
+    tags(
+        (name = "surgedb", description = "SurgeDB Vector Search API")
+    )
+)]
+struct ApiDoc;
+
+// =============================================================================
+// Middleware
+// =============================================================================
+
+async fn metrics_middleware(
+    State(state): State<AppState>,
+    req: Request,
+    next: Next,
+) -> impl IntoResponse {
+    let start = Instant::now();
+    let method = req.method().clone();
+    let trace_id = latency::trace_id(req.headers());
+
+    let response = next.run(req).await;
+
+    let elapsed = start.elapsed();
+    state
+        .metrics
+        .record_request(&method, elapsed.as_secs_f64() * 1000.0);
+    state.metrics.latency.observe(elapsed, trace_id);
+
+    response
+}
+
+/// Span for a request, carrying the caller's trace ID so database logs can be
+/// matched with the trace of the request that caused them
+fn request_span(req: &Request) -> tracing::Span {
+    tracing::debug_span!(
+        "request",
+        method = %req.method(),
+        uri = %req.uri(),
+        trace_id = latency::trace_id(req.headers()),
+    )
+}
+
+/// [`tokio::task::spawn_blocking`] inside the current request span
+pub(crate) fn spawn_blocking<F, R>(f: F) -> tokio::task::JoinHandle<R>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    let span = tracing::Span::current();
+    tokio::task::spawn_blocking(move || span.in_scope(f))
+}
+
+fn perf_enabled() -> bool {
+    std::env::var("SURGEDB_PERF_LOG").is_ok()
+}
+
+fn log_perf(op: &str, total_ms: f64, work_ms: f64, map_ms: Option<f64>, count: Option<usize>) {
+    if !perf_enabled() {
+        return;
+    }
+    match (map_ms, count) {
+        (Some(map_ms), Some(count)) => {
+            info!(target: "perf", op, total_ms, work_ms, map_ms, count);
+        }
+        (Some(map_ms), None) => {
+            info!(target: "perf", op, total_ms, work_ms, map_ms);
+        }
+        (None, Some(count)) => {
+            info!(target: "perf", op, total_ms, work_ms, count);
+        }
+        (None, None) => {
+            info!(target: "perf", op, total_ms, work_ms);
+        }
+    }
+}
+
+async fn auth_middleware(
+    State(state): State<AppState>,
+    mut req: Request,
+    next: Next,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let caller = if state.config.auth_enabled() {
+        let auth_header = req
+            .headers()
+            .get("x-api-key")
+            .and_then(|v| v.to_str().ok())
+            .or_else(|| {
+                req.headers()
+                    .get(axum::http::header::AUTHORIZATION)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.strip_prefix("Bearer "))
+            });
+
+        let caller = auth_header
+            .and_then(|key| authenticate(&state, key).or_else(|| token_caller(&state, key)));
+        match caller {
+            Some(caller) => caller,
+            None if auth_header.is_none() && is_public_search(&state.config, &req) => {
+                check_public_rate_limit(&state, &req)?;
+                Caller {
+                    key_name: None,
+                    admin: false,
+                    role: KeyRole::Read,
+                    namespace: None,
+                    token: None,
+                }
+            }
+            None => {
+                return Err((
+                    StatusCode::UNAUTHORIZED,
+                    Json(ErrorResponse {
+                        error: "Invalid or missing API key".to_string(),
+                    }),
+                ));
+            }
+        }
+    } else {
+        Caller {
+            key_name: None,
+            admin: true,
+            role: KeyRole::Write,
+            namespace: None,
+            token: None,
+        }
+    };
+
+    if let Some(claims) = &caller.token {
+        let allowed = tokens::required_scope(&req).is_some_and(|(scope, name)| {
+            claims.scopes.contains(&scope) && claims.covers(name, &state.db.resolve_name(name))
+        });
+        if !allowed {
+            return Err((
+                StatusCode::FORBIDDEN,
+                Json(ErrorResponse {
+                    error: "Token does not allow this request".to_string(),
+                }),
+            ));
+        }
+    }
+
+    if caller.role == KeyRole::Read && !leaves_database_unchanged(&req) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(ErrorResponse {
+                error: "Read-only API key".to_string(),
+            }),
+        ));
+    }
+
+    if let Some(namespace) = &caller.namespace {
+        let allowed = match tenants::target(&req) {
+            tenants::Target::Namespace => true,
+            tenants::Target::Collection(name) => {
+                tenants::in_namespace(namespace, &state.db.resolve_name(name))
+            }
+            tenants::Target::Other => false,
+        };
+        if !allowed {
+            return Err((
+                StatusCode::FORBIDDEN,
+                Json(ErrorResponse {
+                    error: format!(
+                        "Tenant keys can only use collections named {}.<name>",
+                        namespace
+                    ),
+                }),
+            ));
+        }
+    }
+
+    let path = req.uri().path();
+    if !caller.admin && (path.starts_with("/admin") || path.starts_with("/_system")) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(ErrorResponse {
+                error: "Admin API key required".to_string(),
+            }),
+        ));
+    }
+
+    if let Some(name) = changed_collection(&req) {
+        check_writable(&state.db, name)?;
+    }
+
+    if let Some(leader) = state.replication.leader_url() {
+        if !path.starts_with("/admin") && !leaves_database_unchanged(&req) {
+            return Err((
+                StatusCode::FORBIDDEN,
+                Json(ErrorResponse {
+                    error: format!("Read-only replica; send writes to the leader at {}", leader),
+                }),
+            ));
+        }
+    }
+
+    req.extensions_mut().insert(caller);
+    Ok(next.run(req).await)
+}
+
+/// Resolve an API key to a caller: the primary `API_KEY` is admin, `API_KEYS`
+/// entries are not, and tenant keys are limited to their namespace; named and
+/// tenant keys carry their role
+fn authenticate(state: &AppState, key: &str) -> Option<Caller> {
+    let config = &state.config;
+    if config.api_key.as_deref() == Some(key) {
+        return Some(Caller {
+            key_name: Some(ADMIN_KEY_NAME.to_string()),
+            admin: true,
+            role: KeyRole::Write,
+            namespace: None,
+            token: None,
+        });
+    }
+    let named = config
+        .api_keys
+        .iter()
+        .find(|(_, (secret, _))| secret.as_str() == key)
+        .map(|(name, &(_, role))| Caller {
+            key_name: Some(name.clone()),
+            admin: false,
+            role,
+            namespace: None,
+            token: None,
+        });
+    named.or_else(|| {
+        state.tenants.authenticate(key).map(|tenant| Caller {
+            key_name: Some(format!("{}.{}", tenant.namespace, tenant.name)),
+            admin: false,
+            role: tenant.role,
+            namespace: Some(tenant.namespace),
+            token: None,
+        })
+    })
+}
+
+/// Reject requests made with an API key (or without auth) that aren't signed
+/// with the request signing secret, if one is set
+///
+/// Scoped tokens and public searches are meant for clients that can't keep a
+/// secret, so they are left unsigned.
+async fn signature_middleware(
+    State(state): State<AppState>,
+    req: Request,
+    next: Next,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let Some(signer) = state.signer.clone() else {
+        return Ok(next.run(req).await);
+    };
+    let signed_caller = req.extensions().get::<Caller>().is_some_and(|caller| {
+        caller.token.is_none() && (caller.admin || caller.key_name.is_some())
+    });
+    if !signed_caller {
+        return Ok(next.run(req).await);
+    }
+
+    let (parts, body) = req.into_parts();
+    let body = axum::body::to_bytes(body, state.config.max_request_size_bytes)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+        })?;
+    let header = |name: &str| parts.headers.get(name).and_then(|v| v.to_str().ok());
+    let path = parts
+        .uri
+        .path_and_query()
+        .map(|p| p.as_str())
+        .unwrap_or_else(|| parts.uri.path());
+    signer
+        .verify(
+            header(TIMESTAMP_HEADER),
+            header(SIGNATURE_HEADER),
+            parts.method.as_str(),
+            path,
+            &body,
+            Utc::now().timestamp(),
+        )
+        .map_err(|e| {
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error: format!("Invalid request signature: {}", e),
+                }),
+            )
+        })?;
+    Ok(next
+        .run(Request::from_parts(parts, axum::body::Body::from(body)))
+        .await)
+}
+
+/// Resolve a scoped token to a caller without admin rights or a key name
+fn token_caller(state: &AppState, token: &str) -> Option<Caller> {
+    let claims = state
+        .tokens
+        .as_ref()?
+        .verify(token, Utc::now().timestamp())?;
+    Some(Caller {
+        key_name: None,
+        admin: false,
+        role: KeyRole::Write,
+        namespace: None,
+        token: Some(Arc::new(claims)),
+    })
+}
+
+/// Reject a request whose `value` for `name` exceeds the caller's `limit`
+fn check_limit(
+    name: &str,
+    value: usize,
+    limit: usize,
+) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    if value > limit {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: format!("{} of {} exceeds limit of {}", name, value, limit),
+            }),
+        ));
+    }
+    Ok(())
+}
+
+/// Per-search overrides, with `ef_search` and `oversampling` checked against `max_k`
+fn search_params(
+    k: usize,
+    ef_search: Option<usize>,
+    rescore: Option<bool>,
+    oversampling: Option<f32>,
+    limits: &Limits,
+) -> Result<SearchParams, (StatusCode, Json<ErrorResponse>)> {
+    if let Some(ef_search) = ef_search {
+        check_limit("ef_search", ef_search, limits.max_k)?;
+    }
+    if let Some(oversampling) = oversampling {
+        if oversampling.is_nan() || oversampling < 1.0 {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: format!("oversampling must be at least 1, got {}", oversampling),
+                }),
+            ));
+        }
+        let candidates = (k as f32 * oversampling).ceil() as usize;
+        check_limit("k * oversampling", candidates, limits.max_k)?;
+    }
+    Ok(SearchParams {
+        ef_search,
+        rescore,
+        oversampling,
+        ..Default::default()
+    })
+}
+
+/// Metric of the space a search runs in: the named space `using`, or the
+/// collection's primary vectors
+fn search_metric(collection: &Collection, using: Option<&str>) -> DistanceMetric {
+    let config = collection.config();
+    using
+        .and_then(|space| config.named_vectors.iter().find(|n| n.name == space))
+        .map_or(config.distance_metric, |named| named.distance_metric)
+}
+
+/// Stored vectors of the records `ids`, in the named space `using` if given,
+/// or all `None` unless `with_vector`
+fn result_vectors<'a>(
+    collection: &Collection,
+    using: Option<&str>,
+    ids: impl ExactSizeIterator<Item = &'a VectorId>,
+    with_vector: bool,
+) -> Vec<Option<Vec<f32>>> {
+    if !with_vector {
+        return vec![None; ids.len()];
+    }
+    ids.map(|id| {
+        let id = id.to_string();
+        match using {
+            Some(space) => collection
+                .get_named_vectors(&id)
+                .into_iter()
+                .find_map(|(name, vector)| (name == space).then_some(vector)),
+            None => collection.get(&id).ok().flatten().map(|(vector, _)| vector),
+        }
+    })
+    .collect()
+}
+
+/// Metadata of the records `ids`, in order, or all `None` unless
+/// `include_metadata`
+///
+/// Records deleted since they were ranked get `None`.
+fn result_metadata<'a>(
+    collection: &Collection,
+    ids: impl ExactSizeIterator<Item = &'a VectorId>,
+    include_metadata: bool,
+) -> Vec<Option<Value>> {
+    if !include_metadata {
+        return vec![None; ids.len()];
+    }
+    let ids: Vec<String> = ids.map(|id| id.to_string()).collect();
+    let mut found: HashMap<String, Option<Value>> = collection
+        .get_metadata_batch(&ids)
+        .into_iter()
+        .map(|(id, metadata)| (id.to_string(), metadata))
+        .collect();
+    ids.iter().map(|id| found.remove(id).flatten()).collect()
+}
+
+/// Hold off traffic the database can't serve while it recovers
+///
+/// Nothing is served until the snapshots are loaded. While WAL tails replay,
+/// reads are served from the partially recovered collections but writes are
+/// rejected, since they would land before the entries still being replayed.
+async fn recovery_middleware(
+    State(state): State<AppState>,
+    req: Request,
+    next: Next,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    match recovery_error(&state.db, is_read_request(&req)) {
+        None => Ok(next.run(req).await),
+        Some(error) => Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ErrorResponse { error }),
+        )),
+    }
+}
+
+/// Why a read (or write) can't be served while the database recovers, if it can't
+pub(crate) fn recovery_error(db: &Database, read: bool) -> Option<String> {
+    let status = db.recovery_status();
+    if status.is_ready() || (status.is_readable() && read) {
+        return None;
+    }
+
+    let error = match status.phase {
+        RecoveryPhase::LoadingSnapshots => format!(
+            "Database is recovering (loaded {}/{} collections)",
+            status.collections_loaded, status.collections_total
+        ),
+        RecoveryPhase::Failed => format!(
+            "Database recovery failed: {}",
+            status.error.as_deref().unwrap_or("unknown error")
+        ),
+        _ => format!(
+            "Database is recovering (WAL replay {:.0}%), only reads are served",
+            status.percent
+        ),
+    };
+    Some(error)
+}
+
+/// Wait until `db` accepts writes; returns false if its recovery failed
+pub(crate) async fn wait_for_recovery(db: &Database) -> bool {
+    loop {
+        match db.recovery_status().phase {
+            RecoveryPhase::Ready => return true,
+            RecoveryPhase::Failed => return false,
+            _ => tokio::time::sleep(Duration::from_millis(100)).await,
+        }
+    }
+}
+
+/// The part of a search request that picks the searched space
+#[derive(Deserialize)]
+struct SearchSpace {
+    using: Option<String>,
+}
+
+/// Reshape search and upsert responses into the format the request or the
+/// server's `RESPONSE_FORMAT` picks, for clients of other APIs; see [`compat`]
+async fn compat_middleware(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let format = match req.headers().get(compat::FORMAT_HEADER) {
+        Some(value) => match value.to_str().ok().and_then(ResponseFormat::parse) {
+            Some(format) => format,
+            None => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(ErrorResponse {
+                        error: format!(
+                            "Unknown {}: use native, camel, qdrant or pinecone",
+                            compat::FORMAT_HEADER
+                        ),
+                    }),
+                )
+                    .into_response()
+            }
+        },
+        None => state.config.response_format,
+    };
+    let Some((endpoint, name)) = compat::endpoint(req.method(), req.uri().path())
+        .filter(|_| format != ResponseFormat::Native)
+        .map(|(endpoint, name)| (endpoint, name.to_string()))
+    else {
+        return next.run(req).await;
+    };
+    let start = Instant::now();
+
+    // Scores are oriented by the metric of the space searched
+    let (req, using) = if endpoint == compat::Endpoint::Search {
+        let (parts, body) = req.into_parts();
+        let bytes = match axum::body::to_bytes(body, state.config.max_request_size_bytes).await {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(ErrorResponse {
+                        error: e.to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+        };
+        let using = serde_json::from_slice::<SearchSpace>(&bytes)
+            .ok()
+            .and_then(|space| space.using);
+        (
+            Request::from_parts(parts, axum::body::Body::from(bytes)),
+            using,
+        )
+    } else {
+        (req, None)
+    };
+
+    let response = next.run(req).await;
+    let metric = state
+        .db
+        .get_collection(&name)
+        .map(|collection| search_metric(&collection, using.as_deref()))
+        .unwrap_or_default();
+    let commit_seq = response
+        .headers()
+        .get(COMMIT_SEQ_HEADER)
+        .and_then(|v| v.to_str().ok()?.parse().ok());
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = axum::body::to_bytes(body, usize::MAX).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let body = serde_json::from_slice(&bytes).ok().or_else(|| {
+        std::str::from_utf8(&bytes)
+            .ok()
+            .map(|text| Value::String(text.to_string()))
+    });
+    let context = compat::Context {
+        status: parts.status,
+        metric,
+        commit_seq,
+        elapsed: start.elapsed(),
+    };
+    match compat::reshape(format, endpoint, body, &context) {
+        Some(reshaped) => {
+            parts.headers.remove(header::CONTENT_LENGTH);
+            parts.headers.insert(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("application/json"),
+            );
+            let bytes = serde_json::to_vec(&reshaped).unwrap_or_default();
+            Response::from_parts(parts, axum::body::Body::from(bytes))
+        }
+        None => Response::from_parts(parts, axum::body::Body::from(bytes)),
+    }
+}
+
+/// Response header carrying the collection's commit sequence
+const COMMIT_SEQ_HEADER: &str = "x-commit-seq";
+
+/// Tag successful requests to a collection with its commit sequence, and the
+/// ID of its write log if it keeps one (as `x-log-id`)
+///
+/// After a write the sequence is read once the write returned, so it covers
+/// that write; pass it as `min_seq` to a search to read your own writes.
+/// Before any other request it is read up front, so the response reflects at
+/// least every write up to it. On-disk collections keep counting across
+/// restarts; a new `x-log-id` means the collection was recreated and its
+/// sequence started over.
+async fn commit_seq_middleware(
+    State(state): State<AppState>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let written = written_collection(&req).map(str::to_string);
+    let read = match &written {
+        Some(_) => None,
+        None => {
+            match collection_in_path(&req).and_then(|name| state.db.get_collection(name).ok()) {
+                Some(collection) => spawn_blocking(move || commit_position(&collection))
+                    .await
+                    .ok(),
+                None => None,
+            }
+        }
+    };
+    let mut response = next.run(req).await;
+    if !response.status().is_success() {
+        return response;
+    }
+    let position = match (read, written) {
+        (Some(position), _) => Some(position),
+        (None, Some(name)) => match state.db.get_collection(&name) {
+            Ok(collection) => spawn_blocking(move || commit_position(&collection))
+                .await
+                .ok(),
+            Err(_) => None,
+        },
+        (None, None) => None,
+    };
+    if let Some((seq, log_id)) = position {
+        let headers = response.headers_mut();
+        headers.insert(COMMIT_SEQ_HEADER, HeaderValue::from(seq));
+        if let Some(log_id) = log_id {
+            headers.insert(
+                HeaderName::from_static(LOG_ID_HEADER),
+                HeaderValue::from(log_id),
+            );
+        }
+    }
+    response
+}
+
+/// Commit sequence of `collection`, and the ID of its write log if it keeps one
+fn commit_position(collection: &Collection) -> (u64, Option<u64>) {
+    match collection.log_position() {
+        Ok(position) => (position.seq, Some(position.log_id)),
+        Err(_) => (collection.write_seq(), None),
+    }
+}
+
+/// Collection (or alias) named in the path of `req`, if any
+fn collection_in_path(req: &Request) -> Option<&str> {
+    let mut segments = req.uri().path().trim_matches('/').split('/');
+    match (segments.next(), segments.next()) {
+        (Some("collections"), Some(name)) => Some(name),
+        _ => None,
+    }
+}
+
+/// Response header warning that a write left the collection near its quota
+const QUOTA_WARNING_HEADER: &str = "x-quota-warning";
+
+/// Warn on successful writes that leave a collection near the caller's
+/// `max_vectors` quota
+///
+/// The warning is sent once the collection holds `QUOTA_WARNING_RATIO` of the
+/// quota, and counted in the `surgedb_quota_warnings_total` metric.
+async fn quota_middleware(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let collection = written_collection(&req).map(str::to_string);
+    let max_vectors = req.extensions().get::<Caller>().and_then(|caller| {
+        state
+            .limits
+            .effective(caller.key_name.as_deref())
+            .max_vectors
+    });
+    let mut response = next.run(req).await;
+    let (Some(name), Some(max_vectors)) = (collection, max_vectors) else {
+        return response;
+    };
+    if !response.status().is_success() {
+        return response;
+    }
+    let Ok(collection) = state.db.get_collection(&name) else {
+        return response;
+    };
+    let Ok(count) = spawn_blocking(move || collection.len()).await else {
+        return response;
+    };
+    if (count as f64) < max_vectors as f64 * state.config.quota_warning_ratio {
+        return response;
+    }
+
+    state
+        .metrics
+        .record_quota_warning(&state.db.resolve_name(&name));
+    let warning = format!(
+        "max_vectors: {} of {} ({:.0}%)",
+        count,
+        max_vectors,
+        100.0 * count as f64 / max_vectors.max(1) as f64
+    );
+    if let Ok(value) = HeaderValue::from_str(&warning) {
+        response.headers_mut().insert(QUOTA_WARNING_HEADER, value);
+    }
+    response
+}
+
+/// Collection whose vectors `req` writes, if any
+fn written_collection(req: &Request) -> Option<&str> {
+    let segments: Vec<&str> = req.uri().path().trim_matches('/').split('/').collect();
+    match (req.method(), segments.as_slice()) {
+        (&Method::POST, ["collections", name, "vectors" | "upsert" | "import" | "bulk-load"])
+        | (&Method::POST, ["collections", name, "parquet", "import"])
+        | (&Method::POST, ["collections", name, "vectors", "batch" | "delete"])
+        | (&Method::POST, ["collections", name, "documents", _, "replace"])
+        | (&Method::POST, ["collections", name, "dev", "seed"])
+        | (&Method::DELETE, ["collections", name, "vectors", _])
+        | (&Method::PATCH, ["collections", name, "vectors", _, "metadata"]) => Some(name),
+        _ => None,
+    }
+}
+
+/// Wait until `collection` shows write `min_seq`, for at most `timeout`
+async fn wait_for_seq(
+    collection: &Collection,
+    min_seq: u64,
+    timeout: Duration,
+) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    let deadline = Instant::now() + timeout;
+    let mut delay = Duration::from_millis(1);
+    loop {
+        let c = collection.clone();
+        let seq = spawn_blocking(move || c.write_seq()).await.map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+        })?;
+        if seq >= min_seq {
+            return Ok(());
+        }
+        if Instant::now() >= deadline {
+            return Err((
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(ErrorResponse {
+                    error: format!(
+                        "Write {} not visible after {}ms (collection is at {})",
+                        min_seq,
+                        timeout.as_millis(),
+                        seq
+                    ),
+                }),
+            ));
+        }
+        tokio::time::sleep(delay).await;
+        delay = (delay * 2).min(Duration::from_millis(50));
+    }
+}
+
+/// Requests that don't modify the database
+fn is_read_request(req: &Request) -> bool {
+    if req.method() == Method::GET {
+        return true;
+    }
+    let segments: Vec<&str> = req.uri().path().trim_matches('/').split('/').collect();
+    req.method() == Method::POST
+        && matches!(
+            segments.as_slice(),
+            [
+                "collections",
+                _,
+                "search" | "payloads" | "recommend" | "hard-negatives"
+            ] | ["collections", _, "search", "groups" | "stream"]
+        )
+}
+
+/// Requests that leave the collections and their records as they are:
+/// reads, searches, snapshots and exports
+fn leaves_database_unchanged(req: &Request) -> bool {
+    if is_read_request(req) || req.method() == Method::HEAD {
+        return true;
+    }
+    let segments: Vec<&str> = req.uri().path().trim_matches('/').split('/').collect();
+    req.method() == Method::POST
+        && matches!(
+            segments.as_slice(),
+            ["collections", _, "count" | "scroll" | "snapshot"]
+                | ["collections", _, "search", ..]
+                | ["collections", _, "parquet", "export"]
+                | ["backups"]
+                | ["cluster", "collections", _, "search"]
+        )
+}
+
+/// Collection `req` would change, if any
+fn changed_collection(req: &Request) -> Option<&str> {
+    let mut segments = req.uri().path().trim_matches('/').split('/');
+    let (Some("collections"), Some(name)) = (segments.next(), segments.next()) else {
+        return None;
+    };
+    (!leaves_database_unchanged(req)).then_some(name)
+}
+
+/// Reject a write to `name` if it is a system collection, directly or
+/// through an alias
+///
+/// The REST and gRPC APIs both check every write with it.
+fn check_writable(db: &Database, name: &str) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    if !naming::is_reserved(&db.resolve_name(name)) {
+        return Ok(());
+    }
+    Err((
+        StatusCode::FORBIDDEN,
+        Json(ErrorResponse {
+            error: "System collections are read-only".to_string(),
+        }),
+    ))
+}
+
+/// Whether an unauthenticated request targets search on a public collection.
+///
+/// Only `POST /collections/:name/search` and its follow-up payload fetch are
+/// allowed; writes, listing and every other endpoint still require an API key.
+fn is_public_search(config: &AppConfig, req: &Request) -> bool {
+    if req.method() != Method::POST || config.public_collections.is_empty() {
+        return false;
+    }
+    let segments: Vec<&str> = req.uri().path().trim_matches('/').split('/').collect();
+    match segments.as_slice() {
+        ["collections", name, "search" | "payloads"] => config
+            .public_collections
+            .contains(config.name_case.canonicalize(name).as_ref()),
+        _ => false,
+    }
+}
+
+fn check_public_rate_limit(
+    state: &AppState,
+    req: &Request,
+) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    // Without the client's address every caller would share one bucket, so
+    // one of them could use up the quota for all; refuse instead
+    let Some(ConnectInfo(addr)) = req.extensions().get::<ConnectInfo<SocketAddr>>() else {
+        static WARNED: std::sync::Once = std::sync::Once::new();
+        WARNED.call_once(|| {
+            warn!(
+                "Public search refused: the router is not served with \
+                 into_make_service_with_connect_info, so clients can't be rate limited"
+            )
+        });
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(ErrorResponse {
+                error: "Invalid or missing API key".to_string(),
+            }),
+        ));
+    };
+    let client_ip = addr.ip();
+
+    if state.public_limiter.check(&client_ip) {
+        Ok(())
+    } else {
+        Err((
+            StatusCode::TOO_MANY_REQUESTS,
+            Json(ErrorResponse {
+                error: "Public search rate limit exceeded".to_string(),
+            }),
+        ))
+    }
+}
+
+// =============================================================================
+// Main Entry Point
+// =============================================================================
+
+impl AppState {
+    /// Shared state of the API served on `db`
+    ///
+    /// Starts the webhook, compaction, graph repair and metrics background
+    /// tasks, and resumes the deployments and mirrors a previous
+    /// [`shutdown`](Self::shutdown) saved in the data directory, so it must
+    /// be called from within a Tokio runtime.
+    /// Applies the configured name case to `db`.
+    pub fn new(db: Arc<Database>, config: AppConfig) -> Self {
+        db.set_name_case(config.name_case);
+        let metrics = Arc::new(MetricsRegistry::new());
+        let data_dir = std::path::Path::new(&config.data_dir);
+        #[cfg(feature = "chaos")]
+        let chaos = Arc::new(chaos::Chaos::default());
+        let mirrors = MirrorRegistry::new(Some(data_dir.join("mirrors.json")));
+        let signer = config
+            .signing_secret
+            .as_deref()
+            .map(|secret| Arc::new(RequestSigner::new(secret, config.signature_tolerance_secs)));
+        let mut webhooks = WebhookRegistry::new(Some(data_dir.join("webhooks.json")));
+        if let Some(signer) = &signer {
+            webhooks = webhooks.with_signer(signer.clone());
+        }
+        #[cfg(feature = "chaos")]
+        let mirrors = mirrors.with_chaos(chaos.clone());
+        let replication = match &config.replica_of {
+            Some(url) => Replication::follower(
+                url.clone(),
+                config.replica_api_key.clone(),
+                Duration::from_millis(config.replication_poll_interval_ms.max(1)),
+                std::path::PathBuf::from(&config.snapshot_dir),
+                Some(data_dir.join("replication.json")),
+            ),
+            None => Replication::leader(),
+        };
+        #[cfg(feature = "chaos")]
+        let replication = replication.with_chaos(chaos.clone());
+        #[cfg(feature = "cluster")]
+        let cluster = config.cluster.clone().map(|cluster| {
+            assert!(
+                config.replica_of.is_none(),
+                "A cluster node can't be a read-only replica"
+            );
+            let cluster = cluster::Cluster::new(
+                cluster,
+                config.api_key.clone(),
+                db.clone(),
+                data_dir.join("raft"),
+            )
+            .expect("Invalid cluster configuration");
+            Arc::new(cluster)
+        });
+        let state = AppState {
+            db,
+            config: config.clone(),
+            start_time: Instant::now(),
+            boot_id: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_nanos() as u64)
+                .unwrap_or_default(),
+            metrics: metrics.clone(),
+            public_limiter: Arc::new(RateLimiter::new(
+                config.public_rate_limit_per_min,
+                Duration::from_secs(60),
+            )),
+            limits: Arc::new(LimitsRegistry::new(
+                config.hard_limits,
+                config.soft_limits,
+                Some(std::path::Path::new(&config.data_dir).join("limits.json")),
+            )),
+            tenants: Arc::new(TenantRegistry::new(Some(data_dir.join("tenants.json")))),
+            webhooks: Arc::new(webhooks),
+            deployments: Arc::new(DeploymentRegistry::new(Some(
+                data_dir.join("deployments.json"),
+            ))),
+            jobs: Arc::default(),
+            mirrors: Arc::new(mirrors),
+            compaction: Arc::new(CompactionRegistry::new(
+                config.compaction.clone(),
+                Some(data_dir.join("compaction.json")),
+            )),
+            repairs: Arc::default(),
+            backups: config.backup.as_ref().map(|backup| {
+                let scratch_dir = std::path::PathBuf::from(&config.snapshot_dir);
+                Arc::new(
+                    BackupStore::new(backup, scratch_dir).expect("Invalid backup configuration"),
+                )
+            }),
+            replication: Arc::new(replication),
+            tokens: config
+                .token_secret
+                .as_deref()
+                .or(config.api_key.as_deref())
+                .map(|secret| Arc::new(TokenSigner::new(secret))),
+            signer,
+            background: Arc::default(),
+            #[cfg(feature = "chaos")]
+            chaos,
+            #[cfg(feature = "cluster")]
+            cluster,
+        };
+
+        state
+            .deployments
+            .resume(state.db.clone(), state.webhooks.clone());
+        state.mirrors.resume(state.db.clone());
+        if let Some(task) = state.replication.start(state.db.clone()) {
+            state.background.lock().push(task.abort_handle());
+        }
+        if let (Some(backups), Some(backup)) = (&state.backups, &config.backup) {
+            if backup.interval_secs > 0 {
+                let interval = Duration::from_secs(backup.interval_secs);
+                let task = backups.start(state.db.clone(), interval, backup.retain);
+                state.background.lock().push(task.abort_handle());
+            }
+        }
+        #[cfg(feature = "cluster")]
+        if let Some(cluster) = &state.cluster {
+            let tasks = cluster.start();
+            state
+                .background
+                .lock()
+                .extend(tasks.iter().map(|task| task.abort_handle()));
+        }
+
+        // Background task for collection threshold webhooks
+        let webhooks = state.webhooks.clone();
+        let webhook_db = state.db.clone();
+        let webhook_interval = Duration::from_secs(config.webhook_check_interval_secs.max(1));
+        let webhook_task = tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(webhook_interval).await;
+                webhooks.evaluate(&webhook_db).await;
+            }
+        });
+
+        // Background task for scheduled compaction
+        let compaction = state.compaction.clone();
+        let compaction_db = state.db.clone();
+        let compaction_interval = Duration::from_secs(config.compaction_check_interval_secs.max(1));
+        let compaction_task = tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(compaction_interval).await;
+                compaction.run_due(&compaction_db).await;
+            }
+        });
+
+        // Background task relinking graphs around deleted nodes, from startup on
+        let repairs = state.repairs.clone();
+        let repair_db = state.db.clone();
+        let repair = config.graph_repair.clone();
+        let repair_task = tokio::spawn(async move {
+            if repair.interval_secs == 0 {
+                return;
+            }
+            loop {
+                repairs.run(&repair_db, repair.batch).await;
+                tokio::time::sleep(Duration::from_secs(repair.interval_secs)).await;
+            }
+        });
+
+        // Background task copying collection activity into `_usage`
+        let usage_retention = Duration::from_secs(config.usage_retention_days * 86_400);
+        let usage_db = state.db.clone();
+        let usage_task = tokio::spawn(async move {
+            if usage_retention.is_zero() {
+                return;
+            }
+            let recorder = Arc::new(UsageRecorder::new(usage_retention));
+            loop {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                recorder.record(&usage_db).await;
+            }
+        });
+
+        // Background task for metrics collection
+        let state_clone = state.clone();
+        let metrics_task = tokio::spawn(async move {
+            let mut sys = System::new_all();
+            // Initial snapshot
+            {
+                sys.refresh_all();
+                let pid = sysinfo::get_current_pid().ok();
+                let process_memory = pid
+                    .and_then(|p| sys.process(p))
+                    .map(|p| p.memory())
+                    .unwrap_or(0);
+                let db_stats = state_clone.db.get_stats();
+                let snapshot = MetricsSnapshot {
+                    timestamp: Utc::now(),
+                    memory_usage_mb: process_memory / 1024 / 1024,
+                    read_requests: 0,
+                    write_requests: 0,
+                    avg_latency_ms: 0.0,
+                    storage_usage_bytes: db_stats.total_memory_bytes as u64,
+                };
+                state_clone.metrics.history.write().push_back(snapshot);
+            }
+
+            loop {
+                tokio::time::sleep(Duration::from_secs(6)).await;
+                sys.refresh_all();
+
+                let pid = sysinfo::get_current_pid().ok();
+                let process_memory = pid
+                    .and_then(|p| sys.process(p))
+                    .map(|p| p.memory())
+                    .unwrap_or(0);
+
+                let reads = state_clone
+                    .metrics
+                    .current_reads
+                    .swap(0, std::sync::atomic::Ordering::Relaxed);
+                let writes = state_clone
+                    .metrics
+                    .current_writes
+                    .swap(0, std::sync::atomic::Ordering::Relaxed);
+                let count = state_clone
+                    .metrics
+                    .latency_count
+                    .swap(0, std::sync::atomic::Ordering::Relaxed);
+                let total_lat_us = state_clone
+                    .metrics
+                    .total_latency_us
+                    .swap(0, std::sync::atomic::Ordering::Relaxed);
+
+                let avg_latency = if count > 0 {
+                    (total_lat_us as f64 / 1000.0) / count as f64
+                } else {
+                    0.0
+                };
+
+                // Storage usage calculation
+                let db_stats = state_clone.db.get_stats();
+                let storage_bytes = db_stats.total_memory_bytes as u64;
+
+                let snapshot = MetricsSnapshot {
+                    timestamp: Utc::now(),
+                    memory_usage_mb: process_memory / 1024 / 1024,
+                    read_requests: reads,
+                    write_requests: writes,
+                    avg_latency_ms: avg_latency,
+                    storage_usage_bytes: storage_bytes,
+                };
+
+                let mut history = state_clone.metrics.history.write();
+                if history.len() >= 600 {
+                    history.pop_front();
+                }
+                history.push_back(snapshot);
+            }
+        });
+        state.background.lock().extend([
+            webhook_task.abort_handle(),
+            compaction_task.abort_handle(),
+            repair_task.abort_handle(),
+            usage_task.abort_handle(),
+            metrics_task.abort_handle(),
+        ]);
+
+        state
+    }
+
+    /// Stop the background jobs, saving what is needed to resume them, and
+    /// flush the collections
+    ///
+    /// Call once the API no longer takes requests. Jobs stop in this order:
+    /// deployments, which write to the database, are interrupted before their
+    /// next import batch; then mirrors stop and keep their unsent changes;
+    /// then the webhook, compaction and graph repair checks and metrics
+    /// sampling are cancelled. Imports, compactions and index rebuilds already
+    /// running on collections get `SHUTDOWN_TIMEOUT_SECS` to finish. Last, every
+    /// collection with writes since its last checkpoint is checkpointed, so
+    /// the next start has no WAL to replay. The next [`AppState::new`] on the
+    /// same data directory resumes the deployments and mirrors.
+    pub async fn shutdown(&self) {
+        self.deployments.shutdown().await;
+        self.mirrors.shutdown().await;
+        for task in self.background.lock().drain(..) {
+            task.abort();
+        }
+        self.drain_collection_jobs().await;
+        self.flush_collections().await;
+    }
+
+    /// Wait up to `SHUTDOWN_TIMEOUT_SECS` for the jobs running on collections
+    async fn drain_collection_jobs(&self) {
+        let deadline = Instant::now() + Duration::from_secs(self.config.shutdown_timeout_secs);
+        let mut waiting = false;
+        loop {
+            let busy: Vec<String> = self
+                .db
+                .list_collections()
+                .into_iter()
+                .filter(|name| {
+                    self.db
+                        .get_collection(name)
+                        .is_ok_and(|c| c.active_jobs() > 0)
+                })
+                .collect();
+            if busy.is_empty() {
+                return;
+            }
+            if Instant::now() >= deadline {
+                warn!("Jobs still running at shutdown on: {}", busy.join(", "));
+                return;
+            }
+            if !waiting {
+                info!("Waiting for jobs on {} collections...", busy.len());
+                waiting = true;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    /// Checkpoint the collections with writes left only in their WAL
+    ///
+    /// Collections still running a job are skipped; their WAL is replayed on
+    /// the next start.
+    async fn flush_collections(&self) {
+        let names = self.db.list_collections();
+        info!("Flushing {} collections...", names.len());
+        let mut checkpointed = 0;
+        for (i, name) in names.iter().enumerate() {
+            let Ok(collection) = self.db.get_collection(name) else {
+                continue;
+            };
+            if collection.active_jobs() > 0 {
+                warn!("Not flushing {}: a job is still running", name);
+                continue;
+            }
+            match tokio::task::spawn_blocking(move || collection.flush()).await {
+                Ok(Ok(true)) => {
+                    checkpointed += 1;
+                    info!("Flushed {} ({}/{})", name, i + 1, names.len());
+                }
+                Ok(Ok(false)) => debug!("Nothing to flush in {}", name),
+                Ok(Err(e)) => warn!("Failed to flush {}: {}", name, e),
+                Err(e) => warn!("Failed to flush {}: {}", name, e),
+            }
+        }
+        info!("Flushed {} collections with unsaved writes", checkpointed);
+    }
+}
+
+/// Build the HTTP API for `state`, to serve it or mount it in another app
+///
+/// Includes authentication, recovery gating, metrics and request limits, but
+/// no CORS layer, which is left to the host app. Nest it under a prefix with
+/// [`Router::nest`] to share the host's listener and middleware. Serve the
+/// host app with `into_make_service_with_connect_info::<SocketAddr>()`:
+/// public search is rate limited per client IP, and is refused when the
+/// client's [`ConnectInfo`] is missing.
+pub fn build_router(state: AppState) -> Router {
+    let api_routes = Router::new()
+        .route("/stats", get(get_stats))
+        .route("/stats/activity", get(get_activity))
+        .route("/_system/collections", get(list_system_collections))
+        .route(
+            "/_system/collections/:name/records",
+            get(list_system_records),
+        )
+        .route("/capabilities", get(get_capabilities))
+        .route("/metrics", get(get_metrics))
+        .route("/metrics/history", get(get_metrics_history))
+        .route(
+            "/collections",
+            post(create_collection).get(list_collections),
+        )
+        .route(
+            "/collections/:name",
+            get(get_collection_info)
+                .head(collection_exists)
+                .put(put_collection)
+                .patch(update_collection)
+                .delete(delete_collection),
+        )
+        .route(
+            "/collections/:name/vectors",
+            post(insert_vector).get(list_vectors),
+        )
+        .route("/collections/:name/scroll", post(scroll_vectors))
+        .route(
+            "/collections/:name/count",
+            get(count_vectors).post(count_vectors_post),
+        )
+        .route("/collections/:name/vectors/delete", post(delete_vectors))
+        .route(
+            "/collections/:name/vectors/batch",
+            post(batch_insert_vector),
+        )
+        .route("/collections/:name/upsert", post(upsert_vector))
+        .route(
+            "/collections/:name/documents/:doc_id/replace",
+            post(replace_document),
+        )
+        .route(
+            "/collections/:name/vectors/:id",
+            get(get_vector).head(vector_exists).delete(delete_vector),
+        )
+        .route(
+            "/collections/:name/vectors/:id/metadata",
+            patch(update_metadata),
+        )
+        .route("/collections/:name/index/export", get(export_index))
+        .route(
+            "/collections/:name/index/:field/rebuild",
+            post(rebuild_field_index),
+        )
+        .route("/collections/:name/summary", get(collection_summary))
+        .route("/collections/:name/snapshot", post(snapshot_collection))
+        .route("/collections/:name/restore", post(restore_collection))
+        .route("/backups", post(create_backup).get(list_backups))
+        .route("/backups/:id/restore", post(restore_backup))
+        .route("/collections/:name/parquet/export", post(export_parquet))
+        .route("/collections/:name/parquet/import", post(import_parquet))
+        .route("/collections/:name/tune", post(tune_collection))
+        .route("/collections/:name/search", post(search_vector))
+        .route("/collections/:name/recommend", post(recommend_vectors))
+        .route(
+            "/collections/:name/hard-negatives",
+            post(mine_hard_negatives),
+        )
+        .route("/collections/:name/search/groups", post(search_grouped))
+        .route("/collections/:name/search/stream", post(search_stream))
+        .route("/collections/:name/search/batch", post(search_batch))
+        .route("/collections/:name/search/hybrid", post(search_hybrid))
+        .route("/collections/:name/search/text", post(search_text))
+        .route("/collections/:name/payloads", post(get_payloads))
+        .route(
+            "/collections/:name/filter-cache",
+            post(cache_filter).get(list_cached_filters),
+        )
+        .route(
+            "/collections/:name/filter-cache/:id",
+            delete(uncache_filter),
+        )
+        .route(
+            "/collections/:name/filter-cache/:id/recall",
+            get(estimate_filter_recall),
+        )
+        .route(
+            "/collections/:name/webhooks",
+            post(create_webhook).get(list_webhooks),
+        )
+        .route("/collections/:name/webhooks/:id", delete(delete_webhook))
+        .route(
+            "/collections/:name/compaction",
+            get(get_compaction)
+                .post(compact_collection)
+                .put(set_compaction),
+        )
+        .route(
+            "/collections/:name/mirror",
+            post(create_mirror).get(list_mirrors),
+        )
+        .route("/collections/:name/mirror/:id", delete(delete_mirror))
+        .route("/replication/status", get(replication_status))
+        .route("/replication/collections", get(replication_catalog))
+        .route("/replication/collections/:name/log", get(replication_log))
+        .route(
+            "/replication/collections/:name/snapshot",
+            get(replication_snapshot),
+        )
+        .route("/aliases", get(list_aliases))
+        .route("/aliases/:alias", put(set_alias).delete(delete_alias))
+        .route(
+            "/deployments",
+            post(create_deployment).get(list_deployments),
+        )
+        .route("/deployments/:id", get(get_deployment))
+        .route("/jobs", get(list_jobs))
+        .route("/jobs/:id", get(get_job))
+        .route("/admin/limits", get(get_limits).put(update_soft_limits))
+        .route(
+            "/admin/limits/keys/:key_name",
+            put(set_key_limits).delete(delete_key_limits),
+        )
+        .route(
+            "/admin/logging",
+            get(get_log_filter)
+                .put(set_log_filter)
+                .delete(reset_log_filter),
+        )
+        .route("/admin/tokens", post(mint_token))
+        .route("/admin/tenants", get(list_tenants))
+        .route(
+            "/admin/tenants/:namespace",
+            put(put_tenant).delete(delete_tenant),
+        )
+        .route("/admin/tenants/:namespace/keys", post(create_tenant_key))
+        .route(
+            "/admin/tenants/:namespace/keys/:key_name",
+            delete(revoke_tenant_key),
+        );
+
+    // Imports stream bodies of any size for as long as they take, so only
+    // the other routes get the size limit and timeout
+    let api_routes = api_routes
+        .layer(TimeoutLayer::new(Duration::from_secs(
+            state.config.request_timeout_secs,
+        )))
+        .layer(RequestBodyLimitLayer::new(
+            state.config.max_request_size_bytes,
+        ))
+        .route("/collections/:name/import", post(import_vectors))
+        .route("/collections/:name/bulk-load", post(bulk_load_vectors));
+
+    #[cfg(feature = "dev")]
+    let api_routes = dev::install(api_routes);
+
+    #[cfg(feature = "chaos")]
+    let api_routes = chaos::install(api_routes, state.chaos.clone());
+
+    #[cfg(feature = "cluster")]
+    let api_routes = match &state.cluster {
+        Some(cluster) => cluster::install(api_routes, cluster.clone()),
+        None => api_routes,
+    };
+
+    let api_routes = api_routes
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            commit_seq_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            quota_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            recovery_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            signature_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            compat_middleware,
+        ));
+
+    Router::new()
+        .route("/health", get(health_check))
+        .route("/health/ready", get(readiness_check))
+        .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()))
+        .layer(TimeoutLayer::new(Duration::from_secs(
+            state.config.request_timeout_secs,
+        )))
+        .layer(RequestBodyLimitLayer::new(
+            state.config.max_request_size_bytes,
+        ))
+        .merge(api_routes)
+        .layer(TraceLayer::new_for_http().make_span_with(request_span))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            metrics_middleware,
+        ))
+        .layer(CompressionLayer::new())
+        .with_state(state)
+}
+
+/// Recover the database and check every collection's vectors and graph,
+/// logging what is wrong; returns whether all of it is sound
+async fn verify_database(config: &AppConfig) -> bool {
+    info!("Verifying SurgeDB data in {}", config.data_dir);
+    let (data_dir, mode) = (config.data_dir.clone(), config.recovery_mode);
+    let db = match tokio::task::spawn_blocking(move || Database::open_with_recovery(data_dir, mode))
+        .await
+        .expect("Verification panicked")
+    {
+        Ok(db) => db,
+        Err(e) => {
+            error!("Recovery failed: {}", e);
+            return false;
+        }
+    };
+
+    let recovery = db.recovery_status();
+    if recovery.wal_records_dropped > 0 || recovery.wal_bytes_truncated > 0 {
+        warn!(
+            "Recovery dropped {} WAL records and cut off {} torn bytes",
+            recovery.wal_records_dropped, recovery.wal_bytes_truncated
+        );
+    }
+
+    let mut ok = true;
+    let mut names = db.list_collections();
+    names.extend(db.list_system_collections());
+    for name in names {
+        let Some(report) = db.get_collection(&name).ok().and_then(|c| c.verify().ok()) else {
+            continue;
+        };
+        if report.is_ok() {
+            info!(
+                "{}: OK, {} vectors, {} graph nodes",
+                name, report.vectors, report.graph_nodes
+            );
+            continue;
+        }
+        ok = false;
+        error!("{}: {} problems", name, report.problem_count);
+        for problem in &report.problems {
+            error!("{}: {}", name, problem);
+        }
+    }
+    ok
+}
+
+/// Run the server configured from the environment until it is shut down
+pub async fn run() {
+    let config = AppConfig::from_env();
+
+    logging::init(&config.log_level);
+
+    if std::env::args().skip(1).any(|arg| arg == "--verify") {
+        let ok = verify_database(&config).await;
+        std::process::exit(if ok { 0 } else { 1 });
+    }
+
+    info!("Starting SurgeDB Server v{}", env!("CARGO_PKG_VERSION"));
+
+    // Collections recover in the background; see /health/ready for progress
+    let db = Database::open_recovering_with(&config.data_dir, config.recovery_mode)
+        .expect("Failed to open database");
+    let state = AppState::new(db, config.clone());
+
+    if !config.public_collections.is_empty() {
+        info!(
+            "Public search enabled for collections: {:?}",
+            config.public_collections
+        );
+    }
+
+    let cors = CorsLayer::new()
+        .allow_origin(config.cors_allow_origin.parse::<HeaderValue>().unwrap())
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+        ])
+        .allow_headers([
+            axum::http::header::CONTENT_TYPE,
+            axum::http::header::IF_NONE_MATCH,
+            axum::http::header::AUTHORIZATION,
+            HeaderName::from_static("x-api-key"),
+            HeaderName::from_static(TIMESTAMP_HEADER),
+            HeaderName::from_static(SIGNATURE_HEADER),
+        ])
+        .expose_headers([
+            HeaderName::from_static(COMMIT_SEQ_HEADER),
+            HeaderName::from_static(LOG_ID_HEADER),
+            HeaderName::from_static(QUOTA_WARNING_HEADER),
+            axum::http::header::ETAG,
+        ]);
+
+    let api_router = build_router(state.clone()).layer(cors);
+
+    let drain_timeout = Duration::from_secs(config.shutdown_timeout_secs);
+
+    #[cfg(feature = "grpc")]
+    let grpc_server = config.grpc_port.map(|port| {
+        // gRPC calls carry no signature, so they would bypass the check
+        assert!(
+            state.signer.is_none(),
+            "GRPC_PORT can't be set with REQUEST_SIGNING_SECRET: gRPC calls are not signed"
+        );
+        let grpc_addr = SocketAddr::from(([0, 0, 0, 0], port));
+        tokio::spawn(grpc::serve(state.clone(), grpc_addr, drain_timeout))
+    });
+
+    let api_app = api_router.clone();
+
+    let web_app = Router::new()
+        .nest("/api", api_router)
+        .route("/", get(index_handler))
+        .route("/*path", get(static_handler))
+        .fallback(index_handler);
+
+    let api_addr = SocketAddr::from(([0, 0, 0, 0], config.port));
+    let web_addr = SocketAddr::from(([0, 0, 0, 0], config.web_port));
+
+    let tls = config.tls.as_ref().map(|tls| {
+        info!(
+            "TLS enabled{}",
+            if tls.requires_client_cert() {
+                ", client certificates required"
+            } else {
+                ""
+            }
+        );
+        tls.load().expect("Invalid TLS configuration")
+    });
+
+    info!("API Server listening on {}", api_addr);
+    info!("Web Interface listening on {}", web_addr);
+
+    let api_listener = tokio::net::TcpListener::bind(api_addr).await.unwrap();
+    let web_listener = tokio::net::TcpListener::bind(web_addr).await.unwrap();
+
+    let api_server = serve(api_listener, api_app, tls.clone(), drain_timeout);
+    let web_server = serve(web_listener, web_app, tls, drain_timeout);
+
+    // Every port drains its requests before the data is flushed, so no
+    // acknowledged write lands after the flush
+    let (api_result, web_result) = tokio::join!(api_server, web_server);
+    if let Err(e) = api_result {
+        warn!("API server error: {}", e);
+    }
+    if let Err(e) = web_result {
+        warn!("Web server error: {}", e);
+    }
+    #[cfg(feature = "grpc")]
+    if let Some(grpc_server) = grpc_server {
+        if let Ok(Err(e)) = grpc_server.await {
+            warn!("gRPC server error: {}", e);
+        }
+    }
+
+    info!("Stopping background jobs...");
+    state.shutdown().await;
+}
+
+/// Serve `app` on `listener` until a shutdown signal, over TLS if `tls` is set
+///
+/// After the signal no connections are accepted, and requests in flight get
+/// `drain_timeout` to finish before their connections are dropped.
+async fn serve(
+    listener: tokio::net::TcpListener,
+    app: Router,
+    tls: Option<RustlsConfig>,
+    drain_timeout: Duration,
+) -> std::io::Result<()> {
+    let service = app.into_make_service_with_connect_info::<SocketAddr>();
+    let Some(tls) = tls else {
+        let (signalled, mut on_signal) = tokio::sync::watch::channel(false);
+        let server = axum::serve(listener, service).with_graceful_shutdown(async move {
+            shutdown_signal().await;
+            let _ = signalled.send(true);
+        });
+        let deadline = async move {
+            if on_signal.wait_for(|signalled| *signalled).await.is_err() {
+                std::future::pending::<()>().await;
+            }
+            tokio::time::sleep(drain_timeout).await;
+        };
+        return tokio::select! {
+            res = server => res,
+            _ = deadline => {
+                warn!("Dropping requests still running after {:?}", drain_timeout);
+                Ok(())
+            }
+        };
+    };
+    let handle = axum_server::Handle::new();
+    let on_signal = handle.clone();
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        on_signal.graceful_shutdown(Some(drain_timeout));
+    });
+    axum_server::from_tcp_rustls(listener.into_std()?, tls)
+        .handle(handle)
+        .serve(service)
+        .await
+}
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install signal handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => info!("Received Ctrl+C, shutting down..."),
+        _ = terminate => info!("Received SIGTERM, shutting down..."),
+    }
+}
+
+// =============================================================================
+// Route Handlers
+// =============================================================================
+
+/// OpenMetrics content type; Prometheus only parses exemplars in this format
+const OPENMETRICS_CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    responses(
+        (status = 200, description = "Request latency histogram in OpenMetrics text format, with trace ID exemplars", body = String, content_type = "application/openmetrics-text")
+    ),
+    security(("api_key" = []))
+)]
+async fn get_metrics(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, OPENMETRICS_CONTENT_TYPE)],
+        // The histogram ends the exposition with `# EOF`
+        format!(
+            "{}{}{}{}",
+            state.metrics.render_quota_warnings(),
+            render_norm_rejections(&state.db),
+            state.repairs.render(),
+            state.metrics.latency.render()
+        ),
+    )
+}
+
+/// Records refused by each collection's norm filter, in OpenMetrics format
+fn render_norm_rejections(db: &Database) -> String {
+    const NAME: &str = "surgedb_norm_rejections";
+    let mut out = format!(
+        "# TYPE {NAME} counter\n# HELP {NAME} Records refused for a vector norm outside the collection's norm filter.\n"
+    );
+    for name in db.list_collections() {
+        let Ok(collection) = db.get_collection(&name) else {
+            continue;
+        };
+        let rejected = collection.norm_rejections();
+        if rejected > 0 || collection.norm_filter().is_some() {
+            out.push_str(&format!(
+                "{NAME}_total{{collection=\"{}\"}} {}\n",
+                name, rejected
+            ));
+        }
+    }
+    out
+}
+
+#[utoipa::path(
+    get,
+    path = "/metrics/history",
+    responses(
+        (status = 200, description = "Metrics history", body = [MetricsSnapshot])
+    ),
+    security(("api_key" = []))
+)]
+async fn get_metrics_history(State(state): State<AppState>) -> Json<Vec<MetricsSnapshot>> {
+    let history = state.metrics.history.read();
+    Json(history.iter().cloned().collect())
+}
+
+#[utoipa::path(
+    get,
+    path = "/health",
+    responses(
+        (status = 200, description = "Server is healthy", body = HealthResponse)
+    )
+)]
+async fn health_check(State(state): State<AppState>) -> Json<HealthResponse> {
+    // Only refresh process info, not entire system
+    let mut sys = System::new();
+    let pid = sysinfo::get_current_pid().ok();
+    if let Some(p) = pid {
+        sys.refresh_process(p);
+    }
+
+    let process_memory = pid
+        .and_then(|p| sys.process(p))
+        .map(|p| p.memory())
+        .unwrap_or(0);
+
+    Json(HealthResponse {
+        status: "OK".to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        uptime_seconds: state.start_time.elapsed().as_secs(),
+        memory_usage_mb: process_memory / 1024 / 1024,
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/capabilities",
+    responses(
+        (status = 200, description = "Supported options, features and the caller's limits", body = CapabilitiesResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn get_capabilities(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+) -> Json<CapabilitiesResponse> {
+    let limits = state.limits.effective(caller.key_name.as_deref());
+    let config = &state.config;
+    Json(CapabilitiesResponse {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        distance_metrics: vec![
+            DistanceMetric::Cosine,
+            DistanceMetric::Euclidean,
+            DistanceMetric::DotProduct,
+        ],
+        quantizations: vec![QuantizationType::None, QuantizationType::Binary],
+        index_types: vec!["HNSW", "Flat"],
+        id_types: vec![IdType::String, IdType::U64],
+        metadata_compression: vec![MetadataCompression::None, MetadataCompression::Zstd],
+        features: FeatureFlags {
+            hybrid: true,
+            sparse: true,
+            named_vectors: true,
+            text_search: true,
+            grouping: true,
+            partitions: true,
+            filter_expressions: true,
+            public_search: !config.public_collections.is_empty(),
+            scoped_tokens: state.tokens.is_some(),
+            signed_requests: state.signer.is_some(),
+            backups: state.backups.is_some(),
+            #[cfg(feature = "grpc")]
+            grpc: config.grpc_port.is_some(),
+            #[cfg(not(feature = "grpc"))]
+            grpc: false,
+        },
+        limits: RequestLimits {
+            max_k: limits.max_k,
+            max_batch_size: limits.max_batch_size,
+            max_dimensions: limits.max_dimensions,
+            max_vectors: limits.max_vectors,
+            max_request_size_bytes: config.max_request_size_bytes,
+            request_timeout_secs: config.request_timeout_secs,
+            min_seq_timeout_ms: config.min_seq_timeout_ms,
+            max_cached_filters: MAX_CACHED_FILTERS,
+            max_expr_length: surgedb_core::expr::MAX_SOURCE_LEN,
+        },
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/health/ready",
+    responses(
+        (status = 200, description = "Recovery finished, all requests are served", body = ReadinessResponse),
+        (status = 503, description = "Still recovering or recovery failed", body = ReadinessResponse)
+    )
+)]
+async fn readiness_check(State(state): State<AppState>) -> (StatusCode, Json<ReadinessResponse>) {
+    let recovery = state.db.recovery_status();
+    let ready = recovery.is_ready();
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(ReadinessResponse { ready, recovery }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/stats",
+    responses(
+        (status = 200, description = "Database statistics", body = StatsResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn get_stats(State(state): State<AppState>) -> Json<StatsResponse> {
+    let stats = state.db.get_stats();
+    let uptime = state.start_time.elapsed().as_secs();
+    Json(StatsResponse {
+        uptime_seconds: uptime,
+        database: stats,
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/stats/activity",
+    responses(
+        (status = 200, description = "Searches, inserts, deletes and latency of every collection, minute by minute", body = ActivityResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn get_activity(State(state): State<AppState>) -> Json<ActivityResponse> {
+    Json(ActivityResponse {
+        minutes: ACTIVITY_MINUTES,
+        collections: state.db.activity(),
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/_system/collections",
+    responses(
+        (status = 200, description = "System collections, such as `_usage`", body = [SystemCollectionInfo]),
+        (status = 403, description = "Admin API key required", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn list_system_collections(State(state): State<AppState>) -> Json<Vec<SystemCollectionInfo>> {
+    let collections = state
+        .db
+        .list_system_collections()
+        .into_iter()
+        .filter_map(|name| {
+            let collection = state.db.get_collection(&name).ok()?;
+            Some(SystemCollectionInfo {
+                vector_count: collection.len(),
+                name,
+            })
+        })
+        .collect();
+    Json(collections)
+}
+
+#[utoipa::path(
+    get,
+    path = "/_system/collections/{name}/records",
+    params(
+        ("name" = String, Path, description = "System collection name"),
+        PaginationParams
+    ),
+    responses(
+        (status = 200, description = "Records of the system collection, as from `GET /collections/{name}/vectors`", body = [VectorListEntry]),
+        (status = 403, description = "Admin API key required", body = ErrorResponse),
+        (status = 404, description = "No such system collection", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn list_system_records(
+    state: State<AppState>,
+    Path(name): Path<String>,
+    params: Query<PaginationParams>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    if !naming::is_reserved(&name) {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("Not a system collection: {}", name),
+            }),
+        ));
+    }
+    list_vectors(state, Path(name), params, headers).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/collections",
+    request_body = CreateCollectionRequest,
+    responses(
+        (status = 200, description = "Collection created"),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 409, description = "A deleted collection of that name is still in use", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn create_collection(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Json(payload): Json<CreateCollectionRequest>,
+) -> Result<&'static str, (StatusCode, Json<ErrorResponse>)> {
+    let limits = state.limits.effective(caller.key_name.as_deref());
+    let config = collection_config(payload.settings, &limits)?;
+    check_tenant_collection(&state, &caller, &payload.name, true)?;
+
+    match state.db.create_collection(&payload.name, config) {
+        Ok(_) => {
+            info!("Created collection: {}", payload.name);
+            Ok("Created")
+        }
+        Err(e) => {
+            warn!("Failed to create collection {}: {}", payload.name, e);
+            let status = match e {
+                surgedb_core::Error::CollectionBusy(_) => StatusCode::CONFLICT,
+                _ => StatusCode::BAD_REQUEST,
+            };
+            Err((
+                status,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            ))
+        }
+    }
+}
+
+/// Reject naming a collection `name` with a tenant key unless it is in the
+/// tenant's namespace, or creating it past the tenant's `max_collections`
+fn check_tenant_collection(
+    state: &AppState,
+    caller: &Caller,
+    name: &str,
+    create: bool,
+) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    let Some(namespace) = &caller.namespace else {
+        return Ok(());
+    };
+    let name = state.db.resolve_name(name);
+    if !tenants::in_namespace(namespace, &name) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(ErrorResponse {
+                error: format!(
+                    "Tenant keys can only use collections named {}.<name>",
+                    namespace
+                ),
+            }),
+        ));
+    }
+    let max_collections = state
+        .tenants
+        .quotas(namespace)
+        .and_then(|quotas| quotas.max_collections);
+    let (true, Some(max_collections)) = (create, max_collections) else {
+        return Ok(());
+    };
+    if state.db.get_collection(&name).is_ok() {
+        return Ok(());
+    }
+    let collections = tenants::usage(&state.db, namespace, None).collections;
+    if collections >= max_collections {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: format!(
+                    "Tenant {} has reached its max_collections quota of {}",
+                    namespace, max_collections
+                ),
+            }),
+        ));
+    }
+    Ok(())
+}
+
+/// Database configuration for `settings`, within the caller's `limits`
+fn collection_config(
+    settings: CollectionSettings,
+    limits: &Limits,
+) -> Result<DbConfig, (StatusCode, Json<ErrorResponse>)> {
+    check_limit("dimensions", settings.dimensions, limits.max_dimensions)?;
+
+    let mut hnsw = HnswConfig::default();
+    if let Some(m) = settings.m {
+        hnsw = hnsw.with_m(m);
+    }
+    hnsw.ef_construction = settings.ef_construction.unwrap_or(hnsw.ef_construction);
+    hnsw.ef_search = settings.ef_search.unwrap_or(hnsw.ef_search);
+
+    let config = DbConfig {
+        dimensions: settings.dimensions,
+        hnsw,
+        distance_metric: settings.distance_metric,
+        quantization: settings.quantization.unwrap_or(QuantizationType::None),
+        id_type: settings.id_type.unwrap_or_default(),
+        metadata_compression: settings.metadata_compression.unwrap_or_default(),
+        partition_field: settings.partition_field,
+        text_fields: settings.text_fields.unwrap_or_default(),
+        named_vectors: settings.named_vectors.unwrap_or_default(),
+        index: settings.index.unwrap_or_default(),
+        group_commit: settings.group_commit,
+        metadata_limits: settings.metadata_limits.unwrap_or_default(),
+        transform: settings.transform,
+        norm_filter: settings.norm_filter,
+        dtype: settings.dtype.unwrap_or_default(),
+        dtype_conversion: settings.dtype_conversion.unwrap_or_default(),
+        ..DbConfig::default()
+    };
+    Ok(config)
+}
+
+#[derive(Serialize, ToSchema)]
+struct PutCollectionResponse {
+    /// False if the collection already existed
+    created: bool,
+}
+
+#[utoipa::path(
+    put,
+    path = "/collections/{name}",
+    params(
+        ("name" = String, Path, description = "Collection name")
+    ),
+    request_body = CollectionSettings,
+    responses(
+        (status = 201, description = "Collection created", body = PutCollectionResponse),
+        (status = 200, description = "Collection already existed and was left as it is", body = PutCollectionResponse),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 409, description = "Collection exists with other dimensions or distance metric, or a deleted collection of that name is still in use", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn put_collection(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path(name): Path<String>,
+    Json(payload): Json<CollectionSettings>,
+) -> Result<(StatusCode, Json<PutCollectionResponse>), (StatusCode, Json<ErrorResponse>)> {
+    let limits = state.limits.effective(caller.key_name.as_deref());
+    let config = collection_config(payload, &limits)?;
+    check_tenant_collection(&state, &caller, &name, true)?;
+    let (dimensions, distance_metric) = (config.dimensions, config.distance_metric);
+
+    let created = state
+        .db
+        .create_collection_if_missing(&name, config)
+        .map_err(|e| {
+            warn!("Failed to create collection {}: {}", name, e);
+            let status = match e {
+                surgedb_core::Error::CollectionBusy(_) => StatusCode::CONFLICT,
+                _ => StatusCode::BAD_REQUEST,
+            };
+            (
+                status,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+        })?;
+    if created {
+        info!("Created collection: {}", name);
+        return Ok((StatusCode::CREATED, Json(PutCollectionResponse { created })));
+    }
+
+    // Settings that decide what the collection can hold must agree
+    let existing = state
+        .db
+        .get_collection(&name)
+        .map(|c| c.config())
+        .map_err(|e| {
+            (
+                StatusCode::CONFLICT,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+        })?;
+    if existing.dimensions != dimensions || existing.distance_metric != distance_metric {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(ErrorResponse {
+                error: format!(
+                    "Collection {} exists with {} dimensions and {:?} distance",
+                    name, existing.dimensions, existing.distance_metric
+                ),
+            }),
+        ));
+    }
+    Ok((StatusCode::OK, Json(PutCollectionResponse { created })))
+}
+
+#[utoipa::path(
+    head,
+    path = "/collections/{name}",
+    params(
+        ("name" = String, Path, description = "Collection name or alias")
+    ),
+    responses(
+        (status = 200, description = "Collection exists"),
+        (status = 404, description = "Collection not found")
+    ),
+    security(("api_key" = []))
+)]
+async fn collection_exists(State(state): State<AppState>, Path(name): Path<String>) -> StatusCode {
+    if state.db.get_collection(&name).is_ok() {
+        StatusCode::OK
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/collections",
+    responses(
+        (status = 200, description = "List of collection names", body = [String]),
+        (status = 304, description = "Unchanged since the ETag in If-None-Match")
+    ),
+    security(("api_key" = []))
+)]
+async fn list_collections(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    headers: HeaderMap,
+) -> Response {
+    let etag = etag::tag(&[state.boot_id, state.db.catalog_version()]);
+    if etag::matches(&headers, &etag) {
+        return etag::not_modified(&etag);
+    }
+    let mut names = state.db.list_collections();
+    if let Some(namespace) = &caller.namespace {
+        names.retain(|name| tenants::in_namespace(namespace, name));
+    }
+    etag::with_tag(Json(names), &etag)
+}
+
+/// ETag for reads of `collection`, taken before the data is read
+///
+/// `catalog` must be read before `collection` was looked up.
+async fn collection_etag(
+    state: &AppState,
+    catalog: u64,
+    collection: &Collection,
+) -> Result<String, (StatusCode, Json<ErrorResponse>)> {
+    let c = collection.clone();
+    let seq = spawn_blocking(move || c.write_seq()).await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    Ok(etag::tag(&[state.boot_id, catalog, seq]))
+}
+
+#[utoipa::path(
+    get,
+    path = "/collections/{name}",
+    params(
+        ("name" = String, Path, description = "Collection name or alias")
+    ),
+    responses(
+        (status = 200, description = "Collection configuration, counts, memory use and graph shape", body = CollectionInfo),
+        (status = 304, description = "Unchanged since the ETag in If-None-Match"),
+        (status = 404, description = "Collection not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn get_collection_info(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let catalog = state.db.catalog_version();
+    let collection = state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    let etag = collection_etag(&state, catalog, &collection).await?;
+    if etag::matches(&headers, &etag) {
+        return Ok(etag::not_modified(&etag));
+    }
+
+    let name = state.db.resolve_name(&name);
+    let info = spawn_blocking(move || {
+        let stats = collection.stats();
+        CollectionInfo {
+            name,
+            config: collection.config(),
+            vector_count: stats.vector_count,
+            deleted_count: stats.deleted_count,
+            write_seq: stats.write_seq,
+            log_id: stats.log_id,
+            memory_usage_bytes: stats.memory_breakdown.total(),
+            memory_breakdown: stats.memory_breakdown,
+            norm_rejections: stats.norm_rejections,
+            graph: collection.graph_stats(),
+        }
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    Ok(etag::with_tag(Json(info), &etag))
+}
+
+#[derive(Deserialize, IntoParams)]
+struct DeleteCollectionParams {
+    /// Delete even while an import or compaction is running; its work is lost
+    force: Option<bool>,
+}
+
+#[utoipa::path(
+    delete,
+    path = "/collections/{name}",
+    params(
+        ("name" = String, Path, description = "Collection name"),
+        DeleteCollectionParams
+    ),
+    responses(
+        (status = 200, description = "Collection deleted"),
+        (status = 404, description = "Collection not found", body = ErrorResponse),
+        (status = 409, description = "An alias points to the collection, or an import or compaction is running and `force` is not set", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn delete_collection(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Query(params): Query<DeleteCollectionParams>,
+) -> Result<&'static str, (StatusCode, Json<ErrorResponse>)> {
+    let deleted = if params.force.unwrap_or(false) {
+        state.db.force_delete_collection(&name)
+    } else {
+        state.db.delete_collection(&name)
+    };
+    match deleted {
+        Ok(_) => {
+            if let Err(e) = state.webhooks.remove_collection(&name) {
+                warn!("{}", e);
+            }
+            state.mirrors.remove_collection(&name);
+            if let Err(e) = state.compaction.remove_collection(&name) {
+                warn!("{}", e);
+            }
+            info!("Deleted collection: {}", name);
+            Ok("Deleted")
+        }
+        Err(e) => {
+            let status = match e {
+                surgedb_core::Error::CollectionNotFound(_) => StatusCode::NOT_FOUND,
+                _ => StatusCode::CONFLICT,
+            };
+            Err((
+                status,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            ))
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+struct UpdateCollectionRequest {
+    /// New name; aliases, webhooks, mirrors and compaction settings of the
+    /// collection follow it
+    #[schema(example = "products_v2")]
+    name: Option<String>,
+    /// Default candidate list size of searches
+    #[schema(example = 128)]
+    ef_search: Option<usize>,
+    /// Quantization to re-encode the collection with, in the background.
+    /// On-disk collections only.
+    quantization: Option<QuantizationType>,
+    /// New transform of query vectors, or `null` to remove it. A transform
+    /// applied on insert can only be changed while the collection is empty.
+    #[serde(default, deserialize_with = "present")]
+    #[schema(value_type = Option<Object>)]
+    transform: Option<Option<VectorTransform>>,
+    /// New norm filter of written vectors, or `null` to remove it
+    #[serde(default, deserialize_with = "present")]
+    #[schema(value_type = Option<Object>)]
+    norm_filter: Option<Option<NormFilter>>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct UpdateCollectionResponse {
+    /// Name of the collection after the update
+    name: String,
+    /// Whether re-encoding with the new quantization has started
+    reencoding: bool,
+}
+
+#[utoipa::path(
+    patch,
+    path = "/collections/{name}",
+    params(
+        ("name" = String, Path, description = "Collection name or alias")
+    ),
+    request_body = UpdateCollectionRequest,
+    responses(
+        (status = 200, description = "Collection updated", body = UpdateCollectionResponse),
+        (status = 202, description = "Collection updated, and re-encoding with the new quantization started in the background", body = UpdateCollectionResponse),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse),
+        (status = 409, description = "The new name is taken, or an import, compaction or re-encode is running", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn update_collection(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path(name): Path<String>,
+    Json(payload): Json<UpdateCollectionRequest>,
+) -> Result<(StatusCode, Json<UpdateCollectionResponse>), (StatusCode, Json<ErrorResponse>)> {
+    let error = |e: surgedb_core::Error| {
+        let status = match e {
+            surgedb_core::Error::CollectionNotFound(_) => StatusCode::NOT_FOUND,
+            surgedb_core::Error::DuplicateCollection(_)
+            | surgedb_core::Error::CollectionBusy(_) => StatusCode::CONFLICT,
+            _ => StatusCode::BAD_REQUEST,
+        };
+        (
+            status,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    };
+    if let Some(new_name) = &payload.name {
+        check_tenant_collection(&state, &caller, new_name, false)?;
+    }
+    let current = state.db.resolve_name(&name);
+    let collection = state.db.get_collection(&current).map_err(error)?;
+    let quantization = payload
+        .quantization
+        .filter(|&q| q != collection.config().quantization);
+    if quantization.is_some() && !collection.on_disk() {
+        return Err(error(surgedb_core::Error::InvalidConfig(
+            "Quantization can only be changed on on-disk collections".to_string(),
+        )));
+    }
+
+    // The rename and new default apply right away; re-encoding follows
+    let new_name = payload.name.clone().unwrap_or_else(|| current.clone());
+    let update = CollectionUpdate {
+        name: payload.name,
+        ef_search: payload.ef_search,
+        quantization: None,
+        transform: payload.transform,
+        norm_filter: payload.norm_filter,
+    };
+    let db = state.db.clone();
+    let from = current.clone();
+    spawn_blocking(move || db.update_collection(&from, update))
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+        })?
+        .map_err(error)?;
+    if new_name != current {
+        if let Err(e) = state.webhooks.rename_collection(&current, &new_name) {
+            warn!("{}", e);
+        }
+        state.mirrors.rename_collection(&current, &new_name);
+        if let Err(e) = state.compaction.rename_collection(&current, &new_name) {
+            warn!("{}", e);
+        }
+    }
+
+    let Some(quantization) = quantization else {
+        return Ok((
+            StatusCode::OK,
+            Json(UpdateCollectionResponse {
+                name: new_name,
+                reencoding: false,
+            }),
+        ));
+    };
+    let db = state.db.clone();
+    let target = new_name.clone();
+    tokio::spawn(async move {
+        let update = CollectionUpdate {
+            quantization: Some(quantization),
+            ..CollectionUpdate::default()
+        };
+        let name = target.clone();
+        match spawn_blocking(move || db.update_collection(&target, update)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => warn!("Failed to re-encode collection {}: {}", name, e),
+            Err(e) => warn!("Re-encoding collection {} panicked: {}", name, e),
+        }
+    });
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(UpdateCollectionResponse {
+            name: new_name,
+            reencoding: true,
+        }),
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/collections/{name}/vectors",
+    params(
+        ("name" = String, Path, description = "Collection name")
+    ),
+    request_body = InsertRequest,
+    responses(
+        (status = 200, description = "Vector inserted"),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn insert_vector(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path(name): Path<String>,
+    Json(payload): Json<InsertRequest>,
+) -> Result<&'static str, (StatusCode, Json<ErrorResponse>)> {
+    let handler_start = Instant::now();
+    let max_vectors = write_limits(&state, &caller, &name).max_vectors;
+    let collection = state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    validate_sparse(std::slice::from_ref(&payload))?;
+    validate_named(&collection, std::slice::from_ref(&payload))?;
+
+    let mirrored = mirror_target(&state, &name).map(|target| (target, payload.clone()));
+    let work_start = Instant::now();
+    let result = spawn_blocking(move || {
+        let sparse = payload.sparse.map(|sparse| (payload.id.clone(), sparse));
+        let named = payload.vectors.map(|vectors| (payload.id.clone(), vectors));
+        let vector = collection.convert_vector(&payload.vector)?;
+        check_vector_quota(&collection, [&payload.id], max_vectors)?;
+        collection.insert(payload.id, &vector, payload.metadata)?;
+        set_sparse_vectors(&collection, sparse)?;
+        set_named_vectors(&collection, named)
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    let work_ms = work_start.elapsed().as_secs_f64() * 1000.0;
+    let total_ms = handler_start.elapsed().as_secs_f64() * 1000.0;
+    log_perf("insert_vector", total_ms, work_ms, None, None);
+
+    match result {
+        Ok(_) => {
+            if let Some((target, item)) = mirrored {
+                state.mirrors.publish(&target, Change::Upsert(vec![item]));
+            }
+            Ok("Inserted")
+        }
+        Err(e) => Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/collections/{name}/upsert",
+    params(
+        ("name" = String, Path, description = "Collection name")
+    ),
+    params(
+        ("If-Match" = Option<u64>, Header, description = "Upsert only if the vector exists at this version"),
+        ("If-None-Match" = Option<String>, Header, description = "`*` to insert only if the vector doesn't exist")
+    ),
+    request_body = InsertRequest,
+    responses(
+        (status = 200, description = "Vector upserted"),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 412, description = "Vector missing or not at the If-Match version, or existing with If-None-Match", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn upsert_vector(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+    Json(payload): Json<InsertRequest>,
+) -> Result<&'static str, (StatusCode, Json<ErrorResponse>)> {
+    let handler_start = Instant::now();
+    let if_version = etag::if_match_version(&headers)
+        .map_err(|error| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })))?;
+    let if_absent = etag::if_none_match_any(&headers);
+    if if_absent && if_version.is_some() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "If-Match and If-None-Match cannot be combined".to_string(),
+            }),
+        ));
+    }
+    let max_vectors = write_limits(&state, &caller, &name).max_vectors;
+    let collection = state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    validate_sparse(std::slice::from_ref(&payload))?;
+    validate_named(&collection, std::slice::from_ref(&payload))?;
+
+    let mirrored = mirror_target(&state, &name).map(|target| (target, payload.clone()));
+    let work_start = Instant::now();
+    let result = spawn_blocking(move || {
+        let sparse = payload.sparse.map(|sparse| (payload.id.clone(), sparse));
+        let named = payload.vectors.map(|vectors| (payload.id.clone(), vectors));
+        let vector = collection.convert_vector(&payload.vector)?;
+        check_vector_quota(&collection, [&payload.id], max_vectors)?;
+        match if_version {
+            Some(version) => {
+                collection.upsert_if_version(payload.id, &vector, payload.metadata, version)?
+            }
+            None if if_absent => {
+                let id = payload.id.clone();
+                if !collection.insert_if_absent(payload.id, &vector, payload.metadata)? {
+                    return Err(surgedb_core::Error::VersionConflict {
+                        actual: collection.version(&id)?,
+                        id,
+                        expected: None,
+                    });
+                }
+            }
+            None => collection.upsert(payload.id, &vector, payload.metadata)?,
+        }
+        set_sparse_vectors(&collection, sparse)?;
+        set_named_vectors(&collection, named)
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    let work_ms = work_start.elapsed().as_secs_f64() * 1000.0;
+    let total_ms = handler_start.elapsed().as_secs_f64() * 1000.0;
+    log_perf("upsert_vector", total_ms, work_ms, None, None);
+
+    match result {
+        Ok(_) => {
+            if let Some((target, item)) = mirrored {
+                state.mirrors.publish(&target, Change::Upsert(vec![item]));
+            }
+            Ok("Upserted")
+        }
+        Err(e) => Err((
+            version_conflict_status(&e),
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )),
+    }
+}
+
+/// 412 for a write whose `If-Match` or `If-None-Match` condition didn't
+/// hold, 400 otherwise
+fn version_conflict_status(e: &surgedb_core::Error) -> StatusCode {
+    match e {
+        surgedb_core::Error::VersionConflict { .. } => StatusCode::PRECONDITION_FAILED,
+        _ => StatusCode::BAD_REQUEST,
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/collections/{name}/vectors/batch",
+    params(
+        ("name" = String, Path, description = "Collection name")
+    ),
+    request_body = BatchInsertRequest,
+    responses(
+        (status = 200, description = "Number of vectors upserted, with norm stats if requested", body = BatchInsertResponse),
+        (status = 400, description = "Invalid request", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn batch_insert_vector(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path(name): Path<String>,
+    Json(payload): Json<BatchInsertRequest>,
+) -> Result<Json<BatchInsertResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let handler_start = Instant::now();
+    let limits = write_limits(&state, &caller, &name);
+    check_limit("batch size", payload.vectors.len(), limits.max_batch_size)?;
+    validate_sparse(&payload.vectors)?;
+
+    let collection = state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    validate_named(&collection, &payload.vectors)?;
+
+    let count = payload.vectors.len();
+    let normalize = payload.normalize.unwrap_or(false);
+    let with_stats = payload.with_stats.unwrap_or(false);
+    let mirror = mirror_target(&state, &name);
+    let mirrored = mirror.is_some();
+    let work_start = Instant::now();
+    let result = spawn_blocking(move || {
+        let (mut items, attached) = converted(&collection, payload.vectors)?;
+
+        let mut stats = None;
+        if normalize || with_stats {
+            let norms: Vec<f32> = items.iter().map(|(_, v, _)| ingest::l2_norm(v)).collect();
+            if normalize {
+                for ((_, vector, _), norm) in items.iter_mut().zip(&norms) {
+                    ingest::normalize(vector, *norm);
+                }
+            }
+            if with_stats {
+                let ids = items.iter().map(|(id, _, _)| id.as_str());
+                stats = Some(ingest::analyze(
+                    ids,
+                    norms,
+                    payload.norm_checks.unwrap_or_default(),
+                ));
+            }
+        }
+
+        let changed = store_items(&collection, items, attached, limits.max_vectors, mirrored)?;
+        Ok::<_, surgedb_core::Error>((stats, changed))
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    let work_ms = work_start.elapsed().as_secs_f64() * 1000.0;
+    let total_ms = handler_start.elapsed().as_secs_f64() * 1000.0;
+    log_perf("batch_insert_vector", total_ms, work_ms, None, Some(count));
+
+    match result {
+        Ok((stats, changed)) => {
+            if let (Some(target), Some(vectors)) = (mirror, changed) {
+                state.mirrors.publish(&target, Change::Upsert(vectors));
+            }
+            Ok(Json(match stats {
+                None => BatchInsertResponse::Count(count),
+                Some(stats) => BatchInsertResponse::WithStats(BatchInsertWithStats {
+                    upserted: count,
+                    stats,
+                }),
+            }))
+        }
+        Err(e) => Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )),
+    }
+}
+
+/// Sparse and named vectors sent along with a record
+type AttachedVectors = (Option<SparseVector>, Option<HashMap<String, Vec<f32>>>);
+
+/// A record as stored: ID, vector and metadata
+type StoredItem = (String, Vec<f32>, Option<Value>);
+
+/// Records as stored, with their vectors converted from the collection's
+/// dtype, and the vectors sent along with them
+fn converted(
+    collection: &Collection,
+    items: Vec<InsertRequest>,
+) -> surgedb_core::Result<(Vec<StoredItem>, Vec<AttachedVectors>)> {
+    let mut records = Vec::with_capacity(items.len());
+    let mut attached = Vec::with_capacity(items.len());
+    for item in items {
+        let vector = collection.convert_vector(&item.vector)?;
+        records.push((item.id, vector, item.metadata));
+        attached.push((item.sparse, item.vectors));
+    }
+    Ok((records, attached))
+}
+
+/// Upsert `items` and their `attached` vectors once they pass the quota
+///
+/// Returns the records as mirrors get them (as stored, after normalization)
+/// if `mirrored`.
+fn store_items(
+    collection: &Collection,
+    items: Vec<StoredItem>,
+    attached: Vec<AttachedVectors>,
+    max_vectors: Option<usize>,
+    mirrored: bool,
+) -> surgedb_core::Result<Option<Vec<InsertRequest>>> {
+    let changed = mirrored.then(|| {
+        items
+            .iter()
+            .zip(&attached)
+            .map(
+                |((id, vector, metadata), (sparse, vectors))| InsertRequest {
+                    id: id.clone(),
+                    vector: vector.iter().copied().map(f64::from).collect(),
+                    metadata: metadata.clone(),
+                    sparse: sparse.clone(),
+                    vectors: vectors.clone(),
+                },
+            )
+            .collect()
+    });
+    let ids: Vec<String> = items.iter().map(|(id, _, _)| id.clone()).collect();
+    check_vector_quota(collection, &ids, max_vectors)?;
+    collection.upsert_batch(items)?;
+    let (sparse, named): (Vec<_>, Vec<_>) = ids
+        .into_iter()
+        .zip(attached)
+        .map(|(id, (sparse, vectors))| {
+            (
+                sparse.map(|sparse| (id.clone(), sparse)),
+                vectors.map(|vectors| (id, vectors)),
+            )
+        })
+        .unzip();
+    set_sparse_vectors(collection, sparse.into_iter().flatten())?;
+    set_named_vectors(collection, named.into_iter().flatten())?;
+    Ok(changed)
+}
+
+/// Records stored per batch of an import unless the request sets `batch_size`
+const DEFAULT_IMPORT_BATCH_SIZE: usize = 1_000;
+
+#[derive(Deserialize, IntoParams)]
+struct ImportParams {
+    /// Records stored per batch; at most the caller's `max_batch_size`
+    #[param(example = 1000)]
+    batch_size: Option<usize>,
+    /// Scale every vector to unit length before storing it
+    normalize: Option<bool>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct ImportResponse {
+    /// Records upserted
+    imported: usize,
+    /// Batches they were stored in
+    batches: usize,
+}
+
+#[utoipa::path(
+    post,
+    path = "/collections/{name}/import",
+    params(
+        ("name" = String, Path, description = "Collection name"),
+        ImportParams
+    ),
+    request_body(
+        content = InsertRequest,
+        content_type = "application/x-ndjson",
+        description = "One record per line, as for a single insert"
+    ),
+    responses(
+        (status = 200, description = "Every record upserted", body = ImportResponse),
+        (status = 400, description = "Invalid line or batch; the batches before it stay stored", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn import_vectors(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path(name): Path<String>,
+    Query(params): Query<ImportParams>,
+    body: axum::body::Body,
+) -> Result<Json<ImportResponse>, (StatusCode, Json<ErrorResponse>)> {
+    use futures_util::StreamExt;
+
+    let handler_start = Instant::now();
+    let limits = write_limits(&state, &caller, &name);
+    let batch_size = params
+        .batch_size
+        .unwrap_or(DEFAULT_IMPORT_BATCH_SIZE.min(limits.max_batch_size))
+        .max(1);
+    check_limit("batch_size", batch_size, limits.max_batch_size)?;
+
+    let collection = state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    // Held across batches, so the collection isn't deleted mid-import
+    let _job = collection.begin_job();
+    let normalize = params.normalize.unwrap_or(false);
+    let mirror = mirror_target(&state, &name);
+    let mut imported = 0;
+    let mut batches = 0;
+    let failed = |imported: usize, error: String| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: format!("{} ({} records imported before it)", error, imported),
+            }),
+        )
+    };
+
+    // The body is read a chunk at a time and the next chunk is only pulled
+    // once the batches completed so far are stored
+    let mut stream = body.into_data_stream();
+    let mut pending: Vec<u8> = Vec::new();
+    let mut batch = Vec::with_capacity(batch_size);
+    let mut line_number = 0;
+    loop {
+        let chunk = stream.next().await;
+        let done = chunk.is_none();
+        match chunk {
+            Some(Ok(bytes)) => pending.extend_from_slice(&bytes),
+            Some(Err(e)) => return Err(failed(imported, e.to_string())),
+            None => {}
+        }
+
+        // Complete lines, and at the end whatever follows the last newline
+        let mut lines = Vec::new();
+        let mut start = 0;
+        while let Some(end) = pending[start..].iter().position(|&b| b == b'\n') {
+            lines.push(pending[start..start + end].to_vec());
+            start += end + 1;
+        }
+        pending.drain(..start);
+        if done && !pending.is_empty() {
+            lines.push(std::mem::take(&mut pending));
+        }
+        if pending.len() > state.config.max_request_size_bytes {
+            return Err(failed(
+                imported,
+                format!(
+                    "Line {} is longer than {} bytes",
+                    line_number + 1,
+                    state.config.max_request_size_bytes
+                ),
+            ));
+        }
+
+        for line in lines {
+            line_number += 1;
+            if line.trim_ascii().is_empty() {
+                continue;
+            }
+            let item: InsertRequest = serde_json::from_slice(&line)
+                .map_err(|e| failed(imported, format!("Line {}: {}", line_number, e)))?;
+            batch.push(item);
+            if batch.len() == batch_size {
+                let items = std::mem::replace(&mut batch, Vec::with_capacity(batch_size));
+                imported += import_batch(
+                    &state,
+                    &collection,
+                    mirror.as_deref(),
+                    items,
+                    normalize,
+                    limits.max_vectors,
+                )
+                .await
+                .map_err(|e| failed(imported, e))?;
+                batches += 1;
+            }
+        }
+
+        if done {
+            break;
+        }
+    }
+    if !batch.is_empty() {
+        imported += import_batch(
+            &state,
+            &collection,
+            mirror.as_deref(),
+            batch,
+            normalize,
+            limits.max_vectors,
+        )
+        .await
+        .map_err(|e| failed(imported, e))?;
+        batches += 1;
+    }
+
+    let total_ms = handler_start.elapsed().as_secs_f64() * 1000.0;
+    log_perf("import_vectors", total_ms, total_ms, None, Some(imported));
+    info!(
+        "Imported {} vectors into {} in {} batches",
+        imported, name, batches
+    );
+    Ok(Json(ImportResponse { imported, batches }))
+}
+
+/// Store one batch of an import, returning how many records it had
+async fn import_batch(
+    state: &AppState,
+    collection: &Collection,
+    mirror: Option<&str>,
+    items: Vec<InsertRequest>,
+    normalize: bool,
+    max_vectors: Option<usize>,
+) -> Result<usize, String> {
+    validate_sparse(&items).map_err(|(_, Json(e))| e.error)?;
+    validate_named(collection, &items).map_err(|(_, Json(e))| e.error)?;
+    let count = items.len();
+    let collection = collection.clone();
+    let mirrored = mirror.is_some();
+    let changed = spawn_blocking(move || {
+        let (mut records, attached) = converted(&collection, items)?;
+        if normalize {
+            for (_, vector, _) in records.iter_mut() {
+                let norm = ingest::l2_norm(vector);
+                ingest::normalize(vector, norm);
+            }
+        }
+        store_items(&collection, records, attached, max_vectors, mirrored)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+
+    if let (Some(target), Some(vectors)) = (mirror, changed) {
+        state.mirrors.publish(target, Change::Upsert(vectors));
+    }
+    Ok(count)
+}
+
+/// One record of a bulk load and its nearest neighbors
+#[derive(Deserialize, ToSchema)]
+struct BulkLoadRecord {
+    /// String, or a JSON integer for `U64` collections
+    #[serde(deserialize_with = "string_or_u64")]
+    #[schema(example = "vec1")]
+    id: String,
+    /// In the collection's `dtype`
+    #[schema(example = "[0.1, 0.2, 0.3]")]
+    vector: Vec<f64>,
+    metadata: Option<Value>,
+    /// Positions in the body, from 0 and not counting blank lines, of the
+    /// record's nearest neighbors; negative entries, such as the `-1`
+    /// padding of a FAISS result, are skipped
+    #[serde(default)]
+    #[schema(example = "[4, 17, -1]")]
+    neighbors: Vec<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct BulkLoadResponse {
+    /// Records stored
+    loaded: usize,
+}
+
+#[utoipa::path(
+    post,
+    path = "/collections/{name}/bulk-load",
+    params(("name" = String, Path, description = "Collection name")),
+    request_body(
+        content = BulkLoadRecord,
+        content_type = "application/x-ndjson",
+        description = "One record per line with its precomputed nearest neighbors, such as a kNN graph built offline"
+    ),
+    responses(
+        (status = 200, description = "Records stored and the graph linked from their neighbors", body = BulkLoadResponse),
+        (status = 400, description = "Invalid line, the collection isn't empty, or it has no HNSW index; nothing is stored", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn bulk_load_vectors(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path(name): Path<String>,
+    body: axum::body::Body,
+) -> Result<Json<BulkLoadResponse>, (StatusCode, Json<ErrorResponse>)> {
+    use futures_util::StreamExt;
+
+    let handler_start = Instant::now();
+    let limits = write_limits(&state, &caller, &name);
+    let collection = state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    let bad_request = |error: String| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error }));
+
+    // Held while the body is read, so the collection isn't deleted meanwhile
+    let job = collection.begin_job();
+    let mut stream = body.into_data_stream();
+    let mut pending: Vec<u8> = Vec::new();
+    let mut records: Vec<BulkLoadRecord> = Vec::new();
+    let mut line_number = 0;
+    loop {
+        let chunk = stream.next().await;
+        let done = chunk.is_none();
+        match chunk {
+            Some(Ok(bytes)) => pending.extend_from_slice(&bytes),
+            Some(Err(e)) => return Err(bad_request(e.to_string())),
+            None => {}
+        }
+
+        let mut start = 0;
+        let mut parse = |line: &[u8]| {
+            line_number += 1;
+            if line.trim_ascii().is_empty() {
+                return Ok(());
+            }
+            let record = serde_json::from_slice(line)
+                .map_err(|e| bad_request(format!("Line {}: {}", line_number, e)))?;
+            records.push(record);
+            Ok(())
+        };
+        while let Some(end) = pending[start..].iter().position(|&b| b == b'\n') {
+            parse(&pending[start..start + end])?;
+            start += end + 1;
+        }
+        pending.drain(..start);
+        if done {
+            parse(&std::mem::take(&mut pending))?;
+            break;
+        }
+        if pending.len() > state.config.max_request_size_bytes {
+            return Err(bad_request(format!(
+                "Line {} is longer than {} bytes",
+                line_number + 1,
+                state.config.max_request_size_bytes
+            )));
+        }
+    }
+
+    let loaded = records.len();
+    let mirror = mirror_target(&state, &name);
+    let mirrored = mirror.is_some();
+    let changed = spawn_blocking(move || {
+        let _job = job;
+        let mut items = Vec::with_capacity(records.len());
+        let mut neighbors = Vec::with_capacity(records.len());
+        for record in records {
+            let vector = collection.convert_vector(&record.vector)?;
+            items.push((record.id, vector, record.metadata));
+            neighbors.push(
+                record
+                    .neighbors
+                    .into_iter()
+                    .filter_map(|n| usize::try_from(n).ok())
+                    .collect(),
+            );
+        }
+        let changed = mirrored.then(|| {
+            items
+                .iter()
+                .map(|(id, vector, metadata)| InsertRequest {
+                    id: id.clone(),
+                    vector: vector.iter().copied().map(f64::from).collect(),
+                    metadata: metadata.clone(),
+                    sparse: None,
+                    vectors: None,
+                })
+                .collect::<Vec<_>>()
+        });
+        check_vector_quota(
+            &collection,
+            items.iter().map(|(id, _, _)| id),
+            limits.max_vectors,
+        )?;
+        collection.bulk_load(items, neighbors)?;
+        Ok::<_, surgedb_core::Error>(changed)
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?
+    .map_err(|e| bad_request(e.to_string()))?;
+
+    if let (Some(target), Some(vectors)) = (mirror, changed) {
+        state.mirrors.publish(&target, Change::Upsert(vectors));
+    }
+
+    let total_ms = handler_start.elapsed().as_secs_f64() * 1000.0;
+    log_perf("bulk_load", total_ms, total_ms, None, Some(loaded));
+    info!("Bulk loaded {} vectors into {}", loaded, name);
+    Ok(Json(BulkLoadResponse { loaded }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/collections/{name}/documents/{doc_id}/replace",
+    params(
+        ("name" = String, Path, description = "Collection name"),
+        ("doc_id" = String, Path, description = "Document ID, matched against the `doc_id` metadata field")
+    ),
+    request_body = ReplaceDocumentRequest,
+    responses(
+        (status = 200, description = "Document chunks replaced", body = ReplaceDocumentResponse),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn replace_document(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path((name, doc_id)): Path<(String, String)>,
+    Json(payload): Json<ReplaceDocumentRequest>,
+) -> Result<Json<ReplaceDocumentResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let handler_start = Instant::now();
+    let limits = write_limits(&state, &caller, &name);
+    check_limit("batch size", payload.vectors.len(), limits.max_batch_size)?;
+    validate_sparse(&payload.vectors)?;
+
+    let collection = state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    validate_named(&collection, &payload.vectors)?;
+
+    let (items, attached): (Vec<_>, Vec<_>) = payload
+        .vectors
+        .into_iter()
+        .map(|item| {
+            let mut metadata = match item.metadata {
+                None => serde_json::Map::new(),
+                Some(Value::Object(map)) => map,
+                Some(_) => {
+                    return Err((
+                        StatusCode::BAD_REQUEST,
+                        Json(ErrorResponse {
+                            error: format!("Metadata of chunk {} must be an object", item.id),
+                        }),
+                    ))
+                }
+            };
+            metadata.insert(DOC_ID_FIELD.to_string(), Value::String(doc_id.clone()));
+            let sparse = item.sparse.map(|sparse| (item.id.clone(), sparse));
+            let named = item.vectors.map(|vectors| (item.id.clone(), vectors));
+            let vector = collection.convert_vector(&item.vector).map_err(|e| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    Json(ErrorResponse {
+                        error: e.to_string(),
+                    }),
+                )
+            })?;
+            Ok((
+                (item.id, vector, Some(Value::Object(metadata))),
+                (sparse, named),
+            ))
+        })
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .unzip();
+
+    let inserted = items.len();
+    let mirrored = mirror_target(&state, &name).map(|target| {
+        let vectors = items
+            .iter()
+            .zip(&attached)
+            .map(|((id, vector, metadata), (sparse, named))| InsertRequest {
+                id: id.clone(),
+                vector: vector.iter().copied().map(f64::from).collect(),
+                metadata: metadata.clone(),
+                sparse: sparse.as_ref().map(|(_, sparse)| sparse.clone()),
+                vectors: named.as_ref().map(|(_, vectors)| vectors.clone()),
+            })
+            .collect();
+        (target, doc_id.clone(), vectors)
+    });
+    let filter = Filter::Exact(DOC_ID_FIELD.to_string(), Value::String(doc_id));
+    let work_start = Instant::now();
+    let max_vectors = limits.max_vectors;
+    let result = spawn_blocking(move || {
+        check_vector_quota(&collection, items.iter().map(|(id, _, _)| id), max_vectors)?;
+        let deleted = collection.replace(&filter, items)?;
+        let (sparse, named): (Vec<_>, Vec<_>) = attached.into_iter().unzip();
+        set_sparse_vectors(&collection, sparse.into_iter().flatten())?;
+        set_named_vectors(&collection, named.into_iter().flatten())?;
+        Ok::<_, surgedb_core::Error>(deleted)
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    let work_ms = work_start.elapsed().as_secs_f64() * 1000.0;
+    let total_ms = handler_start.elapsed().as_secs_f64() * 1000.0;
+    log_perf("replace_document", total_ms, work_ms, None, Some(inserted));
+
+    match result {
+        Ok(deleted) => {
+            if let Some((target, doc_id, vectors)) = mirrored {
+                state
+                    .mirrors
+                    .publish(&target, Change::Replace { doc_id, vectors });
+            }
+            Ok(Json(ReplaceDocumentResponse { deleted, inserted }))
+        }
+        Err(e) => Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )),
+    }
+}
+
+#[utoipa::path(
+    head,
+    path = "/collections/{name}/vectors/{id}",
+    params(
+        ("name" = String, Path, description = "Collection name"),
+        ("id" = String, Path, description = "Vector ID")
+    ),
+    responses(
+        (status = 200, description = "Vector exists"),
+        (status = 404, description = "Collection or vector not found")
+    ),
+    security(("api_key" = []))
+)]
+async fn vector_exists(
+    State(state): State<AppState>,
+    Path((name, id)): Path<(String, String)>,
+) -> StatusCode {
+    match state.db.get_collection(&name) {
+        Ok(collection) if collection.contains(&id) => StatusCode::OK,
+        _ => StatusCode::NOT_FOUND,
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/collections/{name}/vectors/{id}",
+    params(
+        ("name" = String, Path, description = "Collection name"),
+        ("id" = String, Path, description = "Vector ID")
+    ),
+    responses(
+        (status = 200, description = "Vector found", body = VectorResponse),
+        (status = 304, description = "Unchanged since the ETag in If-None-Match"),
+        (status = 404, description = "Vector not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn get_vector(
+    State(state): State<AppState>,
+    Path((name, id)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let catalog = state.db.catalog_version();
+    let collection = state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    let etag = collection_etag(&state, catalog, &collection).await?;
+    if etag::matches(&headers, &etag) {
+        return Ok(etag::not_modified(&etag));
+    }
+
+    let id_clone = id.clone();
+    let result = spawn_blocking(move || {
+        collection.get_versioned(&id_clone).map(|found| {
+            found.map(|(vector, metadata, version)| {
+                let named = collection.get_named_vectors(&id_clone);
+                (vector, metadata, version, named.into_iter().collect())
+            })
+        })
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    match result {
+        Ok(Some((vector, metadata, version, vectors))) => Ok(etag::with_tag(
+            Json(VectorResponse {
+                id,
+                vector,
+                metadata,
+                version: Some(version),
+                vectors,
+            }),
+            &etag,
+        )),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Vector not found".to_string(),
+            }),
+        )),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/collections/{name}/vectors/{id}",
+    params(
+        ("name" = String, Path, description = "Collection name"),
+        ("id" = String, Path, description = "Vector ID"),
+        ("If-Match" = Option<u64>, Header, description = "Delete only if the vector is at this version")
+    ),
+    responses(
+        (status = 200, description = "Vector deleted"),
+        (status = 400, description = "If-Match is not a version", body = ErrorResponse),
+        (status = 404, description = "Vector not found", body = ErrorResponse),
+        (status = 412, description = "Vector missing or not at the If-Match version", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn delete_vector(
+    State(state): State<AppState>,
+    Path((name, id)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<&'static str, (StatusCode, Json<ErrorResponse>)> {
+    let if_version = etag::if_match_version(&headers)
+        .map_err(|error| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })))?;
+    let collection = state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    let id_clone = id.clone();
+    let result = spawn_blocking(move || match if_version {
+        Some(version) => collection
+            .delete_if_version(&id_clone, version)
+            .map(|()| true),
+        None => collection.delete(&id_clone),
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    match result {
+        Ok(true) => {
+            if let Some(target) = mirror_target(&state, &name) {
+                state.mirrors.publish(&target, Change::Delete(id));
+            }
+            Ok("Deleted")
+        }
+        Ok(false) => Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Vector not found".to_string(),
+            }),
+        )),
+        Err(e) => Err((
+            match e {
+                surgedb_core::Error::VersionConflict { .. } => StatusCode::PRECONDITION_FAILED,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            },
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )),
+    }
+}
+
+/// Vectors to delete: either `ids` or a `filter`
+#[derive(Deserialize, ToSchema)]
+struct DeleteVectorsRequest {
+    #[schema(example = "[\"vec1\", \"vec2\"]")]
+    ids: Option<Vec<String>>,
+    /// Delete every vector whose metadata matches
+    filter: Option<Filter>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct DeleteVectorsResponse {
+    /// Vectors that existed and were deleted
+    deleted: usize,
+}
+
+#[utoipa::path(
+    post,
+    path = "/collections/{name}/vectors/delete",
+    params(
+        ("name" = String, Path, description = "Collection name")
+    ),
+    request_body = DeleteVectorsRequest,
+    responses(
+        (status = 200, description = "Number of vectors deleted", body = DeleteVectorsResponse),
+        (status = 400, description = "Neither or both of ids and filter, or an invalid filter", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn delete_vectors(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path(name): Path<String>,
+    Json(payload): Json<DeleteVectorsRequest>,
+) -> Result<Json<DeleteVectorsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let bad_request = |error: String| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error }));
+    match (&payload.ids, &payload.filter) {
+        (Some(ids), None) => {
+            let limits = state.limits.effective(caller.key_name.as_deref());
+            check_limit("batch size", ids.len(), limits.max_batch_size)?;
+        }
+        (None, Some(filter)) => filter.validate().map_err(|e| bad_request(e.to_string()))?,
+        _ => {
+            return Err(bad_request(
+                "Pass either ids or a filter to delete".to_string(),
+            ))
+        }
+    }
+
+    let collection = state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    let deleted = spawn_blocking(move || match (payload.ids, payload.filter) {
+        (Some(ids), _) => collection.delete_batch(&ids),
+        (_, Some(filter)) => collection.delete_by_filter(&filter),
+        (None, None) => Ok(Vec::new()),
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    if !deleted.is_empty() {
+        info!("Deleted {} vectors from {}", deleted.len(), name);
+        if let Some(target) = mirror_target(&state, &name) {
+            let ids = deleted.iter().map(|id| id.to_string()).collect();
+            state.mirrors.publish(&target, Change::DeleteMany(ids));
+        }
+    }
+    Ok(Json(DeleteVectorsResponse {
+        deleted: deleted.len(),
+    }))
+}
+
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
+struct UpdateMetadataRequest {
+    /// New metadata, or a JSON merge patch of the current metadata
+    metadata: Value,
+    /// Merge into the current metadata, where `null` removes a key, instead
+    /// of replacing it (default: true)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    merge: Option<bool>,
+}
+
+#[utoipa::path(
+    patch,
+    path = "/collections/{name}/vectors/{id}/metadata",
+    params(
+        ("name" = String, Path, description = "Collection name"),
+        ("id" = String, Path, description = "Vector ID")
+    ),
+    request_body = UpdateMetadataRequest,
+    responses(
+        (status = 200, description = "Metadata updated"),
+        (status = 404, description = "Vector not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn update_metadata(
+    State(state): State<AppState>,
+    Path((name, id)): Path<(String, String)>,
+    Json(payload): Json<UpdateMetadataRequest>,
+) -> Result<&'static str, (StatusCode, Json<ErrorResponse>)> {
+    let collection = state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    let mirrored = mirror_target(&state, &name).map(|target| (target, payload.clone()));
+    let id_clone = id.clone();
+    let result = spawn_blocking(move || {
+        collection.update_metadata(&id_clone, payload.metadata, payload.merge.unwrap_or(true))
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    match result {
+        Ok(true) => {
+            if let Some((target, update)) = mirrored {
+                state
+                    .mirrors
+                    .publish(&target, Change::Metadata { id, update });
+            }
+            Ok("Updated")
+        }
+        Ok(false) => Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Vector not found".to_string(),
+            }),
+        )),
+        Err(e) => Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )),
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct VectorListEntry {
+    id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<Value>,
+}
+
+/// One page of a cursor listing
+#[derive(Serialize, ToSchema)]
+struct VectorListPage {
+    vectors: Vec<VectorListEntry>,
+    /// Cursor of the next page, absent after the last one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_cursor: Option<String>,
+}
+
+/// Opaque token for a list cursor
+fn encode_cursor(cursor: &ListCursor) -> String {
+    format!(
+        "{:016x}{:016x}{:016x}",
+        cursor.epoch, cursor.frontier, cursor.next
+    )
+}
+
+/// Records per scroll page unless the request says
+const DEFAULT_SCROLL_LIMIT: usize = 100;
+
+#[derive(Deserialize, ToSchema)]
+struct ScrollRequest {
+    /// Empty or absent to start, then the previous page's `next_cursor`
+    #[serde(default)]
+    #[schema(example = "")]
+    cursor: Option<String>,
+    /// Records per page (default 100, at most the batch size limit)
+    #[schema(example = 100)]
+    limit: Option<usize>,
+    /// Only return records whose metadata matches
+    filter: Option<Filter>,
+}
+
+/// One page of a scroll
+#[derive(Serialize, ToSchema)]
+struct ScrollResponse {
+    /// Records with their vectors and metadata; named vectors are not included
+    records: Vec<VectorResponse>,
+    /// Cursor of the next page, absent after the last one. A filtered page
+    /// can be short or empty and still have one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_cursor: Option<String>,
+}
+
+fn decode_cursor(token: &str) -> Option<ListCursor> {
+    if token.len() != 48 || !token.is_ascii() {
+        return None;
+    }
+    let field = |i: usize| u64::from_str_radix(&token[i * 16..(i + 1) * 16], 16).ok();
+    Some(ListCursor {
+        epoch: field(0)?,
+        frontier: field(1)?,
+        next: field(2)?,
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/collections/{name}/vectors",
+    params(
+        ("name" = String, Path, description = "Collection name"),
+        PaginationParams
+    ),
+    responses(
+        (status = 200, description = "List of vector records, or a `VectorListPage` when `cursor` is given", body = [VectorListEntry]),
+        (status = 400, description = "Invalid or expired cursor", body = ErrorResponse),
+        (status = 304, description = "Unchanged since the ETag in If-None-Match")
+    ),
+    security(("api_key" = []))
+)]
+async fn list_vectors(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Query(params): Query<PaginationParams>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let catalog = state.db.catalog_version();
+    let collection = state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    let etag = collection_etag(&state, catalog, &collection).await?;
+    if etag::matches(&headers, &etag) {
+        return Ok(etag::not_modified(&etag));
+    }
+
+    let offset = params.offset.unwrap_or(0);
+    let limit = params.limit.unwrap_or(10).min(100);
+    let cursor = match params.cursor.as_deref() {
+        None => None,
+        Some("") => Some(None),
+        Some(token) => Some(Some(decode_cursor(token).ok_or_else(|| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: "Invalid cursor".to_string(),
+                }),
+            )
+        })?)),
+    };
+
+    let Some(cursor) = cursor else {
+        let result = spawn_blocking(move || collection.list(offset, limit))
+            .await
+            .map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: e.to_string(),
+                    }),
+                )
+            })?;
+        let entries: Vec<VectorListEntry> = result
+            .into_iter()
+            .map(|(id, metadata)| VectorListEntry {
+                id: id.to_string(),
+                metadata,
+            })
+            .collect();
+        return Ok(etag::with_tag(Json(entries), &etag));
+    };
+
+    let page = spawn_blocking(move || collection.list_page(cursor, limit))
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+        })?
+        .map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+        })?;
+    let page = VectorListPage {
+        vectors: page
+            .records
+            .into_iter()
+            .map(|(id, metadata)| VectorListEntry {
+                id: id.to_string(),
+                metadata,
+            })
+            .collect(),
+        next_cursor: page.next.as_ref().map(encode_cursor),
+    };
+    Ok(etag::with_tag(Json(page), &etag))
+}
+
+#[utoipa::path(
+    post,
+    path = "/collections/{name}/scroll",
+    params(
+        ("name" = String, Path, description = "Collection name")
+    ),
+    request_body = ScrollRequest,
+    responses(
+        (status = 200, description = "A page of full records and the cursor of the next", body = ScrollResponse),
+        (status = 400, description = "Invalid filter, limit or cursor, or the cursor expired", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn scroll_vectors(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path(name): Path<String>,
+    Json(payload): Json<ScrollRequest>,
+) -> Result<Json<ScrollResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let limits = state.limits.effective(caller.key_name.as_deref());
+    let limit = payload.limit.unwrap_or(DEFAULT_SCROLL_LIMIT);
+    check_limit("limit", limit, limits.max_batch_size)?;
+    let bad_request = |error: String| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error }));
+    let cursor = match payload.cursor.as_deref() {
+        None | Some("") => None,
+        Some(token) => {
+            Some(decode_cursor(token).ok_or_else(|| bad_request("Invalid cursor".to_string()))?)
+        }
+    };
+    let filter = payload.filter;
+    if let Some(filter) = &filter {
+        filter.validate().map_err(|e| bad_request(e.to_string()))?;
+    }
+
+    let collection = state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    let page = spawn_blocking(move || collection.scroll(cursor, limit, filter.as_ref()))
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+        })?
+        .map_err(|e| bad_request(e.to_string()))?;
+    Ok(Json(ScrollResponse {
+        records: page
+            .records
+            .into_iter()
+            .map(|(id, vector, metadata)| VectorResponse {
+                id: id.to_string(),
+                vector,
+                metadata,
+                version: None,
+                vectors: HashMap::new(),
+            })
+            .collect(),
+        next_cursor: page.next.as_ref().map(encode_cursor),
+    }))
+}
+
+#[derive(Deserialize, IntoParams)]
+struct CountParams {
+    /// Filter as JSON, e.g. `{"Exact":["tenant","acme"]}`; counts every
+    /// vector when absent
+    filter: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct CountRequest {
+    /// Count only vectors whose metadata matches
+    filter: Option<Filter>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct CountResponse {
+    count: usize,
+}
+
+#[utoipa::path(
+    get,
+    path = "/collections/{name}/count",
+    params(
+        ("name" = String, Path, description = "Collection name"),
+        CountParams
+    ),
+    responses(
+        (status = 200, description = "Number of matching vectors", body = CountResponse),
+        (status = 400, description = "Invalid filter", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn count_vectors(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Query(params): Query<CountParams>,
+) -> Result<Json<CountResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let filter = params
+        .filter
+        .map(|json| serde_json::from_str::<Filter>(&json))
+        .transpose()
+        .map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: format!("Invalid filter: {}", e),
+                }),
+            )
+        })?;
+    count_matching(&state, &name, filter).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/collections/{name}/count",
+    params(
+        ("name" = String, Path, description = "Collection name")
+    ),
+    request_body = CountRequest,
+    responses(
+        (status = 200, description = "Number of matching vectors", body = CountResponse),
+        (status = 400, description = "Invalid filter", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn count_vectors_post(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(payload): Json<CountRequest>,
+) -> Result<Json<CountResponse>, (StatusCode, Json<ErrorResponse>)> {
+    count_matching(&state, &name, payload.filter).await
+}
+
+async fn count_matching(
+    state: &AppState,
+    name: &str,
+    filter: Option<Filter>,
+) -> Result<Json<CountResponse>, (StatusCode, Json<ErrorResponse>)> {
+    if let Some(filter) = &filter {
+        filter.validate().map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+        })?;
+    }
+    let collection = state.db.get_collection(name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    let count = spawn_blocking(move || collection.count(filter.as_ref()))
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+        })?
+        // Only an expression in the filter can fail
+        .map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+        })?;
+    Ok(Json(CountResponse { count }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/collections/{name}/index/{field}/rebuild",
+    params(
+        ("name" = String, Path, description = "Collection name"),
+        ("field" = String, Path, description = "Metadata field, dotted for nested ones")
+    ),
+    responses(
+        (status = 202, description = "Rebuild started; follow it under /jobs", body = Job),
+        (status = 400, description = "Collection has no metadata index", body = ErrorResponse),
+        (status = 403, description = "Admin API key required", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse),
+        (status = 409, description = "The field's index is already being rebuilt", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn rebuild_field_index(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path((name, field)): Path<(String, String)>,
+) -> Result<(StatusCode, Json<Job>), (StatusCode, Json<ErrorResponse>)> {
+    require_admin(&caller)?;
+    let collection = state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    if !collection.has_metadata_index() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "Quantized collections have no metadata index".to_string(),
+            }),
+        ));
+    }
+    let job = state
+        .jobs
+        .start_index_rebuild(collection, &state.db.resolve_name(&name), &field)
+        .map_err(|error| (StatusCode::CONFLICT, Json(ErrorResponse { error })))?;
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/collections/{name}/index/export",
+    params(
+        ("name" = String, Path, description = "Collection name"),
+        IndexExportParams
+    ),
+    responses(
+        (status = 200, description = "HNSW adjacency as GraphML or a tab-separated edge list", body = String),
+        (status = 400, description = "Collection has no HNSW index", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn export_index(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Query(params): Query<IndexExportParams>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let collection = state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    let format = params.format.unwrap_or_default();
+    let start = Instant::now();
+    let result = spawn_blocking(move || {
+        collection
+            .export_graph(params.level, params.sample)
+            .map(|graph| match format {
+                GraphFormat::Graphml => graph.to_graphml(),
+                GraphFormat::Edgelist => graph.to_edgelist(),
+            })
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    let total_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+    let body = result.map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    log_perf("index_export", total_ms, total_ms, None, None);
+
+    let content_type = match format {
+        GraphFormat::Graphml => "application/graphml+xml",
+        GraphFormat::Edgelist => "text/plain; charset=utf-8",
+    };
+    Ok(([(header::CONTENT_TYPE, content_type)], body))
+}
+
+#[utoipa::path(
+    get,
+    path = "/collections/{name}/summary",
+    params(
+        ("name" = String, Path, description = "Collection name"),
+        SummaryParams
+    ),
+    responses(
+        (status = 200, description = "Cluster centroids, sizes and the members nearest each centroid, largest cluster first", body = SummaryResponse),
+        (status = 400, description = "Invalid cluster count", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn collection_summary(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Query(params): Query<SummaryParams>,
+) -> Result<Json<SummaryResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let collection = state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    let k = params.k.unwrap_or(DEFAULT_SUMMARY_CLUSTERS);
+    let start = Instant::now();
+    let summary = spawn_blocking(move || collection.summary(k))
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+        })?
+        .map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+        })?;
+    let total_ms = start.elapsed().as_secs_f64() * 1000.0;
+    log_perf(
+        "collection_summary",
+        total_ms,
+        total_ms,
+        None,
+        Some(summary.clusters.len()),
+    );
+    Ok(Json(SummaryResponse {
+        k,
+        summary: (*summary).clone(),
+    }))
+}
+
+/// Path of `file` in the snapshot directory, defaulting to `<name>.<extension>`
+///
+/// Only admins may use snapshot and Parquet files, and only plain file names
+/// are accepted, so no other part of the server's filesystem can be read or
+/// written.
+fn snapshot_path(
+    state: &AppState,
+    caller: &Caller,
+    name: &str,
+    file: Option<String>,
+    extension: &str,
+) -> Result<(String, std::path::PathBuf), (StatusCode, Json<ErrorResponse>)> {
+    require_admin(caller)?;
+    let file = file.unwrap_or_else(|| format!("{}.{}", name, extension));
+    let plain = std::path::Path::new(&file)
+        .file_name()
+        .is_some_and(|f| f == file.as_str());
+    if !plain || file.starts_with('.') {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: format!("Invalid file name: {}", file),
+            }),
+        ));
+    }
+    let path = std::path::Path::new(&state.config.snapshot_dir).join(&file);
+    Ok((file, path))
+}
+
+#[utoipa::path(
+    post,
+    path = "/collections/{name}/snapshot",
+    params(("name" = String, Path, description = "Collection name")),
+    request_body = SnapshotRequest,
+    responses(
+        (status = 200, description = "Snapshot written to SNAPSHOT_DIR", body = SnapshotResponse),
+        (status = 403, description = "Admin API key required", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn snapshot_collection(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path(name): Path<String>,
+    payload: Option<Json<SnapshotRequest>>,
+) -> Result<Json<SnapshotResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let Json(payload) = payload.unwrap_or_default();
+    let (file, path) = snapshot_path(&state, &caller, &name, payload.file, "snap")?;
+    let collection = state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    let start = Instant::now();
+    let result = spawn_blocking(move || {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        collection.snapshot(&path)
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    let total_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+    let vectors = result.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    log_perf("snapshot", total_ms, total_ms, None, Some(vectors));
+    info!(
+        "Snapshot of {} written to {} ({} vectors)",
+        name, file, vectors
+    );
+
+    Ok(Json(SnapshotResponse { file, vectors }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/collections/{name}/restore",
+    params(("name" = String, Path, description = "Name of the collection to create")),
+    request_body = SnapshotRequest,
+    responses(
+        (status = 200, description = "Collection created from the snapshot", body = SnapshotResponse),
+        (status = 400, description = "Collection exists or the file is not a valid snapshot", body = ErrorResponse),
+        (status = 403, description = "Admin API key required", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn restore_collection(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path(name): Path<String>,
+    payload: Option<Json<SnapshotRequest>>,
+) -> Result<Json<SnapshotResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let Json(payload) = payload.unwrap_or_default();
+    let (file, path) = snapshot_path(&state, &caller, &name, payload.file, "snap")?;
+
+    let start = Instant::now();
+    let db = state.db.clone();
+    let collection = name.clone();
+    let result = spawn_blocking(move || db.restore(&collection, &path))
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+        })?;
+    let total_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+    let vectors = result.map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    log_perf("restore", total_ms, total_ms, None, Some(vectors));
+
+    Ok(Json(SnapshotResponse { file, vectors }))
+}
+
+/// The backup store, for admins only
+fn backup_store(
+    state: &AppState,
+    caller: &Caller,
+) -> Result<Arc<BackupStore>, (StatusCode, Json<ErrorResponse>)> {
+    require_admin(caller)?;
+    state.backups.clone().ok_or_else(|| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "Backups are not configured; set BACKUP_URL".to_string(),
+            }),
+        )
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/backups",
+    request_body = BackupRequest,
+    responses(
+        (status = 200, description = "Backup uploaded to BACKUP_URL", body = BackupManifest),
+        (status = 400, description = "Backups are not configured", body = ErrorResponse),
+        (status = 403, description = "Admin API key required", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse),
+        (status = 500, description = "The backup could not be written", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn create_backup(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    payload: Option<Json<BackupRequest>>,
+) -> Result<Json<BackupManifest>, (StatusCode, Json<ErrorResponse>)> {
+    let backups = backup_store(&state, &caller)?;
+    let Json(payload) = payload.unwrap_or_default();
+    let names = match payload.collections {
+        Some(names) => {
+            let mut resolved = Vec::with_capacity(names.len());
+            for name in names {
+                state.db.get_collection(&name).map_err(|e| {
+                    (
+                        StatusCode::NOT_FOUND,
+                        Json(ErrorResponse {
+                            error: e.to_string(),
+                        }),
+                    )
+                })?;
+                resolved.push(state.db.resolve_name(&name));
+            }
+            Some(resolved)
+        }
+        None => None,
+    };
+
+    let start = Instant::now();
+    let manifest = backups
+        .create(&state.db, names, false)
+        .await
+        .map_err(|error| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse { error }),
+            )
+        })?;
+    let total_ms = start.elapsed().as_secs_f64() * 1000.0;
+    let vectors = manifest.collections.iter().map(|c| c.vectors).sum();
+    log_perf("backup", total_ms, total_ms, None, Some(vectors));
+    Ok(Json(manifest))
+}
+
+#[utoipa::path(
+    get,
+    path = "/backups",
+    responses(
+        (status = 200, description = "Complete backups in BACKUP_URL, oldest first", body = [BackupManifest]),
+        (status = 400, description = "Backups are not configured", body = ErrorResponse),
+        (status = 403, description = "Admin API key required", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn list_backups(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+) -> Result<Json<Vec<BackupManifest>>, (StatusCode, Json<ErrorResponse>)> {
+    let backups = backup_store(&state, &caller)?;
+    let list = backups.list().await.map_err(|error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse { error }),
+        )
+    })?;
+    Ok(Json(list))
+}
+
+#[utoipa::path(
+    post,
+    path = "/backups/{id}/restore",
+    params(("id" = String, Path, description = "Backup ID")),
+    request_body = BackupRequest,
+    responses(
+        (status = 200, description = "Collections created from the backup", body = RestoreBackupResponse),
+        (status = 400, description = "Backups are not configured, or a collection already exists or is not in the backup", body = ErrorResponse),
+        (status = 403, description = "Admin API key required", body = ErrorResponse),
+        (status = 404, description = "Backup not found", body = ErrorResponse),
+        (status = 500, description = "The backup could not be read", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn restore_backup(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<String>,
+    payload: Option<Json<BackupRequest>>,
+) -> Result<Json<RestoreBackupResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let backups = backup_store(&state, &caller)?;
+    let Json(payload) = payload.unwrap_or_default();
+    let manifest = backups
+        .get(&id)
+        .await
+        .map_err(|error| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse { error }),
+            )
+        })?
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse {
+                    error: format!("Backup not found: {}", id),
+                }),
+            )
+        })?;
+
+    let bad_request = |error: String| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error }));
+    if let Some(names) = &payload.collections {
+        if let Some(missing) = names
+            .iter()
+            .find(|name| !manifest.collections.iter().any(|c| &c.name == *name))
+        {
+            return Err(bad_request(format!(
+                "Backup {} has no collection {}",
+                id, missing
+            )));
+        }
+    }
+    let existing = state.db.list_collections();
+    if let Some(taken) = manifest.collections.iter().find(|c| {
+        payload
+            .collections
+            .as_ref()
+            .is_none_or(|names| names.contains(&c.name))
+            && existing.contains(&c.name)
+    }) {
+        return Err(bad_request(format!(
+            "Collection {} already exists; delete it before restoring",
+            taken.name
+        )));
+    }
+
+    let start = Instant::now();
+    let collections = backups
+        .restore(&state.db, &manifest, payload.collections)
+        .await
+        .map_err(|error| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse { error }),
+            )
+        })?;
+    let total_ms = start.elapsed().as_secs_f64() * 1000.0;
+    let vectors = collections.iter().map(|c| c.vectors).sum();
+    log_perf("restore_backup", total_ms, total_ms, None, Some(vectors));
+    Ok(Json(RestoreBackupResponse { id, collections }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/collections/{name}/parquet/export",
+    params(("name" = String, Path, description = "Collection name")),
+    request_body = SnapshotRequest,
+    responses(
+        (status = 200, description = "Records written to a Parquet file in SNAPSHOT_DIR", body = SnapshotResponse),
+        (status = 403, description = "Admin API key required", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn export_parquet(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path(name): Path<String>,
+    payload: Option<Json<SnapshotRequest>>,
+) -> Result<Json<SnapshotResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let Json(payload) = payload.unwrap_or_default();
+    let (file, path) = snapshot_path(&state, &caller, &name, payload.file, "parquet")?;
+    let collection = state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    let start = Instant::now();
+    let result = spawn_blocking(move || {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        collection.export_parquet(&path)
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    let total_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+    let vectors = result.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    log_perf("parquet_export", total_ms, total_ms, None, Some(vectors));
+    info!(
+        "Parquet export of {} written to {} ({} vectors)",
+        name, file, vectors
+    );
+
+    Ok(Json(SnapshotResponse { file, vectors }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/collections/{name}/parquet/import",
+    params(("name" = String, Path, description = "Collection name")),
+    request_body = SnapshotRequest,
+    responses(
+        (status = 200, description = "Records of the Parquet file upserted", body = SnapshotResponse),
+        (status = 400, description = "The file is missing, not Parquet, or its records don't fit the collection", body = ErrorResponse),
+        (status = 403, description = "Admin API key required", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn import_parquet(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path(name): Path<String>,
+    payload: Option<Json<SnapshotRequest>>,
+) -> Result<Json<SnapshotResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let Json(payload) = payload.unwrap_or_default();
+    let (file, path) = snapshot_path(&state, &caller, &name, payload.file, "parquet")?;
+    let collection = state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    let start = Instant::now();
+    let result = spawn_blocking(move || collection.import_parquet(&path))
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+        })?;
+    let total_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+    let vectors = result.map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    log_perf("parquet_import", total_ms, total_ms, None, Some(vectors));
+    info!(
+        "Parquet import into {} from {} ({} vectors)",
+        name, file, vectors
+    );
+
+    Ok(Json(SnapshotResponse { file, vectors }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/collections/{name}/tune",
+    params(("name" = String, Path, description = "Collection name")),
+    request_body = TuneRequest,
+    responses(
+        (status = 200, description = "Trials and the recommended parameters", body = TuneResponse),
+        (status = 400, description = "Invalid sweep or too few vectors", body = ErrorResponse),
+        (status = 403, description = "Admin API key required", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn tune_collection(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path(name): Path<String>,
+    payload: Option<Json<TuneRequest>>,
+) -> Result<Json<TuneResponse>, (StatusCode, Json<ErrorResponse>)> {
+    require_admin(&caller)?;
+    let Json(payload) = payload.unwrap_or_default();
+    let defaults = TuneOptions::default();
+    let options = TuneOptions {
+        sample_size: payload.sample_size.unwrap_or(defaults.sample_size),
+        queries: payload.queries.unwrap_or(defaults.queries),
+        k: payload.k.unwrap_or(defaults.k),
+        target_recall: payload.target_recall.unwrap_or(defaults.target_recall),
+        max_latency_us: payload.max_latency_us,
+        m: payload.m.unwrap_or(defaults.m),
+        ef_construction: payload.ef_construction.unwrap_or(defaults.ef_construction),
+        ef_search: payload.ef_search.unwrap_or(defaults.ef_search),
+    };
+
+    let limits = state.limits.effective(caller.key_name.as_deref());
+    check_limit("sample_size", options.sample_size, MAX_TUNE_SAMPLE)?;
+    check_limit("queries", options.queries, MAX_TUNE_SAMPLE)?;
+    check_limit("k", options.k, limits.max_k)?;
+    check_limit(
+        "m x ef_construction",
+        options.m.len() * options.ef_construction.len(),
+        MAX_TUNE_BUILDS,
+    )?;
+    for &ef in &options.ef_construction {
+        check_limit("ef_construction", ef, limits.max_k)?;
+    }
+    for &ef in &options.ef_search {
+        check_limit("ef_search", ef, limits.max_k)?;
+    }
+
+    let collection = state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    let start = Instant::now();
+    let result = spawn_blocking(move || collection.tune(&options))
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+        })?;
+    let total_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+    let report = result.map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    log_perf("tune", total_ms, total_ms, None, Some(report.sample_size));
+
+    if payload.apply {
+        state
+            .db
+            .set_ef_search(&name, report.recommended.ef_search)
+            .map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: e.to_string(),
+                    }),
+                )
+            })?;
+        info!(
+            "Collection {} now searches with ef_search {}",
+            name, report.recommended.ef_search
+        );
+    }
+
+    Ok(Json(TuneResponse {
+        report,
+        applied: payload.apply,
+    }))
+}
+
+/// Fetch the record referenced by `field` in each result's metadata
+///
+/// Each distinct ID is fetched once; missing fields or records yield `None`.
+fn lookup_related(
+    lookup: Option<&(String, Collection)>,
+    results: &[SearchHit],
+) -> Vec<Option<LookupResult>> {
+    let Some((field, target)) = lookup else {
+        return results.iter().map(|_| None).collect();
+    };
+
+    let mut fetched: HashMap<String, Option<Option<Value>>> = HashMap::new();
+    results
+        .iter()
+        .map(|(_, _, metadata)| {
+            let id = match get_value_by_path(metadata.as_ref()?, field)? {
+                Value::String(s) => s.clone(),
+                Value::Number(n) => n.to_string(),
+                _ => return None,
+            };
+            let metadata = fetched
+                .entry(id.clone())
+                .or_insert_with(|| target.get(&id).ok().flatten().map(|(_, metadata)| metadata))
+                .clone()?;
+            Some(LookupResult { id, metadata })
+        })
+        .collect()
+}
+
+/// Reject malformed sparse vectors before anything is written
+fn validate_sparse(items: &[InsertRequest]) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    for item in items {
+        if let Some(sparse) = &item.sparse {
+            sparse.validate().map_err(|e| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    Json(ErrorResponse {
+                        error: format!("Invalid sparse vector of {}: {}", item.id, e),
+                    }),
+                )
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// Reject named vectors the collection has no space for, or of the wrong
+/// size, before anything is written
+fn validate_named(
+    collection: &Collection,
+    items: &[InsertRequest],
+) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    if items.iter().all(|item| item.vectors.is_none()) {
+        return Ok(());
+    }
+    let spaces = collection.config().named_vectors;
+    for item in items {
+        for (name, vector) in item.vectors.iter().flatten() {
+            let error = match spaces.iter().find(|space| &space.name == name) {
+                None => format!("Unknown named vector space '{}' in {}", name, item.id),
+                Some(space) if space.dimensions != vector.len() => format!(
+                    "Named vector '{}' of {} has {} dimensions, expected {}",
+                    name,
+                    item.id,
+                    vector.len(),
+                    space.dimensions
+                ),
+                Some(_) => continue,
+            };
+            return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse { error })));
+        }
+    }
+    Ok(())
+}
+
+/// Limits of `caller` for writes to `collection`, with `max_vectors` lowered
+/// to what its tenant's quota leaves after the tenant's other collections
+pub(crate) fn write_limits(state: &AppState, caller: &Caller, collection: &str) -> Limits {
+    let mut limits = state.limits.effective(caller.key_name.as_deref());
+    let quota = caller
+        .namespace
+        .as_deref()
+        .and_then(|namespace| Some((namespace, state.tenants.quotas(namespace)?.max_vectors?)));
+    if let Some((namespace, max_vectors)) = quota {
+        let name = state.db.resolve_name(collection);
+        let others = tenants::usage(&state.db, namespace, Some(&name)).vectors;
+        let left = max_vectors.saturating_sub(others);
+        limits.max_vectors = Some(limits.max_vectors.map_or(left, |max| max.min(left)));
+    }
+    limits
+}
+
+/// Fail if writing `ids` would grow `collection` past `max_vectors`
+///
+/// IDs already in the collection don't count, as writing them overwrites.
+pub(crate) fn check_vector_quota<'a>(
+    collection: &Collection,
+    ids: impl IntoIterator<Item = &'a String>,
+    max_vectors: Option<usize>,
+) -> surgedb_core::Result<()> {
+    let Some(max_vectors) = max_vectors else {
+        return Ok(());
+    };
+    let ids: Vec<String> = ids
+        .into_iter()
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .cloned()
+        .collect();
+    let existing = collection.get_metadata_batch(&ids).len();
+    let after = collection.len() + ids.len().saturating_sub(existing);
+    if after > max_vectors {
+        return Err(surgedb_core::Error::CapacityExceeded {
+            message: format!(
+                "{} vectors would exceed the max_vectors quota of {}",
+                after, max_vectors
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// Attach named vectors to records that were just written
+pub(crate) fn set_named_vectors(
+    collection: &Collection,
+    named: impl IntoIterator<Item = (String, HashMap<String, Vec<f32>>)>,
+) -> surgedb_core::Result<()> {
+    for (id, vectors) in named {
+        for (name, vector) in vectors {
+            collection.set_named_vector(&id, &name, &vector)?;
+        }
+    }
+    Ok(())
+}
+
+/// Attach sparse vectors to records that were just written
+pub(crate) fn set_sparse_vectors(
+    collection: &Collection,
+    sparse: impl IntoIterator<Item = (String, SparseVector)>,
+) -> surgedb_core::Result<()> {
+    for (id, vector) in sparse {
+        collection.set_sparse(&id, vector)?;
+    }
+    Ok(())
+}
+
+/// Record search usage for metering
+fn record_usage(collection: &str, usage: SearchUsage, cpu_time: Duration) -> SearchUsageResponse {
+    let cpu_time_us = cpu_time.as_micros() as u64;
+    debug!(
+        target: "usage",
+        collection,
+        vectors_scanned = usage.vectors_scanned,
+        graph_hops = usage.graph_hops,
+        rescored_candidates = usage.rescored_candidates,
+        cpu_time_us,
+    );
+    SearchUsageResponse {
+        vectors_scanned: usage.vectors_scanned,
+        graph_hops: usage.graph_hops,
+        rescored_candidates: usage.rescored_candidates,
+        cpu_time_us,
+    }
+}
+
+/// Record search usage for metering and shape the response body
+fn search_response(
+    collection: &str,
+    results: Vec<SearchResult>,
+    usage: SearchUsage,
+    cpu_time: Duration,
+    with_usage: bool,
+) -> SearchResponse {
+    let usage = record_usage(collection, usage, cpu_time);
+    if !with_usage {
+        return SearchResponse::Results(results);
+    }
+    SearchResponse::WithUsage(SearchWithUsageResponse { results, usage })
+}
+
+#[utoipa::path(
+    post,
+    path = "/collections/{name}/search",
+    params(
+        ("name" = String, Path, description = "Collection name")
+    ),
+    request_body = SearchRequest,
+    responses(
+        (status = 200, description = "List of nearest neighbors, with a usage block if requested", body = SearchResponse),
+        (status = 400, description = "Invalid request", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn search_vector(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path(name): Path<String>,
+    Json(payload): Json<SearchRequest>,
+) -> Result<Json<SearchResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let handler_start = Instant::now();
+    let limits = state.limits.effective(caller.key_name.as_deref());
+    check_limit("k", payload.k, limits.max_k)?;
+    let mut params = search_params(
+        payload.k,
+        payload.ef_search,
+        payload.rescore,
+        payload.oversampling,
+        &limits,
+    )?;
+    if payload.score_threshold.is_some_and(|t| !t.is_finite()) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "score_threshold must be a finite number".to_string(),
+            }),
+        ));
+    }
+    let include_metadata = payload.include_metadata.unwrap_or(true);
+    let with_usage = payload.with_usage.unwrap_or(false);
+    let with_vector = payload.with_vector.unwrap_or(false);
+    let vector = payload.vector;
+    let k = payload.k;
+    let filter = payload.filter;
+    let using = payload.using;
+    let bounds = DistanceBounds {
+        max_distance_to: payload.max_distance_to,
+        min_distance_to: payload.min_distance_to,
+    };
+    if using.is_some() && !bounds.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "max_distance_to and min_distance_to can't be combined with using"
+                    .to_string(),
+            }),
+        ));
+    }
+    if let Some(filter) = &filter {
+        filter.validate().map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+        })?;
+    }
+
+    let collection = state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    let metric = search_metric(&collection, using.as_deref());
+    if let Some(threshold) = payload.score_threshold {
+        params.max_distance = metric.max_distance_for_score(threshold);
+    }
+
+    if let Some(min_seq) = payload.min_seq {
+        let timeout = Duration::from_millis(state.config.min_seq_timeout_ms);
+        wait_for_seq(&collection, min_seq, timeout).await?;
+    }
+
+    let lookup = match payload.lookup {
+        Some(spec) => {
+            let target = spec.collection.as_deref().unwrap_or(&name);
+            // Tokens may only read the collections they cover, and unauthenticated
+            // public searches only other public collections
+            if let Some(claims) = &caller.token {
+                if !claims.covers(target, &state.db.resolve_name(target)) {
+                    return Err((
+                        StatusCode::FORBIDDEN,
+                        Json(ErrorResponse {
+                            error: format!("Lookup collection is not covered by token: {}", target),
+                        }),
+                    ));
+                }
+            }
+            // Tenant keys may only read their own namespace
+            check_tenant_collection(&state, &caller, target, false)?;
+            let is_public_caller =
+                caller.key_name.is_none() && !caller.admin && caller.token.is_none();
+            let is_public = state
+                .config
+                .public_collections
+                .contains(state.config.name_case.canonicalize(target).as_ref());
+            if is_public_caller && !is_public {
+                return Err((
+                    StatusCode::FORBIDDEN,
+                    Json(ErrorResponse {
+                        error: format!("Lookup collection is not public: {}", target),
+                    }),
+                ));
+            }
+            let target = state.db.get_collection(target).map_err(|e| {
+                (
+                    StatusCode::NOT_FOUND,
+                    Json(ErrorResponse {
+                        error: e.to_string(),
+                    }),
+                )
+            })?;
+            Some((spec.field, target))
+        }
+        None => None,
+    };
+
+    // Named spaces are only searched with metadata, which is dropped below if unwanted
+    if include_metadata || lookup.is_some() || using.is_some() {
+        let work_start = Instant::now();
+        let result = spawn_blocking(move || {
+            let cpu_start = Instant::now();
+            match &using {
+                Some(space) => collection.search_named(space, &vector, k, filter.as_ref(), params),
+                None if !bounds.is_empty() => {
+                    collection.search_bounded(&vector, k, filter.as_ref(), params, &bounds)
+                }
+                None => collection.search_with_params(&vector, k, filter.as_ref(), params),
+            }
+            .map(|(results, usage)| {
+                let related = lookup_related(lookup.as_ref(), &results);
+                let ids = results.iter().map(|(id, _, _)| id);
+                let vectors = result_vectors(&collection, using.as_deref(), ids, with_vector);
+                (results, related, vectors, usage, cpu_start.elapsed())
+            })
+        })
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+        })?;
+
+        match result {
+            Ok((results, related, vectors, usage, cpu_time)) => {
+                let map_start = Instant::now();
+                let response: Vec<SearchResult> = results
+                    .into_iter()
+                    .zip(related)
+                    .zip(vectors)
+                    .map(
+                        |(((id, distance, metadata), lookup), vector)| SearchResult {
+                            id: id.as_str().to_string(),
+                            distance,
+                            score: metric.score(distance),
+                            vector,
+                            metadata: metadata.filter(|_| include_metadata),
+                            lookup,
+                        },
+                    )
+                    .collect();
+                let work_ms = work_start.elapsed().as_secs_f64() * 1000.0;
+                let map_ms = map_start.elapsed().as_secs_f64() * 1000.0;
+                let total_ms = handler_start.elapsed().as_secs_f64() * 1000.0;
+                log_perf("search_vector", total_ms, work_ms, Some(map_ms), Some(response.len()));
+                Ok(Json(search_response(
+                    &name, response, usage, cpu_time, with_usage,
+                )))
+            }
+            Err(e) => Err((
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )),
+        }
+    } else {
+        let work_start = Instant::now();
+        let result = spawn_blocking(move || {
+            let cpu_start = Instant::now();
+            collection
+                .search_ids_with_params(&vector, k, filter.as_ref(), params)
+                .map(|(results, usage)| {
+                    let ids = results.iter().map(|(id, _)| id);
+                    let vectors = result_vectors(&collection, None, ids, with_vector);
+                    (results, vectors, usage, cpu_start.elapsed())
+                })
+        })
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+        })?;
+
+        match result {
+            Ok((results, vectors, usage, cpu_time)) => {
+                let map_start = Instant::now();
+                let response: Vec<SearchResult> = results
+                    .into_iter()
+                    .zip(vectors)
+                    .map(|((id, distance), vector)| SearchResult {
+                        id: id.as_str().to_string(),
+                        distance,
+                        score: metric.score(distance),
+                        vector,
+                        metadata: None,
+                        lookup: None,
+                    })
+                    .collect();
+                let work_ms = work_start.elapsed().as_secs_f64() * 1000.0;
+                let map_ms = map_start.elapsed().as_secs_f64() * 1000.0;
+                let total_ms = handler_start.elapsed().as_secs_f64() * 1000.0;
+                log_perf("search_vector", total_ms, work_ms, Some(map_ms), Some(response.len()));
+                Ok(Json(search_response(
+                    &name, response, usage, cpu_time, with_usage,
+                )))
+            }
+            Err(e) => Err((
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )),
+        }
+    }
+}
+
+/// Results serialized and sent per chunk of a streamed search
+const SEARCH_STREAM_CHUNK: usize = 256;
+/// Chunks a streamed search prepares before waiting for the client to read
+const SEARCH_STREAM_BUFFER: usize = 4;
+
+/// Append `value` to a streamed response, as an NDJSON line or an SSE event
+fn stream_event(buf: &mut Vec<u8>, sse: bool, value: &impl Serialize) {
+    if sse {
+        buf.extend_from_slice(b"data: ");
+    }
+    let _ = serde_json::to_writer(&mut *buf, value);
+    buf.extend_from_slice(if sse { b"\n\n" } else { b"\n" });
+}
+
+#[utoipa::path(
+    post,
+    path = "/collections/{name}/search/stream",
+    params(
+        ("name" = String, Path, description = "Collection name")
+    ),
+    request_body = StreamSearchRequest,
+    responses(
+        (status = 200, description = "One SearchResult per line (NDJSON), or per `data:` event with `Accept: text/event-stream`, followed by a `{\"done\": true, \"results\": n}` trailer", body = SearchResult),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn search_stream(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+    Json(payload): Json<StreamSearchRequest>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let limits = state.limits.effective(caller.key_name.as_deref());
+    check_limit("k", payload.k, limits.max_k)?;
+    let mut params = search_params(payload.k, payload.ef_search, None, None, &limits)?;
+    if payload.score_threshold.is_some_and(|t| !t.is_finite()) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "score_threshold must be a finite number".to_string(),
+            }),
+        ));
+    }
+    if let Some(filter) = &payload.filter {
+        filter.validate().map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+        })?;
+    }
+    let include_metadata = payload.include_metadata.unwrap_or(true);
+    let with_vector = payload.with_vector.unwrap_or(false);
+    let sse = headers
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("text/event-stream"));
+
+    let collection = state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    let metric = search_metric(&collection, None);
+    if let Some(threshold) = payload.score_threshold {
+        params.max_distance = metric.max_distance_for_score(threshold);
+    }
+    if let Some(min_seq) = payload.min_seq {
+        let timeout = Duration::from_millis(state.config.min_seq_timeout_ms);
+        wait_for_seq(&collection, min_seq, timeout).await?;
+    }
+
+    // Rank first so a failing search is an error status rather than a cut
+    // stream; only IDs and distances are held for the whole result set
+    let (vector, k, filter) = (payload.vector, payload.k, payload.filter);
+    let search = collection.clone();
+    let hits = spawn_blocking(move || {
+        let cpu_start = Instant::now();
+        search
+            .search_ids_with_params(&vector, k, filter.as_ref(), params)
+            .map(|(hits, usage)| (hits, usage, cpu_start.elapsed()))
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    let (hits, usage, cpu_time) = hits.map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    record_usage(&name, usage, cpu_time);
+
+    // Metadata and vectors are read one chunk at a time; the bounded channel
+    // holds the producer back until the client has read the earlier chunks
+    let (tx, rx) = tokio::sync::mpsc::channel::<Vec<u8>>(SEARCH_STREAM_BUFFER);
+    spawn_blocking(move || {
+        for chunk in hits.chunks(SEARCH_STREAM_CHUNK) {
+            let metadata = result_metadata(
+                &collection,
+                chunk.iter().map(|(id, _)| id),
+                include_metadata,
+            );
+            let vectors = result_vectors(
+                &collection,
+                None,
+                chunk.iter().map(|(id, _)| id),
+                with_vector,
+            );
+            let mut buf = Vec::new();
+            for (((id, distance), metadata), vector) in chunk.iter().zip(metadata).zip(vectors) {
+                let result = SearchResult {
+                    id: id.to_string(),
+                    distance: *distance,
+                    score: metric.score(*distance),
+                    vector,
+                    metadata,
+                    lookup: None,
+                };
+                stream_event(&mut buf, sse, &result);
+            }
+            if tx.blocking_send(buf).is_err() {
+                // The client went away
+                return;
+            }
+        }
+        let mut buf = Vec::new();
+        if sse {
+            buf.extend_from_slice(b"event: done\n");
+        }
+        stream_event(
+            &mut buf,
+            sse,
+            &serde_json::json!({ "done": true, "results": hits.len() }),
+        );
+        let _ = tx.blocking_send(buf);
+    });
+
+    let body = futures_util::stream::unfold(rx, |mut rx| async move {
+        let chunk = rx.recv().await?;
+        Some((Ok::<_, std::convert::Infallible>(chunk), rx))
+    });
+    let content_type = if sse {
+        "text/event-stream"
+    } else {
+        "application/x-ndjson"
+    };
+    Ok((
+        [(header::CONTENT_TYPE, content_type)],
+        axum::body::Body::from_stream(body),
+    )
+        .into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/collections/{name}/recommend",
+    params(
+        ("name" = String, Path, description = "Collection name")
+    ),
+    request_body = RecommendRequest,
+    responses(
+        (status = 200, description = "Recommended records, never the examples, with a usage block if requested", body = SearchResponse),
+        (status = 400, description = "Invalid request or unknown example ID", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn recommend_vectors(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path(name): Path<String>,
+    Json(payload): Json<RecommendRequest>,
+) -> Result<Json<SearchResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let bad_request = |error: String| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error }));
+    let limits = state.limits.effective(caller.key_name.as_deref());
+    check_limit("k", payload.k, limits.max_k)?;
+    let examples = Recommend {
+        positive: payload.positive,
+        negative: payload.negative,
+        strategy: payload.strategy,
+    };
+    check_limit(
+        "examples",
+        examples.examples().count(),
+        limits.max_batch_size,
+    )?;
+    examples
+        .validate()
+        .map_err(|e| bad_request(e.to_string()))?;
+    let mut params = search_params(payload.k, payload.ef_search, None, None, &limits)?;
+    if payload.score_threshold.is_some_and(|t| !t.is_finite()) {
+        return Err(bad_request(
+            "score_threshold must be a finite number".to_string(),
+        ));
+    }
+    if let Some(filter) = &payload.filter {
+        filter.validate().map_err(|e| bad_request(e.to_string()))?;
+    }
+    let include_metadata = payload.include_metadata.unwrap_or(true);
+    let with_usage = payload.with_usage.unwrap_or(false);
+    let k = payload.k;
+    let filter = payload.filter;
+
+    let collection = state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    let metric = collection.config().distance_metric;
+    if let Some(threshold) = payload.score_threshold {
+        params.max_distance = metric.max_distance_for_score(threshold);
+    }
+
+    let result = spawn_blocking(move || {
+        let cpu_start = Instant::now();
+        collection
+            .recommend(&examples, k, filter.as_ref(), params)
+            .map(|(hits, usage)| (hits, usage, cpu_start.elapsed()))
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    let (hits, usage, cpu_time) = result.map_err(|e| bad_request(e.to_string()))?;
+
+    let results = hits
+        .into_iter()
+        .map(|(id, distance, metadata)| SearchResult {
+            id: id.to_string(),
+            distance,
+            score: metric.score(distance),
+            vector: None,
+            metadata: metadata.filter(|_| include_metadata),
+            lookup: None,
+        })
+        .collect();
+    Ok(Json(search_response(
+        &name, results, usage, cpu_time, with_usage,
+    )))
+}
+
+#[utoipa::path(
+    post,
+    path = "/collections/{name}/hard-negatives",
+    params(
+        ("name" = String, Path, description = "Collection name")
+    ),
+    request_body = HardNegativesRequest,
+    responses(
+        (status = 200, description = "Hard negatives per query, in query order", body = HardNegativesResponse),
+        (status = 400, description = "Invalid request or unknown positive ID", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn mine_hard_negatives(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path(name): Path<String>,
+    Json(payload): Json<HardNegativesRequest>,
+) -> Result<Json<HardNegativesResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let handler_start = Instant::now();
+    let bad_request = |error: String| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error }));
+    let limits = state.limits.effective(caller.key_name.as_deref());
+    check_limit("batch size", payload.queries.len(), limits.max_batch_size)?;
+    check_limit("k", payload.k, limits.max_k)?;
+    let params = search_params(payload.k, payload.ef_search, None, None, &limits)?;
+    let options = HardNegatives {
+        k: payload.k,
+        skip: payload.skip,
+        margin: payload.margin,
+    };
+    options.validate().map_err(|e| bad_request(e.to_string()))?;
+    if let Some(filter) = &payload.filter {
+        filter.validate().map_err(|e| bad_request(e.to_string()))?;
+    }
+    let include_metadata = payload.include_metadata.unwrap_or(true);
+    let with_usage = payload.with_usage.unwrap_or(false);
+    let queries = payload.queries;
+    let filter = payload.filter;
+
+    let collection = state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    if let Some(min_seq) = payload.min_seq {
+        let timeout = Duration::from_millis(state.config.min_seq_timeout_ms);
+        wait_for_seq(&collection, min_seq, timeout).await?;
+    }
+
+    let metric = collection.config().distance_metric;
+    let work_start = Instant::now();
+    let result = spawn_blocking(move || {
+        let cpu_start = Instant::now();
+        let (mined, usage) =
+            collection.hard_negatives(&queries, &options, filter.as_ref(), params)?;
+        let results: Vec<MinedNegativesResult> = mined
+            .into_iter()
+            .map(|mined| {
+                let metadata = result_metadata(
+                    &collection,
+                    mined.negatives.iter().map(|(id, _)| id),
+                    include_metadata,
+                );
+                MinedNegativesResult {
+                    positive_id: mined.positive_id,
+                    positive_score: metric.score(mined.positive_distance),
+                    negatives: mined
+                        .negatives
+                        .into_iter()
+                        .zip(metadata)
+                        .map(|((id, distance), metadata)| SearchResult {
+                            id: id.to_string(),
+                            distance,
+                            score: metric.score(distance),
+                            vector: None,
+                            metadata,
+                            lookup: None,
+                        })
+                        .collect(),
+                }
+            })
+            .collect();
+        Ok::<_, surgedb_core::Error>((results, usage, cpu_start.elapsed()))
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    let (results, usage, cpu_time) = result.map_err(|e| bad_request(e.to_string()))?;
+    let usage = record_usage(&name, usage, cpu_time);
+
+    let work_ms = work_start.elapsed().as_secs_f64() * 1000.0;
+    let total_ms = handler_start.elapsed().as_secs_f64() * 1000.0;
+    log_perf(
+        "hard_negatives",
+        total_ms,
+        work_ms,
+        None,
+        Some(results.len()),
+    );
+    Ok(Json(HardNegativesResponse {
+        results,
+        usage: with_usage.then_some(usage),
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/collections/{name}/search/groups",
+    params(
+        ("name" = String, Path, description = "Collection name")
+    ),
+    request_body = GroupedSearchRequest,
+    responses(
+        (status = 200, description = "Nearest hits per value of the `group_by` field", body = GroupedSearchResponse),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn search_grouped(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path(name): Path<String>,
+    Json(payload): Json<GroupedSearchRequest>,
+) -> Result<Json<GroupedSearchResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let bad_request = |error: String| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error }));
+    let limits = state.limits.effective(caller.key_name.as_deref());
+    let group_by = GroupBy {
+        field: payload.group_by,
+        groups: payload.groups,
+        group_size: payload.group_size,
+    };
+    group_by
+        .validate()
+        .map_err(|e| bad_request(e.to_string()))?;
+    let k = group_by.capacity();
+    check_limit("groups * group_size", k, limits.max_k)?;
+    let mut params = search_params(
+        k,
+        payload.ef_search,
+        payload.rescore,
+        payload.oversampling,
+        &limits,
+    )?;
+    if payload.score_threshold.is_some_and(|t| !t.is_finite()) {
+        return Err(bad_request(
+            "score_threshold must be a finite number".to_string(),
+        ));
+    }
+    if let Some(filter) = &payload.filter {
+        filter.validate().map_err(|e| bad_request(e.to_string()))?;
+    }
+    let include_metadata = payload.include_metadata.unwrap_or(true);
+    let with_usage = payload.with_usage.unwrap_or(false);
+    let with_vector = payload.with_vector.unwrap_or(false);
+    let vector = payload.vector;
+    let filter = payload.filter;
+
+    let collection = state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    let metric = collection.config().distance_metric;
+    if let Some(threshold) = payload.score_threshold {
+        params.max_distance = metric.max_distance_for_score(threshold);
+    }
+    if let Some(min_seq) = payload.min_seq {
+        let timeout = Duration::from_millis(state.config.min_seq_timeout_ms);
+        wait_for_seq(&collection, min_seq, timeout).await?;
+    }
+
+    let result = spawn_blocking(move || {
+        let cpu_start = Instant::now();
+        collection
+            .search_grouped(&vector, &group_by, filter.as_ref(), params)
+            .map(|(groups, usage)| {
+                let groups: Vec<_> = groups
+                    .into_iter()
+                    .map(|group| {
+                        let ids = group.hits.iter().map(|(id, _, _)| id);
+                        let vectors = result_vectors(&collection, None, ids, with_vector);
+                        (group, vectors)
+                    })
+                    .collect();
+                (groups, usage, cpu_start.elapsed())
+            })
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    let (groups, usage, cpu_time) = result.map_err(|e| bad_request(e.to_string()))?;
+
+    let groups = groups
+        .into_iter()
+        .map(|(group, vectors)| SearchGroupResult {
+            key: group.key,
+            hits: group
+                .hits
+                .into_iter()
+                .zip(vectors)
+                .map(|((id, distance, metadata), vector)| SearchResult {
+                    id: id.to_string(),
+                    distance,
+                    score: metric.score(distance),
+                    vector,
+                    metadata: metadata.filter(|_| include_metadata),
+                    lookup: None,
+                })
+                .collect(),
+        })
+        .collect();
+    let usage = record_usage(&name, usage, cpu_time);
+    Ok(Json(GroupedSearchResponse {
+        groups,
+        usage: with_usage.then_some(usage),
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/collections/{name}/search/batch",
+    params(
+        ("name" = String, Path, description = "Collection name")
+    ),
+    request_body = BatchSearchRequest,
+    responses(
+        (status = 200, description = "One result list (or results plus usage) per query, in query order", body = Vec<SearchResponse>),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn search_batch(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path(name): Path<String>,
+    Json(payload): Json<BatchSearchRequest>,
+) -> Result<Json<Vec<SearchResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let handler_start = Instant::now();
+    let limits = state.limits.effective(caller.key_name.as_deref());
+    check_limit("batch size", payload.queries.len(), limits.max_batch_size)?;
+    check_limit("k", payload.k, limits.max_k)?;
+    let params = search_params(
+        payload.k,
+        payload.ef_search,
+        payload.rescore,
+        payload.oversampling,
+        &limits,
+    )?;
+    let include_metadata = payload.include_metadata.unwrap_or(true);
+    let with_usage = payload.with_usage.unwrap_or(false);
+    let queries = payload.queries;
+    let k = payload.k;
+    let filter = payload.filter;
+    if let Some(filter) = &filter {
+        filter.validate().map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+        })?;
+    }
+
+    let collection = state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    if let Some(min_seq) = payload.min_seq {
+        let timeout = Duration::from_millis(state.config.min_seq_timeout_ms);
+        wait_for_seq(&collection, min_seq, timeout).await?;
+    }
+
+    let metric = collection.config().distance_metric;
+    let work_start = Instant::now();
+    let result = spawn_blocking(move || {
+        let cpu_start = Instant::now();
+        let results = if include_metadata {
+            collection
+                .search_batch(&queries, k, filter.as_ref(), params)?
+                .into_iter()
+                .map(|(hits, usage)| {
+                    let results = hits
+                        .into_iter()
+                        .map(|(id, distance, metadata)| SearchResult {
+                            id: id.as_str().to_string(),
+                            distance,
+                            score: metric.score(distance),
+                            vector: None,
+                            metadata,
+                            lookup: None,
+                        })
+                        .collect();
+                    (results, usage)
+                })
+                .collect()
+        } else {
+            collection
+                .search_ids_batch(&queries, k, filter.as_ref(), params)?
+                .into_iter()
+                .map(|(hits, usage)| {
+                    let results = hits
+                        .into_iter()
+                        .map(|(id, distance)| SearchResult {
+                            id: id.as_str().to_string(),
+                            distance,
+                            score: metric.score(distance),
+                            vector: None,
+                            metadata: None,
+                            lookup: None,
+                        })
+                        .collect();
+                    (results, usage)
+                })
+                .collect::<Vec<(Vec<SearchResult>, SearchUsage)>>()
+        };
+        Ok::<_, surgedb_core::Error>((results, cpu_start.elapsed()))
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    let (results, cpu_time) = result.map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    // Queries ran in parallel; attribute an equal share of the wall time to each
+    let cpu_time = cpu_time / results.len().max(1) as u32;
+    let work_ms = work_start.elapsed().as_secs_f64() * 1000.0;
+    let total_ms = handler_start.elapsed().as_secs_f64() * 1000.0;
+    log_perf("search_batch", total_ms, work_ms, None, Some(results.len()));
+
+    Ok(Json(
+        results
+            .into_iter()
+            .map(|(results, usage)| search_response(&name, results, usage, cpu_time, with_usage))
+            .collect(),
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/collections/{name}/search/hybrid",
+    params(
+        ("name" = String, Path, description = "Collection name")
+    ),
+    request_body = HybridSearchRequest,
+    responses(
+        (status = 200, description = "Results ranked by fused dense and sparse score, with a usage block if requested", body = HybridSearchResponse),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn search_hybrid(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path(name): Path<String>,
+    Json(payload): Json<HybridSearchRequest>,
+) -> Result<Json<HybridSearchResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let handler_start = Instant::now();
+    let limits = state.limits.effective(caller.key_name.as_deref());
+    check_limit("k", payload.k, limits.max_k)?;
+    let params = search_params(
+        payload.k,
+        payload.ef_search,
+        payload.rescore,
+        payload.oversampling,
+        &limits,
+    )?;
+    let include_metadata = payload.include_metadata.unwrap_or(true);
+    let explain = payload.explain.unwrap_or(false);
+    let with_usage = payload.with_usage.unwrap_or(false);
+    let fusion = payload.fusion.unwrap_or_default();
+    let vector = payload.vector;
+    let sparse = payload.sparse;
+    let k = payload.k;
+    let filter = payload.filter;
+    if let Some(filter) = &filter {
+        filter.validate().map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+        })?;
+    }
+
+    let collection = state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    if let Some(min_seq) = payload.min_seq {
+        let timeout = Duration::from_millis(state.config.min_seq_timeout_ms);
+        wait_for_seq(&collection, min_seq, timeout).await?;
+    }
+
+    let work_start = Instant::now();
+    let result = spawn_blocking(move || {
+        let cpu_start = Instant::now();
+        collection
+            .search_hybrid(&vector, &sparse, k, filter.as_ref(), fusion, params)
+            .map(|(hits, usage)| (hits, usage, cpu_start.elapsed()))
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    let (hits, usage, cpu_time) = result.map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    let results: Vec<HybridSearchResult> = hits
+        .into_iter()
+        .map(|hit: HybridHit| HybridSearchResult {
+            id: hit.id.as_str().to_string(),
+            score: hit.score,
+            distance: hit.distance,
+            sparse_score: hit.sparse_score,
+            explanation: explain.then_some(hit.explanation),
+            metadata: hit.metadata.filter(|_| include_metadata),
+        })
+        .collect();
+    let work_ms = work_start.elapsed().as_secs_f64() * 1000.0;
+    let total_ms = handler_start.elapsed().as_secs_f64() * 1000.0;
+    log_perf(
+        "search_hybrid",
+        total_ms,
+        work_ms,
+        None,
+        Some(results.len()),
+    );
+
+    let usage = record_usage(&name, usage, cpu_time);
+    Ok(Json(if with_usage {
+        HybridSearchResponse::WithUsage(HybridSearchWithUsageResponse { results, usage })
+    } else {
+        HybridSearchResponse::Results(results)
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/collections/{name}/search/text",
+    params(
+        ("name" = String, Path, description = "Collection name")
+    ),
+    request_body = TextSearchRequest,
+    responses(
+        (status = 200, description = "Results ranked by BM25 score, or fused with the vector search when `vector` is given, with a usage block if requested", body = HybridSearchResponse),
+        (status = 400, description = "Invalid request or no text fields", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn search_text(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path(name): Path<String>,
+    Json(payload): Json<TextSearchRequest>,
+) -> Result<Json<HybridSearchResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let handler_start = Instant::now();
+    let limits = state.limits.effective(caller.key_name.as_deref());
+    check_limit("k", payload.k, limits.max_k)?;
+    let params = search_params(
+        payload.k,
+        payload.ef_search,
+        payload.rescore,
+        payload.oversampling,
+        &limits,
+    )?;
+    let include_metadata = payload.include_metadata.unwrap_or(true);
+    let explain = payload.explain.unwrap_or(false);
+    let with_usage = payload.with_usage.unwrap_or(false);
+    let fusion = payload.fusion.unwrap_or_default();
+    let vector = payload.vector;
+    let query = payload.query;
+    let k = payload.k;
+    let filter = payload.filter;
+    if let Some(filter) = &filter {
+        filter.validate().map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+        })?;
+    }
+
+    let collection = state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    if let Some(min_seq) = payload.min_seq {
+        let timeout = Duration::from_millis(state.config.min_seq_timeout_ms);
+        wait_for_seq(&collection, min_seq, timeout).await?;
+    }
+
+    let work_start = Instant::now();
+    let result = spawn_blocking(move || {
+        let cpu_start = Instant::now();
+        let (hits, usage) = match vector {
+            Some(vector) => collection.search_hybrid_text(
+                &vector,
+                &query,
+                k,
+                filter.as_ref(),
+                fusion,
+                params,
+            )?,
+            None => {
+                let (hits, usage) = collection.search_text(&query, k, filter.as_ref())?;
+                let hits = hits
+                    .into_iter()
+                    .enumerate()
+                    .map(|(rank, (id, score, metadata))| HybridHit {
+                        id,
+                        score,
+                        distance: None,
+                        sparse_score: Some(score),
+                        explanation: FusionExplanation {
+                            sparse_rank: Some(rank + 1),
+                            sparse_contribution: score,
+                            ..Default::default()
+                        },
+                        metadata,
+                    })
+                    .collect();
+                (hits, usage)
+            }
+        };
+        Ok::<_, surgedb_core::Error>((hits, usage, cpu_start.elapsed()))
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    let (hits, usage, cpu_time) = result.map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    let results: Vec<HybridSearchResult> = hits
+        .into_iter()
+        .map(|hit: HybridHit| HybridSearchResult {
+            id: hit.id.as_str().to_string(),
+            score: hit.score,
+            distance: hit.distance,
+            sparse_score: hit.sparse_score,
+            explanation: explain.then_some(hit.explanation),
+            metadata: hit.metadata.filter(|_| include_metadata),
+        })
+        .collect();
+    let work_ms = work_start.elapsed().as_secs_f64() * 1000.0;
+    let total_ms = handler_start.elapsed().as_secs_f64() * 1000.0;
+    log_perf("search_text", total_ms, work_ms, None, Some(results.len()));
+
+    let usage = record_usage(&name, usage, cpu_time);
+    Ok(Json(if with_usage {
+        HybridSearchResponse::WithUsage(HybridSearchWithUsageResponse { results, usage })
+    } else {
+        HybridSearchResponse::Results(results)
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/collections/{name}/payloads",
+    params(
+        ("name" = String, Path, description = "Collection name")
+    ),
+    request_body = PayloadsRequest,
+    responses(
+        (status = 200, description = "Metadata of the found IDs, in request order", body = Vec<Payload>),
+        (status = 400, description = "Too many IDs", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn get_payloads(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path(name): Path<String>,
+    Json(payload): Json<PayloadsRequest>,
+) -> Result<Json<Vec<Payload>>, (StatusCode, Json<ErrorResponse>)> {
+    let handler_start = Instant::now();
+    let limits = state.limits.effective(caller.key_name.as_deref());
+    check_limit("batch size", payload.ids.len(), limits.max_batch_size)?;
+
+    let collection = state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    let work_start = Instant::now();
+    let found = spawn_blocking(move || collection.get_metadata_batch(&payload.ids))
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+        })?;
+    let work_ms = work_start.elapsed().as_secs_f64() * 1000.0;
+
+    let response: Vec<Payload> = found
+        .into_iter()
+        .map(|(id, metadata)| Payload {
+            id: id.as_str().to_string(),
+            metadata,
+        })
+        .collect();
+    let total_ms = handler_start.elapsed().as_secs_f64() * 1000.0;
+    log_perf(
+        "get_payloads",
+        total_ms,
+        work_ms,
+        None,
+        Some(response.len()),
+    );
+    Ok(Json(response))
+}
+
+// =============================================================================
+// Filter Cache
+// =============================================================================
+
+#[utoipa::path(
+    post,
+    path = "/collections/{name}/filter-cache",
+    params(
+        ("name" = String, Path, description = "Collection name")
+    ),
+    request_body = CacheFilterRequest,
+    responses(
+        (status = 200, description = "Filter cached", body = CachedFilter),
+        (status = 400, description = "Invalid filter or too many cached filters", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn cache_filter(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(payload): Json<CacheFilterRequest>,
+) -> Result<Json<CachedFilter>, (StatusCode, Json<ErrorResponse>)> {
+    let handler_start = Instant::now();
+    let collection = state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    let work_start = Instant::now();
+    let info = spawn_blocking(move || collection.cache_filter(payload.filter))
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+        })?
+        .map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+        })?;
+    let work_ms = work_start.elapsed().as_secs_f64() * 1000.0;
+
+    info!(
+        "Cached filter {} on {} ({} matches)",
+        info.id, name, info.matches
+    );
+    let total_ms = handler_start.elapsed().as_secs_f64() * 1000.0;
+    log_perf(
+        "cache_filter",
+        total_ms,
+        work_ms,
+        None,
+        Some(info.matches as usize),
+    );
+    Ok(Json(info.into()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/collections/{name}/filter-cache",
+    params(
+        ("name" = String, Path, description = "Collection name")
+    ),
+    responses(
+        (status = 200, description = "Cached filters with match counts and hits", body = [CachedFilter]),
+        (status = 404, description = "Collection not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn list_cached_filters(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<Vec<CachedFilter>>, (StatusCode, Json<ErrorResponse>)> {
+    let collection = state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    Ok(Json(
+        collection
+            .cached_filters()
+            .into_iter()
+            .map(CachedFilter::from)
+            .collect(),
+    ))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/collections/{name}/filter-cache/{id}",
+    params(
+        ("name" = String, Path, description = "Collection name"),
+        ("id" = String, Path, description = "Cached filter ID")
+    ),
+    responses(
+        (status = 200, description = "Cached filter dropped"),
+        (status = 404, description = "Collection or cached filter not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn uncache_filter(
+    State(state): State<AppState>,
+    Path((name, id)): Path<(String, String)>,
+) -> Result<&'static str, (StatusCode, Json<ErrorResponse>)> {
+    let collection = state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    if collection.uncache_filter(&id) {
+        info!("Dropped cached filter {} on {}", id, name);
+        Ok("Deleted")
+    } else {
+        Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("Cached filter not found: {}", id),
+            }),
+        ))
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/collections/{name}/filter-cache/{id}/recall",
+    params(
+        ("name" = String, Path, description = "Collection name"),
+        ("id" = String, Path, description = "Cached filter ID"),
+        FilterRecallParams
+    ),
+    responses(
+        (status = 200, description = "Filtered-search recall and the strategy searches with the filter should use", body = FilterRecallResponse),
+        (status = 400, description = "Sample too large or nothing matches the filter", body = ErrorResponse),
+        (status = 404, description = "Collection or cached filter not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn estimate_filter_recall(
+    State(state): State<AppState>,
+    Path((name, id)): Path<(String, String)>,
+    Query(params): Query<FilterRecallParams>,
+) -> Result<Json<FilterRecallResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let collection = state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    let Some(cached) = collection.cached_filters().into_iter().find(|f| f.id == id) else {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("Cached filter not found: {}", id),
+            }),
+        ));
+    };
+    let sample_size = params.sample_size.unwrap_or(DEFAULT_FILTER_RECALL_QUERIES);
+    check_limit("sample_size", sample_size, MAX_FILTER_RECALL_QUERIES)?;
+    let k = params.k.unwrap_or(10);
+    let target_recall = params.target_recall.unwrap_or(0.95);
+
+    let start = Instant::now();
+    let report = spawn_blocking(move || {
+        collection.estimate_filter_recall(&cached.filter, sample_size, k, target_recall)
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?
+    .map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    let total_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+    let Some(report) = report else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: format!("No records match cached filter {}", id),
+            }),
+        ));
+    };
+    log_perf(
+        "filter_recall",
+        total_ms,
+        total_ms,
+        None,
+        Some(report.queries),
+    );
+    info!(
+        "Filter {} on {}: recall@{} {:.3}, recommending {:?}",
+        id, name, report.k, report.recall, report.recommended
+    );
+    Ok(Json(FilterRecallResponse { id, report }))
+}
+
+// =============================================================================
+// Aliases & Deployments
+// =============================================================================
+
+#[utoipa::path(
+    get,
+    path = "/aliases",
+    responses(
+        (status = 200, description = "All aliases", body = [AliasEntry]),
+        (status = 304, description = "Unchanged since the ETag in If-None-Match")
+    ),
+    security(("api_key" = []))
+)]
+async fn list_aliases(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    let etag = etag::tag(&[state.boot_id, state.db.catalog_version()]);
+    if etag::matches(&headers, &etag) {
+        return etag::not_modified(&etag);
+    }
+    let aliases: Vec<AliasEntry> = state
+        .db
+        .list_aliases()
+        .into_iter()
+        .map(|(alias, collection)| AliasEntry { alias, collection })
+        .collect();
+    etag::with_tag(Json(aliases), &etag)
+}
+
+#[utoipa::path(
+    put,
+    path = "/aliases/{alias}",
+    params(
+        ("alias" = String, Path, description = "Alias name")
+    ),
+    request_body = SetAliasRequest,
+    responses(
+        (status = 200, description = "Alias now points to the collection", body = AliasEntry),
+        (status = 400, description = "Invalid alias", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn set_alias(
+    State(state): State<AppState>,
+    Path(alias): Path<String>,
+    Json(payload): Json<SetAliasRequest>,
+) -> Result<Json<AliasEntry>, (StatusCode, Json<ErrorResponse>)> {
+    match state.db.set_alias(&alias, &payload.collection) {
+        Ok(previous) => {
+            info!(
+                "Alias {} -> {} (was {:?})",
+                alias, payload.collection, previous
+            );
+            Ok(Json(AliasEntry {
+                alias,
+                collection: payload.collection,
+            }))
+        }
+        Err(e) => {
+            let status = match e {
+                surgedb_core::Error::CollectionNotFound(_) => StatusCode::NOT_FOUND,
+                _ => StatusCode::BAD_REQUEST,
+            };
+            Err((
+                status,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            ))
+        }
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/aliases/{alias}",
+    params(
+        ("alias" = String, Path, description = "Alias name")
+    ),
+    responses(
+        (status = 200, description = "Alias deleted"),
+        (status = 404, description = "Alias not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn delete_alias(
+    State(state): State<AppState>,
+    Path(alias): Path<String>,
+) -> Result<&'static str, (StatusCode, Json<ErrorResponse>)> {
+    match state.db.delete_alias(&alias) {
+        Ok(_) => {
+            info!("Deleted alias: {}", alias);
+            Ok("Deleted")
+        }
+        Err(e) => {
+            let status = match e {
+                surgedb_core::Error::AliasNotFound(_) => StatusCode::NOT_FOUND,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            Err((
+                status,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            ))
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/deployments",
+    request_body = DeploymentRequest,
+    responses(
+        (status = 202, description = "Deployment started", body = Deployment),
+        (status = 400, description = "Invalid request", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn create_deployment(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Json(payload): Json<DeploymentRequest>,
+) -> Result<(StatusCode, Json<Deployment>), (StatusCode, Json<ErrorResponse>)> {
+    let limits = state.limits.effective(caller.key_name.as_deref());
+    check_limit("batch size", payload.vectors.len(), limits.max_batch_size)?;
+    validate_sparse(&payload.vectors)?;
+
+    let job = state
+        .deployments
+        .start(state.db.clone(), state.webhooks.clone(), payload)
+        .map_err(|error| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })))?;
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/deployments",
+    responses(
+        (status = 200, description = "Recent deployments, newest first", body = [Deployment])
+    ),
+    security(("api_key" = []))
+)]
+async fn list_deployments(State(state): State<AppState>) -> Json<Vec<Deployment>> {
+    Json(state.deployments.list())
+}
+
+#[utoipa::path(
+    get,
+    path = "/deployments/{id}",
+    params(
+        ("id" = String, Path, description = "Deployment ID")
+    ),
+    responses(
+        (status = 200, description = "Deployment state", body = Deployment),
+        (status = 404, description = "Deployment not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn get_deployment(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Deployment>, (StatusCode, Json<ErrorResponse>)> {
+    state.deployments.get(&id).map(Json).ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("Deployment not found: {}", id),
+            }),
+        )
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/jobs",
+    responses(
+        (status = 200, description = "Recent background jobs, newest first", body = [Job])
+    ),
+    security(("api_key" = []))
+)]
+async fn list_jobs(State(state): State<AppState>) -> Json<Vec<Job>> {
+    Json(state.jobs.list())
+}
+
+#[utoipa::path(
+    get,
+    path = "/jobs/{id}",
+    params(
+        ("id" = String, Path, description = "Job ID")
+    ),
+    responses(
+        (status = 200, description = "Job state and progress", body = Job),
+        (status = 404, description = "Job not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn get_job(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Job>, (StatusCode, Json<ErrorResponse>)> {
+    state.jobs.get(&id).map(Json).ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("Job not found: {}", id),
+            }),
+        )
+    })
+}
+
+// =============================================================================
+// Webhooks
+// =============================================================================
+
+#[utoipa::path(
+    post,
+    path = "/collections/{name}/webhooks",
+    params(
+        ("name" = String, Path, description = "Collection name")
+    ),
+    request_body = CreateWebhookRequest,
+    responses(
+        (status = 200, description = "Webhook created", body = Webhook),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn create_webhook(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(payload): Json<CreateWebhookRequest>,
+) -> Result<Json<Webhook>, (StatusCode, Json<ErrorResponse>)> {
+    state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    let hook = state
+        .webhooks
+        .add(&name, payload)
+        .map_err(|error| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })))?;
+    info!(
+        "Created webhook {} on {} ({:?} {})",
+        hook.id, name, hook.metric, hook.threshold
+    );
+    Ok(Json(hook))
+}
+
+#[utoipa::path(
+    get,
+    path = "/collections/{name}/webhooks",
+    params(
+        ("name" = String, Path, description = "Collection name")
+    ),
+    responses(
+        (status = 200, description = "Webhooks on the collection", body = [Webhook])
+    ),
+    security(("api_key" = []))
+)]
+async fn list_webhooks(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Json<Vec<Webhook>> {
+    Json(state.webhooks.list(&name))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/collections/{name}/webhooks/{id}",
+    params(
+        ("name" = String, Path, description = "Collection name"),
+        ("id" = String, Path, description = "Webhook ID")
+    ),
+    responses(
+        (status = 200, description = "Webhook deleted"),
+        (status = 404, description = "Webhook not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn delete_webhook(
+    State(state): State<AppState>,
+    Path((name, id)): Path<(String, String)>,
+) -> Result<&'static str, (StatusCode, Json<ErrorResponse>)> {
+    match state.webhooks.remove(&name, &id) {
+        Ok(true) => {
+            info!("Deleted webhook {} on {}", id, name);
+            Ok("Deleted")
+        }
+        Ok(false) => Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("Webhook not found: {}", id),
+            }),
+        )),
+        Err(error) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse { error }),
+        )),
+    }
+}
+
+/// Garbage stats of `name` after following aliases, or 404
+async fn collection_garbage(
+    state: &AppState,
+    name: &str,
+) -> Result<surgedb_core::GarbageStats, (StatusCode, Json<ErrorResponse>)> {
+    let collection = state.db.get_collection(name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    spawn_blocking(move || collection.garbage_stats())
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+        })
+}
+
+#[utoipa::path(
+    get,
+    path = "/collections/{name}/compaction",
+    params(("name" = String, Path, description = "Collection name")),
+    responses(
+        (status = 200, description = "Garbage metrics and compaction state", body = CompactionStatus),
+        (status = 403, description = "Admin API key required", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn get_compaction(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path(name): Path<String>,
+) -> Result<Json<CompactionStatus>, (StatusCode, Json<ErrorResponse>)> {
+    require_admin(&caller)?;
+    let garbage = collection_garbage(&state, &name).await?;
+    let name = state.db.resolve_name(&name);
+    Ok(Json(state.compaction.status(&name, garbage)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/collections/{name}/compaction",
+    params(("name" = String, Path, description = "Collection name")),
+    responses(
+        (status = 200, description = "Collection compacted", body = CompactionRun),
+        (status = 403, description = "Admin API key required", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse),
+        (status = 409, description = "Collection is already being compacted", body = ErrorResponse),
+        (status = 500, description = "Compaction failed", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn compact_collection(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path(name): Path<String>,
+) -> Result<Json<CompactionRun>, (StatusCode, Json<ErrorResponse>)> {
+    require_admin(&caller)?;
+    collection_garbage(&state, &name).await?;
+    let name = state.db.resolve_name(&name);
+    match state
+        .compaction
+        .compact(&state.db, &name, CompactionTrigger::Manual)
+        .await
+    {
+        Some(Ok(run)) => {
+            log_perf(
+                "compact",
+                run.duration_ms,
+                run.duration_ms,
+                None,
+                Some(run.vectors),
+            );
+            Ok(Json(run))
+        }
+        Some(Err(error)) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse { error }),
+        )),
+        None => Err((
+            StatusCode::CONFLICT,
+            Json(ErrorResponse {
+                error: format!("Collection {} is already being compacted", name),
+            }),
+        )),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/collections/{name}/compaction",
+    params(("name" = String, Path, description = "Collection name")),
+    request_body = SetCompactionRequest,
+    responses(
+        (status = 200, description = "Override saved", body = CompactionStatus),
+        (status = 403, description = "Admin API key required", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn set_compaction(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path(name): Path<String>,
+    Json(payload): Json<SetCompactionRequest>,
+) -> Result<Json<CompactionStatus>, (StatusCode, Json<ErrorResponse>)> {
+    require_admin(&caller)?;
+    let garbage = collection_garbage(&state, &name).await?;
+    let name = state.db.resolve_name(&name);
+    state
+        .compaction
+        .set_auto(&name, payload.auto)
+        .map_err(|error| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse { error }),
+            )
+        })?;
+    info!("Automatic compaction of {}: {:?}", name, payload.auto);
+    Ok(Json(state.compaction.status(&name, garbage)))
+}
+
+// =============================================================================
+// Mirrors
+// =============================================================================
+
+/// Collection whose writes to `name` must be published to mirrors, if any
+fn mirror_target(state: &AppState, name: &str) -> Option<String> {
+    let collection = state.db.resolve_name(name);
+    state.mirrors.is_mirrored(&collection).then_some(collection)
+}
+
+fn require_admin(caller: &Caller) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    if caller.admin {
+        return Ok(());
+    }
+    Err((
+        StatusCode::FORBIDDEN,
+        Json(ErrorResponse {
+            error: "Admin API key required".to_string(),
+        }),
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/collections/{name}/mirror",
+    params(
+        ("name" = String, Path, description = "Collection name")
+    ),
+    request_body = MirrorRequest,
+    responses(
+        (status = 200, description = "Mirror started", body = Mirror),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 403, description = "Admin API key required", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn create_mirror(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path(name): Path<String>,
+    Json(payload): Json<MirrorRequest>,
+) -> Result<Json<Mirror>, (StatusCode, Json<ErrorResponse>)> {
+    require_admin(&caller)?;
+    state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    let collection = state.db.resolve_name(&name);
+    let mirror = state
+        .mirrors
+        .start(state.db.clone(), &collection, payload)
+        .map_err(|error| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })))?;
+    Ok(Json(mirror))
+}
+
+#[utoipa::path(
+    get,
+    path = "/collections/{name}/mirror",
+    params(
+        ("name" = String, Path, description = "Collection name")
+    ),
+    responses(
+        (status = 200, description = "Mirrors of the collection and their progress", body = [Mirror]),
+        (status = 403, description = "Admin API key required", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn list_mirrors(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path(name): Path<String>,
+) -> Result<Json<Vec<Mirror>>, (StatusCode, Json<ErrorResponse>)> {
+    require_admin(&caller)?;
+    Ok(Json(state.mirrors.list(&state.db.resolve_name(&name))))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/collections/{name}/mirror/{id}",
+    params(
+        ("name" = String, Path, description = "Collection name"),
+        ("id" = String, Path, description = "Mirror ID")
+    ),
+    responses(
+        (status = 200, description = "Mirror stopped; queued changes are discarded"),
+        (status = 403, description = "Admin API key required", body = ErrorResponse),
+        (status = 404, description = "Mirror not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn delete_mirror(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path((name, id)): Path<(String, String)>,
+) -> Result<&'static str, (StatusCode, Json<ErrorResponse>)> {
+    require_admin(&caller)?;
+    if !state.mirrors.remove(&state.db.resolve_name(&name), &id) {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("Mirror not found: {}", id),
+            }),
+        ));
+    }
+    info!("Stopped mirror {} on {}", id, name);
+    Ok("Deleted")
+}
+
+// =============================================================================
+// Replication
+// =============================================================================
+
+#[utoipa::path(
+    get,
+    path = "/replication/status",
+    responses(
+        (status = 200, description = "Role of this server and, on a follower, how far each collection has been replicated", body = ReplicationStatus)
+    ),
+    security(("api_key" = []))
+)]
+async fn replication_status(State(state): State<AppState>) -> Json<ReplicationStatus> {
+    Json(state.replication.status(&state.db))
+}
+
+#[utoipa::path(
+    get,
+    path = "/replication/collections",
+    responses(
+        (status = 200, description = "Collections and aliases to replicate, with the position of each collection's log", body = Catalog),
+        (status = 403, description = "Admin API key required", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn replication_catalog(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+) -> Result<Json<Catalog>, (StatusCode, Json<ErrorResponse>)> {
+    require_admin(&caller)?;
+    Ok(Json(replication::catalog(&state.db)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/replication/collections/{name}/log",
+    params(
+        ("name" = String, Path, description = "Collection name"),
+        LogQuery
+    ),
+    responses(
+        (status = 200, description = "Log entries after the position, oldest first", body = LogPage),
+        (status = 403, description = "Admin API key required", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse),
+        (status = 410, description = "The entries are no longer in the log; copy the collection from a snapshot", body = ErrorResponse),
+        (status = 503, description = "The log is still being replayed", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn replication_log(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path(name): Path<String>,
+    Query(query): Query<LogQuery>,
+) -> Result<Json<LogPage>, (StatusCode, Json<ErrorResponse>)> {
+    require_admin(&caller)?;
+    let collection = state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    let from = LogPosition {
+        log_id: query.log_id,
+        seq: query.after,
+    };
+    let limit = query.limit.unwrap_or(MAX_LOG_PAGE).min(MAX_LOG_PAGE);
+    let entries = spawn_blocking(move || collection.log_after(from, limit))
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+        })?
+        .map_err(|e| {
+            let status = match e {
+                surgedb_core::Error::InvalidConfig(_) => StatusCode::SERVICE_UNAVAILABLE,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            (
+                status,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+        })?;
+    let Some(entries) = entries else {
+        return Err((
+            StatusCode::GONE,
+            Json(ErrorResponse {
+                error: format!(
+                    "Entries after {} are no longer in the log of {}",
+                    query.after, name
+                ),
+            }),
+        ));
+    };
+    Ok(Json(LogPage {
+        entries: entries
+            .into_iter()
+            .map(|(seq, entry)| LoggedEntry { seq, entry })
+            .collect(),
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/replication/collections/{name}/snapshot",
+    params(("name" = String, Path, description = "Collection name")),
+    responses(
+        (status = 200, description = "Snapshot file of the collection; the `x-log-id` and `x-log-seq` headers give the log position it covers", content_type = "application/octet-stream"),
+        (status = 403, description = "Admin API key required", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn replication_snapshot(
+    State(state): State<AppState>,
+    Extension(caller): Extension<Caller>,
+    Path(name): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    require_admin(&caller)?;
+    let collection = state.db.get_collection(&name).map_err(|e| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    let path = std::path::Path::new(&state.config.snapshot_dir).join(format!(
+        ".replication-{}-{:x}.snap",
+        name,
+        Utc::now().timestamp_nanos_opt().unwrap_or_default()
+    ));
+    let result = spawn_blocking(move || {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let snapshot = collection
+            .snapshot_at(&path)
+            .and_then(|position| Ok((position, std::fs::read(&path)?)));
+        let _ = std::fs::remove_file(&path);
+        snapshot
+    })
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    let (position, bytes) = result.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+            (
+                HeaderName::from_static(LOG_ID_HEADER),
+                position.log_id.to_string(),
+            ),
+            (
+                HeaderName::from_static(LOG_SEQ_HEADER),
+                position.seq.to_string(),
+            ),
+        ],
+        bytes,
+    ))
+}
+
+// =============================================================================
+// Admin: Limits
+// =============================================================================
+
+#[utoipa::path(
+    get,
+    path = "/admin/limits",
+    responses(
+        (status = 200, description = "Hard limits, soft defaults and per-key overrides", body = LimitsSnapshot),
+        (status = 403, description = "Admin API key required", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn get_limits(State(state): State<AppState>) -> Json<LimitsSnapshot> {
+    Json(state.limits.snapshot())
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/limits",
+    request_body = Limits,
+    responses(
+        (status = 200, description = "Soft limits updated", body = LimitsSnapshot),
+        (status = 400, description = "Value exceeds a hard limit", body = ErrorResponse),
+        (status = 403, description = "Admin API key required", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn update_soft_limits(
+    State(state): State<AppState>,
+    Json(payload): Json<Limits>,
+) -> Result<Json<LimitsSnapshot>, (StatusCode, Json<ErrorResponse>)> {
+    state
+        .limits
+        .set_soft(payload)
+        .map_err(|error| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })))?;
+    info!("Updated soft limits: {:?}", payload);
+    Ok(Json(state.limits.snapshot()))
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/limits/keys/{key_name}",
+    params(
+        ("key_name" = String, Path, description = "API key name, or `<namespace>.<name>` for a tenant key")
+    ),
+    request_body = LimitOverrides,
+    responses(
+        (status = 200, description = "Overrides set", body = LimitsSnapshot),
+        (status = 400, description = "Value exceeds a hard limit", body = ErrorResponse),
+        (status = 403, description = "Admin API key required", body = ErrorResponse),
+        (status = 404, description = "Unknown API key name", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn set_key_limits(
+    State(state): State<AppState>,
+    Path(key_name): Path<String>,
+    Json(payload): Json<LimitOverrides>,
+) -> Result<Json<LimitsSnapshot>, (StatusCode, Json<ErrorResponse>)> {
+    if key_name != ADMIN_KEY_NAME
+        && !state.config.api_keys.contains_key(&key_name)
+        && !state.tenants.has_key(&key_name)
+    {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("Unknown API key name: {}", key_name),
+            }),
+        ));
+    }
+    state
+        .limits
+        .set_override(&key_name, payload)
+        .map_err(|error| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })))?;
+    info!(
+        "Updated limit overrides for key {}: {:?}",
+        key_name, payload
+    );
+    Ok(Json(state.limits.snapshot()))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/limits/keys/{key_name}",
+    params(
+        ("key_name" = String, Path, description = "API key name")
+    ),
+    responses(
+        (status = 200, description = "Overrides removed", body = LimitsSnapshot),
+        (status = 403, description = "Admin API key required", body = ErrorResponse),
+        (status = 404, description = "No overrides set for key", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn delete_key_limits(
+    State(state): State<AppState>,
+    Path(key_name): Path<String>,
+) -> Result<Json<LimitsSnapshot>, (StatusCode, Json<ErrorResponse>)> {
+    match state.limits.remove_override(&key_name) {
+        Ok(true) => {
+            info!("Removed limit overrides for key {}", key_name);
+            Ok(Json(state.limits.snapshot()))
+        }
+        Ok(false) => Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("No limit overrides for key: {}", key_name),
+            }),
+        )),
+        Err(error) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse { error }),
+        )),
+    }
+}
+
+// =============================================================================
+// Admin: Logging
+// =============================================================================
+
+#[derive(Deserialize, ToSchema)]
+struct SetLogFilterRequest {
+    /// Directives in `RUST_LOG` syntax, replacing the current ones
+    #[schema(example = "info,surgedb_core::hnsw=debug")]
+    filter: String,
+}
+
+/// The log filter installed at startup, or 409 when a host application
+/// embedding the router set up logging itself
+fn log_filter() -> Result<&'static logging::LogFilter, (StatusCode, Json<ErrorResponse>)> {
+    logging::filter().ok_or_else(|| {
+        (
+            StatusCode::CONFLICT,
+            Json(ErrorResponse {
+                error: "Logging is not managed by this server".to_string(),
+            }),
+        )
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/logging",
+    responses(
+        (status = 200, description = "Log filter in effect", body = LogFilterState),
+        (status = 403, description = "Admin API key required", body = ErrorResponse),
+        (status = 409, description = "Logging is set up by the embedding application", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn get_log_filter() -> Result<Json<LogFilterState>, (StatusCode, Json<ErrorResponse>)> {
+    Ok(Json(log_filter()?.state()))
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/logging",
+    request_body = SetLogFilterRequest,
+    responses(
+        (status = 200, description = "Log filter changed", body = LogFilterState),
+        (status = 400, description = "Invalid filter directives", body = ErrorResponse),
+        (status = 403, description = "Admin API key required", body = ErrorResponse),
+        (status = 409, description = "Logging is set up by the embedding application", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn set_log_filter(
+    Json(payload): Json<SetLogFilterRequest>,
+) -> Result<Json<LogFilterState>, (StatusCode, Json<ErrorResponse>)> {
+    let state = log_filter()?
+        .set(&payload.filter)
+        .map_err(|error| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })))?;
+    info!("Log filter changed to {:?}", state.filter);
+    Ok(Json(state))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/logging",
+    responses(
+        (status = 200, description = "Log filter restored to the startup one", body = LogFilterState),
+        (status = 403, description = "Admin API key required", body = ErrorResponse),
+        (status = 409, description = "Logging is set up by the embedding application", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn reset_log_filter() -> Result<Json<LogFilterState>, (StatusCode, Json<ErrorResponse>)> {
+    let state = log_filter()?.reset().map_err(|error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse { error }),
+        )
+    })?;
+    info!("Log filter reset to {:?}", state.filter);
+    Ok(Json(state))
+}
+
+// =============================================================================
+// Admin: Tokens
+// =============================================================================
+
+#[utoipa::path(
+    post,
+    path = "/admin/tokens",
+    request_body = MintTokenRequest,
+    responses(
+        (status = 200, description = "Signed token", body = MintTokenResponse),
+        (status = 400, description = "Invalid scopes, collections or TTL, or no signing secret", body = ErrorResponse),
+        (status = 403, description = "Admin API key required", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn mint_token(
+    State(state): State<AppState>,
+    Json(payload): Json<MintTokenRequest>,
+) -> Result<Json<MintTokenResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let bad_request = |error: String| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error }));
+    let Some(signer) = &state.tokens else {
+        return Err(bad_request(
+            "Scoped tokens need TOKEN_SECRET or API_KEY to be set".to_string(),
+        ));
+    };
+    if payload.scopes.is_empty() || payload.collections.is_empty() {
+        return Err(bad_request(
+            "A token needs at least one scope and one collection".to_string(),
+        ));
+    }
+    let ttl_secs = payload.ttl_secs.unwrap_or(DEFAULT_TOKEN_TTL_SECS);
+    if ttl_secs == 0 || ttl_secs > MAX_TOKEN_TTL_SECS {
+        return Err(bad_request(format!(
+            "ttl_secs must be between 1 and {}, got {}",
+            MAX_TOKEN_TTL_SECS, ttl_secs
+        )));
+    }
+
+    let expires_at = Utc::now() + chrono::Duration::seconds(ttl_secs as i64);
+    let claims = TokenClaims {
+        scopes: payload.scopes,
+        collections: payload.collections,
+        exp: expires_at.timestamp(),
+        label: payload.label,
+    };
+    info!(
+        "Minted token for {:?} on {:?} until {} ({})",
+        claims.scopes,
+        claims.collections,
+        expires_at,
+        claims.label.as_deref().unwrap_or("unlabeled")
+    );
+    Ok(Json(MintTokenResponse {
+        token: signer.sign(&claims),
+        expires_at,
+    }))
+}
+
+// =============================================================================
+// Admin: Tenants
+// =============================================================================
+
+#[utoipa::path(
+    get,
+    path = "/admin/tenants",
+    responses(
+        (status = 200, description = "Tenants with their quotas, key names and usage", body = [TenantInfo]),
+        (status = 403, description = "Admin API key required", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn list_tenants(State(state): State<AppState>) -> Json<Vec<TenantInfo>> {
+    Json(state.tenants.list(&state.db))
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/tenants/{namespace}",
+    params(
+        ("namespace" = String, Path, description = "Namespace; the tenant owns the collections named `<namespace>.<name>`")
+    ),
+    request_body = TenantQuotas,
+    responses(
+        (status = 201, description = "Tenant created"),
+        (status = 200, description = "Quotas of the existing tenant replaced"),
+        (status = 400, description = "Invalid namespace, or no API_KEY set", body = ErrorResponse),
+        (status = 403, description = "Admin API key required", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn put_tenant(
+    State(state): State<AppState>,
+    Path(namespace): Path<String>,
+    Json(payload): Json<TenantQuotas>,
+) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+    if state.config.api_key.is_none() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "Tenants need API_KEY to be set".to_string(),
+            }),
+        ));
+    }
+    let created = state
+        .tenants
+        .put(&namespace, payload)
+        .map_err(|error| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })))?;
+    info!("Set tenant {} with quotas {:?}", namespace, payload);
+    Ok(if created {
+        StatusCode::CREATED
+    } else {
+        StatusCode::OK
+    })
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/tenants/{namespace}",
+    params(
+        ("namespace" = String, Path, description = "Namespace of the tenant")
+    ),
+    responses(
+        (status = 200, description = "Tenant removed and its keys revoked; its collections are kept"),
+        (status = 403, description = "Admin API key required", body = ErrorResponse),
+        (status = 404, description = "Unknown tenant", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn delete_tenant(
+    State(state): State<AppState>,
+    Path(namespace): Path<String>,
+) -> Result<&'static str, (StatusCode, Json<ErrorResponse>)> {
+    match state.tenants.remove(&namespace) {
+        Ok(true) => {
+            info!("Removed tenant {}", namespace);
+            Ok("Deleted")
+        }
+        Ok(false) => Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("Unknown tenant: {}", namespace),
+            }),
+        )),
+        Err(error) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse { error }),
+        )),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/tenants/{namespace}/keys",
+    params(
+        ("namespace" = String, Path, description = "Namespace of the tenant")
+    ),
+    request_body = CreateTenantKeyRequest,
+    responses(
+        (status = 201, description = "Key issued; it can't be shown again", body = TenantKeyResponse),
+        (status = 400, description = "Invalid or taken key name", body = ErrorResponse),
+        (status = 403, description = "Admin API key required", body = ErrorResponse),
+        (status = 404, description = "Unknown tenant", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn create_tenant_key(
+    State(state): State<AppState>,
+    Path(namespace): Path<String>,
+    Json(payload): Json<CreateTenantKeyRequest>,
+) -> Result<(StatusCode, Json<TenantKeyResponse>), (StatusCode, Json<ErrorResponse>)> {
+    if !state.tenants.contains(&namespace) {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("Unknown tenant: {}", namespace),
+            }),
+        ));
+    }
+    let key = state
+        .tenants
+        .add_key(&namespace, &payload.name, payload.role)
+        .map_err(|error| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })))?;
+    info!("Issued key {} to tenant {}", payload.name, namespace);
+    Ok((
+        StatusCode::CREATED,
+        Json(TenantKeyResponse {
+            key_name: format!("{}.{}", namespace, payload.name),
+            key,
+        }),
+    ))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/tenants/{namespace}/keys/{key_name}",
+    params(
+        ("namespace" = String, Path, description = "Namespace of the tenant"),
+        ("key_name" = String, Path, description = "Name of the key within the tenant")
+    ),
+    responses(
+        (status = 200, description = "Key revoked"),
+        (status = 403, description = "Admin API key required", body = ErrorResponse),
+        (status = 404, description = "Unknown tenant or key", body = ErrorResponse)
+    ),
+    security(("api_key" = []))
+)]
+async fn revoke_tenant_key(
+    State(state): State<AppState>,
+    Path((namespace, key_name)): Path<(String, String)>,
+) -> Result<&'static str, (StatusCode, Json<ErrorResponse>)> {
+    match state.tenants.revoke_key(&namespace, &key_name) {
+        Ok(true) => {
+            info!("Revoked key {} of tenant {}", key_name, namespace);
+            Ok("Revoked")
+        }
+        Ok(false) => Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("Tenant {} has no key named {}", namespace, key_name),
+            }),
+        )),
+        Err(error) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse { error }),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_public_search_needs_connect_info() {
+        let dir = std::env::temp_dir().join(format!("surgedb-lib-test-{}", std::process::id()));
+        let vars = [("API_KEY", "secret"), ("PUBLIC_COLLECTIONS", "docs")];
+        let mut config = AppConfig::from_vars(|name| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.to_string())
+                .ok_or(std::env::VarError::NotPresent)
+        });
+        config.data_dir = dir.to_string_lossy().into_owned();
+        let db = Database::new();
+        db.create_collection("docs", surgedb_core::Config::builder(2).build().unwrap())
+            .unwrap();
+        let app = build_router(AppState::new(Arc::new(db), config));
+
+        // Mounted without `into_make_service_with_connect_info`
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .unwrap();
+        let url = format!(
+            "http://{}/collections/docs/search",
+            listener.local_addr().unwrap()
+        );
+        let server = tokio::spawn(async move { axum::serve(listener, app).await });
+        let response = reqwest::Client::new()
+            .post(url)
+            .json(&serde_json::json!({ "vector": [1.0, 0.0], "k": 1 }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        server.abort();
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,209 @@
+//! Request guardrails (max k, batch size, dimensions) and the vector quota
+//!
+//! Hard limits are fixed at startup and can never be exceeded. Soft limits
+//! are the defaults applied to every caller and can be raised per API key
+//! (up to the hard limits) through the admin API. Runtime changes are saved
+//! to `limits.json` in the data directory so they survive restarts.
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tracing::warn;
+use utoipa::ToSchema;
+
+/// A set of guardrail values
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, ToSchema)]
+pub struct Limits {
+    /// Max neighbors returned by a single search
+    pub max_k: usize,
+    /// Max vectors in a single batch insert
+    pub max_batch_size: usize,
+    /// Max dimensions of a new collection
+    pub max_dimensions: usize,
+    /// Max vectors a collection may hold after a write; unset means no quota
+    /// (or the hard one, if set)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_vectors: Option<usize>,
+}
+
+impl Limits {
+    fn clamp_to(self, hard: &Limits) -> Limits {
+        Limits {
+            max_k: self.max_k.min(hard.max_k),
+            max_batch_size: self.max_batch_size.min(hard.max_batch_size),
+            max_dimensions: self.max_dimensions.min(hard.max_dimensions),
+            max_vectors: match (self.max_vectors, hard.max_vectors) {
+                (Some(soft), Some(hard)) => Some(soft.min(hard)),
+                (soft, hard) => soft.or(hard),
+            },
+        }
+    }
+}
+
+/// Per-key overrides; unset fields fall back to the soft limits
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, ToSchema)]
+pub struct LimitOverrides {
+    pub max_k: Option<usize>,
+    pub max_batch_size: Option<usize>,
+    pub max_dimensions: Option<usize>,
+    pub max_vectors: Option<usize>,
+}
+
+/// Snapshot of all configured limits, as returned by the admin API
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct LimitsSnapshot {
+    pub hard: Limits,
+    pub soft: Limits,
+    /// Overrides keyed by API key name
+    pub overrides: HashMap<String, LimitOverrides>,
+}
+
+/// The subset of the snapshot that is editable at runtime and persisted
+#[derive(Serialize, Deserialize, Default)]
+struct PersistedLimits {
+    soft: Option<Limits>,
+    #[serde(default)]
+    overrides: HashMap<String, LimitOverrides>,
+}
+
+/// Shared registry of hard limits, soft defaults and per-key overrides
+pub struct LimitsRegistry {
+    hard: Limits,
+    /// Soft limits from configuration
+    default_soft: Limits,
+    /// Soft limits set through the admin API, taking precedence over `default_soft`
+    runtime_soft: RwLock<Option<Limits>>,
+    overrides: RwLock<HashMap<String, LimitOverrides>>,
+    path: Option<PathBuf>,
+}
+
+impl LimitsRegistry {
+    /// Create a registry, loading any runtime changes saved at `path`
+    pub fn new(hard: Limits, soft: Limits, path: Option<PathBuf>) -> Self {
+        let persisted = path
+            .as_ref()
+            .and_then(|p| std::fs::read(p).ok())
+            .and_then(
+                |bytes| match serde_json::from_slice::<PersistedLimits>(&bytes) {
+                    Ok(persisted) => Some(persisted),
+                    Err(e) => {
+                        warn!("Ignoring unreadable limits file: {}", e);
+                        None
+                    }
+                },
+            )
+            .unwrap_or_default();
+
+        Self {
+            hard,
+            default_soft: soft.clamp_to(&hard),
+            runtime_soft: RwLock::new(persisted.soft.map(|s| s.clamp_to(&hard))),
+            overrides: RwLock::new(persisted.overrides),
+            path,
+        }
+    }
+
+    /// Effective limits for a caller; `None` means no per-key override applies
+    pub fn effective(&self, key_name: Option<&str>) -> Limits {
+        let soft = self.soft();
+        let overrides = key_name.and_then(|name| self.overrides.read().get(name).copied());
+        match overrides {
+            Some(o) => Limits {
+                max_k: o.max_k.unwrap_or(soft.max_k),
+                max_batch_size: o.max_batch_size.unwrap_or(soft.max_batch_size),
+                max_dimensions: o.max_dimensions.unwrap_or(soft.max_dimensions),
+                max_vectors: o.max_vectors.or(soft.max_vectors),
+            }
+            .clamp_to(&self.hard),
+            None => soft,
+        }
+    }
+
+    pub fn snapshot(&self) -> LimitsSnapshot {
+        LimitsSnapshot {
+            hard: self.hard,
+            soft: self.soft(),
+            overrides: self.overrides.read().clone(),
+        }
+    }
+
+    fn soft(&self) -> Limits {
+        self.runtime_soft.read().unwrap_or(self.default_soft)
+    }
+
+    /// Replace the soft defaults; fails if any value exceeds the hard limits
+    pub fn set_soft(&self, soft: Limits) -> Result<(), String> {
+        self.check_hard(
+            soft.max_k,
+            soft.max_batch_size,
+            soft.max_dimensions,
+            soft.max_vectors,
+        )?;
+        *self.runtime_soft.write() = Some(soft.clamp_to(&self.hard));
+        self.save()
+    }
+
+    /// Set the overrides for one key; fails if any value exceeds the hard limits
+    pub fn set_override(&self, key_name: &str, overrides: LimitOverrides) -> Result<(), String> {
+        self.check_hard(
+            overrides.max_k.unwrap_or(0),
+            overrides.max_batch_size.unwrap_or(0),
+            overrides.max_dimensions.unwrap_or(0),
+            overrides.max_vectors,
+        )?;
+        self.overrides
+            .write()
+            .insert(key_name.to_string(), overrides);
+        self.save()
+    }
+
+    /// Remove the overrides for one key; returns false if none were set
+    pub fn remove_override(&self, key_name: &str) -> Result<bool, String> {
+        let removed = self.overrides.write().remove(key_name).is_some();
+        if removed {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    fn check_hard(
+        &self,
+        max_k: usize,
+        max_batch_size: usize,
+        max_dimensions: usize,
+        max_vectors: Option<usize>,
+    ) -> Result<(), String> {
+        let checks = [
+            ("max_k", max_k, self.hard.max_k),
+            ("max_batch_size", max_batch_size, self.hard.max_batch_size),
+            ("max_dimensions", max_dimensions, self.hard.max_dimensions),
+            (
+                "max_vectors",
+                max_vectors.unwrap_or(0),
+                self.hard.max_vectors.unwrap_or(usize::MAX),
+            ),
+        ];
+        for (name, value, hard) in checks {
+            if value > hard {
+                return Err(format!(
+                    "{} of {} exceeds hard limit of {}",
+                    name, value, hard
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let persisted = PersistedLimits {
+            soft: *self.runtime_soft.read(),
+            overrides: self.overrides.read().clone(),
+        };
+        let bytes = serde_json::to_vec_pretty(&persisted).map_err(|e| e.to_string())?;
+        std::fs::write(path, bytes).map_err(|e| format!("Failed to save limits: {}", e))
+    }
+}
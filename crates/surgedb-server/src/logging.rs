@@ -0,0 +1,85 @@
+//! Log filter that can be changed while the server runs
+//!
+//! [`init`] installs the tracing subscriber with its `EnvFilter` behind a
+//! reload layer, so `/admin/logging` can turn on debug logging for a single
+//! module (e.g. `info,surgedb_core::hnsw=debug`) during an incident and turn
+//! it off again without a restart. A host embedding the router that installs
+//! its own subscriber has no filter to change.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, EnvFilter, Registry};
+use utoipa::ToSchema;
+
+static LOG_FILTER: OnceLock<LogFilter> = OnceLock::new();
+
+/// The installed filter and the directives it was built from
+pub struct LogFilter {
+    handle: reload::Handle<EnvFilter, Registry>,
+    /// Directives the server started with
+    initial: String,
+    current: Mutex<String>,
+}
+
+/// Current and startup log filter directives
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct LogFilterState {
+    /// Directives in effect, in `RUST_LOG` syntax
+    #[schema(example = "info,surgedb_core::hnsw=debug")]
+    pub filter: String,
+    /// Directives the server started with, restored by `DELETE`
+    #[schema(example = "info")]
+    pub initial: String,
+}
+
+/// Install the global tracing subscriber, filtering with `RUST_LOG` if set
+/// and `default` otherwise
+pub fn init(default: &str) {
+    let initial = std::env::var(EnvFilter::DEFAULT_ENV)
+        .ok()
+        .filter(|directives| EnvFilter::try_new(directives).is_ok())
+        .unwrap_or_else(|| default.to_string());
+    let (filter, handle) = reload::Layer::new(EnvFilter::new(&initial));
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().with_target(false))
+        .init();
+    let _ = LOG_FILTER.set(LogFilter {
+        handle,
+        current: Mutex::new(initial.clone()),
+        initial,
+    });
+}
+
+/// The filter installed by [`init`], if this process called it
+pub fn filter() -> Option<&'static LogFilter> {
+    LOG_FILTER.get()
+}
+
+impl LogFilter {
+    pub fn state(&self) -> LogFilterState {
+        LogFilterState {
+            filter: self.current.lock().clone(),
+            initial: self.initial.clone(),
+        }
+    }
+
+    /// Filter with `directives` from now on
+    pub fn set(&self, directives: &str) -> Result<LogFilterState, String> {
+        let filter = EnvFilter::try_new(directives)
+            .map_err(|e| format!("Invalid log filter {:?}: {}", directives, e))?;
+        let mut current = self.current.lock();
+        self.handle.reload(filter).map_err(|e| e.to_string())?;
+        *current = directives.to_string();
+        drop(current);
+        Ok(self.state())
+    }
+
+    /// Go back to the directives the server started with
+    pub fn reset(&self) -> Result<LogFilterState, String> {
+        self.set(&self.initial)
+    }
+}
@@ -0,0 +1,694 @@
+//! One-way collection mirroring to a remote SurgeDB instance
+//!
+//! A mirror first copies every vector the collection holds, then tails the
+//! collection's writes and replays them on the remote through its regular
+//! API (batch upsert, delete, document replace). Writes are queued while the
+//! copy runs, so the remote converges on the source even when both overlap.
+//! Failed pushes are retried with backoff until they succeed, except when the
+//! remote rejects a change outright (4xx), which is counted and skipped.
+//!
+//! Mirrors are kept in memory. A shutdown saves them with the changes they
+//! have not pushed yet, and the next start resumes them; a mirror stopped
+//! during its copy starts the copy over. Mirrors are lost if the process
+//! crashes. Writes made while the queue is full are dropped and counted;
+//! recreate the mirror to copy the collection again.
+
+use crate::{InsertRequest, UpdateMetadataRequest};
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use reqwest::{Method, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use surgedb_core::Database;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+/// Changes queued per mirror before further writes are dropped
+const QUEUE_CAPACITY: usize = 10_000;
+/// Vectors sent per batch upsert
+const PUSH_BATCH_SIZE: usize = 500;
+/// Timeout for a single push
+const PUSH_TIMEOUT: Duration = Duration::from_secs(30);
+const INITIAL_RETRY_DELAY: Duration = Duration::from_millis(200);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+#[derive(Deserialize, ToSchema)]
+pub struct MirrorRequest {
+    /// Base URL of the remote SurgeDB API
+    #[schema(example = "https://prod.example.com:3000")]
+    pub url: String,
+    /// API key sent to the remote as `X-API-Key`
+    pub api_key: Option<String>,
+    /// Collection on the remote; defaults to the same name. Must already exist.
+    pub remote_collection: Option<String>,
+    /// Copy the vectors already in the collection before streaming writes (default true)
+    pub backfill: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MirrorState {
+    /// Copying the vectors that existed when the mirror was created
+    Backfilling,
+    /// Pushing writes as they happen
+    Streaming,
+    /// The last push failed and is being retried
+    Retrying,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct Mirror {
+    pub id: String,
+    pub collection: String,
+    pub url: String,
+    pub remote_collection: String,
+    pub state: MirrorState,
+    /// Vectors copied by the backfill
+    pub backfilled: u64,
+    /// Changes delivered to the remote
+    pub pushed: u64,
+    /// Changes waiting to be pushed
+    pub pending: usize,
+    /// Changes dropped because the queue was full
+    pub dropped: u64,
+    /// Changes the remote rejected
+    pub rejected: u64,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A successful write to a mirrored collection
+#[derive(Serialize, Deserialize, Clone)]
+pub enum Change {
+    Upsert(Vec<InsertRequest>),
+    Delete(String),
+    /// Deletes of one request, such as a delete by filter, by ID
+    DeleteMany(Vec<String>),
+    Replace {
+        doc_id: String,
+        vectors: Vec<InsertRequest>,
+    },
+    Metadata {
+        id: String,
+        update: UpdateMetadataRequest,
+    },
+}
+
+impl Change {
+    fn len(&self) -> u64 {
+        match self {
+            Change::Upsert(vectors) => vectors.len() as u64,
+            Change::DeleteMany(ids) => ids.len() as u64,
+            _ => 1,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum PushBody<'a> {
+    Vectors { vectors: &'a [InsertRequest] },
+    Ids { ids: &'a [String] },
+    Metadata(&'a UpdateMetadataRequest),
+}
+
+enum PushError {
+    /// Worth retrying: network errors, timeouts, 429 and 5xx
+    Transient(String),
+    /// The remote refused the change; retrying won't help
+    Rejected(String),
+}
+
+/// A mirror saved by a shutdown, to be resumed
+#[derive(Serialize, Deserialize)]
+struct SavedMirror {
+    mirror: Mirror,
+    api_key: Option<String>,
+    /// Whether the copy had not finished; it starts over when resumed
+    backfill: bool,
+    /// Changes not pushed yet, oldest first
+    unsent: Vec<Change>,
+}
+
+/// State shared between a mirror's worker and the registry
+struct Shared {
+    info: RwLock<Mirror>,
+    api_key: Option<String>,
+    /// Whether the copy is still to finish
+    backfilling: AtomicBool,
+    backfilled: AtomicU64,
+    pushed: AtomicU64,
+    dropped: AtomicU64,
+    rejected: AtomicU64,
+}
+
+impl Shared {
+    fn snapshot(&self) -> Mirror {
+        let mut mirror = self.info.read().clone();
+        mirror.backfilled = self.backfilled.load(Ordering::Relaxed);
+        mirror.pushed = self.pushed.load(Ordering::Relaxed);
+        mirror.dropped = self.dropped.load(Ordering::Relaxed);
+        mirror.rejected = self.rejected.load(Ordering::Relaxed);
+        mirror
+    }
+
+    fn set_state(&self, state: MirrorState, error: Option<String>) {
+        let mut info = self.info.write();
+        info.state = state;
+        if error.is_some() {
+            info.last_error = error;
+        }
+    }
+}
+
+struct Handle {
+    shared: Arc<Shared>,
+    tx: mpsc::Sender<Change>,
+    /// Asks the worker to stop and return the changes it has not pushed
+    stop: oneshot::Sender<()>,
+    task: JoinHandle<Vec<Change>>,
+}
+
+impl Handle {
+    fn snapshot(&self) -> Mirror {
+        let mut mirror = self.shared.snapshot();
+        mirror.pending = self.tx.max_capacity() - self.tx.capacity();
+        mirror
+    }
+}
+
+pub struct MirrorRegistry {
+    mirrors: RwLock<Vec<Handle>>,
+    /// Where mirrors are saved on shutdown
+    path: Option<PathBuf>,
+    client: reqwest::Client,
+    #[cfg(feature = "chaos")]
+    chaos: Option<Arc<crate::chaos::Chaos>>,
+}
+
+impl MirrorRegistry {
+    /// Create a registry that saves its mirrors at `path` on shutdown
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self {
+            mirrors: RwLock::new(Vec::new()),
+            path,
+            client: reqwest::Client::builder()
+                .timeout(PUSH_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            #[cfg(feature = "chaos")]
+            chaos: None,
+        }
+    }
+
+    /// Drop pushes while fault injection asks for replication traffic to be dropped
+    #[cfg(feature = "chaos")]
+    pub fn with_chaos(mut self, chaos: Arc<crate::chaos::Chaos>) -> Self {
+        self.chaos = Some(chaos);
+        self
+    }
+
+    /// Start mirroring `collection`, which must be a collection name, not an alias
+    pub fn start(
+        self: &Arc<Self>,
+        db: Arc<Database>,
+        collection: &str,
+        request: MirrorRequest,
+    ) -> Result<Mirror, String> {
+        let url = Url::parse(&request.url).map_err(|e| format!("Invalid mirror url: {}", e))?;
+        if !matches!(url.scheme(), "http" | "https") || url.cannot_be_a_base() {
+            return Err("Mirror url must be http(s)".to_string());
+        }
+
+        let backfill = request.backfill.unwrap_or(true);
+        let mirror = Mirror {
+            id: format!(
+                "mir_{:x}",
+                Utc::now().timestamp_nanos_opt().unwrap_or_default()
+            ),
+            collection: collection.to_string(),
+            url: request.url,
+            remote_collection: request
+                .remote_collection
+                .unwrap_or_else(|| collection.to_string()),
+            state: if backfill {
+                MirrorState::Backfilling
+            } else {
+                MirrorState::Streaming
+            },
+            backfilled: 0,
+            pushed: 0,
+            pending: 0,
+            dropped: 0,
+            rejected: 0,
+            last_error: None,
+            created_at: Utc::now(),
+        };
+        let mirror = self.spawn(db, mirror, request.api_key, backfill, Vec::new());
+        info!(
+            "Mirroring {} to {} ({})",
+            mirror.collection, mirror.url, mirror.remote_collection
+        );
+        Ok(mirror)
+    }
+
+    /// Restart the mirrors a shutdown saved, then remove the file
+    ///
+    /// Queued changes are pushed right away; copies start over once `db`
+    /// has recovered.
+    pub fn resume(self: &Arc<Self>, db: Arc<Database>) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let Ok(bytes) = std::fs::read(path) else {
+            return;
+        };
+        let _ = std::fs::remove_file(path);
+        let saved: Vec<SavedMirror> = match serde_json::from_slice(&bytes) {
+            Ok(saved) => saved,
+            Err(e) => {
+                warn!("Ignoring unreadable mirrors file: {}", e);
+                return;
+            }
+        };
+        for SavedMirror {
+            mirror,
+            api_key,
+            backfill,
+            unsent,
+        } in saved
+        {
+            info!(
+                "Resuming mirror {} of {} with {} unsent changes",
+                mirror.id,
+                mirror.collection,
+                unsent.len()
+            );
+            self.spawn(db.clone(), mirror, api_key, backfill, unsent);
+        }
+    }
+
+    /// Stop every mirror and save it, with the changes it has not pushed
+    pub async fn shutdown(&self) {
+        let handles = std::mem::take(&mut *self.mirrors.write());
+        let mut saved = Vec::with_capacity(handles.len());
+        for Handle {
+            shared,
+            tx: _,
+            stop,
+            task,
+        } in handles
+        {
+            let _ = stop.send(());
+            let unsent = task.await.unwrap_or_default();
+            saved.push(SavedMirror {
+                mirror: shared.snapshot(),
+                api_key: shared.api_key.clone(),
+                backfill: shared.backfilling.load(Ordering::Relaxed),
+                unsent,
+            });
+        }
+
+        let (Some(path), false) = (&self.path, saved.is_empty()) else {
+            return;
+        };
+        let result = serde_json::to_vec(&saved)
+            .map_err(|e| e.to_string())
+            .and_then(|bytes| std::fs::write(path, bytes).map_err(|e| e.to_string()));
+        match result {
+            Ok(()) => info!("Saved {} mirrors", saved.len()),
+            Err(e) => warn!("Failed to save mirrors: {}", e),
+        }
+    }
+
+    /// Register `mirror` and start its worker with `unsent` queued first
+    fn spawn(
+        self: &Arc<Self>,
+        db: Arc<Database>,
+        mirror: Mirror,
+        api_key: Option<String>,
+        backfill: bool,
+        unsent: Vec<Change>,
+    ) -> Mirror {
+        let shared = Arc::new(Shared {
+            backfilling: AtomicBool::new(backfill),
+            backfilled: AtomicU64::new(mirror.backfilled),
+            pushed: AtomicU64::new(mirror.pushed),
+            dropped: AtomicU64::new(mirror.dropped),
+            rejected: AtomicU64::new(mirror.rejected),
+            info: RwLock::new(mirror),
+            api_key,
+        });
+
+        let (tx, mut rx) = mpsc::channel(QUEUE_CAPACITY);
+        for change in unsent {
+            if tx.try_send(change).is_err() {
+                shared.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        // The backfill waits until the mirror is registered, so every write
+        // it doesn't see is queued
+        let (registered_tx, registered) = oneshot::channel();
+        let (stop, stopped) = oneshot::channel::<()>();
+        let registry = self.clone();
+        let worker = shared.clone();
+        let task = tokio::spawn(async move {
+            let mut unsent = Vec::new();
+            if registered.await.is_err() {
+                return unsent;
+            }
+            tokio::select! {
+                _ = stopped => {}
+                _ = registry.run(&worker, db, backfill, &mut rx, &mut unsent) => {}
+            }
+            // Stopped: hand back everything not pushed, oldest first
+            rx.close();
+            while let Ok(change) = rx.try_recv() {
+                unsent.push(change);
+            }
+            unsent
+        });
+
+        let handle = Handle {
+            shared,
+            tx,
+            stop,
+            task,
+        };
+        let mirror = handle.snapshot();
+        self.mirrors.write().push(handle);
+        let _ = registered_tx.send(());
+        mirror
+    }
+
+    pub fn list(&self, collection: &str) -> Vec<Mirror> {
+        self.mirrors
+            .read()
+            .iter()
+            .filter(|h| h.shared.info.read().collection == collection)
+            .map(Handle::snapshot)
+            .collect()
+    }
+
+    /// Stop one mirror; returns false if it did not exist
+    pub fn remove(&self, collection: &str, id: &str) -> bool {
+        let mut mirrors = self.mirrors.write();
+        let before = mirrors.len();
+        mirrors.retain(|h| {
+            let info = h.shared.info.read();
+            let keep = !(info.collection == collection && info.id == id);
+            if !keep {
+                h.task.abort();
+            }
+            keep
+        });
+        mirrors.len() != before
+    }
+
+    /// Keep the mirrors of a renamed collection running under its new name
+    pub fn rename_collection(&self, from: &str, to: &str) {
+        for handle in self.mirrors.read().iter() {
+            let mut info = handle.shared.info.write();
+            if info.collection == from {
+                info.collection = to.to_string();
+            }
+        }
+    }
+
+    /// Stop all mirrors of a deleted collection
+    pub fn remove_collection(&self, collection: &str) {
+        self.mirrors.write().retain(|h| {
+            let keep = h.shared.info.read().collection != collection;
+            if !keep {
+                h.task.abort();
+            }
+            keep
+        });
+    }
+
+    /// Whether writes to `collection` need to be published
+    pub fn is_mirrored(&self, collection: &str) -> bool {
+        self.mirrors
+            .read()
+            .iter()
+            .any(|h| h.shared.info.read().collection == collection)
+    }
+
+    /// Queue a successful write for every mirror of `collection`
+    pub fn publish(&self, collection: &str, change: Change) {
+        let mirrors = self.mirrors.read();
+        let targets: Vec<&Handle> = mirrors
+            .iter()
+            .filter(|h| h.shared.info.read().collection == collection)
+            .collect();
+        let Some((last, rest)) = targets.split_last() else {
+            return;
+        };
+        for handle in rest {
+            Self::enqueue(handle, change.clone());
+        }
+        Self::enqueue(last, change);
+    }
+
+    fn enqueue(handle: &Handle, change: Change) {
+        if handle.tx.try_send(change).is_err() {
+            let dropped = handle.shared.dropped.fetch_add(1, Ordering::Relaxed);
+            if dropped == 0 {
+                warn!(
+                    "Mirror {} queue is full; dropping writes",
+                    handle.shared.info.read().id
+                );
+            }
+        }
+    }
+
+    /// Push changes until the queue closes
+    ///
+    /// The change being pushed, and one taken off the queue early, are kept
+    /// in `unsent` until they are done, so a stopped worker can save them.
+    async fn run(
+        &self,
+        shared: &Shared,
+        db: Arc<Database>,
+        backfill: bool,
+        rx: &mut mpsc::Receiver<Change>,
+        unsent: &mut Vec<Change>,
+    ) {
+        // Resumed mirrors may start while the database is still recovering
+        if backfill && crate::wait_for_recovery(&db).await {
+            self.backfill(shared, &db).await;
+            shared.backfilling.store(false, Ordering::Relaxed);
+            shared.set_state(MirrorState::Streaming, None);
+        }
+
+        loop {
+            let change = match unsent.pop() {
+                Some(change) => change,
+                None => match rx.recv().await {
+                    Some(change) => change,
+                    None => return,
+                },
+            };
+            // Fold queued upserts into one request
+            let mut carried = None;
+            let change = match change {
+                Change::Upsert(mut vectors) => {
+                    while vectors.len() < PUSH_BATCH_SIZE {
+                        match rx.try_recv() {
+                            Ok(Change::Upsert(more)) => vectors.extend(more),
+                            Ok(other) => {
+                                carried = Some(other);
+                                break;
+                            }
+                            Err(_) => break,
+                        }
+                    }
+                    Change::Upsert(vectors)
+                }
+                other => other,
+            };
+            unsent.push(change);
+            unsent.extend(carried);
+            let delivered = self.deliver(shared, &unsent[0]).await;
+            let change = unsent.remove(0);
+            if delivered {
+                shared.pushed.fetch_add(change.len(), Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Copy the vectors currently in the collection
+    async fn backfill(&self, shared: &Shared, db: &Arc<Database>) {
+        let name = shared.info.read().collection.clone();
+        let source = db.clone();
+        let listed = tokio::task::spawn_blocking(move || {
+            let collection = source.get_collection(&name).ok()?;
+            let ids: Vec<String> = collection
+                .list(0, usize::MAX)
+                .into_iter()
+                .map(|(id, _)| id.to_string())
+                .collect();
+            Some((collection, ids))
+        })
+        .await;
+        let Ok(Some((collection, ids))) = listed else {
+            return;
+        };
+
+        for chunk in ids.chunks(PUSH_BATCH_SIZE) {
+            let collection = collection.clone();
+            let chunk = chunk.to_vec();
+            let vectors = tokio::task::spawn_blocking(move || {
+                chunk
+                    .into_iter()
+                    .filter_map(|id| {
+                        // Vectors deleted since the listing are skipped
+                        let (vector, metadata) = collection.get(&id).ok()??;
+                        let sparse = collection.get_sparse(&id);
+                        let named = collection.get_named_vectors(&id);
+                        Some(InsertRequest {
+                            vectors: (!named.is_empty()).then(|| named.into_iter().collect()),
+                            id,
+                            vector: vector.into_iter().map(f64::from).collect(),
+                            metadata,
+                            sparse,
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .await
+            .unwrap_or_default();
+            if vectors.is_empty() {
+                continue;
+            }
+
+            let change = Change::Upsert(vectors);
+            if self.deliver(shared, &change).await {
+                shared.backfilled.fetch_add(change.len(), Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Push `change`, retrying transient failures; returns whether it was applied
+    async fn deliver(&self, shared: &Shared, change: &Change) -> bool {
+        let mut delay = INITIAL_RETRY_DELAY;
+        loop {
+            match self.push(shared, change).await {
+                Ok(()) => {
+                    let mut info = shared.info.write();
+                    if info.state == MirrorState::Retrying {
+                        info.state = if shared.backfilling.load(Ordering::Relaxed) {
+                            MirrorState::Backfilling
+                        } else {
+                            MirrorState::Streaming
+                        };
+                    }
+                    return true;
+                }
+                Err(PushError::Rejected(error)) => {
+                    warn!(
+                        "Mirror {} change rejected: {}",
+                        shared.info.read().id,
+                        error
+                    );
+                    shared.rejected.fetch_add(1, Ordering::Relaxed);
+                    shared.info.write().last_error = Some(error);
+                    return false;
+                }
+                Err(PushError::Transient(error)) => {
+                    let id = shared.info.read().id.clone();
+                    warn!(
+                        "Mirror {} push failed, retrying in {:?}: {}",
+                        id, delay, error
+                    );
+                    shared.set_state(MirrorState::Retrying, Some(error));
+                    tokio::time::sleep(delay).await;
+                    delay = (delay * 2).min(MAX_RETRY_DELAY);
+                }
+            }
+        }
+    }
+
+    async fn push(&self, shared: &Shared, change: &Change) -> Result<(), PushError> {
+        #[cfg(feature = "chaos")]
+        if self.chaos.as_ref().is_some_and(|c| c.drop_replication()) {
+            return Err(PushError::Transient(
+                "Injected fault: replication dropped".to_string(),
+            ));
+        }
+
+        let (base, collection) = {
+            let info = shared.info.read();
+            (info.url.clone(), info.remote_collection.clone())
+        };
+        let mut url = Url::parse(&base).map_err(|e| PushError::Rejected(e.to_string()))?;
+        let (method, segments, body) = match change {
+            Change::Upsert(vectors) => (
+                Method::POST,
+                vec!["vectors", "batch"],
+                Some(PushBody::Vectors { vectors }),
+            ),
+            Change::Delete(id) => (Method::DELETE, vec!["vectors", id.as_str()], None),
+            Change::DeleteMany(ids) => (
+                Method::POST,
+                vec!["vectors", "delete"],
+                Some(PushBody::Ids { ids }),
+            ),
+            Change::Metadata { id, update } => (
+                Method::PATCH,
+                vec!["vectors", id.as_str(), "metadata"],
+                Some(PushBody::Metadata(update)),
+            ),
+            Change::Replace { doc_id, vectors } => (
+                Method::POST,
+                vec!["documents", doc_id.as_str(), "replace"],
+                Some(PushBody::Vectors { vectors }),
+            ),
+        };
+        if let Ok(mut path) = url.path_segments_mut() {
+            path.pop_if_empty()
+                .push("collections")
+                .push(&collection)
+                .extend(segments);
+        }
+
+        let mut request = self.client.request(method, url);
+        if let Some(key) = &shared.api_key {
+            request = request.header("x-api-key", key);
+        }
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| PushError::Transient(e.to_string()))?;
+        let status = response.status();
+        // Deleting what the remote never had still leaves both sides in agreement
+        if status.is_success()
+            || (status == StatusCode::NOT_FOUND && matches!(change, Change::Delete(_)))
+        {
+            return Ok(());
+        }
+        let error = format!(
+            "Remote returned {}: {}",
+            status,
+            response.text().await.unwrap_or_default()
+        );
+        if status.is_server_error()
+            || status == StatusCode::TOO_MANY_REQUESTS
+            || status == StatusCode::REQUEST_TIMEOUT
+        {
+            Err(PushError::Transient(error))
+        } else {
+            Err(PushError::Rejected(error))
+        }
+    }
+}
@@ -0,0 +1,51 @@
+//! Fixed-window request rate limiting
+//!
+//! Used to throttle unauthenticated traffic (e.g. public search) per client.
+
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+/// Prune expired windows once the table grows past this many keys
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Simple fixed-window rate limiter keyed by client identity
+pub struct RateLimiter<K> {
+    limit: u32,
+    window: Duration,
+    windows: Mutex<HashMap<K, (Instant, u32)>>,
+}
+
+impl<K: Hash + Eq + Clone> RateLimiter<K> {
+    /// Allow `limit` requests per `window` for each key
+    pub fn new(limit: u32, window: Duration) -> Self {
+        Self {
+            limit,
+            window,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record a request for `key`; returns false if the key is over its limit
+    pub fn check(&self, key: &K) -> bool {
+        let now = Instant::now();
+        let mut windows = self.windows.lock();
+
+        if windows.len() > PRUNE_THRESHOLD {
+            let window = self.window;
+            windows.retain(|_, (start, _)| now.duration_since(*start) < window);
+        }
+
+        let entry = windows.entry(key.clone()).or_insert((now, 0));
+        if now.duration_since(entry.0) >= self.window {
+            *entry = (now, 0);
+        }
+
+        if entry.1 >= self.limit {
+            return false;
+        }
+        entry.1 += 1;
+        true
+    }
+}
@@ -0,0 +1,121 @@
+//! Background graph repair
+//!
+//! Deleting a vector leaves the HNSW links pointing at it in place until the
+//! collection is compacted, and a crash can leave broken links behind. A
+//! background task walks every collection periodically and relinks a bounded
+//! number of damaged nodes per pass, those in the most searched regions
+//! first, so search quality recovers long before a full reindex. The backlog
+//! left after each pass is exported on `/metrics`.
+
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::sync::Arc;
+use surgedb_core::{Database, GraphRepair};
+use tracing::info;
+
+/// How often graphs are repaired and how much per pass
+#[derive(Clone, Debug)]
+pub struct RepairConfig {
+    /// Seconds between passes; graphs are not repaired if zero
+    pub interval_secs: u64,
+    /// Nodes relinked per collection and pass
+    pub batch: usize,
+}
+
+impl RepairConfig {
+    /// Settings from the variables `var` looks up
+    pub fn from_vars(var: impl Fn(&str) -> Result<String, std::env::VarError>) -> Self {
+        Self {
+            interval_secs: var("GRAPH_REPAIR_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(30),
+            batch: var("GRAPH_REPAIR_BATCH")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(1_000),
+        }
+    }
+}
+
+/// Repair state of one collection
+#[derive(Clone, Copy, Debug, Default)]
+struct RepairState {
+    /// Damaged nodes left after the last pass
+    backlog: usize,
+    /// Nodes relinked since the server started
+    relinked: u64,
+}
+
+/// Results of the repair passes, by collection
+#[derive(Default)]
+pub struct GraphRepairs {
+    collections: RwLock<HashMap<String, RepairState>>,
+}
+
+impl GraphRepairs {
+    /// Relink up to `batch` nodes of every collection
+    ///
+    /// Skipped while the database is still recovering.
+    pub async fn run(&self, db: &Arc<Database>, batch: usize) {
+        if crate::recovery_error(db, false).is_some() {
+            return;
+        }
+        let names = db.list_collections();
+        let db_clone = db.clone();
+        let passes = tokio::task::spawn_blocking(move || {
+            names
+                .into_iter()
+                .filter_map(|name| {
+                    let collection = db_clone.get_collection(&name).ok()?;
+                    Some((name, collection.repair_graph(batch)))
+                })
+                .collect::<Vec<(String, GraphRepair)>>()
+        })
+        .await
+        .unwrap_or_default();
+
+        let mut collections = self.collections.write();
+        collections.retain(|name, _| passes.iter().any(|(n, _)| n == name));
+        for (name, pass) in passes {
+            if pass.relinked > 0 {
+                info!(
+                    "Relinked {} graph nodes of {}, {} left",
+                    pass.relinked, name, pass.backlog
+                );
+            }
+            let state = collections.entry(name).or_default();
+            state.backlog = pass.backlog;
+            state.relinked += pass.relinked as u64;
+        }
+    }
+
+    /// Backlog and relinked nodes of each collection, in OpenMetrics format
+    pub fn render(&self) -> String {
+        const BACKLOG: &str = "surgedb_graph_repair_backlog";
+        const RELINKED: &str = "surgedb_graph_repairs";
+        let collections = self.collections.read();
+        let mut names: Vec<&String> = collections.keys().collect();
+        names.sort();
+
+        let mut out = format!(
+            "# TYPE {BACKLOG} gauge\n# HELP {BACKLOG} HNSW nodes still linking to deleted or missing nodes after the last repair pass.\n"
+        );
+        for name in &names {
+            out.push_str(&format!(
+                "{BACKLOG}{{collection=\"{}\"}} {}\n",
+                name, collections[*name].backlog
+            ));
+        }
+        out.push_str(&format!(
+            "# TYPE {RELINKED} counter\n# HELP {RELINKED} HNSW nodes relinked by background repair.\n"
+        ));
+        for name in &names {
+            out.push_str(&format!(
+                "{RELINKED}_total{{collection=\"{}\"}} {}\n",
+                name, collections[*name].relinked
+            ));
+        }
+        out
+    }
+}
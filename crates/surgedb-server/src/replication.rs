@@ -0,0 +1,564 @@
+//! Leader/follower replication
+//!
+//! Every server can lead: through `/replication/collections` (admin key
+//! required) a follower reads the leader's catalog, copies each collection
+//! from a snapshot, then tails the collection's write-ahead log and writes
+//! the entries to its own copy. A server started with `REPLICA_OF` follows
+//! that leader as a hot standby. It serves reads and rejects writes.
+//!
+//! The follower polls the leader every `REPLICATION_POLL_INTERVAL_MS`, backing
+//! off while the leader can't be reached. A collection is copied afresh when
+//! it is new, when its configuration changed, when the leader recreated it,
+//! or when a checkpoint cleared log entries the follower had not read yet.
+//! Collections and aliases the leader no longer has are dropped; system
+//! collections are left alone on both sides. How far each collection has
+//! been replayed is saved to `replication.json` in the data directory, so a
+//! restarted follower carries on where it stopped. Entries applied just
+//! before a crash may be applied again, which leaves the same records.
+
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use reqwest::{StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use surgedb_core::{Database, LogPosition, WalEntry};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+use utoipa::{IntoParams, ToSchema};
+
+/// Most log entries returned per request
+pub const MAX_LOG_PAGE: usize = 1_000;
+/// Timeout of catalog and log requests
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Timeout of a snapshot download
+const SNAPSHOT_TIMEOUT: Duration = Duration::from_secs(600);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// Response headers of a snapshot download, with the log position it covers
+pub const LOG_ID_HEADER: &str = "x-log-id";
+pub const LOG_SEQ_HEADER: &str = "x-log-seq";
+
+/// A collection as the leader describes it to followers
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct CatalogEntry {
+    pub name: String,
+    #[schema(value_type = Object)]
+    pub config: serde_json::Value,
+    /// Where the collection's write log stands
+    #[schema(value_type = Object)]
+    pub position: LogPosition,
+}
+
+/// The collections and aliases a follower mirrors
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct Catalog {
+    pub collections: Vec<CatalogEntry>,
+    /// Alias -> collection
+    pub aliases: HashMap<String, String>,
+}
+
+/// Where a follower reads a collection's log from
+#[derive(Deserialize, IntoParams)]
+pub struct LogQuery {
+    /// Log the follower has been replaying
+    pub log_id: u64,
+    /// Sequence number of the last entry it applied
+    pub after: u64,
+    /// Most entries to return (default and maximum 1000)
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct LoggedEntry {
+    pub seq: u64,
+    pub entry: WalEntry,
+}
+
+/// Log entries after the requested position, oldest first
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct LogPage {
+    #[schema(value_type = Vec<Object>)]
+    pub entries: Vec<LoggedEntry>,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplicationRole {
+    Leader,
+    Follower,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FollowerState {
+    /// Not synced with the leader yet
+    Connecting,
+    /// Copying a collection from a snapshot
+    Copying,
+    /// Tailing the leader's logs
+    Streaming,
+    /// The last sync failed and is being retried
+    Retrying,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CollectionReplication {
+    pub name: String,
+    /// Log sequence number the collection is at; on a follower, the last
+    /// entry of the leader's log it applied
+    pub seq: u64,
+    /// Leader's sequence number when last polled (followers only)
+    pub leader_seq: Option<u64>,
+    /// Entries the follower is behind by (followers only)
+    pub lag: Option<u64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ReplicationStatus {
+    pub role: ReplicationRole,
+    /// URL of the leader (followers only)
+    pub leader: Option<String>,
+    /// Followers only
+    pub state: Option<FollowerState>,
+    /// When the follower last caught up with the leader
+    pub last_sync: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub collections: Vec<CollectionReplication>,
+}
+
+/// Collections and aliases of `db` to replicate, with their log positions
+pub fn catalog(db: &Database) -> Catalog {
+    let collections = db
+        .list_collections()
+        .into_iter()
+        .filter_map(|name| {
+            let collection = db.get_collection(&name).ok()?;
+            Some(CatalogEntry {
+                config: serde_json::to_value(collection.config()).ok()?,
+                position: collection.log_position().ok()?,
+                name,
+            })
+        })
+        .collect::<Vec<_>>();
+    let names: HashSet<&str> = collections.iter().map(|c| c.name.as_str()).collect();
+    let aliases = db
+        .list_aliases()
+        .into_iter()
+        .filter(|(_, target)| names.contains(target.as_str()))
+        .collect();
+    Catalog {
+        collections,
+        aliases,
+    }
+}
+
+/// Progress of a follower, as reported by its status
+struct Progress {
+    state: FollowerState,
+    last_sync: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+    /// Leader's log sequence numbers when last polled
+    leader_seqs: HashMap<String, u64>,
+}
+
+struct Follower {
+    url: String,
+    api_key: Option<String>,
+    poll_interval: Duration,
+    /// Where snapshots are downloaded to while they are restored
+    snapshot_dir: PathBuf,
+    /// Where positions are saved
+    path: Option<PathBuf>,
+    /// Position in the leader's log each collection has been replayed to
+    positions: RwLock<HashMap<String, LogPosition>>,
+    progress: RwLock<Progress>,
+    client: reqwest::Client,
+}
+
+pub struct Replication {
+    follower: Option<Arc<Follower>>,
+    #[cfg(feature = "chaos")]
+    chaos: Option<Arc<crate::chaos::Chaos>>,
+}
+
+impl Replication {
+    /// Serve followers without following anyone
+    pub fn leader() -> Self {
+        Self {
+            follower: None,
+            #[cfg(feature = "chaos")]
+            chaos: None,
+        }
+    }
+
+    /// Follow the server at `url`, loading positions saved at `path`
+    pub fn follower(
+        url: String,
+        api_key: Option<String>,
+        poll_interval: Duration,
+        snapshot_dir: PathBuf,
+        path: Option<PathBuf>,
+    ) -> Self {
+        let positions = path
+            .as_ref()
+            .and_then(|p| std::fs::read(p).ok())
+            .and_then(|bytes| match serde_json::from_slice(&bytes) {
+                Ok(positions) => Some(positions),
+                Err(e) => {
+                    warn!("Ignoring unreadable replication file: {}", e);
+                    None
+                }
+            })
+            .unwrap_or_default();
+        Self {
+            follower: Some(Arc::new(Follower {
+                url,
+                api_key,
+                poll_interval,
+                snapshot_dir,
+                path,
+                positions: RwLock::new(positions),
+                progress: RwLock::new(Progress {
+                    state: FollowerState::Connecting,
+                    last_sync: None,
+                    last_error: None,
+                    leader_seqs: HashMap::new(),
+                }),
+                client: reqwest::Client::new(),
+            })),
+            #[cfg(feature = "chaos")]
+            chaos: None,
+        }
+    }
+
+    /// Drop polls of the leader while fault injection asks for replication
+    /// traffic to be dropped
+    #[cfg(feature = "chaos")]
+    pub fn with_chaos(mut self, chaos: Arc<crate::chaos::Chaos>) -> Self {
+        self.chaos = Some(chaos);
+        self
+    }
+
+    /// URL of the leader being followed; a follower rejects writes
+    pub fn leader_url(&self) -> Option<&str> {
+        self.follower.as_ref().map(|f| f.url.as_str())
+    }
+
+    /// Start following the leader, once `db` has recovered; `None` on a leader
+    pub fn start(&self, db: Arc<Database>) -> Option<JoinHandle<()>> {
+        let follower = self.follower.clone()?;
+        #[cfg(feature = "chaos")]
+        let chaos = self.chaos.clone();
+        Some(tokio::spawn(async move {
+            if !crate::wait_for_recovery(&db).await {
+                warn!("Not following {}: database recovery failed", follower.url);
+                return;
+            }
+            info!("Following {}", follower.url);
+            let mut retry_delay = follower.poll_interval;
+            loop {
+                #[cfg(feature = "chaos")]
+                let result = if chaos.as_ref().is_some_and(|c| c.drop_replication()) {
+                    Err("Injected fault: replication dropped".to_string())
+                } else {
+                    follower.sync(&db).await
+                };
+                #[cfg(not(feature = "chaos"))]
+                let result = follower.sync(&db).await;
+
+                let delay = match result {
+                    Ok(()) => {
+                        let mut progress = follower.progress.write();
+                        progress.state = FollowerState::Streaming;
+                        progress.last_sync = Some(Utc::now());
+                        retry_delay = follower.poll_interval;
+                        follower.poll_interval
+                    }
+                    Err(error) => {
+                        warn!(
+                            "Replication from {} failed, retrying in {:?}: {}",
+                            follower.url, retry_delay, error
+                        );
+                        let mut progress = follower.progress.write();
+                        progress.state = FollowerState::Retrying;
+                        progress.last_error = Some(error);
+                        let delay = retry_delay;
+                        retry_delay =
+                            (retry_delay * 2).min(MAX_RETRY_DELAY.max(follower.poll_interval));
+                        delay
+                    }
+                };
+                tokio::time::sleep(delay).await;
+            }
+        }))
+    }
+
+    pub fn status(&self, db: &Database) -> ReplicationStatus {
+        let Some(follower) = &self.follower else {
+            return ReplicationStatus {
+                role: ReplicationRole::Leader,
+                leader: None,
+                state: None,
+                last_sync: None,
+                last_error: None,
+                collections: catalog(db)
+                    .collections
+                    .into_iter()
+                    .map(|entry| CollectionReplication {
+                        name: entry.name,
+                        seq: entry.position.seq,
+                        leader_seq: None,
+                        lag: None,
+                    })
+                    .collect(),
+            };
+        };
+        let progress = follower.progress.read();
+        let positions = follower.positions.read();
+        let mut collections: Vec<_> = positions
+            .iter()
+            .map(|(name, position)| {
+                let leader_seq = progress.leader_seqs.get(name).copied();
+                CollectionReplication {
+                    name: name.clone(),
+                    seq: position.seq,
+                    leader_seq,
+                    lag: leader_seq.map(|seq| seq.saturating_sub(position.seq)),
+                }
+            })
+            .collect();
+        collections.sort_by(|a, b| a.name.cmp(&b.name));
+        ReplicationStatus {
+            role: ReplicationRole::Follower,
+            leader: Some(follower.url.clone()),
+            state: Some(progress.state),
+            last_sync: progress.last_sync,
+            last_error: progress.last_error.clone(),
+            collections,
+        }
+    }
+}
+
+impl Follower {
+    /// Bring every collection and alias in line with the leader
+    async fn sync(&self, db: &Arc<Database>) -> Result<(), String> {
+        let response = self
+            .get(&["replication", "collections"], &[], REQUEST_TIMEOUT)
+            .await?;
+        let catalog: Catalog = success(response)
+            .await?
+            .json()
+            .await
+            .map_err(|e| e.to_string())?;
+        self.progress.write().leader_seqs = catalog
+            .collections
+            .iter()
+            .map(|entry| (entry.name.clone(), entry.position.seq))
+            .collect();
+
+        for (alias, target) in db.list_aliases() {
+            if catalog.aliases.get(&alias) != Some(&target) {
+                db.delete_alias(&alias).map_err(|e| e.to_string())?;
+            }
+        }
+        let leading: HashSet<&str> = catalog
+            .collections
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect();
+        for name in db.list_collections() {
+            if !leading.contains(name.as_str()) {
+                info!("Dropping {}, which the leader no longer has", name);
+                self.forget(&name)?;
+                db.delete_collection(&name).map_err(|e| e.to_string())?;
+            }
+        }
+
+        // One collection failing doesn't hold up the others
+        let mut result = Ok(());
+        for entry in &catalog.collections {
+            if let Err(e) = self.follow(db, entry).await {
+                result = Err(format!("{}: {}", entry.name, e));
+            }
+        }
+
+        for (alias, target) in &catalog.aliases {
+            if db.resolve_name(alias) != *target {
+                if let Err(e) = db.set_alias(alias, target) {
+                    result = Err(format!("alias {}: {}", alias, e));
+                }
+            }
+        }
+        result
+    }
+
+    /// Replay the leader's log of one collection, copying it first if needed
+    async fn follow(&self, db: &Arc<Database>, entry: &CatalogEntry) -> Result<(), String> {
+        let name = &entry.name;
+        let saved = self.positions.read().get(name).copied();
+        let current = saved.filter(|position| {
+            position.log_id == entry.position.log_id
+                && db.list_collections().contains(name)
+                && db.get_collection(name).is_ok_and(|c| {
+                    serde_json::to_value(c.config()).ok().as_ref() == Some(&entry.config)
+                })
+        });
+        let mut position = match current {
+            Some(position) => position,
+            None => self.copy(db, name).await?,
+        };
+
+        while position.seq < entry.position.seq {
+            let Some(entries) = self.log_page(name, position).await? else {
+                position = self.copy(db, name).await?;
+                continue;
+            };
+            let Some(last) = entries.last().map(|e| e.seq) else {
+                break;
+            };
+            let collection = db.get_collection(name).map_err(|e| e.to_string())?;
+            tokio::task::spawn_blocking(move || {
+                collection.apply_log(entries.into_iter().map(|e| e.entry).collect())
+            })
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| e.to_string())?;
+            position.seq = last;
+            self.remember(name, position)?;
+        }
+        Ok(())
+    }
+
+    /// Replace the local copy of `name` with a snapshot of the leader's
+    async fn copy(&self, db: &Arc<Database>, name: &str) -> Result<LogPosition, String> {
+        info!("Copying {} from {}", name, self.url);
+        self.progress.write().state = FollowerState::Copying;
+        self.forget(name)?;
+
+        let response = self
+            .get(
+                &["replication", "collections", name, "snapshot"],
+                &[],
+                SNAPSHOT_TIMEOUT,
+            )
+            .await?;
+        let response = success(response).await?;
+        let header = |header: &str| -> Result<u64, String> {
+            response
+                .headers()
+                .get(header)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse().ok())
+                .ok_or_else(|| format!("Snapshot response is missing {}", header))
+        };
+        let position = LogPosition {
+            log_id: header(LOG_ID_HEADER)?,
+            seq: header(LOG_SEQ_HEADER)?,
+        };
+        let bytes = response.bytes().await.map_err(|e| e.to_string())?;
+
+        let db = db.clone();
+        let name_owned = name.to_string();
+        let file = self.snapshot_dir.join(format!(".replica-{}.snap", name));
+        tokio::task::spawn_blocking(move || {
+            std::fs::create_dir_all(file.parent().unwrap_or(&file))?;
+            std::fs::write(&file, &bytes)?;
+            if db.list_collections().contains(&name_owned) {
+                db.delete_collection(&name_owned)?;
+            }
+            let restored = db.restore(&name_owned, &file);
+            let _ = std::fs::remove_file(&file);
+            restored
+        })
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+
+        self.remember(name, position)?;
+        Ok(position)
+    }
+
+    /// Entries after `position`, or `None` if the leader no longer has them
+    async fn log_page(
+        &self,
+        name: &str,
+        position: LogPosition,
+    ) -> Result<Option<Vec<LoggedEntry>>, String> {
+        let query = [
+            ("log_id", position.log_id.to_string()),
+            ("after", position.seq.to_string()),
+        ];
+        let response = self
+            .get(
+                &["replication", "collections", name, "log"],
+                &query,
+                REQUEST_TIMEOUT,
+            )
+            .await?;
+        if response.status() == StatusCode::GONE {
+            return Ok(None);
+        }
+        let page: LogPage = success(response)
+            .await?
+            .json()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(Some(page.entries))
+    }
+
+    /// GET `segments` under the leader's URL
+    async fn get(
+        &self,
+        segments: &[&str],
+        query: &[(&str, String)],
+        timeout: Duration,
+    ) -> Result<reqwest::Response, String> {
+        let mut url = Url::parse(&self.url).map_err(|e| format!("Invalid REPLICA_OF: {}", e))?;
+        url.path_segments_mut()
+            .map_err(|_| "Invalid REPLICA_OF: not a base URL".to_string())?
+            .pop_if_empty()
+            .extend(segments);
+        let mut request = self.client.get(url).query(query).timeout(timeout);
+        if let Some(key) = &self.api_key {
+            request = request.header("x-api-key", key);
+        }
+        request.send().await.map_err(|e| e.to_string())
+    }
+
+    fn remember(&self, name: &str, position: LogPosition) -> Result<(), String> {
+        self.positions.write().insert(name.to_string(), position);
+        self.save()
+    }
+
+    fn forget(&self, name: &str) -> Result<(), String> {
+        if self.positions.write().remove(name).is_some() {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let bytes = serde_json::to_vec(&*self.positions.read()).map_err(|e| e.to_string())?;
+        std::fs::write(path, bytes)
+            .map_err(|e| format!("Failed to save replication positions: {}", e))
+    }
+}
+
+/// `response`, or the leader's error if it didn't succeed
+async fn success(response: reqwest::Response) -> Result<reqwest::Response, String> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    Err(format!(
+        "Leader returned {}: {}",
+        status,
+        response.text().await.unwrap_or_default()
+    ))
+}
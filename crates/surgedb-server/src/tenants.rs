@@ -0,0 +1,356 @@
+//! Tenants: API keys scoped to a namespace of collections
+//!
+//! A tenant owns the collections named `<namespace>.<name>` and can't see or
+//! touch any other. Admins create tenants with optional quotas on their
+//! collections and vectors, and issue and revoke any number of keys per
+//! tenant. A key is shown once when issued; only its SHA-256 hash is saved,
+//! with the tenants, to `tenants.json` in the data directory.
+
+use axum::extract::Request;
+use axum::http::Method;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use parking_lot::RwLock;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::PathBuf;
+use surgedb_core::Database;
+use tracing::warn;
+use utoipa::ToSchema;
+
+use crate::KeyRole;
+
+/// Longest namespace or key name, in bytes
+const MAX_NAME_LEN: usize = 32;
+
+/// Limits on what a tenant's namespace holds; unset means unlimited
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, ToSchema)]
+pub struct TenantQuotas {
+    /// Max vectors across all of the tenant's collections
+    pub max_vectors: Option<usize>,
+    /// Max collections in the namespace
+    pub max_collections: Option<usize>,
+}
+
+/// A tenant as returned by the admin API
+#[derive(Serialize, Clone, Debug, ToSchema)]
+pub struct TenantInfo {
+    pub namespace: String,
+    #[serde(flatten)]
+    pub quotas: TenantQuotas,
+    /// Names of the tenant's keys
+    pub keys: Vec<String>,
+    /// Names of the keys with the `read` role
+    pub read_keys: Vec<String>,
+    #[serde(flatten)]
+    pub usage: TenantUsage,
+}
+
+/// What a tenant's namespace holds
+#[derive(Serialize, Clone, Copy, Debug, Default, ToSchema)]
+pub struct TenantUsage {
+    pub collections: usize,
+    pub vectors: usize,
+}
+
+/// A tenant key resolved from a request
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TenantKey {
+    pub namespace: String,
+    /// Name of the key within its tenant
+    pub name: String,
+    pub role: KeyRole,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+struct Tenant {
+    #[serde(default)]
+    quotas: TenantQuotas,
+    /// SHA-256 of each key, hex-encoded, by key name
+    #[serde(default)]
+    keys: BTreeMap<String, String>,
+    /// Names of the keys with the `read` role; the others can write
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    read_keys: BTreeSet<String>,
+}
+
+impl Tenant {
+    fn role(&self, key_name: &str) -> KeyRole {
+        if self.read_keys.contains(key_name) {
+            KeyRole::Read
+        } else {
+            KeyRole::Write
+        }
+    }
+}
+
+#[derive(Default)]
+struct Inner {
+    tenants: BTreeMap<String, Tenant>,
+    /// The tenant and name of each key, by hash
+    by_hash: HashMap<String, TenantKey>,
+}
+
+impl Inner {
+    fn new(tenants: BTreeMap<String, Tenant>) -> Self {
+        let by_hash = tenants
+            .iter()
+            .flat_map(|(namespace, tenant)| {
+                tenant.keys.iter().map(|(name, hash)| {
+                    (
+                        hash.clone(),
+                        TenantKey {
+                            namespace: namespace.clone(),
+                            name: name.clone(),
+                            role: tenant.role(name),
+                        },
+                    )
+                })
+            })
+            .collect();
+        Self { tenants, by_hash }
+    }
+}
+
+/// Shared registry of tenants and their keys
+pub struct TenantRegistry {
+    inner: RwLock<Inner>,
+    path: Option<PathBuf>,
+}
+
+impl TenantRegistry {
+    /// Create a registry, loading the tenants saved at `path`
+    pub fn new(path: Option<PathBuf>) -> Self {
+        let tenants = path
+            .as_ref()
+            .and_then(|p| std::fs::read(p).ok())
+            .and_then(|bytes| match serde_json::from_slice(&bytes) {
+                Ok(tenants) => Some(tenants),
+                Err(e) => {
+                    warn!("Ignoring unreadable tenants file: {}", e);
+                    None
+                }
+            })
+            .unwrap_or_default();
+        Self {
+            inner: RwLock::new(Inner::new(tenants)),
+            path,
+        }
+    }
+
+    /// The tenant key `key` is, if any
+    pub fn authenticate(&self, key: &str) -> Option<TenantKey> {
+        self.inner.read().by_hash.get(&hash(key)).cloned()
+    }
+
+    pub fn contains(&self, namespace: &str) -> bool {
+        self.inner.read().tenants.contains_key(namespace)
+    }
+
+    pub fn quotas(&self, namespace: &str) -> Option<TenantQuotas> {
+        self.inner
+            .read()
+            .tenants
+            .get(namespace)
+            .map(|tenant| tenant.quotas)
+    }
+
+    /// Whether `key_name` names a tenant key, as `<namespace>.<name>`
+    pub fn has_key(&self, key_name: &str) -> bool {
+        key_name.split_once('.').is_some_and(|(namespace, name)| {
+            self.inner
+                .read()
+                .tenants
+                .get(namespace)
+                .is_some_and(|tenant| tenant.keys.contains_key(name))
+        })
+    }
+
+    /// All tenants with what their namespaces hold in `db`
+    pub fn list(&self, db: &Database) -> Vec<TenantInfo> {
+        let tenants: Vec<_> = self
+            .inner
+            .read()
+            .tenants
+            .iter()
+            .map(|(namespace, tenant)| (namespace.clone(), tenant.clone()))
+            .collect();
+        tenants
+            .into_iter()
+            .map(|(namespace, tenant)| TenantInfo {
+                usage: usage(db, &namespace, None),
+                keys: tenant.keys.into_keys().collect(),
+                read_keys: tenant.read_keys.into_iter().collect(),
+                quotas: tenant.quotas,
+                namespace,
+            })
+            .collect()
+    }
+
+    /// Create a tenant, or set the quotas of an existing one; returns whether
+    /// it was created
+    pub fn put(&self, namespace: &str, quotas: TenantQuotas) -> Result<bool, String> {
+        validate_name("namespace", namespace)?;
+        let created = {
+            let mut inner = self.inner.write();
+            let created = !inner.tenants.contains_key(namespace);
+            inner
+                .tenants
+                .entry(namespace.to_string())
+                .or_default()
+                .quotas = quotas;
+            created
+        };
+        self.save()?;
+        Ok(created)
+    }
+
+    /// Remove a tenant and revoke its keys, leaving its collections; returns
+    /// false if it did not exist
+    pub fn remove(&self, namespace: &str) -> Result<bool, String> {
+        let removed = {
+            let mut inner = self.inner.write();
+            let removed = inner.tenants.remove(namespace).is_some();
+            inner.by_hash.retain(|_, key| key.namespace != namespace);
+            removed
+        };
+        if removed {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    /// Issue a new key named `name` with `role` to the tenant, returning the key
+    pub fn add_key(&self, namespace: &str, name: &str, role: KeyRole) -> Result<String, String> {
+        validate_name("key name", name)?;
+        let mut bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut bytes);
+        let key = URL_SAFE_NO_PAD.encode(bytes);
+        {
+            let mut inner = self.inner.write();
+            let tenant = inner
+                .tenants
+                .get_mut(namespace)
+                .ok_or_else(|| format!("Unknown tenant: {}", namespace))?;
+            if tenant.keys.contains_key(name) {
+                return Err(format!(
+                    "Tenant {} already has a key named {}",
+                    namespace, name
+                ));
+            }
+            tenant.keys.insert(name.to_string(), hash(&key));
+            if role == KeyRole::Read {
+                tenant.read_keys.insert(name.to_string());
+            }
+            inner.by_hash.insert(
+                hash(&key),
+                TenantKey {
+                    namespace: namespace.to_string(),
+                    name: name.to_string(),
+                    role,
+                },
+            );
+        }
+        self.save()?;
+        Ok(key)
+    }
+
+    /// Revoke one key of the tenant; returns false if it did not exist
+    pub fn revoke_key(&self, namespace: &str, name: &str) -> Result<bool, String> {
+        let revoked = {
+            let mut inner = self.inner.write();
+            let hash = inner.tenants.get_mut(namespace).and_then(|tenant| {
+                tenant.read_keys.remove(name);
+                tenant.keys.remove(name)
+            });
+            if let Some(hash) = &hash {
+                inner.by_hash.remove(hash);
+            }
+            hash.is_some()
+        };
+        if revoked {
+            self.save()?;
+        }
+        Ok(revoked)
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let bytes =
+            serde_json::to_vec_pretty(&self.inner.read().tenants).map_err(|e| e.to_string())?;
+        std::fs::write(path, bytes).map_err(|e| format!("Failed to save tenants: {}", e))
+    }
+}
+
+/// Whether `collection` (a resolved name) belongs to `namespace`
+pub fn in_namespace(namespace: &str, collection: &str) -> bool {
+    collection
+        .strip_prefix(namespace)
+        .and_then(|rest| rest.strip_prefix('.'))
+        .is_some_and(|rest| !rest.is_empty())
+}
+
+/// Collections of `namespace` in `db` and the vectors they hold, leaving out
+/// the collection `except`
+pub fn usage(db: &Database, namespace: &str, except: Option<&str>) -> TenantUsage {
+    let mut usage = TenantUsage::default();
+    for name in db.list_collections() {
+        if !in_namespace(namespace, &name) || except == Some(name.as_str()) {
+            continue;
+        }
+        if let Ok(collection) = db.get_collection(&name) {
+            usage.collections += 1;
+            usage.vectors += collection.len();
+        }
+    }
+    usage
+}
+
+/// What a request made with a tenant key is about
+pub enum Target<'a> {
+    /// The tenant's own collections as a whole (listing, creating) or no
+    /// collection at all; handlers scope these themselves
+    Namespace,
+    /// The collection or alias in the path
+    Collection(&'a str),
+    /// Anything else, which tenants may not do
+    Other,
+}
+
+pub fn target(req: &Request) -> Target<'_> {
+    let segments: Vec<&str> = req.uri().path().trim_matches('/').split('/').collect();
+    match (req.method(), segments.as_slice()) {
+        (&Method::GET | &Method::POST, ["collections"]) | (&Method::GET, ["capabilities"]) => {
+            Target::Namespace
+        }
+        (_, ["collections", name, ..]) => Target::Collection(name),
+        _ => Target::Other,
+    }
+}
+
+/// Namespaces and key names take ASCII lowercase letters, digits, `_` and `-`
+fn validate_name(what: &str, name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && !name.starts_with('_')
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_' || b == b'-');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid {} {:?}: use 1 to {} lowercase letters, digits, `_` and `-`, not starting with `_`",
+            what, name, MAX_NAME_LEN
+        ))
+    }
+}
+
+fn hash(key: &str) -> String {
+    format!("{:x}", Sha256::digest(key.as_bytes()))
+}
@@ -0,0 +1,135 @@
+//! Throwaway servers for integration tests
+//!
+//! [`spawn_ephemeral`] serves the full HTTP API on a random local port, with
+//! its own temporary data directory and default settings that ignore the
+//! environment (no API keys, default limits), or [`spawn_ephemeral_with`]
+//! the settings given. Client code can then be tested against a real server
+//! without Docker:
+//!
+//! ```no_run
+//! # async fn client_test() {
+//! let server = surgedb_server::test::spawn_ephemeral().await.unwrap();
+//! let collections = reqwest::get(format!("{}/collections", server.url()))
+//!     .await
+//!     .unwrap();
+//! assert!(collections.status().is_success());
+//! server.shutdown().await;
+//! # }
+//! ```
+
+use crate::{build_router, AppConfig, AppState};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use surgedb_core::Database;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+/// Tells apart the data directories of servers spawned by one process
+static NEXT_SERVER: AtomicUsize = AtomicUsize::new(0);
+
+/// A running ephemeral server
+///
+/// Dropping it stops the server and deletes its data directory; use
+/// [`shutdown`](Self::shutdown) to also wait for in-flight requests.
+pub struct EphemeralServer {
+    url: String,
+    addr: SocketAddr,
+    data_dir: PathBuf,
+    state: AppState,
+    shutdown: Option<oneshot::Sender<()>>,
+    task: Option<JoinHandle<()>>,
+}
+
+/// Start a server on `127.0.0.1` with a random port and a fresh data directory
+///
+/// Must be called from within a Tokio runtime, such as a `#[tokio::test]`.
+pub async fn spawn_ephemeral() -> std::io::Result<EphemeralServer> {
+    spawn_ephemeral_with(&[]).await
+}
+
+/// Like [`spawn_ephemeral`], configured with the environment variables
+/// `vars` (e.g. `("API_KEY", "secret")`) instead of the defaults
+pub async fn spawn_ephemeral_with(vars: &[(&str, &str)]) -> std::io::Result<EphemeralServer> {
+    let data_dir = std::env::temp_dir().join(format!(
+        "surgedb-test-{}-{}",
+        std::process::id(),
+        NEXT_SERVER.fetch_add(1, Ordering::Relaxed)
+    ));
+    if data_dir.exists() {
+        std::fs::remove_dir_all(&data_dir)?;
+    }
+
+    let mut config = AppConfig::from_vars(|name| {
+        vars.iter()
+            .find(|(var, _)| *var == name)
+            .map(|(_, value)| value.to_string())
+            .ok_or(std::env::VarError::NotPresent)
+    });
+    config.data_dir = data_dir.join("data").to_string_lossy().into_owned();
+    config.snapshot_dir = data_dir.join("snapshots").to_string_lossy().into_owned();
+    let db = Database::open(&config.data_dir).map_err(std::io::Error::other)?;
+
+    let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0)).await?;
+    let addr = listener.local_addr()?;
+    let state = AppState::new(Arc::new(db), config);
+    let app = build_router(state.clone());
+    let (shutdown, stopped) = oneshot::channel::<()>();
+    let task = tokio::spawn(async move {
+        let _ = axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(async {
+            let _ = stopped.await;
+        })
+        .await;
+    });
+
+    Ok(EphemeralServer {
+        url: format!("http://{}", addr),
+        addr,
+        data_dir,
+        state,
+        shutdown: Some(shutdown),
+        task: Some(task),
+    })
+}
+
+impl EphemeralServer {
+    /// Base URL of the API, e.g. `http://127.0.0.1:41234`
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Directory holding the server's collections and snapshots
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    /// Stop accepting connections, wait for in-flight requests, stop the
+    /// background jobs and delete the data
+    pub async fn shutdown(mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        if let Some(task) = self.task.take() {
+            let _ = task.await;
+        }
+        self.state.shutdown().await;
+    }
+}
+
+impl Drop for EphemeralServer {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+        let _ = std::fs::remove_dir_all(&self.data_dir);
+    }
+}
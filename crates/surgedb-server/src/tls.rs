@@ -0,0 +1,108 @@
+//! TLS termination for the API and web ports
+//!
+//! With `TLS_CERT_PATH` and `TLS_KEY_PATH` set to PEM files, both ports serve
+//! HTTPS (HTTP/2 or HTTP/1.1) with rustls instead of plain HTTP. Setting
+//! `TLS_CLIENT_CA_PATH` as well turns on mutual TLS: clients must present a
+//! certificate issued by one of the CAs in that file, or may, with
+//! `TLS_CLIENT_AUTH=optional`. API keys are checked either way.
+
+use axum_server::tls_rustls::RustlsConfig;
+use rustls::crypto::ring;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{RootCertStore, ServerConfig};
+use std::io::BufReader;
+use std::sync::Arc;
+
+/// Certificate and client verification settings, from `TLS_*` variables
+#[derive(Clone, Debug)]
+pub struct TlsConfig {
+    cert_path: String,
+    key_path: String,
+    /// CAs client certificates are verified against; no client auth if unset
+    client_ca_path: Option<String>,
+    /// Let clients without a certificate connect when `client_ca_path` is set
+    client_auth_optional: bool,
+}
+
+impl TlsConfig {
+    /// Settings from the variables `var` looks up; `None` without a
+    /// certificate or key
+    pub fn from_vars(var: impl Fn(&str) -> Result<String, std::env::VarError>) -> Option<Self> {
+        let path = |name| var(name).ok().filter(|v| !v.trim().is_empty());
+        let (cert_path, key_path) = (path("TLS_CERT_PATH"), path("TLS_KEY_PATH"));
+        if cert_path.is_none() && key_path.is_none() {
+            return None;
+        }
+        // Only one of them is refused by `load` rather than served unencrypted
+        Some(Self {
+            cert_path: cert_path.unwrap_or_default(),
+            key_path: key_path.unwrap_or_default(),
+            client_ca_path: path("TLS_CLIENT_CA_PATH"),
+            client_auth_optional: var("TLS_CLIENT_AUTH").is_ok_and(|v| v.trim() == "optional"),
+        })
+    }
+
+    /// Whether clients must present a certificate
+    pub fn requires_client_cert(&self) -> bool {
+        self.client_ca_path.is_some() && !self.client_auth_optional
+    }
+
+    /// Load the certificate, key and client CAs into a server configuration
+    pub fn load(&self) -> Result<RustlsConfig, String> {
+        if self.cert_path.is_empty() || self.key_path.is_empty() {
+            return Err("Set both TLS_CERT_PATH and TLS_KEY_PATH".to_string());
+        }
+        let provider = Arc::new(ring::default_provider());
+        let builder = ServerConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(|e| e.to_string())?;
+        let builder = match &self.client_ca_path {
+            Some(path) => {
+                let mut roots = RootCertStore::empty();
+                for cert in read_certs(path)? {
+                    roots
+                        .add(cert)
+                        .map_err(|e| format!("Invalid client CA in {}: {}", path, e))?;
+                }
+                let verifier = WebPkiClientVerifier::builder_with_provider(roots.into(), provider);
+                let verifier = if self.client_auth_optional {
+                    verifier.allow_unauthenticated()
+                } else {
+                    verifier
+                };
+                builder.with_client_cert_verifier(
+                    verifier
+                        .build()
+                        .map_err(|e| format!("Invalid client CAs in {}: {}", path, e))?,
+                )
+            }
+            None => builder.with_no_client_auth(),
+        };
+        let mut config = builder
+            .with_single_cert(read_certs(&self.cert_path)?, read_key(&self.key_path)?)
+            .map_err(|e| format!("Invalid certificate or key: {}", e))?;
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        Ok(RustlsConfig::from_config(Arc::new(config)))
+    }
+}
+
+fn read_certs(path: &str) -> Result<Vec<CertificateDer<'static>>, String> {
+    let certs = rustls_pemfile::certs(&mut BufReader::new(open(path)?))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read certificates from {}: {}", path, e))?;
+    if certs.is_empty() {
+        return Err(format!("No certificates in {}", path));
+    }
+    Ok(certs)
+}
+
+fn read_key(path: &str) -> Result<PrivateKeyDer<'static>, String> {
+    rustls_pemfile::private_key(&mut BufReader::new(open(path)?))
+        .map_err(|e| format!("Failed to read the private key from {}: {}", path, e))?
+        .ok_or_else(|| format!("No private key in {}", path))
+}
+
+fn open(path: &str) -> Result<std::fs::File, String> {
+    std::fs::File::open(path).map_err(|e| format!("Failed to open {}: {}", path, e))
+}
@@ -0,0 +1,252 @@
+//! Scoped temporary tokens
+//!
+//! Admins mint short-lived tokens limited to some operations on some
+//! collections (e.g. search only, on `docs`, for 15 minutes), so browsers and
+//! notebooks can call the API without holding a long-lived API key. A token
+//! is its claims signed with HMAC-SHA256 by the server; nothing is stored, so
+//! tokens can't be revoked one by one, only all at once by changing the
+//! signing secret.
+
+use axum::extract::Request;
+use axum::http::Method;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use utoipa::ToSchema;
+
+/// Operations a token can be allowed
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenScope {
+    /// Vector, hybrid and text search, and payload lookups
+    Search,
+    /// Collection info and fetching vectors
+    Read,
+    /// Inserting, updating and deleting vectors
+    Write,
+}
+
+/// What a token grants, and until when
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TokenClaims {
+    pub scopes: Vec<TokenScope>,
+    /// Collections (or aliases) the token applies to
+    pub collections: Vec<String>,
+    /// Expiry, in seconds since the Unix epoch
+    pub exp: i64,
+    /// Free-form note on who the token was minted for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+impl TokenClaims {
+    /// Whether the token covers the collection requested as `name`, which
+    /// resolves to `resolved`
+    pub fn covers(&self, name: &str, resolved: &str) -> bool {
+        self.collections.iter().any(|c| c == name || c == resolved)
+    }
+}
+
+/// Scope `req` needs and the collection it targets; `None` for requests no
+/// token may make
+pub fn required_scope(req: &Request) -> Option<(TokenScope, &str)> {
+    if let Some(name) = crate::written_collection(req) {
+        return Some((TokenScope::Write, name));
+    }
+    let segments: Vec<&str> = req.uri().path().trim_matches('/').split('/').collect();
+    match (req.method(), segments.as_slice()) {
+        (
+            &Method::POST,
+            ["collections", name, "search" | "payloads" | "recommend" | "hard-negatives"],
+        )
+        | (
+            &Method::POST,
+            ["collections", name, "search", "batch" | "groups" | "hybrid" | "stream" | "text"],
+        ) => Some((TokenScope::Search, name)),
+        (&Method::GET | &Method::HEAD, ["collections", name])
+        | (&Method::GET | &Method::POST, ["collections", name, "count"])
+        | (&Method::POST, ["collections", name, "scroll"])
+        | (&Method::GET, ["collections", name, "summary" | "vectors"])
+        | (&Method::GET, ["collections", name, "vectors", _]) => Some((TokenScope::Read, name)),
+        _ => None,
+    }
+}
+
+/// Signs and verifies tokens with the server's secret
+pub struct TokenSigner {
+    key: Vec<u8>,
+}
+
+impl TokenSigner {
+    pub fn new(secret: &str) -> Self {
+        Self {
+            key: secret.as_bytes().to_vec(),
+        }
+    }
+
+    fn mac(&self, payload: &str) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC takes any key size");
+        mac.update(payload.as_bytes());
+        mac
+    }
+
+    /// Token for `claims`, as `<claims>.<signature>` in unpadded base64url
+    pub fn sign(&self, claims: &TokenClaims) -> String {
+        let json = serde_json::to_vec(claims).expect("claims serialize");
+        let payload = URL_SAFE_NO_PAD.encode(json);
+        let signature = URL_SAFE_NO_PAD.encode(self.mac(&payload).finalize().into_bytes());
+        format!("{}.{}", payload, signature)
+    }
+
+    /// Claims of `token` if it was signed with this key and hasn't expired by
+    /// `now` (seconds since the Unix epoch)
+    pub fn verify(&self, token: &str, now: i64) -> Option<TokenClaims> {
+        let (payload, signature) = token.split_once('.')?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+        self.mac(payload).verify_slice(&signature).ok()?;
+        let claims: TokenClaims =
+            serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).ok()?).ok()?;
+        (claims.exp > now).then_some(claims)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+
+    const NOW: i64 = 1_700_000_000;
+
+    fn claims(scopes: Vec<TokenScope>) -> TokenClaims {
+        TokenClaims {
+            scopes,
+            collections: vec!["docs".to_string()],
+            exp: NOW + 900,
+            label: Some("notebook".to_string()),
+        }
+    }
+
+    /// Whether `claims` allow `method` on `path`, as the auth middleware
+    /// decides it
+    fn allows(claims: &TokenClaims, method: Method, path: &str) -> bool {
+        let req = Request::builder()
+            .method(method)
+            .uri(path)
+            .body(Body::empty())
+            .unwrap();
+        required_scope(&req).is_some_and(|(scope, name)| {
+            claims.scopes.contains(&scope) && claims.covers(name, name)
+        })
+    }
+
+    #[test]
+    fn test_signed_token_verifies() {
+        let signer = TokenSigner::new("secret");
+        let token = signer.sign(&claims(vec![TokenScope::Search]));
+        let verified = signer.verify(&token, NOW).unwrap();
+        assert_eq!(verified.scopes, [TokenScope::Search]);
+        assert_eq!(verified.collections, ["docs"]);
+        assert_eq!(verified.exp, NOW + 900);
+        assert_eq!(verified.label.as_deref(), Some("notebook"));
+    }
+
+    #[test]
+    fn test_expired_token_is_rejected() {
+        let signer = TokenSigner::new("secret");
+        let token = signer.sign(&claims(vec![TokenScope::Search]));
+        assert!(signer.verify(&token, NOW + 899).is_some());
+        assert!(signer.verify(&token, NOW + 900).is_none());
+        assert!(signer.verify(&token, NOW + 3600).is_none());
+    }
+
+    #[test]
+    fn test_tampered_token_is_rejected() {
+        let signer = TokenSigner::new("secret");
+        let token = signer.sign(&claims(vec![TokenScope::Read]));
+        let (payload, signature) = token.split_once('.').unwrap();
+
+        // Claims widened to write, under the original signature
+        let widened = signer.sign(&claims(vec![TokenScope::Read, TokenScope::Write]));
+        let (widened, _) = widened.split_once('.').unwrap();
+        assert!(signer
+            .verify(&format!("{}.{}", widened, signature), NOW)
+            .is_none());
+
+        let mut flipped = signature.as_bytes().to_vec();
+        flipped[0] = if flipped[0] == b'A' { b'B' } else { b'A' };
+        let flipped = String::from_utf8(flipped).unwrap();
+        assert!(signer
+            .verify(&format!("{}.{}", payload, flipped), NOW)
+            .is_none());
+
+        for malformed in [
+            "",
+            ".",
+            payload,
+            "not-base64!.sig",
+            &format!("{}.", payload),
+        ] {
+            assert!(signer.verify(malformed, NOW).is_none(), "{malformed}");
+        }
+    }
+
+    #[test]
+    fn test_token_from_another_secret_is_rejected() {
+        let token = TokenSigner::new("other").sign(&claims(vec![TokenScope::Search]));
+        assert!(TokenSigner::new("secret").verify(&token, NOW).is_none());
+    }
+
+    #[test]
+    fn test_read_token_is_refused_writes_and_admin_routes() {
+        let read = claims(vec![TokenScope::Read]);
+        assert!(allows(&read, Method::GET, "/collections/docs"));
+        assert!(allows(&read, Method::GET, "/collections/docs/vectors/v1"));
+        assert!(allows(&read, Method::POST, "/collections/docs/count"));
+        assert!(!allows(&read, Method::POST, "/collections/docs/search"));
+
+        assert!(!allows(&read, Method::POST, "/collections/docs/vectors"));
+        assert!(!allows(&read, Method::POST, "/collections/docs/upsert"));
+        assert!(!allows(
+            &read,
+            Method::DELETE,
+            "/collections/docs/vectors/v1"
+        ));
+        assert!(!allows(
+            &read,
+            Method::PATCH,
+            "/collections/docs/vectors/v1/metadata"
+        ));
+        // Routes no token may call, whatever its scopes
+        let all = claims(vec![
+            TokenScope::Search,
+            TokenScope::Read,
+            TokenScope::Write,
+        ]);
+        assert!(allows(&all, Method::POST, "/collections/docs/vectors"));
+        for (method, path) in [
+            (Method::DELETE, "/collections/docs"),
+            (Method::POST, "/collections"),
+            (Method::POST, "/tokens"),
+            (Method::GET, "/collections"),
+            (Method::POST, "/collections/docs/snapshot"),
+        ] {
+            assert!(!allows(&all, method, path), "{path}");
+        }
+    }
+
+    #[test]
+    fn test_token_is_refused_other_collections() {
+        let token = claims(vec![TokenScope::Search, TokenScope::Read]);
+        assert!(allows(&token, Method::POST, "/collections/docs/search"));
+        assert!(!allows(&token, Method::POST, "/collections/private/search"));
+        assert!(!allows(&token, Method::GET, "/collections/private"));
+        assert!(!allows(&token, Method::POST, "/collections/docs2/search"));
+
+        // An alias given at minting covers the collection it resolves to
+        assert!(token.covers("docs", "docs_v2"));
+        assert!(token.covers("latest", "docs"));
+        assert!(!token.covers("latest", "docs_v2"));
+    }
+}
@@ -0,0 +1,105 @@
+//! Usage history in the `_usage` system collection
+//!
+//! Every collection counts its operations per minute for the last hour (see
+//! [`surgedb_core::activity`]). Once a minute, a background task copies the
+//! minutes that have ended into the `_usage` system collection, one record per
+//! collection and minute with ID `<collection>@<start>`, so the history
+//! outlives the hour and restarts and is backed up like any collection. Idle
+//! minutes are skipped. Once an hour, records older than the retention are
+//! deleted.
+
+use parking_lot::Mutex;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use surgedb_core::filter::Filter;
+use surgedb_core::Database;
+use tracing::{debug, warn};
+
+/// System collection holding the usage history
+pub const USAGE_COLLECTION: &str = "_usage";
+
+pub struct UsageRecorder {
+    retention: Duration,
+    /// Start of the first minute not recorded yet
+    next_minute: Mutex<u64>,
+}
+
+impl UsageRecorder {
+    pub fn new(retention: Duration) -> Self {
+        Self {
+            retention,
+            next_minute: Mutex::new(0),
+        }
+    }
+
+    /// Record the minutes of every collection that ended since the last call
+    pub async fn record(self: &Arc<Self>, db: &Arc<Database>) {
+        if !crate::wait_for_recovery(db).await {
+            return;
+        }
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let (recorder, db) = (self.clone(), db.clone());
+        match tokio::task::spawn_blocking(move || recorder.record_until(&db, now)).await {
+            Ok(Ok(0)) => {}
+            Ok(Ok(recorded)) => debug!("Recorded {} minutes of usage", recorded),
+            Ok(Err(e)) => warn!("Failed to record usage: {}", e),
+            Err(e) => warn!("Usage recording panicked: {}", e),
+        }
+    }
+
+    /// Record the minutes that ended before `now`; returns how many were written
+    fn record_until(&self, db: &Database, now: u64) -> surgedb_core::Result<usize> {
+        let current = now / 60 * 60;
+        let mut next_minute = self.next_minute.lock();
+        let from = *next_minute;
+        if current <= from {
+            return Ok(0);
+        }
+
+        let mut items = Vec::new();
+        for (name, minutes) in db.activity() {
+            for minute in minutes {
+                if minute.start < from
+                    || minute.start >= current
+                    || minute.searches + minute.inserts + minute.deletes == 0
+                {
+                    continue;
+                }
+                let mut metadata = serde_json::to_value(minute)?;
+                if let Value::Object(fields) = &mut metadata {
+                    fields.insert("collection".to_string(), Value::from(name.as_str()));
+                }
+                items.push((
+                    format!("{}@{}", name, minute.start),
+                    vec![0.0],
+                    Some(metadata),
+                ));
+            }
+        }
+
+        let usage = db.system_collection(USAGE_COLLECTION)?;
+        let recorded = items.len();
+        if !items.is_empty() {
+            usage.upsert_batch(items)?;
+        }
+        if from / 3600 != current / 3600 {
+            let cutoff = current.saturating_sub(self.retention.as_secs());
+            let expired = usage.delete_by_filter(&Filter::Range {
+                field: "start".to_string(),
+                gt: None,
+                gte: None,
+                lt: Some(cutoff as f64),
+                lte: None,
+            })?;
+            if !expired.is_empty() {
+                debug!("Deleted {} expired usage records", expired.len());
+            }
+        }
+        *next_minute = current;
+        Ok(recorded)
+    }
+}
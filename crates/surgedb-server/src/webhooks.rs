@@ -0,0 +1,349 @@
+//! Collection threshold webhooks
+//!
+//! Each webhook watches one metric of one collection. A background task
+//! evaluates all webhooks periodically and POSTs an event when a threshold is
+//! crossed (`threshold.triggered`) and again when it recovers
+//! (`threshold.resolved`), so receivers are not flooded while a condition
+//! persists. Webhooks are saved to `webhooks.json` in the data directory.
+//! With a request signing secret set, deliveries carry the same signature
+//! headers as signed API requests.
+
+use crate::signing::{RequestSigner, SIGNATURE_HEADER, TIMESTAMP_HEADER};
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use surgedb_core::Database;
+use tracing::{debug, warn};
+use utoipa::ToSchema;
+
+/// Queries sampled per recall estimate
+const RECALL_SAMPLE_SIZE: usize = 20;
+/// k used for recall estimates
+const RECALL_K: usize = 10;
+/// Timeout for a single webhook delivery
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Collection metric a webhook watches
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ThresholdMetric {
+    /// Live vectors; fires when above the threshold
+    VectorCount,
+    /// Estimated memory (or disk, for persistent collections) in bytes; fires when above
+    MemoryBytes,
+    /// Deleted-but-unreclaimed share of all slots (0.0-1.0); fires when above
+    TombstoneRatio,
+    /// Estimated search recall@10 (0.0-1.0); fires when below
+    Recall,
+}
+
+impl ThresholdMetric {
+    fn breached(self, value: f64, threshold: f64) -> bool {
+        match self {
+            ThresholdMetric::Recall => value < threshold,
+            _ => value > threshold,
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateWebhookRequest {
+    #[schema(example = "https://example.com/hooks/surgedb")]
+    pub url: String,
+    pub metric: ThresholdMetric,
+    #[schema(example = 1000000.0)]
+    pub threshold: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct Webhook {
+    pub id: String,
+    pub collection: String,
+    pub url: String,
+    pub metric: ThresholdMetric,
+    pub threshold: f64,
+    /// Whether the threshold is currently breached
+    #[serde(default)]
+    pub triggered: bool,
+}
+
+/// Body POSTed to a webhook's URL
+#[derive(Serialize, Debug)]
+struct WebhookEvent<'a> {
+    event: &'static str,
+    webhook_id: &'a str,
+    collection: &'a str,
+    metric: ThresholdMetric,
+    threshold: f64,
+    value: f64,
+    timestamp: DateTime<Utc>,
+}
+
+pub struct WebhookRegistry {
+    hooks: RwLock<Vec<Webhook>>,
+    path: Option<PathBuf>,
+    client: reqwest::Client,
+    signer: Option<Arc<RequestSigner>>,
+}
+
+impl WebhookRegistry {
+    /// Create a registry, loading webhooks saved at `path`
+    pub fn new(path: Option<PathBuf>) -> Self {
+        let hooks = path
+            .as_ref()
+            .and_then(|p| std::fs::read(p).ok())
+            .and_then(|bytes| match serde_json::from_slice(&bytes) {
+                Ok(hooks) => Some(hooks),
+                Err(e) => {
+                    warn!("Ignoring unreadable webhooks file: {}", e);
+                    None
+                }
+            })
+            .unwrap_or_default();
+
+        Self {
+            hooks: RwLock::new(hooks),
+            path,
+            client: reqwest::Client::builder()
+                .timeout(DELIVERY_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            signer: None,
+        }
+    }
+
+    /// Sign deliveries with `signer`
+    pub fn with_signer(mut self, signer: Arc<RequestSigner>) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    pub fn add(&self, collection: &str, req: CreateWebhookRequest) -> Result<Webhook, String> {
+        if !(req.url.starts_with("http://") || req.url.starts_with("https://")) {
+            return Err("Webhook url must be http(s)".to_string());
+        }
+        if !req.threshold.is_finite() {
+            return Err("Webhook threshold must be a finite number".to_string());
+        }
+
+        let hook = Webhook {
+            id: format!(
+                "wh_{:x}",
+                Utc::now().timestamp_nanos_opt().unwrap_or_default()
+            ),
+            collection: collection.to_string(),
+            url: req.url,
+            metric: req.metric,
+            threshold: req.threshold,
+            triggered: false,
+        };
+        self.hooks.write().push(hook.clone());
+        self.save()?;
+        Ok(hook)
+    }
+
+    pub fn list(&self, collection: &str) -> Vec<Webhook> {
+        self.hooks
+            .read()
+            .iter()
+            .filter(|h| h.collection == collection)
+            .cloned()
+            .collect()
+    }
+
+    /// Remove one webhook; returns false if it did not exist
+    pub fn remove(&self, collection: &str, id: &str) -> Result<bool, String> {
+        let removed = {
+            let mut hooks = self.hooks.write();
+            let before = hooks.len();
+            hooks.retain(|h| !(h.collection == collection && h.id == id));
+            hooks.len() != before
+        };
+        if removed {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    /// Point the webhooks of a renamed collection at its new name
+    pub fn rename_collection(&self, from: &str, to: &str) -> Result<(), String> {
+        let renamed = {
+            let mut hooks = self.hooks.write();
+            let mut renamed = false;
+            for hook in hooks.iter_mut().filter(|h| h.collection == from) {
+                hook.collection = to.to_string();
+                renamed = true;
+            }
+            renamed
+        };
+        if renamed {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Remove all webhooks of a deleted collection
+    pub fn remove_collection(&self, collection: &str) -> Result<(), String> {
+        let removed = {
+            let mut hooks = self.hooks.write();
+            let before = hooks.len();
+            hooks.retain(|h| h.collection != collection);
+            hooks.len() != before
+        };
+        if removed {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Evaluate every webhook and deliver events for thresholds that changed state
+    pub async fn evaluate(&self, db: &Arc<Database>) {
+        let watched: HashMap<String, bool> =
+            self.hooks.read().iter().fold(HashMap::new(), |mut acc, h| {
+                *acc.entry(h.collection.clone()).or_default() |=
+                    h.metric == ThresholdMetric::Recall;
+                acc
+            });
+        if watched.is_empty() {
+            return;
+        }
+
+        let db = db.clone();
+        let values = tokio::task::spawn_blocking(move || {
+            watched
+                .into_iter()
+                .filter_map(|(name, needs_recall)| {
+                    let collection = db.get_collection(&name).ok()?;
+                    Some((name, collection_values(&collection, needs_recall)))
+                })
+                .collect::<HashMap<_, _>>()
+        })
+        .await
+        .unwrap_or_default();
+
+        let mut changed = false;
+        for hook in self.hooks.write().iter_mut() {
+            let Some(value) = values
+                .get(&hook.collection)
+                .and_then(|v| v.get(&hook.metric))
+                .copied()
+            else {
+                continue;
+            };
+
+            let breached = hook.metric.breached(value, hook.threshold);
+            if breached == hook.triggered {
+                continue;
+            }
+            hook.triggered = breached;
+            changed = true;
+
+            let event = if breached {
+                "threshold.triggered"
+            } else {
+                "threshold.resolved"
+            };
+            self.deliver(hook, event, value);
+        }
+
+        if changed {
+            if let Err(e) = self.save() {
+                warn!("{}", e);
+            }
+        }
+    }
+
+    fn deliver(&self, hook: &Webhook, event: &'static str, value: f64) {
+        let body = WebhookEvent {
+            event,
+            webhook_id: &hook.id,
+            collection: &hook.collection,
+            metric: hook.metric,
+            threshold: hook.threshold,
+            value,
+            timestamp: Utc::now(),
+        };
+        debug!(
+            "Webhook {} {} ({:?} = {})",
+            hook.id, event, hook.metric, value
+        );
+
+        let Ok(bytes) = serde_json::to_vec(&body) else {
+            return;
+        };
+        let mut request = self
+            .client
+            .post(&hook.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json");
+        if let (Some(signer), Ok(url)) = (&self.signer, reqwest::Url::parse(&hook.url)) {
+            let path = match url.query() {
+                Some(query) => format!("{}?{}", url.path(), query),
+                None => url.path().to_string(),
+            };
+            let timestamp = body.timestamp.timestamp();
+            request = request
+                .header(TIMESTAMP_HEADER, timestamp.to_string())
+                .header(
+                    SIGNATURE_HEADER,
+                    signer.signature(timestamp, "POST", &path, &bytes),
+                );
+        }
+        let request = request.body(bytes);
+        let id = hook.id.clone();
+        tokio::spawn(async move {
+            match request.send().await {
+                Ok(resp) if !resp.status().is_success() => {
+                    warn!("Webhook {} delivery got {}", id, resp.status());
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Webhook {} delivery failed: {}", id, e),
+            }
+        });
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let bytes = serde_json::to_vec_pretty(&*self.hooks.read()).map_err(|e| e.to_string())?;
+        std::fs::write(path, bytes).map_err(|e| format!("Failed to save webhooks: {}", e))
+    }
+}
+
+/// Current value of every metric for a collection (recall only if requested)
+fn collection_values(
+    collection: &surgedb_core::db::Collection,
+    needs_recall: bool,
+) -> HashMap<ThresholdMetric, f64> {
+    let stats = collection.stats();
+    let slots = stats.vector_count + stats.deleted_count;
+    let tombstone_ratio = if slots == 0 {
+        0.0
+    } else {
+        stats.deleted_count as f64 / slots as f64
+    };
+
+    let mut values = HashMap::from([
+        (ThresholdMetric::VectorCount, stats.vector_count as f64),
+        (
+            ThresholdMetric::MemoryBytes,
+            stats.memory_usage_bytes as f64,
+        ),
+        (ThresholdMetric::TombstoneRatio, tombstone_ratio),
+    ]);
+
+    if needs_recall {
+        match collection.estimate_recall(RECALL_SAMPLE_SIZE, RECALL_K) {
+            Ok(Some(recall)) => {
+                values.insert(ThresholdMetric::Recall, recall);
+            }
+            Ok(None) => {}
+            Err(e) => warn!("Recall estimate failed: {}", e),
+        }
+    }
+    values
+}
@@ -0,0 +1,57 @@
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+use surgedb_server::test::spawn_ephemeral_with;
+
+const ADMIN_KEY: &str = "admin-secret";
+
+#[tokio::test]
+async fn test_public_lookup_ignores_name_case() {
+    let server = spawn_ephemeral_with(&[
+        ("API_KEY", ADMIN_KEY),
+        ("PUBLIC_COLLECTIONS", "docs,Parents"),
+        ("COLLECTION_NAME_CASE", "lowercase"),
+    ])
+    .await
+    .unwrap();
+    let client = Client::new();
+    for name in ["docs", "parents", "private"] {
+        let response = client
+            .post(format!("{}/collections", server.url()))
+            .header("x-api-key", ADMIN_KEY)
+            .json(&json!({ "name": name, "dimensions": 2 }))
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success(), "{}", response.status());
+        let body = json!({ "id": "a", "vector": [1.0, 0.0], "metadata": { "parent": "a" } });
+        let response = client
+            .post(format!("{}/collections/{}/vectors", server.url(), name))
+            .header("x-api-key", ADMIN_KEY)
+            .json(&body)
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success(), "{}", response.status());
+    }
+
+    let search = |collection: &str| {
+        client
+            .post(format!("{}/collections/Docs/search", server.url()))
+            .json(&json!({
+                "vector": [1.0, 0.0],
+                "k": 1,
+                "lookup": { "field": "parent", "collection": collection }
+            }))
+            .send()
+    };
+    for spelling in ["parents", "PARENTS", "Parents"] {
+        let response = search(spelling).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK, "{spelling}");
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(body[0]["lookup"]["id"], "a");
+    }
+    let response = search("Private").await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    server.shutdown().await;
+}
@@ -0,0 +1,25 @@
+use reqwest::{Client, StatusCode};
+use serde_json::json;
+use surgedb_server::test::spawn_ephemeral;
+
+#[tokio::test]
+async fn test_system_collections_are_read_only() {
+    let server = spawn_ephemeral().await.unwrap();
+    let client = Client::new();
+
+    let response = client
+        .post(format!("{}/collections/_usage/vectors", server.url()))
+        .json(&json!({ "id": "a", "vector": [0.0] }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = client
+        .delete(format!("{}/collections/_audit", server.url()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    server.shutdown().await;
+}
@@ -0,0 +1,78 @@
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+use surgedb_server::test::{spawn_ephemeral_with, EphemeralServer};
+
+const ADMIN_KEY: &str = "admin-secret";
+
+async fn post(
+    client: &Client,
+    server: &EphemeralServer,
+    path: &str,
+    key: &str,
+    body: Value,
+) -> reqwest::Response {
+    client
+        .post(format!("{}{}", server.url(), path))
+        .header("x-api-key", key)
+        .json(&body)
+        .send()
+        .await
+        .unwrap()
+}
+
+/// Issue a key for a new tenant owning `<namespace>.*`
+async fn tenant_key(client: &Client, server: &EphemeralServer, namespace: &str) -> String {
+    let response = client
+        .put(format!("{}/admin/tenants/{}", server.url(), namespace))
+        .header("x-api-key", ADMIN_KEY)
+        .json(&json!({}))
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success(), "{}", response.status());
+    let path = format!("/admin/tenants/{}/keys", namespace);
+    let response = post(client, server, &path, ADMIN_KEY, json!({ "name": "app" })).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let body: Value = response.json().await.unwrap();
+    body["key"].as_str().unwrap().to_string()
+}
+
+async fn collection_with_vector(client: &Client, server: &EphemeralServer, name: &str) {
+    let body = json!({ "name": name, "dimensions": 2 });
+    let response = post(client, server, "/collections", ADMIN_KEY, body).await;
+    assert!(response.status().is_success(), "{}", response.status());
+    let path = format!("/collections/{}/vectors", name);
+    let body = json!({ "id": "a", "vector": [1.0, 0.0], "metadata": { "parent": "a" } });
+    let response = post(client, server, &path, ADMIN_KEY, body).await;
+    assert!(response.status().is_success(), "{}", response.status());
+}
+
+#[tokio::test]
+async fn test_search_lookup_stays_in_tenant_namespace() {
+    let server = spawn_ephemeral_with(&[("API_KEY", ADMIN_KEY)])
+        .await
+        .unwrap();
+    let client = Client::new();
+    collection_with_vector(&client, &server, "acme.docs").await;
+    collection_with_vector(&client, &server, "acme.parents").await;
+    collection_with_vector(&client, &server, "globex.docs").await;
+    let acme = tenant_key(&client, &server, "acme").await;
+
+    let search = |collection: &str| {
+        json!({
+            "vector": [1.0, 0.0],
+            "k": 1,
+            "lookup": { "field": "parent", "collection": collection }
+        })
+    };
+    let path = "/collections/acme.docs/search";
+    let response = post(&client, &server, path, &acme, search("acme.parents")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body[0]["lookup"]["id"], "a");
+
+    let response = post(&client, &server, path, &acme, search("globex.docs")).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    server.shutdown().await;
+}
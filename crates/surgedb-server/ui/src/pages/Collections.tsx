@@ -396,6 +396,9 @@ export default function Collections() {
                     <option value="Cosine">COSINE</option>
                     <option value="Euclidean">EUCLIDEAN</option>
                     <option value="DotProduct">DOT_PRODUCT</option>
+                    <option value="Manhattan">MANHATTAN</option>
+                    <option value="Hamming">HAMMING</option>
+                    <option value="Jaccard">JACCARD</option>
                   </select>
                 </div>
                 <div className="space-y-2">
@@ -114,12 +114,22 @@ impl From<surgedb_core::Error> for SurgeError {
             surgedb_core::Error::VectorNotFound(_) => "VectorNotFound",
             surgedb_core::Error::DuplicateId(_) => "DuplicateId",
             surgedb_core::Error::EmptyIndex => "EmptyIndex",
+            surgedb_core::Error::InvalidId(_) => "InvalidId",
+            surgedb_core::Error::MetadataLimitExceeded { .. } => "MetadataLimitExceeded",
+            surgedb_core::Error::NormOutOfRange { .. } => "NormOutOfRange",
+            surgedb_core::Error::UnrepresentableValue { .. } => "UnrepresentableValue",
+            surgedb_core::Error::VersionConflict { .. } => "VersionConflict",
             surgedb_core::Error::InvalidConfig(_) => "InvalidConfig",
             surgedb_core::Error::InvalidHnswParam { .. } => "InvalidHnswParam",
+            surgedb_core::Error::InvalidFilter(_) => "InvalidFilter",
             surgedb_core::Error::Storage(_) => "StorageError",
             surgedb_core::Error::CollectionNotFound(_) => "CollectionNotFound",
             surgedb_core::Error::DuplicateCollection(_) => "DuplicateCollection",
             surgedb_core::Error::CapacityExceeded { .. } => "CapacityExceeded",
+            surgedb_core::Error::AliasNotFound(_) => "AliasNotFound",
+            surgedb_core::Error::AliasConflict(_) => "AliasConflict",
+            surgedb_core::Error::CollectionBusy(_) => "CollectionBusy",
+            surgedb_core::Error::InvalidCollectionName { .. } => "InvalidCollectionName",
             surgedb_core::Error::Io(_) => "IoError",
             surgedb_core::Error::WalCorrupted { .. } => "WalCorrupted",
             surgedb_core::Error::SnapshotCorrupted { .. } => "SnapshotCorrupted",
@@ -0,0 +1,44 @@
+use serde_json::json;
+use surgedb_core::filter::Filter;
+use surgedb_core::{Config, Database, DistanceMetric};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // 1. Open (or recover) an on-disk database, like a SQLite file
+    let db = Database::open("./embedded_data")?;
+
+    // 2. Create the collection on first run
+    if db.get_collection("notes").is_err() {
+        let config = Config::builder(3)
+            .distance_metric(DistanceMetric::Cosine)
+            .text_fields(["title"])
+            .build()?;
+        db.create_collection("notes", config)?;
+    }
+    let notes = db.get_collection("notes")?;
+
+    // 3. Write; every write goes to the collection's WAL before it returns
+    notes.upsert(
+        "groceries".to_string(),
+        &[1.0, 0.0, 0.0],
+        Some(json!({"title": "Groceries", "pinned": true})),
+    )?;
+    notes.upsert(
+        "trip".to_string(),
+        &[0.0, 1.0, 0.0],
+        Some(json!({"title": "Trip plans", "pinned": false})),
+    )?;
+
+    // 4. Search, optionally filtered on metadata
+    let pinned = Filter::Exact("pinned".to_string(), json!(true));
+    for (id, distance, metadata) in notes.search(&[0.9, 0.1, 0.0], 5, Some(&pinned))? {
+        println!("{} ({:.3}): {:?}", id, distance, metadata);
+    }
+
+    // 5. Walk every record a page at a time
+    for record in notes.scan().batch_size(100) {
+        let (id, vector, _) = record?;
+        println!("{} -> {:?}", id, vector);
+    }
+
+    Ok(())
+}